@@ -1 +1,6 @@
 wasm-pack build crates/engine --target web --out-dir ../../src/wasm/engine
+
+# SIMD build, picked by the loader when the browser supports simd128
+$env:RUSTFLAGS = "-C target-feature=+simd128"
+wasm-pack build crates/engine --target web --out-dir ../../src/wasm/engine-simd -- --features simd
+Remove-Item Env:RUSTFLAGS
@@ -0,0 +1,180 @@
+// ════════════════════════════════════════════════════════════════
+// Command line
+//     mssim run --market market.json (--scenario s.json | --preset id)
+//               [--severity 1] [--paths 100000] [--steps 252]
+//               [--horizon 1] [--seed 42] [--levels 0.95,0.99]
+//               [--out results.csv | results.parquet]
+//               [--paths-out paths.parquet] [--summary-out summary.parquet]
+//               [--compression snappy] [--threads 0] [--numerics fast]
+//     mssim presets
+// ════════════════════════════════════════════════════════════════
+
+use crate::parquet::Compression;
+
+pub const USAGE: &str = "\
+usage: mssim run --market FILE (--scenario FILE | --preset ID) [options]
+       mssim presets
+
+options:
+  --severity S     preset strength, 1 = as published (default 1)
+  --paths N        Monte Carlo paths (default 100000)
+  --steps N        time steps per path (default 252)
+  --horizon T      horizon in years (default 1)
+  --seed N         RNG seed (default 42)
+  --levels A,B     VaR / CVaR confidence levels (default 0.95,0.99)
+  --out FILE       write per-path portfolio returns (.csv or .parquet)
+  --paths-out FILE write every path to Parquet (path, step, asset, value)
+  --summary-out FILE
+                   write the summary statistics to Parquet (metric, value)
+  --compression C  Parquet compression: snappy (default) or none
+  --threads N      simulation threads, 0 = one per core (default 0);
+                   results do not depend on N
+  --numerics M     fast (default) or strict: portable math, so results
+                   are bit-identical across platforms";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Run(Box<RunArgs>),
+    Presets,
+    Help,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScenarioSource {
+    File(String),
+    Preset(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunArgs {
+    pub market: String,
+    pub scenario: ScenarioSource,
+    pub severity: f64,
+    pub paths: usize,
+    pub steps: usize,
+    pub horizon: f64,
+    pub seed: u64,
+    pub levels: Vec<f64>,
+    pub out: Option<String>,
+    pub paths_out: Option<String>,
+    pub summary_out: Option<String>,
+    pub compression: Compression,
+    /// 0 = std::thread::available_parallelism
+    pub threads: usize,
+    /// mssim_engine::strict mode
+    pub strict: bool,
+}
+
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    match args.first().map(String::as_str) {
+        None | Some("help" | "-h" | "--help") => Ok(Command::Help),
+        Some("presets") => Ok(Command::Presets),
+        Some("run") => parse_run(&args[1..]).map(|run| Command::Run(Box::new(run))),
+        Some(other) => Err(format!("unknown command '{other}'")),
+    }
+}
+
+fn parse_run(args: &[String]) -> Result<RunArgs, String> {
+    let (mut market, mut scenario, mut out) = (None, None, None);
+    let mut run = RunArgs {
+        market: String::new(),
+        scenario: ScenarioSource::Preset(String::new()),
+        severity: 1.0,
+        paths: 100_000,
+        steps: 252,
+        horizon: 1.0,
+        seed: 42,
+        levels: vec![0.95, 0.99],
+        out: None,
+        paths_out: None,
+        summary_out: None,
+        compression: Compression::Snappy,
+        threads: 0,
+        strict: false,
+    };
+    let mut rest = args.iter();
+    while let Some(flag) = rest.next() {
+        let value = rest.next().ok_or_else(|| format!("{flag} needs a value"))?;
+        match flag.as_str() {
+            "--market" => market = Some(value.clone()),
+            "--scenario" => scenario = Some(ScenarioSource::File(value.clone())),
+            "--preset" => scenario = Some(ScenarioSource::Preset(value.clone())),
+            "--severity" => run.severity = number(flag, value)?,
+            "--paths" => run.paths = number(flag, value)?,
+            "--steps" => run.steps = number(flag, value)?,
+            "--horizon" => run.horizon = number(flag, value)?,
+            "--seed" => run.seed = number(flag, value)?,
+            "--levels" => run.levels = value.split(',').map(|x| number(flag, x.trim())).collect::<Result<_, _>>()?,
+            "--out" => out = Some(value.clone()),
+            "--paths-out" => run.paths_out = Some(value.clone()),
+            "--summary-out" => run.summary_out = Some(value.clone()),
+            "--compression" => run.compression = Compression::from_name(value)?,
+            "--threads" => run.threads = number(flag, value)?,
+            "--numerics" => {
+                run.strict = match value.as_str() {
+                    "fast" => false,
+                    "strict" => true,
+                    _ => return Err(format!("--numerics: '{value}' must be fast or strict")),
+                }
+            }
+            _ => return Err(format!("unknown option '{flag}'")),
+        }
+    }
+    run.market = market.ok_or("--market is required")?;
+    run.scenario = scenario.ok_or("one of --scenario or --preset is required")?;
+    run.out = out;
+    if run.paths == 0 || run.steps == 0 {
+        return Err("--paths and --steps must be positive".into());
+    }
+    Ok(run)
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{flag}: '{value}' is not a valid number"))
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_run() {
+        let cmd = parse(&argv("run --market m.json --preset black_swan --paths 5000 --levels 0.9,0.975")).unwrap();
+        let Command::Run(run) = cmd else { panic!("expected run") };
+        assert_eq!(run.market, "m.json");
+        assert_eq!(run.scenario, ScenarioSource::Preset("black_swan".into()));
+        assert_eq!((run.paths, run.steps, run.seed), (5000, 252, 42));
+        assert_eq!(run.levels, vec![0.9, 0.975]);
+        assert_eq!(run.out, None);
+        assert_eq!(run.compression, Compression::Snappy);
+
+        let cmd = parse(&argv("run --market m.json --preset x --paths-out p.parquet --compression none --threads 3")).unwrap();
+        let Command::Run(run) = cmd else { panic!("expected run") };
+        assert_eq!(run.paths_out.as_deref(), Some("p.parquet"));
+        assert_eq!(run.compression, Compression::None);
+        assert_eq!(run.threads, 3);
+        assert!(!run.strict);
+        let cmd = parse(&argv("run --market m.json --preset x --numerics strict")).unwrap();
+        assert!(matches!(cmd, Command::Run(run) if run.strict));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(&[]).unwrap(), Command::Help);
+        assert_eq!(parse(&argv("presets")).unwrap(), Command::Presets);
+        assert!(parse(&argv("run --preset black_swan")).unwrap_err().contains("--market"));
+        assert!(parse(&argv("run --market m.json")).unwrap_err().contains("--scenario"));
+        assert!(parse(&argv("run --market m.json --preset x --paths many")).is_err());
+        assert!(parse(&argv("run --market m.json --preset x --bogus 1")).is_err());
+        assert!(parse(&argv("run --market")).is_err());
+        assert!(parse(&argv("run --market m.json --preset x --compression lzma")).is_err());
+        assert!(parse(&argv("run --market m.json --preset x --numerics exact")).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+use mssim_engine::json::{self, Json};
+use mssim_engine::math;
+use mssim_engine::presets::{AssetClass, Preset};
+use mssim_engine::scenario as document;
+use mssim_engine::simulate::JumpParams;
+use nalgebra::{DMatrix, DVector};
+
+// ════════════════════════════════════════════════════════════════
+// Input files
+//
+// market.json — the base market, in the engine's field names:
+//     { "assets": ["SPX", "AGG"],            (optional labels)
+//       "asset_classes": ["equities", "bonds"], (needed for presets)
+//       "base_drift": [...], "base_vol": [...],
+//       "base_correlation": [... N×N row-major ...],
+//       "weights": [...] }                    (optional, default 1/N)
+//
+// scenario.json — any of delta_drift, vol_multiplier, correlation_skew,
+// jump_lambda, jump_mean, jump_vol (missing = no shock). Jump fields
+// are a number or an array of 1 or N. A ShockConfig saved with
+// to_json() is a valid scenario file as-is.
+//
+// A versioned scenario document (kind "scenario", see
+// mssim_engine::scenario) works as either file: the market comes from
+// its "market" section and the shock from its "shock" section.
+// ════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug)]
+pub struct Market {
+    pub assets: Vec<String>,
+    pub asset_classes: Option<Vec<AssetClass>>,
+    pub base_drift: DVector<f64>,
+    pub base_vol: DVector<f64>,
+    pub base_correlation: DMatrix<f64>,
+    pub weights: Vec<f64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub delta_drift: DVector<f64>,
+    pub vol_multiplier: DVector<f64>,
+    pub correlation_skew: f64,
+    /// Length 1 (broadcast) or N, as SimConfig::with_asset_jumps takes
+    pub jumps: Vec<JumpParams>,
+}
+
+pub fn load_market(text: &str) -> Result<Market, String> {
+    let json = section(text, "market")?;
+    let base_drift = numbers(&json, "base_drift")?.ok_or("market: base_drift is required")?;
+    let n = base_drift.len();
+    if n == 0 {
+        return Err("market: base_drift is empty".into());
+    }
+    let base_vol = numbers(&json, "base_vol")?.ok_or("market: base_vol is required")?;
+    let correlation = numbers(&json, "base_correlation")?.ok_or("market: base_correlation is required")?;
+    let weights = numbers(&json, "weights")?.unwrap_or_else(|| vec![1.0 / n as f64; n]);
+    for (field, len, want) in [
+        ("base_vol", base_vol.len(), n),
+        ("base_correlation", correlation.len(), n * n),
+        ("weights", weights.len(), n),
+    ] {
+        if len != want {
+            return Err(format!("market: {field} has length {len}, expected {want}"));
+        }
+    }
+    let base_correlation = DMatrix::from_row_slice(n, n, &correlation);
+    math::validate_correlation(&base_correlation, 1e-6)
+        .map_err(|d| format!("market: {} at ({}, {})", d.reason, d.row, d.col))?;
+
+    let assets = match json.get("assets") {
+        Some(Json::Array(items)) => items.iter().map(|x| x.as_str().unwrap_or_default().to_string()).collect(),
+        _ => (0..n).map(|i| format!("asset {i}")).collect(),
+    };
+    let asset_classes = match json.get("asset_classes") {
+        Some(Json::Array(items)) if items.len() == n => Some(
+            items
+                .iter()
+                .map(|x| AssetClass::from_name(x.as_str().unwrap_or_default()))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        Some(_) => return Err(format!("market: asset_classes must be {n} class names")),
+        None => None,
+    };
+    Ok(Market {
+        assets,
+        asset_classes,
+        base_drift: DVector::from_vec(base_drift),
+        base_vol: DVector::from_vec(base_vol),
+        base_correlation,
+        weights,
+    })
+}
+
+pub fn load_scenario(text: &str, n: usize) -> Result<Scenario, String> {
+    let json = section(text, "shock")?;
+    let per_asset = |field: &str, default: f64| -> Result<DVector<f64>, String> {
+        match numbers(&json, field)? {
+            None => Ok(DVector::from_element(n, default)),
+            Some(xs) if xs.len() == n => Ok(DVector::from_vec(xs)),
+            Some(xs) => Err(format!("scenario: {field} has length {}, expected {n}", xs.len())),
+        }
+    };
+    let jump = |field: &str| -> Result<Vec<f64>, String> {
+        match json.get(field) {
+            None => Ok(vec![0.0]),
+            Some(Json::Number(x)) => Ok(vec![*x]),
+            Some(_) => match numbers(&json, field)? {
+                Some(xs) if xs.len() == 1 || xs.len() == n => Ok(xs),
+                _ => Err(format!("scenario: {field} must be a number or an array of 1 or {n}")),
+            },
+        }
+    };
+    let (lambda, mean, vol) = (jump("jump_lambda")?, jump("jump_mean")?, jump("jump_vol")?);
+    let width = lambda.len().max(mean.len()).max(vol.len());
+    let at = |xs: &[f64], i: usize| if xs.len() == 1 { xs[0] } else { xs[i] };
+    if [&lambda, &mean, &vol].iter().any(|xs| xs.len() != 1 && xs.len() != width) {
+        return Err("scenario: jump arrays must share one length".into());
+    }
+    Ok(Scenario {
+        delta_drift: per_asset("delta_drift", 0.0)?,
+        vol_multiplier: per_asset("vol_multiplier", 1.0)?,
+        correlation_skew: json.get("correlation_skew").and_then(Json::as_f64).unwrap_or(0.0),
+        jumps: (0..width)
+            .map(|i| JumpParams { lambda: at(&lambda, i), mean: at(&mean, i), vol: at(&vol, i) })
+            .collect(),
+    })
+}
+
+pub fn preset_scenario(id: &str, market: &Market, severity: f64) -> Result<Scenario, String> {
+    let classes = market.asset_classes.as_ref().ok_or("market: presets need asset_classes")?;
+    let shock = Preset::from_name(id)?.shock(classes, severity)?;
+    Ok(Scenario {
+        delta_drift: DVector::from_vec(shock.delta_drift),
+        vol_multiplier: DVector::from_vec(shock.vol_multiplier),
+        correlation_skew: shock.correlation_skew,
+        jumps: vec![JumpParams { lambda: shock.jump_lambda, mean: shock.jump_mean, vol: shock.jump_vol }],
+    })
+}
+
+/// The whole file, or one section of a scenario document after the
+/// engine has checked its version and contents.
+fn section(text: &str, key: &str) -> Result<Json, String> {
+    let json = json::parse(text)?;
+    if json.get("kind").and_then(Json::as_str) != Some("scenario") {
+        return Ok(json);
+    }
+    document::Scenario::from_json(text)?;
+    json.get(key).cloned().ok_or_else(|| format!("scenario document: missing {key}"))
+}
+
+/// An array of numbers at `field` in full f64 precision (None if absent).
+fn numbers(json: &Json, field: &str) -> Result<Option<Vec<f64>>, String> {
+    match json.get(field) {
+        None => Ok(None),
+        Some(Json::Array(items)) => items
+            .iter()
+            .map(|x| x.as_f64().ok_or_else(|| format!("{field}: expected numbers")))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(_) => Err(format!("{field}: expected an array of numbers")),
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    const MARKET: &str = r#"{
+        "assets": ["SPX", "AGG"], "asset_classes": ["equities", "bonds"],
+        "base_drift": [0.08, 0.03], "base_vol": [0.18, 0.05],
+        "base_correlation": [1, 0.2, 0.2, 1]
+    }"#;
+
+    #[test]
+    fn test_market_defaults_and_validation() {
+        let m = load_market(MARKET).unwrap();
+        assert_eq!(m.assets, vec!["SPX", "AGG"]);
+        assert_eq!(m.weights, vec![0.5, 0.5]);
+        assert_eq!(m.asset_classes.as_deref(), Some(&[AssetClass::Equities, AssetClass::Bonds][..]));
+
+        let bad = MARKET.replace("[1, 0.2, 0.2, 1]", "[1, 0.2, 0.3, 1]");
+        assert!(load_market(&bad).unwrap_err().contains("(0, 1)"));
+        let short = MARKET.replace("[0.18, 0.05]", "[0.18]");
+        assert!(load_market(&short).unwrap_err().contains("base_vol"));
+    }
+
+    #[test]
+    fn test_scenario_file_and_preset() {
+        let s = load_scenario(r#"{"vol_multiplier": [2, 1.5], "correlation_skew": 0.4, "jump_lambda": 1.0}"#, 2).unwrap();
+        assert_eq!(s.delta_drift, DVector::zeros(2));
+        assert_relative_eq!(s.vol_multiplier[1], 1.5);
+        assert_eq!(s.jumps, vec![JumpParams { lambda: 1.0, mean: 0.0, vol: 0.0 }]);
+        assert!(load_scenario(r#"{"delta_drift": [0.1]}"#, 2).is_err());
+
+        let m = load_market(MARKET).unwrap();
+        let p = preset_scenario("black_swan", &m, 1.0).unwrap();
+        assert!(p.correlation_skew > 0.0 && p.delta_drift[0] < 0.0);
+    }
+
+    #[test]
+    fn test_scenario_document_as_both_files() {
+        let mut doc = document::Scenario::new(vec![0.08, 0.03], vec![0.18, 0.05], vec![1.0, 0.2, 0.2, 1.0]);
+        doc.market.assets = vec!["SPX".into(), "AGG".into()];
+        doc.market.weights = vec![0.6, 0.4];
+        doc.shock.vol_multiplier = vec![2.0, 1.0];
+        let text = doc.to_json();
+
+        let m = load_market(&text).unwrap();
+        assert_eq!(m.assets, vec!["SPX", "AGG"]);
+        assert_eq!(m.weights, vec![0.6, 0.4]);
+        let s = load_scenario(&text, 2).unwrap();
+        assert_relative_eq!(s.vol_multiplier[0], 2.0);
+        assert!(load_market(&text.replace("\"version\":1", "\"version\":99")).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::ops::Range;
+
+use mssim_engine::presets::Preset;
+use mssim_engine::risk;
+use mssim_engine::simulate::{PathStream, SimConfig};
+use mssim_engine::{shock_market, ShockedMarket};
+
+mod args;
+mod input;
+mod parquet;
+
+use args::{Command, RunArgs, ScenarioSource};
+use parquet::{Kind, Values};
+
+// ════════════════════════════════════════════════════════════════
+// mssim — run shock scenarios from files, outside the browser
+//
+// Same Phase A pipeline and CPU simulator as the wasm build. Paths are
+// generated in chunks and reduced to one portfolio return per path
+// (weights × terminal asset returns), so 10⁶-path runs fit in memory;
+// --paths-out streams each chunk to Parquet as its own row group.
+// Chunks are spread over --threads cores with per-path seeding, so a
+// seed gives the same returns on any machine and thread count.
+// ════════════════════════════════════════════════════════════════
+
+/// Paths simulated per chunk before reduction to portfolio returns
+const CHUNK_PATHS: usize = 10_000;
+
+fn main() {
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&argv) {
+        eprintln!("mssim: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run(argv: &[String]) -> Result<(), String> {
+    match args::parse(argv)? {
+        Command::Help => println!("{}", args::USAGE),
+        Command::Presets => {
+            for p in Preset::ALL {
+                println!("{:<18}{}", p.id(), p.name());
+            }
+        }
+        Command::Run(run) => run_scenario(&run)?,
+    }
+    Ok(())
+}
+
+fn run_scenario(run: &RunArgs) -> Result<(), String> {
+    mssim_engine::strict::set_enabled(run.strict);
+    let read = |path: &str| fs::read_to_string(path).map_err(|e| format!("{path}: {e}"));
+    let market = input::load_market(&read(&run.market)?)?;
+    let scenario = match &run.scenario {
+        ScenarioSource::File(path) => input::load_scenario(&read(path)?, market.weights.len())?,
+        ScenarioSource::Preset(id) => input::preset_scenario(id, &market, run.severity)?,
+    };
+    if let Some(out) = &run.out {
+        if !out.ends_with(".csv") && !out.ends_with(".parquet") {
+            return Err(format!("{out}: returns are written as .csv or .parquet"));
+        }
+    }
+    for out in run.paths_out.iter().chain(&run.summary_out) {
+        if !out.ends_with(".parquet") {
+            return Err(format!("{out}: paths and summaries are written as .parquet"));
+        }
+    }
+
+    let shocked = shock_market(
+        &market.base_drift,
+        &market.base_vol,
+        &market.base_correlation,
+        &scenario.delta_drift,
+        &scenario.vol_multiplier,
+        scenario.correlation_skew,
+        true,
+    )
+    .map_err(|e| e.to_string())?;
+    let config = SimConfig::new(run.horizon, run.steps, run.paths, run.seed).with_asset_jumps(scenario.jumps.clone());
+    let mut paths_out = match &run.paths_out {
+        Some(path) => Some((path, parquet_writer(path, PATH_COLUMNS, run)?)),
+        None => None,
+    };
+    let threads = thread_count(run.threads);
+    let returns = portfolio_returns(&shocked, &config, &market.weights, threads, paths_out.as_mut().map(|(_, w)| w))?;
+    if let Some((path, writer)) = paths_out {
+        writer.finish().map_err(|e| format!("{path}: {e}"))?;
+        println!("wrote {} paths × {} steps to {path}", returns.len(), run.steps + 1);
+    }
+    let stats = Summary::new(&returns, &run.levels)?;
+    print!("{}", stats.text(&market, &shocked, returns.len()));
+
+    if let Some(out) = &run.out {
+        if out.ends_with(".parquet") {
+            let mut w = parquet_writer(out, &[("path", Kind::Int32), ("return", Kind::Double)], run)?;
+            let ids = (0..returns.len() as i32).collect();
+            w.write_row_group(&[Values::Int32(ids), Values::Double(returns.clone())]).map_err(|e| format!("{out}: {e}"))?;
+            w.finish().map_err(|e| format!("{out}: {e}"))?;
+        } else {
+            let mut csv = String::from("path,return\n");
+            for (i, r) in returns.iter().enumerate() {
+                let _ = writeln!(csv, "{i},{r}");
+            }
+            fs::write(out, csv).map_err(|e| format!("{out}: {e}"))?;
+        }
+        println!("wrote {} paths to {out}", returns.len());
+    }
+    if let Some(out) = &run.summary_out {
+        let (metrics, values) = stats.rows().into_iter().unzip();
+        let mut w = parquet_writer(out, &[("metric", Kind::Utf8), ("value", Kind::Double)], run)?;
+        w.write_row_group(&[Values::Utf8(metrics), Values::Double(values)]).map_err(|e| format!("{out}: {e}"))?;
+        w.finish().map_err(|e| format!("{out}: {e}"))?;
+        println!("wrote summary to {out}");
+    }
+    Ok(())
+}
+
+type ParquetFile = parquet::Writer<BufWriter<File>>;
+
+const PATH_COLUMNS: &[(&str, Kind)] =
+    &[("path", Kind::Int32), ("step", Kind::Int32), ("asset", Kind::Int32), ("value", Kind::Float)];
+
+fn parquet_writer(path: &str, fields: &[(&str, Kind)], run: &RunArgs) -> Result<ParquetFile, String> {
+    let file = File::create(path).map_err(|e| format!("{path}: {e}"))?;
+    parquet::Writer::new(BufWriter::new(file), fields, run.compression).map_err(|e| format!("{path}: {e}"))
+}
+
+/// Σ_a w_a·(S_T,a − 1) per path, simulated CHUNK_PATHS at a time;
+/// each chunk's paths also go to `paths_out` as one row group. Chunks
+/// run `threads` at a time on scoped threads and are collected in path
+/// order; paths are seeded by index, so the output is the same for any
+/// thread count.
+fn portfolio_returns(
+    market: &ShockedMarket,
+    config: &SimConfig,
+    weights: &[f64],
+    threads: usize,
+    mut paths_out: Option<&mut ParquetFile>,
+) -> Result<Vec<f64>, String> {
+    let (n, rows) = (weights.len(), config.steps + 1);
+    let stream = PathStream::new(&market.drift, &market.vol, &market.cholesky_l, config)?;
+    let keep_paths = paths_out.is_some();
+    let simulate = |range: Range<usize>| -> Result<(Vec<f32>, Vec<f64>), String> {
+        let chunk = stream.paths(range.clone());
+        let chunk_config = SimConfig { n_paths: range.len(), ..config.clone() };
+        let terminal = risk::terminal_pnl(&chunk, n, &chunk_config)?;
+        let returns = risk::portfolio_pnl(&terminal, weights)?;
+        Ok((if keep_paths { chunk } else { Vec::new() }, returns))
+    };
+
+    let chunks: Vec<Range<usize>> =
+        (0..config.n_paths).step_by(CHUNK_PATHS).map(|start| start..config.n_paths.min(start + CHUNK_PATHS)).collect();
+    let mut returns = Vec::with_capacity(config.n_paths);
+    for wave in chunks.chunks(threads.max(1)) {
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = wave.iter().map(|range| scope.spawn(|| simulate(range.clone()))).collect();
+            handles.into_iter().map(|h| h.join().expect("simulation thread panicked")).collect()
+        });
+        for result in results {
+            let (chunk, chunk_returns) = result?;
+            if let Some(w) = paths_out.as_deref_mut() {
+                // Interleaved [path][step][asset], so row k is chunk[k]
+                let first = returns.len();
+                let path = (0..chunk.len()).map(|k| (first + k / (rows * n)) as i32).collect();
+                let step = (0..chunk.len()).map(|k| (k / n % rows) as i32).collect();
+                let asset = (0..chunk.len()).map(|k| (k % n) as i32).collect();
+                w.write_row_group(&[Values::Int32(path), Values::Int32(step), Values::Int32(asset), Values::Float(chunk)])?;
+            }
+            returns.extend(chunk_returns);
+        }
+    }
+    Ok(returns)
+}
+
+/// --threads, with 0 meaning one per available core
+fn thread_count(requested: usize) -> usize {
+    match requested {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+}
+
+/// Distribution of portfolio returns, for the console and --summary-out
+struct Summary {
+    mean: f64,
+    sd: f64,
+    worst: f64,
+    best: f64,
+    p_loss: f64,
+    risk: Vec<risk::VarCvar>,
+}
+
+impl Summary {
+    fn new(returns: &[f64], levels: &[f64]) -> Result<Summary, String> {
+        let m = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / m;
+        Ok(Summary {
+            mean,
+            sd: (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (m - 1.0).max(1.0)).sqrt(),
+            worst: returns.iter().copied().fold(f64::INFINITY, f64::min),
+            best: returns.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            p_loss: returns.iter().filter(|&&r| r < 0.0).count() as f64 / m,
+            risk: risk::compute_var_cvar(returns, &[], levels)?,
+        })
+    }
+
+    /// (metric, value) rows, e.g. ("var_0.99", 0.31)
+    fn rows(&self) -> Vec<(String, f64)> {
+        let mut rows: Vec<(String, f64)> = [
+            ("mean", self.mean),
+            ("sd", self.sd),
+            ("worst", self.worst),
+            ("best", self.best),
+            ("p_loss", self.p_loss),
+        ]
+        .iter()
+        .map(|&(k, v)| (k.to_string(), v))
+        .collect();
+        for v in &self.risk {
+            rows.push((format!("var_{}", v.level), v.var));
+            rows.push((format!("cvar_{}", v.level), v.cvar));
+        }
+        rows
+    }
+
+    fn text(&self, market: &input::Market, shocked: &ShockedMarket, n_paths: usize) -> String {
+        let Summary { mean, sd, worst, best, p_loss, .. } = *self;
+        let mut out = String::new();
+        let _ = writeln!(out, "shocked market");
+        for (i, name) in market.assets.iter().enumerate() {
+            let _ = writeln!(out, "  {name:<12} drift {:>8.4}  vol {:>7.4}", shocked.drift[i], shocked.vol[i]);
+        }
+        let r = &shocked.repair;
+        let spectrum = match (r.min_eigenvalue_before, r.min_eigenvalue_after) {
+            (Some(before), Some(after)) => format!("λmin {before:.3e} → {after:.3e}"),
+            _ => "blend already PD".to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "  repair: {} Higham iterations{}, {spectrum}, ridge {:.1e}",
+            r.iterations,
+            if r.converged { "" } else { " (not converged)" },
+            shocked.ridge_jitter,
+        );
+        let _ = writeln!(out, "portfolio return over {n_paths} paths");
+        let _ = writeln!(out, "  mean {mean:>9.4}  sd {sd:>8.4}  worst {worst:>8.4}  best {best:>8.4}");
+        let _ = writeln!(out, "  P(loss) {:.2}%", 100.0 * p_loss);
+        for v in &self.risk {
+            let _ = writeln!(out, "  {:>5.1}%  VaR {:>8.4}  CVaR {:>8.4}", 100.0 * v.level, v.var, v.cvar);
+        }
+        out
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{DMatrix, DVector};
+
+    #[test]
+    fn test_parallel_returns_match_single_thread() {
+        let market = shock_market(
+            &DVector::from_vec(vec![0.05, 0.08]),
+            &DVector::from_vec(vec![0.2, 0.3]),
+            &DMatrix::from_row_slice(2, 2, &[1.0, 0.4, 0.4, 1.0]),
+            &DVector::zeros(2),
+            &DVector::from_element(2, 1.5),
+            0.2,
+            true,
+        )
+        .unwrap();
+        let config = SimConfig::new(1.0, 4, 2 * CHUNK_PATHS + 123, 7);
+        let weights = [0.6, 0.4];
+        let single = portfolio_returns(&market, &config, &weights, 1, None).unwrap();
+        assert_eq!(single.len(), config.n_paths);
+        assert_eq!(portfolio_returns(&market, &config, &weights, 3, None).unwrap(), single);
+        assert_eq!(portfolio_returns(&market, &config, &weights, 8, None).unwrap(), single);
+
+        // Same as reducing the one-shot stream
+        let mut stream = PathStream::new(&market.drift, &market.vol, &market.cholesky_l, &config).unwrap();
+        let all = stream.next_chunk(config.n_paths);
+        let terminal = risk::terminal_pnl(&all, 2, &config).unwrap();
+        assert_eq!(risk::portfolio_pnl(&terminal, &weights).unwrap(), single);
+    }
+}
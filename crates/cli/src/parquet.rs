@@ -0,0 +1,481 @@
+use std::io::Write;
+
+// ════════════════════════════════════════════════════════════════
+// Parquet output
+//
+// A small writer for flat tables of required (non-null) columns:
+// INT32, FLOAT, DOUBLE and UTF8 strings, PLAIN-encoded, one data page
+// per column chunk, row groups written as they arrive so simulations
+// larger than memory can stream to disk. Pages are uncompressed or
+// Snappy-compressed. Metadata uses the Thrift compact protocol as
+// parquet.thrift specifies; readers needing nothing more than the
+// format itself (DuckDB, Spark, pyarrow, Polars) can load the files.
+// ════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Snappy,
+}
+
+impl Compression {
+    pub fn from_name(name: &str) -> Result<Compression, String> {
+        match name {
+            "none" | "uncompressed" => Ok(Compression::None),
+            "snappy" => Ok(Compression::Snappy),
+            other => Err(format!("unknown compression '{other}' (expected none or snappy)")),
+        }
+    }
+
+    /// parquet.thrift CompressionCodec
+    fn codec(self) -> i32 {
+        match self {
+            Compression::None => 0,
+            Compression::Snappy => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Int32,
+    Float,
+    Double,
+    Utf8,
+}
+
+impl Kind {
+    /// parquet.thrift Type
+    fn physical(self) -> i32 {
+        match self {
+            Kind::Int32 => 1,
+            Kind::Float => 4,
+            Kind::Double => 5,
+            Kind::Utf8 => 6,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Values {
+    Int32(Vec<i32>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    Utf8(Vec<String>),
+}
+
+impl Values {
+    fn kind(&self) -> Kind {
+        match self {
+            Values::Int32(_) => Kind::Int32,
+            Values::Float(_) => Kind::Float,
+            Values::Double(_) => Kind::Double,
+            Values::Utf8(_) => Kind::Utf8,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Values::Int32(xs) => xs.len(),
+            Values::Float(xs) => xs.len(),
+            Values::Double(xs) => xs.len(),
+            Values::Utf8(xs) => xs.len(),
+        }
+    }
+
+    /// PLAIN encoding
+    fn plain(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Values::Int32(xs) => xs.iter().for_each(|x| out.extend_from_slice(&x.to_le_bytes())),
+            Values::Float(xs) => xs.iter().for_each(|x| out.extend_from_slice(&x.to_le_bytes())),
+            Values::Double(xs) => xs.iter().for_each(|x| out.extend_from_slice(&x.to_le_bytes())),
+            Values::Utf8(xs) => xs.iter().for_each(|s| {
+                out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+                out.extend_from_slice(s.as_bytes());
+            }),
+        }
+        out
+    }
+}
+
+struct ChunkMeta {
+    offset: i64,
+    uncompressed: i64,
+    compressed: i64,
+}
+
+struct RowGroupMeta {
+    rows: i64,
+    chunks: Vec<ChunkMeta>,
+}
+
+// ────────────────────────────────────────────────────────────────
+// Writer
+// ────────────────────────────────────────────────────────────────
+
+pub struct Writer<W: Write> {
+    out: W,
+    pos: i64,
+    fields: Vec<(String, Kind)>,
+    compression: Compression,
+    row_groups: Vec<RowGroupMeta>,
+}
+
+const MAGIC: &[u8] = b"PAR1";
+
+impl<W: Write> Writer<W> {
+    pub fn new(mut out: W, fields: &[(&str, Kind)], compression: Compression) -> Result<Writer<W>, String> {
+        out.write_all(MAGIC).map_err(|e| e.to_string())?;
+        Ok(Writer {
+            out,
+            pos: MAGIC.len() as i64,
+            fields: fields.iter().map(|&(name, kind)| (name.to_string(), kind)).collect(),
+            compression,
+            row_groups: Vec::new(),
+        })
+    }
+
+    /// One row group; columns in schema order, all of one length.
+    pub fn write_row_group(&mut self, columns: &[Values]) -> Result<(), String> {
+        if columns.len() != self.fields.len() || columns.iter().zip(&self.fields).any(|(c, (_, k))| c.kind() != *k) {
+            return Err("parquet: columns must match the schema".into());
+        }
+        let rows = columns.first().map_or(0, Values::len);
+        if columns.iter().any(|c| c.len() != rows) {
+            return Err("parquet: columns must share one length".into());
+        }
+        let mut chunks = Vec::with_capacity(columns.len());
+        for c in columns {
+            let plain = c.plain();
+            let data = match self.compression {
+                Compression::None => plain.clone(),
+                Compression::Snappy => snappy_compress(&plain),
+            };
+            let header = page_header(rows, plain.len(), data.len());
+            let offset = self.pos;
+            for bytes in [&header, &data] {
+                self.out.write_all(bytes).map_err(|e| e.to_string())?;
+                self.pos += bytes.len() as i64;
+            }
+            chunks.push(ChunkMeta {
+                offset,
+                uncompressed: (header.len() + plain.len()) as i64,
+                compressed: (header.len() + data.len()) as i64,
+            });
+        }
+        self.row_groups.push(RowGroupMeta { rows: rows as i64, chunks });
+        Ok(())
+    }
+
+    /// Footer (FileMetaData, its length, magic); returns the sink.
+    pub fn finish(mut self) -> Result<W, String> {
+        let footer = self.file_metadata();
+        for bytes in [&footer[..], &(footer.len() as u32).to_le_bytes(), MAGIC] {
+            self.out.write_all(bytes).map_err(|e| e.to_string())?;
+        }
+        self.out.flush().map_err(|e| e.to_string())?;
+        Ok(self.out)
+    }
+
+    fn file_metadata(&self) -> Vec<u8> {
+        let mut t = Compact::default();
+        t.i32(1, 1);
+        t.list(2, STRUCT, self.fields.len() + 1);
+        t.begin_element();
+        t.string(4, "schema");
+        t.i32(5, self.fields.len() as i32);
+        t.end();
+        for (name, kind) in &self.fields {
+            t.begin_element();
+            t.i32(1, kind.physical());
+            t.i32(3, 0); // REQUIRED
+            t.string(4, name);
+            if *kind == Kind::Utf8 {
+                t.i32(6, 0); // ConvertedType UTF8
+            }
+            t.end();
+        }
+        t.i64(3, self.row_groups.iter().map(|g| g.rows).sum());
+        t.list(4, STRUCT, self.row_groups.len());
+        for g in &self.row_groups {
+            t.begin_element();
+            t.list(1, STRUCT, g.chunks.len());
+            for (c, (name, kind)) in g.chunks.iter().zip(&self.fields) {
+                t.begin_element();
+                t.i64(2, c.offset);
+                t.begin(3);
+                t.i32(1, kind.physical());
+                t.list(2, I32, 1);
+                t.varint(0); // PLAIN
+                t.list(3, BINARY, 1);
+                t.bytes(name.as_bytes());
+                t.i32(4, self.compression.codec());
+                t.i64(5, g.rows);
+                t.i64(6, c.uncompressed);
+                t.i64(7, c.compressed);
+                t.i64(9, c.offset);
+                t.end();
+                t.end();
+            }
+            t.i64(2, g.chunks.iter().map(|c| c.uncompressed).sum());
+            t.i64(3, g.rows);
+            t.end();
+        }
+        t.string(6, concat!("mssim-cli ", env!("CARGO_PKG_VERSION")));
+        t.finish()
+    }
+}
+
+/// PageHeader for a v1 data page of required values (no levels)
+fn page_header(rows: usize, uncompressed: usize, compressed: usize) -> Vec<u8> {
+    let mut t = Compact::default();
+    t.i32(1, 0); // DATA_PAGE
+    t.i32(2, uncompressed as i32);
+    t.i32(3, compressed as i32);
+    t.begin(5);
+    t.i32(1, rows as i32);
+    t.i32(2, 0); // PLAIN
+    t.i32(3, 3); // RLE
+    t.i32(4, 3);
+    t.end();
+    t.finish()
+}
+
+// ────────────────────────────────────────────────────────────────
+// Thrift compact protocol (write side)
+// ────────────────────────────────────────────────────────────────
+
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+#[derive(Default)]
+struct Compact {
+    buf: Vec<u8>,
+    last_id: i16,
+    stack: Vec<i16>,
+}
+
+impl Compact {
+    fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.buf.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        self.buf.push(v as u8);
+    }
+
+    fn zigzag(&mut self, v: i64) {
+        self.varint(((v << 1) ^ (v >> 63)) as u64);
+    }
+
+    fn header(&mut self, id: i16, ty: u8) {
+        let delta = id - self.last_id;
+        if (1..=15).contains(&delta) {
+            self.buf.push((delta as u8) << 4 | ty);
+        } else {
+            self.buf.push(ty);
+            self.zigzag(id as i64);
+        }
+        self.last_id = id;
+    }
+
+    fn i32(&mut self, id: i16, v: i32) {
+        self.header(id, I32);
+        self.zigzag(v as i64);
+    }
+
+    fn i64(&mut self, id: i16, v: i64) {
+        self.header(id, I64);
+        self.zigzag(v);
+    }
+
+    fn bytes(&mut self, b: &[u8]) {
+        self.varint(b.len() as u64);
+        self.buf.extend_from_slice(b);
+    }
+
+    fn string(&mut self, id: i16, s: &str) {
+        self.header(id, BINARY);
+        self.bytes(s.as_bytes());
+    }
+
+    fn list(&mut self, id: i16, elem: u8, len: usize) {
+        self.header(id, LIST);
+        if len < 15 {
+            self.buf.push((len as u8) << 4 | elem);
+        } else {
+            self.buf.push(0xF0 | elem);
+            self.varint(len as u64);
+        }
+    }
+
+    /// Struct-valued field
+    fn begin(&mut self, id: i16) {
+        self.header(id, STRUCT);
+        self.begin_element();
+    }
+
+    /// Struct inside a list
+    fn begin_element(&mut self) {
+        self.stack.push(self.last_id);
+        self.last_id = 0;
+    }
+
+    fn end(&mut self) {
+        self.buf.push(0);
+        self.last_id = self.stack.pop().unwrap_or(0);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.buf.push(0);
+        self.buf
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Snappy (raw block format, as Parquet stores it)
+//
+// Greedy matcher over a 4-byte hash table; literals plus 2-byte-offset
+// copies only, which every decoder accepts.
+// ────────────────────────────────────────────────────────────────
+
+pub fn snappy_compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2 + 16);
+    let mut v = input.len() as u64;
+    while v >= 0x80 {
+        out.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+
+    let mut table = vec![usize::MAX; 1 << 14];
+    let (mut i, mut literal) = (0, 0);
+    while i + 4 <= input.len() {
+        let key = u32::from_le_bytes([input[i], input[i + 1], input[i + 2], input[i + 3]]);
+        let h = (key.wrapping_mul(0x1e35_a7bd) >> 18) as usize;
+        let candidate = std::mem::replace(&mut table[h], i);
+        if candidate == usize::MAX || i - candidate > 0xFFFF || input[candidate..candidate + 4] != input[i..i + 4] {
+            i += 1;
+            continue;
+        }
+        let mut len = 4;
+        while i + len < input.len() && input[candidate + len] == input[i + len] {
+            len += 1;
+        }
+        emit_literal(&mut out, &input[literal..i]);
+        let offset = (i - candidate) as u16;
+        let mut left = len;
+        while left > 0 {
+            let n = left.min(64);
+            out.push(((n - 1) << 2 | 2) as u8);
+            out.extend_from_slice(&offset.to_le_bytes());
+            left -= n;
+        }
+        i += len;
+        literal = i;
+    }
+    emit_literal(&mut out, &input[literal..]);
+    out
+}
+
+fn emit_literal(out: &mut Vec<u8>, bytes: &[u8]) {
+    if bytes.is_empty() {
+        return;
+    }
+    let n = bytes.len() - 1;
+    if n < 60 {
+        out.push((n << 2) as u8);
+    } else {
+        // 60..63: length − 1 follows in 1..4 little-endian bytes
+        let width = [1 << 8, 1 << 16, 1 << 24].iter().take_while(|&&limit| n >= limit).count() + 1;
+        out.push(((59 + width) << 2) as u8);
+        out.extend_from_slice(&n.to_le_bytes()[..width]);
+    }
+    out.extend_from_slice(bytes);
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snappy_decompress(data: &[u8]) -> Vec<u8> {
+        let (mut len, mut shift, mut i) = (0usize, 0, 0);
+        loop {
+            len |= ((data[i] & 0x7F) as usize) << shift;
+            shift += 7;
+            i += 1;
+            if data[i - 1] < 0x80 {
+                break;
+            }
+        }
+        let mut out = Vec::with_capacity(len);
+        while i < data.len() {
+            let tag = data[i];
+            i += 1;
+            match tag & 3 {
+                0 => {
+                    let mut n = (tag >> 2) as usize;
+                    if n >= 60 {
+                        let width = n - 59;
+                        n = (0..width).map(|k| (data[i + k] as usize) << (8 * k)).sum();
+                        i += width;
+                    }
+                    out.extend_from_slice(&data[i..i + n + 1]);
+                    i += n + 1;
+                }
+                2 => {
+                    let n = (tag >> 2) as usize + 1;
+                    let offset = u16::from_le_bytes([data[i], data[i + 1]]) as usize;
+                    i += 2;
+                    for _ in 0..n {
+                        out.push(out[out.len() - offset]);
+                    }
+                }
+                _ => panic!("unexpected tag"),
+            }
+        }
+        assert_eq!(out.len(), len);
+        out
+    }
+
+    #[test]
+    fn test_snappy_round_trip() {
+        let repetitive: Vec<u8> = (0..100_000u32).flat_map(|i| (i / 7).to_le_bytes()).collect();
+        let packed = snappy_compress(&repetitive);
+        assert!(packed.len() < repetitive.len() / 3);
+        assert_eq!(snappy_decompress(&packed), repetitive);
+
+        let noisy: Vec<u8> = (0..70_000u64).map(|i| (i.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 56) as u8).collect();
+        assert_eq!(snappy_decompress(&snappy_compress(&noisy)), noisy);
+        assert_eq!(snappy_decompress(&snappy_compress(b"abc")), b"abc");
+    }
+
+    #[test]
+    fn test_compact_encoding_and_file_layout() {
+        let mut t = Compact::default();
+        t.i32(1, -1);
+        t.i64(20, 300);
+        t.begin(21);
+        t.string(1, "x");
+        t.end();
+        assert_eq!(t.finish(), vec![0x15, 0x01, 0x06, 0x28, 0xD8, 0x04, 0x1C, 0x18, 0x01, b'x', 0x00, 0x00]);
+
+        let mut w = Writer::new(Vec::new(), &[("path", Kind::Int32), ("metric", Kind::Utf8)], Compression::Snappy).unwrap();
+        w.write_row_group(&[Values::Int32(vec![0, 1]), Values::Utf8(vec!["a".into(), "bc".into()])]).unwrap();
+        assert!(w.write_row_group(&[Values::Int32(vec![0]), Values::Double(vec![1.0])]).is_err());
+        let file = w.finish().unwrap();
+        assert_eq!(&file[..4], MAGIC);
+        assert_eq!(&file[file.len() - 4..], MAGIC);
+        let footer = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap()) as usize;
+        let meta = &file[file.len() - 8 - footer..file.len() - 8];
+        assert!(meta.windows(6).any(|w| w == b"metric"));
+        assert_eq!(meta.last(), Some(&0));
+    }
+}
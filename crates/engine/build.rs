@@ -0,0 +1,24 @@
+use std::path::Path;
+use std::process::Command;
+
+// ════════════════════════════════════════════════════════════════
+// Exposes the commit the engine was built from as MSSIM_GIT_HASH
+// (for engine_info). An MSSIM_GIT_HASH already in the environment
+// wins, e.g. in builds from a source tarball; otherwise git is asked,
+// and "unknown" stands in when neither is available.
+// ════════════════════════════════════════════════════════════════
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=MSSIM_GIT_HASH");
+    for path in ["../../.git/HEAD", "../../.git/refs/heads", "../../.git/packed-refs"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+    let hash = std::env::var("MSSIM_GIT_HASH").ok().or_else(|| {
+        let out = Command::new("git").args(["rev-parse", "--short=12", "HEAD"]).output().ok()?;
+        let hash = String::from_utf8(out.stdout).ok()?.trim().to_string();
+        (out.status.success() && !hash.is_empty()).then_some(hash)
+    });
+    println!("cargo:rustc-env=MSSIM_GIT_HASH={}", hash.as_deref().unwrap_or("unknown"));
+}
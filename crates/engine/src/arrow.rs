@@ -0,0 +1,501 @@
+use crate::risk::VarCvar;
+use crate::simulate::{OutputLayout, SimConfig};
+
+// ════════════════════════════════════════════════════════════════
+// Arrow IPC stream export
+//
+// Writes the Arrow IPC *stream* format (schema message, record
+// batches, end-of-stream marker) so DuckDB, Polars, pyarrow or
+// Observable's Arrow loader can read simulator and risk output
+// directly. Like json.rs this is hand-rolled to keep the wasm build
+// dependency-free: only the flat, non-nullable UInt32 / Float32 /
+// Float64 columns the engine emits, metadata version V5, no
+// compression. Everything is little-endian and 8-byte aligned.
+// ════════════════════════════════════════════════════════════════
+
+/// Rows per record batch for long-format path tables
+pub const BATCH_ROWS: usize = 1 << 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataType {
+    UInt32,
+    Float32,
+    Float64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Column {
+    UInt32(Vec<u32>),
+    Float32(Vec<f32>),
+    Float64(Vec<f64>),
+}
+
+impl Column {
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Column::UInt32(_) => DataType::UInt32,
+            Column::Float32(_) => DataType::Float32,
+            Column::Float64(_) => DataType::Float64,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Column::UInt32(xs) => xs.len(),
+            Column::Float32(xs) => xs.len(),
+            Column::Float64(xs) => xs.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn write_le(&self, out: &mut Vec<u8>) {
+        match self {
+            Column::UInt32(xs) => xs.iter().for_each(|x| out.extend_from_slice(&x.to_le_bytes())),
+            Column::Float32(xs) => xs.iter().for_each(|x| out.extend_from_slice(&x.to_le_bytes())),
+            Column::Float64(xs) => xs.iter().for_each(|x| out.extend_from_slice(&x.to_le_bytes())),
+        }
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Stream writer
+// ────────────────────────────────────────────────────────────────
+
+/// Accumulates one IPC stream: the schema on creation, a record batch
+/// per write_batch, the end-of-stream marker on finish.
+pub struct StreamWriter {
+    fields: Vec<(String, DataType)>,
+    out: Vec<u8>,
+}
+
+impl StreamWriter {
+    pub fn new(fields: &[(&str, DataType)]) -> StreamWriter {
+        let fields: Vec<(String, DataType)> = fields.iter().map(|&(name, ty)| (name.to_string(), ty)).collect();
+        let mut b = Builder::default();
+        let header = schema(&mut b, &fields);
+        let mut out = Vec::new();
+        write_message(&mut out, b, MessageHeader::Schema, header, &[]);
+        StreamWriter { fields, out }
+    }
+
+    /// One record batch; columns must match the schema in order and type
+    /// and share one length.
+    pub fn write_batch(&mut self, columns: &[Column]) -> Result<(), &'static str> {
+        if columns.len() != self.fields.len()
+            || columns.iter().zip(&self.fields).any(|(c, (_, ty))| c.data_type() != *ty)
+        {
+            return Err("Arrow input mismatch: columns must match the schema");
+        }
+        let rows = columns.first().map_or(0, Column::len);
+        if columns.iter().any(|c| c.len() != rows) {
+            return Err("Arrow input mismatch: columns must share one length");
+        }
+
+        // Body: per column an empty validity buffer and the values,
+        // each value buffer padded to 8 bytes
+        let mut body = Vec::new();
+        let mut buffers = Vec::with_capacity(2 * columns.len());
+        for c in columns {
+            buffers.push([body.len() as i64, 0]);
+            let start = body.len();
+            c.write_le(&mut body);
+            buffers.push([start as i64, (body.len() - start) as i64]);
+            body.resize(body.len().next_multiple_of(8), 0);
+        }
+
+        let mut b = Builder::default();
+        let nodes = b.structs(&vec![[rows as i64, 0]; columns.len()]);
+        let buffers = b.structs(&buffers);
+        let header = b.table(&[Some(Slot::I64(rows as i64)), Some(Slot::Ref(nodes)), Some(Slot::Ref(buffers))]);
+        write_message(&mut self.out, b, MessageHeader::RecordBatch, header, &body);
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.out.extend_from_slice(&CONTINUATION.to_le_bytes());
+        self.out.extend_from_slice(&0u32.to_le_bytes());
+        self.out
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Engine outputs
+// ────────────────────────────────────────────────────────────────
+
+/// Long-format paths: columns path, step, asset (UInt32) and value
+/// (Float32), batched by whole paths of about BATCH_ROWS rows. Reads
+/// `paths` in `config.layout`.
+pub fn paths_to_ipc(paths: &[f32], n: usize, config: &SimConfig) -> Result<Vec<u8>, &'static str> {
+    let (n_paths, rows) = (config.n_paths, config.steps + 1);
+    if n == 0 || paths.len() != n_paths * rows * n {
+        return Err("Arrow input mismatch: paths must be n_paths × (steps + 1) × N");
+    }
+    let mut writer = StreamWriter::new(&[
+        ("path", DataType::UInt32),
+        ("step", DataType::UInt32),
+        ("asset", DataType::UInt32),
+        ("value", DataType::Float32),
+    ]);
+    let per_batch = (BATCH_ROWS / (rows * n)).max(1);
+    for first in (0..n_paths).step_by(per_batch) {
+        let last = (first + per_batch).min(n_paths);
+        let len = (last - first) * rows * n;
+        let (mut path, mut step, mut asset, mut value) =
+            (Vec::with_capacity(len), Vec::with_capacity(len), Vec::with_capacity(len), Vec::with_capacity(len));
+        for p in first..last {
+            for t in 0..rows {
+                for a in 0..n {
+                    let idx = match config.layout {
+                        OutputLayout::Interleaved => (p * rows + t) * n + a,
+                        OutputLayout::Planar => (a * n_paths + p) * rows + t,
+                    };
+                    path.push(p as u32);
+                    step.push(t as u32);
+                    asset.push(a as u32);
+                    value.push(paths[idx]);
+                }
+            }
+        }
+        writer.write_batch(&[Column::UInt32(path), Column::UInt32(step), Column::UInt32(asset), Column::Float32(value)])?;
+    }
+    Ok(writer.finish())
+}
+
+/// [path][asset] terminal P&L (risk::terminal_pnl) as columns path,
+/// asset (UInt32) and pnl (Float64).
+pub fn terminal_pnl_to_ipc(pnl: &[f64], n: usize) -> Result<Vec<u8>, &'static str> {
+    if n == 0 || !pnl.len().is_multiple_of(n) {
+        return Err("Arrow input mismatch: pnl must be n_paths × N");
+    }
+    let mut writer = StreamWriter::new(&[
+        ("path", DataType::UInt32),
+        ("asset", DataType::UInt32),
+        ("pnl", DataType::Float64),
+    ]);
+    let per_batch = BATCH_ROWS.next_multiple_of(n);
+    for (k, chunk) in pnl.chunks(per_batch).enumerate() {
+        let offset = k * per_batch;
+        let path = (0..chunk.len()).map(|i| ((offset + i) / n) as u32).collect();
+        let asset = (0..chunk.len()).map(|i| ((offset + i) % n) as u32).collect();
+        writer.write_batch(&[Column::UInt32(path), Column::UInt32(asset), Column::Float64(chunk.to_vec())])?;
+    }
+    Ok(writer.finish())
+}
+
+/// One row per confidence level: level, var, cvar (Float64).
+pub fn var_cvar_to_ipc(results: &[VarCvar]) -> Vec<u8> {
+    let mut writer =
+        StreamWriter::new(&[("level", DataType::Float64), ("var", DataType::Float64), ("cvar", DataType::Float64)]);
+    let column = |f: fn(&VarCvar) -> f64| Column::Float64(results.iter().map(f).collect());
+    // Infallible: three Float64 columns of one length
+    let _ = writer.write_batch(&[column(|r| r.level), column(|r| r.var), column(|r| r.cvar)]);
+    writer.finish()
+}
+
+// ────────────────────────────────────────────────────────────────
+// Messages (Schema.fbs / Message.fbs)
+// ────────────────────────────────────────────────────────────────
+
+const CONTINUATION: u32 = 0xFFFF_FFFF;
+/// MetadataVersion::V5
+const METADATA_V5: i16 = 4;
+
+#[derive(Clone, Copy)]
+enum MessageHeader {
+    Schema = 1,
+    RecordBatch = 3,
+}
+
+/// Encapsulated message: continuation marker, metadata length, the
+/// Message flatbuffer (8-byte aligned), then the body.
+fn write_message(out: &mut Vec<u8>, mut b: Builder, kind: MessageHeader, header: Offset, body: &[u8]) {
+    let message = b.table(&[
+        Some(Slot::I16(METADATA_V5)),
+        Some(Slot::U8(kind as u8)),
+        Some(Slot::Ref(header)),
+        Some(Slot::I64(body.len() as i64)),
+    ]);
+    let metadata = b.finish(message);
+    out.extend_from_slice(&CONTINUATION.to_le_bytes());
+    out.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+    out.extend_from_slice(&metadata);
+    out.extend_from_slice(body);
+}
+
+fn schema(b: &mut Builder, fields: &[(String, DataType)]) -> Offset {
+    let fields: Vec<Offset> = fields
+        .iter()
+        .map(|(name, ty)| {
+            let name = b.string(name);
+            // Type union: Int = 2 { bitWidth, is_signed }, FloatingPoint = 3 { precision }
+            let (type_tag, ty) = match ty {
+                DataType::UInt32 => (2, b.table(&[Some(Slot::I32(32)), Some(Slot::U8(0))])),
+                DataType::Float32 => (3, b.table(&[Some(Slot::I16(1))])),
+                DataType::Float64 => (3, b.table(&[Some(Slot::I16(2))])),
+            };
+            // Readers reject a missing children vector, even for primitives
+            let children = b.refs(&[]);
+            b.table(&[
+                Some(Slot::Ref(name)),
+                Some(Slot::U8(0)),
+                Some(Slot::U8(type_tag)),
+                Some(Slot::Ref(ty)),
+                None,
+                Some(Slot::Ref(children)),
+            ])
+        })
+        .collect();
+    let fields = b.refs(&fields);
+    b.table(&[Some(Slot::I16(0)), Some(Slot::Ref(fields))])
+}
+
+// ────────────────────────────────────────────────────────────────
+// FlatBuffers, built back to front
+//
+// Objects are prepended, so each is identified by its distance from
+// the end of the buffer; a uoffset stored at distance `at` pointing to
+// `target` holds at − target. finish() pads the total to a multiple
+// of 8, which makes end-relative alignment absolute.
+// ────────────────────────────────────────────────────────────────
+
+/// Distance of an object from the end of the buffer
+type Offset = usize;
+
+enum Slot {
+    U8(u8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    /// uoffset to an object already in the buffer
+    Ref(Offset),
+}
+
+impl Slot {
+    fn size(&self) -> usize {
+        match self {
+            Slot::U8(_) => 1,
+            Slot::I16(_) => 2,
+            Slot::I32(_) | Slot::Ref(_) => 4,
+            Slot::I64(_) => 8,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Builder {
+    buf: Vec<u8>,
+}
+
+impl Builder {
+    fn prepend(&mut self, bytes: &[u8]) {
+        self.buf.splice(0..0, bytes.iter().copied());
+    }
+
+    /// Pad so that `additional` bytes prepended next end up aligned.
+    fn pad(&mut self, align: usize, additional: usize) {
+        let n = (align - (self.buf.len() + additional) % align) % align;
+        self.prepend(&vec![0; n]);
+    }
+
+    fn string(&mut self, s: &str) -> Offset {
+        let mut bytes = (s.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(s.as_bytes());
+        bytes.push(0);
+        self.pad(4, bytes.len());
+        self.prepend(&bytes);
+        self.buf.len()
+    }
+
+    /// Vector of 16-byte structs (FieldNode, Buffer), 8-byte aligned
+    fn structs(&mut self, items: &[[i64; 2]]) -> Offset {
+        let bytes: Vec<u8> = items.iter().flatten().flat_map(|x| x.to_le_bytes()).collect();
+        self.pad(8, bytes.len());
+        self.prepend(&bytes);
+        self.prepend(&(items.len() as u32).to_le_bytes());
+        self.buf.len()
+    }
+
+    /// Vector of tables
+    fn refs(&mut self, items: &[Offset]) -> Offset {
+        let len = 4 * (items.len() + 1);
+        self.pad(4, len);
+        let start = self.buf.len() + len;
+        let mut bytes = (items.len() as u32).to_le_bytes().to_vec();
+        for (i, &target) in items.iter().enumerate() {
+            bytes.extend_from_slice(&((start - 4 * (i + 1) - target) as u32).to_le_bytes());
+        }
+        self.prepend(&bytes);
+        start
+    }
+
+    /// Table with one entry per schema slot (None = absent), followed in
+    /// memory by nothing and preceded by its vtable.
+    fn table(&mut self, slots: &[Option<Slot>]) -> Offset {
+        // soffset first, then fields largest first at natural alignment
+        let mut order: Vec<usize> = (0..slots.len()).filter(|&i| slots[i].is_some()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(slots[i].as_ref().map_or(0, Slot::size)));
+        let mut at = vec![0u16; slots.len()];
+        let mut len = 4usize;
+        for &i in &order {
+            let size = slots[i].as_ref().map_or(0, Slot::size);
+            len = len.next_multiple_of(size);
+            at[i] = len as u16;
+            len += size;
+        }
+
+        self.pad(8, len);
+        let start = self.buf.len() + len;
+        let vtable_len = 4 + 2 * slots.len();
+        let mut bytes = vec![0u8; len];
+        bytes[..4].copy_from_slice(&(vtable_len as i32).to_le_bytes());
+        for &i in &order {
+            let k = at[i] as usize;
+            match slots[i].as_ref() {
+                Some(Slot::U8(x)) => bytes[k] = *x,
+                Some(Slot::I16(x)) => bytes[k..k + 2].copy_from_slice(&x.to_le_bytes()),
+                Some(Slot::I32(x)) => bytes[k..k + 4].copy_from_slice(&x.to_le_bytes()),
+                Some(Slot::I64(x)) => bytes[k..k + 8].copy_from_slice(&x.to_le_bytes()),
+                Some(Slot::Ref(target)) => bytes[k..k + 4].copy_from_slice(&((start - k - target) as u32).to_le_bytes()),
+                None => {}
+            }
+        }
+        self.prepend(&bytes);
+
+        let mut vtable = Vec::with_capacity(vtable_len);
+        vtable.extend_from_slice(&(vtable_len as u16).to_le_bytes());
+        vtable.extend_from_slice(&(len as u16).to_le_bytes());
+        at.iter().for_each(|k| vtable.extend_from_slice(&k.to_le_bytes()));
+        self.prepend(&vtable);
+        start
+    }
+
+    fn finish(mut self, root: Offset) -> Vec<u8> {
+        self.pad(8, 4);
+        let at = self.buf.len() + 4;
+        self.prepend(&((at - root) as u32).to_le_bytes());
+        self.buf
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    // Minimal FlatBuffers reader to check what the builder wrote
+    fn u32_at(b: &[u8], at: usize) -> usize {
+        u32::from_le_bytes(b[at..at + 4].try_into().unwrap()) as usize
+    }
+
+    fn i64_at(b: &[u8], at: usize) -> i64 {
+        i64::from_le_bytes(b[at..at + 8].try_into().unwrap())
+    }
+
+    /// Absolute position of field `slot` of the table at `table`
+    fn field(b: &[u8], table: usize, slot: usize) -> Option<usize> {
+        let vtable = table - i32::from_le_bytes(b[table..table + 4].try_into().unwrap()) as usize;
+        let vtable_len = u16::from_le_bytes([b[vtable], b[vtable + 1]]) as usize;
+        if 4 + 2 * slot >= vtable_len {
+            return None;
+        }
+        let k = u16::from_le_bytes([b[vtable + 4 + 2 * slot], b[vtable + 5 + 2 * slot]]) as usize;
+        (k != 0).then_some(table + k)
+    }
+
+    fn deref(b: &[u8], at: usize) -> usize {
+        at + u32_at(b, at)
+    }
+
+    /// (header type, header table, body) of each message until EOS
+    fn messages(stream: &[u8]) -> Vec<(u8, Vec<u8>, usize, Vec<u8>)> {
+        let mut out = Vec::new();
+        let mut pos = 0;
+        loop {
+            assert_eq!(u32_at(stream, pos), CONTINUATION as usize);
+            let len = u32_at(stream, pos + 4);
+            if len == 0 {
+                assert_eq!(pos + 8, stream.len());
+                return out;
+            }
+            assert_eq!(len % 8, 0);
+            let meta = stream[pos + 8..pos + 8 + len].to_vec();
+            let root = deref(&meta, 0);
+            assert_eq!(i16::from_le_bytes(meta[field(&meta, root, 0).unwrap()..][..2].try_into().unwrap()), 4);
+            let kind = meta[field(&meta, root, 1).unwrap()];
+            let header = deref(&meta, field(&meta, root, 2).unwrap());
+            let body_len = i64_at(&meta, field(&meta, root, 3).unwrap()) as usize;
+            let body = stream[pos + 8 + len..pos + 8 + len + body_len].to_vec();
+            pos += 8 + len + body_len;
+            out.push((kind, meta, header, body));
+        }
+    }
+
+    #[test]
+    fn test_stream_schema_and_batches_decode() {
+        let config = SimConfig::new(1.0, 2, 3, 0);
+        let paths: Vec<f32> = (0..3 * 3 * 2).map(|i| i as f32).collect();
+        let stream = paths_to_ipc(&paths, 2, &config).unwrap();
+        let msgs = messages(&stream);
+        assert_eq!(msgs.len(), 2);
+
+        // Schema: four fields path/step/asset/value, the last Float32
+        let (kind, meta, schema, _) = &msgs[0];
+        assert_eq!(*kind, MessageHeader::Schema as u8);
+        let fields = deref(meta, field(meta, *schema, 1).unwrap());
+        assert_eq!(u32_at(meta, fields), 4);
+        let names: Vec<String> = (0..4)
+            .map(|i| {
+                let f = deref(meta, fields + 4 + 4 * i);
+                let s = deref(meta, field(meta, f, 0).unwrap());
+                String::from_utf8(meta[s + 4..s + 4 + u32_at(meta, s)].to_vec()).unwrap()
+            })
+            .collect();
+        assert_eq!(names, ["path", "step", "asset", "value"]);
+        let value = deref(meta, fields + 16);
+        assert_eq!(meta[field(meta, value, 2).unwrap()], 3);
+        let float = deref(meta, field(meta, value, 3).unwrap());
+        assert_eq!(meta[field(meta, float, 0).unwrap()], 1);
+        assert!(field(meta, value, 5).is_some());
+
+        // Batch: 18 rows, buffers 8-aligned, values in [path][step][asset]
+        let (kind, meta, batch, body) = &msgs[1];
+        assert_eq!(*kind, MessageHeader::RecordBatch as u8);
+        assert_eq!(i64_at(meta, field(meta, *batch, 0).unwrap()), 18);
+        let buffers = deref(meta, field(meta, *batch, 2).unwrap());
+        assert_eq!(u32_at(meta, buffers), 8);
+        assert_eq!((buffers + 4) % 8, 0);
+        let buffer = |i: usize| (i64_at(meta, buffers + 4 + 16 * i) as usize, i64_at(meta, buffers + 12 + 16 * i) as usize);
+        let (step_at, step_len) = buffer(3);
+        assert_eq!((step_at % 8, step_len), (0, 72));
+        assert_eq!(u32_at(body, step_at + 4 * 7), 0);
+        assert_eq!(u32_at(body, step_at + 4 * 8), 1);
+        let (value_at, _) = buffer(7);
+        let v = f32::from_le_bytes(body[value_at + 4 * 17..][..4].try_into().unwrap());
+        assert_relative_eq!(v, 17.0);
+    }
+
+    #[test]
+    fn test_batching_and_validation() {
+        let config = SimConfig::new(1.0, 9, 20_000, 0);
+        let paths = vec![1.0f32; 20_000 * 10];
+        assert_eq!(messages(&paths_to_ipc(&paths, 1, &config).unwrap()).len(), 1 + 4);
+        assert!(paths_to_ipc(&paths[1..], 1, &config).is_err());
+
+        let pnl = terminal_pnl_to_ipc(&[0.1, -0.2, 0.3, 0.0], 2).unwrap();
+        let msgs = messages(&pnl);
+        let body = &msgs[1].3;
+        assert_eq!(u32_at(body, 16 + 4 * 3), 1);
+        assert!(terminal_pnl_to_ipc(&[0.1, 0.2, 0.3], 2).is_err());
+
+        let mut w = StreamWriter::new(&[("x", DataType::Float64)]);
+        assert!(w.write_batch(&[Column::Float32(vec![1.0])]).is_err());
+        let r = var_cvar_to_ipc(&[VarCvar { level: 0.95, var: 0.1, cvar: 0.2 }]);
+        assert_eq!(messages(&r)[1].3.len(), 24);
+    }
+}
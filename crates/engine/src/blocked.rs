@@ -0,0 +1,112 @@
+use nalgebra::DMatrix;
+
+// ════════════════════════════════════════════════════════════════
+// Cache-blocked dense kernels for very large N
+//
+// Past a few hundred assets an N×N f64 matrix no longer fits in cache
+// (8 MB at N = 1000), and column-at-a-time loops — nalgebra's
+// Cholesky, the dot-product reconstruction in simd.rs — stream it from
+// memory once per column. These split the work into BLOCK-wide strips
+// whose products go through nalgebra's gemm, which tiles for cache
+// itself. Results agree with the unblocked forms to rounding; callers
+// switch over at MIN_N so smaller problems keep their exact output.
+// ════════════════════════════════════════════════════════════════
+
+/// Strip width
+pub const BLOCK: usize = 64;
+
+/// Smallest N routed through the blocked kernels
+pub const MIN_N: usize = 512;
+
+/// Aᵀ·B for A (k×m) and B (k×n), one BLOCK-wide column strip of the
+/// output at a time. With `symmetric` (the caller knows AᵀB = BᵀA, as
+/// for the Gram products here) each strip stops at the diagonal and the
+/// lower triangle is mirrored, halving the work and making the result
+/// exactly symmetric.
+pub fn matmul_tn(a: &DMatrix<f64>, b: &DMatrix<f64>, symmetric: bool) -> DMatrix<f64> {
+    let (m, n) = (a.ncols(), b.ncols());
+    assert_eq!(a.nrows(), b.nrows(), "matmul_tn: inner dimensions differ");
+    let mut out = DMatrix::zeros(m, n);
+    for j0 in (0..n).step_by(BLOCK) {
+        let jb = BLOCK.min(n - j0);
+        let i0 = if symmetric { j0 } else { 0 };
+        out.view_mut((i0, j0), (m - i0, jb)).gemm_tr(1.0, &a.columns(i0, m - i0), &b.columns(j0, jb), 0.0);
+    }
+    if symmetric {
+        out.fill_upper_triangle_with_lower_triangle();
+    }
+    out
+}
+
+/// Lower-triangular L with L·Lᵀ = Σ by right-looking block Cholesky:
+/// factor a BLOCK-wide column panel, then subtract its outer product
+/// from the trailing matrix one column strip at a time (lower part
+/// only). None when a pivot is not positive, as with nalgebra's
+/// Cholesky::new. Factors in `sigma`'s allocation.
+pub fn cholesky(sigma: DMatrix<f64>) -> Option<DMatrix<f64>> {
+    let n = sigma.nrows();
+    let mut l = sigma;
+    for k0 in (0..n).step_by(BLOCK) {
+        let k1 = (k0 + BLOCK).min(n);
+        let (mut panel, mut trailing) = l.columns_range_pair_mut(k0..k1, k1..);
+
+        // Panel, left-looking: column j −= Σ L[j, p]·column p over the
+        // panel's earlier columns, then scale by the pivot
+        for j in 0..k1 - k0 {
+            let row = k0 + j;
+            for p in 0..j {
+                let l_jp = panel[(row, p)];
+                for i in row..n {
+                    panel[(i, j)] -= l_jp * panel[(i, p)];
+                }
+            }
+            let pivot = panel[(row, j)];
+            if pivot.is_nan() || pivot <= 0.0 {
+                return None;
+            }
+            let d = pivot.sqrt();
+            panel[(row, j)] = d;
+            panel.view_mut((row + 1, j), (n - row - 1, 1)).unscale_mut(d);
+        }
+
+        // Trailing update: A₂₂ −= L₂₁·L₂₁ᵀ
+        for j0 in (k1..n).step_by(BLOCK) {
+            let jb = BLOCK.min(n - j0);
+            let lj = panel.rows(j0, jb).transpose();
+            trailing.view_mut((j0, j0 - k1), (n - j0, jb)).gemm(-1.0, &panel.rows(j0, n - j0), &lj, 1.0);
+        }
+    }
+    l.fill_upper_triangle(0.0, 1);
+    Some(l)
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_blocked_kernels_match_dense() {
+        // N not a multiple of BLOCK, so edge tiles are exercised
+        let n = 2 * BLOCK + 21;
+        let a = DMatrix::from_fn(n, n, |i, j| ((i * 7 + j * 13) % 17) as f64 / 17.0 - 0.5);
+        let b = DMatrix::from_fn(n, 40, |i, j| ((i * 3 + j * 5) % 11) as f64 / 11.0);
+        assert_relative_eq!(matmul_tn(&a, &b, false), a.transpose() * &b, epsilon = 1e-12);
+        let gram = matmul_tn(&a, &a, true);
+        assert_relative_eq!(gram, a.transpose() * &a, epsilon = 1e-12);
+        assert_eq!(gram, gram.transpose());
+
+        let sigma = &gram + DMatrix::identity(n, n);
+        let l = cholesky(sigma.clone()).unwrap();
+        let expected = nalgebra::linalg::Cholesky::new(sigma.clone()).unwrap().l();
+        assert_relative_eq!(l, expected, epsilon = 1e-10);
+        assert!((0..n).all(|j| (0..j).all(|i| l[(i, j)] == 0.0)));
+
+        let mut indefinite = sigma;
+        indefinite[(n - 1, n - 1)] = -1.0;
+        assert!(cholesky(indefinite).is_none());
+    }
+}
@@ -0,0 +1,1004 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::simulate::GarchParams;
+
+// ════════════════════════════════════════════════════════════════
+// Calibration — base market inputs estimated from return history
+//
+// Inputs are T × N row-major per-period log returns, oldest row first.
+// Vols come back annualized (σ = √(P·var) for P periods a year); the
+// correlation is what compute_shock takes as its base R.
+// ════════════════════════════════════════════════════════════════
+
+// ────────────────────────────────────────────────────────────────
+// Preprocessing — tame bad prints before estimating
+// Flagged rows are removed first. Each remaining cell is then clamped
+// to its asset's [p, 1 − p] quantiles (winsorizing, quantiles by
+// linear interpolation over the asset's rows) and to ±clip. NaN cells
+// (missing) pass through untouched and are left out of the quantiles.
+// Every changed cell is reported, in input row numbers.
+// ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Preprocessing {
+    /// Tail probability p ∈ [0, ½) to winsorize at (0 = off)
+    pub winsorize: f64,
+    /// Largest |return| kept per period
+    pub clip: Option<f64>,
+    /// Input rows to drop (e.g. known bad dates)
+    pub drop_rows: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adjustment {
+    /// Row in the input, before dropping
+    pub row: usize,
+    pub asset: usize,
+    pub original: f64,
+    pub value: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreprocessReport {
+    /// Sorted, without duplicates
+    pub dropped_rows: Vec<usize>,
+    /// Winsorizing bounds per asset (±∞ when off)
+    pub lower: DVector<f64>,
+    pub upper: DVector<f64>,
+    pub adjustments: Vec<Adjustment>,
+}
+
+/// The cleaned T' × N returns and what was changed
+pub fn preprocess_returns(
+    returns: &[f64],
+    n: usize,
+    options: &Preprocessing,
+) -> Result<(Vec<f64>, PreprocessReport), &'static str> {
+    if n == 0 || returns.is_empty() || !returns.len().is_multiple_of(n) {
+        return Err("Calibration input mismatch: returns must be T × N");
+    }
+    if returns.iter().any(|r| r.is_infinite()) {
+        return Err("Calibration input invalid: returns must be finite or NaN (missing)");
+    }
+    if !(options.winsorize >= 0.0 && options.winsorize < 0.5) {
+        return Err("Calibration input invalid: winsorize tail must lie in [0, 0.5)");
+    }
+    if options.clip.is_some_and(|c| !(c.is_finite() && c > 0.0)) {
+        return Err("Calibration input invalid: clip must be positive");
+    }
+    let t = returns.len() / n;
+    let mut dropped_rows = options.drop_rows.clone();
+    dropped_rows.sort_unstable();
+    dropped_rows.dedup();
+    if dropped_rows.last().is_some_and(|&r| r >= t) {
+        return Err("Calibration input invalid: dropped row out of range");
+    }
+    let kept: Vec<usize> = (0..t).filter(|r| dropped_rows.binary_search(r).is_err()).collect();
+    if kept.is_empty() {
+        return Err("Calibration input invalid: every row dropped");
+    }
+
+    let mut lower = DVector::from_element(n, f64::NEG_INFINITY);
+    let mut upper = DVector::from_element(n, f64::INFINITY);
+    if options.winsorize > 0.0 {
+        for a in 0..n {
+            let mut xs: Vec<f64> = kept.iter().map(|&r| returns[r * n + a]).filter(|v| !v.is_nan()).collect();
+            if xs.is_empty() {
+                continue;
+            }
+            xs.sort_by(f64::total_cmp);
+            lower[a] = quantile(&xs, options.winsorize);
+            upper[a] = quantile(&xs, 1.0 - options.winsorize);
+        }
+    }
+    let clip = options.clip.unwrap_or(f64::INFINITY);
+
+    let mut out = Vec::with_capacity(kept.len() * n);
+    let mut adjustments = Vec::new();
+    for &row in &kept {
+        for a in 0..n {
+            let original = returns[row * n + a];
+            let value = if original.is_nan() { original } else { original.clamp(lower[a], upper[a]).clamp(-clip, clip) };
+            if value != original && !original.is_nan() {
+                adjustments.push(Adjustment { row, asset: a, original, value });
+            }
+            out.push(value);
+        }
+    }
+    Ok((out, PreprocessReport { dropped_rows, lower, upper, adjustments }))
+}
+
+/// Linear-interpolation quantile of sorted values
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let h = q * (sorted.len() - 1) as f64;
+    let (i, frac) = (h.floor() as usize, h.fract());
+    match sorted.get(i + 1) {
+        Some(&next) => sorted[i] + frac * (next - sorted[i]),
+        None => sorted[i],
+    }
+}
+
+/// How observations are weighted in the covariance estimate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CorrelationMethod {
+    /// Equal weights about the sample mean, divided by T − 1
+    Sample,
+    /// RiskMetrics EWMA: S_t = λ·S_{t−1} + (1 − λ)·x_t·x_tᵀ about a zero
+    /// mean, so row t − k carries weight ∝ λᵏ (0.94 for daily data)
+    Ewma { lambda: f64 },
+}
+
+impl CorrelationMethod {
+    /// "sample" or "ewma" (which uses `lambda`)
+    pub fn from_name(name: &str, lambda: f64) -> Result<Self, &'static str> {
+        match name.to_ascii_lowercase().as_str() {
+            "sample" => Ok(CorrelationMethod::Sample),
+            "ewma" => Ok(CorrelationMethod::Ewma { lambda }),
+            _ => Err("Calibration input invalid: method must be sample or ewma"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CorrelationEstimate {
+    /// Annualized vol per asset
+    pub vol: DVector<f64>,
+    pub correlation: DMatrix<f64>,
+    /// T for Sample; 1 / Σwᵢ² (Kish) for EWMA
+    pub effective_observations: f64,
+}
+
+pub fn correlation_from_returns(
+    returns: &[f64],
+    n: usize,
+    method: CorrelationMethod,
+    periods_per_year: f64,
+) -> Result<CorrelationEstimate, &'static str> {
+    if n == 0 || returns.is_empty() || !returns.len().is_multiple_of(n) {
+        return Err("Calibration input mismatch: returns must be T × N");
+    }
+    let t = returns.len() / n;
+    if t < 2 {
+        return Err("Calibration input invalid: need at least 2 periods");
+    }
+    if returns.iter().any(|r| !r.is_finite()) {
+        return Err("Calibration input invalid: returns must be finite");
+    }
+    if !(periods_per_year.is_finite() && periods_per_year > 0.0) {
+        return Err("Calibration input invalid: periods per year must be positive");
+    }
+
+    let x = DMatrix::from_row_slice(t, n, returns);
+    let (weights, mean) = match method {
+        CorrelationMethod::Sample => {
+            let mean = x.row_mean().transpose();
+            (DVector::from_element(t, 1.0 / (t as f64 - 1.0)), mean)
+        }
+        CorrelationMethod::Ewma { lambda } => {
+            if !(lambda > 0.0 && lambda < 1.0) {
+                return Err("Calibration input invalid: EWMA lambda must lie in (0, 1)");
+            }
+            let mut w = DVector::from_fn(t, |i, _| lambda.powi((t - 1 - i) as i32));
+            w /= w.sum();
+            (w, DVector::zeros(n))
+        }
+    };
+    let effective_observations = match method {
+        CorrelationMethod::Sample => t as f64,
+        CorrelationMethod::Ewma { .. } => 1.0 / weights.norm_squared(),
+    };
+
+    let mut centered = x;
+    for (i, mut row) in centered.row_iter_mut().enumerate() {
+        row -= mean.transpose();
+        row *= weights[i].sqrt();
+    }
+    let cov = centered.transpose() * &centered;
+    if cov.diagonal().iter().any(|&v| v <= 0.0) {
+        return Err("Calibration input invalid: an asset's returns are constant");
+    }
+    Ok(CorrelationEstimate {
+        vol: cov.diagonal().map(|v| (v * periods_per_year).sqrt()),
+        correlation: covariance_to_correlation(&cov),
+        effective_observations,
+    })
+}
+
+/// R = D⁻¹·Σ·D⁻¹ with a unit diagonal and entries clamped to [−1, 1]
+fn covariance_to_correlation(cov: &DMatrix<f64>) -> DMatrix<f64> {
+    let n = cov.nrows();
+    let sd = cov.diagonal().map(f64::sqrt);
+    DMatrix::from_fn(n, n, |a, b| if a == b { 1.0 } else { (cov[(a, b)] / (sd[a] * sd[b])).clamp(-1.0, 1.0) })
+}
+
+// ────────────────────────────────────────────────────────────────
+// Pairwise-complete estimation — for panels with missing cells (NaN)
+// Each vol uses the asset's own observations; each correlation the
+// rows where both assets of the pair are observed, about that
+// overlap's means. Pairs with fewer than `min_overlap` common rows
+// get 0. Mixing samples this way can leave R indefinite, in which
+// case it is repaired with math::nearest_pd; the per-pair counts say
+// which entries rest on little data.
+// ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug)]
+pub struct PairwiseEstimate {
+    /// Annualized vol per asset
+    pub vol: DVector<f64>,
+    /// PD-repaired when `repaired`, else equal to raw_correlation
+    pub correlation: DMatrix<f64>,
+    pub raw_correlation: DMatrix<f64>,
+    /// Common observations per pair (N×N; the diagonal is each asset's
+    /// own count)
+    pub counts: DMatrix<usize>,
+    pub min_eigenvalue_before: f64,
+    pub repaired: bool,
+}
+
+pub fn pairwise_correlation(
+    returns: &[f64],
+    n: usize,
+    min_overlap: usize,
+    periods_per_year: f64,
+) -> Result<PairwiseEstimate, &'static str> {
+    if n == 0 || returns.is_empty() || !returns.len().is_multiple_of(n) {
+        return Err("Calibration input mismatch: returns must be T × N");
+    }
+    if returns.iter().any(|r| r.is_infinite()) {
+        return Err("Calibration input invalid: returns must be finite or NaN (missing)");
+    }
+    if !(periods_per_year.is_finite() && periods_per_year > 0.0) {
+        return Err("Calibration input invalid: periods per year must be positive");
+    }
+    let t = returns.len() / n;
+    let min_overlap = min_overlap.max(2);
+    let column = |a: usize| (0..t).map(move |i| returns[i * n + a]);
+
+    let mut vol = DVector::zeros(n);
+    let mut counts = DMatrix::zeros(n, n);
+    for a in 0..n {
+        let xs: Vec<f64> = column(a).filter(|v| !v.is_nan()).collect();
+        if xs.len() < 2 {
+            return Err("Calibration input invalid: every asset needs at least 2 observations");
+        }
+        let (_, var) = mean_var(&xs);
+        if var <= 0.0 {
+            return Err("Calibration input invalid: an asset's returns are constant");
+        }
+        vol[a] = (var * periods_per_year).sqrt();
+        counts[(a, a)] = xs.len();
+    }
+
+    let mut raw = DMatrix::identity(n, n);
+    for a in 0..n {
+        for b in a + 1..n {
+            let (xa, xb): (Vec<f64>, Vec<f64>) =
+                column(a).zip(column(b)).filter(|(x, y)| !x.is_nan() && !y.is_nan()).unzip();
+            counts[(a, b)] = xa.len();
+            counts[(b, a)] = xa.len();
+            if xa.len() < min_overlap {
+                continue;
+            }
+            let ((ma, va), (mb, vb)) = (mean_var(&xa), mean_var(&xb));
+            if va <= 0.0 || vb <= 0.0 {
+                continue;
+            }
+            let cov = xa.iter().zip(&xb).map(|(x, y)| (x - ma) * (y - mb)).sum::<f64>() / (xa.len() - 1) as f64;
+            let r = (cov / (va * vb).sqrt()).clamp(-1.0, 1.0);
+            raw[(a, b)] = r;
+            raw[(b, a)] = r;
+        }
+    }
+
+    let min_eigenvalue_before = raw.symmetric_eigenvalues().min();
+    let repaired = min_eigenvalue_before <= 0.0;
+    let correlation = if repaired { crate::math::nearest_pd(&raw) } else { raw.clone() };
+    Ok(PairwiseEstimate { vol, correlation, raw_correlation: raw, counts, min_eigenvalue_before, repaired })
+}
+
+/// Mean and T − 1 variance
+fn mean_var(xs: &[f64]) -> (f64, f64) {
+    let m = xs.len() as f64;
+    let mean = xs.iter().sum::<f64>() / m;
+    (mean, xs.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (m - 1.0))
+}
+
+// ────────────────────────────────────────────────────────────────
+// Random-matrix denoising — Marchenko–Pastur eigenvalue clipping
+// The sample correlation of N independent series over T observations
+// has eigenvalues spread over [(1 − √q)², (1 + √q)²], q = N / T, so
+// eigenvalues up to λ₊ = (1 + √q)² are indistinguishable from noise.
+// Those are replaced by their average (keeping the trace), the
+// eigenvectors are kept, and the result is rescaled to a unit
+// diagonal. Eigenvalues above λ₊ are the signal and pass through.
+// ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug)]
+pub struct Denoised {
+    pub correlation: DMatrix<f64>,
+    /// Input eigenvalues, descending
+    pub eigenvalues: DVector<f64>,
+    /// Upper edge of the noise band, (1 + √(N/T))²
+    pub lambda_max: f64,
+    /// Eigenvalues above lambda_max
+    pub signal_count: usize,
+    /// λ_max / λ_min before and after (∞ if singular before)
+    pub condition_before: f64,
+    pub condition_after: f64,
+}
+
+pub fn denoise_correlation(correlation: &DMatrix<f64>, observations: f64) -> Result<Denoised, &'static str> {
+    let n = correlation.nrows();
+    if n == 0 || correlation.ncols() != n {
+        return Err("Denoising input mismatch: correlation must be N×N");
+    }
+    if !(observations.is_finite() && observations > 0.0) {
+        return Err("Denoising input invalid: observations must be positive");
+    }
+    if correlation.iter().any(|v| !v.is_finite()) {
+        return Err("Denoising input invalid: correlation must be finite");
+    }
+    let lambda_max = (1.0 + (n as f64 / observations).sqrt()).powi(2);
+    let eigen = correlation.clone().symmetric_eigen();
+    let mut values = eigen.eigenvalues.clone();
+    let noise: Vec<usize> = (0..n).filter(|&i| values[i] <= lambda_max).collect();
+    if !noise.is_empty() {
+        let average = noise.iter().map(|&i| values[i]).sum::<f64>() / noise.len() as f64;
+        for &i in &noise {
+            values[i] = average;
+        }
+    }
+    let v = &eigen.eigenvectors;
+    let cleaned = v * DMatrix::from_diagonal(&values) * v.transpose();
+    let out = covariance_to_correlation(&cleaned);
+
+    let mut eigenvalues = eigen.eigenvalues;
+    eigenvalues.as_mut_slice().sort_by(|a, b| b.total_cmp(a));
+    let condition = |e: &DVector<f64>| if e.min() > 0.0 { e.max() / e.min() } else { f64::INFINITY };
+    Ok(Denoised {
+        condition_before: condition(&eigenvalues),
+        condition_after: condition(&out.symmetric_eigenvalues()),
+        correlation: out,
+        eigenvalues,
+        lambda_max,
+        signal_count: n - noise.len(),
+    })
+}
+
+// ────────────────────────────────────────────────────────────────
+// Rolling windows — correlation_from_returns over each trailing
+// `window` rows, stepping back `stride` rows from the last, so the
+// final estimate always covers the most recent history. Windows come
+// back oldest first.
+// ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug)]
+pub struct RollingEstimate {
+    /// Rows start..end of the input
+    pub start: usize,
+    pub end: usize,
+    pub estimate: CorrelationEstimate,
+}
+
+pub fn rolling_correlation(
+    returns: &[f64],
+    n: usize,
+    window: usize,
+    stride: usize,
+    method: CorrelationMethod,
+    periods_per_year: f64,
+) -> Result<Vec<RollingEstimate>, &'static str> {
+    if n == 0 || returns.is_empty() || !returns.len().is_multiple_of(n) {
+        return Err("Calibration input mismatch: returns must be T × N");
+    }
+    let t = returns.len() / n;
+    if window < 2 || window > t {
+        return Err("Calibration input invalid: window must be between 2 and T");
+    }
+    if stride == 0 {
+        return Err("Calibration input invalid: stride must be positive");
+    }
+    let mut out = Vec::with_capacity((t - window) / stride + 1);
+    for end in (window..=t).rev().step_by(stride) {
+        let start = end - window;
+        let estimate = correlation_from_returns(&returns[start * n..end * n], n, method, periods_per_year)?;
+        out.push(RollingEstimate { start, end, estimate });
+    }
+    out.reverse();
+    Ok(out)
+}
+
+/// Mean off-diagonal entry of a correlation matrix (0 for N = 1)
+pub fn average_correlation(correlation: &DMatrix<f64>) -> f64 {
+    let n = correlation.nrows();
+    if n < 2 {
+        return 0.0;
+    }
+    (correlation.sum() - correlation.trace()) / (n * (n - 1)) as f64
+}
+
+// ────────────────────────────────────────────────────────────────
+// Jump detection — threshold on bipower variation
+// BV = (π/2)·mean(|y_t|·|y_{t−1}|) estimates the diffusive per-period
+// variance without the jumps (a jump enters one product with a small
+// neighbour, not its own square). Returns beyond `threshold`·√BV from
+// the median are flagged as jumps; the rest give the diffusive vol.
+// Jump sizes are measured from the diffusive mean, in the log terms
+// the simulator draws them in (λ per year, μ_J, σ_J).
+// ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JumpEstimate {
+    /// Jumps per year
+    pub lambda: f64,
+    pub mean: f64,
+    /// Sample sd of the jump sizes (0 with fewer than two jumps)
+    pub vol: f64,
+    /// Annualized vol of the returns not flagged
+    pub diffusive_vol: f64,
+    /// Rows flagged as jumps
+    pub jumps: Vec<usize>,
+}
+
+/// One estimate per asset of a T × N log-return matrix; a threshold of
+/// 4 (sd of the diffusion) is typical for daily data.
+pub fn estimate_jumps(
+    returns: &[f64],
+    n: usize,
+    threshold: f64,
+    periods_per_year: f64,
+) -> Result<Vec<JumpEstimate>, &'static str> {
+    if n == 0 || returns.is_empty() || !returns.len().is_multiple_of(n) {
+        return Err("Calibration input mismatch: returns must be T × N");
+    }
+    let t = returns.len() / n;
+    if t < 3 {
+        return Err("Calibration input invalid: need at least 3 periods");
+    }
+    if returns.iter().any(|r| !r.is_finite()) {
+        return Err("Calibration input invalid: returns must be finite");
+    }
+    if !(threshold.is_finite() && threshold > 0.0) {
+        return Err("Calibration input invalid: jump threshold must be positive");
+    }
+    if !(periods_per_year.is_finite() && periods_per_year > 0.0) {
+        return Err("Calibration input invalid: periods per year must be positive");
+    }
+
+    let years = t as f64 / periods_per_year;
+    let mut out = Vec::with_capacity(n);
+    for a in 0..n {
+        let x: Vec<f64> = (0..t).map(|i| returns[i * n + a]).collect();
+        let median = median(&x);
+        let y: Vec<f64> = x.iter().map(|v| (v - median).abs()).collect();
+        let bv = std::f64::consts::FRAC_PI_2 * y.windows(2).map(|w| w[0] * w[1]).sum::<f64>() / (t - 1) as f64;
+        if bv <= 0.0 {
+            return Err("Calibration input invalid: an asset's returns are constant");
+        }
+        let cutoff = threshold * bv.sqrt();
+        let jumps: Vec<usize> = (0..t).filter(|&i| y[i] > cutoff).collect();
+
+        let diffusive: Vec<f64> = (0..t).filter(|i| !jumps.contains(i)).map(|i| x[i]).collect();
+        if diffusive.len() < 2 {
+            return Err("Calibration input invalid: fewer than 2 periods left unflagged; raise the jump threshold");
+        }
+        let m = diffusive.len() as f64;
+        let mu = diffusive.iter().sum::<f64>() / m;
+        let var = diffusive.iter().map(|v| (v - mu).powi(2)).sum::<f64>() / (m - 1.0);
+        let sizes: Vec<f64> = jumps.iter().map(|&i| x[i] - mu).collect();
+        let k = sizes.len() as f64;
+        let mean = if sizes.is_empty() { 0.0 } else { sizes.iter().sum::<f64>() / k };
+        let vol = if sizes.len() < 2 {
+            0.0
+        } else {
+            (sizes.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (k - 1.0)).sqrt()
+        };
+        out.push(JumpEstimate {
+            lambda: k / years,
+            mean,
+            vol,
+            diffusive_vol: (var * periods_per_year).sqrt(),
+            jumps,
+        });
+    }
+    Ok(out)
+}
+
+// ────────────────────────────────────────────────────────────────
+// GARCH(1,1) — Gaussian maximum likelihood
+// ε_t = x_t − x̄, h₁ = sample variance, h_{t+1} = ω + α·ε_t² + β·h_t;
+// maximizes −½·Σ(ln 2π + ln h_t + ε_t²/h_t) by Nelder–Mead over
+// (ω / s², α, β) from (0.05, 0.05, 0.90), inside the box ω > 0,
+// α, β ≥ 0, α + β ≤ GARCH_MAX_PERSISTENCE. Parameters are per data
+// period, which is SimConfig's per-step unit when a step is one
+// period (steps = horizon × periods per year).
+// ────────────────────────────────────────────────────────────────
+
+/// Upper bound on α + β (covariance stationarity with a margin)
+pub const GARCH_MAX_PERSISTENCE: f64 = 0.9999;
+
+const GARCH_MAX_ITERATIONS: usize = 2000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GarchFit {
+    pub params: GarchParams,
+    /// Mean return per period, removed before fitting
+    pub mean: f64,
+    pub log_likelihood: f64,
+    /// Annualized √(ω / (1 − α − β))
+    pub long_run_vol: f64,
+    /// Annualized √h_{T+1}, the vol the next period starts from
+    pub current_vol: f64,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+/// One fit per asset of a T × N log-return matrix (T ≥ 20).
+pub fn fit_garch(returns: &[f64], n: usize, periods_per_year: f64) -> Result<Vec<GarchFit>, &'static str> {
+    if n == 0 || returns.is_empty() || !returns.len().is_multiple_of(n) {
+        return Err("Calibration input mismatch: returns must be T × N");
+    }
+    let t = returns.len() / n;
+    if t < 20 {
+        return Err("Calibration input invalid: GARCH needs at least 20 periods");
+    }
+    if returns.iter().any(|r| !r.is_finite()) {
+        return Err("Calibration input invalid: returns must be finite");
+    }
+    if !(periods_per_year.is_finite() && periods_per_year > 0.0) {
+        return Err("Calibration input invalid: periods per year must be positive");
+    }
+
+    let mut fits = Vec::with_capacity(n);
+    for a in 0..n {
+        let x: Vec<f64> = (0..t).map(|i| returns[i * n + a]).collect();
+        let mean = x.iter().sum::<f64>() / t as f64;
+        let eps: Vec<f64> = x.iter().map(|v| v - mean).collect();
+        let var = eps.iter().map(|e| e * e).sum::<f64>() / (t - 1) as f64;
+        if var <= 0.0 {
+            return Err("Calibration input invalid: an asset's returns are constant");
+        }
+        let params = |p: &[f64; 3]| GarchParams { omega: p[0] * var, alpha: p[1], beta: p[2] };
+        let objective = |p: &[f64; 3]| {
+            let feasible = p[0] > 0.0 && p[1] >= 0.0 && p[2] >= 0.0 && p[1] + p[2] <= GARCH_MAX_PERSISTENCE;
+            if feasible {
+                -garch_log_likelihood(&eps, var, &params(p)).0
+            } else {
+                f64::INFINITY
+            }
+        };
+        let (best, iterations, converged) = nelder_mead(objective, [0.05, 0.05, 0.90], 0.02, GARCH_MAX_ITERATIONS);
+        let p = params(&best);
+        let (log_likelihood, next) = garch_log_likelihood(&eps, var, &p);
+        fits.push(GarchFit {
+            params: p,
+            mean,
+            log_likelihood,
+            long_run_vol: (p.omega / (1.0 - p.alpha - p.beta) * periods_per_year).sqrt(),
+            current_vol: (next * periods_per_year).sqrt(),
+            iterations,
+            converged,
+        });
+    }
+    Ok(fits)
+}
+
+/// Gaussian log-likelihood of the residuals and h_{T+1}
+fn garch_log_likelihood(eps: &[f64], h1: f64, p: &GarchParams) -> (f64, f64) {
+    let ln_2pi = (2.0 * std::f64::consts::PI).ln();
+    let mut h = h1;
+    let mut ll = 0.0;
+    for &e in eps {
+        ll -= 0.5 * (ln_2pi + h.ln() + e * e / h);
+        h = p.omega + p.alpha * e * e + p.beta * h;
+    }
+    (ll, h)
+}
+
+/// Minimize `f` from `x0` with an initial simplex of edge `step`;
+/// returns (x, iterations, converged) where convergence is a simplex
+/// whose values agree to 1e-10 relative.
+fn nelder_mead<const D: usize>(
+    f: impl Fn(&[f64; D]) -> f64,
+    x0: [f64; D],
+    step: f64,
+    max_iterations: usize,
+) -> ([f64; D], usize, bool) {
+    let mut simplex: Vec<([f64; D], f64)> = (0..=D)
+        .map(|k| {
+            let mut x = x0;
+            if k > 0 {
+                x[k - 1] += step;
+            }
+            (x, f(&x))
+        })
+        .collect();
+    let towards = |a: &[f64; D], b: &[f64; D], t: f64| -> [f64; D] { std::array::from_fn(|i| a[i] + t * (b[i] - a[i])) };
+    for iteration in 0..max_iterations {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let (best, worst) = (simplex[0].1, simplex[D].1);
+        if (worst - best).abs() <= 1e-10 * best.abs().max(1e-10) {
+            return (simplex[0].0, iteration, true);
+        }
+        let centroid: [f64; D] = std::array::from_fn(|i| simplex[..D].iter().map(|(x, _)| x[i]).sum::<f64>() / D as f64);
+        let reflected = towards(&centroid, &simplex[D].0, -1.0);
+        let fr = f(&reflected);
+        if fr < best {
+            let expanded = towards(&centroid, &simplex[D].0, -2.0);
+            let fe = f(&expanded);
+            simplex[D] = if fe < fr { (expanded, fe) } else { (reflected, fr) };
+        } else if fr < simplex[D - 1].1 {
+            simplex[D] = (reflected, fr);
+        } else {
+            let contracted = towards(&centroid, &simplex[D].0, if fr < worst { -0.5 } else { 0.5 });
+            let fc = f(&contracted);
+            if fc < fr.min(worst) {
+                simplex[D] = (contracted, fc);
+            } else {
+                let x_best = simplex[0].0;
+                for vertex in simplex.iter_mut().skip(1) {
+                    vertex.0 = towards(&x_best, &vertex.0, 0.5);
+                    vertex.1 = f(&vertex.0);
+                }
+            }
+        }
+    }
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    (simplex[0].0, max_iterations, false)
+}
+
+// ────────────────────────────────────────────────────────────────
+// Black–Litterman drift blending
+// Equilibrium excess drift Π = δ·Σ·w_mkt; each view k says a
+// portfolio p_k returns q_k with confidence c_k ∈ (0, 1], which sets
+// Ω_kk = (1 − c_k)/c_k · p_k·τΣ·p_kᵀ (c = 1 forces the view, c = ½
+// weighs it like the prior). Posterior
+//     μ = r_f + Π + τΣ·Pᵀ·(P·τΣ·Pᵀ + Ω)⁻¹·(Q − r_f − P·Π)
+// in the form that stays defined for Ω = 0. Drifts and Σ are annual,
+// so "tech drops 20%" is p = e_tech, q = −0.20.
+// ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct View {
+    /// Portfolio the view is on (N; e_i for a single asset, e_i − e_j
+    /// for "i beats j")
+    pub portfolio: Vec<f64>,
+    /// Its expected annual drift; a relative view's r_f terms cancel
+    pub expected: f64,
+    pub confidence: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct BlackLitterman {
+    /// r_f + Π
+    pub equilibrium: DVector<f64>,
+    pub posterior: DVector<f64>,
+    /// Σ + M, with M the posterior uncertainty of the mean
+    pub posterior_covariance: DMatrix<f64>,
+}
+
+pub fn black_litterman(
+    covariance: &DMatrix<f64>,
+    market_weights: &DVector<f64>,
+    risk_aversion: f64,
+    tau: f64,
+    risk_free: f64,
+    views: &[View],
+) -> Result<BlackLitterman, &'static str> {
+    let n = covariance.nrows();
+    if n == 0 || covariance.ncols() != n || market_weights.len() != n {
+        return Err("Black-Litterman input mismatch: need an N×N covariance and N weights");
+    }
+    if covariance.iter().chain(market_weights.iter()).any(|x| !x.is_finite()) {
+        return Err("Black-Litterman input invalid: covariance and weights must be finite");
+    }
+    if !(risk_aversion.is_finite() && risk_aversion > 0.0 && tau.is_finite() && tau > 0.0 && risk_free.is_finite()) {
+        return Err("Black-Litterman input invalid: risk aversion and tau must be positive");
+    }
+    for v in views {
+        if v.portfolio.len() != n {
+            return Err("Black-Litterman input mismatch: each view needs one portfolio weight per asset");
+        }
+        if !(v.confidence > 0.0 && v.confidence <= 1.0 && v.expected.is_finite()) {
+            return Err("Black-Litterman input invalid: view returns must be finite, confidences in (0, 1]");
+        }
+    }
+
+    let excess = covariance * market_weights * risk_aversion;
+    let equilibrium = excess.add_scalar(risk_free);
+    if views.is_empty() {
+        return Ok(BlackLitterman { equilibrium: equilibrium.clone(), posterior: equilibrium, posterior_covariance: covariance.clone() });
+    }
+    let k = views.len();
+    let p = DMatrix::from_fn(k, n, |i, j| views[i].portfolio[j]);
+    let scaled = covariance * tau;
+    let p_scaled = &p * &scaled;
+    let mut a = &p_scaled * p.transpose();
+    for (i, v) in views.iter().enumerate() {
+        a[(i, i)] *= 1.0 / v.confidence;
+    }
+    // A = P·τΣ·Pᵀ + Ω, since (1 + (1 − c)/c) = 1/c on the diagonal
+    let scale = a.diagonal().max();
+    let a_inv = a
+        .cholesky()
+        .filter(|c| scale > 0.0 && c.l_dirty().diagonal().iter().all(|&d| d * d > 1e-12 * scale))
+        .ok_or("Black-Litterman input invalid: views are redundant or on zero-variance portfolios")?;
+    let rf_per_view = DVector::from_fn(k, |i, _| risk_free * views[i].portfolio.iter().sum::<f64>());
+    let surprise = DVector::from_fn(k, |i, _| views[i].expected) - rf_per_view - &p * &excess;
+    let posterior = &equilibrium + p_scaled.transpose() * a_inv.solve(&surprise);
+    let uncertainty = &scaled - p_scaled.transpose() * a_inv.solve(&p_scaled);
+    Ok(BlackLitterman { equilibrium, posterior, posterior_covariance: covariance + uncertainty })
+}
+
+fn median(xs: &[f64]) -> f64 {
+    let mut sorted = xs.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        0.5 * (sorted[mid - 1] + sorted[mid])
+    } else {
+        sorted[mid]
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn panel() -> Vec<f64> {
+        // Two correlated assets and one unrelated, 6 periods
+        vec![
+            0.010, 0.008, 0.002, //
+            -0.020, -0.015, 0.001, //
+            0.015, 0.012, -0.003, //
+            -0.005, -0.004, 0.004, //
+            0.020, 0.018, -0.001, //
+            -0.010, -0.011, 0.002,
+        ]
+    }
+
+    #[test]
+    fn test_preprocess_winsorizes_clips_and_drops() {
+        // One fat-finger print (asset 0, row 3) among 21 rows
+        let mut r: Vec<f64> = (0..21).flat_map(|i| [0.001 * (i as f64 - 10.0), 0.0005 * i as f64]).collect();
+        r[6] = 0.5;
+        let options = Preprocessing { winsorize: 0.05, clip: Some(0.008), drop_rows: vec![20, 0, 20] };
+        let (out, report) = preprocess_returns(&r, 2, &options).unwrap();
+        assert_eq!(report.dropped_rows, vec![0, 20]);
+        assert_eq!(out.len(), 19 * 2);
+        assert!(out.iter().all(|x| x.abs() <= 0.008));
+
+        let spike = report.adjustments.iter().find(|a| a.row == 3 && a.asset == 0).unwrap();
+        assert_eq!(spike.original, 0.5);
+        assert_eq!(spike.value, 0.008);
+        // Upper 5% quantile of asset 1 over rows 1..=19: 0.0005·(1 + 0.95·18)
+        assert_relative_eq!(report.upper[1], 0.0005 * 18.1, epsilon = 1e-12);
+        for a in &report.adjustments {
+            assert_eq!(out[(a.row - 1) * 2 + a.asset], a.value);
+        }
+        assert!(preprocess_returns(&r, 2, &Preprocessing { drop_rows: vec![21], ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn test_sample_matches_textbook_estimate() {
+        let r = panel();
+        let e = correlation_from_returns(&r, 3, CorrelationMethod::Sample, 252.0).unwrap();
+        let a: Vec<f64> = r.iter().step_by(3).copied().collect();
+        let mean = a.iter().sum::<f64>() / 6.0;
+        let var = a.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / 5.0;
+        assert_relative_eq!(e.vol[0], (252.0 * var).sqrt(), epsilon = 1e-12);
+        assert!(e.correlation[(0, 1)] > 0.99);
+        assert_eq!(e.correlation, e.correlation.transpose());
+        assert!(crate::math::validate_correlation(&e.correlation, 1e-9).is_ok());
+        assert_eq!(e.effective_observations, 6.0);
+        assert!(correlation_from_returns(&r[..5], 3, CorrelationMethod::Sample, 252.0).is_err());
+        assert!(correlation_from_returns(&r[..3], 3, CorrelationMethod::Sample, 252.0).is_err());
+    }
+
+    #[test]
+    fn test_ewma_weights_recent_rows() {
+        let r = panel();
+        let lambda = 0.9;
+        let e = correlation_from_returns(&r, 3, CorrelationMethod::Ewma { lambda }, 1.0).unwrap();
+        let w: Vec<f64> = (0..6).map(|i| lambda.powi(5 - i)).collect();
+        let total: f64 = w.iter().sum();
+        let var: f64 = (0..6).map(|i| w[i] / total * r[3 * i] * r[3 * i]).sum();
+        assert_relative_eq!(e.vol[0], var.sqrt(), epsilon = 1e-12);
+        let kish = total * total / w.iter().map(|x| x * x).sum::<f64>();
+        assert_relative_eq!(e.effective_observations, kish, epsilon = 1e-12);
+        assert!(e.effective_observations < 6.0);
+
+        // λ → 1 approaches the equal-weighted, zero-mean estimate
+        let flat = correlation_from_returns(&r, 3, CorrelationMethod::Ewma { lambda: 1.0 - 1e-12 }, 1.0).unwrap();
+        let second_moment = (0..6).map(|i| r[3 * i] * r[3 * i]).sum::<f64>() / 6.0;
+        assert_relative_eq!(flat.vol[0], second_moment.sqrt(), epsilon = 1e-9);
+        assert_eq!(CorrelationMethod::from_name("EWMA", 0.94), Ok(CorrelationMethod::Ewma { lambda: 0.94 }));
+        assert!(CorrelationMethod::from_name("ewma", 1.5).is_ok());
+        assert!(correlation_from_returns(&r, 3, CorrelationMethod::Ewma { lambda: 1.5 }, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_pairwise_uses_each_overlap_and_repairs() {
+        let nan = f64::NAN;
+        // A and B overlap in 4 rows, C only with A (and barely)
+        let r = vec![
+            0.010, nan, 0.010, //
+            -0.020, nan, -0.020, //
+            0.015, 0.012, nan, //
+            -0.005, -0.004, nan, //
+            0.020, 0.018, nan, //
+            -0.010, -0.011, nan,
+        ];
+        let e = pairwise_correlation(&r, 3, 3, 252.0).unwrap();
+        assert_eq!(e.counts[(0, 0)], 6);
+        assert_eq!((e.counts[(0, 1)], e.counts[(1, 0)], e.counts[(0, 2)], e.counts[(1, 2)]), (4, 4, 2, 0));
+        let listwise = correlation_from_returns(
+            &[0.015, 0.012, -0.005, -0.004, 0.020, 0.018, -0.010, -0.011],
+            2,
+            CorrelationMethod::Sample,
+            252.0,
+        )
+        .unwrap();
+        assert_relative_eq!(e.raw_correlation[(0, 1)], listwise.correlation[(0, 1)], epsilon = 1e-12);
+        // Below min_overlap: left at 0
+        assert_eq!(e.raw_correlation[(0, 2)], 0.0);
+        assert!(!e.repaired && e.correlation == e.raw_correlation);
+
+        // Pairwise estimates with inconsistent overlaps: ρ_AB ≈ 1,
+        // ρ_AC ≈ 1 but ρ_BC ≈ −1 cannot all hold, so R is repaired
+        let r = vec![
+            0.01, 0.01, nan, //
+            -0.02, -0.02, nan, //
+            0.03, 0.029, nan, //
+            0.01, nan, 0.01, //
+            -0.02, nan, -0.02, //
+            0.03, nan, 0.031, //
+            nan, 0.01, -0.01, //
+            nan, -0.02, 0.02, //
+            nan, 0.03, -0.03,
+        ];
+        let e = pairwise_correlation(&r, 3, 2, 252.0).unwrap();
+        assert!(e.repaired && e.min_eigenvalue_before < 0.0);
+        assert!(e.correlation.symmetric_eigenvalues().min() > -1e-9);
+        assert_eq!(e.correlation.diagonal(), DVector::from_element(3, 1.0));
+    }
+
+    #[test]
+    fn test_denoise_keeps_factor_and_flattens_noise() {
+        use crate::simulate::{NormalSampler, Pcg32};
+
+        // One market factor (loading 0.6) over 40 assets, 100 periods
+        let (n, t) = (40, 100);
+        let mut normals = NormalSampler::new(Pcg32::new(5, 0));
+        let mut r = Vec::with_capacity(n * t);
+        for _ in 0..t {
+            let m = normals.sample();
+            r.extend((0..n).map(|_| 0.01 * (0.6 * m + 0.8 * normals.sample())));
+        }
+        let sample = correlation_from_returns(&r, n, CorrelationMethod::Sample, 252.0).unwrap();
+        let d = denoise_correlation(&sample.correlation, t as f64).unwrap();
+        assert_relative_eq!(d.lambda_max, (1.0 + 0.4f64.sqrt()).powi(2), epsilon = 1e-12);
+        assert_eq!(d.signal_count, 1);
+        assert!(d.condition_after < d.condition_before / 5.0);
+        assert!(crate::math::validate_correlation(&d.correlation, 1e-9).is_ok());
+        // Off-diagonals close to the true ρ = 0.36 on average
+        assert_relative_eq!(average_correlation(&d.correlation), 0.36, epsilon = 0.08);
+        assert!(denoise_correlation(&sample.correlation, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_rolling_correlation_tracks_regime() {
+        use crate::simulate::{NormalSampler, Pcg32};
+
+        // Independent for 200 rows, then driven by a common factor
+        let mut normals = NormalSampler::new(Pcg32::new(3, 0));
+        let mut r = Vec::new();
+        for i in 0..400 {
+            let common = if i >= 200 { normals.sample() } else { 0.0 };
+            for _ in 0..2 {
+                r.push(0.01 * (common + normals.sample()));
+            }
+        }
+        let rolling = rolling_correlation(&r, 2, 100, 30, CorrelationMethod::Sample, 252.0).unwrap();
+        let ends: Vec<usize> = rolling.iter().map(|w| w.end).collect();
+        assert_eq!(ends, vec![100, 130, 160, 190, 220, 250, 280, 310, 340, 370, 400]);
+        assert_eq!((rolling[0].start, rolling.len()), (0, 11));
+        let avg: Vec<f64> = rolling.iter().map(|w| average_correlation(&w.estimate.correlation)).collect();
+        assert!(avg[0].abs() < 0.3 && avg[10] > 0.35);
+        let last = correlation_from_returns(&r[600..], 2, CorrelationMethod::Sample, 252.0).unwrap();
+        assert_eq!(rolling[10].estimate.correlation, last.correlation);
+        assert!(rolling[10].estimate.vol[0] > rolling[0].estimate.vol[0]);
+        assert!(rolling_correlation(&r, 2, 500, 1, CorrelationMethod::Sample, 252.0).is_err());
+    }
+
+    #[test]
+    fn test_estimate_jumps_recovers_planted_jumps() {
+        use crate::simulate::{NormalSampler, Pcg32};
+
+        // Asset 0: 1% daily diffusion plus a −8% jump every 100 days;
+        // asset 1: diffusion only
+        let mut normals = NormalSampler::new(Pcg32::new(7, 0));
+        let t = 2000;
+        let mut r = Vec::with_capacity(2 * t);
+        for i in 0..t {
+            let jump = if i % 100 == 50 { -0.08 } else { 0.0 };
+            r.push(0.0002 + 0.01 * normals.sample() + jump);
+            r.push(0.0001 + 0.005 * normals.sample());
+        }
+        let e = estimate_jumps(&r, 2, 4.0, 252.0).unwrap();
+        assert_eq!(e[0].jumps, (0..20).map(|k| 100 * k + 50).collect::<Vec<_>>());
+        assert_relative_eq!(e[0].lambda, 20.0 * 252.0 / 2000.0, epsilon = 1e-12);
+        assert_relative_eq!(e[0].mean, -0.08, epsilon = 0.01);
+        assert!(e[0].vol < 0.02);
+        assert_relative_eq!(e[0].diffusive_vol, 0.01 * 252f64.sqrt(), max_relative = 0.05);
+        assert!(e[1].jumps.len() <= 1 && e[1].lambda < 0.2);
+        assert_relative_eq!(e[1].diffusive_vol, 0.005 * 252f64.sqrt(), max_relative = 0.05);
+        assert!(estimate_jumps(&r, 2, 0.0, 252.0).is_err());
+        // A threshold that flags all but one period leaves no diffusive variance
+        assert!(estimate_jumps(&[0.0, 0.01, -0.01], 1, 0.01, 252.0).is_err());
+        assert!(estimate_jumps(&[0.0, 0.01, -0.01], 1, 4.0, 252.0).is_ok());
+    }
+
+    #[test]
+    fn test_fit_garch_recovers_parameters() {
+        use crate::simulate::{NormalSampler, Pcg32};
+
+        let truth = GarchParams { omega: 2e-6, alpha: 0.08, beta: 0.90 };
+        let mut normals = NormalSampler::new(Pcg32::new(11, 0));
+        let mut h = truth.omega / (1.0 - truth.alpha - truth.beta);
+        let r: Vec<f64> = (0..5000)
+            .map(|_| {
+                let e = h.sqrt() * normals.sample();
+                h = truth.omega + truth.alpha * e * e + truth.beta * h;
+                0.0003 + e
+            })
+            .collect();
+        let fit = fit_garch(&r, 1, 252.0).unwrap()[0];
+        assert!(fit.converged);
+        assert_relative_eq!(fit.params.alpha, truth.alpha, epsilon = 0.03);
+        assert_relative_eq!(fit.params.beta, truth.beta, epsilon = 0.04);
+        assert!(fit.params.alpha + fit.params.beta <= GARCH_MAX_PERSISTENCE);
+        assert_relative_eq!(fit.long_run_vol, (252.0 * 1e-4f64).sqrt(), max_relative = 0.2);
+
+        // The optimum beats the generating parameters on this sample
+        let var = r.iter().map(|v| (v - fit.mean).powi(2)).sum::<f64>() / 4999.0;
+        let eps: Vec<f64> = r.iter().map(|v| v - fit.mean).collect();
+        assert!(fit.log_likelihood >= garch_log_likelihood(&eps, var, &truth).0);
+        assert!(fit_garch(&r[..10], 1, 252.0).is_err());
+    }
+
+    #[test]
+    fn test_black_litterman_blends_views() {
+        let vol = DVector::from_vec(vec![0.2, 0.1, 0.15]);
+        let r = DMatrix::from_row_slice(3, 3, &[1.0, 0.3, 0.5, 0.3, 1.0, 0.2, 0.5, 0.2, 1.0]);
+        let cov = DMatrix::from_diagonal(&vol) * r * DMatrix::from_diagonal(&vol);
+        let w = DVector::from_vec(vec![0.5, 0.3, 0.2]);
+        let (delta, tau, rf) = (2.5, 0.05, 0.02);
+
+        let none = black_litterman(&cov, &w, delta, tau, rf, &[]).unwrap();
+        let pi = (&cov * &w * delta).add_scalar(rf);
+        assert_relative_eq!(none.equilibrium, pi, epsilon = 1e-12);
+        assert_eq!(none.posterior, none.equilibrium);
+
+        // A certain view is matched exactly; a weaker one moves part way
+        let view = |confidence| View { portfolio: vec![1.0, 0.0, 0.0], expected: -0.20, confidence };
+        let sure = black_litterman(&cov, &w, delta, tau, rf, &[view(1.0)]).unwrap();
+        assert_relative_eq!(sure.posterior[0], -0.20, epsilon = 1e-12);
+        // Correlated assets follow it down
+        assert!(sure.posterior[2] < pi[2] && sure.posterior[1] < pi[1]);
+        let half = black_litterman(&cov, &w, delta, tau, rf, &[view(0.5)]).unwrap();
+        assert_relative_eq!(half.posterior[0], 0.5 * (pi[0] - 0.20), epsilon = 1e-12);
+        assert!(half.posterior_covariance[(0, 0)] > cov[(0, 0)]);
+
+        // Relative view: asset 1 beats asset 2 by 3%
+        let rel = View { portfolio: vec![0.0, 1.0, -1.0], expected: 0.03, confidence: 1.0 };
+        let out = black_litterman(&cov, &w, delta, tau, rf, &[rel]).unwrap();
+        assert_relative_eq!(out.posterior[1] - out.posterior[2], 0.03, epsilon = 1e-12);
+        assert!(black_litterman(&cov, &w, delta, tau, rf, &[view(0.0)]).is_err());
+        assert!(black_litterman(&cov, &w, delta, tau, rf, &[view(1.0), view(1.0)]).is_err());
+    }
+}
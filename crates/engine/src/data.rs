@@ -0,0 +1,268 @@
+use nalgebra::{DMatrix, DVector};
+
+// ════════════════════════════════════════════════════════════════
+// Historical returns ingestion
+//
+// Reads a returns panel from CSV or TSV — a header row of asset names
+// after a date column, one row per period — and turns it into the
+// base drift / vol / correlation the shock pipeline takes. Empty
+// cells (or NA / NaN / null) mark missing observations, so assets
+// with later inception dates ("ragged starts") load as-is:
+//   • drift and vol use each asset's own observations,
+//   • correlation uses the rows where every asset is observed.
+// Annualization assumes GBM: with log returns x per period Δt = 1/P,
+// σ² = P·Var(x) and μ = P·E[x] + σ²/2.
+// ════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReturnsPanel {
+    pub assets: Vec<String>,
+    /// Date column as written (not parsed)
+    pub dates: Vec<String>,
+    /// T × N row-major; NaN where an asset has no observation
+    pub returns: Vec<f64>,
+}
+
+impl ReturnsPanel {
+    pub fn num_assets(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn num_periods(&self) -> usize {
+        self.dates.len()
+    }
+
+    /// First row with an observation per asset (None if never observed)
+    pub fn first_observed(&self) -> Vec<Option<usize>> {
+        let n = self.num_assets();
+        (0..n).map(|a| (0..self.num_periods()).find(|&t| !self.returns[t * n + a].is_nan())).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReturnKind {
+    /// r = P_t / P_{t−1} − 1
+    Simple,
+    /// x = ln(P_t / P_{t−1})
+    Log,
+}
+
+/// Base market estimated from a panel
+#[derive(Clone, Debug)]
+pub struct HistoricalMarket {
+    pub drift: DVector<f64>,
+    pub vol: DVector<f64>,
+    pub correlation: DMatrix<f64>,
+    /// Observations behind each asset's drift and vol
+    pub observations: Vec<usize>,
+    /// Rows with every asset observed, behind the correlation
+    pub overlap_rows: usize,
+}
+
+// ────────────────────────────────────────────────────────────────
+// Parsing
+// ────────────────────────────────────────────────────────────────
+
+/// Parse a returns panel. The delimiter is a tab if the header has
+/// one, else a comma; fields may be double-quoted. The date column is
+/// the one headed "date" (any case), else the first column.
+pub fn parse_returns(text: &str) -> Result<ReturnsPanel, &'static str> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+    let header = lines.next().ok_or("Data input invalid: no header row")?;
+    let delimiter = if header.contains('\t') { '\t' } else { ',' };
+    let header = split_row(header, delimiter)?;
+    if header.len() < 2 {
+        return Err("Data input invalid: need a date column and at least one asset");
+    }
+    let date_col = header.iter().position(|h| h.eq_ignore_ascii_case("date")).unwrap_or(0);
+    let assets: Vec<String> =
+        header.iter().enumerate().filter(|&(i, _)| i != date_col).map(|(_, h)| h.clone()).collect();
+
+    let (mut dates, mut returns) = (Vec::new(), Vec::new());
+    for line in lines {
+        let row = split_row(line, delimiter)?;
+        if row.len() != header.len() {
+            return Err("Data input mismatch: every row needs one cell per header column");
+        }
+        for (i, cell) in row.iter().enumerate() {
+            if i == date_col {
+                dates.push(cell.clone());
+            } else {
+                returns.push(parse_cell(cell)?);
+            }
+        }
+    }
+    if dates.is_empty() {
+        return Err("Data input invalid: no data rows");
+    }
+    Ok(ReturnsPanel { assets, dates, returns })
+}
+
+fn parse_cell(cell: &str) -> Result<f64, &'static str> {
+    match cell {
+        "" | "NA" | "N/A" | "NaN" | "nan" | "null" => Ok(f64::NAN),
+        _ => match cell.parse::<f64>() {
+            Ok(x) if x.is_finite() => Ok(x),
+            _ => Err("Data input invalid: cells must be numbers or empty"),
+        },
+    }
+}
+
+fn split_row(line: &str, delimiter: char) -> Result<Vec<String>, &'static str> {
+    let mut cells = Vec::new();
+    let (mut cell, mut quoted) = (String::new(), false);
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                cell.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            c => cell.push(c),
+        }
+    }
+    if quoted {
+        return Err("Data input invalid: unterminated quote");
+    }
+    cells.push(cell.trim().to_string());
+    Ok(cells)
+}
+
+// ────────────────────────────────────────────────────────────────
+// Estimation
+// ────────────────────────────────────────────────────────────────
+
+/// Annualized drift, vol and correlation with `periods_per_year`
+/// periods (252 for daily, 52 weekly, 12 monthly).
+pub fn market_inputs(
+    panel: &ReturnsPanel,
+    kind: ReturnKind,
+    periods_per_year: f64,
+) -> Result<HistoricalMarket, &'static str> {
+    let (n, t) = (panel.num_assets(), panel.num_periods());
+    if n == 0 || panel.returns.len() != t * n {
+        return Err("Data input mismatch: returns must be T × N");
+    }
+    if !(periods_per_year > 0.0 && periods_per_year.is_finite()) {
+        return Err("Data input invalid: periods_per_year must be positive");
+    }
+    let mut x = Vec::with_capacity(panel.returns.len());
+    for &r in &panel.returns {
+        x.push(match kind {
+            ReturnKind::Log => r,
+            ReturnKind::Simple if r.is_nan() => r,
+            ReturnKind::Simple if r > -1.0 => r.ln_1p(),
+            ReturnKind::Simple => return Err("Data input invalid: simple returns must be > −1"),
+        });
+    }
+
+    let mut drift = DVector::<f64>::zeros(n);
+    let mut vol = DVector::zeros(n);
+    let mut observations = vec![0; n];
+    for a in 0..n {
+        let xs: Vec<f64> = (0..t).map(|i| x[i * n + a]).filter(|v| !v.is_nan()).collect();
+        if xs.len() < 2 {
+            return Err("Data input invalid: every asset needs at least 2 observations");
+        }
+        let m = xs.len() as f64;
+        let mean = xs.iter().sum::<f64>() / m;
+        let var = xs.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (m - 1.0);
+        if var <= 0.0 {
+            return Err("Data input invalid: an asset's returns are constant");
+        }
+        vol[a] = (var * periods_per_year).sqrt();
+        drift[a] = mean * periods_per_year + 0.5 * vol[a] * vol[a];
+        observations[a] = xs.len();
+    }
+
+    // Listwise-complete rows for the correlation: always PSD, and for
+    // ragged starts the window since the youngest asset's inception
+    let rows: Vec<usize> = (0..t).filter(|&i| (0..n).all(|a| !x[i * n + a].is_nan())).collect();
+    if rows.len() < 2 {
+        return Err("Data input invalid: need at least 2 rows where every asset is observed");
+    }
+    let m = rows.len() as f64;
+    let mean: Vec<f64> = (0..n).map(|a| rows.iter().map(|&i| x[i * n + a]).sum::<f64>() / m).collect();
+    let mut cov = DMatrix::<f64>::zeros(n, n);
+    for &i in &rows {
+        for a in 0..n {
+            for b in a..n {
+                cov[(a, b)] += (x[i * n + a] - mean[a]) * (x[i * n + b] - mean[b]);
+            }
+        }
+    }
+    let mut correlation = DMatrix::identity(n, n);
+    for a in 0..n {
+        for b in a + 1..n {
+            let denom = (cov[(a, a)] * cov[(b, b)]).sqrt();
+            if denom <= 0.0 {
+                return Err("Data input invalid: an asset is constant over the overlapping rows");
+            }
+            let r = (cov[(a, b)] / denom).clamp(-1.0, 1.0);
+            correlation[(a, b)] = r;
+            correlation[(b, a)] = r;
+        }
+    }
+    Ok(HistoricalMarket { drift, vol, correlation, observations, overlap_rows: rows.len() })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_parse_csv_and_tsv() {
+        let csv = "Date,\"SPX, total\",AGG\n2024-01-02,0.01,\n2024-01-03,-0.02,0.001\n2024-01-04,NA,0.002\n";
+        let p = parse_returns(csv).unwrap();
+        assert_eq!(p.assets, vec!["SPX, total", "AGG"]);
+        assert_eq!(p.dates, vec!["2024-01-02", "2024-01-03", "2024-01-04"]);
+        assert!(p.returns[1].is_nan() && p.returns[4].is_nan());
+        assert_eq!(p.first_observed(), vec![Some(0), Some(1)]);
+
+        let tsv = "AGG\tdate\tGLD\n0.001\td1\t0.3\n";
+        let p = parse_returns(tsv).unwrap();
+        assert_eq!(p.assets, vec!["AGG", "GLD"]);
+        assert_eq!(p.dates, vec!["d1"]);
+        assert_eq!(p.returns, vec![0.001, 0.3]);
+
+        assert!(parse_returns("date,A\nd1,abc\n").is_err());
+        assert!(parse_returns("date,A,B\nd1,0.1\n").is_err());
+        assert!(parse_returns("date,A\n").is_err());
+    }
+
+    #[test]
+    fn test_market_inputs_ragged_start() {
+        // B starts two periods late; correlation uses the last 4 rows
+        let csv = "date,A,B,C\n\
+                   1,0.010,,0.002\n\
+                   2,-0.020,,0.001\n\
+                   3,0.015,0.012,-0.003\n\
+                   4,-0.005,-0.004,0.004\n\
+                   5,0.020,0.018,-0.001\n\
+                   6,-0.010,-0.011,0.002\n";
+        let p = parse_returns(csv).unwrap();
+        let m = market_inputs(&p, ReturnKind::Log, 252.0).unwrap();
+        assert_eq!(m.observations, vec![6, 4, 6]);
+        assert_eq!(m.overlap_rows, 4);
+
+        let a = [0.010, -0.020, 0.015, -0.005, 0.020, -0.010];
+        let mean = a.iter().sum::<f64>() / 6.0;
+        let var = a.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / 5.0;
+        assert_relative_eq!(m.vol[0], (252.0 * var).sqrt(), epsilon = 1e-12);
+        assert_relative_eq!(m.drift[0], 252.0 * mean + 126.0 * var, epsilon = 1e-12);
+        assert!(m.correlation[(0, 1)] > 0.99);
+        assert_eq!(m.correlation, m.correlation.transpose());
+        assert!(crate::math::validate_correlation(&m.correlation, 1e-9).is_ok());
+
+        let simple = market_inputs(&p, ReturnKind::Simple, 252.0).unwrap();
+        assert!(simple.vol[0] < m.vol[0] * 1.01 && simple.vol[0] > m.vol[0] * 0.99);
+        assert!(market_inputs(&parse_returns("date,A\n1,0.1\n").unwrap(), ReturnKind::Log, 252.0).is_err());
+    }
+}
@@ -1,8 +1,30 @@
+use std::cell::OnceCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use wasm_bindgen::prelude::*;
-use js_sys::Float32Array;
+use js_sys::{Float32Array, Int32Array, Uint32Array, Uint8Array};
 use nalgebra::{DMatrix, DVector};
 
+use crate::arrow;
+use crate::blocked;
+use crate::calibrate;
+use crate::data;
+use crate::error::{self, check_finite, check_lengths, guard, EngineError};
+use crate::fx;
+use crate::gpu;
+use crate::json::{self, Json};
+use crate::leverage;
 use crate::math;
+use crate::portfolio;
+use crate::presets;
+use crate::preview;
+use crate::replay;
+use crate::risk;
+use crate::scenario;
+use crate::sensitivity;
+use crate::simulate;
+use crate::strict;
+use crate::structure;
 
 // ════════════════════════════════════════════════════════════════
 // EngineResult — returned to JS with zero-copy Float32Array views
@@ -12,10 +34,30 @@ pub struct EngineResult {
     adjusted_drift: Vec<f32>,
     adjusted_vol: Vec<f32>,
     cholesky_l: Vec<f32>,
+    /// Post-blend, post-repair R (N×N row-major)
+    adjusted_correlation: Vec<f32>,
+    /// Σ = L·Lᵀ, filled on first access
+    covariance: OnceCell<Vec<f32>>,
+    diagnostics: Diagnostics,
     num_assets: usize,
-    jump_lambda: f32,
-    jump_mean: f32,
-    jump_vol: f32,
+    /// Per-asset jump parameters (N each, broadcast from scalars)
+    jump_lambda: Vec<f32>,
+    jump_mean: Vec<f32>,
+    jump_vol: Vec<f32>,
+    ridge_jitter: f32,
+    timings: Timings,
+}
+
+/// Flattening order for N×N outputs. ColumnMajor matches WGSL/GLSL
+/// matrix uniforms (tightly packed; std140 vec3 column padding is left
+/// to the caller). PackedLowerTriangular is rows of the lower triangle,
+/// L₀₀, L₁₀, L₁₁, L₂₀, …, N(N+1)/2 values.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatrixLayout {
+    RowMajor,
+    ColumnMajor,
+    PackedLowerTriangular,
 }
 
 #[wasm_bindgen]
@@ -35,31 +77,420 @@ impl EngineResult {
         Float32Array::from(self.cholesky_l.as_slice())
     }
 
+    /// L flattened in `layout`; RowMajor is the cholesky_l getter.
+    pub fn cholesky_l_in(&self, layout: MatrixLayout) -> Float32Array {
+        let n = self.num_assets;
+        let l = |i: usize, j: usize| self.cholesky_l[i * n + j];
+        let out: Vec<f32> = match layout {
+            MatrixLayout::RowMajor => self.cholesky_l.clone(),
+            MatrixLayout::ColumnMajor => (0..n).flat_map(|j| (0..n).map(move |i| l(i, j))).collect(),
+            MatrixLayout::PackedLowerTriangular => (0..n).flat_map(|i| (0..=i).map(move |j| l(i, j))).collect(),
+        };
+        Float32Array::from(out.as_slice())
+    }
+
+    /// Correlation after the crisis blend and PD repair, N×N row-major
+    /// (before any ridge jitter added to Σ; see ridge_jitter).
+    #[wasm_bindgen(getter)]
+    pub fn adjusted_correlation(&self) -> Float32Array {
+        Float32Array::from(self.adjusted_correlation.as_slice())
+    }
+
+    /// Σ = L·Lᵀ (N×N row-major) as the simulator uses it, ridge jitter
+    /// included; computed in f64 on first access and cached.
+    #[wasm_bindgen(getter)]
+    pub fn covariance(&self) -> Float32Array {
+        let cov = self.covariance.get_or_init(|| {
+            let n = self.num_assets;
+            let l = DMatrix::from_row_iterator(n, n, self.cholesky_l.iter().map(|&x| x as f64));
+            let cov = if strict::enabled() {
+                strict::gram(&l)
+            } else if n >= blocked::MIN_N {
+                let lt = l.transpose();
+                blocked::matmul_tn(&lt, &lt, true)
+            } else {
+                &l * l.transpose()
+            };
+            cov.transpose().iter().map(|&x| x as f32).collect()
+        });
+        Float32Array::from(cov.as_slice())
+    }
+
     #[wasm_bindgen(getter)]
     pub fn num_assets(&self) -> usize {
         self.num_assets
     }
 
+    // ── Zero-copy views (see "Zero-copy views" below) ────────────
+
+    pub fn adjusted_drift_view(&self) -> Float32Array {
+        view(&self.adjusted_drift)
+    }
+
+    pub fn adjusted_vol_view(&self) -> Float32Array {
+        view(&self.adjusted_vol)
+    }
+
+    pub fn cholesky_l_view(&self) -> Float32Array {
+        view(&self.cholesky_l)
+    }
+
+    /// Byte offset of L (N×N f32, row-major) in wasm memory
+    pub fn cholesky_l_ptr(&self) -> *const f32 {
+        self.cholesky_l.as_ptr()
+    }
+
+    /// Scalar jump parameters (asset 0's when they vary per asset) for
+    /// the GPU uniform block; see jump_lambdas etc. for the full vectors.
     #[wasm_bindgen(getter)]
     pub fn jump_lambda(&self) -> f32 {
-        self.jump_lambda
+        self.jump_lambda.first().copied().unwrap_or(0.0)
     }
 
     #[wasm_bindgen(getter)]
     pub fn jump_mean(&self) -> f32 {
-        self.jump_mean
+        self.jump_mean.first().copied().unwrap_or(0.0)
     }
 
     #[wasm_bindgen(getter)]
     pub fn jump_vol(&self) -> f32 {
-        self.jump_vol
+        self.jump_vol.first().copied().unwrap_or(0.0)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn jump_lambdas(&self) -> Float32Array {
+        Float32Array::from(self.jump_lambda.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn jump_means(&self) -> Float32Array {
+        Float32Array::from(self.jump_mean.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn jump_vols(&self) -> Float32Array {
+        Float32Array::from(self.jump_vol.as_slice())
+    }
+
+    /// Portfolio drift w·μ on the shocked drift (one weight per asset).
+    pub fn portfolio_drift(&self, weights: &[f32]) -> Result<f32, EngineError> {
+        guard(|| {
+            let p = to_portfolio(weights, &[])?;
+            let drift = p.drift(&to_dvector(&self.adjusted_drift))?;
+            Ok(drift as f32)
+        })
+    }
+
+    /// Portfolio vol √(wᵀΣw) on the shocked covariance Σ = L·Lᵀ.
+    pub fn portfolio_vol(&self, weights: &[f32]) -> Result<f32, EngineError> {
+        guard(|| {
+            let p = to_portfolio(weights, &[])?;
+            let n = self.num_assets;
+            let l: Vec<f64> = self.cholesky_l.iter().map(|&x| x as f64).collect();
+            let vol = p.vol(&DMatrix::from_row_slice(n, n, &l))?;
+            Ok(vol as f32)
+        })
+    }
+
+    /// Shocked market in f64 for the Rust-side analytics
+    fn market_state(&self) -> sensitivity::MarketState {
+        let n = self.num_assets;
+        let l: Vec<f64> = self.cholesky_l.iter().map(|&x| x as f64).collect();
+        let jumps = self.jump_lambda.iter().any(|&l| l > 0.0).then(|| {
+            (0..n)
+                .map(|i| simulate::JumpParams {
+                    lambda: self.jump_lambda[i] as f64,
+                    mean: self.jump_mean[i] as f64,
+                    vol: self.jump_vol[i] as f64,
+                })
+                .collect()
+        });
+        sensitivity::MarketState {
+            drift: to_dvector(&self.adjusted_drift),
+            vol: to_dvector(&self.adjusted_vol),
+            cholesky_l: DMatrix::from_row_slice(n, n, &l),
+            jumps,
+        }
+    }
+
+    /// Diagonal jitter ε added to Σ before factorization (0 when none was needed).
+    #[wasm_bindgen(getter)]
+    pub fn ridge_jitter(&self) -> f32 {
+        self.ridge_jitter
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn diagnostics(&self) -> Diagnostics {
+        self.diagnostics
+    }
+
+    /// Time spent in each Phase A step for this result
+    #[wasm_bindgen(getter)]
+    pub fn timings(&self) -> Timings {
+        self.timings
+    }
+
+    /// Consume into a plain object {num_assets, adjusted_drift,
+    /// adjusted_vol, cholesky_l, adjusted_correlation, covariance,
+    /// jump_lambda, jump_mean, jump_vol, ridge_jitter, transfer} whose
+    /// arrays own their buffers, for a worker's
+    /// `postMessage(obj, obj.transfer)` (see "Transferable output").
+    pub fn into_transferable(self) -> js_sys::Object {
+        let covariance = self.covariance();
+        let out = js_sys::Object::new();
+        let transfer = js_sys::Array::new();
+        let arrays = [
+            ("adjusted_drift", transferable(&self.adjusted_drift, &transfer)),
+            ("adjusted_vol", transferable(&self.adjusted_vol, &transfer)),
+            ("cholesky_l", transferable(&self.cholesky_l, &transfer)),
+            ("adjusted_correlation", transferable(&self.adjusted_correlation, &transfer)),
+            ("covariance", {
+                transfer.push(&covariance.buffer());
+                covariance
+            }),
+            ("jump_lambda", transferable(&self.jump_lambda, &transfer)),
+            ("jump_mean", transferable(&self.jump_mean, &transfer)),
+            ("jump_vol", transferable(&self.jump_vol, &transfer)),
+        ];
+        let set = |key: &str, value: &JsValue| {
+            // Setting a property on a fresh Object cannot fail
+            let _ = js_sys::Reflect::set(&out, &JsValue::from_str(key), value);
+        };
+        set("num_assets", &JsValue::from(self.num_assets as u32));
+        for (key, array) in &arrays {
+            set(key, array);
+        }
+        set("ridge_jitter", &JsValue::from(self.ridge_jitter));
+        set("transfer", &transfer);
+        out
+    }
+
+    /// Save as JSON (see "JSON save / load" below).
+    pub fn to_json(&self) -> String {
+        let d = &self.diagnostics;
+        Json::Object(vec![
+            ("kind".into(), Json::String("engine_result".into())),
+            ("version".into(), Json::Number(JSON_VERSION)),
+            ("num_assets".into(), Json::Number(self.num_assets as f64)),
+            ("adjusted_drift".into(), Json::from_f32s(&self.adjusted_drift)),
+            ("adjusted_vol".into(), Json::from_f32s(&self.adjusted_vol)),
+            ("cholesky_l".into(), Json::from_f32s(&self.cholesky_l)),
+            ("adjusted_correlation".into(), Json::from_f32s(&self.adjusted_correlation)),
+            ("jump_lambda".into(), Json::from_f32s(&self.jump_lambda)),
+            ("jump_mean".into(), Json::from_f32s(&self.jump_mean)),
+            ("jump_vol".into(), Json::from_f32s(&self.jump_vol)),
+            ("ridge_jitter".into(), Json::Number(self.ridge_jitter as f64)),
+            (
+                "diagnostics".into(),
+                Json::Object(vec![
+                    ("higham_iterations".into(), Json::Number(d.higham_iterations as f64)),
+                    ("higham_converged".into(), Json::Bool(d.higham_converged)),
+                    ("min_eigenvalue_before".into(), d.min_eigenvalue_before.map_or(Json::Null, |x| Json::Number(x as f64))),
+                    ("min_eigenvalue_after".into(), d.min_eigenvalue_after.map_or(Json::Null, |x| Json::Number(x as f64))),
+                    ("frobenius_distance".into(), Json::Number(d.frobenius_distance as f64)),
+                ]),
+            ),
+        ])
+        .to_string()
+    }
+
+    pub fn from_json(text: &str) -> Result<EngineResult, EngineError> {
+        guard(|| {
+            let json = load_json(text, "engine_result")?;
+            let n = json_f32(&json, "num_assets")? as usize;
+            let result = EngineResult {
+                adjusted_drift: json_f32s(&json, "adjusted_drift")?,
+                adjusted_vol: json_f32s(&json, "adjusted_vol")?,
+                cholesky_l: json_f32s(&json, "cholesky_l")?,
+                adjusted_correlation: json_f32s(&json, "adjusted_correlation")?,
+                covariance: OnceCell::new(),
+                diagnostics: {
+                    let d = json.get("diagnostics").ok_or(EngineError::MissingField { field: "diagnostics" })?;
+                    Diagnostics {
+                        higham_iterations: json_f32(d, "higham_iterations")? as u32,
+                        higham_converged: d
+                            .get("higham_converged")
+                            .and_then(Json::as_bool)
+                            .ok_or(EngineError::MissingField { field: "higham_converged" })?,
+                        // null when the repair was skipped
+                        min_eigenvalue_before: json_f32(d, "min_eigenvalue_before").ok(),
+                        min_eigenvalue_after: json_f32(d, "min_eigenvalue_after").ok(),
+                        frobenius_distance: json_f32(d, "frobenius_distance")?,
+                        ridge_jitter: json_f32(&json, "ridge_jitter")?,
+                    }
+                },
+                num_assets: n,
+                jump_lambda: json_f32s(&json, "jump_lambda")?,
+                jump_mean: json_f32s(&json, "jump_mean")?,
+                jump_vol: json_f32s(&json, "jump_vol")?,
+                ridge_jitter: json_f32(&json, "ridge_jitter")?,
+                // Machine-specific, so not serialized
+                timings: Timings::default(),
+            };
+            check_lengths(&[
+                ("adjusted_drift", n, result.adjusted_drift.len()),
+                ("adjusted_vol", n, result.adjusted_vol.len()),
+                ("cholesky_l", n * n, result.cholesky_l.len()),
+                ("adjusted_correlation", n * n, result.adjusted_correlation.len()),
+                ("jump_lambda", n, result.jump_lambda.len()),
+                ("jump_mean", n, result.jump_mean.len()),
+                ("jump_vol", n, result.jump_vol.len()),
+            ])?;
+            Ok(result)
+        })
+    }
+}
+
+/// How Phase A got from the blended correlation to L.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct Diagnostics {
+    higham_iterations: u32,
+    higham_converged: bool,
+    min_eigenvalue_before: Option<f32>,
+    min_eigenvalue_after: Option<f32>,
+    frobenius_distance: f32,
+    ridge_jitter: f32,
+}
+
+#[wasm_bindgen]
+impl Diagnostics {
+    /// The blended matrix was not PD (λ_min below the 1e-10 PSD floor)
+    #[wasm_bindgen(getter)]
+    pub fn repair_needed(&self) -> bool {
+        self.min_eigenvalue_before.is_some_and(|x| x < 1e-10)
+    }
+
+    /// Higham iterations run (0 when repair was skipped)
+    #[wasm_bindgen(getter)]
+    pub fn higham_iterations(&self) -> u32 {
+        self.higham_iterations
+    }
+
+    /// False when Higham stopped at its iteration cap
+    #[wasm_bindgen(getter)]
+    pub fn higham_converged(&self) -> bool {
+        self.higham_converged
+    }
+
+    /// λ_min of the blended correlation; undefined when the PD
+    /// pre-check passed and skipped the eigensolve (λ_min > 1e-8)
+    #[wasm_bindgen(getter)]
+    pub fn min_eigenvalue_before(&self) -> Option<f32> {
+        self.min_eigenvalue_before
+    }
+
+    /// λ_min of the repaired correlation; undefined as above
+    #[wasm_bindgen(getter)]
+    pub fn min_eigenvalue_after(&self) -> Option<f32> {
+        self.min_eigenvalue_after
+    }
+
+    /// ‖R_repaired − R_blended‖_F
+    #[wasm_bindgen(getter)]
+    pub fn frobenius_distance(&self) -> f32 {
+        self.frobenius_distance
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn ridge_jitter(&self) -> f32 {
+        self.ridge_jitter
+    }
+
+    /// Any fallback: ridge jitter on Σ or an unconverged repair
+    #[wasm_bindgen(getter)]
+    pub fn regularized(&self) -> bool {
+        self.ridge_jitter > 0.0 || !self.higham_converged
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Timings — wall-clock milliseconds per pipeline phase
+// Blend covers Steps 1–3, repair Step 4 (the is_pd pre-check or the
+// Higham loop), covariance Step 5 and Cholesky Step 6 (ridge retries
+// and in-place rescales included). Phases a call did not run read 0:
+// an Engine recompute after a Δμ change times only the blend, an
+// EngineResult has no simulation time and a PathBuffer nothing else.
+// The clock is performance.now() in the browser (Date.now() where it
+// is missing, so ms resolution) and a monotonic clock natively.
+// ────────────────────────────────────────────────────────────────
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Timings {
+    pub(crate) blend_ms: f64,
+    pub(crate) repair_ms: f64,
+    pub(crate) covariance_ms: f64,
+    pub(crate) cholesky_ms: f64,
+    pub(crate) simulation_ms: f64,
+}
+
+#[wasm_bindgen]
+impl Timings {
+    #[wasm_bindgen(getter)]
+    pub fn blend_ms(&self) -> f64 {
+        self.blend_ms
     }
+
+    #[wasm_bindgen(getter)]
+    pub fn repair_ms(&self) -> f64 {
+        self.repair_ms
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn covariance_ms(&self) -> f64 {
+        self.covariance_ms
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn cholesky_ms(&self) -> f64 {
+        self.cholesky_ms
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn simulation_ms(&self) -> f64 {
+        self.simulation_ms
+    }
+
+    /// Sum over the phases
+    #[wasm_bindgen(getter)]
+    pub fn total_ms(&self) -> f64 {
+        self.blend_ms + self.repair_ms + self.covariance_ms + self.cholesky_ms + self.simulation_ms
+    }
+}
+
+/// Runs `f`, adding its wall-clock time to `slot`
+pub(crate) fn timed<R>(slot: &mut f64, f: impl FnOnce() -> R) -> R {
+    let start = now_ms();
+    let out = f();
+    *slot += now_ms() - start;
+    out
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    let performance = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("performance")).ok();
+    performance
+        .filter(|p| p.is_object())
+        .and_then(|p| {
+            let now: js_sys::Function = js_sys::Reflect::get(&p, &JsValue::from_str("now")).ok()?.dyn_into().ok()?;
+            now.call0(&p).ok()?.as_f64()
+        })
+        .unwrap_or_else(js_sys::Date::now)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START.get_or_init(std::time::Instant::now).elapsed().as_secs_f64() * 1e3
 }
 
 // ════════════════════════════════════════════════════════════════
 // compute_shock — main entry point called from JS
+// num_assets only restates base_drift.len(); it is kept for existing
+// callers, and new code should use compute_shock_inferred.
 // ════════════════════════════════════════════════════════════════
 #[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
 pub fn compute_shock(
     num_assets: usize,
     base_drift: &[f32],
@@ -71,79 +502,4918 @@ pub fn compute_shock(
     jump_lambda: f32,
     jump_mean: f32,
     jump_vol: f32,
-) -> Result<EngineResult, JsValue> {
-    let n = num_assets;
-
-    // ── Validate input lengths ──────────────────────────────────
-    if base_drift.len() != n
-        || base_vol.len() != n
-        || base_correlation.len() != n * n
-        || delta_drift.len() != n
-        || vol_multiplier.len() != n
-    {
-        return Err(JsValue::from_str(&format!(
-            "Input length mismatch: expected N={}, got drift={}, vol={}, corr={}, dd={}, vm={}",
+) -> Result<EngineResult, EngineError> {
+    guard(|| {
+        let n = num_assets;
+
+        // ── Validate input lengths ──────────────────────────────
+        check_lengths(&[
+            ("base_drift", n, base_drift.len()),
+            ("base_vol", n, base_vol.len()),
+            ("base_correlation", n * n, base_correlation.len()),
+            ("delta_drift", n, delta_drift.len()),
+            ("vol_multiplier", n, vol_multiplier.len()),
+        ])?;
+
+        // ── Reject NaN / ±∞ before they reach the factorization ─
+        check_finite(&[
+            ("base_drift", base_drift),
+            ("base_vol", base_vol),
+            ("base_correlation", base_correlation),
+            ("delta_drift", delta_drift),
+            ("vol_multiplier", vol_multiplier),
+            ("correlation_skew", &[correlation_skew]),
+            ("jump_lambda", &[jump_lambda]),
+            ("jump_mean", &[jump_mean]),
+            ("jump_vol", &[jump_vol]),
+        ])?;
+
+        // ── Convert f32 → f64 for nalgebra precision ────────────
+        let base_corr_m = base_correlation_matrix(n, base_correlation, CORRELATION_TOLERANCE)?;
+
+        run_pipeline(
+            n,
+            base_drift,
+            base_vol,
+            &base_corr_m,
+            delta_drift,
+            vol_multiplier,
+            correlation_skew,
+            true,
+            (&[jump_lambda], &[jump_mean], &[jump_vol]),
+        )
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// compute_shock_inferred — compute_shock without num_assets
+// N is base_drift.len(); base_correlation must be a perfect square of
+// matching side, and every other array is checked against N as usual.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn compute_shock_inferred(
+    base_drift: &[f32],
+    base_vol: &[f32],
+    base_correlation: &[f32],
+    delta_drift: &[f32],
+    vol_multiplier: &[f32],
+    correlation_skew: f32,
+    jump_lambda: f32,
+    jump_mean: f32,
+    jump_vol: f32,
+) -> Result<EngineResult, EngineError> {
+    guard(|| {
+        let n = infer_num_assets(base_drift, base_correlation)?;
+        compute_shock(
+            n,
+            base_drift,
+            base_vol,
+            base_correlation,
+            delta_drift,
+            vol_multiplier,
+            correlation_skew,
+            jump_lambda,
+            jump_mean,
+            jump_vol,
+        )
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// compute_shock_packed — base correlation as its strict upper triangle
+// N(N−1)/2 values in row order, R₀₁, R₀₂, …, R₀,ₙ₋₁, R₁₂, …; the unit
+// diagonal and the lower half are implied, so the matrix crossing the
+// boundary is symmetric by construction.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn compute_shock_packed(
+    base_drift: &[f32],
+    base_vol: &[f32],
+    packed_correlation: &[f32],
+    delta_drift: &[f32],
+    vol_multiplier: &[f32],
+    correlation_skew: f32,
+    jump_lambda: f32,
+    jump_mean: f32,
+    jump_vol: f32,
+) -> Result<EngineResult, EngineError> {
+    guard(|| {
+        let n = base_drift.len();
+        compute_shock(
+            n,
+            base_drift,
+            base_vol,
+            &unpack_correlation(n, packed_correlation)?,
+            delta_drift,
+            vol_multiplier,
+            correlation_skew,
+            jump_lambda,
+            jump_mean,
+            jump_vol,
+        )
+    })
+}
+
+/// Strict upper triangle (row order) → full N×N row-major correlation.
+fn unpack_correlation(n: usize, packed: &[f32]) -> Result<Vec<f32>, EngineError> {
+    check_lengths(&[("packed_correlation", n * n.saturating_sub(1) / 2, packed.len())])?;
+    let mut r = vec![0.0; n * n];
+    let mut values = packed.iter();
+    for i in 0..n {
+        r[i * n + i] = 1.0;
+        for j in i + 1..n {
+            let x = *values.next().unwrap_or(&0.0);
+            r[i * n + j] = x;
+            r[j * n + i] = x;
+        }
+    }
+    Ok(r)
+}
+
+/// N from base_drift, cross-checked against an N×N base_correlation.
+fn infer_num_assets(base_drift: &[f32], base_correlation: &[f32]) -> Result<usize, EngineError> {
+    let side = base_correlation.len().isqrt();
+    if side * side != base_correlation.len() {
+        return Err(EngineError::ShapeMismatch {
+            reason: "Correlation input mismatch: length is not a perfect square",
+        });
+    }
+    let n = base_drift.len();
+    check_lengths(&[("base_correlation", n * n, base_correlation.len())])?;
+    Ok(n)
+}
+
+// ════════════════════════════════════════════════════════════════
+// compute_shock_asset_jumps — compute_shock with per-asset jumps
+// Each jump array is length N, or length 1 to broadcast a scalar.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn compute_shock_asset_jumps(
+    num_assets: usize,
+    base_drift: &[f32],
+    base_vol: &[f32],
+    base_correlation: &[f32],
+    delta_drift: &[f32],
+    vol_multiplier: &[f32],
+    correlation_skew: f32,
+    jump_lambda: &[f32],
+    jump_mean: &[f32],
+    jump_vol: &[f32],
+) -> Result<EngineResult, EngineError> {
+    guard(|| {
+        let n = num_assets;
+        check_finite(&[
+            ("base_drift", base_drift),
+            ("base_vol", base_vol),
+            ("base_correlation", base_correlation),
+            ("delta_drift", delta_drift),
+            ("vol_multiplier", vol_multiplier),
+            ("correlation_skew", &[correlation_skew]),
+            ("jump_lambda", jump_lambda),
+            ("jump_mean", jump_mean),
+            ("jump_vol", jump_vol),
+        ])?;
+        let base_corr_m = base_correlation_matrix(n, base_correlation, CORRELATION_TOLERANCE)?;
+        run_pipeline(
+            n,
+            base_drift,
+            base_vol,
+            &base_corr_m,
+            delta_drift,
+            vol_multiplier,
+            correlation_skew,
+            true,
+            (jump_lambda, jump_mean, jump_vol),
+        )
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// compute_shock_factors — same pipeline, base correlation supplied
+// as an N×K loading matrix (row-major), a K×K factor covariance
+// (row-major; empty for orthogonal unit-variance factors) and N
+// idiosyncratic variances
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn compute_shock_factors(
+    num_assets: usize,
+    num_factors: usize,
+    base_drift: &[f32],
+    base_vol: &[f32],
+    factor_loadings: &[f32],
+    factor_covariance: &[f32],
+    idio_var: &[f32],
+    delta_drift: &[f32],
+    vol_multiplier: &[f32],
+    correlation_skew: f32,
+    jump_lambda: f32,
+    jump_mean: f32,
+    jump_vol: f32,
+) -> Result<EngineResult, EngineError> {
+    guard(|| {
+        let n = num_assets;
+        let k = num_factors;
+
+        // ── Validate input lengths ──────────────────────────────────
+        check_lengths(&[
+            ("base_drift", n, base_drift.len()),
+            ("base_vol", n, base_vol.len()),
+            ("factor_loadings", n * k, factor_loadings.len()),
+            ("idio_var", n, idio_var.len()),
+            ("delta_drift", n, delta_drift.len()),
+            ("vol_multiplier", n, vol_multiplier.len()),
+        ])?;
+
+        if !factor_covariance.is_empty() {
+            check_lengths(&[("factor_covariance", k * k, factor_covariance.len())])?;
+        }
+
+        // ── Reject NaN / ±∞ before they reach the factorization ─────
+        check_finite(&[
+            ("base_drift", base_drift),
+            ("base_vol", base_vol),
+            ("factor_loadings", factor_loadings),
+            ("factor_covariance", factor_covariance),
+            ("idio_var", idio_var),
+            ("delta_drift", delta_drift),
+            ("vol_multiplier", vol_multiplier),
+            ("correlation_skew", &[correlation_skew]),
+            ("jump_lambda", &[jump_lambda]),
+            ("jump_mean", &[jump_mean]),
+            ("jump_vol", &[jump_vol]),
+        ])?;
+
+        let bl: Vec<f64> = factor_loadings.iter().map(|&x| x as f64).collect();
+        let fc: Vec<f64> = factor_covariance.iter().map(|&x| x as f64).collect();
+        let iv: Vec<f64> = idio_var.iter().map(|&x| x as f64).collect();
+        let factor_cov = (!fc.is_empty()).then(|| DMatrix::from_row_slice(k, k, &fc));
+        let base_corr_m = math::corr_from_factors(
+            &DMatrix::from_row_slice(n, k, &bl),
+            factor_cov.as_ref(),
+            &DVector::from_vec(iv),
+        )?;
+
+        // Factor-built R is PD, and (1-s)·R + s·J stays PD for 0 ≤ s < 1,
+        // so Higham repair is only needed at full crisis skew or when a
+        // negative skew subtracts J.
+        let repair = !(0.0..1.0).contains(&correlation_skew);
+
+        run_pipeline(
             n,
-            base_drift.len(),
-            base_vol.len(),
-            base_correlation.len(),
-            delta_drift.len(),
-            vol_multiplier.len(),
-        )));
+            base_drift,
+            base_vol,
+            &base_corr_m,
+            delta_drift,
+            vol_multiplier,
+            correlation_skew,
+            repair,
+            (&[jump_lambda], &[jump_mean], &[jump_vol]),
+        )
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// ShockConfig — named-setter alternative to compute_shock's positional
+// arguments. Starts as "no shock" (Δμ = 0, multipliers 1, skew 0, no
+// jumps) over the base market; N is taken from base_drift. New options
+// become new setters, so existing callers keep working.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ShockConfig {
+    base_drift: Vec<f32>,
+    base_vol: Vec<f32>,
+    base_correlation: Vec<f32>,
+    delta_drift: Vec<f32>,
+    vol_multiplier: Vec<f32>,
+    correlation_skew: f32,
+    jump_lambda: Vec<f32>,
+    jump_mean: Vec<f32>,
+    jump_vol: Vec<f32>,
+    correlation_tolerance: f32,
+}
+
+#[wasm_bindgen]
+impl ShockConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new(base_drift: &[f32], base_vol: &[f32], base_correlation: &[f32]) -> ShockConfig {
+        let n = base_drift.len();
+        ShockConfig {
+            base_drift: base_drift.to_vec(),
+            base_vol: base_vol.to_vec(),
+            base_correlation: base_correlation.to_vec(),
+            delta_drift: vec![0.0; n],
+            vol_multiplier: vec![1.0; n],
+            correlation_skew: 0.0,
+            jump_lambda: vec![0.0],
+            jump_mean: vec![0.0],
+            jump_vol: vec![0.0],
+            correlation_tolerance: CORRELATION_TOLERANCE,
+        }
     }
 
-    // ── Convert f32 → f64 for nalgebra precision ────────────────
-    let bd: Vec<f64> = base_drift.iter().map(|&x| x as f64).collect();
-    let bv: Vec<f64> = base_vol.iter().map(|&x| x as f64).collect();
-    let bc: Vec<f64> = base_correlation.iter().map(|&x| x as f64).collect();
-    let dd: Vec<f64> = delta_drift.iter().map(|&x| x as f64).collect();
-    let vm: Vec<f64> = vol_multiplier.iter().map(|&x| x as f64).collect();
+    /// As new(), with the correlation in compute_shock_packed's layout
+    pub fn from_packed_correlation(
+        base_drift: &[f32],
+        base_vol: &[f32],
+        packed_correlation: &[f32],
+    ) -> Result<ShockConfig, EngineError> {
+        guard(|| {
+            let r = unpack_correlation(base_drift.len(), packed_correlation)?;
+            Ok(ShockConfig::new(base_drift, base_vol, &r))
+        })
+    }
 
-    let base_drift_v = DVector::from_vec(bd);
-    let base_vol_v = DVector::from_vec(bv);
-    let base_corr_m = DMatrix::from_row_slice(n, n, &bc);
-    let delta_drift_v = DVector::from_vec(dd);
-    let vol_mult_v = DVector::from_vec(vm);
+    #[wasm_bindgen(getter)]
+    pub fn num_assets(&self) -> usize {
+        self.base_drift.len()
+    }
 
-    // ── Run the math pipeline ───────────────────────────────────
-    // Step 1: Adjust drift
-    let adj_drift = math::adjust_drift(&base_drift_v, &delta_drift_v);
+    /// Additive drift shock Δμ (N)
+    pub fn set_delta_drift(&mut self, delta_drift: &[f32]) {
+        self.delta_drift = delta_drift.to_vec();
+    }
 
-    // Step 2: Adjust volatility
-    let adj_vol = math::adjust_vol(&base_vol_v, &vol_mult_v);
+    /// Multiplicative vol shock (N)
+    pub fn set_vol_multiplier(&mut self, vol_multiplier: &[f32]) {
+        self.vol_multiplier = vol_multiplier.to_vec();
+    }
 
-    // Step 3: Blend correlation toward crisis mode
-    let blended = math::blend_correlation(&base_corr_m, correlation_skew as f64);
+    /// Blend toward the crisis correlation, 0 (base) to 1 (crisis)
+    pub fn set_correlation_skew(&mut self, correlation_skew: f32) {
+        self.correlation_skew = correlation_skew;
+    }
 
-    // Step 4: Project to nearest positive-definite (Higham)
-    let pd = math::nearest_pd(&blended);
+    /// The same Merton jump process for every asset
+    pub fn set_jumps(&mut self, jump_lambda: f32, jump_mean: f32, jump_vol: f32) {
+        self.set_asset_jumps(&[jump_lambda], &[jump_mean], &[jump_vol]);
+    }
 
-    // Step 5: Rebuild covariance Σ = D·R·D
-    let cov = math::rebuild_covariance(&adj_vol, &pd);
+    /// Per-asset jumps; each array is length N, or 1 to broadcast
+    pub fn set_asset_jumps(&mut self, jump_lambda: &[f32], jump_mean: &[f32], jump_vol: &[f32]) {
+        self.jump_lambda = jump_lambda.to_vec();
+        self.jump_mean = jump_mean.to_vec();
+        self.jump_vol = jump_vol.to_vec();
+    }
 
-    // Step 6: Cholesky decomposition
-    let l = math::cholesky_decompose(&cov)
-        .map_err(|e| JsValue::from_str(e))?;
+    /// Slack for the base correlation's symmetry / unit-diagonal /
+    /// [−1, 1] checks (default 1e-6)
+    pub fn set_correlation_tolerance(&mut self, tolerance: f32) {
+        self.correlation_tolerance = tolerance;
+    }
+}
 
-    // ── Pack results as flattened f32 arrays ─────────────────────
-    let adj_drift_f32: Vec<f32> = adj_drift.iter().map(|&x| x as f32).collect();
-    let adj_vol_f32: Vec<f32> = adj_vol.iter().map(|&x| x as f32).collect();
+impl ShockConfig {
+    fn check_finite(&self) -> Result<(), EngineError> {
+        check_finite(&[
+            ("base_drift", &self.base_drift),
+            ("base_vol", &self.base_vol),
+            ("base_correlation", &self.base_correlation),
+            ("delta_drift", &self.delta_drift),
+            ("vol_multiplier", &self.vol_multiplier),
+            ("correlation_skew", &[self.correlation_skew]),
+            ("jump_lambda", &self.jump_lambda),
+            ("jump_mean", &self.jump_mean),
+            ("jump_vol", &self.jump_vol),
+        ])
+    }
 
-    // Flatten L in row-major for GPU uniform upload
-    let mut cholesky_f32 = Vec::with_capacity(n * n);
-    for i in 0..n {
-        for j in 0..n {
-            cholesky_f32.push(l[(i, j)] as f32);
+    pub fn builder() -> ShockConfigBuilder {
+        ShockConfigBuilder::default()
+    }
+
+    /// Everything compute_shock_config checks before running; returns
+    /// the base R as f64
+    fn validate(&self) -> Result<DMatrix<f64>, EngineError> {
+        let n = infer_num_assets(&self.base_drift, &self.base_correlation)?;
+        check_lengths(&[
+            ("base_vol", n, self.base_vol.len()),
+            ("delta_drift", n, self.delta_drift.len()),
+            ("vol_multiplier", n, self.vol_multiplier.len()),
+        ])?;
+        for jumps in [&self.jump_lambda, &self.jump_mean, &self.jump_vol] {
+            broadcast(jumps, n)?;
         }
+        self.check_finite()?;
+        base_correlation_matrix(n, &self.base_correlation, self.correlation_tolerance)
     }
 
-    Ok(EngineResult {
-        adjusted_drift: adj_drift_f32,
-        adjusted_vol: adj_vol_f32,
-        cholesky_l: cholesky_f32,
-        num_assets: n,
-        jump_lambda,
-        jump_mean,
-        jump_vol,
-    })
+    /// Phase A in f64, as compute_shock_config runs it, for native
+    /// callers that want the ShockedMarket rather than f32 arrays
+    pub fn shock_market(&self) -> Result<ShockedMarket, EngineError> {
+        let base_correlation = self.validate()?;
+        shock_market(
+            &to_dvector(&self.base_drift),
+            &to_dvector(&self.base_vol),
+            &base_correlation,
+            &to_dvector(&self.delta_drift),
+            &to_dvector(&self.vol_multiplier),
+            self.correlation_skew as f64,
+            true,
+        )
+    }
+
+    /// Jumps per asset, for SimConfig::with_asset_jumps
+    pub fn jump_params(&self) -> Result<Vec<simulate::JumpParams>, EngineError> {
+        let n = self.num_assets();
+        let (lambda, mean, vol) = (broadcast(&self.jump_lambda, n)?, broadcast(&self.jump_mean, n)?, broadcast(&self.jump_vol, n)?);
+        Ok((0..n)
+            .map(|i| simulate::JumpParams { lambda: lambda[i] as f64, mean: mean[i] as f64, vol: vol[i] as f64 })
+            .collect())
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// ShockConfigBuilder — ShockConfig for native Rust callers
+//     let config = ShockConfig::builder()
+//         .base_market(&mu, &sigma, &r)
+//         .vol_multiplier(&[2.0, 1.5, 1.0])
+//         .skew(0.85)
+//         .jumps(0.5, -0.1, 0.15)
+//         .build()?;
+//     let market = config.shock_market()?;
+// Anything not set keeps new()'s "no shock". build() runs every check
+// compute_shock_config does, so a built config is known to compute.
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug, Default)]
+pub struct ShockConfigBuilder {
+    base: Option<(Vec<f32>, Vec<f32>, Vec<f32>)>,
+    delta_drift: Option<Vec<f32>>,
+    vol_multiplier: Option<Vec<f32>>,
+    correlation_skew: f32,
+    jumps: Option<(Vec<f32>, Vec<f32>, Vec<f32>)>,
+    correlation_tolerance: Option<f32>,
+}
+
+impl ShockConfigBuilder {
+    /// μ and σ (N each) and R (N×N row-major); required
+    pub fn base_market(mut self, base_drift: &[f32], base_vol: &[f32], base_correlation: &[f32]) -> Self {
+        self.base = Some((base_drift.to_vec(), base_vol.to_vec(), base_correlation.to_vec()));
+        self
+    }
+
+    pub fn delta_drift(mut self, delta_drift: &[f32]) -> Self {
+        self.delta_drift = Some(delta_drift.to_vec());
+        self
+    }
+
+    pub fn vol_multiplier(mut self, vol_multiplier: &[f32]) -> Self {
+        self.vol_multiplier = Some(vol_multiplier.to_vec());
+        self
+    }
+
+    /// Correlation skew, 0 (base) to 1 (crisis)
+    pub fn skew(mut self, correlation_skew: f32) -> Self {
+        self.correlation_skew = correlation_skew;
+        self
+    }
+
+    pub fn jumps(self, jump_lambda: f32, jump_mean: f32, jump_vol: f32) -> Self {
+        self.asset_jumps(&[jump_lambda], &[jump_mean], &[jump_vol])
+    }
+
+    /// Each array length N, or 1 to broadcast
+    pub fn asset_jumps(mut self, jump_lambda: &[f32], jump_mean: &[f32], jump_vol: &[f32]) -> Self {
+        self.jumps = Some((jump_lambda.to_vec(), jump_mean.to_vec(), jump_vol.to_vec()));
+        self
+    }
+
+    pub fn correlation_tolerance(mut self, tolerance: f32) -> Self {
+        self.correlation_tolerance = Some(tolerance);
+        self
+    }
+
+    pub fn build(self) -> Result<ShockConfig, EngineError> {
+        let (base_drift, base_vol, base_correlation) = self.base.ok_or(EngineError::InvalidInput {
+            reason: "ShockConfig input invalid: base_market is required",
+        })?;
+        let mut config = ShockConfig::new(&base_drift, &base_vol, &base_correlation);
+        if let Some(delta_drift) = self.delta_drift {
+            config.delta_drift = delta_drift;
+        }
+        if let Some(vol_multiplier) = self.vol_multiplier {
+            config.vol_multiplier = vol_multiplier;
+        }
+        config.correlation_skew = self.correlation_skew;
+        if let Some((lambda, mean, vol)) = self.jumps {
+            (config.jump_lambda, config.jump_mean, config.jump_vol) = (lambda, mean, vol);
+        }
+        if let Some(tolerance) = self.correlation_tolerance {
+            config.correlation_tolerance = tolerance;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// JSON save / load
+// ShockConfig and EngineResult serialize to flat objects keyed by
+// their field names, tagged with "kind" and "version"; arrays are
+// row-major as in the getters. Loading checks the tag and shapes, so
+// a saved scenario reloads into exactly the object that wrote it.
+// ────────────────────────────────────────────────────────────────
+const JSON_VERSION: f64 = 1.0;
+
+fn load_json(text: &str, kind: &'static str) -> Result<Json, EngineError> {
+    let json = json::parse(text)?;
+    if json.get("kind").and_then(Json::as_str) != Some(kind) {
+        return Err(EngineError::MissingField { field: "kind" });
+    }
+    if json.get("version").and_then(Json::as_f64).is_none_or(|v| v > JSON_VERSION) {
+        return Err(EngineError::InvalidInput { reason: "JSON input invalid: unsupported version" });
+    }
+    Ok(json)
+}
+
+fn json_f32s(json: &Json, field: &'static str) -> Result<Vec<f32>, EngineError> {
+    json.get(field).and_then(Json::as_f32_vec).ok_or(EngineError::MissingField { field })
+}
+
+fn json_f32(json: &Json, field: &'static str) -> Result<f32, EngineError> {
+    json.get(field).and_then(Json::as_f64).map(|x| x as f32).ok_or(EngineError::MissingField { field })
+}
+
+#[wasm_bindgen]
+impl ShockConfig {
+    pub fn to_json(&self) -> String {
+        Json::Object(vec![
+            ("kind".into(), Json::String("shock_config".into())),
+            ("version".into(), Json::Number(JSON_VERSION)),
+            ("base_drift".into(), Json::from_f32s(&self.base_drift)),
+            ("base_vol".into(), Json::from_f32s(&self.base_vol)),
+            ("base_correlation".into(), Json::from_f32s(&self.base_correlation)),
+            ("delta_drift".into(), Json::from_f32s(&self.delta_drift)),
+            ("vol_multiplier".into(), Json::from_f32s(&self.vol_multiplier)),
+            ("correlation_skew".into(), Json::Number(self.correlation_skew as f64)),
+            ("jump_lambda".into(), Json::from_f32s(&self.jump_lambda)),
+            ("jump_mean".into(), Json::from_f32s(&self.jump_mean)),
+            ("jump_vol".into(), Json::from_f32s(&self.jump_vol)),
+            ("correlation_tolerance".into(), Json::Number(self.correlation_tolerance as f64)),
+        ])
+        .to_string()
+    }
+
+    pub fn from_json(text: &str) -> Result<ShockConfig, EngineError> {
+        guard(|| {
+            let json = load_json(text, "shock_config")?;
+            Ok(ShockConfig {
+                base_drift: json_f32s(&json, "base_drift")?,
+                base_vol: json_f32s(&json, "base_vol")?,
+                base_correlation: json_f32s(&json, "base_correlation")?,
+                delta_drift: json_f32s(&json, "delta_drift")?,
+                vol_multiplier: json_f32s(&json, "vol_multiplier")?,
+                correlation_skew: json_f32(&json, "correlation_skew")?,
+                jump_lambda: json_f32s(&json, "jump_lambda")?,
+                jump_mean: json_f32s(&json, "jump_mean")?,
+                jump_vol: json_f32s(&json, "jump_vol")?,
+                correlation_tolerance: json_f32(&json, "correlation_tolerance").unwrap_or(CORRELATION_TOLERANCE),
+            })
+        })
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// ScenarioDocument — the versioned scenario file (scenario.rs): a
+// ShockConfig, simulation settings, weights and metadata in the format
+// mssim-cli and the Python bindings read and write too.
+// ────────────────────────────────────────────────────────────────
+#[wasm_bindgen]
+pub struct ScenarioDocument {
+    inner: scenario::Scenario,
+}
+
+#[wasm_bindgen]
+impl ScenarioDocument {
+    #[wasm_bindgen(constructor)]
+    pub fn new(config: &ShockConfig, options: &SimulationOptions, name: &str) -> Result<ScenarioDocument, EngineError> {
+        guard(|| {
+            let f64s = |xs: &[f32]| xs.iter().map(|&x| x as f64).collect::<Vec<_>>();
+            let mut s = scenario::Scenario::new(
+                f64s(&config.base_drift),
+                f64s(&config.base_vol),
+                f64s(&config.base_correlation),
+            );
+            s.metadata.name = name.to_string();
+            s.shock.delta_drift = f64s(&config.delta_drift);
+            s.shock.vol_multiplier = f64s(&config.vol_multiplier);
+            s.shock.correlation_skew = config.correlation_skew as f64;
+            let width = config.jump_lambda.len().max(config.jump_mean.len()).max(config.jump_vol.len());
+            let at = |xs: &[f32], i: usize| xs.get(i).or(xs.first()).map_or(0.0, |&x| x as f64);
+            s.shock.jumps = (0..width)
+                .map(|i| simulate::JumpParams {
+                    lambda: at(&config.jump_lambda, i),
+                    mean: at(&config.jump_mean, i),
+                    vol: at(&config.jump_vol, i),
+                })
+                .collect();
+            let c = &options.config;
+            s.simulation.horizon = c.horizon;
+            s.simulation.steps = c.steps;
+            s.simulation.n_paths = c.n_paths;
+            s.simulation.seed = c.seed;
+            s.validate()?;
+            Ok(ScenarioDocument { inner: s })
+        })
+    }
+
+    pub fn from_json(text: &str) -> Result<ScenarioDocument, EngineError> {
+        guard(|| {
+            Ok(ScenarioDocument { inner: scenario::Scenario::from_json(text)? })
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        self.inner.to_json()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.inner.metadata.name.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_name(&mut self, name: &str) {
+        self.inner.metadata.name = name.to_string();
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn description(&self) -> String {
+        self.inner.metadata.description.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_description(&mut self, description: &str) {
+        self.inner.metadata.description = description.to_string();
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn assets(&self) -> Vec<String> {
+        self.inner.market.assets.clone()
+    }
+
+    /// Labels for the N assets; ignored unless there is one per asset
+    pub fn set_assets(&mut self, assets: Vec<String>) {
+        if assets.len() == self.inner.num_assets() {
+            self.inner.market.assets = assets;
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn weights(&self) -> Float32Array {
+        let w: Vec<f32> = self.inner.market.weights.iter().map(|&x| x as f32).collect();
+        Float32Array::from(w.as_slice())
+    }
+
+    pub fn set_weights(&mut self, weights: &[f32]) -> Result<(), EngineError> {
+        guard(|| {
+            check_lengths(&[("weights", self.inner.num_assets(), weights.len())])?;
+            self.inner.market.weights = weights.iter().map(|&x| x as f64).collect();
+            Ok(())
+        })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn levels(&self) -> Float32Array {
+        let a: Vec<f32> = self.inner.simulation.levels.iter().map(|&x| x as f32).collect();
+        Float32Array::from(a.as_slice())
+    }
+
+    pub fn shock_config(&self) -> ShockConfig {
+        let f32s = |xs: &[f64]| xs.iter().map(|&x| x as f32).collect::<Vec<_>>();
+        let (m, s) = (&self.inner.market, &self.inner.shock);
+        let mut config = ShockConfig::new(&f32s(&m.base_drift), &f32s(&m.base_vol), &f32s(&m.base_correlation));
+        config.delta_drift = f32s(&s.delta_drift);
+        config.vol_multiplier = f32s(&s.vol_multiplier);
+        config.correlation_skew = s.correlation_skew as f32;
+        config.jump_lambda = s.jumps.iter().map(|j| j.lambda as f32).collect();
+        config.jump_mean = s.jumps.iter().map(|j| j.mean as f32).collect();
+        config.jump_vol = s.jumps.iter().map(|j| j.vol as f32).collect();
+        config
+    }
+
+    /// Horizon, steps, paths and seed (jumps come with shock_config)
+    pub fn simulation_options(&self) -> SimulationOptions {
+        let s = &self.inner.simulation;
+        SimulationOptions { config: simulate::SimConfig::new(s.horizon, s.steps, s.n_paths, s.seed) }
+    }
+}
+
+/// compute_shock driven by a ShockConfig.
+#[wasm_bindgen]
+pub fn compute_shock_config(config: &ShockConfig) -> Result<EngineResult, EngineError> {
+    guard(|| {
+        let base_corr_m = config.validate()?;
+        run_pipeline(
+            config.num_assets(),
+            &config.base_drift,
+            &config.base_vol,
+            &base_corr_m,
+            &config.delta_drift,
+            &config.vol_multiplier,
+            config.correlation_skew,
+            true,
+            (&config.jump_lambda, &config.jump_mean, &config.jump_vol),
+        )
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// compute_shock_preview — compute_shock_config in single precision
+// For interactive previews (slider drags): Phase A runs in f32 on the
+// config's arrays as given, with no f64 round trip, via preview.rs.
+// Results agree with compute_shock_config to f32 precision (≈1e-4);
+// use that for final runs.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub fn compute_shock_preview(config: &ShockConfig) -> Result<EngineResult, EngineError> {
+    guard(|| {
+        let n = infer_num_assets(&config.base_drift, &config.base_correlation)?;
+        config.check_finite()?;
+        check_lengths(&[
+            ("base_vol", n, config.base_vol.len()),
+            ("base_correlation", n * n, config.base_correlation.len()),
+            ("delta_drift", n, config.delta_drift.len()),
+            ("vol_multiplier", n, config.vol_multiplier.len()),
+        ])?;
+        let base_corr = DMatrix::from_row_slice(n, n, &config.base_correlation);
+        math::validate_correlation(&base_corr, config.correlation_tolerance)
+            .map_err(|d| EngineError::InvalidCorrelation { row: d.row, col: d.col, reason: d.reason })?;
+        let jump_lambda = broadcast(&config.jump_lambda, n)?;
+        let jump_mean = broadcast(&config.jump_mean, n)?;
+        let jump_vol = broadcast(&config.jump_vol, n)?;
+
+        let market = preview::shock_market(
+            &DVector::from_column_slice(&config.base_drift),
+            &DVector::from_column_slice(&config.base_vol),
+            &base_corr,
+            &DVector::from_column_slice(&config.delta_drift),
+            &DVector::from_column_slice(&config.vol_multiplier),
+            config.correlation_skew,
+            true,
+        )?;
+        Ok(EngineResult {
+            adjusted_drift: market.drift.as_slice().to_vec(),
+            adjusted_vol: market.vol.as_slice().to_vec(),
+            cholesky_l: market.cholesky_l.transpose().as_slice().to_vec(),
+            adjusted_correlation: market.correlation.transpose().as_slice().to_vec(),
+            covariance: OnceCell::new(),
+            diagnostics: Diagnostics {
+                higham_iterations: market.higham_iterations as u32,
+                higham_converged: market.higham_converged,
+                min_eigenvalue_before: Some(market.min_eigenvalue_before),
+                min_eigenvalue_after: Some(market.min_eigenvalue_after),
+                frobenius_distance: market.distance,
+                ridge_jitter: market.ridge_jitter,
+            },
+            num_assets: n,
+            jump_lambda,
+            jump_mean,
+            jump_vol,
+            ridge_jitter: market.ridge_jitter,
+            timings: market.timings,
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// compute_shock_batch — B scenarios against one base market in one
+// call. delta_drift / vol_multiplier are B×N row-major, one skew per
+// scenario, and each jump array has length B or 1 (shared by all).
+// The base correlation's spectrum is computed once and reused to skip
+// Higham repair where the blend is provably PD already (see
+// BatchCorrelation); scenarios with equal skew share the repaired R.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn compute_shock_batch(
+    base_drift: &[f32],
+    base_vol: &[f32],
+    base_correlation: &[f32],
+    delta_drift: &[f32],
+    vol_multiplier: &[f32],
+    correlation_skew: &[f32],
+    jump_lambda: &[f32],
+    jump_mean: &[f32],
+    jump_vol: &[f32],
+) -> Result<Vec<EngineResult>, EngineError> {
+    guard(|| {
+        let (n, b) = (infer_num_assets(base_drift, base_correlation)?, correlation_skew.len());
+        check_lengths(&[
+            ("base_vol", n, base_vol.len()),
+            ("base_correlation", n * n, base_correlation.len()),
+            ("delta_drift", b * n, delta_drift.len()),
+            ("vol_multiplier", b * n, vol_multiplier.len()),
+        ])?;
+        check_finite(&[
+            ("base_drift", base_drift),
+            ("base_vol", base_vol),
+            ("base_correlation", base_correlation),
+            ("delta_drift", delta_drift),
+            ("vol_multiplier", vol_multiplier),
+            ("correlation_skew", correlation_skew),
+            ("jump_lambda", jump_lambda),
+            ("jump_mean", jump_mean),
+            ("jump_vol", jump_vol),
+        ])?;
+        let jump_lambda = broadcast(jump_lambda, b)?;
+        let jump_mean = broadcast(jump_mean, b)?;
+        let jump_vol = broadcast(jump_vol, b)?;
+
+        let mut correlation = BatchCorrelation::new(&base_correlation_matrix(n, base_correlation, CORRELATION_TOLERANCE)?);
+        let (base_drift, base_vol) = (to_dvector(base_drift), to_dvector(base_vol));
+        (0..b)
+            .map(|k| {
+                let rows = k * n..(k + 1) * n;
+                let mut timings = Timings::default();
+                let (drift, vol) = timed(&mut timings.blend_ms, || {
+                    (
+                        math::adjust_drift(&base_drift, &to_dvector(&delta_drift[rows.clone()])),
+                        math::adjust_vol(&base_vol, &to_dvector(&vol_multiplier[rows])),
+                    )
+                });
+                // Step 3 is timed with the repair here, and a skew seen
+                // earlier in the batch costs neither
+                let (pd, report) = timed(&mut timings.repair_ms, || correlation.repaired(correlation_skew[k] as f64));
+                let market = finish_market(drift, vol, pd, report, timings)?;
+                Ok(pack_result(&market, vec![jump_lambda[k]; n], vec![jump_mean[k]; n], vec![jump_vol[k]; n]))
+            })
+            .collect()
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// ComputeHandle — compute_shock_config in resumable slices
+// For large N each Higham iteration is a full eigendecomposition, so
+// the JS side drives the repair a few iterations at a time and yields
+// to the event loop in between:
+//     const h = new ComputeHandle(config);
+//     while (!h.step(4)) await new Promise(r => setTimeout(r));
+//     const result = h.finish();
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct ComputeHandle {
+    drift: DVector<f64>,
+    vol: DVector<f64>,
+    blended: DMatrix<f64>,
+    /// None when the blend passed math::is_pd (nothing to iterate)
+    projection: Option<math::HighamProjection>,
+    jumps: (Vec<f32>, Vec<f32>, Vec<f32>),
+    /// Summed over step() calls
+    timings: Timings,
+}
+
+#[wasm_bindgen]
+impl ComputeHandle {
+    /// Validates the config and runs Steps 1–3; no repair iterations yet.
+    #[wasm_bindgen(constructor)]
+    pub fn new(config: &ShockConfig) -> Result<ComputeHandle, EngineError> {
+        guard(|| {
+            let base = config.validate()?;
+            let n = config.num_assets();
+            let jumps = (
+                broadcast(&config.jump_lambda, n)?,
+                broadcast(&config.jump_mean, n)?,
+                broadcast(&config.jump_vol, n)?,
+            );
+            let mut timings = Timings::default();
+            let (drift, vol, blended) = timed(&mut timings.blend_ms, || {
+                (
+                    math::adjust_drift(&to_dvector(&config.base_drift), &to_dvector(&config.delta_drift)),
+                    math::adjust_vol(&to_dvector(&config.base_vol), &to_dvector(&config.vol_multiplier)),
+                    math::blend_correlation(&base, config.correlation_skew as f64),
+                )
+            });
+            let projection = timed(&mut timings.repair_ms, || {
+                (!math::is_pd(&blended, REPAIR_SKIP_MIN_EIGENVALUE)).then(|| math::HighamProjection::new(&blended, None))
+            });
+            Ok(ComputeHandle { drift, vol, blended, projection, jumps, timings })
+        })
+    }
+
+    /// Run up to `iterations` Higham iterations; true once converged.
+    pub fn step(&mut self, iterations: usize) -> bool {
+        let projection = &mut self.projection;
+        timed(&mut self.timings.repair_ms, || projection.as_mut().is_none_or(|p| p.step(iterations)))
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn done(&self) -> bool {
+        self.projection.as_ref().is_none_or(|p| p.is_finished())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn iterations(&self) -> usize {
+        self.projection.as_ref().map_or(0, |p| p.iterations())
+    }
+
+    /// Upper bound on iterations, for a progress bar (0 when the blend
+    /// needs no repair)
+    #[wasm_bindgen(getter)]
+    pub fn max_iterations(&self) -> usize {
+        self.projection.as_ref().map_or(0, |p| p.max_iterations())
+    }
+
+    /// Completes any remaining iterations, then Steps 5–6.
+    pub fn finish(&mut self) -> Result<EngineResult, EngineError> {
+        guard(|| {
+            let (projection, blended) = (&mut self.projection, &self.blended);
+            let (pd, report) = timed(&mut self.timings.repair_ms, || match projection.as_mut() {
+                Some(projection) => {
+                    projection.step(usize::MAX);
+                    let pd = projection.result();
+                    let report = RepairReport::new(blended, &pd, Some(projection));
+                    (pd, report)
+                }
+                None => already_pd(blended),
+            });
+            let market = finish_market(self.drift.clone(), self.vol.clone(), pd, report, self.timings)?;
+            let (lambda, mean, vol) = self.jumps.clone();
+            Ok(pack_result(&market, lambda, mean, vol))
+        })
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Engine — stateful Phase A for interactive sliders
+// Holds the base market and the last computed stages; each setter
+// marks only what depends on it as stale, and recompute() redoes just
+// those stages:
+//     Δμ           → Step 1 only
+//     vol mult.    → Steps 2, 5–6 (the repaired R is kept; at large N
+//                    a few assets' changes update L in place)
+//     skew         → Steps 3–6
+//     jumps        → nothing (packed as-is)
+// Setters that leave a value unchanged invalidate nothing, and with
+// set_cache_capacity a stale stage whose inputs were seen recently is
+// restored rather than redone (see StageCache). Stages are
+// rebuilt in the Engine's Workspace, so steady slider traffic reuses
+// the same f64 buffers.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct Engine {
+    config: ShockConfig,
+    base_correlation: DMatrix<f64>,
+    /// Steps 3–4 output for the current skew
+    correlation: Option<(DMatrix<f64>, RepairReport)>,
+    /// Steps 1–6 output; drift may lag behind `drift_stale`
+    market: Option<ShockedMarket>,
+    drift_stale: bool,
+    /// strict::enabled() when the stages above were built
+    strict: bool,
+    workspace: Workspace,
+    cache: StageCache,
+    /// Counted in memory_stats().engine_bytes
+    held: Held,
+}
+
+/// Buffers recompute() refills instead of allocating: the f64 shock
+/// inputs, the blend, Σ, and the last invalidated market, whose drift,
+/// vol, R and L are overwritten by the next rebuild. Only the f32
+/// arrays handed to JS are allocated per call.
+struct Workspace {
+    base_drift: DVector<f64>,
+    base_vol: DVector<f64>,
+    delta_drift: DVector<f64>,
+    vol_multiplier: DVector<f64>,
+    blended: DMatrix<f64>,
+    covariance: DMatrix<f64>,
+    /// New vol / old vol per asset, for rescale_cholesky
+    scale: DVector<f64>,
+    spare: Option<ShockedMarket>,
+}
+
+impl Workspace {
+    fn new(config: &ShockConfig) -> Self {
+        let n = config.num_assets();
+        Workspace {
+            base_drift: to_dvector(&config.base_drift),
+            base_vol: to_dvector(&config.base_vol),
+            delta_drift: DVector::zeros(n),
+            vol_multiplier: DVector::zeros(n),
+            blended: DMatrix::zeros(n, n),
+            covariance: DMatrix::zeros(n, n),
+            scale: DVector::zeros(n),
+            spare: None,
+        }
+    }
+
+    /// Steps 1–2 and 5–6 for the repaired `pd`, in the spare market's
+    /// buffers; like finish_market, except that when only vols moved
+    /// (`same_correlation`) and the spare's L needed no ridge, L is
+    /// carried over by math::rescale_cholesky (kN flops against N³/3).
+    fn finish(
+        &mut self,
+        pd: &DMatrix<f64>,
+        repair: RepairReport,
+        same_correlation: bool,
+        mut timings: Timings,
+    ) -> Result<ShockedMarket, EngineError> {
+        let n = self.base_drift.len();
+        let mut market = self.take_spare(repair, timings);
+        let rescale = same_correlation
+            && market.ridge_jitter == 0.0
+            && market.cholesky_l.shape() == (n, n);
+        if rescale {
+            self.scale.copy_from(&market.vol);
+        }
+        timed(&mut timings.blend_ms, || {
+            math::adjust_drift_into(&self.base_drift, &self.delta_drift, &mut market.drift);
+            math::adjust_vol_into(&self.base_vol, &self.vol_multiplier, &mut market.vol);
+        });
+        market.correlation.copy_from(pd);
+        market.repair = repair;
+        timed(&mut timings.covariance_ms, || math::rebuild_covariance_into(&market.vol, pd, &mut self.covariance));
+        if rescale {
+            for (scale, &vol) in self.scale.iter_mut().zip(market.vol.iter()) {
+                *scale = vol / *scale;
+            }
+            let start = now_ms();
+            if math::rescale_cholesky(&mut market.cholesky_l, &self.scale).is_ok() {
+                timings.cholesky_ms += now_ms() - start;
+                market.ridge_jitter = 0.0;
+                market.timings = timings;
+                return Ok(market);
+            }
+            timings.cholesky_ms += now_ms() - start;
+        }
+        let factored = timed(&mut timings.cholesky_ms, || {
+            math::cholesky_with_jitter_into(&self.covariance, &mut market.cholesky_l)
+        });
+        market.timings = timings;
+        match factored {
+            Ok(jitter) => {
+                market.ridge_jitter = jitter;
+                Ok(market)
+            }
+            Err(_) => {
+                let min_eigenvalue = self.covariance.clone().symmetric_eigen().eigenvalues.min();
+                // No usable factor to rescale from next time
+                market.cholesky_l = DMatrix::zeros(0, 0);
+                self.spare = Some(market);
+                Err(EngineError::NotPositiveDefinite { min_eigenvalue })
+            }
+        }
+    }
+
+    /// Step 1 only, with vol and L taken from a cached factor (see
+    /// StageCache); the same market finish() built for these inputs.
+    fn restore(&mut self, pd: &DMatrix<f64>, repair: RepairReport, factor: &Factor, mut timings: Timings) -> ShockedMarket {
+        let n = self.base_drift.len();
+        let mut market = self.take_spare(repair, timings);
+        timed(&mut timings.blend_ms, || {
+            math::adjust_drift_into(&self.base_drift, &self.delta_drift, &mut market.drift)
+        });
+        market.vol.copy_from(&factor.vol);
+        if market.cholesky_l.shape() != (n, n) {
+            market.cholesky_l = DMatrix::zeros(n, n);
+        }
+        market.cholesky_l.copy_from(&factor.cholesky_l);
+        market.ridge_jitter = factor.ridge_jitter;
+        market.correlation.copy_from(pd);
+        market.repair = repair;
+        market.timings = timings;
+        market
+    }
+
+    /// Reallocate the N×N scratch after Engine::release_buffers
+    fn reserve(&mut self) {
+        let n = self.base_drift.len();
+        for m in [&mut self.blended, &mut self.covariance] {
+            if m.shape() != (n, n) {
+                *m = DMatrix::zeros(n, n);
+            }
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        let vectors = [&self.base_drift, &self.base_vol, &self.delta_drift, &self.vol_multiplier, &self.scale];
+        vectors.iter().map(|v| size_of_val(v.as_slice())).sum::<usize>()
+            + size_of_val(self.blended.as_slice())
+            + size_of_val(self.covariance.as_slice())
+            + self.spare.as_ref().map_or(0, market_bytes)
+    }
+
+    fn take_spare(&mut self, repair: RepairReport, timings: Timings) -> ShockedMarket {
+        let n = self.base_drift.len();
+        self.spare.take().unwrap_or_else(|| ShockedMarket {
+            drift: DVector::zeros(n),
+            vol: DVector::zeros(n),
+            cholesky_l: DMatrix::zeros(n, n),
+            ridge_jitter: 0.0,
+            correlation: DMatrix::zeros(n, n),
+            repair,
+            timings,
+        })
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// StageCache — opt-in memo of the Engine's expensive stages
+// Entries are keyed by a hash of the stage's effective inputs, as the
+// f32 bits that were set (plus the strict-mode flag), and keep those
+// inputs so a collision is a miss rather than a wrong result:
+//     Steps 3–4 (repaired R)      ← skew
+//     Steps 2, 5–6 (vol, L)       ← skew, vol multipliers
+// Δμ and the jumps feed neither, so dragging them never refactors, and
+// returning a slider to an earlier value reuses its R and L instead of
+// redoing Higham and Cholesky. Each stage keeps at most `capacity`
+// entries, least recently used evicted first; an entry holds N² f64s
+// (8 MB at N = 1000), which is why the cache is off by default.
+// ────────────────────────────────────────────────────────────────
+#[derive(Default)]
+struct StageCache {
+    capacity: usize,
+    correlations: Memo<(DMatrix<f64>, RepairReport)>,
+    factors: Memo<Factor>,
+    hits: u32,
+}
+
+/// Steps 2, 5–6 output
+struct Factor {
+    vol: DVector<f64>,
+    cholesky_l: DMatrix<f64>,
+    ridge_jitter: f64,
+}
+
+impl StageCache {
+    fn bytes(&self) -> usize {
+        let correlations = self.correlations.0.iter().map(|(_, key, (pd, _))| size_of_val(key.as_slice()) + size_of_val(pd.as_slice()));
+        let factors = self.factors.0.iter().map(|(_, key, f)| {
+            size_of_val(key.as_slice()) + size_of_val(f.vol.as_slice()) + size_of_val(f.cholesky_l.as_slice())
+        });
+        correlations.chain(factors).sum()
+    }
+}
+
+/// (hash, inputs, output), most recently used last
+struct Memo<V>(Vec<(u64, Vec<u32>, V)>);
+
+impl<V> Default for Memo<V> {
+    fn default() -> Self {
+        Memo(Vec::new())
+    }
+}
+
+impl<V> Memo<V> {
+    fn get(&mut self, key: &[u32]) -> Option<&V> {
+        let hash = input_hash(key);
+        let i = self.0.iter().position(|(h, k, _)| *h == hash && k == key)?;
+        let entry = self.0.remove(i);
+        self.0.push(entry);
+        self.0.last().map(|(_, _, v)| v)
+    }
+
+    /// `value` is only built when the memo has room for it
+    fn put(&mut self, capacity: usize, key: Vec<u32>, value: impl FnOnce() -> V) {
+        if capacity > 0 {
+            self.0.push((input_hash(&key), key, value()));
+            self.truncate(capacity);
+        }
+    }
+
+    fn truncate(&mut self, capacity: usize) {
+        let excess = self.0.len().saturating_sub(capacity);
+        self.0.drain(..excess);
+    }
+}
+
+fn input_hash(key: &[u32]) -> u64 {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Strict mode, then skew and vol multipliers as bits
+fn stage_key(correlation_skew: f32, vol_multiplier: &[f32]) -> Vec<u32> {
+    let inputs = std::iter::once(correlation_skew).chain(vol_multiplier.iter().copied()).map(f32::to_bits);
+    std::iter::once(strict::enabled() as u32).chain(inputs).collect()
+}
+
+/// f32 input into an f64 buffer of the same length
+fn fill(dst: &mut DVector<f64>, src: &[f32]) {
+    for (d, &x) in dst.iter_mut().zip(src) {
+        *d = x as f64;
+    }
+}
+
+#[wasm_bindgen]
+impl Engine {
+    #[wasm_bindgen(constructor)]
+    pub fn new(base_drift: &[f32], base_vol: &[f32], base_correlation: &[f32]) -> Result<Engine, EngineError> {
+        guard(|| {
+            Engine::from_config(&ShockConfig::new(base_drift, base_vol, base_correlation))
+        })
+    }
+
+    /// Start from a full scenario rather than "no shock".
+    pub fn from_config(config: &ShockConfig) -> Result<Engine, EngineError> {
+        guard(|| {
+            let n = infer_num_assets(&config.base_drift, &config.base_correlation)?;
+            check_lengths(&[
+                ("base_vol", n, config.base_vol.len()),
+                ("base_correlation", n * n, config.base_correlation.len()),
+            ])?;
+            Ok(Engine {
+                config: config.clone(),
+                base_correlation: base_correlation_matrix(n, &config.base_correlation, config.correlation_tolerance)?,
+                correlation: None,
+                market: None,
+                drift_stale: true,
+                strict: strict::enabled(),
+                workspace: Workspace::new(config),
+                cache: StageCache::default(),
+                held: Held::new(&ENGINE_BYTES, 0),
+            })
+        })
+    }
+
+    /// The current scenario, e.g. to save with to_json()
+    #[wasm_bindgen(getter)]
+    pub fn config(&self) -> ShockConfig {
+        self.config.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn num_assets(&self) -> usize {
+        self.config.num_assets()
+    }
+
+    pub fn set_delta_drift(&mut self, delta_drift: &[f32]) {
+        if self.config.delta_drift != delta_drift {
+            self.config.set_delta_drift(delta_drift);
+            self.drift_stale = true;
+        }
+    }
+
+    pub fn set_vol_multiplier(&mut self, vol_multiplier: &[f32]) {
+        if self.config.vol_multiplier != vol_multiplier {
+            self.config.set_vol_multiplier(vol_multiplier);
+            self.workspace.spare = self.market.take();
+        }
+    }
+
+    pub fn set_correlation_skew(&mut self, correlation_skew: f32) {
+        if self.config.correlation_skew != correlation_skew {
+            self.config.set_correlation_skew(correlation_skew);
+            self.correlation = None;
+            self.workspace.spare = self.market.take();
+        }
+    }
+
+    pub fn set_jumps(&mut self, jump_lambda: f32, jump_mean: f32, jump_vol: f32) {
+        self.config.set_jumps(jump_lambda, jump_mean, jump_vol);
+    }
+
+    pub fn set_asset_jumps(&mut self, jump_lambda: &[f32], jump_mean: &[f32], jump_vol: &[f32]) {
+        self.config.set_asset_jumps(jump_lambda, jump_mean, jump_vol);
+    }
+
+    /// Remember up to `entries` past repaired correlations and factors
+    /// (see StageCache); 0, the default, turns the cache off and frees
+    /// it. Results are unchanged either way.
+    pub fn set_cache_capacity(&mut self, entries: usize) {
+        let cache = &mut self.cache;
+        cache.capacity = entries;
+        cache.correlations.truncate(entries);
+        cache.factors.truncate(entries);
+        self.update_held();
+    }
+
+    /// Free the cache, the invalidated market and the N×N scratch;
+    /// the next recompute() rebuilds from Step 1 and reallocates what
+    /// it needs. The cache capacity is kept, and EngineResults already
+    /// returned keep their arrays. Freed memory goes back to the
+    /// allocator, not the browser: wasm linear memory never shrinks, but
+    /// later allocations reuse it.
+    pub fn release_buffers(&mut self) {
+        self.cache.correlations = Memo::default();
+        self.cache.factors = Memo::default();
+        self.correlation = None;
+        self.market = None;
+        let ws = &mut self.workspace;
+        ws.spare = None;
+        ws.blended = DMatrix::zeros(0, 0);
+        ws.covariance = DMatrix::zeros(0, 0);
+        self.update_held();
+    }
+
+    /// Stages served from the cache so far
+    #[wasm_bindgen(getter)]
+    pub fn cache_hits(&self) -> u32 {
+        self.cache.hits
+    }
+
+    /// Same output as compute_shock_config(engine.config), redoing only
+    /// the stages invalidated since the last call.
+    pub fn recompute(&mut self) -> Result<EngineResult, EngineError> {
+        guard(|| {
+            let result = self.rebuild();
+            self.update_held();
+            result
+        })
+    }
+}
+
+impl Engine {
+    fn rebuild(&mut self) -> Result<EngineResult, EngineError> {
+        if self.strict != strict::enabled() {
+            self.strict = strict::enabled();
+            self.correlation = None;
+            self.workspace.spare = self.market.take();
+        }
+        let c = &self.config;
+        let n = c.num_assets();
+        check_lengths(&[("delta_drift", n, c.delta_drift.len()), ("vol_multiplier", n, c.vol_multiplier.len())])?;
+        c.check_finite()?;
+        let jump_lambda = broadcast(&c.jump_lambda, n)?;
+        let jump_mean = broadcast(&c.jump_mean, n)?;
+        let jump_vol = broadcast(&c.jump_vol, n)?;
+        let ws = &mut self.workspace;
+        fill(&mut ws.delta_drift, &c.delta_drift);
+
+        let mut timings = Timings::default();
+        let market = match self.market.take() {
+            Some(mut market) => {
+                if self.drift_stale {
+                    timed(&mut timings.blend_ms, || {
+                        math::adjust_drift_into(&ws.base_drift, &ws.delta_drift, &mut market.drift)
+                    });
+                }
+                market.timings = timings;
+                market
+            }
+            None => {
+                ws.reserve();
+                let cache = &mut self.cache;
+                let same_correlation = self.correlation.is_some();
+                let skew_key = stage_key(c.correlation_skew, &[]);
+                let (pd, report) = match self.correlation.take() {
+                    Some(repaired) => repaired,
+                    None => match cache.correlations.get(&skew_key) {
+                        Some(repaired) => {
+                            cache.hits += 1;
+                            repaired.clone()
+                        }
+                        None => {
+                            timed(&mut timings.blend_ms, || {
+                                math::blend_correlation_into(&self.base_correlation, c.correlation_skew as f64, &mut ws.blended)
+                            });
+                            let (pd, report) = timed(&mut timings.repair_ms, || repair_correlation(&ws.blended));
+                            cache.correlations.put(cache.capacity, skew_key, || (pd.clone(), report));
+                            (pd, report)
+                        }
+                    },
+                };
+                fill(&mut ws.vol_multiplier, &c.vol_multiplier);
+                let factor_key = stage_key(c.correlation_skew, &c.vol_multiplier);
+                let market = match cache.factors.get(&factor_key) {
+                    Some(factor) => {
+                        cache.hits += 1;
+                        Ok(ws.restore(&pd, report, factor, timings))
+                    }
+                    None => ws.finish(&pd, report, same_correlation, timings).inspect(|market| {
+                        cache.factors.put(cache.capacity, factor_key, || Factor {
+                            vol: market.vol.clone(),
+                            cholesky_l: market.cholesky_l.clone(),
+                            ridge_jitter: market.ridge_jitter,
+                        })
+                    }),
+                };
+                self.correlation = Some((pd, report));
+                market?
+            }
+        };
+        self.drift_stale = false;
+        let result = pack_result(&market, jump_lambda, jump_mean, jump_vol);
+        self.market = Some(market);
+        Ok(result)
+    }
+
+    fn update_held(&mut self) {
+        let stages = self.correlation.as_ref().map_or(0, |(pd, _)| size_of_val(pd.as_slice()))
+            + self.market.as_ref().map_or(0, market_bytes);
+        self.held.set(size_of_val(self.base_correlation.as_slice()) + stages + self.workspace.bytes() + self.cache.bytes());
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// run_pipeline — shared Phase A steps once the base R is assembled
+// ════════════════════════════════════════════════════════════════
+#[allow(clippy::too_many_arguments)]
+fn run_pipeline(
+    n: usize,
+    base_drift: &[f32],
+    base_vol: &[f32],
+    base_corr_m: &DMatrix<f64>,
+    delta_drift: &[f32],
+    vol_multiplier: &[f32],
+    correlation_skew: f32,
+    repair: bool,
+    (jump_lambda, jump_mean, jump_vol): (&[f32], &[f32], &[f32]),
+) -> Result<EngineResult, EngineError> {
+    check_lengths(&[
+        ("base_drift", n, base_drift.len()),
+        ("base_vol", n, base_vol.len()),
+        ("delta_drift", n, delta_drift.len()),
+        ("vol_multiplier", n, vol_multiplier.len()),
+    ])?;
+    let jump_lambda = broadcast(jump_lambda, n)?;
+    let jump_mean = broadcast(jump_mean, n)?;
+    let jump_vol = broadcast(jump_vol, n)?;
+
+    let market = shock_market(
+        &to_dvector(base_drift),
+        &to_dvector(base_vol),
+        base_corr_m,
+        &to_dvector(delta_drift),
+        &to_dvector(vol_multiplier),
+        correlation_skew as f64,
+        repair,
+    )?;
+    Ok(pack_result(&market, jump_lambda, jump_mean, jump_vol))
+}
+
+fn pack_result(market: &ShockedMarket, jump_lambda: Vec<f32>, jump_mean: Vec<f32>, jump_vol: Vec<f32>) -> EngineResult {
+    let n = market.drift.len();
+
+    // ── Pack results as flattened f32 arrays ─────────────────────
+    let adj_drift_f32: Vec<f32> = market.drift.iter().map(|&x| x as f32).collect();
+    let adj_vol_f32: Vec<f32> = market.vol.iter().map(|&x| x as f32).collect();
+
+    // Flatten L in row-major for GPU uniform upload
+    let mut cholesky_f32 = Vec::with_capacity(n * n);
+    for i in 0..n {
+        for j in 0..n {
+            cholesky_f32.push(market.cholesky_l[(i, j)] as f32);
+        }
+    }
+    let correlation_f32: Vec<f32> = market.correlation.transpose().iter().map(|&x| x as f32).collect();
+
+    EngineResult {
+        adjusted_drift: adj_drift_f32,
+        adjusted_vol: adj_vol_f32,
+        cholesky_l: cholesky_f32,
+        adjusted_correlation: correlation_f32,
+        covariance: OnceCell::new(),
+        diagnostics: Diagnostics {
+            higham_iterations: market.repair.iterations as u32,
+            higham_converged: market.repair.converged,
+            min_eigenvalue_before: market.repair.min_eigenvalue_before.map(|x| x as f32),
+            min_eigenvalue_after: market.repair.min_eigenvalue_after.map(|x| x as f32),
+            frobenius_distance: market.repair.distance as f32,
+            ridge_jitter: market.ridge_jitter as f32,
+        },
+        num_assets: n,
+        jump_lambda,
+        jump_mean,
+        jump_vol,
+        ridge_jitter: market.ridge_jitter as f32,
+        timings: market.timings,
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// shock_market — the Phase A math pipeline in f64, no JS types
+// Public for native consumers (mssim-cli); the wasm entry points above
+// are thin wrappers that add validation and f32 packing.
+// ════════════════════════════════════════════════════════════════
+pub struct ShockedMarket {
+    pub drift: DVector<f64>,
+    pub vol: DVector<f64>,
+    pub cholesky_l: DMatrix<f64>,
+    pub ridge_jitter: f64,
+    /// Correlation after blend and repair (Step 4 output)
+    pub correlation: DMatrix<f64>,
+    pub repair: RepairReport,
+    pub timings: Timings,
+}
+
+pub fn shock_market(
+    base_drift: &DVector<f64>,
+    base_vol: &DVector<f64>,
+    base_corr: &DMatrix<f64>,
+    delta_drift: &DVector<f64>,
+    vol_multiplier: &DVector<f64>,
+    correlation_skew: f64,
+    repair: bool,
+) -> Result<ShockedMarket, EngineError> {
+    let mut timings = Timings::default();
+    let (adj_drift, adj_vol, blended) = timed(&mut timings.blend_ms, || {
+        // Step 1: Adjust drift
+        let adj_drift = math::adjust_drift(base_drift, delta_drift);
+
+        // Step 2: Adjust volatility
+        let adj_vol = math::adjust_vol(base_vol, vol_multiplier);
+
+        // Step 3: Blend correlation toward crisis mode
+        (adj_drift, adj_vol, math::blend_correlation(base_corr, correlation_skew))
+    });
+
+    // Step 4: Project to nearest positive-definite (Higham), unless it
+    // already is
+    let (pd, report) = if repair {
+        timed(&mut timings.repair_ms, || repair_correlation(&blended))
+    } else {
+        let report = RepairReport::new(&blended, &blended, None);
+        (blended, report)
+    };
+
+    finish_market(adj_drift, adj_vol, pd, report, timings)
+}
+
+/// Step 4 behind the math::is_pd pre-check: a blend whose λ_min clears
+/// REPAIR_SKIP_MIN_EIGENVALUE is what nearest_pd would hand back (to
+/// rounding), so only a PD-failing blend runs the Higham loop.
+fn repair_correlation(blended: &DMatrix<f64>) -> (DMatrix<f64>, RepairReport) {
+    if math::is_pd(blended, REPAIR_SKIP_MIN_EIGENVALUE) {
+        return already_pd(blended);
+    }
+    let mut projection = math::HighamProjection::new(blended, None);
+    projection.step(usize::MAX);
+    let pd = projection.result();
+    let report = RepairReport::new(blended, &pd, Some(&projection));
+    (pd, report)
+}
+
+/// A PD blend as nearest_pd's U-projection leaves it: symmetrized,
+/// unit diagonal, no iterations. λ_min is left uncomputed; the caller
+/// already knows it clears REPAIR_SKIP_MIN_EIGENVALUE.
+fn already_pd(blended: &DMatrix<f64>) -> (DMatrix<f64>, RepairReport) {
+    let mut r = (blended + blended.transpose()) * 0.5;
+    r.fill_diagonal(1.0);
+    let report = RepairReport {
+        iterations: 0,
+        converged: true,
+        min_eigenvalue_before: None,
+        min_eigenvalue_after: None,
+        distance: (&r - blended).norm(),
+    };
+    (r, report)
+}
+
+/// Step 4 as it happened, for EngineResult.diagnostics
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RepairReport {
+    pub iterations: usize,
+    pub converged: bool,
+    /// None when the is_pd pre-check skipped the repair, so no
+    /// eigensolve was spent (λ_min is then above 1e-8)
+    pub min_eigenvalue_before: Option<f64>,
+    pub min_eigenvalue_after: Option<f64>,
+    /// ‖R_repaired − R_blended‖_F
+    pub distance: f64,
+}
+
+impl RepairReport {
+    /// `projection` is None when Higham was not run (the matrix is used as is).
+    fn new(blended: &DMatrix<f64>, pd: &DMatrix<f64>, projection: Option<&math::HighamProjection>) -> Self {
+        let min_eigenvalue = |m: &DMatrix<f64>| {
+            let sym = (m + m.transpose()) * 0.5;
+            sym.symmetric_eigenvalues().iter().copied().fold(f64::INFINITY, f64::min)
+        };
+        let before = min_eigenvalue(blended);
+        RepairReport {
+            iterations: projection.map_or(0, |p| p.iterations()),
+            converged: projection.is_none_or(|p| p.converged()),
+            min_eigenvalue_before: Some(before),
+            min_eigenvalue_after: Some(if projection.is_some() { min_eigenvalue(pd) } else { before }),
+            distance: (pd - blended).norm(),
+        }
+    }
+}
+
+/// Steps 5–6 on an already repaired correlation matrix; `timings`
+/// holds Steps 1–4's.
+fn finish_market(
+    adj_drift: DVector<f64>,
+    adj_vol: DVector<f64>,
+    pd: DMatrix<f64>,
+    repair: RepairReport,
+    mut timings: Timings,
+) -> Result<ShockedMarket, EngineError> {
+    // Step 5: Rebuild covariance Σ = D·R·D
+    let cov = timed(&mut timings.covariance_ms, || math::rebuild_covariance(&adj_vol, &pd));
+
+    // Step 6: Cholesky decomposition (with minimal ridge if near-singular)
+    let (l, jitter) = timed(&mut timings.cholesky_ms, || math::cholesky_with_jitter(&cov)).map_err(|_| {
+        EngineError::NotPositiveDefinite { min_eigenvalue: cov.clone().symmetric_eigen().eigenvalues.min() }
+    })?;
+
+    Ok(ShockedMarket {
+        drift: adj_drift,
+        vol: adj_vol,
+        cholesky_l: l,
+        ridge_jitter: jitter,
+        correlation: pd,
+        repair,
+        timings,
+    })
+}
+
+// ────────────────────────────────────────────────────────────────
+// BatchCorrelation — Steps 3–4 shared across a batch
+// J = 11ᵀ is PSD, so for 0 ≤ s ≤ 1, λ_min((1 − s)·R + s·J) ≥
+// (1 − s)·λ_min(R). When that bound clears the PSD projection's floor
+// the blend is already PD and nearest_pd would hand it back unchanged
+// (unit diagonal, symmetrized), so one eigendecomposition of R
+// replaces the Higham loop for every such scenario. Skews outside
+// [0, 1] subtract a PSD term, have no such bound and always repair.
+// ────────────────────────────────────────────────────────────────
+const REPAIR_SKIP_MIN_EIGENVALUE: f64 = 1e-8;
+
+struct BatchCorrelation {
+    base: DMatrix<f64>,
+    min_eigenvalue: f64,
+    repaired: Vec<(f64, DMatrix<f64>, RepairReport)>,
+}
+
+impl BatchCorrelation {
+    fn new(base: &DMatrix<f64>) -> Self {
+        let base = (base + base.transpose()) * 0.5;
+        let min_eigenvalue = base.clone().symmetric_eigenvalues().min();
+        BatchCorrelation { base, min_eigenvalue, repaired: Vec::new() }
+    }
+
+    fn repaired(&mut self, skew: f64) -> (DMatrix<f64>, RepairReport) {
+        if let Some((_, r, report)) = self.repaired.iter().find(|(s, _, _)| *s == skew) {
+            return (r.clone(), *report);
+        }
+        let blended = math::blend_correlation(&self.base, skew);
+        let (r, report) = if (0.0..=1.0).contains(&skew) && (1.0 - skew) * self.min_eigenvalue > REPAIR_SKIP_MIN_EIGENVALUE {
+            already_pd(&blended)
+        } else {
+            repair_correlation(&blended)
+        };
+        self.repaired.push((skew, r.clone(), report));
+        (r, report)
+    }
+}
+
+/// Default slack for the base correlation checks: f32 round-off only.
+const CORRELATION_TOLERANCE: f32 = 1e-6;
+
+/// Row-major N×N base correlation in f64, rejected with the offending
+/// (i, j) unless math::validate_correlation accepts it.
+fn base_correlation_matrix(n: usize, r: &[f32], tolerance: f32) -> Result<DMatrix<f64>, EngineError> {
+    if !(tolerance.is_finite() && tolerance >= 0.0) {
+        return Err(EngineError::InvalidInput {
+            reason: "Correlation input invalid: tolerance must be finite and non-negative",
+        });
+    }
+    check_lengths(&[("base_correlation", n * n, r.len())])?;
+    check_finite(&[("base_correlation", r)])?;
+    let m = DMatrix::from_row_iterator(n, n, r.iter().map(|&x| x as f64));
+    math::validate_correlation(&m, tolerance as f64)
+        .map_err(|d| EngineError::InvalidCorrelation { row: d.row, col: d.col, reason: d.reason })?;
+    Ok(m)
+}
+
+/// Length-1 input repeated N times; length-N input passed through.
+fn broadcast(xs: &[f32], n: usize) -> Result<Vec<f32>, &'static str> {
+    match xs.len() {
+        1 => Ok(vec![xs[0]; n]),
+        len if len == n => Ok(xs.to_vec()),
+        _ => Err("Input length mismatch: per-asset parameters must have length 1 or N"),
+    }
+}
+
+/// Notionals (currency amounts) when given, otherwise unit-notional weights.
+fn to_portfolio(weights: &[f32], notionals: &[f32]) -> Result<portfolio::Portfolio, &'static str> {
+    if notionals.is_empty() {
+        portfolio::Portfolio::from_weights(weights.iter().map(|&w| w as f64).collect(), 1.0)
+    } else {
+        portfolio::Portfolio::from_notionals(&notionals.iter().map(|&x| x as f64).collect::<Vec<_>>())
+    }
+}
+
+fn to_dvector(xs: &[f32]) -> DVector<f64> {
+    DVector::from_iterator(xs.len(), xs.iter().map(|&x| x as f64))
+}
+
+// ════════════════════════════════════════════════════════════════
+// interpolate_correlation — SPD-geodesic frame for UI transitions
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub fn interpolate_correlation(
+    num_assets: usize,
+    r0: &[f32],
+    r1: &[f32],
+    t: f32,
+) -> Result<Float32Array, EngineError> {
+    guard(|| {
+        let n = num_assets;
+        check_lengths(&[
+            ("r0", n * n, r0.len()),
+            ("r1", n * n, r1.len()),
+        ])?;
+
+        let a: Vec<f64> = r0.iter().map(|&x| x as f64).collect();
+        let b: Vec<f64> = r1.iter().map(|&x| x as f64).collect();
+        let rt = math::interpolate_correlation(
+            &DMatrix::from_row_slice(n, n, &a),
+            &DMatrix::from_row_slice(n, n, &b),
+            t as f64,
+        )?;
+
+        // Row-major, matching the input layout
+        let mut out = Vec::with_capacity(n * n);
+        for i in 0..n {
+            for j in 0..n {
+                out.push(rt[(i, j)] as f32);
+            }
+        }
+        Ok(Float32Array::from(out.as_slice()))
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// validate_correlation — the base-correlation check on its own
+// Every entry point taking a base correlation runs it (tolerance 1e-6,
+// or ShockConfig's); this lets the UI flag a bad upload before use.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub fn validate_correlation(num_assets: usize, correlation: &[f32], tolerance: f32) -> Result<(), EngineError> {
+    guard(|| {
+        base_correlation_matrix(num_assets, correlation, tolerance).map(|_| ())
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// interpolate_shock — tween between two scenarios for animation
+// Shock parameters and the base drift / vol move linearly in t; when
+// the base correlations differ they follow the SPD geodesic (falling
+// back to a linear blend if an endpoint is not PD). Every frame runs
+// through the full pipeline, so each intermediate R is repaired and
+// factorable like any other input.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub fn interpolate_shock(config_a: &ShockConfig, config_b: &ShockConfig, t: f32) -> Result<ShockConfig, EngineError> {
+    guard(|| {
+        let n = config_a.num_assets();
+        check_lengths(&[
+            ("config_b.base_drift", n, config_b.num_assets()),
+            ("config_a.base_correlation", n * n, config_a.base_correlation.len()),
+            ("config_b.base_correlation", n * n, config_b.base_correlation.len()),
+        ])?;
+        if !(0.0..=1.0).contains(&t) {
+            return Err(EngineError::InvalidInput { reason: "Interpolation input invalid: t must be in [0, 1]" });
+        }
+        let lerp = |a: &[f32], b: &[f32]| -> Result<Vec<f32>, EngineError> {
+            let (a, b) = (broadcast(a, n)?, broadcast(b, n)?);
+            Ok(a.iter().zip(&b).map(|(&x, &y)| x + (y - x) * t).collect())
+        };
+
+        let base_correlation = if config_a.base_correlation == config_b.base_correlation {
+            config_a.base_correlation.clone()
+        } else {
+            let to_matrix = |r: &[f32]| DMatrix::from_row_slice(n, n, &r.iter().map(|&x| x as f64).collect::<Vec<_>>());
+            let (r0, r1) = (to_matrix(&config_a.base_correlation), to_matrix(&config_b.base_correlation));
+            let rt = math::interpolate_correlation(&r0, &r1, t as f64).unwrap_or_else(|_| &r0 * (1.0 - t as f64) + &r1 * t as f64);
+            rt.transpose().iter().map(|&x| x as f32).collect()
+        };
+
+        Ok(ShockConfig {
+            base_drift: lerp(&config_a.base_drift, &config_b.base_drift)?,
+            base_vol: lerp(&config_a.base_vol, &config_b.base_vol)?,
+            base_correlation,
+            delta_drift: lerp(&config_a.delta_drift, &config_b.delta_drift)?,
+            vol_multiplier: lerp(&config_a.vol_multiplier, &config_b.vol_multiplier)?,
+            correlation_skew: config_a.correlation_skew + (config_b.correlation_skew - config_a.correlation_skew) * t,
+            jump_lambda: lerp(&config_a.jump_lambda, &config_b.jump_lambda)?,
+            jump_mean: lerp(&config_a.jump_mean, &config_b.jump_mean)?,
+            jump_vol: lerp(&config_a.jump_vol, &config_b.jump_vol)?,
+            correlation_tolerance: config_a.correlation_tolerance,
+        })
+    })
+}
+
+/// The EngineResult for frame t of interpolate_shock.
+#[wasm_bindgen]
+pub fn interpolate_shock_result(config_a: &ShockConfig, config_b: &ShockConfig, t: f32) -> Result<EngineResult, EngineError> {
+    guard(|| {
+        compute_shock_config(&interpolate_shock(config_a, config_b, t)?)
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Stress presets — named scenarios resolved against asset classes
+// The fields map one-to-one onto compute_shock's shock arguments.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct ScenarioPreset {
+    id: String,
+    name: String,
+    delta_drift: Vec<f32>,
+    vol_multiplier: Vec<f32>,
+    correlation_skew: f32,
+    jump_lambda: f32,
+    jump_mean: f32,
+    jump_vol: f32,
+}
+
+#[wasm_bindgen]
+impl ScenarioPreset {
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn delta_drift(&self) -> Float32Array {
+        Float32Array::from(self.delta_drift.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn vol_multiplier(&self) -> Float32Array {
+        Float32Array::from(self.vol_multiplier.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn correlation_skew(&self) -> f32 {
+        self.correlation_skew
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn jump_lambda(&self) -> f32 {
+        self.jump_lambda
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn jump_mean(&self) -> f32 {
+        self.jump_mean
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn jump_vol(&self) -> f32 {
+        self.jump_vol
+    }
+}
+
+/// Preset ids accepted by stress_preset
+#[wasm_bindgen]
+pub fn preset_ids() -> Vec<String> {
+    presets::Preset::ALL.iter().map(|p| p.id().to_string()).collect()
+}
+
+/// `asset_classes` holds one class id per asset (equities, bonds,
+/// commodities, real_estate, cash); severity 1 is the preset as shipped.
+#[wasm_bindgen]
+pub fn stress_preset(preset: &str, asset_classes: Vec<String>, severity: f32) -> Result<ScenarioPreset, EngineError> {
+    guard(|| {
+        let p = presets::Preset::from_name(preset)?;
+        let classes = asset_classes
+            .iter()
+            .map(|c| presets::AssetClass::from_name(c))
+            .collect::<Result<Vec<_>, _>>()?;
+        let s = p.shock(&classes, severity as f64)?;
+        let to_f32 = |xs: &[f64]| xs.iter().map(|&x| x as f32).collect::<Vec<_>>();
+        Ok(ScenarioPreset {
+            id: p.id().to_string(),
+            name: p.name().to_string(),
+            delta_drift: to_f32(&s.delta_drift),
+            vol_multiplier: to_f32(&s.vol_multiplier),
+            correlation_skew: s.correlation_skew as f32,
+            jump_lambda: s.jump_lambda as f32,
+            jump_mean: s.jump_mean as f32,
+            jump_vol: s.jump_vol as f32,
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// SimulationOptions — JS-side builder for simulate::SimConfig
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct SimulationOptions {
+    config: simulate::SimConfig,
+}
+
+#[wasm_bindgen]
+impl SimulationOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new(horizon: f32, steps: usize, n_paths: usize, seed: u64) -> SimulationOptions {
+        SimulationOptions {
+            config: simulate::SimConfig::new(horizon as f64, steps, n_paths, seed),
+        }
+    }
+
+    /// Market-wide jumps: one shared Poisson count per step, each asset
+    /// taking β_i × the common log-jump. λ ≤ 0 clears them.
+    pub fn set_systemic_jumps(
+        &mut self,
+        jump_lambda: f32,
+        jump_mean: f32,
+        jump_vol: f32,
+        betas: &[f32],
+    ) {
+        self.config.systemic_jumps = (jump_lambda > 0.0).then(|| simulate::SystemicJumps {
+            lambda: jump_lambda as f64,
+            mean: jump_mean as f64,
+            vol: jump_vol as f64,
+            betas: betas.iter().map(|&b| b as f64).collect(),
+        });
+    }
+
+    /// Self-exciting jump clustering: each jump adds `excitation` to the
+    /// intensity, decaying at rate `decay` (per year); applies to every
+    /// jump process, with its λ as the baseline. excitation ≤ 0 clears it.
+    pub fn set_hawkes(&mut self, excitation: f32, decay: f32) {
+        self.config.hawkes = (excitation > 0.0).then_some(simulate::HawkesParams {
+            excitation: excitation as f64,
+            decay: decay as f64,
+        });
+    }
+
+    /// Per-asset continuous dividend / carry yields (N); prices drift at
+    /// μ − q and simulate_total_return also reports the reinvested
+    /// series. An empty array clears them.
+    pub fn set_dividend_yield(&mut self, yields: &[f32]) {
+        self.config.dividend_yield = (!yields.is_empty()).then(|| yields.iter().map(|&q| q as f64).collect());
+    }
+
+    /// Same seed and options ⇒ bit-identical paths (see SimConfig).
+    pub fn set_seed(&mut self, seed: u64) {
+        self.config.seed = seed;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn seed(&self) -> u64 {
+        self.config.seed
+    }
+
+    pub fn set_jumps(&mut self, jump_lambda: f32, jump_mean: f32, jump_vol: f32) {
+        self.config.jumps = (jump_lambda > 0.0).then(|| {
+            vec![simulate::JumpParams {
+                lambda: jump_lambda as f64,
+                mean: jump_mean as f64,
+                vol: jump_vol as f64,
+            }]
+        });
+    }
+
+    /// Per-asset jumps (e.g. EngineResult.jump_lambdas …); each array is
+    /// length N or 1 (broadcast). All-zero intensities clear the jumps.
+    pub fn set_asset_jumps(&mut self, jump_lambda: &[f32], jump_mean: &[f32], jump_vol: &[f32]) -> Result<(), EngineError> {
+        guard(|| {
+            let n = jump_lambda.len().max(jump_mean.len()).max(jump_vol.len());
+            let lambda = broadcast(jump_lambda, n)?;
+            let mean = broadcast(jump_mean, n)?;
+            let vol = broadcast(jump_vol, n)?;
+            self.config.jumps = lambda.iter().any(|&l| l > 0.0).then(|| {
+                (0..n)
+                    .map(|i| simulate::JumpParams {
+                        lambda: lambda[i] as f64,
+                        mean: mean[i] as f64,
+                        vol: vol[i] as f64,
+                    })
+                    .collect()
+            });
+            Ok(())
+        })
+    }
+
+    /// Vasicek short rates for rates assets (each slice length N); an
+    /// asset with κ ≤ 0 keeps its GBM dynamics. The bond price return is
+    /// r·dt − D·Δr + ½·C·Δr² with D = duration, C = convexity.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_short_rates(
+        &mut self,
+        kappa: &[f32],
+        theta: &[f32],
+        sigma: &[f32],
+        r0: &[f32],
+        duration: &[f32],
+        convexity: &[f32],
+    ) -> Result<(), EngineError> {
+        guard(|| {
+            let n = kappa.len();
+            check_lengths(&[
+                ("theta", n, theta.len()),
+                ("sigma", n, sigma.len()),
+                ("r0", n, r0.len()),
+                ("duration", n, duration.len()),
+                ("convexity", n, convexity.len()),
+            ])?;
+            let rates: Vec<_> = (0..n)
+                .map(|i| {
+                    (kappa[i] > 0.0).then(|| simulate::ShortRateParams {
+                        kappa: kappa[i] as f64,
+                        theta: theta[i] as f64,
+                        sigma: sigma[i] as f64,
+                        r0: r0[i] as f64,
+                        duration: duration[i] as f64,
+                        convexity: convexity[i] as f64,
+                    })
+                })
+                .collect();
+            self.config.short_rates = rates.iter().any(Option::is_some).then_some(rates);
+            Ok(())
+        })
+    }
+
+    /// Per-asset Heston parameters (each slice has length N).
+    pub fn set_heston(
+        &mut self,
+        kappa: &[f32],
+        theta: &[f32],
+        xi: &[f32],
+        rho: &[f32],
+    ) -> Result<(), EngineError> {
+        guard(|| {
+            let n = kappa.len();
+            check_lengths(&[
+                ("theta", n, theta.len()),
+                ("xi", n, xi.len()),
+                ("rho", n, rho.len()),
+            ])?;
+            let params = (0..n)
+                .map(|i| simulate::HestonParams {
+                    kappa: kappa[i] as f64,
+                    theta: theta[i] as f64,
+                    xi: xi[i] as f64,
+                    rho: rho[i] as f64,
+                })
+                .collect();
+            self.config.vol_model = simulate::VolModel::Heston(params);
+            Ok(())
+        })
+    }
+
+    /// Per-asset GARCH(1,1) parameters in per-step variance units.
+    pub fn set_garch(&mut self, omega: &[f32], alpha: &[f32], beta: &[f32]) -> Result<(), EngineError> {
+        guard(|| {
+            let n = omega.len();
+            check_lengths(&[
+                ("alpha", n, alpha.len()),
+                ("beta", n, beta.len()),
+            ])?;
+            let params = (0..n)
+                .map(|i| simulate::GarchParams {
+                    omega: omega[i] as f64,
+                    alpha: alpha[i] as f64,
+                    beta: beta[i] as f64,
+                })
+                .collect();
+            self.config.vol_model = simulate::VolModel::Garch(params);
+            Ok(())
+        })
+    }
+
+    /// Per-asset Variance Gamma returns (θ drift of the subordinated
+    /// BM, ν variance rate of the Gamma clock; each slice length N).
+    pub fn set_variance_gamma(&mut self, theta: &[f32], nu: &[f32]) -> Result<(), EngineError> {
+        guard(|| {
+            check_lengths(&[("nu", theta.len(), nu.len())])?;
+            let params = theta
+                .iter()
+                .zip(nu)
+                .map(|(&t, &v)| simulate::VarianceGammaParams { theta: t as f64, nu: v as f64 })
+                .collect();
+            self.config.vol_model = simulate::VolModel::VarianceGamma(params);
+            Ok(())
+        })
+    }
+
+    /// Per-asset Normal Inverse Gaussian returns (κ variance rate of the
+    /// inverse-Gaussian clock; each slice length N).
+    pub fn set_nig(&mut self, theta: &[f32], kappa: &[f32]) -> Result<(), EngineError> {
+        guard(|| {
+            check_lengths(&[("kappa", theta.len(), kappa.len())])?;
+            let params = theta
+                .iter()
+                .zip(kappa)
+                .map(|(&t, &k)| simulate::NigParams { theta: t as f64, kappa: k as f64 })
+                .collect();
+            self.config.vol_model = simulate::VolModel::Nig(params);
+            Ok(())
+        })
+    }
+
+    /// Local-vol grid: `vols` is N × times × moneyness (row-major; pass an
+    /// empty `moneyness` for a time-only term structure). The shock's
+    /// `vol_multiplier` (N) scales the whole surface.
+    pub fn set_local_vol(
+        &mut self,
+        times: &[f32],
+        moneyness: &[f32],
+        vols: &[f32],
+        vol_multiplier: &[f32],
+    ) -> Result<(), EngineError> {
+        guard(|| {
+            let widen = |xs: &[f32]| xs.iter().map(|&x| x as f64).collect::<Vec<f64>>();
+            let n = vol_multiplier.len();
+            let mut surface = simulate::LocalVolSurface::new(n, widen(times), widen(moneyness), widen(vols))?;
+            surface.scale(&to_dvector(vol_multiplier));
+            self.config.vol_model = simulate::VolModel::Local(surface);
+            Ok(())
+        })
+    }
+
+    /// Piecewise-constant drift/vol: `bucket_ends` (B, years) with base
+    /// drift and vol as B×N row-major; each shock is 1×N (broadcast to
+    /// every bucket) or B×N (per-bucket).
+    pub fn set_term_structure(
+        &mut self,
+        bucket_ends: &[f32],
+        base_drift: &[f32],
+        base_vol: &[f32],
+        delta_drift: &[f32],
+        vol_multiplier: &[f32],
+    ) -> Result<(), EngineError> {
+        guard(|| {
+            let b = bucket_ends.len();
+            if b == 0 || !base_drift.len().is_multiple_of(b) || base_vol.len() != base_drift.len() {
+                return Err(EngineError::from("Input length mismatch: base drift and vol must both be B×N"));
+            }
+            let n = base_drift.len() / b;
+            let rows = |xs: &[f32]| -> Result<DMatrix<f64>, EngineError> {
+                if n == 0 || !xs.len().is_multiple_of(n) {
+                    return Err(EngineError::from("Input length mismatch: shocks must be 1×N or B×N"));
+                }
+                let v: Vec<f64> = xs.iter().map(|&x| x as f64).collect();
+                Ok(DMatrix::from_row_slice(xs.len() / n, n, &v))
+            };
+            let drift = math::adjust_drift_term(&rows(base_drift)?, &rows(delta_drift)?)?;
+            let vol = math::adjust_vol_term(&rows(base_vol)?, &rows(vol_multiplier)?)?;
+            self.config.term_structure = Some(simulate::TermStructure {
+                ends: bucket_ends.iter().map(|&e| e as f64).collect(),
+                drift,
+                vol,
+            });
+            Ok(())
+        })
+    }
+
+    pub fn clear_term_structure(&mut self) {
+        self.config.term_structure = None;
+    }
+
+    pub fn set_constant_vol(&mut self) {
+        self.config.vol_model = simulate::VolModel::Constant;
+    }
+
+    /// Drive the diffusion with Sobol points (true) or PCG32 (false).
+    pub fn set_sobol(&mut self, enabled: bool) {
+        self.config.driver = if enabled {
+            simulate::Driver::Sobol
+        } else {
+            simulate::Driver::PseudoRandom
+        };
+    }
+
+    /// Stratify the terminal shock along Σ's first principal component (true)
+    /// or use plain PCG32 (false).
+    pub fn set_stratified(&mut self, enabled: bool) {
+        self.config.driver = if enabled {
+            simulate::Driver::Stratified
+        } else {
+            simulate::Driver::PseudoRandom
+        };
+    }
+
+    /// Pseudo-random algorithm: "pcg32" (default), "xoshiro256++",
+    /// "pcg64" or "philox4x32" (matches a Philox WebGPU kernel).
+    pub fn set_rng(&mut self, algorithm: &str) -> Result<(), EngineError> {
+        guard(|| {
+            self.config.rng = crate::rng::RngKind::from_name(algorithm)?;
+            Ok(())
+        })
+    }
+
+    /// Return paths as planar [asset][path][step] (true) or interleaved
+    /// [path][step][asset] (false, the default).
+    pub fn set_planar_layout(&mut self, enabled: bool) {
+        self.config.layout = if enabled {
+            simulate::OutputLayout::Planar
+        } else {
+            simulate::OutputLayout::Interleaved
+        };
+    }
+
+    /// Importance sampling: simulate under log drifts shifted by
+    /// `drift_shift` (N, annualized); an empty array clears the tilt.
+    /// Tilted runs go through simulate_weighted.
+    pub fn set_importance_tilt(&mut self, drift_shift: &[f32]) {
+        self.config.tilt = (!drift_shift.is_empty()).then(|| drift_shift.iter().map(|&x| x as f64).collect());
+    }
+
+    /// Rescale each batch of draws to sample mean 0 / variance 1, and
+    /// with `exact_correlation` also to sample covariance I per step.
+    pub fn set_moment_matching(&mut self, enabled: bool, exact_correlation: bool) {
+        self.config.moment_matching = match (enabled, exact_correlation) {
+            (false, _) => simulate::MomentMatching::None,
+            (true, false) => simulate::MomentMatching::MeanVariance,
+            (true, true) => simulate::MomentMatching::Full,
+        };
+    }
+
+    /// Build paths coarse-to-fine with a Brownian bridge (true) or
+    /// step by step (false).
+    pub fn set_brownian_bridge(&mut self, enabled: bool) {
+        self.config.construction = if enabled {
+            simulate::PathConstruction::BrownianBridge
+        } else {
+            simulate::PathConstruction::Incremental
+        };
+    }
+
+    pub fn set_gaussian_innovations(&mut self) {
+        self.config.innovations = simulate::Innovations::Gaussian;
+    }
+
+    pub fn set_student_t_innovations(&mut self, nu: f32) {
+        self.config.innovations = simulate::Innovations::StudentT { nu: nu as f64 };
+    }
+
+    pub fn set_skew_t_innovations(&mut self, nu: f32, gamma: f32) {
+        self.config.innovations = simulate::Innovations::SkewT {
+            nu: nu as f64,
+            gamma: gamma as f64,
+        };
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// simulate_paths — CPU Monte Carlo for browsers without WebGPU
+// Takes the EngineResult arrays (incl. jump params) as-is; returns
+// [path][step][asset].
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_paths(
+    drift: &[f32],
+    vol: &[f32],
+    cholesky_l: &[f32],
+    horizon: f32,
+    steps: usize,
+    n_paths: usize,
+    seed: u64,
+    jump_lambda: f32,
+    jump_mean: f32,
+    jump_vol: f32,
+) -> Result<Float32Array, EngineError> {
+    guard(|| {
+        let mut options = SimulationOptions::new(horizon, steps, n_paths, seed);
+        options.set_jumps(jump_lambda, jump_mean, jump_vol);
+        simulate_with_options(drift, vol, cholesky_l, &options)
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// simulate_with_options — full simulator surface (jumps, Heston, …)
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub fn simulate_with_options(
+    drift: &[f32],
+    vol: &[f32],
+    cholesky_l: &[f32],
+    options: &SimulationOptions,
+) -> Result<Float32Array, EngineError> {
+    guard(|| {
+        let n = drift.len();
+        check_lengths(&[
+            ("vol", n, vol.len()),
+            ("cholesky_l", n * n, cholesky_l.len()),
+        ])?;
+
+        let mu: Vec<f64> = drift.iter().map(|&x| x as f64).collect();
+        let sigma: Vec<f64> = vol.iter().map(|&x| x as f64).collect();
+        let l: Vec<f64> = cholesky_l.iter().map(|&x| x as f64).collect();
+
+        let paths = simulate::simulate_paths(
+            &DVector::from_vec(mu),
+            &DVector::from_vec(sigma),
+            &DMatrix::from_row_slice(n, n, &l),
+            &options.config,
+        )?;
+
+        Ok(Float32Array::from(paths.as_slice()))
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// GpuKernel — the WebGPU simulation kernel, generated (gpu.rs)
+// Build the pipeline from `source` and the bind group from
+// `layout_json`; upload `params(...)` to binding 0 and the EngineResult
+// arrays to the bindings named after them. Regenerate when N or the
+// step count changes.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct GpuKernel {
+    kernel: gpu::Kernel,
+}
+
+#[wasm_bindgen]
+impl GpuKernel {
+    /// `rng` is "pcg32" or "philox4x32"; `output` is "terminal",
+    /// "paths" or "portfolio".
+    #[wasm_bindgen(constructor)]
+    pub fn new(num_assets: usize, steps: usize, rng: &str, output: &str, jumps: bool) -> Result<GpuKernel, EngineError> {
+        guard(|| {
+            let kernel = gpu::KernelSpec::new(num_assets, steps)
+                .with_rng(crate::rng::RngKind::from_name(rng)?)
+                .with_output(gpu::KernelOutput::from_name(output)?)
+                .with_jumps(jumps)
+                .generate()?;
+            Ok(GpuKernel { kernel })
+        })
+    }
+
+    /// WGSL source, entry point "main"
+    #[wasm_bindgen(getter)]
+    pub fn source(&self) -> String {
+        self.kernel.source.clone()
+    }
+
+    /// {"workgroup_size", "bindings": [{binding, name, type, bytes |
+    /// bytes_per_path, contents}]}, all in group 0
+    #[wasm_bindgen(getter)]
+    pub fn layout_json(&self) -> String {
+        self.kernel.layout_json()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn workgroup_size(&self) -> u32 {
+        self.kernel.spec.workgroup_size
+    }
+
+    /// dispatchWorkgroups(x) for `n_paths`
+    pub fn workgroups(&self, n_paths: u32) -> u32 {
+        self.kernel.workgroups(n_paths)
+    }
+
+    /// Params uniform for paths path_offset..path_offset + n_paths
+    pub fn params(&self, n_paths: u32, path_offset: u32, seed: u64, horizon: f32) -> Uint8Array {
+        Uint8Array::from(self.kernel.params(n_paths, path_offset, seed, horizon as f64).as_slice())
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Zero-copy views
+// The *_view accessors return a Float32Array aliasing wasm linear
+// memory instead of a copy, and *_ptr / len give the same region for
+// `new Float32Array(wasm.memory.buffer, ptr, len)`. A view is valid
+// only until (a) its owner is freed, or (b) wasm memory grows, which
+// any engine call that allocates may trigger — the old ArrayBuffer is
+// then detached. Consume it straight away (e.g. queue.writeBuffer)
+// and take a fresh view after every engine call; copy (.slice()) to
+// keep the data.
+// ════════════════════════════════════════════════════════════════
+fn view(xs: &[f32]) -> Float32Array {
+    // Safety: the view is handed to JS, which must follow the lifetime
+    // rules above; no Rust allocation happens before it is returned.
+    unsafe { Float32Array::view(xs) }
+}
+
+/// simulate_with_options output kept in wasm memory.
+#[wasm_bindgen]
+pub struct PathBuffer {
+    paths: Vec<f32>,
+    timings: Timings,
+    /// Counts `paths` in memory_stats().path_bytes until dropped
+    _held: Held,
+}
+
+#[wasm_bindgen]
+impl PathBuffer {
+    /// Copy into a JS-owned array
+    pub fn to_array(&self) -> Float32Array {
+        Float32Array::from(self.paths.as_slice())
+    }
+
+    pub fn view(&self) -> Float32Array {
+        view(&self.paths)
+    }
+
+    pub fn ptr(&self) -> *const f32 {
+        self.paths.as_ptr()
+    }
+
+    /// Number of f32 values
+    #[wasm_bindgen(getter)]
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Simulation time (simulation_ms only)
+    #[wasm_bindgen(getter)]
+    pub fn timings(&self) -> Timings {
+        self.timings
+    }
+
+    /// Consume into a Float32Array owning its ArrayBuffer, for
+    /// `postMessage(paths, [paths.buffer])`; the wasm copy is freed.
+    pub fn into_transferable(self) -> Float32Array {
+        Float32Array::from(self.paths.as_slice())
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Transferable output
+// A view can't be posted to another thread: transferring its buffer
+// would detach all of wasm memory. into_transferable instead copies
+// each array once out of linear memory into its own ArrayBuffer and
+// frees the Rust side, so a worker can hand the buffers to the main
+// thread with postMessage's transfer list and no further copies.
+// ────────────────────────────────────────────────────────────────
+fn transferable(xs: &[f32], transfer: &js_sys::Array) -> Float32Array {
+    let array = Float32Array::from(xs);
+    transfer.push(&array.buffer());
+    array
+}
+
+/// simulate_with_options without the copy into a JS array.
+#[wasm_bindgen]
+pub fn simulate_to_buffer(
+    drift: &[f32],
+    vol: &[f32],
+    cholesky_l: &[f32],
+    options: &SimulationOptions,
+) -> Result<PathBuffer, EngineError> {
+    guard(|| {
+        let n = drift.len();
+        check_lengths(&[
+            ("vol", n, vol.len()),
+            ("cholesky_l", n * n, cholesky_l.len()),
+        ])?;
+        let l: Vec<f64> = cholesky_l.iter().map(|&x| x as f64).collect();
+        let mut timings = Timings::default();
+        let paths = timed(&mut timings.simulation_ms, || {
+            simulate::simulate_paths(
+                &to_dvector(drift),
+                &to_dvector(vol),
+                &DMatrix::from_row_slice(n, n, &l),
+                &options.config,
+            )
+        })?;
+        let _held = Held::new(&PATH_BYTES, size_of_val(paths.as_slice()));
+        Ok(PathBuffer { paths, timings, _held })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Strict reproducibility — see strict.rs
+// ════════════════════════════════════════════════════════════════
+
+/// Process-wide: with `enabled`, every later call gives the same bits
+/// on wasm, x86-64 and ARM (f64 Phase A and the CPU simulator), at
+/// some cost in speed. The f32 preview and GPU paths are not covered.
+/// Engines rebuild their stages on the next recompute().
+#[wasm_bindgen]
+pub fn set_strict_numerics(enabled: bool) {
+    strict::set_enabled(enabled);
+}
+
+#[wasm_bindgen]
+pub fn strict_numerics() -> bool {
+    strict::enabled()
+}
+
+// ════════════════════════════════════════════════════════════════
+// Memory accounting
+// Live Engines and PathBuffers add what they hold to a process-wide
+// counter and take it back when freed, so a page can watch
+// memory_stats() for objects it forgot to .free() and call
+// Engine.release_buffers() on ones it keeps but rarely uses. Only those
+// two are counted: SimulationStream, ComputeHandle, HistoricalReplay
+// and EngineResult (its lazily filled covariance included) hold wasm
+// memory too but show up only in linear_memory_bytes. Arrays already
+// copied out to JS are not counted; they live in the JS heap.
+// ════════════════════════════════════════════════════════════════
+static ENGINE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PATH_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// `bytes` counted in `total` until dropped
+struct Held {
+    total: &'static AtomicUsize,
+    bytes: usize,
+}
+
+impl Held {
+    fn new(total: &'static AtomicUsize, bytes: usize) -> Self {
+        total.fetch_add(bytes, Ordering::Relaxed);
+        Held { total, bytes }
+    }
+
+    fn set(&mut self, bytes: usize) {
+        self.total.fetch_add(bytes, Ordering::Relaxed);
+        self.total.fetch_sub(self.bytes, Ordering::Relaxed);
+        self.bytes = bytes;
+    }
+}
+
+impl Drop for Held {
+    fn drop(&mut self) {
+        self.total.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
+
+fn market_bytes(m: &ShockedMarket) -> usize {
+    size_of_val(m.drift.as_slice())
+        + size_of_val(m.vol.as_slice())
+        + size_of_val(m.cholesky_l.as_slice())
+        + size_of_val(m.correlation.as_slice())
+}
+
+/// Byte counts, as f64 so that 4 GiB still fits a JS number exactly
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemoryStats {
+    linear_memory_bytes: f64,
+    engine_bytes: f64,
+    path_bytes: f64,
+}
+
+#[wasm_bindgen]
+impl MemoryStats {
+    /// Size of wasm linear memory (0 off wasm); grows but never shrinks
+    #[wasm_bindgen(getter)]
+    pub fn linear_memory_bytes(&self) -> f64 {
+        self.linear_memory_bytes
+    }
+
+    /// Stages, cache and scratch of live Engines
+    #[wasm_bindgen(getter)]
+    pub fn engine_bytes(&self) -> f64 {
+        self.engine_bytes
+    }
+
+    /// Paths in live PathBuffers (not HistoricalReplay or streams)
+    #[wasm_bindgen(getter)]
+    pub fn path_bytes(&self) -> f64 {
+        self.path_bytes
+    }
+}
+
+#[wasm_bindgen]
+pub fn memory_stats() -> MemoryStats {
+    #[cfg(target_arch = "wasm32")]
+    let linear_memory_bytes = core::arch::wasm32::memory_size::<0>() as f64 * 65536.0;
+    #[cfg(not(target_arch = "wasm32"))]
+    let linear_memory_bytes = 0.0;
+    MemoryStats {
+        linear_memory_bytes,
+        engine_bytes: ENGINE_BYTES.load(Ordering::Relaxed) as f64,
+        path_bytes: PATH_BYTES.load(Ordering::Relaxed) as f64,
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// simulate_weighted — paths plus importance-sampling likelihood ratios
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct WeightedSimulation {
+    paths: Vec<f32>,
+    weights: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl WeightedSimulation {
+    /// n_paths × (steps + 1) × N in the options' layout
+    #[wasm_bindgen(getter)]
+    pub fn paths(&self) -> Float32Array {
+        Float32Array::from(self.paths.as_slice())
+    }
+
+    /// One likelihood ratio per path; weight each path's statistic by it
+    #[wasm_bindgen(getter)]
+    pub fn weights(&self) -> Float32Array {
+        Float32Array::from(self.weights.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+pub fn simulate_weighted(
+    drift: &[f32],
+    vol: &[f32],
+    cholesky_l: &[f32],
+    options: &SimulationOptions,
+) -> Result<WeightedSimulation, EngineError> {
+    guard(|| {
+        let n = drift.len();
+        check_lengths(&[
+            ("vol", n, vol.len()),
+            ("cholesky_l", n * n, cholesky_l.len()),
+        ])?;
+        let l: Vec<f64> = cholesky_l.iter().map(|&x| x as f64).collect();
+        let run = simulate::simulate_weighted_paths(
+            &to_dvector(drift),
+            &to_dvector(vol),
+            &DMatrix::from_row_slice(n, n, &l),
+            &options.config,
+        )?;
+
+        Ok(WeightedSimulation {
+            paths: run.paths,
+            weights: run.weights.iter().map(|&w| w as f32).collect(),
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// control_variate_estimate — E[statistic] corrected with the analytic
+// terminal means of the simulated assets
+// Returns [mean, std_error, raw_mean, raw_std_error].
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub fn control_variate_estimate(
+    statistic: &[f32],
+    paths: &[f32],
+    drift: &[f32],
+    options: &SimulationOptions,
+) -> Result<Float32Array, EngineError> {
+    guard(|| {
+        let stat: Vec<f64> = statistic.iter().map(|&x| x as f64).collect();
+        let est = crate::estimate::terminal_control_variate(&stat, paths, &to_dvector(drift), &options.config)?;
+        let out = [est.mean, est.std_error, est.raw_mean, est.raw_std_error].map(|x| x as f32);
+        Ok(Float32Array::from(out.as_slice()))
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// simulate_total_return — price paths plus dividend-reinvested paths
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct TotalReturnSimulation {
+    price_paths: Vec<f32>,
+    total_return_paths: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl TotalReturnSimulation {
+    #[wasm_bindgen(getter)]
+    pub fn price_paths(&self) -> Float32Array {
+        Float32Array::from(self.price_paths.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn total_return_paths(&self) -> Float32Array {
+        Float32Array::from(self.total_return_paths.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+pub fn simulate_total_return(
+    drift: &[f32],
+    vol: &[f32],
+    cholesky_l: &[f32],
+    options: &SimulationOptions,
+) -> Result<TotalReturnSimulation, EngineError> {
+    guard(|| {
+        let n = drift.len();
+        check_lengths(&[
+            ("vol", n, vol.len()),
+            ("cholesky_l", n * n, cholesky_l.len()),
+        ])?;
+        let l: Vec<f64> = cholesky_l.iter().map(|&x| x as f64).collect();
+        let price_paths = simulate::simulate_paths(
+            &to_dvector(drift),
+            &to_dvector(vol),
+            &DMatrix::from_row_slice(n, n, &l),
+            &options.config,
+        )?;
+        let total_return_paths =
+            simulate::total_return_paths(&price_paths, n, &options.config)?;
+        Ok(TotalReturnSimulation { price_paths, total_return_paths })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// simulate_multi_currency — assets plus FX rates, reported in a base
+// currency. asset_currency tags each asset 0..C; currency 0 is the FX
+// quote reference, fx_* describe the C − 1 other currencies, and
+// asset_fx_correlation is N × (C − 1) row-major.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct MultiCurrencySimulation {
+    local_paths: Vec<f32>,
+    base_paths: Vec<f32>,
+    portfolio_paths: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl MultiCurrencySimulation {
+    /// [path][step][N assets then C − 1 FX rates], local currencies
+    #[wasm_bindgen(getter)]
+    pub fn local_paths(&self) -> Float32Array {
+        Float32Array::from(self.local_paths.as_slice())
+    }
+
+    /// [path][step][asset] in the base currency
+    #[wasm_bindgen(getter)]
+    pub fn base_paths(&self) -> Float32Array {
+        Float32Array::from(self.base_paths.as_slice())
+    }
+
+    /// [path][step] weighted base-currency value (empty without weights)
+    #[wasm_bindgen(getter)]
+    pub fn portfolio_paths(&self) -> Float32Array {
+        Float32Array::from(self.portfolio_paths.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_multi_currency(
+    drift: &[f32],
+    vol: &[f32],
+    correlation: &[f32],
+    fx_drift: &[f32],
+    fx_vol: &[f32],
+    asset_fx_correlation: &[f32],
+    fx_correlation: &[f32],
+    asset_currency: &[u32],
+    base_currency: u32,
+    weights: &[f32],
+    options: &SimulationOptions,
+) -> Result<MultiCurrencySimulation, EngineError> {
+    guard(|| {
+        let (n, f) = (drift.len(), fx_drift.len());
+        check_lengths(&[
+            ("correlation", n * n, correlation.len()),
+            ("asset_fx_correlation", n * f, asset_fx_correlation.len()),
+            ("fx_correlation", f * f, fx_correlation.len()),
+            ("asset_currency", n, asset_currency.len()),
+        ])?;
+        if !weights.is_empty() {
+            check_lengths(&[("weights", n, weights.len())])?;
+        }
+        let to_matrix = |rows: usize, cols: usize, xs: &[f32]| {
+            DMatrix::from_row_slice(rows, cols, &xs.iter().map(|&x| x as f64).collect::<Vec<_>>())
+        };
+        let currencies = fx::CurrencyMap::new(asset_currency.iter().map(|&c| c as usize).collect(), f + 1)?;
+        let market = fx::assemble_market(
+            &to_dvector(drift),
+            &to_dvector(vol),
+            &to_matrix(n, n, correlation),
+            &to_dvector(fx_drift),
+            &to_dvector(fx_vol),
+            &to_matrix(n, f, asset_fx_correlation),
+            &to_matrix(f, f, fx_correlation),
+        )?;
+        let out = fx::simulate_multi_currency(&market, &currencies, base_currency as usize, &options.config)?;
+        let portfolio_paths = if weights.is_empty() {
+            Vec::new()
+        } else {
+            let w: Vec<f64> = weights.iter().map(|&x| x as f64).collect();
+            fx::portfolio_value(&out.base, &w, &options.config)?
+        };
+        Ok(MultiCurrencySimulation { local_paths: out.local, base_paths: out.base, portfolio_paths })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// compute_var_cvar — VaR / CVaR of terminal P&L at each level
+// terminal_pnl is [path][asset] gains (see terminal_pnl_from_paths for
+// engine paths) weighted by `weights`; with empty weights it is one
+// portfolio value per path. Losses are reported as positive numbers.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct RiskMetrics {
+    levels: Vec<f32>,
+    var: Vec<f32>,
+    cvar: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl RiskMetrics {
+    #[wasm_bindgen(getter)]
+    pub fn levels(&self) -> Float32Array {
+        Float32Array::from(self.levels.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn var(&self) -> Float32Array {
+        Float32Array::from(self.var.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn cvar(&self) -> Float32Array {
+        Float32Array::from(self.cvar.as_slice())
+    }
+
+    /// Arrow IPC stream with one row per level (level, var, cvar)
+    pub fn to_arrow(&self) -> Uint8Array {
+        let rows: Vec<risk::VarCvar> = (0..self.levels.len())
+            .map(|i| risk::VarCvar { level: self.levels[i] as f64, var: self.var[i] as f64, cvar: self.cvar[i] as f64 })
+            .collect();
+        Uint8Array::from(arrow::var_cvar_to_ipc(&rows).as_slice())
+    }
+}
+
+#[wasm_bindgen]
+pub fn compute_var_cvar(terminal_pnl: &[f32], weights: &[f32], levels: &[f32]) -> Result<RiskMetrics, EngineError> {
+    guard(|| {
+        let pnl: Vec<f64> = terminal_pnl.iter().map(|&x| x as f64).collect();
+        let w: Vec<f64> = weights.iter().map(|&x| x as f64).collect();
+        let a: Vec<f64> = levels.iter().map(|&x| x as f64).collect();
+        let out = risk::compute_var_cvar(&pnl, &w, &a)?;
+        Ok(RiskMetrics {
+            levels: levels.to_vec(),
+            var: out.iter().map(|r| r.var as f32).collect(),
+            cvar: out.iter().map(|r| r.cvar as f32).collect(),
+        })
+    })
+}
+
+/// Per-step percentiles (in percent, e.g. [1, 5, 25, 50, 75, 95, 99])
+/// of the weighted portfolio value (equal weights when `weights` is
+/// empty): [step][percentile], then [asset][step][percentile] when
+/// per_asset is set. `paths` is simulate_with_options output.
+#[wasm_bindgen]
+pub fn percentile_fan(
+    paths: &[f32],
+    num_assets: usize,
+    weights: &[f32],
+    percentiles: &[f32],
+    per_asset: bool,
+    options: &SimulationOptions,
+) -> Result<Float32Array, EngineError> {
+    guard(|| {
+        let w: Vec<f64> = weights.iter().map(|&x| x as f64).collect();
+        let q: Vec<f64> = percentiles.iter().map(|&x| x as f64).collect();
+        let fan = risk::percentile_fan(paths, num_assets, &options.config, &w, &q, per_asset)?;
+        Ok(Float32Array::from(fan.as_slice()))
+    })
+}
+
+/// Per-asset contributions to portfolio vol (Euler, on Σ = L·Lᵀ) and to
+/// simulated CVaR at `level` (conditional expectation over the tail).
+#[wasm_bindgen]
+pub struct RiskContributions {
+    vol: f32,
+    marginal: Vec<f32>,
+    component: Vec<f32>,
+    percent: Vec<f32>,
+    cvar_component: Vec<f32>,
+    cvar_percent: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl RiskContributions {
+    #[wasm_bindgen(getter)]
+    pub fn vol(&self) -> f32 {
+        self.vol
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn marginal(&self) -> Float32Array {
+        Float32Array::from(self.marginal.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn component(&self) -> Float32Array {
+        Float32Array::from(self.component.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn percent(&self) -> Float32Array {
+        Float32Array::from(self.percent.as_slice())
+    }
+
+    /// Empty when no terminal P&L was supplied
+    #[wasm_bindgen(getter)]
+    pub fn cvar_component(&self) -> Float32Array {
+        Float32Array::from(self.cvar_component.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn cvar_percent(&self) -> Float32Array {
+        Float32Array::from(self.cvar_percent.as_slice())
+    }
+}
+
+/// `terminal_pnl` is [path][asset] (see terminal_pnl_from_paths); pass
+/// an empty array to skip the CVaR decomposition.
+#[wasm_bindgen]
+pub fn risk_contributions(
+    weights: &[f32],
+    cholesky_l: &[f32],
+    terminal_pnl: &[f32],
+    level: f32,
+) -> Result<RiskContributions, EngineError> {
+    guard(|| {
+        let n = weights.len();
+        check_lengths(&[("cholesky_l", n * n, cholesky_l.len())])?;
+        let w: Vec<f64> = weights.iter().map(|&x| x as f64).collect();
+        let l: Vec<f64> = cholesky_l.iter().map(|&x| x as f64).collect();
+        let vol = risk::vol_contributions(&w, &DMatrix::from_row_slice(n, n, &l))?;
+        let cvar = if terminal_pnl.is_empty() {
+            Vec::new()
+        } else {
+            let pnl: Vec<f64> = terminal_pnl.iter().map(|&x| x as f64).collect();
+            risk::cvar_contributions(&pnl, &w, level as f64)?
+        };
+        let total: f64 = cvar.iter().sum();
+        let to_f32 = |xs: &[f64]| xs.iter().map(|&x| x as f32).collect::<Vec<_>>();
+        Ok(RiskContributions {
+            vol: vol.vol as f32,
+            marginal: to_f32(&vol.marginal),
+            component: to_f32(&vol.component),
+            percent: to_f32(&vol.percent),
+            cvar_percent: cvar.iter().map(|&c| if total != 0.0 { (c / total) as f32 } else { 0.0 }).collect(),
+            cvar_component: to_f32(&cvar),
+        })
+    })
+}
+
+/// Expected-shortfall breakdown by asset: average loss per holding over
+/// the tail paths at `level`, its share of ES, and a ranking.
+#[wasm_bindgen]
+pub struct EsDecomposition {
+    es: f32,
+    tail_paths: f32,
+    contribution: Vec<f32>,
+    share: Vec<f32>,
+    ranking: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl EsDecomposition {
+    #[wasm_bindgen(getter)]
+    pub fn es(&self) -> f32 {
+        self.es
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tail_paths(&self) -> f32 {
+        self.tail_paths
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn contribution(&self) -> Float32Array {
+        Float32Array::from(self.contribution.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn share(&self) -> Float32Array {
+        Float32Array::from(self.share.as_slice())
+    }
+
+    /// Asset indices, largest share first
+    #[wasm_bindgen(getter)]
+    pub fn ranking(&self) -> Uint32Array {
+        Uint32Array::from(self.ranking.as_slice())
+    }
+}
+
+/// `terminal_pnl` is [path][asset] (see terminal_pnl_from_paths).
+#[wasm_bindgen]
+pub fn es_decomposition(terminal_pnl: &[f32], weights: &[f32], level: f32) -> Result<EsDecomposition, EngineError> {
+    guard(|| {
+        let pnl: Vec<f64> = terminal_pnl.iter().map(|&x| x as f64).collect();
+        let w: Vec<f64> = weights.iter().map(|&x| x as f64).collect();
+        let d = risk::es_decomposition(&pnl, &w, level as f64)?;
+        Ok(EsDecomposition {
+            es: d.es as f32,
+            tail_paths: d.tail_paths as f32,
+            contribution: d.contribution.iter().map(|&x| x as f32).collect(),
+            share: d.share.iter().map(|&x| x as f32).collect(),
+            ranking: d.ranking.iter().map(|&a| a as u32).collect(),
+        })
+    })
+}
+
+/// Empirical lower / upper tail dependence between every asset pair at
+/// threshold q (e.g. 0.05), from per-step increments or, with
+/// `terminal_only`, terminal returns. Returns [lower N×N, upper N×N].
+#[wasm_bindgen]
+pub fn tail_dependence(
+    paths: &[f32],
+    num_assets: usize,
+    q: f32,
+    terminal_only: bool,
+    options: &SimulationOptions,
+) -> Result<Float32Array, EngineError> {
+    guard(|| {
+        let sample = if terminal_only { risk::TailSample::Terminal } else { risk::TailSample::Steps };
+        let td = risk::tail_dependence(paths, num_assets, &options.config, q as f64, sample)?;
+        let n = num_assets;
+        let out: Vec<f32> = [&td.lower, &td.upper]
+            .iter()
+            .flat_map(|m| (0..n * n).map(move |k| m[(k / n, k % n)] as f32))
+            .collect();
+        Ok(Float32Array::from(out.as_slice()))
+    })
+}
+
+/// [path][asset] terminal P&L S_T − 1 from simulate_with_options output.
+#[wasm_bindgen]
+pub fn terminal_pnl_from_paths(paths: &[f32], num_assets: usize, options: &SimulationOptions) -> Result<Float32Array, EngineError> {
+    guard(|| {
+        let pnl = risk::terminal_pnl(paths, num_assets, &options.config)?;
+        let out: Vec<f32> = pnl.iter().map(|&x| x as f32).collect();
+        Ok(Float32Array::from(out.as_slice()))
+    })
+}
+
+/// simulate_with_options output as an Arrow IPC stream in long format
+/// (path, step, asset, value) for DuckDB, Polars or apache-arrow's
+/// tableFromIPC.
+#[wasm_bindgen]
+pub fn paths_to_arrow(paths: &[f32], num_assets: usize, options: &SimulationOptions) -> Result<Uint8Array, EngineError> {
+    guard(|| {
+        let bytes = arrow::paths_to_ipc(paths, num_assets, &options.config)?;
+        Ok(Uint8Array::from(bytes.as_slice()))
+    })
+}
+
+/// terminal_pnl_from_paths as an Arrow IPC stream (path, asset, pnl).
+#[wasm_bindgen]
+pub fn terminal_pnl_to_arrow(paths: &[f32], num_assets: usize, options: &SimulationOptions) -> Result<Uint8Array, EngineError> {
+    guard(|| {
+        let pnl = risk::terminal_pnl(paths, num_assets, &options.config)?;
+        let bytes = arrow::terminal_pnl_to_ipc(&pnl, num_assets)?;
+        Ok(Uint8Array::from(bytes.as_slice()))
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// simulate_portfolio — buy-and-hold portfolio P&L from the simulator
+// Pass weights (unit notional) or, instead, per-asset notionals.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct PortfolioSimulation {
+    drift: f32,
+    vol: f32,
+    steps: usize,
+    pnl_paths: Vec<f32>,
+    costs: Vec<f32>,
+    liquidated: Vec<i32>,
+}
+
+#[wasm_bindgen]
+impl PortfolioSimulation {
+    #[wasm_bindgen(getter)]
+    pub fn drift(&self) -> f32 {
+        self.drift
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn vol(&self) -> f32 {
+        self.vol
+    }
+
+    /// [path][step] P&L in currency, net of rebalancing costs
+    #[wasm_bindgen(getter)]
+    pub fn pnl_paths(&self) -> Float32Array {
+        Float32Array::from(self.pnl_paths.as_slice())
+    }
+
+    /// Total trading cost per path (zeros for buy-and-hold)
+    #[wasm_bindgen(getter)]
+    pub fn costs(&self) -> Float32Array {
+        Float32Array::from(self.costs.as_slice())
+    }
+
+    /// Step at which forced liquidation started per path (−1 = never)
+    #[wasm_bindgen(getter)]
+    pub fn liquidated_at(&self) -> Int32Array {
+        Int32Array::from(self.liquidated.as_slice())
+    }
+
+    /// Terminal P&L per path (feeds compute_var_cvar with no weights)
+    #[wasm_bindgen(getter)]
+    pub fn terminal_pnl(&self) -> Float32Array {
+        let terminal: Vec<f32> = self.pnl_paths.chunks_exact(self.steps + 1).map(|row| row[self.steps]).collect();
+        Float32Array::from(terminal.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+pub fn simulate_portfolio(
+    drift: &[f32],
+    vol: &[f32],
+    cholesky_l: &[f32],
+    weights: &[f32],
+    notionals: &[f32],
+    options: &SimulationOptions,
+) -> Result<PortfolioSimulation, EngineError> {
+    guard(|| {
+        let p = to_portfolio(weights, notionals)?;
+        run_portfolio(drift, vol, cholesky_l, &p, options)
+    })
+}
+
+/// simulate_portfolio with rebalancing back to the target weights every
+/// `rebalance_every` steps (0 = never) and/or when a weight drifts more
+/// than `drift_band` from target (≤ 0 = no band), paying `cost_rate`
+/// per unit of traded value.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_rebalanced_portfolio(
+    drift: &[f32],
+    vol: &[f32],
+    cholesky_l: &[f32],
+    weights: &[f32],
+    notionals: &[f32],
+    rebalance_every: usize,
+    drift_band: f32,
+    cost_rate: f32,
+    options: &SimulationOptions,
+) -> Result<PortfolioSimulation, EngineError> {
+    guard(|| {
+        let policy = portfolio::RebalancePolicy {
+            every: (rebalance_every > 0).then_some(rebalance_every),
+            band: (drift_band > 0.0).then_some(drift_band as f64),
+            cost_rate: cost_rate as f64,
+        };
+        let p = to_portfolio(weights, notionals)
+            .and_then(|p| p.with_rebalance(policy))?;
+        run_portfolio(drift, vol, cholesky_l, &p, options)
+    })
+}
+
+/// simulate_rebalanced_portfolio with per-asset liquidity: `spread`
+/// (full bid–ask, fraction of price), square-root `impact` and
+/// `days_to_liquidate` at normal volume, all N long or empty for none.
+/// With `max_loss` in (0, 1] everything is sold once the portfolio is
+/// down that fraction, each asset over its days_to_liquidate.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_portfolio_with_liquidity(
+    drift: &[f32],
+    vol: &[f32],
+    cholesky_l: &[f32],
+    weights: &[f32],
+    notionals: &[f32],
+    rebalance_every: usize,
+    drift_band: f32,
+    cost_rate: f32,
+    spread: &[f32],
+    impact: &[f32],
+    days_to_liquidate: &[f32],
+    max_loss: f32,
+    options: &SimulationOptions,
+) -> Result<PortfolioSimulation, EngineError> {
+    guard(|| {
+        let policy = portfolio::RebalancePolicy {
+            every: (rebalance_every > 0).then_some(rebalance_every),
+            band: (drift_band > 0.0).then_some(drift_band as f64),
+            cost_rate: cost_rate as f64,
+        };
+        let mut p = to_portfolio(weights, notionals)
+            .and_then(|p| p.with_rebalance(policy))?;
+        if !(spread.is_empty() && impact.is_empty() && days_to_liquidate.is_empty()) {
+            let n = p.num_assets();
+            check_lengths(&[
+                ("spread", n, spread.len()),
+                ("impact", n, impact.len()),
+                ("days_to_liquidate", n, days_to_liquidate.len()),
+            ])?;
+            let liquidity = (0..n)
+                .map(|a| portfolio::Liquidity {
+                    spread: spread[a] as f64,
+                    impact: impact[a] as f64,
+                    days_to_liquidate: days_to_liquidate[a] as f64,
+                })
+                .collect();
+            p = p.with_liquidity(liquidity)?;
+        }
+        if max_loss > 0.0 {
+            p = p.with_forced_liquidation(max_loss as f64)?;
+        }
+        run_portfolio(drift, vol, cholesky_l, &p, options)
+    })
+}
+
+fn run_portfolio(
+    drift: &[f32],
+    vol: &[f32],
+    cholesky_l: &[f32],
+    p: &portfolio::Portfolio,
+    options: &SimulationOptions,
+) -> Result<PortfolioSimulation, EngineError> {
+    let n = drift.len();
+    check_lengths(&[
+        ("vol", n, vol.len()),
+        ("cholesky_l", n * n, cholesky_l.len()),
+    ])?;
+    let l: Vec<f64> = cholesky_l.iter().map(|&x| x as f64).collect();
+    let out = portfolio::simulate_portfolio(
+        &to_dvector(drift),
+        &to_dvector(vol),
+        &DMatrix::from_row_slice(n, n, &l),
+        p,
+        &options.config,
+    )?;
+    Ok(PortfolioSimulation {
+        drift: out.drift as f32,
+        vol: out.vol as f32,
+        steps: options.config.steps,
+        pnl_paths: out.pnl,
+        costs: out.costs,
+        liquidated: out.liquidated.iter().map(|t| t.map_or(-1, |t| t as i32)).collect(),
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// simulate_leveraged_portfolio — margin calls path by path
+// Weights / notionals are the gross positions, financed at
+// `financing_rate` with equity = gross / leverage. Below
+// `maintenance_margin` (equity / value) holdings are sold pro rata back
+// to `target_margin` (≤ 0 = the initial 1 / leverage); at zero equity
+// the path is wiped out.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct LeveragedSimulation {
+    steps: usize,
+    pnl_paths: Vec<f32>,
+    first_call: Vec<i32>,
+    wiped_out: Vec<u8>,
+    financing: Vec<f32>,
+    call_probability: f32,
+    call_times: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl LeveragedSimulation {
+    /// [path][step] equity P&L in currency
+    #[wasm_bindgen(getter)]
+    pub fn pnl_paths(&self) -> Float32Array {
+        Float32Array::from(self.pnl_paths.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn terminal_pnl(&self) -> Float32Array {
+        let terminal: Vec<f32> = self.pnl_paths.chunks_exact(self.steps + 1).map(|row| row[self.steps]).collect();
+        Float32Array::from(terminal.as_slice())
+    }
+
+    /// Step of the first margin call per path (−1 = none)
+    #[wasm_bindgen(getter)]
+    pub fn first_call(&self) -> Int32Array {
+        Int32Array::from(self.first_call.as_slice())
+    }
+
+    /// 1 where equity was exhausted
+    #[wasm_bindgen(getter)]
+    pub fn wiped_out(&self) -> Uint8Array {
+        Uint8Array::from(self.wiped_out.as_slice())
+    }
+
+    /// Interest paid per path, in currency
+    #[wasm_bindgen(getter)]
+    pub fn financing(&self) -> Float32Array {
+        Float32Array::from(self.financing.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn call_probability(&self) -> f32 {
+        self.call_probability
+    }
+
+    /// Years to first margin call over the called paths, ascending
+    #[wasm_bindgen(getter)]
+    pub fn call_times(&self) -> Float32Array {
+        Float32Array::from(self.call_times.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_leveraged_portfolio(
+    drift: &[f32],
+    vol: &[f32],
+    cholesky_l: &[f32],
+    weights: &[f32],
+    notionals: &[f32],
+    leverage: f32,
+    financing_rate: f32,
+    maintenance_margin: f32,
+    target_margin: f32,
+    options: &SimulationOptions,
+) -> Result<LeveragedSimulation, EngineError> {
+    guard(|| {
+        let n = drift.len();
+        check_lengths(&[
+            ("vol", n, vol.len()),
+            ("cholesky_l", n * n, cholesky_l.len()),
+        ])?;
+        let p = to_portfolio(weights, notionals)?;
+        let policy = leverage::MarginPolicy {
+            leverage: leverage as f64,
+            financing_rate: financing_rate as f64,
+            maintenance_margin: maintenance_margin as f64,
+            target_margin: (target_margin > 0.0).then_some(target_margin as f64),
+        };
+        let l: Vec<f64> = cholesky_l.iter().map(|&x| x as f64).collect();
+        let out = leverage::simulate_margin(
+            &to_dvector(drift),
+            &to_dvector(vol),
+            &DMatrix::from_row_slice(n, n, &l),
+            &p,
+            &policy,
+            &options.config,
+        )?;
+        Ok(LeveragedSimulation {
+            steps: options.config.steps,
+            call_probability: out.call_probability() as f32,
+            call_times: out.call_times(&options.config).iter().map(|&t| t as f32).collect(),
+            first_call: out.first_call.iter().map(|t| t.map_or(-1, |t| t as i32)).collect(),
+            wiped_out: out.wiped_out.iter().map(|&w| w as u8).collect(),
+            financing: out.financing,
+            pnl_paths: out.pnl,
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// bump_sensitivities — which dial matters most
+// Bumps each compute_shock input by `bump` and reruns pipeline plus
+// simulator with the options' seed. `base` is [mean, std, VaR, CVaR]
+// of terminal portfolio P&L; `table` is one such row of ∂/∂input per
+// dial: N drift deltas, N vol multipliers, skew, λ, μ_J, σ_J.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct SensitivityTable {
+    base: Vec<f32>,
+    table: Vec<f32>,
+    bumps: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl SensitivityTable {
+    #[wasm_bindgen(getter)]
+    pub fn base(&self) -> Float32Array {
+        Float32Array::from(self.base.as_slice())
+    }
+
+    /// (2N + 4) × 4 row-major
+    #[wasm_bindgen(getter)]
+    pub fn table(&self) -> Float32Array {
+        Float32Array::from(self.table.as_slice())
+    }
+
+    /// Signed bump per row (skew is bumped down near 1)
+    #[wasm_bindgen(getter)]
+    pub fn bumps(&self) -> Float32Array {
+        Float32Array::from(self.bumps.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn bump_sensitivities(
+    num_assets: usize,
+    base_drift: &[f32],
+    base_vol: &[f32],
+    base_correlation: &[f32],
+    delta_drift: &[f32],
+    vol_multiplier: &[f32],
+    correlation_skew: f32,
+    jump_lambda: f32,
+    jump_mean: f32,
+    jump_vol: f32,
+    weights: &[f32],
+    level: f32,
+    bump: f32,
+    options: &SimulationOptions,
+) -> Result<SensitivityTable, EngineError> {
+    guard(|| {
+        let n = num_assets;
+        check_lengths(&[
+            ("base_drift", n, base_drift.len()),
+            ("base_vol", n, base_vol.len()),
+            ("base_correlation", n * n, base_correlation.len()),
+            ("delta_drift", n, delta_drift.len()),
+            ("vol_multiplier", n, vol_multiplier.len()),
+            ("weights", n, weights.len()),
+        ])?;
+        let inputs = sensitivity::ShockInputs {
+            base_drift: to_dvector(base_drift),
+            base_vol: to_dvector(base_vol),
+            base_corr: base_correlation_matrix(n, base_correlation, CORRELATION_TOLERANCE)?,
+            delta_drift: to_dvector(delta_drift),
+            vol_multiplier: to_dvector(vol_multiplier),
+            skew: correlation_skew as f64,
+            jumps: simulate::JumpParams {
+                lambda: jump_lambda as f64,
+                mean: jump_mean as f64,
+                vol: jump_vol as f64,
+            },
+        };
+        let p = to_portfolio(weights, &[])?;
+        let out = sensitivity::bump_and_revalue(&inputs, &p, level as f64, bump as f64, &options.config)?;
+        let row = |s: &sensitivity::PnlSummary| [s.mean as f32, s.std as f32, s.var as f32, s.cvar as f32];
+        Ok(SensitivityTable {
+            base: row(&out.base).to_vec(),
+            table: out.rows.iter().flat_map(|r| row(&r.gradient)).collect(),
+            bumps: out.rows.iter().map(|r| r.bump as f32).collect(),
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// diff_results — structured "what changed" between two scenarios
+// Element deltas are b − a; the P&L summaries ([mean, std, VaR, CVaR]
+// of terminal portfolio P&L) run both scenarios on the options' seed.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct ScenarioDiff {
+    drift: Vec<f32>,
+    vol: Vec<f32>,
+    correlation: Vec<f32>,
+    before: Vec<f32>,
+    after: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl ScenarioDiff {
+    #[wasm_bindgen(getter)]
+    pub fn drift(&self) -> Float32Array {
+        Float32Array::from(self.drift.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn vol(&self) -> Float32Array {
+        Float32Array::from(self.vol.as_slice())
+    }
+
+    /// N × N row-major change in effective correlation
+    #[wasm_bindgen(getter)]
+    pub fn correlation(&self) -> Float32Array {
+        Float32Array::from(self.correlation.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn before(&self) -> Float32Array {
+        Float32Array::from(self.before.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn after(&self) -> Float32Array {
+        Float32Array::from(self.after.as_slice())
+    }
+
+    /// after − before
+    #[wasm_bindgen(getter)]
+    pub fn summary_delta(&self) -> Float32Array {
+        let delta: Vec<f32> = self.after.iter().zip(&self.before).map(|(b, a)| b - a).collect();
+        Float32Array::from(delta.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+pub fn diff_results(
+    a: &EngineResult,
+    b: &EngineResult,
+    weights: &[f32],
+    level: f32,
+    options: &SimulationOptions,
+) -> Result<ScenarioDiff, EngineError> {
+    guard(|| {
+        let p = to_portfolio(weights, &[])?;
+        let d = sensitivity::diff_scenarios(&a.market_state(), &b.market_state(), &p, level as f64, &options.config)?;
+        let row = |s: &sensitivity::PnlSummary| vec![s.mean as f32, s.std as f32, s.var as f32, s.cvar as f32];
+        let n = d.drift.len();
+        Ok(ScenarioDiff {
+            drift: d.drift.iter().map(|&x| x as f32).collect(),
+            vol: d.vol.iter().map(|&x| x as f32).collect(),
+            correlation: (0..n * n).map(|k| d.correlation[(k / n, k % n)] as f32).collect(),
+            before: row(&d.before),
+            after: row(&d.after),
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// replay_historical — a supplied return history as paths
+// returns is T × N row-major simple returns per period; window ≤ T
+// steps per path (T − window + 1 overlapping paths). An empty
+// vol_multiplier replays the history unscaled. Pass `options` to
+// percentile_fan, terminal_pnl_from_paths etc. with `paths`.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct HistoricalReplay {
+    paths: Vec<f32>,
+    config: simulate::SimConfig,
+}
+
+#[wasm_bindgen]
+impl HistoricalReplay {
+    /// [path][step][asset], S₀ = 1
+    #[wasm_bindgen(getter)]
+    pub fn paths(&self) -> Float32Array {
+        Float32Array::from(self.paths.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn n_paths(&self) -> usize {
+        self.config.n_paths
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn steps(&self) -> usize {
+        self.config.steps
+    }
+
+    /// Options describing the replay's shape
+    #[wasm_bindgen(getter)]
+    pub fn options(&self) -> SimulationOptions {
+        SimulationOptions { config: self.config.clone() }
+    }
+}
+
+#[wasm_bindgen]
+pub fn replay_historical(
+    returns: &[f32],
+    num_assets: usize,
+    window: usize,
+    periods_per_year: f32,
+    vol_multiplier: &[f32],
+) -> Result<HistoricalReplay, EngineError> {
+    guard(|| {
+        let r: Vec<f64> = returns.iter().map(|&x| x as f64).collect();
+        let m = (!vol_multiplier.is_empty()).then(|| to_dvector(vol_multiplier));
+        let out = replay::replay_paths(&r, num_assets, window, periods_per_year as f64, m.as_ref())?;
+        Ok(HistoricalReplay { paths: out.paths, config: out.config })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// bootstrap_historical — block-bootstrapped paths from a return history
+// returns is T × N row-major simple returns per period; each path of
+// options.steps periods (options.n_paths paths, options.seed) joins
+// blocks of `block` consecutive rows. With cholesky_l (N×N, carrying
+// σ) and drift the rows are whitened and recolored to that shocked
+// market; else a non-empty vol_multiplier rescales them about their
+// mean; else they are used as-is. options' horizon is replaced by
+// steps / periods_per_year.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn bootstrap_historical(
+    returns: &[f32],
+    num_assets: usize,
+    block: usize,
+    periods_per_year: f32,
+    options: &SimulationOptions,
+    vol_multiplier: &[f32],
+    drift: &[f32],
+    cholesky_l: &[f32],
+) -> Result<HistoricalReplay, EngineError> {
+    guard(|| {
+        let n = num_assets;
+        let scaling = if !cholesky_l.is_empty() {
+            check_lengths(&[("drift", n, drift.len()), ("cholesky_l", n * n, cholesky_l.len())])?;
+            let l: Vec<f64> = cholesky_l.iter().map(|&x| x as f64).collect();
+            replay::BootstrapScaling::Market { drift: to_dvector(drift), cholesky_l: DMatrix::from_row_slice(n, n, &l) }
+        } else if !vol_multiplier.is_empty() {
+            replay::BootstrapScaling::VolMultiplier(to_dvector(vol_multiplier))
+        } else {
+            replay::BootstrapScaling::None
+        };
+        let r: Vec<f64> = returns.iter().map(|&x| x as f64).collect();
+        let c = &options.config;
+        let out = replay::bootstrap_paths(&r, n, block, periods_per_year as f64, c.steps, c.n_paths, c.seed, &scaling)?;
+        Ok(HistoricalReplay { paths: out.paths, config: out.config })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// market_from_returns — base market from a CSV / TSV returns panel
+// Header of asset names plus a date column; empty cells are missing,
+// so assets may start late. Drift and vol use each asset's own
+// history, the correlation the rows where all assets are observed.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct HistoricalMarket {
+    assets: Vec<String>,
+    drift: Vec<f32>,
+    vol: Vec<f32>,
+    correlation: Vec<f32>,
+    observations: Vec<u32>,
+    overlap_rows: usize,
+}
+
+#[wasm_bindgen]
+impl HistoricalMarket {
+    #[wasm_bindgen(getter)]
+    pub fn assets(&self) -> Vec<String> {
+        self.assets.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn drift(&self) -> Float32Array {
+        Float32Array::from(self.drift.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn vol(&self) -> Float32Array {
+        Float32Array::from(self.vol.as_slice())
+    }
+
+    /// N×N row-major
+    #[wasm_bindgen(getter)]
+    pub fn correlation(&self) -> Float32Array {
+        Float32Array::from(self.correlation.as_slice())
+    }
+
+    /// Observations per asset behind drift and vol
+    #[wasm_bindgen(getter)]
+    pub fn observations(&self) -> Uint32Array {
+        Uint32Array::from(self.observations.as_slice())
+    }
+
+    /// Rows behind the correlation
+    #[wasm_bindgen(getter)]
+    pub fn overlap_rows(&self) -> usize {
+        self.overlap_rows
+    }
+
+    /// Unshocked ShockConfig over this market
+    pub fn shock_config(&self) -> ShockConfig {
+        ShockConfig::new(&self.drift, &self.vol, &self.correlation)
+    }
+}
+
+#[wasm_bindgen]
+pub fn market_from_returns(text: &str, log_returns: bool, periods_per_year: f32) -> Result<HistoricalMarket, EngineError> {
+    guard(|| {
+        let panel = data::parse_returns(text)?;
+        let kind = if log_returns { data::ReturnKind::Log } else { data::ReturnKind::Simple };
+        let m = data::market_inputs(&panel, kind, periods_per_year as f64)?;
+        let n = panel.num_assets();
+        Ok(HistoricalMarket {
+            assets: panel.assets,
+            drift: m.drift.iter().map(|&x| x as f32).collect(),
+            vol: m.vol.iter().map(|&x| x as f32).collect(),
+            correlation: (0..n * n).map(|k| m.correlation[(k / n, k % n)] as f32).collect(),
+            observations: m.observations.iter().map(|&x| x as u32).collect(),
+            overlap_rows: m.overlap_rows,
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// preprocess_returns — drop flagged rows, winsorize each asset at its
+// [p, 1 − p] quantiles (p = 0 is off) and clip |return| to `clip`
+// (0 is off) before calibrating. NaN cells stay missing. The report
+// lists every changed cell in input row numbers.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct PreprocessedReturns {
+    returns: Vec<f32>,
+    num_rows: usize,
+    dropped_rows: Vec<u32>,
+    lower: Vec<f32>,
+    upper: Vec<f32>,
+    adjusted_rows: Vec<u32>,
+    adjusted_assets: Vec<u32>,
+    original_values: Vec<f32>,
+    adjusted_values: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl PreprocessedReturns {
+    /// T' × N row-major, for the calibration entry points
+    #[wasm_bindgen(getter)]
+    pub fn returns(&self) -> Float32Array {
+        Float32Array::from(self.returns.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn dropped_rows(&self) -> Uint32Array {
+        Uint32Array::from(self.dropped_rows.as_slice())
+    }
+
+    /// Winsorizing bounds per asset (±Infinity when off)
+    #[wasm_bindgen(getter)]
+    pub fn lower(&self) -> Float32Array {
+        Float32Array::from(self.lower.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn upper(&self) -> Float32Array {
+        Float32Array::from(self.upper.as_slice())
+    }
+
+    /// Changed cells: input row, asset, value before and after
+    #[wasm_bindgen(getter)]
+    pub fn adjusted_rows(&self) -> Uint32Array {
+        Uint32Array::from(self.adjusted_rows.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn adjusted_assets(&self) -> Uint32Array {
+        Uint32Array::from(self.adjusted_assets.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn original_values(&self) -> Float32Array {
+        Float32Array::from(self.original_values.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn adjusted_values(&self) -> Float32Array {
+        Float32Array::from(self.adjusted_values.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+pub fn preprocess_returns(
+    returns: &[f32],
+    num_assets: usize,
+    winsorize: f32,
+    clip: f32,
+    drop_rows: &[u32],
+) -> Result<PreprocessedReturns, EngineError> {
+    guard(|| {
+        let options = calibrate::Preprocessing {
+            winsorize: winsorize as f64,
+            clip: (clip != 0.0).then_some(clip as f64),
+            drop_rows: drop_rows.iter().map(|&r| r as usize).collect(),
+        };
+        let r: Vec<f64> = returns.iter().map(|&x| x as f64).collect();
+        let (out, report) = calibrate::preprocess_returns(&r, num_assets, &options)?;
+        Ok(PreprocessedReturns {
+            num_rows: out.len() / num_assets,
+            returns: out.iter().map(|&x| x as f32).collect(),
+            dropped_rows: report.dropped_rows.iter().map(|&r| r as u32).collect(),
+            lower: report.lower.iter().map(|&x| x as f32).collect(),
+            upper: report.upper.iter().map(|&x| x as f32).collect(),
+            adjusted_rows: report.adjustments.iter().map(|a| a.row as u32).collect(),
+            adjusted_assets: report.adjustments.iter().map(|a| a.asset as u32).collect(),
+            original_values: report.adjustments.iter().map(|a| a.original as f32).collect(),
+            adjusted_values: report.adjustments.iter().map(|a| a.value as f32).collect(),
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// correlation_from_returns — base vol and R from a T × N log-return
+// matrix, equal-weighted ("sample") or RiskMetrics EWMA ("ewma" with
+// decay λ, e.g. 0.94 daily; lambda is ignored for "sample")
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct CorrelationEstimate {
+    vol: Vec<f32>,
+    correlation: Vec<f32>,
+    effective_observations: f32,
+}
+
+#[wasm_bindgen]
+impl CorrelationEstimate {
+    /// Annualized
+    #[wasm_bindgen(getter)]
+    pub fn vol(&self) -> Float32Array {
+        Float32Array::from(self.vol.as_slice())
+    }
+
+    /// N×N row-major
+    #[wasm_bindgen(getter)]
+    pub fn correlation(&self) -> Float32Array {
+        Float32Array::from(self.correlation.as_slice())
+    }
+
+    /// T for sample, 1 / Σwᵢ² for EWMA
+    #[wasm_bindgen(getter)]
+    pub fn effective_observations(&self) -> f32 {
+        self.effective_observations
+    }
+
+    /// Unshocked ShockConfig with these vols and R
+    pub fn shock_config(&self, base_drift: &[f32]) -> Result<ShockConfig, EngineError> {
+        guard(|| {
+            check_lengths(&[("base_drift", self.vol.len(), base_drift.len())])?;
+            Ok(ShockConfig::new(base_drift, &self.vol, &self.correlation))
+        })
+    }
+}
+
+#[wasm_bindgen]
+pub fn correlation_from_returns(
+    returns: &[f32],
+    num_assets: usize,
+    method: &str,
+    lambda: f32,
+    periods_per_year: f32,
+) -> Result<CorrelationEstimate, EngineError> {
+    guard(|| {
+        let method = calibrate::CorrelationMethod::from_name(method, lambda as f64)?;
+        let r: Vec<f64> = returns.iter().map(|&x| x as f64).collect();
+        let e = calibrate::correlation_from_returns(&r, num_assets, method, periods_per_year as f64)?;
+        Ok(CorrelationEstimate {
+            vol: e.vol.iter().map(|&x| x as f32).collect(),
+            correlation: e.correlation.transpose().iter().map(|&x| x as f32).collect(),
+            effective_observations: e.effective_observations as f32,
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// pairwise_correlation — T × N log returns with NaN for missing cells
+// (e.g. assets with later inception dates). Each pair's correlation
+// uses every row both assets have; pairs with fewer than min_overlap
+// shared rows get 0. An indefinite result is repaired to the nearest
+// correlation matrix; pair_counts says how much data each entry has.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct PairwiseCorrelation {
+    vol: Vec<f32>,
+    correlation: Vec<f32>,
+    raw_correlation: Vec<f32>,
+    pair_counts: Vec<u32>,
+    min_eigenvalue_before: f32,
+    repaired: bool,
+}
+
+#[wasm_bindgen]
+impl PairwiseCorrelation {
+    /// Annualized, from each asset's own observations
+    #[wasm_bindgen(getter)]
+    pub fn vol(&self) -> Float32Array {
+        Float32Array::from(self.vol.as_slice())
+    }
+
+    /// N×N row-major, PD
+    #[wasm_bindgen(getter)]
+    pub fn correlation(&self) -> Float32Array {
+        Float32Array::from(self.correlation.as_slice())
+    }
+
+    /// Before the repair
+    #[wasm_bindgen(getter)]
+    pub fn raw_correlation(&self) -> Float32Array {
+        Float32Array::from(self.raw_correlation.as_slice())
+    }
+
+    /// Shared observations per pair, N×N row-major (diagonal: per asset)
+    #[wasm_bindgen(getter)]
+    pub fn pair_counts(&self) -> Uint32Array {
+        Uint32Array::from(self.pair_counts.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn min_eigenvalue_before(&self) -> f32 {
+        self.min_eigenvalue_before
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn repaired(&self) -> bool {
+        self.repaired
+    }
+
+    /// Unshocked ShockConfig with these vols and the repaired R
+    pub fn shock_config(&self, base_drift: &[f32]) -> Result<ShockConfig, EngineError> {
+        guard(|| {
+            check_lengths(&[("base_drift", self.vol.len(), base_drift.len())])?;
+            Ok(ShockConfig::new(base_drift, &self.vol, &self.correlation))
+        })
+    }
+}
+
+#[wasm_bindgen]
+pub fn pairwise_correlation(
+    returns: &[f32],
+    num_assets: usize,
+    min_overlap: usize,
+    periods_per_year: f32,
+) -> Result<PairwiseCorrelation, EngineError> {
+    guard(|| {
+        let r: Vec<f64> = returns.iter().map(|&x| x as f64).collect();
+        let e = calibrate::pairwise_correlation(&r, num_assets, min_overlap, periods_per_year as f64)?;
+        Ok(PairwiseCorrelation {
+            vol: e.vol.iter().map(|&x| x as f32).collect(),
+            correlation: e.correlation.transpose().iter().map(|&x| x as f32).collect(),
+            raw_correlation: e.raw_correlation.transpose().iter().map(|&x| x as f32).collect(),
+            pair_counts: e.counts.transpose().iter().map(|&c| c as u32).collect(),
+            min_eigenvalue_before: e.min_eigenvalue_before as f32,
+            repaired: e.repaired,
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// denoise_correlation — Marchenko–Pastur clipping of an estimated R:
+// eigenvalues inside the noise band for N assets over `observations`
+// periods (T, or effective_observations for EWMA) are replaced by
+// their average, leaving a better-conditioned base correlation.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct DenoisedCorrelation {
+    correlation: Vec<f32>,
+    eigenvalues: Vec<f32>,
+    lambda_max: f32,
+    signal_count: usize,
+    condition_before: f32,
+    condition_after: f32,
+}
+
+#[wasm_bindgen]
+impl DenoisedCorrelation {
+    /// N×N row-major
+    #[wasm_bindgen(getter)]
+    pub fn correlation(&self) -> Float32Array {
+        Float32Array::from(self.correlation.as_slice())
+    }
+
+    /// Input eigenvalues, descending
+    #[wasm_bindgen(getter)]
+    pub fn eigenvalues(&self) -> Float32Array {
+        Float32Array::from(self.eigenvalues.as_slice())
+    }
+
+    /// Upper edge of the noise band
+    #[wasm_bindgen(getter)]
+    pub fn lambda_max(&self) -> f32 {
+        self.lambda_max
+    }
+
+    /// Eigenvalues kept as signal
+    #[wasm_bindgen(getter)]
+    pub fn signal_count(&self) -> usize {
+        self.signal_count
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn condition_before(&self) -> f32 {
+        self.condition_before
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn condition_after(&self) -> f32 {
+        self.condition_after
+    }
+}
+
+#[wasm_bindgen]
+pub fn denoise_correlation(
+    num_assets: usize,
+    correlation: &[f32],
+    observations: f32,
+) -> Result<DenoisedCorrelation, EngineError> {
+    guard(|| {
+        let r = base_correlation_matrix(num_assets, correlation, CORRELATION_TOLERANCE)?;
+        let d = calibrate::denoise_correlation(&r, observations as f64)?;
+        Ok(DenoisedCorrelation {
+            correlation: d.correlation.transpose().iter().map(|&x| x as f32).collect(),
+            eigenvalues: d.eigenvalues.iter().map(|&x| x as f32).collect(),
+            lambda_max: d.lambda_max as f32,
+            signal_count: d.signal_count,
+            condition_before: d.condition_before as f32,
+            condition_after: d.condition_after as f32,
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// rolling_correlation — correlation_from_returns over trailing windows
+// of `window` rows every `stride` rows, ending at the last row. Each
+// window is a base market: shock_config(i, drift) feeds Engine or
+// interpolate_shock frame by frame, and distance_to compares every
+// window with a (shocked) correlation.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct RollingCalibration {
+    num_assets: usize,
+    starts: Vec<u32>,
+    ends: Vec<u32>,
+    vol: Vec<f32>,
+    correlation: Vec<f32>,
+    average_correlation: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl RollingCalibration {
+    /// Number of windows
+    #[wasm_bindgen(getter)]
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// First row of each window
+    #[wasm_bindgen(getter)]
+    pub fn starts(&self) -> Uint32Array {
+        Uint32Array::from(self.starts.as_slice())
+    }
+
+    /// One past the last row of each window
+    #[wasm_bindgen(getter)]
+    pub fn ends(&self) -> Uint32Array {
+        Uint32Array::from(self.ends.as_slice())
+    }
+
+    /// [window][asset], annualized
+    #[wasm_bindgen(getter)]
+    pub fn vol(&self) -> Float32Array {
+        Float32Array::from(self.vol.as_slice())
+    }
+
+    /// [window][N×N row-major]
+    #[wasm_bindgen(getter)]
+    pub fn correlation(&self) -> Float32Array {
+        Float32Array::from(self.correlation.as_slice())
+    }
+
+    /// Mean off-diagonal correlation per window
+    #[wasm_bindgen(getter)]
+    pub fn average_correlation(&self) -> Float32Array {
+        Float32Array::from(self.average_correlation.as_slice())
+    }
+
+    /// Unshocked ShockConfig for window `index`
+    pub fn shock_config(&self, index: usize, base_drift: &[f32]) -> Result<ShockConfig, EngineError> {
+        guard(|| {
+            let n = self.num_assets;
+            if index >= self.len() {
+                return Err("Calibration input invalid: window index out of range".into());
+            }
+            check_lengths(&[("base_drift", n, base_drift.len())])?;
+            Ok(ShockConfig::new(
+                base_drift,
+                &self.vol[index * n..(index + 1) * n],
+                &self.correlation[index * n * n..(index + 1) * n * n],
+            ))
+        })
+    }
+
+    /// Frobenius distance ‖R_window − R‖ per window
+    pub fn distance_to(&self, correlation: &[f32]) -> Result<Float32Array, EngineError> {
+        guard(|| {
+            let nn = self.num_assets * self.num_assets;
+            check_lengths(&[("correlation", nn, correlation.len())])?;
+            let d: Vec<f32> = self
+                .correlation
+                .chunks_exact(nn)
+                .map(|w| w.iter().zip(correlation).map(|(a, b)| (a - b) * (a - b)).sum::<f32>().sqrt())
+                .collect();
+            Ok(Float32Array::from(d.as_slice()))
+        })
+    }
+}
+
+#[wasm_bindgen]
+pub fn rolling_correlation(
+    returns: &[f32],
+    num_assets: usize,
+    window: usize,
+    stride: usize,
+    method: &str,
+    lambda: f32,
+    periods_per_year: f32,
+) -> Result<RollingCalibration, EngineError> {
+    guard(|| {
+        let method = calibrate::CorrelationMethod::from_name(method, lambda as f64)?;
+        let r: Vec<f64> = returns.iter().map(|&x| x as f64).collect();
+        let windows = calibrate::rolling_correlation(&r, num_assets, window, stride, method, periods_per_year as f64)?;
+        Ok(RollingCalibration {
+            num_assets,
+            starts: windows.iter().map(|w| w.start as u32).collect(),
+            ends: windows.iter().map(|w| w.end as u32).collect(),
+            vol: windows.iter().flat_map(|w| w.estimate.vol.iter().map(|&x| x as f32)).collect(),
+            correlation: windows
+                .iter()
+                .flat_map(|w| w.estimate.correlation.transpose().iter().map(|&x| x as f32).collect::<Vec<_>>())
+                .collect(),
+            average_correlation: windows
+                .iter()
+                .map(|w| calibrate::average_correlation(&w.estimate.correlation) as f32)
+                .collect(),
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// estimate_jumps — per-asset jump parameters from a T × N log-return
+// matrix (bipower-variation threshold, typically 4), ready for
+// ShockConfig.set_asset_jumps(jump_lambda, jump_mean, jump_vol)
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct JumpEstimates {
+    jump_lambda: Vec<f32>,
+    jump_mean: Vec<f32>,
+    jump_vol: Vec<f32>,
+    diffusive_vol: Vec<f32>,
+    jump_counts: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl JumpEstimates {
+    /// Jumps per year
+    #[wasm_bindgen(getter)]
+    pub fn jump_lambda(&self) -> Float32Array {
+        Float32Array::from(self.jump_lambda.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn jump_mean(&self) -> Float32Array {
+        Float32Array::from(self.jump_mean.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn jump_vol(&self) -> Float32Array {
+        Float32Array::from(self.jump_vol.as_slice())
+    }
+
+    /// Annualized vol with the jump rows left out
+    #[wasm_bindgen(getter)]
+    pub fn diffusive_vol(&self) -> Float32Array {
+        Float32Array::from(self.diffusive_vol.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn jump_counts(&self) -> Uint32Array {
+        Uint32Array::from(self.jump_counts.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+pub fn estimate_jumps(
+    returns: &[f32],
+    num_assets: usize,
+    threshold: f32,
+    periods_per_year: f32,
+) -> Result<JumpEstimates, EngineError> {
+    guard(|| {
+        let r: Vec<f64> = returns.iter().map(|&x| x as f64).collect();
+        let e = calibrate::estimate_jumps(&r, num_assets, threshold as f64, periods_per_year as f64)?;
+        Ok(JumpEstimates {
+            jump_lambda: e.iter().map(|j| j.lambda as f32).collect(),
+            jump_mean: e.iter().map(|j| j.mean as f32).collect(),
+            jump_vol: e.iter().map(|j| j.vol as f32).collect(),
+            diffusive_vol: e.iter().map(|j| j.diffusive_vol as f32).collect(),
+            jump_counts: e.iter().map(|j| j.jumps.len() as u32).collect(),
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// fit_garch — per-asset GARCH(1,1) maximum likelihood on a T × N
+// log-return matrix. ω, α, β are per data period, the per-step units
+// of SimulationOptions.set_garch when steps = horizon × periods_per_year.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct GarchFits {
+    fits: Vec<calibrate::GarchFit>,
+}
+
+#[wasm_bindgen]
+impl GarchFits {
+    #[wasm_bindgen(getter)]
+    pub fn omega(&self) -> Float32Array {
+        self.collect(|f| f.params.omega)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn alpha(&self) -> Float32Array {
+        self.collect(|f| f.params.alpha)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn beta(&self) -> Float32Array {
+        self.collect(|f| f.params.beta)
+    }
+
+    /// Annualized √(ω / (1 − α − β))
+    #[wasm_bindgen(getter)]
+    pub fn long_run_vol(&self) -> Float32Array {
+        self.collect(|f| f.long_run_vol)
+    }
+
+    /// Annualized conditional vol for the period after the sample
+    #[wasm_bindgen(getter)]
+    pub fn current_vol(&self) -> Float32Array {
+        self.collect(|f| f.current_vol)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn log_likelihood(&self) -> Float32Array {
+        self.collect(|f| f.log_likelihood)
+    }
+
+    /// 1 where the optimizer converged
+    #[wasm_bindgen(getter)]
+    pub fn converged(&self) -> Uint8Array {
+        let flags: Vec<u8> = self.fits.iter().map(|f| f.converged as u8).collect();
+        Uint8Array::from(flags.as_slice())
+    }
+}
+
+impl GarchFits {
+    fn collect(&self, field: impl Fn(&calibrate::GarchFit) -> f64) -> Float32Array {
+        let xs: Vec<f32> = self.fits.iter().map(|f| field(f) as f32).collect();
+        Float32Array::from(xs.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+pub fn fit_garch(returns: &[f32], num_assets: usize, periods_per_year: f32) -> Result<GarchFits, EngineError> {
+    guard(|| {
+        let r: Vec<f64> = returns.iter().map(|&x| x as f64).collect();
+        Ok(GarchFits { fits: calibrate::fit_garch(&r, num_assets, periods_per_year as f64)? })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// black_litterman — posterior drift from equilibrium and views
+// Π = δ·Σ·w over the shocked Σ = L·Lᵀ of `result`. View k is row k of
+// view_portfolios (K×N row-major) with annual drift view_returns[k]
+// and confidence view_confidences[k] ∈ (0, 1] (1 forces the view).
+// The posterior is a drift vector; as ShockConfig input it becomes
+// delta_drift = posterior − base drift.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct BlackLittermanResult {
+    equilibrium_drift: Vec<f32>,
+    posterior_drift: Vec<f32>,
+    posterior_covariance: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl BlackLittermanResult {
+    /// r_f + δ·Σ·w
+    #[wasm_bindgen(getter)]
+    pub fn equilibrium_drift(&self) -> Float32Array {
+        Float32Array::from(self.equilibrium_drift.as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn posterior_drift(&self) -> Float32Array {
+        Float32Array::from(self.posterior_drift.as_slice())
+    }
+
+    /// Σ plus the posterior uncertainty of the mean, N×N row-major
+    #[wasm_bindgen(getter)]
+    pub fn posterior_covariance(&self) -> Float32Array {
+        Float32Array::from(self.posterior_covariance.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn black_litterman(
+    result: &EngineResult,
+    market_weights: &[f32],
+    risk_aversion: f32,
+    tau: f32,
+    risk_free: f32,
+    view_portfolios: &[f32],
+    view_returns: &[f32],
+    view_confidences: &[f32],
+) -> Result<BlackLittermanResult, EngineError> {
+    guard(|| {
+        let n = result.num_assets;
+        let k = view_returns.len();
+        check_lengths(&[
+            ("market_weights", n, market_weights.len()),
+            ("view_portfolios", k * n, view_portfolios.len()),
+            ("view_confidences", k, view_confidences.len()),
+        ])?;
+        let l = DMatrix::from_row_iterator(n, n, result.cholesky_l.iter().map(|&x| x as f64));
+        let views: Vec<calibrate::View> = (0..k)
+            .map(|i| calibrate::View {
+                portfolio: view_portfolios[i * n..(i + 1) * n].iter().map(|&x| x as f64).collect(),
+                expected: view_returns[i] as f64,
+                confidence: view_confidences[i] as f64,
+            })
+            .collect();
+        let bl = calibrate::black_litterman(
+            &(&l * l.transpose()),
+            &to_dvector(market_weights),
+            risk_aversion as f64,
+            tau as f64,
+            risk_free as f64,
+            &views,
+        )?;
+        Ok(BlackLittermanResult {
+            equilibrium_drift: bl.equilibrium.iter().map(|&x| x as f32).collect(),
+            posterior_drift: bl.posterior.iter().map(|&x| x as f32).collect(),
+            posterior_covariance: bl.posterior_covariance.transpose().iter().map(|&x| x as f32).collect(),
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// cluster_correlation — hierarchical clustering of a base or shocked
+// R (e.g. EngineResult.adjusted_correlation) with "single", "complete"
+// or "average" linkage. `order` permutes rows and columns of a heatmap
+// so the clusters sit on the diagonal; `linkage` is scipy's matrix.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct CorrelationClusters {
+    dendrogram: structure::Dendrogram,
+}
+
+#[wasm_bindgen]
+impl CorrelationClusters {
+    /// (N − 1) × 4 row-major: left id, right id, distance, size (ids
+    /// ≥ N are merge row − N)
+    #[wasm_bindgen(getter)]
+    pub fn linkage(&self) -> Float32Array {
+        let rows: Vec<f32> = self
+            .dendrogram
+            .merges
+            .iter()
+            .flat_map(|m| [m.left as f32, m.right as f32, m.distance as f32, m.size as f32])
+            .collect();
+        Float32Array::from(rows.as_slice())
+    }
+
+    /// Assets in dendrogram leaf order
+    #[wasm_bindgen(getter)]
+    pub fn order(&self) -> Uint32Array {
+        let order: Vec<u32> = self.dendrogram.order.iter().map(|&a| a as u32).collect();
+        Uint32Array::from(order.as_slice())
+    }
+
+    /// Cluster label per asset with the tree cut into k groups
+    pub fn clusters(&self, k: usize) -> Result<Uint32Array, EngineError> {
+        guard(|| {
+            let labels: Vec<u32> = self.dendrogram.clusters(k)?.iter().map(|&l| l as u32).collect();
+            Ok(Uint32Array::from(labels.as_slice()))
+        })
+    }
+}
+
+#[wasm_bindgen]
+pub fn cluster_correlation(
+    num_assets: usize,
+    correlation: &[f32],
+    linkage: &str,
+) -> Result<CorrelationClusters, EngineError> {
+    guard(|| {
+        let linkage = structure::Linkage::from_name(linkage)?;
+        let r = base_correlation_matrix(num_assets, correlation, CORRELATION_TOLERANCE)?;
+        Ok(CorrelationClusters { dendrogram: structure::cluster_correlation(&r, linkage)? })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// pca — top-k principal components of an N×N covariance (e.g.
+// EngineResult.covariance under the shock) or correlation
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct PcaResult {
+    eigenvalues: Vec<f32>,
+    explained_variance_ratio: Vec<f32>,
+    loadings: Vec<f32>,
+}
+
+#[wasm_bindgen]
+impl PcaResult {
+    /// k values, descending
+    #[wasm_bindgen(getter)]
+    pub fn eigenvalues(&self) -> Float32Array {
+        Float32Array::from(self.eigenvalues.as_slice())
+    }
+
+    /// Share of total variance per component
+    #[wasm_bindgen(getter)]
+    pub fn explained_variance_ratio(&self) -> Float32Array {
+        Float32Array::from(self.explained_variance_ratio.as_slice())
+    }
+
+    /// N×k row-major (column i is component i, signed to sum ≥ 0)
+    #[wasm_bindgen(getter)]
+    pub fn loadings(&self) -> Float32Array {
+        Float32Array::from(self.loadings.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+pub fn pca(num_assets: usize, covariance: &[f32], k: usize) -> Result<PcaResult, EngineError> {
+    guard(|| {
+        check_lengths(&[("covariance", num_assets * num_assets, covariance.len())])?;
+        check_finite(&[("covariance", covariance)])?;
+        let cov = DMatrix::from_row_iterator(num_assets, num_assets, covariance.iter().map(|&x| x as f64));
+        let p = structure::pca(&cov, k)?;
+        Ok(PcaResult {
+            eigenvalues: p.eigenvalues.iter().map(|&x| x as f32).collect(),
+            explained_variance_ratio: p.explained_variance_ratio.iter().map(|&x| x as f32).collect(),
+            loadings: p.loadings.transpose().iter().map(|&x| x as f32).collect(),
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// SimulationStream — simulate_with_options in batches of paths
+// Concatenated chunks equal the one-shot output; use it when the full
+// n_paths × (steps + 1) × N buffer would not fit in wasm memory.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct SimulationStream {
+    stream: simulate::PathStream,
+}
+
+#[wasm_bindgen]
+impl SimulationStream {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        drift: &[f32],
+        vol: &[f32],
+        cholesky_l: &[f32],
+        options: &SimulationOptions,
+    ) -> Result<SimulationStream, EngineError> {
+        guard(|| {
+            let n = drift.len();
+            check_lengths(&[
+                ("vol", n, vol.len()),
+                ("cholesky_l", n * n, cholesky_l.len()),
+            ])?;
+            let l: Vec<f64> = cholesky_l.iter().map(|&x| x as f64).collect();
+            let stream = simulate::PathStream::new(
+                &to_dvector(drift),
+                &to_dvector(vol),
+                &DMatrix::from_row_slice(n, n, &l),
+                &options.config,
+            )?;
+            Ok(SimulationStream { stream })
+        })
+    }
+
+    /// Next ≤ n_paths paths; an empty array once the run is exhausted.
+    pub fn next_chunk(&mut self, n_paths: usize) -> Float32Array {
+        Float32Array::from(self.stream.next_chunk(n_paths).as_slice())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn remaining(&self) -> usize {
+        self.stream.remaining()
+    }
+
+    pub fn reset(&mut self) {
+        self.stream.reset();
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Progress callbacks — one-shot runs that report as they go
+// `on_progress(stage, done, total)` is called after every slice of
+// work with stage "repair" (Higham iterations) or "simulate" (paths);
+// returning `false` stops the run with a CANCELLED error. Between
+// calls nothing yields to the event loop, so for a UI that must stay
+// responsive drive ComputeHandle / SimulationStream from JS instead.
+// ════════════════════════════════════════════════════════════════
+fn report_progress(on_progress: &js_sys::Function, stage: &str, done: usize, total: usize) -> Result<(), EngineError> {
+    let keep_going = on_progress
+        .call3(&JsValue::NULL, &JsValue::from_str(stage), &JsValue::from(done as u32), &JsValue::from(total as u32))
+        .map_err(|_| EngineError::InvalidInput { reason: "Progress callback threw" })?;
+    if keep_going == JsValue::FALSE {
+        return Err(EngineError::Cancelled);
+    }
+    Ok(())
+}
+
+/// compute_shock_config, reporting every `iterations_per_event` Higham
+/// iterations (total = the iteration cap; the repair may stop early).
+#[wasm_bindgen]
+pub fn compute_shock_with_progress(
+    config: &ShockConfig,
+    iterations_per_event: usize,
+    on_progress: &js_sys::Function,
+) -> Result<EngineResult, EngineError> {
+    guard(|| {
+        let mut handle = ComputeHandle::new(config)?;
+        while !handle.step(iterations_per_event.max(1)) && !handle.done() {
+            report_progress(on_progress, "repair", handle.iterations(), handle.max_iterations())?;
+        }
+        report_progress(on_progress, "repair", handle.iterations(), handle.iterations())?;
+        handle.finish()
+    })
+}
+
+/// simulate_with_options in batches of `batch_paths`, reporting paths
+/// done after each. Planar layout and moment matching apply per batch,
+/// as with SimulationStream.
+#[wasm_bindgen]
+pub fn simulate_with_progress(
+    drift: &[f32],
+    vol: &[f32],
+    cholesky_l: &[f32],
+    options: &SimulationOptions,
+    batch_paths: usize,
+    on_progress: &js_sys::Function,
+) -> Result<Float32Array, EngineError> {
+    guard(|| {
+        let mut stream = SimulationStream::new(drift, vol, cholesky_l, options)?.stream;
+        let total = options.config.n_paths;
+        let mut paths = Vec::new();
+        while stream.remaining() > 0 {
+            paths.extend(stream.next_chunk(batch_paths.max(1)));
+            report_progress(on_progress, "simulate", total - stream.remaining(), total)?;
+        }
+        Ok(Float32Array::from(paths.as_slice()))
+    })
+}
+
+/// Live fan chart: simulate in batches of `batch_paths` and, every
+/// `every` batches (and once at the end), call
+/// `on_frame(paths_done, total, fan)` with the portfolio's
+/// [step][percentile] fan so far (risk::FanAccumulator, 2048 bins).
+/// Paths are not kept; the final fan is returned.
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen]
+pub fn simulate_fan_stream(
+    drift: &[f32],
+    vol: &[f32],
+    cholesky_l: &[f32],
+    options: &SimulationOptions,
+    weights: &[f32],
+    percentiles: &[f32],
+    batch_paths: usize,
+    every: usize,
+    on_frame: &js_sys::Function,
+) -> Result<Float32Array, EngineError> {
+    guard(|| {
+        if options.config.layout != simulate::OutputLayout::Interleaved {
+            return Err(EngineError::InvalidInput { reason: "Fan stream input invalid: needs the interleaved layout" });
+        }
+        let mut stream = SimulationStream::new(drift, vol, cholesky_l, options)?.stream;
+        let w: Vec<f64> = weights.iter().map(|&x| x as f64).collect();
+        let q: Vec<f64> = percentiles.iter().map(|&x| x as f64).collect();
+        let mut fan = risk::FanAccumulator::new(drift.len(), options.config.steps, &w, 2048)?;
+        let total = options.config.n_paths;
+        let mut batches = 0;
+        loop {
+            fan.add(&stream.next_chunk(batch_paths.max(1)))?;
+            batches += 1;
+            let last = stream.remaining() == 0;
+            if last || batches % every.max(1) == 0 {
+                let frame = Float32Array::from(fan.percentiles(&q)?.as_slice());
+                let keep_going = on_frame
+                    .call3(&JsValue::NULL, &JsValue::from(fan.n_paths() as u32), &JsValue::from(total as u32), &frame)
+                    .map_err(|_| EngineError::InvalidInput { reason: "Frame callback threw" })?;
+                if keep_going == JsValue::FALSE {
+                    return Err(EngineError::Cancelled);
+                }
+                if last {
+                    return Ok(frame);
+                }
+            }
+        }
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// simulate_regimes — Markov regime switching over K shocked markets
+// Each regime row (K×N, row-major) is a delta-drift / vol-multiplier
+// pair plus a skew, run through Phase A against the shared base.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub struct RegimeSimulation {
+    paths: Vec<f32>,
+    regimes: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl RegimeSimulation {
+    /// n_paths × (steps + 1) × N in the options' layout
+    #[wasm_bindgen(getter)]
+    pub fn paths(&self) -> Float32Array {
+        Float32Array::from(self.paths.as_slice())
+    }
+
+    /// n_paths × steps regime indices
+    #[wasm_bindgen(getter)]
+    pub fn regimes(&self) -> Uint8Array {
+        Uint8Array::from(self.regimes.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn simulate_regimes(
+    num_assets: usize,
+    base_drift: &[f32],
+    base_vol: &[f32],
+    base_correlation: &[f32],
+    regime_delta_drift: &[f32],
+    regime_vol_multiplier: &[f32],
+    regime_skew: &[f32],
+    transition: &[f32],
+    initial_regime: usize,
+    options: &SimulationOptions,
+) -> Result<RegimeSimulation, EngineError> {
+    guard(|| {
+        let n = num_assets;
+        let k = regime_skew.len();
+
+        // ── Validate input lengths ──────────────────────────────────
+        check_lengths(&[
+            ("base_drift", n, base_drift.len()),
+            ("base_vol", n, base_vol.len()),
+            ("base_correlation", n * n, base_correlation.len()),
+            ("regime_delta_drift", k * n, regime_delta_drift.len()),
+            ("regime_vol_multiplier", k * n, regime_vol_multiplier.len()),
+            ("transition", k * k, transition.len()),
+        ])?;
+
+        let base_drift_v = to_dvector(base_drift);
+        let base_vol_v = to_dvector(base_vol);
+        let base_corr_m = base_correlation_matrix(n, base_correlation, CORRELATION_TOLERANCE)?;
+
+        let regimes = (0..k)
+            .map(|r| {
+                let market = shock_market(
+                    &base_drift_v,
+                    &base_vol_v,
+                    &base_corr_m,
+                    &to_dvector(&regime_delta_drift[r * n..(r + 1) * n]),
+                    &to_dvector(&regime_vol_multiplier[r * n..(r + 1) * n]),
+                    regime_skew[r] as f64,
+                    true,
+                )?;
+                Ok(simulate::Regime {
+                    drift: market.drift,
+                    vol: market.vol,
+                    cholesky_l: market.cholesky_l,
+                })
+            })
+            .collect::<Result<Vec<_>, EngineError>>()?;
+
+        let p: Vec<f64> = transition.iter().map(|&x| x as f64).collect();
+        let out = simulate::simulate_regime_paths(
+            &regimes,
+            &DMatrix::from_row_slice(k, k, &p),
+            initial_regime,
+            &options.config,
+        )?;
+
+        Ok(RegimeSimulation {
+            paths: out.paths,
+            regimes: out.regimes,
+        })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Build capabilities
+// Two binaries ship: the baseline and one built with
+//     RUSTFLAGS="-C target-feature=+simd128" … --features simd
+// src/engine.ts picks one by testing the browser (WebAssembly.validate
+// on a module using a v128 op) before loading; once loaded,
+// simd_enabled() confirms which build is running, and engine_info()
+// describes the rest of the build for feature detection.
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub fn simd_enabled() -> bool {
+    crate::simd::ENABLED
+}
+
+/// {version, git_hash, features: {simd, threads, f64}, limits:
+/// {max_assets, max_gpu_assets, max_sobol_dimensions}, vol_models,
+/// innovations, rngs, drivers, bootstrap_scalings, presets} for
+/// feature detection, listed from the enums themselves.
+/// `f64` means the shock pipeline's linear algebra runs in double
+/// precision (outputs are f32 either way); `max_assets` is null because
+/// the CPU path has no fixed limit, only memory.
+#[wasm_bindgen]
+pub fn engine_info() -> JsValue {
+    // Our own JSON text, so parsing cannot fail
+    js_sys::JSON::parse(&engine_info_json().to_string()).unwrap_or(JsValue::NULL)
+}
+
+fn engine_info_json() -> Json {
+    let names = |xs: &[&str]| Json::Array(xs.iter().map(|&x| Json::String(x.into())).collect());
+    let rngs: Vec<&str> = crate::rng::RngKind::ALL.iter().map(|r| r.id()).collect();
+    let drivers: Vec<&str> = simulate::Driver::ALL.iter().map(|d| d.id()).collect();
+    let presets: Vec<&str> = presets::Preset::ALL.iter().map(|p| p.id()).collect();
+    Json::Object(vec![
+        ("version".into(), Json::String(env!("CARGO_PKG_VERSION").into())),
+        ("git_hash".into(), Json::String(env!("MSSIM_GIT_HASH").into())),
+        (
+            "features".into(),
+            Json::Object(vec![
+                ("simd".into(), Json::Bool(crate::simd::ENABLED)),
+                ("threads".into(), Json::Bool(cfg!(target_feature = "atomics"))),
+                ("f64".into(), Json::Bool(true)),
+            ]),
+        ),
+        (
+            "limits".into(),
+            Json::Object(vec![
+                ("max_assets".into(), Json::Null),
+                ("max_gpu_assets".into(), Json::Number(gpu::MAX_GPU_ASSETS as f64)),
+                ("max_sobol_dimensions".into(), Json::Number(crate::sobol::SOBOL_MAX_DIM as f64)),
+            ]),
+        ),
+        ("vol_models".into(), names(&simulate::VolModel::IDS)),
+        ("innovations".into(), names(&simulate::Innovations::IDS)),
+        ("rngs".into(), names(&rngs)),
+        ("drivers".into(), names(&drivers)),
+        ("bootstrap_scalings".into(), names(&replay::BootstrapScaling::IDS)),
+        ("presets".into(), names(&presets)),
+    ])
+}
+
+// ════════════════════════════════════════════════════════════════
+// Crash reporting
+// Every exported function or method that returns a Result runs its
+// body under guard(), so panics come back as INTERNAL errors where the
+// target can unwind. In the browser a panic still traps ("unreachable
+// executed"); the hook, installed by those entry points or by
+// install_panic_hook(), logs it to console.error and keeps it for
+// last_panic_info().
+// ════════════════════════════════════════════════════════════════
+#[wasm_bindgen]
+pub fn install_panic_hook() {
+    error::install_panic_hook();
+}
+
+#[wasm_bindgen]
+pub struct PanicInfo {
+    message: String,
+    location: String,
+}
+
+#[wasm_bindgen]
+impl PanicInfo {
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    /// file:line:column in the engine source
+    #[wasm_bindgen(getter)]
+    pub fn location(&self) -> String {
+        self.location.clone()
+    }
+}
+
+/// The most recent engine panic, if any (needs the hook installed)
+#[wasm_bindgen]
+pub fn last_panic_info() -> Option<PanicInfo> {
+    error::last_panic().map(|(message, location)| PanicInfo { message, location })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    const MU: [f32; 3] = [0.05, 0.07, 0.03];
+    const SIGMA: [f32; 3] = [0.2, 0.3, 0.15];
+
+    #[test]
+    fn test_engine_info_lists_every_option() {
+        let info = engine_info_json();
+        let names = |key: &str| match info.get(key) {
+            Some(Json::Array(xs)) => xs.iter().map(|x| x.as_str().unwrap().to_string()).collect::<Vec<_>>(),
+            _ => panic!("{key} missing"),
+        };
+        assert_eq!(names("rngs"), ["pcg32", "xoshiro256++", "pcg64", "philox4x32"]);
+        assert_eq!(names("drivers"), ["pseudo_random", "sobol", "stratified"]);
+        assert_eq!(names("vol_models"), simulate::VolModel::IDS);
+        assert_eq!(names("innovations"), simulate::Innovations::IDS);
+        assert_eq!(names("bootstrap_scalings"), ["none", "vol_multiplier", "market"]);
+        assert_eq!(names("presets").len(), presets::Preset::ALL.len());
+    }
+
+    #[test]
+    fn test_batch_matches_single_config_for_every_skew() {
+        // Skews outside [0, 1] make even the identity indefinite, so the
+        // batch's λ_min(R) shortcut must not apply to them
+        let skews = [-1.0, 0.0, 0.3, 1.0, 1.5];
+        for base in [[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0], [1.0, 0.4, 0.2, 0.4, 1.0, 0.5, 0.2, 0.5, 1.0]] {
+            let b = skews.len();
+            let batch = compute_shock_batch(&MU, &SIGMA, &base, &[0.0; 15], &[1.0; 15], &skews, &[0.0], &[0.0], &[0.0]).unwrap();
+            for (k, row) in batch.iter().enumerate().take(b) {
+                let config = ShockConfig::builder().base_market(&MU, &SIGMA, &base).skew(skews[k]).build().unwrap();
+                let single = compute_shock_config(&config).unwrap();
+                assert_eq!(row.diagnostics.higham_iterations, single.diagnostics.higham_iterations, "skew {}", skews[k]);
+                let (after, expected) = (row.diagnostics.min_eigenvalue_after, single.diagnostics.min_eigenvalue_after);
+                assert_eq!(after.is_some(), expected.is_some());
+                assert_relative_eq!(after.unwrap_or(0.0), expected.unwrap_or(0.0), epsilon = 1e-6);
+                for (x, y) in row.adjusted_correlation.iter().zip(&single.adjusted_correlation) {
+                    assert_relative_eq!(*x, *y, epsilon = 1e-6);
+                }
+                for (x, y) in row.cholesky_l.iter().zip(&single.cholesky_l) {
+                    assert_relative_eq!(*x, *y, epsilon = 1e-6);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pd_blend_skips_the_eigensolve() {
+        let r: [f32; 9] = [1.0, 0.3, 0.1, 0.3, 1.0, 0.2, 0.1, 0.2, 1.0];
+        let blended = math::blend_correlation(&base_correlation_matrix(3, &r, CORRELATION_TOLERANCE).unwrap(), 0.4);
+        let (pd, report) = repair_correlation(&blended);
+        let mut expected = (&blended + blended.transpose()) * 0.5;
+        expected.fill_diagonal(1.0);
+        assert_eq!(pd, expected);
+        assert_relative_eq!(pd, math::nearest_pd(&blended), epsilon = 1e-12);
+        assert_eq!((report.iterations, report.min_eigenvalue_before, report.min_eigenvalue_after), (0, None, None));
+
+        let config = ShockConfig::builder().base_market(&MU, &SIGMA, &r).skew(0.4).build().unwrap();
+        let d = compute_shock_config(&config).unwrap().diagnostics;
+        assert!(d.higham_converged && !d.repair_needed() && d.min_eigenvalue_before.is_none());
+
+        // An indefinite blend still reports both
+        let bad = [1.0, 0.9, -0.9, 0.9, 1.0, 0.9, -0.9, 0.9, 1.0];
+        let (_, report) = repair_correlation(&DMatrix::from_row_slice(3, 3, &bad));
+        assert!(report.iterations > 0 && report.min_eigenvalue_before.is_some_and(|x| x < 0.0));
+        assert!(report.min_eigenvalue_after.is_some_and(|x| x > 0.0));
+    }
+
+    #[test]
+    fn test_factor_negative_skew_keeps_variances() {
+        // s < 0 subtracts J: near-zero ρ blends to ≈ −1 off the diagonal,
+        // which is indefinite and needs the Higham repair
+        let loadings = [0.1, 0.0, 0.0, 0.1, 0.1, 0.1];
+        let idio = [1.0; 3];
+        let out = compute_shock_factors(
+            3, 2, &MU, &[0.2; 3], &loadings, &[], &idio, &[0.0; 3], &[1.0; 3], -1.0, 0.0, 0.0, 0.0,
+        )
+        .unwrap();
+        assert!(out.ridge_jitter < 1e-6, "jitter {}", out.ridge_jitter);
+        for row in out.cholesky_l.chunks_exact(3) {
+            let var: f32 = row.iter().map(|x| x * x).sum();
+            assert_relative_eq!(var, 0.04, max_relative = 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_factor_path_rejects_non_finite_inputs() {
+        let loadings = [0.8, 0.1, 0.6, 0.3, 0.2, 0.7];
+        let run = |vol: &[f32], cov: &[f32]| {
+            compute_shock_factors(3, 2, &MU, vol, &loadings, cov, &[0.3; 3], &[0.0; 3], &[1.0; 3], 0.2, 0.0, 0.0, 0.0)
+                .err()
+        };
+        assert_eq!(run(&[0.2, f32::NAN, 0.2], &[]), Some(EngineError::NonFiniteInput { field: "base_vol", index: 1 }));
+        assert_eq!(
+            run(&[0.2; 3], &[1.0, 0.0, f32::INFINITY, 1.0]),
+            Some(EngineError::NonFiniteInput { field: "factor_covariance", index: 2 })
+        );
+        assert!(run(&[0.2; 3], &[]).is_none());
+    }
+
+    #[test]
+    fn test_guard_covers_entry_points_beyond_compute_shock() {
+        // js_sys constructors panic off wasm, so a valid call still panics
+        // at its Float32Array return and must come back as INTERNAL
+        let options = SimulationOptions::new(1.0, 1, 2, 3);
+        let paths = [1.0, 1.1, 1.0, 0.9];
+        match terminal_pnl_from_paths(&paths, 1, &options).err() {
+            Some(EngineError::Internal { location, .. }) => assert!(!location.is_empty()),
+            other => panic!("expected an internal error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_builder_checks_match_compute_shock_config() {
+        let r = [1.0, 0.3, 0.1, 0.3, 1.0, 0.2, 0.1, 0.2, 1.0];
+        let base = || ShockConfig::builder().base_market(&MU, &SIGMA, &r);
+        let built = base().vol_multiplier(&[2.0, 1.5, 1.0]).skew(0.5).jumps(0.5, -0.1, 0.15).build().unwrap();
+        let mut config = ShockConfig::new(&MU, &SIGMA, &r);
+        config.set_vol_multiplier(&[2.0, 1.5, 1.0]);
+        config.set_correlation_skew(0.5);
+        config.set_jumps(0.5, -0.1, 0.15);
+        assert_eq!(built.to_json(), config.to_json());
+        assert!(compute_shock_config(&built).is_ok());
+        assert!(matches!(ShockConfig::builder().build(), Err(EngineError::InvalidInput { .. })));
+
+        // Each rejection is the one compute_shock_config gives the same config
+        let setters: [fn(&mut ShockConfig); 6] = [
+            |c| c.set_delta_drift(&[0.0, 0.0]),
+            |c| c.set_asset_jumps(&[0.1, 0.2], &[0.0], &[0.1]),
+            |c| c.set_vol_multiplier(&[1.0, f32::NAN, 1.0]),
+            |c| c.set_correlation_skew(f32::INFINITY),
+            |c| c.set_correlation_tolerance(-1e-3),
+            |c| c.set_correlation_tolerance(f32::NAN),
+        ];
+        let builders = [
+            base().delta_drift(&[0.0, 0.0]),
+            base().asset_jumps(&[0.1, 0.2], &[0.0], &[0.1]),
+            base().vol_multiplier(&[1.0, f32::NAN, 1.0]),
+            base().skew(f32::INFINITY),
+            base().correlation_tolerance(-1e-3),
+            base().correlation_tolerance(f32::NAN),
+        ];
+        for (set, builder) in setters.into_iter().zip(builders) {
+            let mut config = ShockConfig::new(&MU, &SIGMA, &r);
+            set(&mut config);
+            let expected = compute_shock_config(&config).err().unwrap();
+            assert_eq!(builder.build().err(), Some(expected));
+        }
+    }
 }
@@ -0,0 +1,270 @@
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Mutex, Once};
+
+use wasm_bindgen::JsValue;
+
+// ════════════════════════════════════════════════════════════════
+// EngineError — typed failures at the wasm boundary
+//
+// The math modules report plain `&'static str` reasons ("X input
+// mismatch: …" / "X input invalid: …"); the wasm entry points lift
+// those into EngineError, which reaches JS as an `Error` carrying a
+// stable `code` plus the variant's fields, so the frontend can branch
+// on `err.code` instead of parsing messages.
+// ════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineError {
+    /// One input array has the wrong length for N (or K, F, …)
+    InputLengthMismatch { field: &'static str, expected: usize, got: usize },
+    /// Inputs disagree in shape in a way not tied to a single array
+    ShapeMismatch { reason: &'static str },
+    /// Σ could not be factored even after ridge regularization
+    NotPositiveDefinite { min_eigenvalue: f64 },
+    /// NaN or ±∞ at `index` of `field`
+    NonFiniteInput { field: &'static str, index: usize },
+    /// A parameter outside its valid range
+    InvalidInput { reason: &'static str },
+    /// Saved JSON lacks `field` or has it with the wrong type
+    MissingField { field: &'static str },
+    /// Base correlation fails `reason` at entry (row, col)
+    InvalidCorrelation { row: usize, col: usize, reason: &'static str },
+    /// A progress callback returned `false`
+    Cancelled,
+    /// The engine panicked (a bug); `location` is file:line:column
+    Internal { message: String, location: String },
+}
+
+impl EngineError {
+    /// Stable identifier exposed to JS as `err.code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            EngineError::InputLengthMismatch { .. } => "INPUT_LENGTH_MISMATCH",
+            EngineError::ShapeMismatch { .. } => "SHAPE_MISMATCH",
+            EngineError::NotPositiveDefinite { .. } => "NOT_POSITIVE_DEFINITE",
+            EngineError::NonFiniteInput { .. } => "NON_FINITE_INPUT",
+            EngineError::InvalidInput { .. } => "INVALID_INPUT",
+            EngineError::MissingField { .. } => "MISSING_FIELD",
+            EngineError::InvalidCorrelation { .. } => "INVALID_CORRELATION",
+            EngineError::Cancelled => "CANCELLED",
+            EngineError::Internal { .. } => "INTERNAL",
+        }
+    }
+
+    /// Static description for callers that still speak `&'static str`
+    pub fn reason(&self) -> &'static str {
+        match self {
+            EngineError::ShapeMismatch { reason }
+            | EngineError::InvalidInput { reason }
+            | EngineError::InvalidCorrelation { reason, .. } => reason,
+            EngineError::InputLengthMismatch { .. } => "Input length mismatch",
+            EngineError::NotPositiveDefinite { .. } => {
+                "Cholesky decomposition failed: matrix is not positive-definite even after ridge regularization"
+            }
+            EngineError::NonFiniteInput { .. } => "Input invalid: non-finite value",
+            EngineError::MissingField { .. } => "JSON input invalid: missing or mistyped field",
+            EngineError::Cancelled => "Cancelled by the progress callback",
+            EngineError::Internal { .. } => "Internal error: the engine panicked",
+        }
+    }
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::InputLengthMismatch { field, expected, got } => {
+                write!(f, "Input length mismatch: expected {field} of length {expected}, got {got}")
+            }
+            EngineError::NotPositiveDefinite { min_eigenvalue } => {
+                write!(f, "{} (smallest eigenvalue {min_eigenvalue:e})", self.reason())
+            }
+            EngineError::NonFiniteInput { field, index } => {
+                write!(f, "Input invalid: {field}[{index}] is NaN or infinite")
+            }
+            EngineError::MissingField { field } => write!(f, "JSON input invalid: missing or mistyped field {field}"),
+            EngineError::InvalidCorrelation { row, col, reason } => write!(f, "{reason} at ({row}, {col})"),
+            EngineError::Cancelled => f.write_str(self.reason()),
+            EngineError::Internal { message, location } => write!(f, "Internal error at {location}: {message}"),
+            EngineError::ShapeMismatch { reason } | EngineError::InvalidInput { reason } => f.write_str(reason),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+/// Module errors follow the "… mismatch: …" / "… invalid: …" convention.
+impl From<&'static str> for EngineError {
+    fn from(reason: &'static str) -> Self {
+        if reason.contains("mismatch") {
+            EngineError::ShapeMismatch { reason }
+        } else {
+            EngineError::InvalidInput { reason }
+        }
+    }
+}
+
+/// `Error` with `name = "EngineError"`, `code`, and the variant fields
+/// (`field`, `expected`, `got`, `minEigenvalue`, `index`, `row`, `col`,
+/// `panicMessage`, `location`).
+impl From<EngineError> for JsValue {
+    fn from(e: EngineError) -> JsValue {
+        let err = js_sys::Error::new(&e.to_string());
+        err.set_name("EngineError");
+        let set = |key: &str, value: JsValue| {
+            // Setting a property on a fresh Error cannot fail
+            let _ = js_sys::Reflect::set(&err, &JsValue::from_str(key), &value);
+        };
+        set("code", JsValue::from_str(e.code()));
+        match e {
+            EngineError::Internal { message, location } => {
+                set("panicMessage", JsValue::from_str(&message));
+                set("location", JsValue::from_str(&location));
+            }
+            EngineError::InputLengthMismatch { field, expected, got } => {
+                set("field", JsValue::from_str(field));
+                set("expected", JsValue::from(expected as u32));
+                set("got", JsValue::from(got as u32));
+            }
+            EngineError::NotPositiveDefinite { min_eigenvalue } => set("minEigenvalue", JsValue::from(min_eigenvalue)),
+            EngineError::NonFiniteInput { field, index } => {
+                set("field", JsValue::from_str(field));
+                set("index", JsValue::from(index as u32));
+            }
+            EngineError::MissingField { field } => set("field", JsValue::from_str(field)),
+            EngineError::InvalidCorrelation { row, col, .. } => {
+                set("row", JsValue::from(row as u32));
+                set("col", JsValue::from(col as u32));
+            }
+            EngineError::ShapeMismatch { .. } | EngineError::InvalidInput { .. } | EngineError::Cancelled => {}
+        }
+        err.into()
+    }
+}
+
+/// First array whose length differs from its expected length, as
+/// (field, expected, got) triples.
+pub(crate) fn check_lengths(expected: &[(&'static str, usize, usize)]) -> Result<(), EngineError> {
+    match expected.iter().find(|(_, want, got)| want != got) {
+        Some(&(field, expected, got)) => Err(EngineError::InputLengthMismatch { field, expected, got }),
+        None => Ok(()),
+    }
+}
+
+/// First NaN / ±∞ across (field, values) pairs, in the order given.
+pub(crate) fn check_finite(inputs: &[(&'static str, &[f32])]) -> Result<(), EngineError> {
+    for &(field, values) in inputs {
+        if let Some(index) = values.iter().position(|x| !x.is_finite()) {
+            return Err(EngineError::NonFiniteInput { field, index });
+        }
+    }
+    Ok(())
+}
+
+// ────────────────────────────────────────────────────────────────
+// Panics — recorded by a hook, and returned as EngineError::Internal
+// from guarded entry points
+// wasm32 builds abort on panic, so guard() only catches on unwinding
+// targets (native, tests). In the browser the hook still logs to
+// console.error and records the message and location before the trap,
+// so the frontend can read them back with last_panic_info().
+// ────────────────────────────────────────────────────────────────
+static LAST_PANIC: Mutex<Option<(String, String)>> = Mutex::new(None);
+static PANIC_HOOK: Once = Once::new();
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console, js_name = error)]
+    fn console_error(message: &str);
+}
+
+/// Idempotent; chains to the previously installed hook.
+pub fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let location = info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+                .unwrap_or_default();
+            let message = info.payload_as_str().unwrap_or("non-string panic payload").to_string();
+            #[cfg(target_arch = "wasm32")]
+            console_error(&format!("mssim-engine panicked at {location}: {message}"));
+            if let Ok(mut last) = LAST_PANIC.lock() {
+                *last = Some((message, location));
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// (message, location) of the most recent panic seen by the hook
+pub fn last_panic() -> Option<(String, String)> {
+    LAST_PANIC.lock().ok().and_then(|last| last.clone())
+}
+
+/// Run an entry point body, turning a panic into EngineError::Internal.
+pub(crate) fn guard<T>(body: impl FnOnce() -> Result<T, EngineError>) -> Result<T, EngineError> {
+    install_panic_hook();
+    // The body's state is dropped on panic, so observing it broken is moot
+    std::panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let fallback = || {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            (message, String::new())
+        };
+        let (message, location) = last_panic().unwrap_or_else(fallback);
+        Err(EngineError::Internal { message, location })
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_reasons_map_to_codes() {
+        let e = EngineError::from("Risk input mismatch: paths must be n_paths × (steps + 1) × N");
+        assert_eq!(e.code(), "SHAPE_MISMATCH");
+        let e = EngineError::from("Margin input invalid: leverage must be at least 1 and the rate finite");
+        assert_eq!(e.code(), "INVALID_INPUT");
+        assert_eq!(e.to_string(), e.reason());
+    }
+
+    #[test]
+    fn test_check_lengths_reports_first_offender() {
+        assert_eq!(check_lengths(&[("base_drift", 3, 3), ("base_vol", 3, 3)]), Ok(()));
+        let e = check_lengths(&[("base_drift", 3, 3), ("base_vol", 3, 2), ("base_correlation", 9, 4)]).unwrap_err();
+        assert_eq!(e, EngineError::InputLengthMismatch { field: "base_vol", expected: 3, got: 2 });
+        assert_eq!(e.to_string(), "Input length mismatch: expected base_vol of length 3, got 2");
+    }
+
+    #[test]
+    fn test_check_finite_reports_field_and_index() {
+        assert_eq!(check_finite(&[("base_drift", &[0.1, -0.2]), ("correlation_skew", &[0.5])]), Ok(()));
+        let e = check_finite(&[("base_drift", &[0.1]), ("base_vol", &[0.2, f32::NAN, f32::INFINITY])]).unwrap_err();
+        assert_eq!(e, EngineError::NonFiniteInput { field: "base_vol", index: 1 });
+        assert_eq!(e.code(), "NON_FINITE_INPUT");
+    }
+
+    #[test]
+    fn test_guard_turns_panics_into_internal_errors() {
+        assert_eq!(guard(|| Ok(3)), Ok(3));
+        let e = guard::<()>(|| panic!("index {} out of range", 7)).unwrap_err();
+        match &e {
+            EngineError::Internal { message, location } => {
+                assert_eq!(message, "index 7 out of range");
+                assert!(location.contains("error.rs"));
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert_eq!(e.code(), "INTERNAL");
+    }
+}
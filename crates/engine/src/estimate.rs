@@ -0,0 +1,239 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::simulate::{Innovations, OutputLayout, SimConfig};
+
+// ════════════════════════════════════════════════════════════════
+// Monte Carlo estimators over simulated paths
+// ════════════════════════════════════════════════════════════════
+
+/// Sample mean of a per-path statistic with its standard error, plus the
+/// plain estimate it improves on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    pub mean: f64,
+    pub std_error: f64,
+    pub raw_mean: f64,
+    pub raw_std_error: f64,
+    /// Regression coefficient per control (empty for plain estimates)
+    pub beta: Vec<f64>,
+}
+
+// ────────────────────────────────────────────────────────────────
+// Analytic terminal mean — E[S_T] with S₀ = 1
+// GBM gives e^{(μ−q)T}, with μT summed step by step over the term
+// structure's buckets when there is one; Heston and GARCH keep it (their
+// log drift carries the −v/2 convexity term); the uncompensated jumps add
+// the compound Poisson factor e^{λT(e^{μ_J + σ_J²/2} − 1)}, with β·μ_J
+// and β²·σ_J² for systemic jumps. Student-t innovations, Hawkes clustering and
+// short-rate bonds have no closed form here, so they are rejected.
+// ────────────────────────────────────────────────────────────────
+pub fn analytic_terminal_mean(drift: &DVector<f64>, config: &SimConfig) -> Result<DVector<f64>, &'static str> {
+    if config.innovations != Innovations::Gaussian {
+        return Err("Control variate input invalid: analytic mean needs Gaussian innovations");
+    }
+    if config.tilt.is_some() {
+        return Err("Control variate input invalid: analytic mean does not hold under an importance tilt");
+    }
+    if config.hawkes.is_some() {
+        return Err("Control variate input invalid: analytic mean has no closed form with Hawkes jumps");
+    }
+    if config.short_rates.as_ref().is_some_and(|r| r.iter().any(Option::is_some)) {
+        return Err("Control variate input invalid: analytic mean does not cover short-rate assets");
+    }
+    let t = config.horizon;
+    let compound = |lambda: f64, mean: f64, vol: f64| lambda * t * ((mean + 0.5 * vol * vol).exp() - 1.0);
+    let mut log_mean = drift.map(|mu| mu * t);
+    if let Some(term) = &config.term_structure {
+        if term.ends.is_empty() || term.drift.ncols() != drift.len() || term.drift.nrows() != term.ends.len() {
+            return Err("Control variate input mismatch: term structure needs B ends and B×N drift");
+        }
+        // Each step takes its bucket's drift, as the simulator does
+        let dt = t / config.steps as f64;
+        for (i, lm) in log_mean.iter_mut().enumerate() {
+            *lm = (0..config.steps).map(|step| term.drift[(term.bucket(step as f64 * dt), i)] * dt).sum();
+        }
+    }
+    if let Some(q) = &config.dividend_yield {
+        if q.len() != drift.len() {
+            return Err("Control variate input mismatch: dividend yield needs one value per asset");
+        }
+        for (lm, q) in log_mean.iter_mut().zip(q) {
+            *lm -= q * t;
+        }
+    }
+    if let Some(jumps) = &config.jumps {
+        if jumps.len() != 1 && jumps.len() != drift.len() {
+            return Err("Control variate input mismatch: jump parameters must be a single set or one per asset");
+        }
+        for (i, lm) in log_mean.iter_mut().enumerate() {
+            let j = jumps[if jumps.len() == 1 { 0 } else { i }];
+            *lm += compound(j.lambda, j.mean, j.vol);
+        }
+    }
+    if let Some(j) = &config.systemic_jumps {
+        if j.betas.len() != drift.len() {
+            return Err("Control variate input mismatch: systemic jumps need one beta per asset");
+        }
+        for (lm, b) in log_mean.iter_mut().zip(&j.betas) {
+            *lm += compound(j.lambda, b * j.mean, b * j.vol);
+        }
+    }
+    Ok(log_mean.map(f64::exp))
+}
+
+// ────────────────────────────────────────────────────────────────
+// Control variates
+// Ŷ = Ȳ − β·(X̄ − E[X]),  β = S_XX⁻¹·S_XY  (multiple regression)
+// SE from the residual variance of Y − β·X.
+// ────────────────────────────────────────────────────────────────
+
+/// `statistic` has one value per path; `controls` is n_paths × K
+/// row-major with known means `control_means` (K).
+pub fn control_variate_mean(
+    statistic: &[f64],
+    controls: &[f64],
+    control_means: &[f64],
+) -> Result<Estimate, &'static str> {
+    let n_paths = statistic.len();
+    let k = control_means.len();
+    if n_paths < 2 || controls.len() != n_paths * k {
+        return Err("Control variate input mismatch: need ≥ 2 paths and n_paths × K controls");
+    }
+    let np = n_paths as f64;
+    let y_mean = statistic.iter().sum::<f64>() / np;
+    let x = DMatrix::from_row_slice(n_paths, k, controls);
+    let x_mean = DVector::from_iterator(k, x.column_iter().map(|c| c.sum() / np));
+
+    let mut s_xx = DMatrix::zeros(k, k);
+    let mut s_xy = DVector::zeros(k);
+    let mut s_yy = 0.0;
+    for (p, &y) in statistic.iter().enumerate() {
+        let dx = x.row(p).transpose() - &x_mean;
+        let dy = y - y_mean;
+        s_xx += &dx * dx.transpose();
+        s_xy += &dx * dy;
+        s_yy += dy * dy;
+    }
+    let raw_var = s_yy / (np - 1.0);
+
+    // Singular S_XX (e.g. a constant control): fall back to no correction
+    let beta = s_xx.clone().cholesky().map_or(DVector::zeros(k), |c| c.solve(&s_xy));
+    let known = DVector::from_column_slice(control_means);
+    let mean = y_mean - beta.dot(&(&x_mean - known));
+    let resid = (s_yy - beta.dot(&s_xy)).max(0.0) / (np - 1.0 - k as f64).max(1.0);
+
+    Ok(Estimate {
+        mean,
+        std_error: (resid / np).sqrt(),
+        raw_mean: y_mean,
+        raw_std_error: (raw_var / np).sqrt(),
+        beta: beta.iter().copied().collect(),
+    })
+}
+
+/// Control-variate estimate of E[statistic] using every asset's terminal
+/// price from `paths` (simulated with `config`) as a control.
+pub fn terminal_control_variate(
+    statistic: &[f64],
+    paths: &[f32],
+    drift: &DVector<f64>,
+    config: &SimConfig,
+) -> Result<Estimate, &'static str> {
+    let (n, n_paths, rows) = (drift.len(), config.n_paths, config.steps + 1);
+    if statistic.len() != n_paths || paths.len() != n_paths * rows * n {
+        return Err("Control variate input mismatch: statistic and paths must match the config");
+    }
+    let means = analytic_terminal_mean(drift, config)?;
+    let mut controls = vec![0.0; n_paths * n];
+    for (p, c) in controls.chunks_exact_mut(n).enumerate() {
+        for (a, x) in c.iter_mut().enumerate() {
+            let idx = match config.layout {
+                OutputLayout::Interleaved => (p * rows + rows - 1) * n + a,
+                OutputLayout::Planar => (a * n_paths + p) * rows + rows - 1,
+            };
+            *x = paths[idx] as f64;
+        }
+    }
+    control_variate_mean(statistic, &controls, means.as_slice())
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulate::{simulate_paths, JumpParams, TermStructure};
+    use approx::assert_relative_eq;
+
+    fn market() -> (DVector<f64>, DVector<f64>, DMatrix<f64>) {
+        let drift = DVector::from_vec(vec![0.08, 0.03]);
+        let vol = DVector::from_vec(vec![0.2, 0.05]);
+        let r = DMatrix::from_row_slice(2, 2, &[1.0, 0.5, 0.5, 1.0]);
+        let cov = crate::math::rebuild_covariance(&vol, &r);
+        (drift, vol, crate::math::cholesky_decompose(&cov).unwrap())
+    }
+
+    fn terminal(paths: &[f32], p: usize, a: usize) -> f64 {
+        paths[p * 10 + 8 + a] as f64
+    }
+
+    #[test]
+    fn test_analytic_mean_includes_jumps() {
+        let drift = DVector::from_vec(vec![0.05]);
+        let jumps = JumpParams { lambda: 2.0, mean: -0.1, vol: 0.2 };
+        let config = SimConfig::new(2.0, 10, 1, 0).with_jumps(jumps);
+        let m = analytic_terminal_mean(&drift, &config).unwrap();
+        assert_relative_eq!(m[0], (0.1 + 4.0 * ((-0.1_f64 + 0.02).exp() - 1.0)).exp(), epsilon = 1e-12);
+
+        let t = config.with_innovations(Innovations::StudentT { nu: 5.0 });
+        assert!(analytic_terminal_mean(&drift, &t).is_err());
+    }
+
+    #[test]
+    fn test_analytic_mean_follows_term_structure() {
+        // Steps start at 0, ¼, ½, ¾: two in each bucket
+        let (drift, vol, l) = market();
+        let term = TermStructure {
+            ends: vec![0.5, 1.0],
+            drift: DMatrix::from_row_slice(2, 2, &[0.3, 0.02, -0.1, 0.04]),
+            vol: DMatrix::from_row_slice(2, 2, &[0.2, 0.05, 0.2, 0.05]),
+        };
+        let config = SimConfig::new(1.0, 4, 4000, 5).with_term_structure(term);
+        let m = analytic_terminal_mean(&drift, &config).unwrap();
+        assert_relative_eq!(m[0], (0.5 * 0.3 - 0.5 * 0.1_f64).exp(), epsilon = 1e-12);
+        assert_relative_eq!(m[1], 0.03_f64.exp(), epsilon = 1e-12);
+
+        let paths = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let s: Vec<f64> = (0..4000).map(|p| terminal(&paths, p, 0)).collect();
+        let mean = s.iter().sum::<f64>() / 4000.0;
+        let sd = (s.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / 3999.0).sqrt();
+        assert!((mean - m[0]).abs() < 4.0 * sd / 4000f64.sqrt(), "{mean} vs {}", m[0]);
+    }
+
+    #[test]
+    fn test_control_recovers_its_own_mean_exactly() {
+        let (drift, vol, l) = market();
+        let config = SimConfig::new(1.0, 4, 500, 3);
+        let paths = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let stat: Vec<f64> = (0..500).map(|p| terminal(&paths, p, 0)).collect();
+        let est = terminal_control_variate(&stat, &paths, &drift, &config).unwrap();
+        assert_relative_eq!(est.mean, 0.08_f64.exp(), epsilon = 1e-9);
+        assert!(est.std_error < 1e-6);
+    }
+
+    #[test]
+    fn test_control_variate_shrinks_call_payoff_error() {
+        // Basket call on the two assets: strongly correlated with the controls
+        let (drift, vol, l) = market();
+        let config = SimConfig::new(1.0, 4, 4000, 17);
+        let paths = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let stat: Vec<f64> = (0..4000)
+            .map(|p| (0.5 * (terminal(&paths, p, 0) + terminal(&paths, p, 1)) - 1.0).max(0.0))
+            .collect();
+        let est = terminal_control_variate(&stat, &paths, &drift, &config).unwrap();
+        assert!(est.std_error < 0.5 * est.raw_std_error);
+        assert!((est.mean - est.raw_mean).abs() < 3.0 * est.raw_std_error);
+        assert_eq!(est.beta.len(), 2);
+    }
+}
@@ -0,0 +1,233 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::math;
+use crate::simulate::{self, OutputLayout, SimConfig};
+
+// ════════════════════════════════════════════════════════════════
+// Multi-currency layer
+//
+// Each asset is priced in one of C currencies. Currency 0 is the
+// reference; every other currency c gets an FX process X_c (reference
+// units per unit of c, X_c(0) = 1) simulated as an extra correlated
+// GBM after the N assets, so the simulator sees N + C − 1 processes
+// and per-asset config vectors (dividend yield, jumps, tilt) span all
+// of them. In base currency b an asset is worth  S·X_{c(a)} / X_b,
+// with X_0 ≡ 1.
+// ════════════════════════════════════════════════════════════════
+
+/// Currency tag per asset; tags are 0..num_currencies.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CurrencyMap {
+    asset_currency: Vec<usize>,
+    num_currencies: usize,
+}
+
+impl CurrencyMap {
+    pub fn new(asset_currency: Vec<usize>, num_currencies: usize) -> Result<Self, &'static str> {
+        if num_currencies == 0 || asset_currency.iter().any(|&c| c >= num_currencies) {
+            return Err("Currency input invalid: every asset needs a currency below num_currencies");
+        }
+        Ok(CurrencyMap { asset_currency, num_currencies })
+    }
+
+    pub fn num_assets(&self) -> usize {
+        self.asset_currency.len()
+    }
+
+    pub fn num_currencies(&self) -> usize {
+        self.num_currencies
+    }
+
+    /// FX processes simulated: one per non-reference currency
+    pub fn num_fx(&self) -> usize {
+        self.num_currencies - 1
+    }
+
+    pub fn currency(&self, asset: usize) -> usize {
+        self.asset_currency[asset]
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Assembly — N assets followed by C − 1 FX rates
+// Same Steps 4–6 as shock_market on the augmented correlation.
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug)]
+pub struct CurrencyMarket {
+    /// N + C − 1 drifts, FX last
+    pub drift: DVector<f64>,
+    pub vol: DVector<f64>,
+    pub cholesky_l: DMatrix<f64>,
+    pub ridge_jitter: f64,
+}
+
+/// `r_cross` is N × (C − 1): asset-to-FX correlations.
+#[allow(clippy::too_many_arguments)]
+pub fn assemble_market(
+    drift: &DVector<f64>,
+    vol: &DVector<f64>,
+    r_assets: &DMatrix<f64>,
+    fx_drift: &DVector<f64>,
+    fx_vol: &DVector<f64>,
+    r_cross: &DMatrix<f64>,
+    r_fx: &DMatrix<f64>,
+) -> Result<CurrencyMarket, &'static str> {
+    let (n, f) = (drift.len(), fx_drift.len());
+    if vol.len() != n || fx_vol.len() != f || r_fx.nrows() != f || r_assets.nrows() != n {
+        return Err("Currency input mismatch: drift, vol and correlation sizes disagree");
+    }
+    let r = math::augment_correlation(r_assets, r_cross, r_fx)?;
+    let all_drift = DVector::from_iterator(n + f, drift.iter().chain(fx_drift.iter()).copied());
+    let all_vol = DVector::from_iterator(n + f, vol.iter().chain(fx_vol.iter()).copied());
+    let cov = math::rebuild_covariance(&all_vol, &math::nearest_pd(&r));
+    let (l, jitter) = math::cholesky_with_jitter(&cov)?;
+    Ok(CurrencyMarket { drift: all_drift, vol: all_vol, cholesky_l: l, ridge_jitter: jitter })
+}
+
+// ────────────────────────────────────────────────────────────────
+// Aggregation — local-currency paths to base currency
+// ────────────────────────────────────────────────────────────────
+
+/// `paths` holds the N + C − 1 simulated processes in `config.layout`;
+/// returns the N asset paths in currency `base`, same layout.
+pub fn to_base_currency(
+    paths: &[f32],
+    currencies: &CurrencyMap,
+    base: usize,
+    config: &SimConfig,
+) -> Result<Vec<f32>, &'static str> {
+    let (n, n_paths, rows) = (currencies.num_assets(), config.n_paths, config.steps + 1);
+    let width = n + currencies.num_fx();
+    if base >= currencies.num_currencies() {
+        return Err("Currency input invalid: base currency out of range");
+    }
+    if paths.len() != n_paths * rows * width {
+        return Err("Currency input mismatch: paths must hold N assets plus C − 1 FX rates");
+    }
+    let index = |p: usize, t: usize, col: usize, w: usize| match config.layout {
+        OutputLayout::Interleaved => (p * rows + t) * w + col,
+        OutputLayout::Planar => (col * n_paths + p) * rows + t,
+    };
+    let fx = |p: usize, t: usize, c: usize| if c == 0 { 1.0 } else { paths[index(p, t, n + c - 1, width)] as f64 };
+
+    let mut out = vec![0.0_f32; n_paths * rows * n];
+    for p in 0..n_paths {
+        for t in 0..rows {
+            let to_base = 1.0 / fx(p, t, base);
+            for a in 0..n {
+                let local = paths[index(p, t, a, width)] as f64;
+                out[index(p, t, a, n)] = (local * fx(p, t, currencies.currency(a)) * to_base) as f32;
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Σ w_a·S_a(t) per path and step ([path][step]) from base-currency paths.
+pub fn portfolio_value(base_paths: &[f32], weights: &[f64], config: &SimConfig) -> Result<Vec<f32>, &'static str> {
+    let (n, n_paths, rows) = (weights.len(), config.n_paths, config.steps + 1);
+    if n == 0 || base_paths.len() != n_paths * rows * n {
+        return Err("Currency input mismatch: one weight per asset in the paths");
+    }
+    let mut out = vec![0.0_f32; n_paths * rows];
+    for (i, v) in out.iter_mut().enumerate() {
+        let (p, t) = (i / rows, i % rows);
+        *v = weights
+            .iter()
+            .enumerate()
+            .map(|(a, w)| {
+                let idx = match config.layout {
+                    OutputLayout::Interleaved => i * n + a,
+                    OutputLayout::Planar => (a * n_paths + p) * rows + t,
+                };
+                w * base_paths[idx] as f64
+            })
+            .sum::<f64>() as f32;
+    }
+    Ok(out)
+}
+
+/// Local-currency paths (N + C − 1 processes) and the N assets in base.
+#[derive(Clone, Debug)]
+pub struct MultiCurrencyPaths {
+    pub local: Vec<f32>,
+    pub base: Vec<f32>,
+}
+
+pub fn simulate_multi_currency(
+    market: &CurrencyMarket,
+    currencies: &CurrencyMap,
+    base: usize,
+    config: &SimConfig,
+) -> Result<MultiCurrencyPaths, &'static str> {
+    if market.drift.len() != currencies.num_assets() + currencies.num_fx() {
+        return Err("Currency input mismatch: market size must be N + C − 1");
+    }
+    let local = simulate::simulate_paths(&market.drift, &market.vol, &market.cholesky_l, config)?;
+    let base = to_base_currency(&local, currencies, base, config)?;
+    Ok(MultiCurrencyPaths { local, base })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    /// Asset 0 in currency 0, asset 1 in currency 1; one FX rate.
+    fn two_currency_market() -> CurrencyMarket {
+        let r = DMatrix::from_row_slice(2, 2, &[1.0, 0.3, 0.3, 1.0]);
+        let cross = DMatrix::from_row_slice(2, 1, &[0.0, -0.4]);
+        assemble_market(
+            &DVector::from_vec(vec![0.05, 0.04]),
+            &DVector::from_vec(vec![0.2, 0.15]),
+            &r,
+            &DVector::from_vec(vec![0.01]),
+            &DVector::from_vec(vec![0.1]),
+            &cross,
+            &DMatrix::identity(1, 1),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reference_base_multiplies_by_fx() {
+        let market = two_currency_market();
+        let currencies = CurrencyMap::new(vec![0, 1], 2).unwrap();
+        let config = SimConfig::new(1.0, 4, 50, 8);
+        let out = simulate_multi_currency(&market, &currencies, 0, &config).unwrap();
+        for i in 0..50 * 5 {
+            let (local, base) = (&out.local[i * 3..i * 3 + 3], &out.base[i * 2..i * 2 + 2]);
+            assert_eq!(base[0], local[0]);
+            assert_relative_eq!(base[1], local[1] * local[2], max_relative = 1e-6);
+        }
+
+        // Reporting in currency 1 divides everything by the rate instead
+        let in_one = to_base_currency(&out.local, &currencies, 1, &config).unwrap();
+        assert_relative_eq!(in_one[1], out.local[1], max_relative = 1e-6);
+        assert_relative_eq!(in_one[9 * 2], out.local[9 * 3] / out.local[9 * 3 + 2], max_relative = 1e-6);
+    }
+
+    #[test]
+    fn test_base_value_mean_matches_quanto_drift() {
+        // E[S·X] = e^{(μ_S + μ_X + ρσ_Sσ_X)T}
+        let market = two_currency_market();
+        let currencies = CurrencyMap::new(vec![0, 1], 2).unwrap();
+        let n_paths = 40_000;
+        let config = SimConfig::new(1.0, 4, n_paths, 21).with_layout(OutputLayout::Planar);
+        let out = simulate_multi_currency(&market, &currencies, 0, &config).unwrap();
+        let mean = (0..n_paths).map(|p| out.base[(n_paths + p) * 5 + 4] as f64).sum::<f64>() / n_paths as f64;
+        assert_relative_eq!(mean, (0.04_f64 + 0.01 - 0.4 * 0.15 * 0.1).exp(), max_relative = 0.01);
+    }
+
+    #[test]
+    fn test_portfolio_value_weights_base_paths() {
+        let config = SimConfig::new(1.0, 1, 1, 0);
+        let base = [1.0, 1.0, 1.2, 0.9];
+        let v = portfolio_value(&base, &[0.25, 0.75], &config).unwrap();
+        assert_relative_eq!(v[1], 0.25 * 1.2 + 0.75 * 0.9, max_relative = 1e-6);
+        assert!(CurrencyMap::new(vec![0, 2], 2).is_err());
+    }
+}
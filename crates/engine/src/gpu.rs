@@ -0,0 +1,546 @@
+use crate::json::Json;
+use crate::rng::RngKind;
+
+// ════════════════════════════════════════════════════════════════
+// WGSL generation for the GPU Monte Carlo kernel
+//
+// Emits the compute shader from the engine's own conventions, so a JS
+// caller building its own pipeline uploads buffers against a layout it
+// is handed rather than one it maintains by hand. The bundled frontend
+// (src/compute.ts) builds its pipeline this way from a one-step
+// portfolio kernel. The generated kernel follows:
+//   • L from pack_result (N×N row-major) already carries σ,
+//   • ln S += (μ − σ²/2)·dt + √dt·(L·Z) per step, then Poisson jumps
+//     with Σ of k jumps ~ N(k·μ_J, k·σ_J²), as in simulate_paths,
+//   • per-path streams keyed on (seed, path) with the same draw order
+//     as NormalSampler (Box–Muller, caching the second variate).
+// WGSL has no u64, so PCG32's 64-bit state is carried as (lo, hi)
+// pairs. Uniforms use the top 24 bits of each word, (u + ½)/2²⁴, so
+// GPU runs follow the CPU streams to f32 precision, not bit for bit;
+// mssim-gpu's Backend::GpuParity replays the kernel's arithmetic.
+// N and the step count are compile-time constants; regenerate the
+// kernel when either changes.
+// ════════════════════════════════════════════════════════════════
+
+/// Largest N the kernel keeps in private arrays
+pub const MAX_GPU_ASSETS: usize = 64;
+
+/// Size of the params uniform (see `Kernel::params`)
+pub const PARAMS_BYTES: usize = 32;
+
+/// What each invocation writes back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KernelOutput {
+    /// S_T per asset, [path][asset]
+    Terminal,
+    /// Whole paths, [path][step][asset] with S₀ = 1, as simulate_paths
+    Paths,
+    /// Σ w_i·(S_T,i − 1) per path; adds a `weights` binding
+    Portfolio,
+}
+
+impl KernelOutput {
+    /// Parse "terminal", "paths" or "portfolio"
+    pub fn from_name(name: &str) -> Result<Self, &'static str> {
+        match name.to_ascii_lowercase().as_str() {
+            "terminal" => Ok(KernelOutput::Terminal),
+            "paths" => Ok(KernelOutput::Paths),
+            "portfolio" => Ok(KernelOutput::Portfolio),
+            _ => Err("GPU input invalid: output must be terminal, paths or portfolio"),
+        }
+    }
+}
+
+/// WebGPU buffer binding type (GPUBufferBindingType)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BindingKind {
+    Uniform,
+    ReadOnlyStorage,
+    Storage,
+}
+
+impl BindingKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BindingKind::Uniform => "uniform",
+            BindingKind::ReadOnlyStorage => "read-only-storage",
+            BindingKind::Storage => "storage",
+        }
+    }
+}
+
+/// Buffer size a binding needs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferSize {
+    Bytes(usize),
+    /// Output buffers: bytes per simulated path
+    BytesPerPath(usize),
+}
+
+/// One entry of bind group 0
+#[derive(Clone, Debug, PartialEq)]
+pub struct Binding {
+    pub binding: u32,
+    pub name: &'static str,
+    pub kind: BindingKind,
+    pub size: BufferSize,
+    /// What to upload, in the engine's getter names
+    pub contents: &'static str,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KernelSpec {
+    pub num_assets: usize,
+    pub steps: usize,
+    /// Pcg32 or Philox4x32 (the others need 64-bit multiplies per draw)
+    pub rng: RngKind,
+    pub output: KernelOutput,
+    /// Emit the Poisson jump stage and its three bindings
+    pub jumps: bool,
+    pub workgroup_size: u32,
+}
+
+impl KernelSpec {
+    pub fn new(num_assets: usize, steps: usize) -> Self {
+        KernelSpec {
+            num_assets,
+            steps,
+            rng: RngKind::Pcg32,
+            output: KernelOutput::Terminal,
+            jumps: true,
+            workgroup_size: 256,
+        }
+    }
+
+    pub fn with_rng(mut self, rng: RngKind) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    pub fn with_output(mut self, output: KernelOutput) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn with_jumps(mut self, jumps: bool) -> Self {
+        self.jumps = jumps;
+        self
+    }
+
+    pub fn with_workgroup_size(mut self, workgroup_size: u32) -> Self {
+        self.workgroup_size = workgroup_size;
+        self
+    }
+
+    /// Bind group 0, in binding order
+    pub fn bindings(&self) -> Vec<Binding> {
+        let n = self.num_assets;
+        let vector = BufferSize::Bytes(4 * n);
+        let mut entries = vec![
+            ("params", BindingKind::Uniform, BufferSize::Bytes(PARAMS_BYTES), "Kernel::params"),
+            ("drift", BindingKind::ReadOnlyStorage, vector, "adjusted_drift"),
+            ("vol", BindingKind::ReadOnlyStorage, vector, "adjusted_vol"),
+            ("cholesky", BindingKind::ReadOnlyStorage, BufferSize::Bytes(4 * n * n), "cholesky_l (row-major)"),
+        ];
+        if self.jumps {
+            entries.push(("jump_lambda", BindingKind::ReadOnlyStorage, vector, "jump_lambdas"));
+            entries.push(("jump_mean", BindingKind::ReadOnlyStorage, vector, "jump_means"));
+            entries.push(("jump_vol", BindingKind::ReadOnlyStorage, vector, "jump_vols"));
+        }
+        let per_path = match self.output {
+            KernelOutput::Terminal => 4 * n,
+            KernelOutput::Paths => 4 * n * (self.steps + 1),
+            KernelOutput::Portfolio => {
+                entries.push(("weights", BindingKind::ReadOnlyStorage, vector, "portfolio weights"));
+                4
+            }
+        };
+        entries.push(("output", BindingKind::Storage, BufferSize::BytesPerPath(per_path), "written by the kernel"));
+        entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, (name, kind, size, contents))| Binding { binding: i as u32, name, kind, size, contents })
+            .collect()
+    }
+
+    pub fn generate(&self) -> Result<Kernel, &'static str> {
+        if self.num_assets == 0 || self.num_assets > MAX_GPU_ASSETS {
+            return Err("GPU input invalid: num_assets must be 1..=64");
+        }
+        if self.steps == 0 {
+            return Err("GPU input invalid: steps must be positive");
+        }
+        if self.workgroup_size == 0 || self.workgroup_size > 256 {
+            return Err("GPU input invalid: workgroup_size must be 1..=256");
+        }
+        let rng = match self.rng {
+            RngKind::Pcg32 => PCG32_WGSL,
+            RngKind::Philox4x32 => PHILOX_WGSL,
+            _ => return Err("GPU input invalid: only pcg32 and philox4x32 have WGSL kernels"),
+        };
+        let bindings = self.bindings();
+        Ok(Kernel { source: self.source(rng, &bindings), bindings, spec: self.clone() })
+    }
+
+    fn source(&self, rng: &str, bindings: &[Binding]) -> String {
+        let mut src = format!(
+            "// Generated by mssim-engine gpu::KernelSpec — do not edit.\n\
+             // N = {n}, steps = {steps}, rng = {rng:?}, output = {output:?}\n\n\
+             const N: u32 = {n}u;\n\
+             const STEPS: u32 = {steps}u;\n\n",
+            n = self.num_assets,
+            steps = self.steps,
+            rng = self.rng,
+            output = self.output,
+        );
+        src.push_str(PARAMS_WGSL);
+        for b in bindings.iter().skip(1) {
+            let access = if b.kind == BindingKind::Storage { "read_write" } else { "read" };
+            src.push_str(&format!("@group(0) @binding({}) var<storage, {access}> {}: array<f32>;\n", b.binding, b.name));
+        }
+        src.push('\n');
+        src.push_str(U64_WGSL);
+        src.push_str(rng);
+        src.push_str(NORMAL_WGSL);
+        if self.jumps {
+            src.push_str(POISSON_WGSL);
+        }
+
+        let row = |step: &str| format!("(p * (STEPS + 1u) + {step}) * N + i");
+        let jump = if self.jumps {
+            "            let k = poisson(jump_lambda[i] * params.dt);\n\
+             \x20           if (k > 0u) {\n\
+             \x20               let kf = f32(k);\n\
+             \x20               log_s[i] += kf * jump_mean[i] + sqrt(kf) * jump_vol[i] * normal();\n\
+             \x20           }\n"
+        } else {
+            ""
+        };
+        let (start, per_step, finish) = match self.output {
+            KernelOutput::Terminal => (
+                String::new(),
+                String::new(),
+                "    for (var i = 0u; i < N; i++) {\n        output[p * N + i] = exp(log_s[i]);\n    }\n".to_string(),
+            ),
+            KernelOutput::Paths => (
+                format!("    for (var i = 0u; i < N; i++) {{\n        output[{}] = 1.0;\n    }}\n", row("0u")),
+                format!("            output[{}] = exp(log_s[i]);\n", row("step")),
+                String::new(),
+            ),
+            KernelOutput::Portfolio => (
+                String::new(),
+                String::new(),
+                "    var r = 0.0;\n    for (var i = 0u; i < N; i++) {\n        r += weights[i] * (exp(log_s[i]) - 1.0);\n    }\n    output[p] = r;\n"
+                    .to_string(),
+            ),
+        };
+        src.push_str(&format!(
+            "// ── Kernel ──────────────────────────────────────────────────────\n\n\
+             @compute @workgroup_size({wg})\n\
+             fn main(@builtin(global_invocation_id) gid: vec3<u32>) {{\n\
+             \x20   let p = gid.x;\n\
+             \x20   if (p >= params.num_paths) {{\n\
+             \x20       return;\n\
+             \x20   }}\n\
+             \x20   rng_init(vec2<u32>(params.seed_lo, params.seed_hi), params.path_offset + p);\n\n\
+             \x20   var drift_dt: array<f32, N>;\n\
+             \x20   var log_s: array<f32, N>;\n\
+             \x20   var z: array<f32, N>;\n\
+             \x20   for (var i = 0u; i < N; i++) {{\n\
+             \x20       drift_dt[i] = (drift[i] - 0.5 * vol[i] * vol[i]) * params.dt;\n\
+             \x20   }}\n\
+             {start}\
+             \x20   for (var step = 1u; step <= STEPS; step++) {{\n\
+             \x20       for (var i = 0u; i < N; i++) {{\n\
+             \x20           z[i] = normal();\n\
+             \x20       }}\n\
+             \x20       for (var i = 0u; i < N; i++) {{\n\
+             \x20           // X = L·Z, lower triangle only\n\
+             \x20           var x = 0.0;\n\
+             \x20           for (var j = 0u; j <= i; j++) {{\n\
+             \x20               x += cholesky[i * N + j] * z[j];\n\
+             \x20           }}\n\
+             \x20           log_s[i] += drift_dt[i] + params.sqrt_dt * x;\n\
+             {jump}\
+             {per_step}\
+             \x20       }}\n\
+             \x20   }}\n\
+             {finish}\
+             }}\n",
+            wg = self.workgroup_size,
+        ));
+        src
+    }
+}
+
+/// A generated kernel and the buffers it expects
+#[derive(Clone, Debug, PartialEq)]
+pub struct Kernel {
+    pub source: String,
+    pub bindings: Vec<Binding>,
+    pub spec: KernelSpec,
+}
+
+impl Kernel {
+    /// Workgroups to dispatch along x for `n_paths`
+    pub fn workgroups(&self, n_paths: u32) -> u32 {
+        n_paths.div_ceil(self.spec.workgroup_size)
+    }
+
+    /// Contents of the params uniform for paths
+    /// path_offset..path_offset + n_paths over `horizon` years.
+    pub fn params(&self, n_paths: u32, path_offset: u32, seed: u64, horizon: f64) -> Vec<u8> {
+        let dt = horizon / self.spec.steps as f64;
+        let words = [
+            n_paths,
+            path_offset,
+            seed as u32,
+            (seed >> 32) as u32,
+            (dt as f32).to_bits(),
+            (dt.sqrt() as f32).to_bits(),
+            0,
+            0,
+        ];
+        words.iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+
+    /// The binding layout as JSON:
+    /// {"workgroup_size", "bindings": [{binding, name, type, bytes | bytes_per_path, contents}]}
+    pub fn layout_json(&self) -> String {
+        let entries = self
+            .bindings
+            .iter()
+            .map(|b| {
+                let size = match b.size {
+                    BufferSize::Bytes(x) => ("bytes", x),
+                    BufferSize::BytesPerPath(x) => ("bytes_per_path", x),
+                };
+                Json::Object(vec![
+                    ("binding".into(), Json::Number(b.binding as f64)),
+                    ("name".into(), Json::String(b.name.into())),
+                    ("type".into(), Json::String(b.kind.as_str().into())),
+                    (size.0.into(), Json::Number(size.1 as f64)),
+                    ("contents".into(), Json::String(b.contents.into())),
+                ])
+            })
+            .collect();
+        Json::Object(vec![
+            ("workgroup_size".into(), Json::Number(self.spec.workgroup_size as f64)),
+            ("bindings".into(), Json::Array(entries)),
+        ])
+        .to_string()
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// WGSL fragments
+// ────────────────────────────────────────────────────────────────
+
+const PARAMS_WGSL: &str = "\
+struct Params {
+    num_paths:   u32,
+    path_offset: u32,   // first global path index of this dispatch
+    seed_lo:     u32,
+    seed_hi:     u32,
+    dt:          f32,   // horizon / STEPS
+    sqrt_dt:     f32,
+    _pad0:       u32,
+    _pad1:       u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+";
+
+const U64_WGSL: &str = "\
+// ── 64-bit helpers on (lo, hi) pairs ────────────────────────────
+
+fn mul_wide(a: u32, b: u32) -> vec2<u32> {
+    let a0 = a & 0xFFFFu;
+    let a1 = a >> 16u;
+    let b0 = b & 0xFFFFu;
+    let b1 = b >> 16u;
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let mid = (p00 >> 16u) + (p01 & 0xFFFFu) + (p10 & 0xFFFFu);
+    return vec2<u32>((p00 & 0xFFFFu) | (mid << 16u), a1 * b1 + (p01 >> 16u) + (p10 >> 16u) + (mid >> 16u));
+}
+
+fn mul64(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let lo = mul_wide(a.x, b.x);
+    return vec2<u32>(lo.x, lo.y + a.x * b.y + a.y * b.x);
+}
+
+fn add64(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let lo = a.x + b.x;
+    return vec2<u32>(lo, a.y + b.y + select(0u, 1u, lo < a.x));
+}
+
+";
+
+const PCG32_WGSL: &str = "\
+// ── PCG-XSH-RR, 64-bit state (simulate::Pcg32) ──────────────────
+
+const PCG_MULTIPLIER = vec2<u32>(0x4C957F2Du, 0x5851F42Du);
+
+var<private> pcg_state: vec2<u32>;
+var<private> pcg_inc: vec2<u32>;
+
+fn rng_next() -> u32 {
+    let old = pcg_state;
+    pcg_state = add64(mul64(old, PCG_MULTIPLIER), pcg_inc);
+    // ((old >> 18) ^ old) >> 27, low 32 bits
+    let x = vec2<u32>(((old.x >> 18u) | (old.y << 14u)) ^ old.x, (old.y >> 18u) ^ old.y);
+    let xsh = (x.x >> 27u) | (x.y << 5u);
+    let rot = old.y >> 27u;
+    return (xsh >> rot) | (xsh << ((32u - rot) & 31u));
+}
+
+// init_state = seed, init_seq = path
+fn rng_init(seed: vec2<u32>, path: u32) {
+    pcg_inc = vec2<u32>((path << 1u) | 1u, path >> 31u);
+    pcg_state = vec2<u32>(0u, 0u);
+    rng_next();
+    pcg_state = add64(pcg_state, seed);
+    rng_next();
+}
+
+";
+
+const PHILOX_WGSL: &str = "\
+// ── Philox4x32-10 (rng::Philox4x32) ─────────────────────────────
+// key = seed, counter = [block, 0, path, 0]
+
+var<private> philox_key: vec2<u32>;
+var<private> philox_counter: vec4<u32>;
+var<private> philox_buffer: vec4<u32>;
+var<private> philox_next: u32;
+
+fn philox_block(counter: vec4<u32>, key: vec2<u32>) -> vec4<u32> {
+    var x = counter;
+    var k = key;
+    for (var round = 0u; round < 10u; round++) {
+        if (round > 0u) {
+            k += vec2<u32>(0x9E3779B9u, 0xBB67AE85u);
+        }
+        let p0 = mul_wide(0xD2511F53u, x.x);
+        let p1 = mul_wide(0xCD9E8D57u, x.z);
+        x = vec4<u32>(p1.y ^ x.y ^ k.x, p1.x, p0.y ^ x.w ^ k.y, p0.x);
+    }
+    return x;
+}
+
+fn rng_init(seed: vec2<u32>, path: u32) {
+    philox_key = seed;
+    philox_counter = vec4<u32>(0u, 0u, path, 0u);
+    philox_next = 4u;
+}
+
+fn rng_next() -> u32 {
+    if (philox_next == 4u) {
+        philox_buffer = philox_block(philox_counter, philox_key);
+        philox_counter.x += 1u;
+        if (philox_counter.x == 0u) {
+            philox_counter.y += 1u;
+        }
+        philox_next = 0u;
+    }
+    let out = philox_buffer[philox_next];
+    philox_next += 1u;
+    return out;
+}
+
+";
+
+const NORMAL_WGSL: &str = "\
+// ── Uniform (0, 1) and Box–Muller with a cached spare ──────────
+
+var<private> spare: f32;
+var<private> has_spare: bool;
+
+fn next_uniform() -> f32 {
+    return (f32(rng_next() >> 8u) + 0.5) / 16777216.0;
+}
+
+fn normal() -> f32 {
+    if (has_spare) {
+        has_spare = false;
+        return spare;
+    }
+    let u1 = next_uniform();
+    let u2 = next_uniform();
+    let r = sqrt(-2.0 * log(u1));
+    let theta = 6.2831853 * u2;
+    spare = r * sin(theta);
+    has_spare = true;
+    return r * cos(theta);
+}
+
+";
+
+const POISSON_WGSL: &str = "\
+// ── Poisson(mean): Knuth's product, normal approximation past 30 ─
+
+fn poisson(mean: f32) -> u32 {
+    if (mean <= 0.0) {
+        return 0u;
+    }
+    if (mean > 30.0) {
+        return u32(max(round(mean + sqrt(mean) * normal()), 0.0));
+    }
+    let limit = exp(-mean);
+    var k = 0u;
+    var prod = next_uniform();
+    while (prod > limit) {
+        k += 1u;
+        prod *= next_uniform();
+    }
+    return k;
+}
+
+";
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bindings_match_source() {
+        for output in [KernelOutput::Terminal, KernelOutput::Paths, KernelOutput::Portfolio] {
+            for jumps in [true, false] {
+                let k = KernelSpec::new(3, 12).with_output(output).with_jumps(jumps).generate().unwrap();
+                for (i, b) in k.bindings.iter().enumerate() {
+                    assert_eq!(b.binding as usize, i);
+                    assert!(k.source.contains(&format!("@binding({i}) var<")));
+                    assert!(k.source.contains(&format!("> {}:", b.name)));
+                }
+                assert_eq!(k.source.matches('{').count(), k.source.matches('}').count());
+                assert_eq!(k.source.contains("fn poisson"), jumps);
+                assert!(k.source.contains("const N: u32 = 3u;") && k.source.contains("const STEPS: u32 = 12u;"));
+            }
+        }
+        let paths = KernelSpec::new(2, 10).with_output(KernelOutput::Paths).generate().unwrap();
+        assert_eq!(paths.bindings.last().unwrap().size, BufferSize::BytesPerPath(4 * 2 * 11));
+        assert!(paths.layout_json().contains(r#""type":"read-only-storage""#));
+
+        let philox = KernelSpec::new(2, 1).with_rng(RngKind::Philox4x32).generate().unwrap();
+        assert!(philox.source.contains("fn philox_block") && !philox.source.contains("pcg_state"));
+        assert!(KernelSpec::new(2, 1).with_rng(RngKind::Pcg64).generate().is_err());
+        assert!(KernelSpec::new(65, 1).generate().is_err());
+    }
+
+    #[test]
+    fn test_params_layout() {
+        let k = KernelSpec::new(2, 4).generate().unwrap();
+        let bytes = k.params(1000, 2048, 0x0000_0001_0000_0002, 1.0);
+        assert_eq!(bytes.len(), PARAMS_BYTES);
+        let word = |i: usize| u32::from_le_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap());
+        assert_eq!((word(0), word(1), word(2), word(3)), (1000, 2048, 2, 1));
+        assert_eq!(f32::from_bits(word(4)), 0.25);
+        assert_eq!(f32::from_bits(word(5)), 0.5);
+        assert_eq!(k.workgroups(1000), 4);
+    }
+}
@@ -0,0 +1,343 @@
+use std::fmt::{self, Write};
+
+// ════════════════════════════════════════════════════════════════
+// Minimal JSON for saving and reloading scenarios
+//
+// Just enough of RFC 8259 for the engine's configs and results:
+// objects, arrays, numbers, strings, booleans and null. Object keys
+// keep their order so saved files diff cleanly. Non-finite numbers
+// have no JSON form and are written as null.
+// ════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// An array of numbers as f32 (None if any element is not a number)
+    pub fn as_f32_vec(&self) -> Option<Vec<f32>> {
+        match self {
+            Json::Array(items) => items.iter().map(|x| x.as_f64().map(|x| x as f32)).collect(),
+            _ => None,
+        }
+    }
+
+    pub fn from_f32s(xs: &[f32]) -> Json {
+        Json::Array(xs.iter().map(|&x| Json::Number(x as f64)).collect())
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Writer — compact output; f32-sourced numbers print in their
+// shortest round-trip form
+// ────────────────────────────────────────────────────────────────
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(x) if !x.is_finite() => f.write_str("null"),
+            Json::Number(x) if *x == (*x as f32) as f64 => write!(f, "{}", *x as f32),
+            Json::Number(x) => write!(f, "{x}"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, k)?;
+                    write!(f, ":{v}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+// ────────────────────────────────────────────────────────────────
+// Parser — recursive descent over bytes, nesting capped at MAX_DEPTH
+// so hostile input cannot exhaust the stack
+// ────────────────────────────────────────────────────────────────
+
+/// Deepest array/object nesting accepted
+pub const MAX_DEPTH: usize = 128;
+
+pub fn parse(text: &str) -> Result<Json, &'static str> {
+    let mut p = Parser { bytes: text.as_bytes(), pos: 0, depth: 0 };
+    let value = p.value()?;
+    p.skip_ws();
+    if p.pos != p.bytes.len() {
+        return Err("JSON input invalid: trailing characters");
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Arrays and objects currently open
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn skip_ws(&mut self) {
+        while self.bytes.get(self.pos).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_ws();
+        let hit = self.bytes.get(self.pos) == Some(&byte);
+        if hit {
+            self.pos += 1;
+        }
+        hit
+    }
+
+    /// Steps past an opening bracket
+    fn open(&mut self) -> Result<(), &'static str> {
+        self.pos += 1;
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("JSON input invalid: nested more than 128 levels deep");
+        }
+        Ok(())
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, &'static str> {
+        if self.bytes[self.pos..].starts_with(word.as_bytes()) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err("JSON input invalid: unexpected token")
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, &'static str> {
+        self.skip_ws();
+        match self.bytes.get(self.pos) {
+            None => Err("JSON input invalid: unexpected end of input"),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b'[') => {
+                self.open()?;
+                let mut items = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        items.push(self.value()?);
+                        if self.eat(b']') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return Err("JSON input invalid: expected ',' or ']'");
+                        }
+                    }
+                }
+                self.depth -= 1;
+                Ok(Json::Array(items))
+            }
+            Some(b'{') => {
+                self.open()?;
+                let mut fields = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        self.skip_ws();
+                        if self.bytes.get(self.pos) != Some(&b'"') {
+                            return Err("JSON input invalid: expected a string key");
+                        }
+                        let key = self.string()?;
+                        if !self.eat(b':') {
+                            return Err("JSON input invalid: expected ':'");
+                        }
+                        fields.push((key, self.value()?));
+                        if self.eat(b'}') {
+                            break;
+                        }
+                        if !self.eat(b',') {
+                            return Err("JSON input invalid: expected ',' or '}'");
+                        }
+                    }
+                }
+                self.depth -= 1;
+                Ok(Json::Object(fields))
+            }
+            Some(_) => self.number(),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, &'static str> {
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_digit() || matches!(b, b'-' | b'+' | b'.' | b'e' | b'E'))
+        {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|x| x.is_finite())
+            .map(Json::Number)
+            .ok_or("JSON input invalid: malformed number")
+    }
+
+    /// Called with `pos` on the opening quote
+    fn string(&mut self) -> Result<String, &'static str> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let rest = &self.bytes[self.pos..];
+            let run = rest.iter().position(|&b| b == b'"' || b == b'\\').ok_or("JSON input invalid: unterminated string")?;
+            out.push_str(std::str::from_utf8(&rest[..run]).map_err(|_| "JSON input invalid: bad UTF-8")?);
+            self.pos += run + 1;
+            if rest[run] == b'"' {
+                return Ok(out);
+            }
+            let escape = *self.bytes.get(self.pos).ok_or("JSON input invalid: unterminated string")?;
+            self.pos += 1;
+            match escape {
+                b'"' => out.push('"'),
+                b'\\' => out.push('\\'),
+                b'/' => out.push('/'),
+                b'b' => out.push('\u{8}'),
+                b'f' => out.push('\u{c}'),
+                b'n' => out.push('\n'),
+                b'r' => out.push('\r'),
+                b't' => out.push('\t'),
+                b'u' => {
+                    let mut code = self.hex4()?;
+                    // Surrogate pair
+                    if (0xD800..0xDC00).contains(&code) && self.bytes[self.pos..].starts_with(b"\\u") {
+                        self.pos += 2;
+                        let low = self.hex4()?;
+                        code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                    }
+                    out.push(char::from_u32(code).ok_or("JSON input invalid: bad \\u escape")?);
+                }
+                _ => return Err("JSON input invalid: unknown escape"),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, &'static str> {
+        let digits = self.bytes.get(self.pos..self.pos + 4).ok_or("JSON input invalid: bad \\u escape")?;
+        self.pos += 4;
+        std::str::from_utf8(digits)
+            .ok()
+            .and_then(|s| u32::from_str_radix(s, 16).ok())
+            .ok_or("JSON input invalid: bad \\u escape")
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let value = Json::Object(vec![
+            ("name".into(), Json::String("Black \"Swan\"\n".into())),
+            ("skew".into(), Json::Number(0.85_f32 as f64)),
+            ("drift".into(), Json::from_f32s(&[0.08, -0.15, 1e-7])),
+            ("ok".into(), Json::Bool(true)),
+            ("none".into(), Json::Null),
+        ]);
+        let text = value.to_string();
+        assert!(text.contains("\"skew\":0.85"));
+        // f32 values reload to the same f32 and re-save identically
+        assert_eq!(parse(&text).unwrap().to_string(), text);
+        assert_eq!(parse(&text).unwrap().get("name"), value.get("name"));
+        assert_eq!(parse(&text).unwrap().get("drift").unwrap().as_f32_vec().unwrap(), vec![0.08, -0.15, 1e-7]);
+    }
+
+    #[test]
+    fn test_parse_whitespace_escapes_and_errors() {
+        let v = parse(" { \"a\" : [ 1 , 2.5e1 ] , \"s\" : \"\\u00e9\\ud83d\\ude00\\/\" } ").unwrap();
+        assert_eq!(v.get("a").unwrap().as_f32_vec().unwrap(), vec![1.0, 25.0]);
+        assert_eq!(v.get("s").unwrap().as_str().unwrap(), "é😀/");
+        assert_eq!(Json::Number(f64::NAN).to_string(), "null");
+        for bad in ["{\"a\":}", "[1,]", "[1 2]", "\"open", "{} x", "1e999"] {
+            assert!(parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_parse_caps_nesting_depth() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_err());
+        // Far past the cap errors out instead of overflowing the stack
+        assert!(parse(&"{\"a\":".repeat(1_000_000)).is_err());
+        // Depth is nesting, not the number of containers
+        let siblings = format!("[{}]", vec![nested(MAX_DEPTH - 1); 3].join(","));
+        assert!(parse(&siblings).is_ok());
+    }
+}
@@ -0,0 +1,225 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::portfolio::Portfolio;
+use crate::simulate::{self, OutputLayout, SimConfig};
+
+// ════════════════════════════════════════════════════════════════
+// Leverage and margin calls
+//
+// The portfolio's weights are the gross (buy-and-hold) positions, worth
+// V₀ = Σw. At leverage k the investor puts up equity E₀ = V₀ / k and
+// borrows D₀ = V₀ − E₀, which accrues at the financing rate:
+// D(t) = D₀·e^{r·t}. Equity is E = V − D and the margin ratio E / V.
+//
+// Path by path, at the end of each step:
+//   E ≤ 0          → wiped out: everything is sold, the (possibly
+//                    negative) equity is frozen from then on.
+//   E / V < maint. → margin call: sell x = V − E / target pro rata and
+//                    repay debt, restoring the target margin (ignoring
+//                    the cost of the sale itself, which is charged via
+//                    the portfolio's trade costs).
+// ════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MarginPolicy {
+    /// Gross exposure / equity (1 = unlevered)
+    pub leverage: f64,
+    /// Annual borrowing rate, continuously compounded
+    pub financing_rate: f64,
+    /// Minimum equity / value before a call
+    pub maintenance_margin: f64,
+    /// Equity / value restored after a call (defaults to 1 / leverage)
+    pub target_margin: Option<f64>,
+}
+
+impl MarginPolicy {
+    fn validate(&self) -> Result<f64, &'static str> {
+        if !(self.leverage.is_finite() && self.leverage >= 1.0 && self.financing_rate.is_finite()) {
+            return Err("Margin input invalid: leverage must be at least 1 and the rate finite");
+        }
+        let initial = 1.0 / self.leverage;
+        let target = self.target_margin.unwrap_or(initial);
+        let valid = self.maintenance_margin >= 0.0 && self.maintenance_margin < initial && target <= 1.0;
+        if !(valid && target > self.maintenance_margin) {
+            return Err("Margin input invalid: need 0 ≤ maintenance < 1 / leverage and maintenance < target ≤ 1");
+        }
+        Ok(target)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MarginPaths {
+    /// [path][step] equity P&L in currency, E(t) − E₀
+    pub pnl: Vec<f32>,
+    /// Step of the first margin call per path
+    pub first_call: Vec<Option<usize>>,
+    /// Number of margin calls per path
+    pub calls: Vec<u32>,
+    /// Whether equity was exhausted on the path
+    pub wiped_out: Vec<bool>,
+    /// Total interest paid per path, in currency
+    pub financing: Vec<f32>,
+}
+
+impl MarginPaths {
+    /// Share of paths with at least one margin call
+    pub fn call_probability(&self) -> f64 {
+        let called = self.first_call.iter().filter(|t| t.is_some()).count();
+        called as f64 / self.first_call.len().max(1) as f64
+    }
+
+    /// Time to first margin call in years, for the paths that had one,
+    /// sorted ascending
+    pub fn call_times(&self, config: &SimConfig) -> Vec<f64> {
+        let dt = config.horizon / config.steps as f64;
+        let mut times: Vec<f64> = self.first_call.iter().flatten().map(|&t| t as f64 * dt).collect();
+        times.sort_by(f64::total_cmp);
+        times
+    }
+}
+
+/// Apply `policy` to simulated paths of the portfolio's assets.
+pub fn margin_paths(
+    portfolio: &Portfolio,
+    policy: &MarginPolicy,
+    paths: &[f32],
+    config: &SimConfig,
+) -> Result<MarginPaths, &'static str> {
+    let target = policy.validate()?;
+    let (n, n_paths, rows) = (portfolio.num_assets(), config.n_paths, config.steps + 1);
+    if paths.len() != n_paths * rows * n {
+        return Err("Margin input mismatch: paths must be n_paths × (steps + 1) × N");
+    }
+    let weights = portfolio.weights();
+    let start: f64 = weights.iter().sum();
+    if start <= 0.0 {
+        return Err("Margin input invalid: gross portfolio value must be positive");
+    }
+    let equity0 = start / policy.leverage;
+    let growth = (policy.financing_rate * config.horizon / config.steps as f64).exp();
+    let notional = portfolio.notional();
+    let price = |p: usize, t: usize, a: usize| {
+        paths[match config.layout {
+            OutputLayout::Interleaved => (p * rows + t) * n + a,
+            OutputLayout::Planar => (a * n_paths + p) * rows + t,
+        }] as f64
+    };
+
+    let mut out = MarginPaths {
+        pnl: vec![0.0; n_paths * rows],
+        first_call: vec![None; n_paths],
+        calls: vec![0; n_paths],
+        wiped_out: vec![false; n_paths],
+        financing: vec![0.0; n_paths],
+    };
+    let mut units = vec![0.0; n];
+    for p in 0..n_paths {
+        units.copy_from_slice(weights);
+        let mut debt = start - equity0;
+        let mut interest = 0.0;
+        let mut frozen: Option<f64> = None;
+        for t in 1..rows {
+            if let Some(equity) = frozen {
+                out.pnl[p * rows + t] = (notional * (equity - equity0)) as f32;
+                continue;
+            }
+            interest += debt * (growth - 1.0);
+            debt *= growth;
+            let value: f64 = (0..n).map(|a| units[a] * price(p, t, a)).sum();
+            let mut equity = value - debt;
+            if equity <= 0.0 || equity < policy.maintenance_margin * value {
+                out.calls[p] += 1;
+                out.first_call[p].get_or_insert(t);
+                // Sale size as a fraction of every holding
+                let fraction = if equity <= 0.0 { 1.0 } else { (1.0 - equity / (target * value)).clamp(0.0, 1.0) };
+                let mut cost = 0.0;
+                for (a, h) in units.iter_mut().enumerate() {
+                    cost += portfolio.trade_cost(a, fraction * *h * price(p, t, a));
+                    *h *= 1.0 - fraction;
+                }
+                debt -= fraction * value - cost;
+                equity = value * (1.0 - fraction) - debt;
+                if fraction == 1.0 {
+                    out.wiped_out[p] = true;
+                    frozen = Some(equity);
+                }
+            }
+            out.pnl[p * rows + t] = (notional * (equity - equity0)) as f32;
+        }
+        out.financing[p] = (notional * interest) as f32;
+    }
+    Ok(out)
+}
+
+/// Simulate the assets (drift, vol, L) and apply the margin policy.
+pub fn simulate_margin(
+    drift: &DVector<f64>,
+    vol: &DVector<f64>,
+    cholesky_l: &DMatrix<f64>,
+    portfolio: &Portfolio,
+    policy: &MarginPolicy,
+    config: &SimConfig,
+) -> Result<MarginPaths, &'static str> {
+    let paths = simulate::simulate_paths(drift, vol, cholesky_l, config)?;
+    margin_paths(portfolio, policy, &paths, config)
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn policy(leverage: f64, maintenance: f64) -> MarginPolicy {
+        MarginPolicy { leverage, financing_rate: 0.0, maintenance_margin: maintenance, target_margin: None }
+    }
+
+    #[test]
+    fn test_leverage_scales_equity_pnl() {
+        // 2× on one asset: +10% on the asset is +20% on equity
+        let paths = [1.0, 1.1];
+        let config = SimConfig::new(1.0, 1, 1, 0);
+        let p = Portfolio::from_weights(vec![1.0], 100.0).unwrap();
+        let out = margin_paths(&p, &policy(2.0, 0.25), &paths, &config).unwrap();
+        assert_relative_eq!(out.pnl[1] as f64, 10.0, epsilon = 1e-5);
+        assert_eq!(out.first_call, vec![None]);
+
+        // Financing at 5% on the 50 borrowed
+        let financed = MarginPolicy { financing_rate: 0.05, ..policy(2.0, 0.25) };
+        let out = margin_paths(&p, &financed, &paths, &config).unwrap();
+        assert_relative_eq!(out.financing[0] as f64, 50.0 * (0.05_f64.exp() - 1.0), max_relative = 1e-5);
+        assert!(margin_paths(&p, &policy(2.0, 0.6), &paths, &config).is_err());
+    }
+
+    #[test]
+    fn test_margin_call_restores_target_and_wipeout_freezes() {
+        // 2×, 25% maintenance: at 0.6 equity is 0.1 of 0.6 → call
+        let paths = [1.0, 0.6, 0.25, 0.9];
+        let config = SimConfig::new(3.0, 3, 1, 0);
+        let p = Portfolio::from_weights(vec![1.0], 1.0).unwrap();
+        let out = margin_paths(&p, &policy(2.0, 0.25), &paths, &config).unwrap();
+        assert_eq!(out.first_call, vec![Some(1)]);
+        // Deleveraged to 50% margin: value 0.2, debt 0.1; at 0.25 value
+        // 0.083 < debt → wiped out, recovery never seen
+        assert_relative_eq!(out.pnl[1] as f64, 0.1 - 0.5, epsilon = 1e-6);
+        assert!(out.wiped_out[0]);
+        assert_eq!(out.calls[0], 2);
+        assert_eq!(out.pnl[3], out.pnl[2]);
+        assert_relative_eq!(out.call_times(&config)[0], 1.0);
+    }
+
+    #[test]
+    fn test_call_probability_rises_with_leverage() {
+        let drift = DVector::from_vec(vec![0.05]);
+        let vol = DVector::from_vec(vec![0.3]);
+        let l = DMatrix::from_element(1, 1, 0.3);
+        let config = SimConfig::new(1.0, 52, 2000, 4);
+        let p = Portfolio::from_weights(vec![1.0], 1.0).unwrap();
+        let prob = |k: f64| simulate_margin(&drift, &vol, &l, &p, &policy(k, 0.2), &config).unwrap().call_probability();
+        let (low, high) = (prob(1.5), prob(4.0));
+        assert!(low < high && high > 0.5);
+        assert_eq!(prob(1.0), 0.0);
+    }
+}
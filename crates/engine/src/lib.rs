@@ -1,4 +1,27 @@
-mod math;
+pub mod arrow;
+pub mod blocked;
+pub mod calibrate;
+pub mod data;
+pub mod error;
+pub mod estimate;
+pub mod fx;
+pub mod gpu;
+pub mod json;
+pub mod leverage;
+pub mod math;
+pub mod portfolio;
+pub mod presets;
+pub mod preview;
+pub mod replay;
+pub mod risk;
+pub mod rng;
+pub mod scenario;
+pub mod sensitivity;
+pub mod simd;
+pub mod simulate;
+pub mod sobol;
+pub mod strict;
+pub mod structure;
 mod engine;
 
 pub use engine::*;
@@ -1,10 +1,105 @@
-use nalgebra::{DMatrix, DVector};
+use nalgebra::{DMatrix, DVector, RealField};
+
+use crate::strict;
+
+// ════════════════════════════════════════════════════════════════
+// Scalar — the precisions Phase A runs in
+// Everything from Step 1 to the Cholesky factor is generic over it:
+// f64 is the accurate path behind every engine entry point, f32 the
+// preview pipeline (preview.rs) and the GPU's native width. Floors
+// and tolerances scale with the precision, and each type picks its
+// own kernels for the hot loops (SIMD and blocked ones for f64).
+// ════════════════════════════════════════════════════════════════
+pub trait Scalar: RealField + Copy {
+    /// Higham's PSD floor ε; the projection converges at 10·ε
+    fn psd_floor() -> Self;
+    /// cholesky_with_jitter's relative pivot floor and first ridge
+    fn ridge_rel_tol() -> Self;
+    /// Σ = D·R·D into `out`, which has R's shape
+    fn scale_symmetric_into(sigma: &DVector<Self>, r: &DMatrix<Self>, out: &mut DMatrix<Self>);
+    /// V·diag(λ)·Vᵀ
+    fn reconstruct(vectors: &DMatrix<Self>, values: &DVector<Self>) -> DMatrix<Self>;
+    /// Lower-triangular factor of `a` in its own allocation
+    fn factor(a: DMatrix<Self>) -> Option<DMatrix<Self>>;
+}
+
+impl Scalar for f64 {
+    fn psd_floor() -> f64 {
+        1e-10
+    }
+
+    fn ridge_rel_tol() -> f64 {
+        RIDGE_REL_TOL
+    }
+
+    fn scale_symmetric_into(sigma: &DVector<f64>, r: &DMatrix<f64>, out: &mut DMatrix<f64>) {
+        crate::simd::scale_symmetric_into(sigma, r, out);
+    }
+
+    fn reconstruct(vectors: &DMatrix<f64>, values: &DVector<f64>) -> DMatrix<f64> {
+        if strict::enabled() {
+            return strict::reconstruct(vectors, values);
+        }
+        if crate::simd::ENABLED {
+            return crate::simd::reconstruct(vectors, values);
+        }
+        vectors * DMatrix::from_diagonal(values) * vectors.transpose()
+    }
+
+    /// Blocked from blocked::MIN_N assets up outside strict mode (the
+    /// blocked gemm is not reproducible), nalgebra's Cholesky otherwise
+    fn factor(a: DMatrix<f64>) -> Option<DMatrix<f64>> {
+        if a.nrows() >= crate::blocked::MIN_N && !crate::strict::enabled() {
+            crate::blocked::cholesky(a)
+        } else {
+            nalgebra::linalg::Cholesky::new(a).map(|chol| chol.unpack())
+        }
+    }
+}
+
+/// The f64 floors where f32 can resolve them, else 100 (PSD) and 10
+/// (ridge) machine epsilons
+impl Scalar for f32 {
+    fn psd_floor() -> f32 {
+        100.0 * f32::EPSILON
+    }
+
+    fn ridge_rel_tol() -> f32 {
+        10.0 * f32::EPSILON
+    }
+
+    fn scale_symmetric_into(sigma: &DVector<f32>, r: &DMatrix<f32>, out: &mut DMatrix<f32>) {
+        for (j, mut column) in out.column_iter_mut().enumerate() {
+            for (i, o) in column.iter_mut().enumerate() {
+                *o = sigma[i] * r[(i, j)] * sigma[j];
+            }
+        }
+    }
+
+    fn reconstruct(vectors: &DMatrix<f32>, values: &DVector<f32>) -> DMatrix<f32> {
+        vectors * DMatrix::from_diagonal(values) * vectors.transpose()
+    }
+
+    fn factor(a: DMatrix<f32>) -> Option<DMatrix<f32>> {
+        nalgebra::linalg::Cholesky::new(a).map(|chol| chol.unpack())
+    }
+}
+
+/// An f64 constant in T
+fn cast<T: Scalar>(x: f64) -> T {
+    nalgebra::convert(x)
+}
+
+/// x > 0, false for NaN
+fn positive<T: Scalar>(x: T) -> bool {
+    x > T::zero()
+}
 
 // ────────────────────────────────────────────────────────────────
 // Phase A — Step 1: adjust_drift
 // μ_new = μ_base + Δμ
 // ────────────────────────────────────────────────────────────────
-pub fn adjust_drift(base: &DVector<f64>, delta: &DVector<f64>) -> DVector<f64> {
+pub fn adjust_drift<T: Scalar>(base: &DVector<T>, delta: &DVector<T>) -> DVector<T> {
     base + delta
 }
 
@@ -12,89 +107,858 @@ pub fn adjust_drift(base: &DVector<f64>, delta: &DVector<f64>) -> DVector<f64> {
 // Phase A — Step 2: adjust_vol
 // σ_new = σ_base × multiplier  (element-wise)
 // ────────────────────────────────────────────────────────────────
-pub fn adjust_vol(base: &DVector<f64>, multiplier: &DVector<f64>) -> DVector<f64> {
+pub fn adjust_vol<T: Scalar>(base: &DVector<T>, multiplier: &DVector<T>) -> DVector<T> {
     base.component_mul(multiplier)
 }
 
+// ────────────────────────────────────────────────────────────────
+// Phase A — Steps 1–2 over a term structure (B buckets × N assets)
+// The shock is either one row broadcast to every bucket or one row
+// per bucket.
+// ────────────────────────────────────────────────────────────────
+pub fn adjust_drift_term<T: Scalar>(base: &DMatrix<T>, delta: &DMatrix<T>) -> Result<DMatrix<T>, &'static str> {
+    let delta = broadcast_rows(delta, base)?;
+    Ok(base + delta)
+}
+
+pub fn adjust_vol_term<T: Scalar>(base: &DMatrix<T>, multiplier: &DMatrix<T>) -> Result<DMatrix<T>, &'static str> {
+    let multiplier = broadcast_rows(multiplier, base)?;
+    Ok(base.component_mul(&multiplier))
+}
+
+fn broadcast_rows<T: Scalar>(shock: &DMatrix<T>, base: &DMatrix<T>) -> Result<DMatrix<T>, &'static str> {
+    if shock.ncols() != base.ncols() || (shock.nrows() != 1 && shock.nrows() != base.nrows()) {
+        return Err("Term structure input mismatch: shock must be 1×N or B×N");
+    }
+    Ok(DMatrix::from_fn(base.nrows(), base.ncols(), |b, i| {
+        shock[(if shock.nrows() == 1 { 0 } else { b }, i)]
+    }))
+}
+
+// ────────────────────────────────────────────────────────────────
+// Phase A — input check: validate_correlation
+// A base correlation must be symmetric with unit diagonal and entries
+// in [−1, 1] (each to within `tolerance`); PD-ness is Step 4's job.
+// Reports the first offending entry in row-major order.
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CorrelationDefect {
+    pub row: usize,
+    pub col: usize,
+    pub reason: &'static str,
+}
+
+pub fn validate_correlation<T: Scalar>(r: &DMatrix<T>, tolerance: T) -> Result<(), CorrelationDefect> {
+    let within = |x: T, bound: T| x.abs() <= bound + tolerance;
+    for i in 0..r.nrows() {
+        for j in 0..r.ncols() {
+            let x = r[(i, j)];
+            let reason = if !within(x, T::one()) {
+                "Correlation input invalid: entry outside [−1, 1]"
+            } else if i == j && !within(x - T::one(), T::zero()) {
+                "Correlation input invalid: diagonal entry is not 1"
+            } else if !within(x - r[(j, i)], T::zero()) {
+                "Correlation input invalid: matrix is not symmetric"
+            } else {
+                continue;
+            };
+            return Err(CorrelationDefect { row: i, col: j, reason });
+        }
+    }
+    Ok(())
+}
+
 // ────────────────────────────────────────────────────────────────
 // Phase A — Step 3: blend_correlation
 // R_new = (1 - skew) * R_base + skew * J   (J = all-ones matrix)
 // ────────────────────────────────────────────────────────────────
-pub fn blend_correlation(r_base: &DMatrix<f64>, skew: f64) -> DMatrix<f64> {
+pub fn blend_correlation<T: Scalar>(r_base: &DMatrix<T>, skew: T) -> DMatrix<T> {
     let n = r_base.nrows();
-    let ones = DMatrix::from_element(n, n, 1.0);
-    r_base * (1.0 - skew) + ones * skew
+    let ones = DMatrix::from_element(n, n, T::one());
+    r_base * (T::one() - skew) + ones * skew
+}
+
+// ────────────────────────────────────────────────────────────────
+// Phase A — Step 3b: augment_correlation
+// Appends F extra processes (e.g. FX rates) to an N-asset correlation:
+//     R = [ R_assets   R_cross ]
+//         [ R_crossᵀ   R_extra ]
+// R_cross is N×F. The result is not repaired; run nearest_pd after.
+// ────────────────────────────────────────────────────────────────
+pub fn augment_correlation<T: Scalar>(
+    r_assets: &DMatrix<T>,
+    r_cross: &DMatrix<T>,
+    r_extra: &DMatrix<T>,
+) -> Result<DMatrix<T>, &'static str> {
+    let (n, f) = (r_assets.nrows(), r_extra.nrows());
+    if !r_assets.is_square() || !r_extra.is_square() || r_cross.shape() != (n, f) {
+        return Err("Correlation input mismatch: expected N×N, N×F and F×F blocks");
+    }
+    let mut r = DMatrix::zeros(n + f, n + f);
+    r.view_mut((0, 0), (n, n)).copy_from(r_assets);
+    r.view_mut((0, n), (n, f)).copy_from(r_cross);
+    r.view_mut((n, 0), (f, n)).copy_from(&r_cross.transpose());
+    r.view_mut((n, n), (f, f)).copy_from(r_extra);
+    Ok(r)
+}
+
+// ────────────────────────────────────────────────────────────────
+// Phase A — Step 0 (alt): corr_from_factors
+// R = S · (B·F·Bᵀ + D) · S   where D = diag(idio_var), S = diag(1/√diag)
+// F is the K×K factor covariance; None takes the factors as
+// standardized and orthogonal (F = I). With F PD and idio_var > 0 the
+// result is PD by construction — no repair needed.
+// ────────────────────────────────────────────────────────────────
+pub fn corr_from_factors<T: Scalar>(
+    loadings: &DMatrix<T>,
+    factor_cov: Option<&DMatrix<T>>,
+    idio_var: &DVector<T>,
+) -> Result<DMatrix<T>, &'static str> {
+    let (n, k) = loadings.shape();
+    if idio_var.len() != n {
+        return Err("Factor model mismatch: idio_var length must equal the number of loading rows");
+    }
+    if idio_var.iter().any(|&v| !positive(v)) {
+        return Err("Factor model invalid: idiosyncratic variances must be strictly positive");
+    }
+
+    let mut cov = match factor_cov {
+        None => loadings * loadings.transpose(),
+        Some(f) => {
+            if f.shape() != (k, k) {
+                return Err("Factor model mismatch: factor covariance must be K×K for K loading columns");
+            }
+            if f != &f.transpose() || cholesky_decompose(f).is_err() {
+                return Err("Factor model invalid: factor covariance must be symmetric positive-definite");
+            }
+            loadings * f * loadings.transpose()
+        }
+    };
+    for i in 0..n {
+        cov[(i, i)] += idio_var[i];
+    }
+
+    Ok(cov_to_corr(&cov))
 }
 
 // ────────────────────────────────────────────────────────────────
 // Phase A — Step 4: nearest_pd  (Higham's alternating projections)
 // Guarantees the blended correlation matrix is positive-definite.
 // ────────────────────────────────────────────────────────────────
-pub fn nearest_pd(mat: &DMatrix<f64>) -> DMatrix<f64> {
-    let n = mat.nrows();
-    let max_iter = 100;
-    let eps = 1e-10;
+pub fn nearest_pd<T: Scalar>(mat: &DMatrix<T>) -> DMatrix<T> {
+    nearest_pd_masked(mat, None)
+}
 
-    // Symmetrize
-    let mut y = (mat + mat.transpose()) * 0.5;
-    let mut ds = DMatrix::zeros(n, n);
+// ────────────────────────────────────────────────────────────────
+// Phase A — Step 4 pre-check: is_pd
+// Cholesky of sym(R) − floor·I exists iff λ_min(sym(R)) > floor, so
+// one factorization (n³/3 flops, none of them an eigensolve) tells
+// whether the Higham loop has anything to repair.
+// ────────────────────────────────────────────────────────────────
+pub fn is_pd<T: Scalar>(r: &DMatrix<T>, floor: T) -> bool {
+    let mut shifted = (r + r.transpose()) * cast::<T>(0.5);
+    for i in 0..shifted.nrows() {
+        shifted[(i, i)] -= floor;
+    }
+    nalgebra::linalg::Cholesky::new(shifted).is_some()
+}
 
-    for _ in 0..max_iter {
-        let r = &y - &ds;
+// ────────────────────────────────────────────────────────────────
+// Phase A — Step 4 (constrained): nearest_pd_masked
+// Same alternating projections, but the U-projection also pins every
+// entry with fixed[(i, j)] == true back to its input value, so only
+// free entries move. The mask is symmetrized (either triangle fixes
+// the pair). The fixed pattern must admit a PD completion; otherwise
+// the result is the closest the loop gets and may not be strictly PD.
+// ────────────────────────────────────────────────────────────────
+pub fn nearest_pd_masked<T: Scalar>(mat: &DMatrix<T>, fixed: Option<&DMatrix<bool>>) -> DMatrix<T> {
+    let mut projection = HighamProjection::new(mat, fixed);
+    projection.step(usize::MAX);
+    projection.result()
+}
 
-        // Project onto S+ (positive semidefinite cone)
-        let eigen = r.clone().symmetric_eigen();
-        let mut vals = eigen.eigenvalues.clone();
-        for v in vals.iter_mut() {
-            if *v < eps {
-                *v = eps;
-            }
+// ────────────────────────────────────────────────────────────────
+// Phase A — Step 4 (resumable): HighamProjection
+// The alternating-projection loop as a state machine, so a caller can
+// run a few iterations at a time (e.g. yielding to the browser between
+// batches on large N) and resume where it stopped. Running it to the
+// end is exactly nearest_pd_masked.
+// ────────────────────────────────────────────────────────────────
+pub struct HighamProjection<T: Scalar = f64> {
+    target: DMatrix<T>,
+    fixed: Option<DMatrix<bool>>,
+    y: DMatrix<T>,
+    ds: DMatrix<T>,
+    iterations: usize,
+    max_iter: usize,
+    converged: bool,
+}
+
+impl<T: Scalar> HighamProjection<T> {
+    pub fn new(mat: &DMatrix<T>, fixed: Option<&DMatrix<bool>>) -> Self {
+        let n = mat.nrows();
+        // Symmetrize
+        let target = (mat + mat.transpose()) * cast::<T>(0.5);
+        HighamProjection {
+            y: target.clone(),
+            target,
+            fixed: fixed.map(|m| DMatrix::from_fn(n, n, |i, j| m[(i, j)] || m[(j, i)])),
+            ds: DMatrix::zeros(n, n),
+            iterations: 0,
+            // Pinned entries slow the alternating projections considerably
+            max_iter: if fixed.is_some() { 1000 } else { 100 },
+            converged: false,
+        }
+    }
+
+    /// Run up to `iterations` more iterations; true once finished.
+    pub fn step(&mut self, iterations: usize) -> bool {
+        let eps = T::psd_floor();
+        let mut budget = iterations;
+        while budget > 0 && !self.is_finished() {
+            budget -= 1;
+            self.iterations += 1;
+            let r = &self.y - &self.ds;
+
+            // Project onto S+ (positive semidefinite cone)
+            let x_pos = project_psd(&r, eps);
+
+            self.ds = &x_pos - &r;
+
+            self.y = self.project_u(&x_pos);
+
+            // Check convergence
+            let diff = (&self.y - &x_pos).norm();
+            self.converged = diff < eps * cast(10.0);
         }
-        let x_pos = &eigen.eigenvectors
-            * DMatrix::from_diagonal(&vals)
-            * eigen.eigenvectors.transpose();
+        self.is_finished()
+    }
 
-        ds = &x_pos - &r;
+    pub fn is_finished(&self) -> bool {
+        self.converged || self.iterations >= self.max_iter
+    }
+
+    /// Finished by meeting the tolerance rather than the iteration cap
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
 
-        // Project onto U (unit diagonal)
-        y = x_pos.clone();
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    pub fn max_iterations(&self) -> usize {
+        self.max_iter
+    }
+
+    /// The current iterate, symmetrized with unit diagonal / fixed entries
+    pub fn result(&self) -> DMatrix<T> {
+        let result = (&self.y + self.y.transpose()) * cast::<T>(0.5);
+        self.project_u(&result)
+    }
+
+    // Project onto U (unit diagonal + fixed entries)
+    fn project_u(&self, x: &DMatrix<T>) -> DMatrix<T> {
+        let n = x.nrows();
+        let mut out = x.clone();
         for i in 0..n {
-            y[(i, i)] = 1.0;
+            for j in 0..n {
+                if i == j {
+                    out[(i, i)] = T::one();
+                } else if self.fixed.as_ref().is_some_and(|m| m[(i, j)]) {
+                    out[(i, j)] = self.target[(i, j)];
+                }
+            }
         }
+        out
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Phase A — Step 4 helper: project_psd
+// X₊ = R + Σ_{λᵢ<ε} (ε − λᵢ)·vᵢvᵢᵀ  — clip the spectrum at ε.
+// From PARTIAL_EIGEN_MIN_N assets up, only the negative tail is
+// resolved (Lanczos); a full eigendecomposition is the fallback.
+// ────────────────────────────────────────────────────────────────
+pub const PARTIAL_EIGEN_MIN_N: usize = 200;
+pub const LANCZOS_MAX_DIM: usize = 80;
+
+pub fn project_psd<T: Scalar>(r: &DMatrix<T>, eps: T) -> DMatrix<T> {
+    if r.nrows() >= PARTIAL_EIGEN_MIN_N {
+        if let Some(x) = project_psd_partial(r, eps) {
+            return x;
+        }
+    }
+    project_psd_full(r, eps)
+}
 
-        // Check convergence
-        let diff = (&y - &x_pos).norm();
-        if diff < eps * 10.0 {
+pub fn project_psd_full<T: Scalar>(r: &DMatrix<T>, eps: T) -> DMatrix<T> {
+    let eigen = r.clone().symmetric_eigen();
+    let mut vals = eigen.eigenvalues.clone();
+    for v in vals.iter_mut() {
+        if *v < eps {
+            *v = eps;
+        }
+    }
+    T::reconstruct(&eigen.eigenvectors, &vals)
+}
+
+// ────────────────────────────────────────────────────────────────
+// Lanczos with full reorthogonalization: O(n²·m) for an m-dim Krylov
+// space instead of O(n³) per projection. Returns None — so the caller
+// falls back to the full path — when a tail Ritz pair has not
+// converged, the tail fills the Krylov space, or the corrected matrix
+// still fails a Cholesky check (i.e. a negative eigenvalue was missed).
+// ────────────────────────────────────────────────────────────────
+pub fn project_psd_partial<T: Scalar>(r: &DMatrix<T>, eps: T) -> Option<DMatrix<T>> {
+    let n = r.nrows();
+    let m_max = LANCZOS_MAX_DIM.min(n);
+    let tol = T::psd_floor() * cast(100.0) * r.norm().max(T::one());
+
+    // Deterministic, non-degenerate start vector
+    let mut q = DVector::from_fn(n, |i, _| cast(1.0 + ((i as f64) * 0.618_033_988_75).fract()));
+    q /= q.norm();
+
+    let mut basis: Vec<DVector<T>> = Vec::with_capacity(m_max);
+    let mut alpha = Vec::with_capacity(m_max);
+    let mut beta: Vec<T> = Vec::with_capacity(m_max);
+
+    for j in 0..m_max {
+        let mut w = r * &q;
+        alpha.push(q.dot(&w));
+        basis.push(q.clone());
+
+        // Full reorthogonalization (twice is enough)
+        for _ in 0..2 {
+            for v in &basis {
+                let c = v.dot(&w);
+                w.axpy(-c, v, T::one());
+            }
+        }
+
+        if j + 1 == m_max {
+            beta.push(w.norm());
             break;
         }
+
+        let b = w.norm();
+        if b < tol {
+            // Invariant subspace found: restart from a fresh direction
+            // so repeated eigenvalues still get their full multiplicity.
+            let mut fresh = DVector::from_fn(n, |i, _| cast((((i + 1) * (j + 7)) as f64 * 0.754_877_666).fract() - 0.5));
+            for _ in 0..2 {
+                for v in &basis {
+                    let c = v.dot(&fresh);
+                    fresh.axpy(-c, v, T::one());
+                }
+            }
+            let norm = fresh.norm();
+            if norm < tol {
+                beta.push(T::zero());
+                break;
+            }
+            beta.push(T::zero());
+            q = fresh / norm;
+        } else {
+            beta.push(b);
+            q = w / b;
+        }
     }
 
-    // Final symmetrize + enforce unit diagonal
-    let result = (&y + y.transpose()) * 0.5;
-    let mut out = result;
-    for i in 0..n {
-        out[(i, i)] = 1.0;
+    // Ritz pairs from the tridiagonal T (m × m)
+    let m = basis.len();
+    let mut t = DMatrix::zeros(m, m);
+    for i in 0..m {
+        t[(i, i)] = alpha[i];
+        if i + 1 < m {
+            t[(i, i + 1)] = beta[i];
+            t[(i + 1, i)] = beta[i];
+        }
     }
-    out
+    let eigen = t.symmetric_eigen();
+    let last_beta = beta[m - 1];
+
+    let tail: Vec<usize> = (0..m).filter(|&k| eigen.eigenvalues[k] < eps).collect();
+    if tail.len() * 2 > m {
+        return None;
+    }
+
+    let mut x = r.clone();
+    for &k in &tail {
+        // Residual ‖R·v − θ·v‖ = β_m · |s_{m,k}|
+        if last_beta * eigen.eigenvectors[(m - 1, k)].abs() > tol {
+            return None;
+        }
+        let mut v = DVector::zeros(n);
+        for (i, b) in basis.iter().enumerate() {
+            v.axpy(eigen.eigenvectors[(i, k)], b, T::one());
+        }
+        let shift = eps - eigen.eigenvalues[k];
+        x.ger(shift, &v, &v, T::one());
+    }
+
+    cholesky_decompose(&x).ok()?;
+    Some(x)
+}
+
+// ────────────────────────────────────────────────────────────────
+// Phase A — Steps 1–3 into reused buffers
+// The same arithmetic as adjust_drift, adjust_vol and
+// blend_correlation, written into `out` (reallocated only when its
+// shape differs), for callers that recompute at a fixed N.
+// ────────────────────────────────────────────────────────────────
+pub fn adjust_drift_into<T: Scalar>(base: &DVector<T>, delta: &DVector<T>, out: &mut DVector<T>) {
+    if out.len() != base.len() {
+        *out = DVector::zeros(base.len());
+    }
+    out.copy_from(base);
+    *out += delta;
+}
+
+pub fn adjust_vol_into<T: Scalar>(base: &DVector<T>, multiplier: &DVector<T>, out: &mut DVector<T>) {
+    if out.len() != base.len() {
+        *out = DVector::zeros(base.len());
+    }
+    for ((o, &b), &m) in out.iter_mut().zip(base.iter()).zip(multiplier.iter()) {
+        *o = b * m;
+    }
+}
+
+pub fn blend_correlation_into<T: Scalar>(r_base: &DMatrix<T>, skew: T, out: &mut DMatrix<T>) {
+    if out.shape() != r_base.shape() {
+        *out = DMatrix::zeros(r_base.nrows(), r_base.ncols());
+    }
+    for (o, &r) in out.iter_mut().zip(r_base.iter()) {
+        *o = r * (T::one() - skew) + skew;
+    }
+}
+
+/// rebuild_covariance into `out`
+pub fn rebuild_covariance_into<T: Scalar>(sigma: &DVector<T>, r: &DMatrix<T>, out: &mut DMatrix<T>) {
+    if out.shape() != r.shape() {
+        *out = DMatrix::zeros(r.nrows(), r.ncols());
+    }
+    T::scale_symmetric_into(sigma, r, out);
 }
 
 // ────────────────────────────────────────────────────────────────
 // Phase A — Step 5: rebuild_covariance
 // Σ = D · R · D   where D = diag(σ_new)
+// Elementwise (Σᵢⱼ = σᵢ·Rᵢⱼ·σⱼ) and written column by column, so it
+// streams R once at any N and needs no blocked form.
 // ────────────────────────────────────────────────────────────────
-pub fn rebuild_covariance(sigma: &DVector<f64>, r: &DMatrix<f64>) -> DMatrix<f64> {
-    let d = DMatrix::from_diagonal(sigma);
-    &d * r * &d
+pub fn rebuild_covariance<T: Scalar>(sigma: &DVector<T>, r: &DMatrix<T>) -> DMatrix<T> {
+    let mut out = DMatrix::zeros(r.nrows(), r.ncols());
+    T::scale_symmetric_into(sigma, r, &mut out);
+    out
 }
 
 // ────────────────────────────────────────────────────────────────
 // Phase A — Step 6: cholesky_decompose
 // LL^T = Σ  →  returns lower-triangular L
 // ────────────────────────────────────────────────────────────────
-pub fn cholesky_decompose(sigma: &DMatrix<f64>) -> Result<DMatrix<f64>, &'static str> {
-    nalgebra::linalg::Cholesky::new(sigma.clone())
-        .map(|chol| chol.l())
-        .ok_or("Cholesky decomposition failed: matrix is not positive-definite")
+pub fn cholesky_decompose<T: Scalar>(sigma: &DMatrix<T>) -> Result<DMatrix<T>, &'static str> {
+    T::factor(sigma.clone()).ok_or("Cholesky decomposition failed: matrix is not positive-definite")
+}
+
+// ────────────────────────────────────────────────────────────────
+// Phase A — Step 6b: cholesky_with_jitter
+// LL^T = Σ + εI  with the smallest ε from a geometric ladder
+// (ε = 0, then RIDGE_REL_TOL·mean(diag Σ)·10^k) that yields a factor
+// whose pivots all clear the same relative tolerance. Returns (L, ε).
+// ε never exceeds RIDGE_MAX_REL·mean(diag Σ): a Σ that needs more is
+// rejected rather than factored with visibly inflated variances.
+// ────────────────────────────────────────────────────────────────
+pub const RIDGE_REL_TOL: f64 = 1e-7;
+pub const RIDGE_MAX_REL: f64 = 1e-4;
+
+pub fn cholesky_with_jitter<T: Scalar>(sigma: &DMatrix<T>) -> Result<(DMatrix<T>, T), &'static str> {
+    let mut l = DMatrix::zeros(0, 0);
+    let jitter = cholesky_with_jitter_into(sigma, &mut l)?;
+    Ok((l, jitter))
+}
+
+/// cholesky_with_jitter writing L into `l`, factoring in its
+/// allocation when it already has Σ's shape; returns ε.
+pub fn cholesky_with_jitter_into<T: Scalar>(sigma: &DMatrix<T>, l: &mut DMatrix<T>) -> Result<T, &'static str> {
+    let n = sigma.nrows();
+    let mean_diag = if n == 0 { T::zero() } else { sigma.diagonal().sum() / cast(n as f64) };
+    let floor = T::ridge_rel_tol() * mean_diag;
+
+    let stable = |l: &DMatrix<T>| (0..n).all(|i| l[(i, i)] * l[(i, i)] >= floor);
+    // Σ + εI factored in place of `l`; `l` is left empty on failure
+    let factor = |l: &mut DMatrix<T>, jitter: T| {
+        let mut a = std::mem::replace(l, DMatrix::zeros(0, 0));
+        if a.shape() == sigma.shape() {
+            a.copy_from(sigma);
+        } else {
+            a = sigma.clone();
+        }
+        if jitter != T::zero() {
+            for i in 0..n {
+                a[(i, i)] += jitter;
+            }
+        }
+        if let Some(factor) = T::factor(a) {
+            *l = factor;
+        }
+        l.shape() == sigma.shape() && stable(l)
+    };
+
+    if factor(l, T::zero()) {
+        return Ok(T::zero());
+    }
+    let cap = cast::<T>(RIDGE_MAX_REL) * mean_diag;
+    let mut jitter = floor;
+    loop {
+        // The last rung is the cap itself
+        let rung = jitter.min(cap);
+        if factor(l, rung) {
+            return Ok(rung);
+        }
+        if jitter >= cap {
+            break;
+        }
+        jitter *= cast(10.0);
+    }
+
+    Err("Cholesky decomposition failed: matrix is not positive-definite even after ridge regularization")
+}
+
+// ────────────────────────────────────────────────────────────────
+// Phase A — Step 6 incremental: rank-k Cholesky update / downdate
+// L·Lᵀ ± U·Uᵀ refactored in place one column of U at a time, O(N²)
+// per column instead of O(N³) from scratch (Givens rotations for an
+// update, hyperbolic ones for a downdate). Either fails once a pivot
+// would become non-positive — for an update only when L is singular,
+// for a downdate when the result is not PD — leaving L unspecified.
+// ────────────────────────────────────────────────────────────────
+pub fn cholesky_update<T: Scalar>(l: &mut DMatrix<T>, u: &DMatrix<T>) -> Result<(), &'static str> {
+    for column in u.column_iter() {
+        rank_one(l, column.iter().copied().collect(), T::one())?;
+    }
+    Ok(())
+}
+
+pub fn cholesky_downdate<T: Scalar>(l: &mut DMatrix<T>, u: &DMatrix<T>) -> Result<(), &'static str> {
+    for column in u.column_iter() {
+        rank_one(l, column.iter().copied().collect(), -T::one())?;
+    }
+    Ok(())
+}
+
+/// L·Lᵀ + sign·x·xᵀ for sign = ±1
+fn rank_one<T: Scalar>(l: &mut DMatrix<T>, mut x: Vec<T>, sign: T) -> Result<(), &'static str> {
+    let n = l.nrows();
+    for k in 0..n {
+        let lkk = l[(k, k)];
+        let r2 = lkk * lkk + sign * x[k] * x[k];
+        if !positive(r2) {
+            return Err("Cholesky update failed: matrix is no longer positive-definite");
+        }
+        let r = r2.sqrt();
+        let (c, s) = (r / lkk, x[k] / lkk);
+        l[(k, k)] = r;
+        for i in k + 1..n {
+            l[(i, k)] = (l[(i, k)] + sign * s * x[i]) / c;
+            x[i] = c * x[i] - s * l[(i, k)];
+        }
+    }
+    Ok(())
+}
+
+// ────────────────────────────────────────────────────────────────
+// Phase A — Step 6 incremental: rescale_cholesky
+// New vols with R unchanged give Σ' = S·Σ·S, S = diag(scale). S·L is
+// lower triangular with a positive diagonal and (S·L)(S·L)ᵀ = S·Σ·S,
+// so it is Σ''s Cholesky factor: row i of L times scaleᵢ, O(kN) for k
+// changed assets. Fails when a pivot falls below cholesky_with_jitter's
+// floor (Σ' would need a ridge), so callers refactor from scratch.
+// ────────────────────────────────────────────────────────────────
+pub fn rescale_cholesky<T: Scalar>(l: &mut DMatrix<T>, scale: &DVector<T>) -> Result<(), &'static str> {
+    let n = l.nrows();
+    if scale.len() != n || scale.iter().any(|&c| !(c.is_finite() && positive(c))) {
+        return Err("Cholesky rescale input invalid: scale must be N positive factors");
+    }
+    for i in (0..n).filter(|&i| scale[i] != T::one()) {
+        let mut row = l.row_mut(i);
+        row *= scale[i];
+    }
+    let mean_diag = l.row_iter().map(|r| r.norm_squared()).fold(T::zero(), |a, b| a + b) / cast(n.max(1) as f64);
+    let floor = T::ridge_rel_tol() * mean_diag;
+    if (0..n).any(|i| l[(i, i)] * l[(i, i)] < floor) {
+        return Err("Cholesky rescale failed: a pivot fell below the ridge floor");
+    }
+    Ok(())
+}
+
+// ────────────────────────────────────────────────────────────────
+// Phase A — Step 6 inverse: correlation_from_cholesky
+// R = S·(L·Lᵀ)·S with S = diag(1/√Σᵢᵢ): the correlation the simulator
+// actually uses, after repair and any ridge jitter. Assets with zero
+// variance get zero off-diagonal entries.
+// ────────────────────────────────────────────────────────────────
+pub fn correlation_from_cholesky<T: Scalar>(l: &DMatrix<T>) -> DMatrix<T> {
+    let cov = l * l.transpose();
+    let n = cov.nrows();
+    DMatrix::from_fn(n, n, |i, j| {
+        let d = (cov[(i, i)] * cov[(j, j)]).sqrt();
+        match (i == j, positive(d)) {
+            (true, _) => T::one(),
+            (false, true) => cov[(i, j)] / d,
+            (false, false) => T::zero(),
+        }
+    })
+}
+
+// ────────────────────────────────────────────────────────────────
+// Transitions: interpolate_correlation  (log-Euclidean geodesic)
+// S(t) = exp((1 - t)·log R₀ + t·log R₁), rescaled to unit diagonal.
+// Every S(t) is SPD, so every frame is a valid correlation matrix;
+// t = 0 and t = 1 reproduce R₀ and R₁.
+// ────────────────────────────────────────────────────────────────
+pub fn interpolate_correlation<T: Scalar>(
+    r0: &DMatrix<T>,
+    r1: &DMatrix<T>,
+    t: T,
+) -> Result<DMatrix<T>, &'static str> {
+    if r0.shape() != r1.shape() {
+        return Err("Correlation interpolation failed: matrices differ in shape");
+    }
+    let log0 = sym_log(r0)?;
+    let log1 = sym_log(r1)?;
+    let s = sym_exp(&(log0 * (T::one() - t) + log1 * t));
+    Ok(cov_to_corr(&s))
+}
+
+/// Matrix logarithm of a symmetric positive-definite matrix.
+fn sym_log<T: Scalar>(mat: &DMatrix<T>) -> Result<DMatrix<T>, &'static str> {
+    let sym = (mat + mat.transpose()) * cast::<T>(0.5);
+    let eigen = sym.symmetric_eigen();
+    if eigen.eigenvalues.iter().any(|&v| !positive(v)) {
+        return Err("Correlation interpolation failed: endpoint is not positive-definite");
+    }
+    let logs = eigen.eigenvalues.map(T::ln);
+    Ok(&eigen.eigenvectors * DMatrix::from_diagonal(&logs) * eigen.eigenvectors.transpose())
+}
+
+/// Matrix exponential of a symmetric matrix.
+fn sym_exp<T: Scalar>(mat: &DMatrix<T>) -> DMatrix<T> {
+    let sym = (mat + mat.transpose()) * cast::<T>(0.5);
+    let eigen = sym.symmetric_eigen();
+    let exps = eigen.eigenvalues.map(T::exp);
+    &eigen.eigenvectors * DMatrix::from_diagonal(&exps) * eigen.eigenvectors.transpose()
+}
+
+/// R = D^{-1/2} · Σ · D^{-1/2}, with the diagonal pinned to exactly 1.
+fn cov_to_corr<T: Scalar>(cov: &DMatrix<T>) -> DMatrix<T> {
+    let n = cov.nrows();
+    let scale = cov.diagonal().map(|v| T::one() / v.sqrt());
+    let s = DMatrix::from_diagonal(&scale);
+    let mut r = &s * cov * &s;
+    r = (&r + r.transpose()) * cast::<T>(0.5);
+    for i in 0..n {
+        r[(i, i)] = T::one();
+    }
+    r
+}
+
+// ────────────────────────────────────────────────────────────────
+// Special functions: ln_gamma  (Lanczos, g = 7, n = 9)
+// Accurate to ~1e-15 for x > 0; reflection handles x < 0.5.
+// ────────────────────────────────────────────────────────────────
+pub fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEF: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Γ(x)·Γ(1−x) = π / sin(πx)
+        let pi = std::f64::consts::PI;
+        return strict::ln((pi / strict::sin(pi * x)).abs()) - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut a = COEF[0];
+    let t = x + G + 0.5;
+    for (i, c) in COEF.iter().enumerate().skip(1) {
+        a += c / (x + i as f64);
+    }
+    0.5 * strict::ln(2.0 * std::f64::consts::PI) + (x + 0.5) * strict::ln(t) - t + strict::ln(a)
+}
+
+// ────────────────────────────────────────────────────────────────
+// Special functions: inv_norm_cdf  (Acklam's rational approximation)
+// Φ⁻¹(p) for p ∈ (0, 1), relative error below 1.2e-9.
+// ────────────────────────────────────────────────────────────────
+pub fn inv_norm_cdf(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.024_25;
+
+    if p <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+    if p < P_LOW {
+        let q = (-2.0 * strict::ln(p)).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -inv_norm_cdf(1.0 - p)
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Block-diagonal structure
+// Regional universes are block-diagonal: R = diag(R₁, …, R_k). Each
+// Phase A step then acts block-by-block, costing Σ bᵢ³ instead of N³
+// and never materialising the zero cross-block entries.
+// ════════════════════════════════════════════════════════════════
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockDiagonal<T: Scalar = f64> {
+    blocks: Vec<DMatrix<T>>,
+}
+
+impl<T: Scalar> BlockDiagonal<T> {
+    pub fn from_blocks(blocks: Vec<DMatrix<T>>) -> Result<Self, &'static str> {
+        if blocks.iter().any(|b| !b.is_square()) {
+            return Err("Block-diagonal matrix invalid: every block must be square");
+        }
+        Ok(Self { blocks })
+    }
+
+    /// Split a dense matrix into the finest contiguous diagonal blocks
+    /// whose off-block entries are all within `tol` of zero.
+    pub fn from_dense(mat: &DMatrix<T>, tol: T) -> Self {
+        let n = mat.nrows();
+        let mut blocks = Vec::new();
+        let mut start = 0;
+        // reach = furthest column the current block must extend to
+        let mut reach = 0;
+        for i in 0..n {
+            for j in (i + 1)..n {
+                if mat[(i, j)].abs() > tol || mat[(j, i)].abs() > tol {
+                    reach = reach.max(j);
+                }
+            }
+            if reach <= i {
+                let size = i + 1 - start;
+                blocks.push(mat.view((start, start), (size, size)).into_owned());
+                start = i + 1;
+                reach = start;
+            }
+        }
+        Self { blocks }
+    }
+
+    pub fn blocks(&self) -> &[DMatrix<T>] {
+        &self.blocks
+    }
+
+    pub fn block_sizes(&self) -> Vec<usize> {
+        self.blocks.iter().map(|b| b.nrows()).collect()
+    }
+
+    pub fn dim(&self) -> usize {
+        self.blocks.iter().map(|b| b.nrows()).sum()
+    }
+
+    pub fn to_dense(&self) -> DMatrix<T> {
+        let n = self.dim();
+        let mut out = DMatrix::zeros(n, n);
+        let mut offset = 0;
+        for b in &self.blocks {
+            let size = b.nrows();
+            out.view_mut((offset, offset), (size, size)).copy_from(b);
+            offset += size;
+        }
+        out
+    }
+
+    fn map_blocks<F>(&self, mut f: F) -> Result<Self, &'static str>
+    where
+        F: FnMut(usize, &DMatrix<T>) -> Result<DMatrix<T>, &'static str>,
+    {
+        let mut offset = 0;
+        let mut blocks = Vec::with_capacity(self.blocks.len());
+        for b in &self.blocks {
+            blocks.push(f(offset, b)?);
+            offset += b.nrows();
+        }
+        Ok(Self { blocks })
+    }
+}
+
+// Step 3 per block: each region blends toward its own all-ones block
+// (within-region contagion); cross-region entries stay exactly zero.
+pub fn blend_correlation_block<T: Scalar>(r_base: &BlockDiagonal<T>, skew: T) -> BlockDiagonal<T> {
+    BlockDiagonal {
+        blocks: r_base.blocks.iter().map(|b| blend_correlation(b, skew)).collect(),
+    }
+}
+
+pub fn nearest_pd_block<T: Scalar>(mat: &BlockDiagonal<T>) -> BlockDiagonal<T> {
+    BlockDiagonal {
+        blocks: mat.blocks.iter().map(nearest_pd).collect(),
+    }
+}
+
+pub fn rebuild_covariance_block<T: Scalar>(
+    sigma: &DVector<T>,
+    r: &BlockDiagonal<T>,
+) -> Result<BlockDiagonal<T>, &'static str> {
+    if sigma.len() != r.dim() {
+        return Err("Block covariance mismatch: sigma length must equal the block dimension");
+    }
+    r.map_blocks(|offset, b| Ok(rebuild_covariance(&sigma.rows(offset, b.nrows()).into_owned(), b)))
+}
+
+/// L = diag(L₁, …, L_k): the Cholesky factor of a block-diagonal Σ is
+/// itself block-diagonal, so each block factors independently.
+pub fn cholesky_decompose_block<T: Scalar>(sigma: &BlockDiagonal<T>) -> Result<BlockDiagonal<T>, &'static str> {
+    sigma.map_blocks(|_, b| cholesky_decompose(b))
 }
 
 // ════════════════════════════════════════════════════════════════
@@ -145,6 +1009,56 @@ mod tests {
         assert_relative_eq!(result, expected, epsilon = 1e-10);
     }
 
+    #[test]
+    fn test_augment_correlation_places_blocks() {
+        let r = DMatrix::from_row_slice(2, 2, &[1.0, 0.5, 0.5, 1.0]);
+        let cross = DMatrix::from_row_slice(2, 1, &[0.1, -0.2]);
+        let fx = DMatrix::identity(1, 1);
+        let full = augment_correlation(&r, &cross, &fx).unwrap();
+        assert_eq!(full, DMatrix::from_row_slice(3, 3, &[
+            1.0,  0.5,  0.1,
+            0.5,  1.0, -0.2,
+            0.1, -0.2,  1.0,
+        ]));
+        assert!(augment_correlation(&r, &cross.transpose(), &fx).is_err());
+    }
+
+    #[test]
+    fn test_correlation_from_cholesky_round_trips() {
+        let vol = DVector::from_vec(vec![0.2, 0.05]);
+        let r = DMatrix::from_row_slice(2, 2, &[1.0, 0.5, 0.5, 1.0]);
+        let l = cholesky_decompose(&rebuild_covariance(&vol, &r)).unwrap();
+        assert_relative_eq!(correlation_from_cholesky(&l), r, epsilon = 1e-12);
+        // Zero-variance asset: no correlation, unit diagonal
+        assert_eq!(correlation_from_cholesky(&DMatrix::<f64>::zeros(2, 2)), DMatrix::identity(2, 2));
+    }
+
+    #[test]
+    fn test_higham_projection_resumes_to_nearest_pd() {
+        let bad = DMatrix::from_row_slice(3, 3, &[1.0, 0.9, -0.9, 0.9, 1.0, 0.9, -0.9, 0.9, 1.0]);
+        let mut projection = HighamProjection::new(&bad, None);
+        let mut calls = 0;
+        while !projection.step(2) {
+            calls += 1;
+        }
+        assert!(calls > 0 && projection.iterations() <= projection.max_iterations());
+        assert_eq!(projection.result(), nearest_pd(&bad));
+    }
+
+    #[test]
+    fn test_is_pd_matches_smallest_eigenvalue() {
+        let r = DMatrix::from_row_slice(3, 3, &[1.0, 0.9, 0.7, 0.9, 1.0, 0.4, 0.7, 0.4, 1.0]);
+        let min = r.clone().symmetric_eigenvalues().min();
+        assert!(min > 0.0);
+        assert!(is_pd(&r, 0.0));
+        assert!(is_pd(&r, 0.99 * min));
+        assert!(!is_pd(&r, 1.01 * min));
+
+        let bad = DMatrix::from_row_slice(3, 3, &[1.0, 0.9, -0.9, 0.9, 1.0, 0.9, -0.9, 0.9, 1.0]);
+        assert!(!is_pd(&bad, 0.0));
+        assert!(is_pd(&nearest_pd(&bad), 0.0));
+    }
+
     #[test]
     fn test_nearest_pd() {
         // Create a matrix that is NOT positive-definite
@@ -223,4 +1137,360 @@ mod tests {
         let reconstructed = &l * l.transpose();
         assert_relative_eq!(reconstructed, cov, epsilon = 1e-6);
     }
+
+    #[test]
+    fn test_cholesky_jitter_not_needed() {
+        let sigma = DVector::from_vec(vec![0.18, 0.06]);
+        let r = DMatrix::from_row_slice(2, 2, &[
+            1.0, 0.3,
+            0.3, 1.0,
+        ]);
+        let cov = rebuild_covariance(&sigma, &r);
+        let (l, jitter) = cholesky_with_jitter(&cov).expect("should succeed");
+        assert_eq!(jitter, 0.0);
+        assert_relative_eq!(&l * l.transpose(), cov, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_cholesky_jitter_singular() {
+        // Perfectly correlated pair → singular covariance
+        let sigma = DVector::from_vec(vec![0.2, 0.1]);
+        let r = DMatrix::from_element(2, 2, 1.0);
+        let cov = rebuild_covariance(&sigma, &r);
+        let (l, jitter) = cholesky_with_jitter(&cov).expect("jitter should rescue singular Σ");
+        assert!(jitter > 0.0);
+
+        // Off-diagonals untouched; only the diagonal moved by ε
+        let reconstructed = &l * l.transpose();
+        assert_relative_eq!(reconstructed[(0, 1)], cov[(0, 1)], epsilon = 1e-12);
+        assert_relative_eq!(reconstructed[(0, 0)], cov[(0, 0)] + jitter, epsilon = 1e-12);
+        assert_relative_eq!(reconstructed[(1, 1)], cov[(1, 1)] + jitter, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_cholesky_jitter_rejects_indefinite() {
+        // λ_min = −0.04 would need ε ≈ mean(diag Σ): rejected, not ridged
+        let cov = DMatrix::from_row_slice(2, 2, &[0.04, 0.08, 0.08, 0.04]);
+        assert!(cholesky_with_jitter(&cov).is_err());
+        assert!(cholesky_with_jitter(&cov.map(|x| x as f32)).is_err());
+        // A slightly indefinite Σ still fits under the cap
+        let mild = DMatrix::from_row_slice(2, 2, &[0.04, 0.040_000_4, 0.040_000_4, 0.04]);
+        let (_, jitter) = cholesky_with_jitter(&mild).unwrap();
+        assert!(jitter > 0.0 && jitter <= RIDGE_MAX_REL * 0.04);
+    }
+
+    #[test]
+    fn test_into_variants_match_allocating_steps() {
+        let r = DMatrix::from_row_slice(3, 3, &[1.0, 0.3, -0.2, 0.3, 1.0, 0.5, -0.2, 0.5, 1.0]);
+        let base = DVector::from_vec(vec![0.05, 0.07, 0.02]);
+        let shock = DVector::from_vec(vec![1.5, 0.9, 2.0]);
+        let (mut drift, mut vol) = (DVector::zeros(0), DVector::zeros(3));
+        adjust_drift_into(&base, &shock, &mut drift);
+        adjust_vol_into(&base, &shock, &mut vol);
+        assert_eq!(drift, adjust_drift(&base, &shock));
+        assert_eq!(vol, adjust_vol(&base, &shock));
+
+        let mut blended = DMatrix::zeros(3, 3);
+        blend_correlation_into(&r, 0.4, &mut blended);
+        assert_eq!(blended, blend_correlation(&r, 0.4));
+        let mut cov = DMatrix::zeros(1, 1);
+        rebuild_covariance_into(&vol, &blended, &mut cov);
+        assert_eq!(cov, rebuild_covariance(&vol, &blended));
+
+        // The buffer is reused across a clean and a jittered factorization
+        let mut l = DMatrix::zeros(3, 3);
+        assert_eq!(cholesky_with_jitter_into(&cov, &mut l), Ok(0.0));
+        assert_eq!(l, cholesky_decompose(&cov).unwrap());
+        let singular = DMatrix::from_element(3, 3, 0.04);
+        let jitter = cholesky_with_jitter_into(&singular, &mut l).unwrap();
+        assert_eq!((l.clone(), jitter), cholesky_with_jitter(&singular).unwrap());
+        assert!(jitter > 0.0);
+    }
+
+    #[test]
+    fn test_rank_k_update_downdate_and_rescale() {
+        let r = DMatrix::from_row_slice(4, 4, &[
+            1.0, 0.6, 0.3, -0.2, //
+            0.6, 1.0, 0.4, 0.1, //
+            0.3, 0.4, 1.0, 0.5, //
+            -0.2, 0.1, 0.5, 1.0,
+        ]);
+        let vol = DVector::from_vec(vec![0.2, 0.3, 0.15, 0.4]);
+        let sigma = rebuild_covariance(&vol, &r);
+        let l0 = cholesky_decompose(&sigma).unwrap();
+        let u = DMatrix::from_fn(4, 2, |i, j| 0.05 * (i as f64 + 1.0) * if j == 0 { 1.0 } else { -0.5 });
+
+        let mut l = l0.clone();
+        cholesky_update(&mut l, &u).unwrap();
+        assert_relative_eq!(l, cholesky_decompose(&(&sigma + &u * u.transpose())).unwrap(), epsilon = 1e-12);
+        cholesky_downdate(&mut l, &u).unwrap();
+        assert_relative_eq!(l, l0, epsilon = 1e-12);
+        assert!(cholesky_downdate(&mut l0.clone(), &(&u * 10.0)).is_err());
+
+        let scale = DVector::from_vec(vec![1.0, 2.5, 1.0, 0.5]);
+        let mut l = l0.clone();
+        rescale_cholesky(&mut l, &scale).unwrap();
+        assert_eq!(l.row(1), l0.row(1) * 2.5);
+        assert_eq!(l.row(2), l0.row(2));
+        let shocked = rebuild_covariance(&vol.component_mul(&scale), &r);
+        assert_relative_eq!(l, cholesky_decompose(&shocked).unwrap(), epsilon = 1e-12);
+        assert!(rescale_cholesky(&mut l, &DVector::from_element(4, 0.0)).is_err());
+    }
+
+    #[test]
+    fn test_corr_from_factors() {
+        // 4 assets, 2 factors
+        let b = DMatrix::from_row_slice(4, 2, &[
+            0.8,  0.1,
+            0.6,  0.3,
+            0.2,  0.7,
+           -0.1,  0.5,
+        ]);
+        let idio = DVector::from_vec(vec![0.3, 0.5, 0.4, 0.6]);
+        let r = corr_from_factors(&b, None, &idio).expect("valid factor model");
+
+        assert_relative_eq!(r, r.transpose(), epsilon = 1e-12);
+        for i in 0..4 {
+            assert_relative_eq!(r[(i, i)], 1.0, epsilon = 1e-12);
+        }
+        // ρ₀₁ = b₀·b₁ / √((|b₀|²+d₀)(|b₁|²+d₁))
+        let expected = (0.8 * 0.6 + 0.1 * 0.3) / ((0.65_f64 + 0.3) * (0.45 + 0.5)).sqrt();
+        assert_relative_eq!(r[(0, 1)], expected, epsilon = 1e-12);
+        assert!(cholesky_decompose(&r).is_ok());
+    }
+
+    #[test]
+    fn test_corr_from_factors_rejects_bad_idio() {
+        let b = DMatrix::from_row_slice(2, 1, &[0.5, 0.5]);
+        assert!(corr_from_factors(&b, None, &DVector::from_vec(vec![0.1])).is_err());
+        assert!(corr_from_factors(&b, None, &DVector::from_vec(vec![0.1, 0.0])).is_err());
+    }
+
+    #[test]
+    fn test_corr_from_factors_uses_factor_covariance() {
+        let b = DMatrix::from_row_slice(3, 2, &[0.8, 0.1, 0.3, 0.6, -0.2, 0.5]);
+        let idio = DVector::from_vec(vec![0.3, 0.4, 0.5]);
+        let f = DMatrix::from_row_slice(2, 2, &[1.5, 0.4, 0.4, 0.8]);
+        // Same as B' = B·chol(F) with orthogonal factors
+        let b_chol = &b * cholesky_decompose(&f).unwrap();
+        let r = corr_from_factors(&b, Some(&f), &idio).unwrap();
+        assert_relative_eq!(r, corr_from_factors(&b_chol, None, &idio).unwrap(), epsilon = 1e-12);
+        // F = I matches None
+        let eye = DMatrix::identity(2, 2);
+        assert_eq!(corr_from_factors(&b, Some(&eye), &idio), corr_from_factors(&b, None, &idio));
+
+        assert!(corr_from_factors(&b, Some(&DMatrix::identity(3, 3)), &idio).is_err());
+        let asym = DMatrix::from_row_slice(2, 2, &[1.0, 0.2, 0.1, 1.0]);
+        assert!(corr_from_factors(&b, Some(&asym), &idio).is_err());
+        let indefinite = DMatrix::from_row_slice(2, 2, &[1.0, 2.0, 2.0, 1.0]);
+        assert!(corr_from_factors(&b, Some(&indefinite), &idio).is_err());
+    }
+
+    #[test]
+    fn test_nearest_pd_masked_preserves_fixed() {
+        // Indefinite: ρ₀₁ = ρ₀₂ = 0.9 but ρ₁₂ = -0.9
+        let bad = DMatrix::<f64>::from_row_slice(3, 3, &[
+            1.0,  0.9,  0.9,
+            0.9,  1.0, -0.9,
+            0.9, -0.9,  1.0,
+        ]);
+        let mut fixed = DMatrix::from_element(3, 3, false);
+        fixed[(0, 1)] = true;
+
+        let result = nearest_pd_masked(&bad, Some(&fixed));
+
+        // Fixed pair untouched (both triangles)
+        assert_eq!(result[(0, 1)], 0.9);
+        assert_eq!(result[(1, 0)], 0.9);
+        // Free entries moved to restore PD
+        assert!((result[(1, 2)] - bad[(1, 2)]).abs() > 1e-3);
+        let eigen = result.clone().symmetric_eigen();
+        for v in eigen.eigenvalues.iter() {
+            assert!(*v > -1e-8, "eigenvalue {} should be non-negative", v);
+        }
+        for i in 0..3 {
+            assert_relative_eq!(result[(i, i)], 1.0, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_nearest_pd_masked_none_matches_unmasked() {
+        let bad = DMatrix::from_row_slice(3, 3, &[
+            1.0, 0.9, 0.9,
+            0.9, 1.0, 0.9,
+            0.9, 0.9, 1.0,
+        ]);
+        let empty = DMatrix::from_element(3, 3, false);
+        assert_relative_eq!(nearest_pd_masked(&bad, Some(&empty)), nearest_pd(&bad), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_interpolate_correlation_endpoints() {
+        let r0 = DMatrix::from_row_slice(3, 3, &[
+            1.0,  0.2,  0.3,
+            0.2,  1.0, -0.1,
+            0.3, -0.1,  1.0,
+        ]);
+        let r1 = nearest_pd(&blend_correlation(&r0, 0.85));
+        assert_relative_eq!(interpolate_correlation(&r0, &r1, 0.0).unwrap(), r0, epsilon = 1e-8);
+        assert_relative_eq!(interpolate_correlation(&r0, &r1, 1.0).unwrap(), r1, epsilon = 1e-8);
+    }
+
+    #[test]
+    fn test_interpolate_correlation_midpoints_valid() {
+        let r0 = DMatrix::from_row_slice(2, 2, &[
+            1.0, -0.8,
+           -0.8,  1.0,
+        ]);
+        let r1 = DMatrix::from_row_slice(2, 2, &[
+            1.0, 0.8,
+            0.8, 1.0,
+        ]);
+        for k in 1..10 {
+            let rt = interpolate_correlation(&r0, &r1, k as f64 / 10.0).unwrap();
+            assert_relative_eq!(rt[(0, 0)], 1.0, epsilon = 1e-12);
+            assert_relative_eq!(rt[(1, 1)], 1.0, epsilon = 1e-12);
+            assert!(rt[(0, 1)].abs() < 1.0);
+            assert!(cholesky_decompose(&rt).is_ok());
+        }
+        // Symmetric endpoints → geodesic midpoint is uncorrelated
+        let mid = interpolate_correlation(&r0, &r1, 0.5).unwrap();
+        assert_relative_eq!(mid[(0, 1)], 0.0, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_project_psd_partial_matches_full() {
+        // 60 assets, two blocks with contradictory cross-correlation
+        let n = 60;
+        let r = DMatrix::from_fn(n, n, |i, j| {
+            if i == j {
+                1.0
+            } else if (i < n / 2) == (j < n / 2) {
+                0.7
+            } else if i == 0 || j == 0 {
+                0.95
+            } else {
+                -0.3
+            }
+        });
+        let eps = 1e-10;
+        let negatives = r.clone().symmetric_eigen().eigenvalues.iter().filter(|&&v| v < 0.0).count();
+        assert!(negatives > 0, "test matrix should be indefinite");
+
+        let full = project_psd_full(&r, eps);
+        let partial = project_psd_partial(&r, eps).expect("Lanczos tail should converge");
+        assert_relative_eq!(partial, full, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_nearest_pd_large_uses_partial_path() {
+        let n = PARTIAL_EIGEN_MIN_N;
+        let r = DMatrix::from_fn(n, n, |i, j| {
+            if i == j {
+                1.0
+            } else if (i + j) % 7 == 0 {
+                0.9
+            } else {
+                0.35
+            }
+        });
+        let negatives = r.clone().symmetric_eigen().eigenvalues.iter().filter(|&&v| v < 0.0).count();
+        assert!(negatives > 0, "test matrix should be indefinite");
+        // Higham's first projection is of r itself; it must not fall back
+        let eps = <f64 as Scalar>::psd_floor();
+        let partial = project_psd_partial(&r, eps).expect("Lanczos tail should converge");
+        assert_relative_eq!(partial, project_psd_full(&r, eps), epsilon = 1e-6);
+
+        let result = nearest_pd(&r);
+        assert!(cholesky_decompose(&result).is_ok());
+        for i in 0..n {
+            assert_relative_eq!(result[(i, i)], 1.0, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_block_diagonal_detect_roundtrip() {
+        let dense = DMatrix::from_row_slice(5, 5, &[
+            1.0, 0.4, 0.0, 0.0, 0.0,
+            0.4, 1.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0, 0.2,
+            0.0, 0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.2, 0.0, 1.0,
+        ]);
+        let bd = BlockDiagonal::from_dense(&dense, 0.0);
+        // {0,1} and {2,3,4} — entry (2,4) forces 3 into the second block
+        assert_eq!(bd.block_sizes(), vec![2, 3]);
+        assert_eq!(bd.to_dense(), dense);
+    }
+
+    #[test]
+    fn test_block_pipeline_matches_dense() {
+        let r1 = DMatrix::from_row_slice(2, 2, &[1.0, 0.3, 0.3, 1.0]);
+        let r2 = DMatrix::from_row_slice(3, 3, &[
+            1.0, 0.9, 0.9,
+            0.9, 1.0, 0.9,
+            0.9, 0.9, 1.0,
+        ]);
+        let bd = BlockDiagonal::from_blocks(vec![r1, r2]).unwrap();
+        let sigma = DVector::from_vec(vec![0.18, 0.06, 0.22, 0.3, 0.1]);
+
+        let pd = nearest_pd_block(&blend_correlation_block(&bd, 0.5));
+        let cov = rebuild_covariance_block(&sigma, &pd).unwrap();
+        let l = cholesky_decompose_block(&cov).unwrap();
+
+        let dense_cov = rebuild_covariance(&sigma, &pd.to_dense());
+        assert_relative_eq!(cov.to_dense(), dense_cov, epsilon = 1e-12);
+        let l_dense = l.to_dense();
+        assert_relative_eq!(&l_dense * l_dense.transpose(), dense_cov, epsilon = 1e-10);
+        assert_eq!(l_dense[(3, 0)], 0.0);
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        assert_relative_eq!(ln_gamma(1.0), 0.0, epsilon = 1e-13);
+        assert_relative_eq!(ln_gamma(5.0), 24.0_f64.ln(), epsilon = 1e-12);
+        assert_relative_eq!(ln_gamma(0.5), std::f64::consts::PI.sqrt().ln(), epsilon = 1e-12);
+        assert_relative_eq!(ln_gamma(0.1), 2.252_712_651_734_206, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_inv_norm_cdf() {
+        assert_relative_eq!(inv_norm_cdf(0.5), 0.0, epsilon = 1e-12);
+        assert_relative_eq!(inv_norm_cdf(0.975), 1.959_963_984_540_054, epsilon = 1e-8);
+        assert_relative_eq!(inv_norm_cdf(0.001), -3.090_232_306_167_813, epsilon = 1e-8);
+        assert_relative_eq!(inv_norm_cdf(0.3), -inv_norm_cdf(0.7), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_term_adjustments_broadcast_or_apply_per_bucket() {
+        let base = DMatrix::from_row_slice(2, 2, &[0.05, 0.02, 0.05, 0.02]);
+        let one = DMatrix::from_row_slice(1, 2, &[-0.1, 0.0]);
+        let drift = adjust_drift_term(&base, &one).unwrap();
+        assert_relative_eq!(drift, DMatrix::from_row_slice(2, 2, &[-0.05, 0.02, -0.05, 0.02]), epsilon = 1e-12);
+
+        let per_bucket = DMatrix::from_row_slice(2, 2, &[3.0, 2.0, 1.0, 1.0]);
+        let vol = adjust_vol_term(&base, &per_bucket).unwrap();
+        assert_relative_eq!(vol, DMatrix::from_row_slice(2, 2, &[0.15, 0.04, 0.05, 0.02]), epsilon = 1e-12);
+
+        assert!(adjust_vol_term(&base, &DMatrix::zeros(3, 2)).is_err());
+    }
+
+    #[test]
+    fn test_validate_correlation_reports_first_defect() {
+        let good = DMatrix::from_row_slice(2, 2, &[1.0, 0.5, 0.5, 1.0]);
+        assert_eq!(validate_correlation(&good, 0.0), Ok(()));
+
+        let asym = DMatrix::from_row_slice(3, 3, &[1.0, 0.2, 0.1, 0.2, 1.0, 0.3, 0.1, 0.3000001, 1.0]);
+        let e = validate_correlation(&asym, 0.0).unwrap_err();
+        assert_eq!((e.row, e.col), (1, 2));
+        assert!(e.reason.contains("symmetric"));
+        assert_eq!(validate_correlation(&asym, 1e-6), Ok(()));
+
+        let diag = DMatrix::from_row_slice(2, 2, &[1.0, 0.5, 0.5, 0.9]);
+        assert_eq!(validate_correlation(&diag, 1e-6).unwrap_err().row, 1);
+        let range = DMatrix::from_row_slice(2, 2, &[1.0, 1.5, 1.5, 1.0]);
+        assert!(validate_correlation(&range, 1e-6).unwrap_err().reason.contains("[−1, 1]"));
+        let nan = DMatrix::from_row_slice(2, 2, &[1.0, f64::NAN, f64::NAN, 1.0]);
+        assert_eq!(validate_correlation(&nan, 1e-6).unwrap_err().col, 1);
+    }
 }
@@ -0,0 +1,440 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::simulate::{self, OutputLayout, SimConfig};
+
+// ════════════════════════════════════════════════════════════════
+// Portfolio aggregation
+//
+// With S₀ = 1 a weight w_a buys w_a units, so the portfolio starts at
+// notional·Σ w_a. Holdings stay fixed (buy-and-hold) unless a
+// RebalancePolicy resets them to the target mix w / Σw, or a forced
+// liquidation sells them into cash.
+// ════════════════════════════════════════════════════════════════
+
+/// Trading days per year, for converting steps to days of volume
+const TRADING_DAYS: f64 = 252.0;
+
+/// When to trade back to target weights; either trigger fires a
+/// rebalance at the end of a step. The default never trades.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RebalancePolicy {
+    /// Every k steps
+    pub every: Option<usize>,
+    /// When any |current − target| weight exceeds the band
+    pub band: Option<f64>,
+    /// Proportional cost per unit of traded value (e.g. 0.001 = 10 bp)
+    pub cost_rate: f64,
+}
+
+/// ([path][step] P&L, cost per path, forced-liquidation step per path)
+type RunOutput = (Vec<f32>, Vec<f32>, Vec<Option<usize>>);
+
+impl RebalancePolicy {
+    fn is_buy_and_hold(&self) -> bool {
+        self.every.is_none() && self.band.is_none()
+    }
+}
+
+/// Per-asset execution costs. Trading value x (in starting-portfolio
+/// units) costs |x|·(spread / 2 + impact·√(|x| / c)) on top of the
+/// policy's cost_rate, where c = |w| / days_to_liquidate is the value
+/// one day of normal volume absorbs (square-root impact).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Liquidity {
+    /// Full bid–ask spread as a fraction of price
+    pub spread: f64,
+    /// Impact coefficient per √(days of volume traded)
+    pub impact: f64,
+    /// Days to exit the initial position at normal volume (0 = no limit)
+    pub days_to_liquidate: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Portfolio {
+    weights: Vec<f64>,
+    notional: f64,
+    rebalance: RebalancePolicy,
+    liquidity: Option<Vec<Liquidity>>,
+    /// Value at or below which everything is sold (pre-notional units)
+    liquidation_floor: Option<f64>,
+}
+
+impl Portfolio {
+    /// Fractional weights (need not sum to 1: leverage and shorts are
+    /// allowed) on a total notional.
+    pub fn from_weights(weights: Vec<f64>, notional: f64) -> Result<Self, &'static str> {
+        if weights.is_empty() || weights.iter().any(|w| !w.is_finite()) || !notional.is_finite() {
+            return Err("Portfolio input invalid: weights and notional must be finite");
+        }
+        Ok(Portfolio {
+            weights,
+            notional,
+            rebalance: RebalancePolicy::default(),
+            liquidity: None,
+            liquidation_floor: None,
+        })
+    }
+
+    pub fn with_rebalance(mut self, policy: RebalancePolicy) -> Result<Self, &'static str> {
+        if !policy.is_buy_and_hold() && self.weights.iter().sum::<f64>() == 0.0 {
+            return Err("Portfolio input invalid: rebalancing needs weights with a non-zero sum");
+        }
+        let valid = policy.every != Some(0) && policy.band.is_none_or(|b| b > 0.0) && policy.cost_rate >= 0.0;
+        if !valid {
+            return Err("Portfolio input invalid: rebalance period and band must be positive, cost non-negative");
+        }
+        self.rebalance = policy;
+        Ok(self)
+    }
+
+    /// Spread, impact and exit speed per asset, charged on rebalancing
+    /// and forced-liquidation trades.
+    pub fn with_liquidity(mut self, liquidity: Vec<Liquidity>) -> Result<Self, &'static str> {
+        self.check(liquidity.len())?;
+        let valid = liquidity.iter().all(|l| {
+            [l.spread, l.impact, l.days_to_liquidate].iter().all(|x| x.is_finite() && *x >= 0.0)
+        });
+        if !valid {
+            return Err("Portfolio input invalid: spread, impact and days to liquidate must be non-negative");
+        }
+        self.liquidity = Some(liquidity);
+        Ok(self)
+    }
+
+    /// Sell everything once the portfolio has lost `max_loss` (0–1] of
+    /// its starting value. Each asset exits in equal tranches over its
+    /// days_to_liquidate (immediately without liquidity parameters), so
+    /// a crashing illiquid holding keeps falling while it is sold.
+    pub fn with_forced_liquidation(mut self, max_loss: f64) -> Result<Self, &'static str> {
+        let start: f64 = self.weights.iter().sum();
+        if !(max_loss > 0.0 && max_loss <= 1.0 && start > 0.0) {
+            return Err("Portfolio input invalid: liquidation loss must lie in (0, 1] on a long portfolio");
+        }
+        self.liquidation_floor = Some(start * (1.0 - max_loss));
+        Ok(self)
+    }
+
+    /// Currency amounts per asset; weights are amount / Σ amounts.
+    pub fn from_notionals(notionals: &[f64]) -> Result<Self, &'static str> {
+        let total: f64 = notionals.iter().sum();
+        if notionals.is_empty() || !total.is_finite() || total == 0.0 {
+            return Err("Portfolio input invalid: notionals must be finite with a non-zero total");
+        }
+        Portfolio::from_weights(notionals.iter().map(|x| x / total).collect(), total)
+    }
+
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    pub fn notional(&self) -> f64 {
+        self.notional
+    }
+
+    pub fn num_assets(&self) -> usize {
+        self.weights.len()
+    }
+
+    /// μ_P = w·μ  (annualized, arithmetic)
+    pub fn drift(&self, drift: &DVector<f64>) -> Result<f64, &'static str> {
+        self.check(drift.len())?;
+        Ok(self.weights.iter().zip(drift.iter()).map(|(w, m)| w * m).sum())
+    }
+
+    /// σ_P = √(wᵀΣw) = |Lᵀw|
+    pub fn vol(&self, cholesky_l: &DMatrix<f64>) -> Result<f64, &'static str> {
+        self.check(cholesky_l.nrows())?;
+        let w = DVector::from_column_slice(&self.weights);
+        Ok((cholesky_l.transpose() * w).norm())
+    }
+
+    /// notional·(V(t) − Σ w_a) per path and step ([path][step]) with
+    /// V(t) = Σ h_a·S_a(t) for holdings h (h = w until a rebalance).
+    pub fn pnl_paths(&self, paths: &[f32], config: &SimConfig) -> Result<Vec<f32>, &'static str> {
+        Ok(self.run(paths, config)?.0)
+    }
+
+    // ────────────────────────────────────────────────────────────
+    // Rebalancing: at a trigger, trade to h' = (w / Σw)·V / S and pay
+    // the trade cost out of V (cost taken on the pre-cost trade, then
+    // the holdings are scaled to the net value).
+    // Forced liquidation: once V ≤ floor, each asset sells a tranche of
+    // h·min(1, days per step / days_to_liquidate) units per step, the
+    // first at the trigger step, into non-earning cash; no rebalancing
+    // after that.
+    // Returns ([path][step] P&L, total cost per path, trigger step).
+    // ────────────────────────────────────────────────────────────
+    fn run(&self, paths: &[f32], config: &SimConfig) -> Result<RunOutput, &'static str> {
+        let (n, n_paths, rows) = (self.num_assets(), config.n_paths, config.steps + 1);
+        if paths.len() != n_paths * rows * n {
+            return Err("Portfolio input mismatch: paths must be n_paths × (steps + 1) × N");
+        }
+        let policy = self.rebalance;
+        let start: f64 = self.weights.iter().sum();
+        let target: Vec<f64> = self.weights.iter().map(|w| w / start).collect();
+        let days_per_step = TRADING_DAYS * config.horizon / config.steps as f64;
+        let price = |p: usize, t: usize, a: usize| {
+            paths[match config.layout {
+                OutputLayout::Interleaved => (p * rows + t) * n + a,
+                OutputLayout::Planar => (a * n_paths + p) * rows + t,
+            }] as f64
+        };
+
+        let mut pnl = vec![0.0_f32; n_paths * rows];
+        let mut costs = vec![0.0_f32; n_paths];
+        let mut liquidated = vec![None; n_paths];
+        let mut units = vec![0.0; n];
+        let mut tranche = vec![0.0; n];
+        let mut s = vec![0.0; n];
+        for p in 0..n_paths {
+            units.copy_from_slice(&self.weights);
+            let (mut cash, mut paid) = (0.0, 0.0);
+            for t in 0..rows {
+                for (a, x) in s.iter_mut().enumerate() {
+                    *x = price(p, t, a);
+                }
+                if liquidated[p].is_some() {
+                    paid += self.sell(&mut units, &tranche, &s, &mut cash);
+                }
+                let mut value = cash + units.iter().zip(&s).map(|(h, x)| h * x).sum::<f64>();
+                pnl[p * rows + t] = (self.notional * (value - start)) as f32;
+                if t == 0 || liquidated[p].is_some() {
+                    continue;
+                }
+                if self.liquidation_floor.is_some_and(|floor| value <= floor) {
+                    liquidated[p] = Some(t);
+                    for a in 0..n {
+                        tranche[a] = units[a] * self.exit_fraction(a, days_per_step);
+                    }
+                    paid += self.sell(&mut units, &tranche, &s, &mut cash);
+                    value = cash + units.iter().zip(&s).map(|(h, x)| h * x).sum::<f64>();
+                    pnl[p * rows + t] = (self.notional * (value - start)) as f32;
+                    continue;
+                }
+                if t == rows - 1 || policy.is_buy_and_hold() || value <= 0.0 {
+                    continue;
+                }
+                let scheduled = policy.every.is_some_and(|k| t.is_multiple_of(k));
+                let drifted = policy.band.is_some_and(|band| {
+                    (0..n).any(|a| (units[a] * s[a] / value - target[a]).abs() > band)
+                });
+                if scheduled || drifted {
+                    let cost: f64 = (0..n).map(|a| self.trade_cost(a, target[a] * value - units[a] * s[a])).sum();
+                    value -= cost;
+                    paid += cost;
+                    for a in 0..n {
+                        units[a] = target[a] * value / s[a];
+                    }
+                    pnl[p * rows + t] = (self.notional * (value - start)) as f32;
+                }
+            }
+            costs[p] = (self.notional * paid) as f32;
+        }
+        Ok((pnl, costs, liquidated))
+    }
+
+    /// Cost of trading `value` of asset a (sign ignored)
+    pub(crate) fn trade_cost(&self, a: usize, value: f64) -> f64 {
+        let x = value.abs();
+        let mut rate = self.rebalance.cost_rate;
+        if let Some(l) = self.liquidity.as_ref().map(|l| l[a]) {
+            rate += 0.5 * l.spread;
+            let capacity = self.weights[a].abs() / l.days_to_liquidate;
+            if capacity.is_finite() && capacity > 0.0 {
+                rate += l.impact * (x / capacity).sqrt();
+            }
+        }
+        rate * x
+    }
+
+    /// Share of the position asset a can exit per step
+    fn exit_fraction(&self, a: usize, days_per_step: f64) -> f64 {
+        match self.liquidity.as_ref().map(|l| l[a].days_to_liquidate) {
+            Some(days) if days > days_per_step => days_per_step / days,
+            _ => 1.0,
+        }
+    }
+
+    /// Sell up to one tranche of every asset into cash; returns the cost
+    fn sell(&self, units: &mut [f64], tranche: &[f64], s: &[f64], cash: &mut f64) -> f64 {
+        let mut paid = 0.0;
+        for a in 0..units.len() {
+            let q = if units[a].abs() <= tranche[a].abs() { units[a] } else { tranche[a] };
+            if q == 0.0 {
+                continue;
+            }
+            let cost = self.trade_cost(a, q * s[a]);
+            *cash += q * s[a] - cost;
+            units[a] -= q;
+            paid += cost;
+        }
+        paid
+    }
+
+    fn check(&self, n: usize) -> Result<(), &'static str> {
+        if n != self.num_assets() {
+            return Err("Portfolio input mismatch: one weight per asset is required");
+        }
+        Ok(())
+    }
+}
+
+/// Portfolio-level view of one simulation run.
+#[derive(Clone, Debug)]
+pub struct PortfolioPaths {
+    pub drift: f64,
+    pub vol: f64,
+    /// [path][step] P&L in currency, net of rebalancing costs
+    pub pnl: Vec<f32>,
+    /// Total trading cost per path (rebalancing and liquidation), in currency
+    pub costs: Vec<f32>,
+    /// Step at which forced liquidation started, per path
+    pub liquidated: Vec<Option<usize>>,
+}
+
+impl PortfolioPaths {
+    /// Terminal P&L per path
+    pub fn terminal(&self, steps: usize) -> Vec<f64> {
+        self.pnl.chunks_exact(steps + 1).map(|row| row[steps] as f64).collect()
+    }
+}
+
+pub fn simulate_portfolio(
+    drift: &DVector<f64>,
+    vol: &DVector<f64>,
+    cholesky_l: &DMatrix<f64>,
+    portfolio: &Portfolio,
+    config: &SimConfig,
+) -> Result<PortfolioPaths, &'static str> {
+    let paths = simulate::simulate_paths(drift, vol, cholesky_l, config)?;
+    let (pnl, costs, liquidated) = portfolio.run(&paths, config)?;
+    Ok(PortfolioPaths {
+        drift: portfolio.drift(drift)?,
+        vol: portfolio.vol(cholesky_l)?,
+        pnl,
+        costs,
+        liquidated,
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn market() -> (DVector<f64>, DVector<f64>, DMatrix<f64>) {
+        let drift = DVector::from_vec(vec![0.08, 0.03]);
+        let vol = DVector::from_vec(vec![0.2, 0.05]);
+        let r = DMatrix::from_row_slice(2, 2, &[1.0, 0.5, 0.5, 1.0]);
+        let cov = crate::math::rebuild_covariance(&vol, &r);
+        (drift, vol, crate::math::cholesky_decompose(&cov).unwrap())
+    }
+
+    #[test]
+    fn test_portfolio_drift_and_vol() {
+        let (drift, _, l) = market();
+        let p = Portfolio::from_weights(vec![0.6, 0.4], 1.0).unwrap();
+        assert_relative_eq!(p.drift(&drift).unwrap(), 0.6 * 0.08 + 0.4 * 0.03, epsilon = 1e-12);
+        // wᵀΣw = 0.36·0.04 + 0.16·0.0025 + 2·0.24·0.5·0.2·0.05
+        let var: f64 = 0.36 * 0.04 + 0.16 * 0.0025 + 2.0 * 0.24 * 0.5 * 0.2 * 0.05;
+        assert_relative_eq!(p.vol(&l).unwrap(), var.sqrt(), epsilon = 1e-12);
+        assert!(p.vol(&DMatrix::identity(3, 3)).is_err());
+    }
+
+    #[test]
+    fn test_notionals_scale_pnl() {
+        let (drift, vol, l) = market();
+        let config = SimConfig::new(1.0, 12, 4000, 6);
+        let p = Portfolio::from_notionals(&[600.0, 400.0]).unwrap();
+        assert_eq!(p.weights(), &[0.6, 0.4]);
+        let out = simulate_portfolio(&drift, &vol, &l, &p, &config).unwrap();
+        assert_eq!(out.pnl.len(), 4000 * 13);
+        assert!(out.pnl.chunks_exact(13).all(|row| row[0] == 0.0));
+
+        // E[P&L_T] = N·Σ w_a·(e^{μ_a T} − 1)
+        let mean = out.terminal(12).iter().sum::<f64>() / 4000.0;
+        let expected = 1000.0 * (0.6 * (0.08_f64.exp() - 1.0) + 0.4 * (0.03_f64.exp() - 1.0));
+        assert_relative_eq!(mean, expected, max_relative = 0.1);
+
+        // Layout does not change the aggregate
+        let planar = simulate_portfolio(&drift, &vol, &l, &p, &config.with_layout(OutputLayout::Planar)).unwrap();
+        assert_eq!(planar.pnl, out.pnl);
+    }
+
+    #[test]
+    fn test_rebalancing_restores_target_mix() {
+        // One asset up 20%, the other down 20%, then both back: buy-and-hold
+        // ends flat while rebalancing at t = 1 harvests the reversal
+        let paths = [1.0, 1.0, 1.2, 0.8, 1.0, 1.0];
+        let config = SimConfig::new(1.0, 2, 1, 0);
+        let hold = Portfolio::from_weights(vec![0.5, 0.5], 1.0).unwrap();
+        let flat = |pnl: Vec<f32>| pnl.iter().all(|x| x.abs() < 1e-6);
+        assert!(flat(hold.pnl_paths(&paths, &config).unwrap()));
+
+        let policy = RebalancePolicy { every: Some(1), ..Default::default() };
+        let rebal = hold.clone().with_rebalance(policy).unwrap();
+        let pnl = rebal.pnl_paths(&paths, &config).unwrap();
+        // V(1) = 1; units 0.5/1.2 and 0.5/0.8 → V(2) = 0.5/1.2 + 0.5/0.8
+        assert_relative_eq!(pnl[2] as f64, 0.5 / 1.2 + 0.5 / 0.8 - 1.0, epsilon = 1e-6);
+
+        // A 25% band is never breached (0.6 vs 0.5 target)
+        let banded = hold.clone().with_rebalance(RebalancePolicy { band: Some(0.25), ..Default::default() }).unwrap();
+        assert!(flat(banded.pnl_paths(&paths, &config).unwrap()));
+    }
+
+    #[test]
+    fn test_impact_grows_with_trade_size() {
+        let liq = Liquidity { spread: 0.002, impact: 0.01, days_to_liquidate: 5.0 };
+        let p = Portfolio::from_weights(vec![0.6, 0.4], 1.0).unwrap().with_liquidity(vec![liq; 2]).unwrap();
+        // Half-spread alone on a tiny trade, impact dominating a large one
+        assert_relative_eq!(p.trade_cost(0, 1e-9) / 1e-9, 0.001, epsilon = 1e-5);
+        assert!(p.trade_cost(0, 0.2) > 2.0 * p.trade_cost(0, 0.1));
+        assert!(p.clone().with_liquidity(vec![liq]).is_err());
+    }
+
+    #[test]
+    fn test_forced_liquidation_sells_into_the_crash() {
+        // Single asset falling 1 → 0.7 → 0.5 → 0.4, one trading day per step
+        let paths = [1.0, 0.7, 0.5, 0.4];
+        let config = SimConfig::new(3.0 / 252.0, 3, 1, 0);
+        let base = Portfolio::from_weights(vec![1.0], 1.0).unwrap().with_forced_liquidation(0.2).unwrap();
+        let quick = Liquidity { spread: 0.02, impact: 0.0, days_to_liquidate: 0.0 };
+
+        // Instant exit at t = 1 after paying the half-spread, then flat
+        let instant = base.clone().with_liquidity(vec![quick]).unwrap();
+        let (pnl, costs, liquidated) = instant.run(&paths, &config).unwrap();
+        assert_eq!(liquidated, vec![Some(1)]);
+        assert_relative_eq!(pnl[3] as f64, 0.7 * 0.99 - 1.0, epsilon = 1e-6);
+        assert_relative_eq!(costs[0] as f64, 0.007, epsilon = 1e-6);
+
+        // Two days to exit: half at 0.7, half at 0.5
+        let slow = base.with_liquidity(vec![Liquidity { days_to_liquidate: 2.0, ..quick }]).unwrap();
+        let pnl = slow.pnl_paths(&paths, &config).unwrap();
+        assert_relative_eq!(pnl[3] as f64, 0.99 * (0.35 + 0.25) - 1.0, epsilon = 1e-6);
+        assert!(pnl[3] < pnl[2] + 1e-6 && pnl[3] < (0.7 * 0.99 - 1.0));
+    }
+
+    #[test]
+    fn test_rebalancing_costs_reduce_pnl() {
+        let (drift, vol, l) = market();
+        let config = SimConfig::new(1.0, 12, 500, 2);
+        let base = Portfolio::from_weights(vec![0.6, 0.4], 1.0).unwrap();
+        let free = base.clone().with_rebalance(RebalancePolicy { every: Some(3), ..Default::default() }).unwrap();
+        let costly = base.with_rebalance(RebalancePolicy { every: Some(3), band: None, cost_rate: 0.01 }).unwrap();
+        let a = simulate_portfolio(&drift, &vol, &l, &free, &config).unwrap();
+        let b = simulate_portfolio(&drift, &vol, &l, &costly, &config).unwrap();
+        assert!(a.costs.iter().all(|&c| c == 0.0));
+        for p in 0..500 {
+            assert!(b.costs[p] > 0.0);
+            assert!(b.pnl[p * 13 + 12] < a.pnl[p * 13 + 12]);
+        }
+        assert!(Portfolio::from_weights(vec![1.0, -1.0], 1.0).unwrap().with_rebalance(RebalancePolicy {
+            every: Some(1),
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
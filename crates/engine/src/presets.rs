@@ -0,0 +1,181 @@
+// ════════════════════════════════════════════════════════════════
+// Built-in stress scenarios
+//
+// Each preset gives a (Δμ, vol multiplier) pair per asset class plus a
+// market-wide correlation skew and jump process. `severity` scales the
+// whole scenario about "no shock": Δμ·s, 1 + (m − 1)·s, skew·s
+// (capped at 1) and λ·s, so s = 1 is the preset as published and
+// s = 0.5 a half-strength version.
+// ════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssetClass {
+    Equities,
+    Bonds,
+    Commodities,
+    RealEstate,
+    Cash,
+}
+
+impl AssetClass {
+    /// Parse the frontend's ids: equities, bonds, commodities, real_estate, cash
+    pub fn from_name(name: &str) -> Result<Self, &'static str> {
+        match name.to_ascii_lowercase().as_str() {
+            "equities" | "equity" => Ok(AssetClass::Equities),
+            "bonds" | "bond" | "rates" => Ok(AssetClass::Bonds),
+            "commodities" | "commodity" => Ok(AssetClass::Commodities),
+            "real_estate" | "realestate" => Ok(AssetClass::RealEstate),
+            "cash" => Ok(AssetClass::Cash),
+            _ => Err("Preset input invalid: expected equities, bonds, commodities, real_estate or cash"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    BlackSwan,
+    Stagflation,
+    RateShock,
+    LiquidityCrunch,
+    MeltUp,
+}
+
+/// Phase A inputs for one scenario, one entry per asset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioShock {
+    pub delta_drift: Vec<f64>,
+    pub vol_multiplier: Vec<f64>,
+    pub correlation_skew: f64,
+    pub jump_lambda: f64,
+    pub jump_mean: f64,
+    pub jump_vol: f64,
+}
+
+/// (Δμ, vol multiplier) per class, in AssetClass order
+type ClassTable = [(f64, f64); 5];
+
+impl Preset {
+    pub const ALL: [Preset; 5] =
+        [Preset::BlackSwan, Preset::Stagflation, Preset::RateShock, Preset::LiquidityCrunch, Preset::MeltUp];
+
+    /// Parse an id (black_swan, stagflation, rate_shock, liquidity_crunch,
+    /// melt_up) or display name
+    pub fn from_name(name: &str) -> Result<Self, &'static str> {
+        let key: String = name.to_ascii_lowercase().chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+        match key.as_str() {
+            "blackswan" => Ok(Preset::BlackSwan),
+            "stagflation" => Ok(Preset::Stagflation),
+            "rateshock" | "ratehike" => Ok(Preset::RateShock),
+            "liquiditycrunch" => Ok(Preset::LiquidityCrunch),
+            "meltup" => Ok(Preset::MeltUp),
+            _ => Err("Preset input invalid: unknown scenario name"),
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Preset::BlackSwan => "black_swan",
+            Preset::Stagflation => "stagflation",
+            Preset::RateShock => "rate_shock",
+            Preset::LiquidityCrunch => "liquidity_crunch",
+            Preset::MeltUp => "melt_up",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::BlackSwan => "Black Swan",
+            Preset::Stagflation => "Stagflation",
+            Preset::RateShock => "Rate Shock",
+            Preset::LiquidityCrunch => "Liquidity Crunch",
+            Preset::MeltUp => "Melt-Up",
+        }
+    }
+
+    /// (class table, skew, λ, μ_J, σ_J)
+    fn parameters(&self) -> (ClassTable, f64, f64, f64, f64) {
+        match self {
+            Preset::BlackSwan => (
+                [(-0.15, 3.0), (0.05, 1.8), (-0.08, 2.5), (-0.12, 2.8), (0.01, 1.0)],
+                0.85, 4.0, -0.12, 0.08,
+            ),
+            Preset::Stagflation => (
+                [(-0.06, 1.8), (-0.02, 1.4), (0.04, 2.0), (-0.04, 1.6), (0.005, 1.0)],
+                0.55, 1.5, -0.05, 0.06,
+            ),
+            Preset::RateShock => (
+                [(-0.02, 1.3), (0.01, 1.1), (-0.01, 1.2), (-0.03, 1.4), (0.005, 1.0)],
+                0.3, 0.5, -0.02, 0.03,
+            ),
+            // Forced selling: everything but cash falls together, illiquid
+            // real estate hardest
+            Preset::LiquidityCrunch => (
+                [(-0.10, 2.2), (-0.03, 1.6), (-0.06, 1.9), (-0.14, 2.6), (0.0, 1.0)],
+                0.7, 2.5, -0.08, 0.06,
+            ),
+            // Risk-on rally: positive jumps, mild vol pickup, loose correlation
+            Preset::MeltUp => (
+                [(0.12, 1.3), (-0.01, 1.0), (0.04, 1.2), (0.06, 1.1), (-0.005, 1.0)],
+                0.2, 0.3, 0.03, 0.04,
+            ),
+        }
+    }
+
+    pub fn shock(&self, classes: &[AssetClass], severity: f64) -> Result<ScenarioShock, &'static str> {
+        if !(severity.is_finite() && severity >= 0.0) {
+            return Err("Preset input invalid: severity must be a non-negative number");
+        }
+        let (table, skew, lambda, mean, vol) = self.parameters();
+        let row = |c: AssetClass| table[c as usize];
+        Ok(ScenarioShock {
+            delta_drift: classes.iter().map(|&c| row(c).0 * severity).collect(),
+            vol_multiplier: classes.iter().map(|&c| 1.0 + (row(c).1 - 1.0) * severity).collect(),
+            correlation_skew: (skew * severity).min(1.0),
+            jump_lambda: lambda * severity,
+            jump_mean: mean,
+            jump_vol: vol,
+        })
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_black_swan_matches_frontend_defaults() {
+        let classes = [AssetClass::Equities, AssetClass::Bonds, AssetClass::Commodities];
+        let s = Preset::BlackSwan.shock(&classes, 1.0).unwrap();
+        assert_eq!(s.delta_drift, vec![-0.15, 0.05, -0.08]);
+        assert_eq!(s.vol_multiplier, vec![3.0, 1.8, 2.5]);
+        assert_eq!((s.correlation_skew, s.jump_lambda, s.jump_mean, s.jump_vol), (0.85, 4.0, -0.12, 0.08));
+    }
+
+    #[test]
+    fn test_severity_scales_about_no_shock() {
+        let classes = [AssetClass::RealEstate, AssetClass::Cash];
+        let zero = Preset::LiquidityCrunch.shock(&classes, 0.0).unwrap();
+        assert_eq!(zero.delta_drift, vec![0.0, 0.0]);
+        assert_eq!(zero.vol_multiplier, vec![1.0, 1.0]);
+        assert_eq!((zero.correlation_skew, zero.jump_lambda), (0.0, 0.0));
+
+        let half = Preset::LiquidityCrunch.shock(&classes, 0.5).unwrap();
+        assert_relative_eq!(half.vol_multiplier[0], 1.8);
+        assert_eq!(Preset::BlackSwan.shock(&classes, 2.0).unwrap().correlation_skew, 1.0);
+        assert!(Preset::MeltUp.shock(&classes, -1.0).is_err());
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        for p in Preset::ALL {
+            assert_eq!(Preset::from_name(p.id()), Ok(p));
+            assert_eq!(Preset::from_name(p.name()), Ok(p));
+        }
+        assert_eq!(AssetClass::from_name("real_estate"), Ok(AssetClass::RealEstate));
+        assert!(Preset::from_name("zombie_apocalypse").is_err());
+    }
+}
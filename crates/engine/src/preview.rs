@@ -0,0 +1,146 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::error::EngineError;
+use crate::math::{self, Scalar};
+use crate::{timed, Timings};
+
+// ════════════════════════════════════════════════════════════════
+// Phase A in any math::Scalar — the single-precision preview path
+//
+// The same six steps as shock_market, so that f32 inputs go through
+// in f32 with no widening copies. Floors and tolerances are the
+// scalar's (see math::Scalar), and the Higham pre-check sits 100× above
+// the PSD floor as REPAIR_SKIP_MIN_EIGENVALUE does for f64. The engine
+// keeps shock_market for anything but previews.
+// ════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug)]
+pub struct Market<T: Scalar> {
+    pub drift: DVector<T>,
+    pub vol: DVector<T>,
+    pub cholesky_l: DMatrix<T>,
+    pub ridge_jitter: T,
+    /// Correlation after blend and repair
+    pub correlation: DMatrix<T>,
+    pub higham_iterations: usize,
+    pub higham_converged: bool,
+    pub min_eigenvalue_before: T,
+    pub min_eigenvalue_after: T,
+    /// ‖R_repaired − R_blended‖_F
+    pub distance: T,
+    pub timings: Timings,
+}
+
+pub fn shock_market<T: Scalar>(
+    base_drift: &DVector<T>,
+    base_vol: &DVector<T>,
+    base_corr: &DMatrix<T>,
+    delta_drift: &DVector<T>,
+    vol_multiplier: &DVector<T>,
+    correlation_skew: T,
+    repair: bool,
+) -> Result<Market<T>, EngineError> {
+    let mut timings = Timings::default();
+    let (drift, vol, blended) = timed(&mut timings.blend_ms, || {
+        (
+            math::adjust_drift(base_drift, delta_drift),
+            math::adjust_vol(base_vol, vol_multiplier),
+            math::blend_correlation(base_corr, correlation_skew),
+        )
+    });
+
+    let min_eigenvalue_before = min_eigenvalue(&blended);
+    let (correlation, higham_iterations, higham_converged) = timed(&mut timings.repair_ms, || {
+        if !repair {
+            (blended.clone(), 0, true)
+        } else if math::is_pd(&blended, T::psd_floor() * nalgebra::convert(100.0)) {
+            let mut r = (&blended + blended.transpose()) * nalgebra::convert::<f64, T>(0.5);
+            r.fill_diagonal(T::one());
+            (r, 0, true)
+        } else {
+            let mut projection = math::HighamProjection::new(&blended, None);
+            projection.step(usize::MAX);
+            (projection.result(), projection.iterations(), projection.converged())
+        }
+    });
+    let min_eigenvalue_after =
+        if higham_iterations > 0 { min_eigenvalue(&correlation) } else { min_eigenvalue_before };
+
+    let cov = timed(&mut timings.covariance_ms, || math::rebuild_covariance(&vol, &correlation));
+    let (cholesky_l, ridge_jitter) = timed(&mut timings.cholesky_ms, || math::cholesky_with_jitter(&cov))
+        .map_err(|_| EngineError::NotPositiveDefinite {
+            min_eigenvalue: min_eigenvalue(&cov).to_subset().unwrap_or(f64::NAN),
+        })?;
+    Ok(Market {
+        distance: (&correlation - &blended).norm(),
+        drift,
+        vol,
+        cholesky_l,
+        ridge_jitter,
+        correlation,
+        higham_iterations,
+        higham_converged,
+        min_eigenvalue_before,
+        min_eigenvalue_after,
+        timings,
+    })
+}
+
+fn min_eigenvalue<T: Scalar>(m: &DMatrix<T>) -> T {
+    let sym = (m + m.transpose()) * nalgebra::convert::<f64, T>(0.5);
+    sym.symmetric_eigenvalues().min()
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f32_pipeline_tracks_f64() {
+        // An indefinite base (needs the Higham repair) and a PD one
+        let indefinite = [1.0, 0.9, -0.9, 0.9, 1.0, 0.9, -0.9, 0.9, 1.0];
+        let pd = [1.0, 0.5, 0.2, 0.5, 1.0, 0.3, 0.2, 0.3, 1.0];
+        let run = |r: [f64; 9], skew: f64| {
+            let m64 = crate::shock_market(
+                &DVector::from_vec(vec![0.05, 0.07, 0.02]),
+                &DVector::from_vec(vec![0.2, 0.3, 0.25]),
+                &DMatrix::from_row_slice(3, 3, &r),
+                &DVector::from_vec(vec![-0.1, 0.0, 0.03]),
+                &DVector::from_vec(vec![1.5, 1.0, 2.0]),
+                skew,
+                true,
+            )
+            .unwrap();
+            let m32 = shock_market::<f32>(
+                &DVector::from_vec(vec![0.05, 0.07, 0.02]),
+                &DVector::from_vec(vec![0.2, 0.3, 0.25]),
+                &DMatrix::from_row_slice(3, 3, &r.map(|x| x as f32)),
+                &DVector::from_vec(vec![-0.1, 0.0, 0.03]),
+                &DVector::from_vec(vec![1.5, 1.0, 2.0]),
+                skew as f32,
+                true,
+            )
+            .unwrap();
+            (m64, m32)
+        };
+        for (r, skew, repaired) in [(indefinite, 0.1, true), (pd, 0.5, false)] {
+            let (m64, m32) = run(r, skew);
+            assert_eq!(m32.higham_iterations > 0, repaired);
+            assert_eq!(m64.repair.iterations > 0, repaired);
+            assert!(m32.higham_converged);
+            let gap = |a: &DMatrix<f64>, b: &DMatrix<f32>| (a - b.map(|x| x as f64)).abs().max();
+            assert!(gap(&m64.correlation, &m32.correlation) < 1e-3);
+            // A repaired R sits on the PSD floor, which is precision-scaled,
+            // so compare Σ = L·Lᵀ rather than L's near-zero last pivot
+            let cov64 = &m64.cholesky_l * m64.cholesky_l.transpose();
+            let cov32 = &m32.cholesky_l * m32.cholesky_l.transpose();
+            assert!(gap(&cov64, &cov32) < 1e-4);
+            if !repaired {
+                assert!(gap(&m64.cholesky_l, &m32.cholesky_l) < 1e-4);
+            }
+        }
+    }
+}
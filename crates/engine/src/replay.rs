@@ -0,0 +1,308 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::simulate::{Pcg32, SimConfig};
+
+// ════════════════════════════════════════════════════════════════
+// Historical replay — actual return history in place of random shocks
+//
+// A T × N matrix of per-period simple returns is compounded into
+// paths with the simulator's shape and S₀ = 1, so every portfolio and
+// risk function that takes (paths, SimConfig) applies unchanged. With
+// a window shorter than T each start date gives one overlapping path
+// (T − window + 1 of them); window = T replays the single history.
+// ════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Debug)]
+pub struct ReplayPaths {
+    /// [path][step][asset]
+    pub paths: Vec<f32>,
+    /// Shape of `paths` (n_paths, steps = window, horizon in years)
+    pub config: SimConfig,
+}
+
+/// `returns` is T × N row-major simple returns (> −1). If given,
+/// `vol_multiplier` stretches each asset's log returns about their
+/// sample mean — r' = r̄ + m·(r − r̄) — so the drift is kept and the
+/// realized vol scales by m.
+pub fn replay_paths(
+    returns: &[f64],
+    n: usize,
+    window: usize,
+    periods_per_year: f64,
+    vol_multiplier: Option<&DVector<f64>>,
+) -> Result<ReplayPaths, &'static str> {
+    if n == 0 || returns.is_empty() || !returns.len().is_multiple_of(n) {
+        return Err("Replay input mismatch: returns must be T × N");
+    }
+    let t = returns.len() / n;
+    if window == 0 || window > t {
+        return Err("Replay input invalid: window must be between 1 and T");
+    }
+    if !(periods_per_year.is_finite() && periods_per_year > 0.0) {
+        return Err("Replay input invalid: periods per year must be positive");
+    }
+    if returns.iter().any(|&r| !(r.is_finite() && r > -1.0)) {
+        return Err("Replay input invalid: returns must be finite and above −100%");
+    }
+    if vol_multiplier.is_some_and(|m| m.len() != n || m.iter().any(|x| !x.is_finite() || *x < 0.0)) {
+        return Err("Replay input invalid: one non-negative vol multiplier per asset");
+    }
+
+    let mut log_r: Vec<f64> = returns.iter().map(|r| r.ln_1p()).collect();
+    if let Some(m) = vol_multiplier {
+        for a in 0..n {
+            let mean = (0..t).map(|k| log_r[k * n + a]).sum::<f64>() / t as f64;
+            for k in 0..t {
+                let x = &mut log_r[k * n + a];
+                *x = mean + m[a] * (*x - mean);
+            }
+        }
+    }
+
+    let n_paths = t - window + 1;
+    let rows = window + 1;
+    let mut paths = vec![0.0_f32; n_paths * rows * n];
+    for (start, path) in paths.chunks_exact_mut(rows * n).enumerate() {
+        let mut log_s = vec![0.0; n];
+        path[..n].iter_mut().for_each(|x| *x = 1.0);
+        for step in 1..rows {
+            for a in 0..n {
+                log_s[a] += log_r[(start + step - 1) * n + a];
+                path[step * n + a] = log_s[a].exp() as f32;
+            }
+        }
+    }
+    let config = SimConfig::new(window as f64 / periods_per_year, window, n_paths, 0);
+    Ok(ReplayPaths { paths, config })
+}
+
+// ────────────────────────────────────────────────────────────────
+// Block bootstrap
+// Each path strings together blocks of `block` consecutive historical
+// periods, one period per step, drawing block starts uniformly over
+// the T − block + 1 windows from Pcg32::for_path(seed, path). Within a
+// block the autocorrelation survives, and every step keeps one real
+// row's cross-section and its tails.
+// ────────────────────────────────────────────────────────────────
+
+/// How the resampled log returns x are adjusted to a shocked market.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BootstrapScaling {
+    None,
+    /// x' = x̄ + m·(x − x̄), as in replay_paths
+    VolMultiplier(DVector<f64>),
+    /// Whitened by the sample Cholesky L̂ and recolored:
+    /// x' = (μ − σ²/2)·dt + √dt·L·L̂⁻¹·(x − x̄), with dt one period and
+    /// σ_a = ‖row a of L‖, so the shocked drift, vol and correlation
+    /// hold while the standardized shocks stay historical.
+    Market { drift: DVector<f64>, cholesky_l: DMatrix<f64> },
+}
+
+impl BootstrapScaling {
+    /// Every variant's id, in declaration order
+    pub const IDS: [&'static str; 3] = ["none", "vol_multiplier", "market"];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            BootstrapScaling::None => "none",
+            BootstrapScaling::VolMultiplier(_) => "vol_multiplier",
+            BootstrapScaling::Market { .. } => "market",
+        }
+    }
+}
+
+/// `returns` is T × N row-major simple returns (> −1); the paths span
+/// `steps` periods (horizon = steps / periods_per_year).
+#[allow(clippy::too_many_arguments)]
+pub fn bootstrap_paths(
+    returns: &[f64],
+    n: usize,
+    block: usize,
+    periods_per_year: f64,
+    steps: usize,
+    n_paths: usize,
+    seed: u64,
+    scaling: &BootstrapScaling,
+) -> Result<ReplayPaths, &'static str> {
+    if n == 0 || returns.is_empty() || !returns.len().is_multiple_of(n) {
+        return Err("Bootstrap input mismatch: returns must be T × N");
+    }
+    let t = returns.len() / n;
+    if block == 0 || block > t {
+        return Err("Bootstrap input invalid: block length must be between 1 and T");
+    }
+    if steps == 0 || n_paths == 0 {
+        return Err("Bootstrap input invalid: steps and n_paths must be positive");
+    }
+    if !(periods_per_year.is_finite() && periods_per_year > 0.0) {
+        return Err("Bootstrap input invalid: periods per year must be positive");
+    }
+    if returns.iter().any(|&r| !(r.is_finite() && r > -1.0)) {
+        return Err("Bootstrap input invalid: returns must be finite and above −100%");
+    }
+
+    let mut x = DMatrix::from_row_iterator(t, n, returns.iter().map(|r| r.ln_1p()));
+    let mean = x.row_mean();
+    match scaling {
+        BootstrapScaling::None => {}
+        BootstrapScaling::VolMultiplier(m) => {
+            if m.len() != n || m.iter().any(|x| !x.is_finite() || *x < 0.0) {
+                return Err("Bootstrap input invalid: one non-negative vol multiplier per asset");
+            }
+            for mut row in x.row_iter_mut() {
+                for a in 0..n {
+                    row[a] = mean[a] + m[a] * (row[a] - mean[a]);
+                }
+            }
+        }
+        BootstrapScaling::Market { drift, cholesky_l } => {
+            if drift.len() != n || cholesky_l.shape() != (n, n) {
+                return Err("Bootstrap input mismatch: market needs N drifts and an N×N Cholesky factor");
+            }
+            if t <= n {
+                return Err("Bootstrap input invalid: recoloring needs more periods than assets");
+            }
+            let mut centered = x.clone();
+            for mut row in centered.row_iter_mut() {
+                row -= &mean;
+            }
+            let sample_cov = centered.transpose() * &centered / (t - 1) as f64;
+            let sample_l = sample_cov
+                .cholesky()
+                .ok_or("Bootstrap input invalid: sample covariance is singular, cannot whiten")?
+                .l();
+            let dt = 1.0 / periods_per_year;
+            // Row-wise x' = c + √dt·ε·(L·L̂⁻¹)ᵀ, with (L·L̂⁻¹)ᵀ = L̂⁻ᵀ·Lᵀ
+            let recolor_t = sample_l
+                .transpose()
+                .solve_upper_triangular(&cholesky_l.transpose())
+                .ok_or("Bootstrap input invalid: sample covariance is singular, cannot whiten")?;
+            let carry = DVector::from_fn(n, |a, _| (drift[a] - 0.5 * cholesky_l.row(a).norm_squared()) * dt);
+            x = centered * recolor_t * dt.sqrt();
+            for mut row in x.row_iter_mut() {
+                row += carry.transpose();
+            }
+        }
+    }
+
+    let windows = (t - block + 1) as f64;
+    let rows = steps + 1;
+    let mut paths = vec![0.0_f32; n_paths * rows * n];
+    for (p, path) in paths.chunks_exact_mut(rows * n).enumerate() {
+        let mut rng = Pcg32::for_path(seed, p);
+        let mut log_s = vec![0.0; n];
+        path[..n].iter_mut().for_each(|v| *v = 1.0);
+        let mut row = 0;
+        for step in 1..rows {
+            if (step - 1) % block == 0 {
+                row = ((rng.next_f64() * windows) as usize).min(t - block);
+            }
+            for a in 0..n {
+                log_s[a] += x[(row, a)];
+                path[step * n + a] = log_s[a].exp() as f32;
+            }
+            row += 1;
+        }
+    }
+    let config = SimConfig::new(steps as f64 / periods_per_year, steps, n_paths, seed);
+    Ok(ReplayPaths { paths, config })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portfolio::Portfolio;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_bootstrap_scaling_ids_cover_every_variant() {
+        let scalings = [
+            BootstrapScaling::None,
+            BootstrapScaling::VolMultiplier(DVector::zeros(1)),
+            BootstrapScaling::Market { drift: DVector::zeros(1), cholesky_l: DMatrix::zeros(1, 1) },
+        ];
+        assert_eq!(scalings.iter().map(|s| s.id()).collect::<Vec<_>>(), BootstrapScaling::IDS);
+    }
+
+    #[test]
+    fn test_full_window_compounds_history() {
+        // Two assets, three periods
+        let returns = [0.1, -0.05, -0.2, 0.02, 0.05, 0.0];
+        let r = replay_paths(&returns, 2, 3, 12.0, None).unwrap();
+        assert_eq!(r.config.n_paths, 1);
+        assert_relative_eq!(r.config.horizon, 0.25);
+        assert_eq!(&r.paths[..2], &[1.0, 1.0]);
+        assert_relative_eq!(r.paths[6] as f64, 1.1 * 0.8 * 1.05, max_relative = 1e-6);
+        assert_relative_eq!(r.paths[7] as f64, 0.95 * 1.02, max_relative = 1e-6);
+
+        // Feeds the portfolio machinery as-is
+        let p = Portfolio::from_weights(vec![0.5, 0.5], 100.0).unwrap();
+        let pnl = p.pnl_paths(&r.paths, &r.config).unwrap();
+        assert_relative_eq!(pnl[3] as f64, 50.0 * (1.1 * 0.8 * 1.05 + 0.95 * 1.02) - 100.0, max_relative = 1e-5);
+    }
+
+    #[test]
+    fn test_rolling_windows_start_each_period() {
+        let returns = [0.01, 0.02, 0.03, 0.04];
+        let r = replay_paths(&returns, 1, 2, 252.0, None).unwrap();
+        assert_eq!(r.config.n_paths, 3);
+        // Path 1 starts at period 1: 1.02 then 1.02·1.03
+        assert_relative_eq!(r.paths[3 + 2] as f64, 1.02 * 1.03, max_relative = 1e-6);
+        assert!(replay_paths(&returns, 1, 5, 252.0, None).is_err());
+    }
+
+    #[test]
+    fn test_bootstrap_resamples_whole_blocks() {
+        // Returns encode (row, asset) so every step can be traced back
+        let t = 50;
+        let returns: Vec<f64> = (0..t).flat_map(|i| [i as f64 * 1e-3, -(i as f64) * 1e-3]).collect();
+        let b = bootstrap_paths(&returns, 2, 5, 252.0, 20, 30, 9, &BootstrapScaling::None).unwrap();
+        assert_eq!(b.paths.len(), 30 * 21 * 2);
+        assert_relative_eq!(b.config.horizon, 20.0 / 252.0);
+        for path in b.paths.chunks_exact(21 * 2) {
+            let rows: Vec<usize> = (1..21)
+                .map(|s| ((path[2 * s] as f64 / path[2 * (s - 1)] as f64).ln().exp_m1() * 1e3).round() as usize)
+                .collect();
+            for block in rows.chunks(5) {
+                assert!(block.windows(2).all(|w| w[1] == w[0] + 1));
+            }
+        }
+        let again = bootstrap_paths(&returns, 2, 5, 252.0, 20, 30, 9, &BootstrapScaling::None).unwrap();
+        assert_eq!(b.paths, again.paths);
+        assert!(bootstrap_paths(&returns, 2, 51, 252.0, 20, 30, 9, &BootstrapScaling::None).is_err());
+    }
+
+    #[test]
+    fn test_bootstrap_recolors_to_shocked_market() {
+        use crate::simulate::NormalSampler;
+
+        // Fat-tailed, uncorrelated history; target ρ = 0.8, σ = (0.3, 0.1)
+        let mut normals = NormalSampler::new(Pcg32::new(5, 0));
+        let returns: Vec<f64> = (0..2000).map(|_| (0.01 * normals.sample().powi(3)).exp_m1()).collect();
+        let l = DMatrix::from_row_slice(2, 2, &[0.3, 0.0, 0.08, 0.06]);
+        let market = BootstrapScaling::Market { drift: DVector::from_vec(vec![0.05, 0.02]), cholesky_l: l.clone() };
+        let b = bootstrap_paths(&returns, 2, 1, 252.0, 1, 20_000, 1, &market).unwrap();
+        let x: Vec<[f64; 2]> = b.paths.chunks_exact(4).map(|p| [(p[2] as f64).ln(), (p[3] as f64).ln()]).collect();
+        let m = x.len() as f64;
+        let mean = [0, 1].map(|a| x.iter().map(|r| r[a]).sum::<f64>() / m);
+        let cov = |a: usize, c: usize| x.iter().map(|r| (r[a] - mean[a]) * (r[c] - mean[c])).sum::<f64>() / m;
+        assert_relative_eq!((cov(0, 0) * 252.0).sqrt(), 0.3, max_relative = 0.05);
+        assert_relative_eq!((cov(1, 1) * 252.0).sqrt(), 0.1, max_relative = 0.05);
+        assert_relative_eq!(cov(0, 1) / (cov(0, 0) * cov(1, 1)).sqrt(), 0.8, epsilon = 0.03);
+        assert_relative_eq!(mean[0] * 252.0, 0.05 - 0.045, epsilon = 0.02);
+    }
+
+    #[test]
+    fn test_vol_multiplier_keeps_drift() {
+        let returns = [0.05, -0.03, 0.02, -0.04];
+        let m = DVector::from_vec(vec![2.0]);
+        let base = replay_paths(&returns, 1, 4, 4.0, None).unwrap();
+        let scaled = replay_paths(&returns, 1, 4, 4.0, Some(&m)).unwrap();
+        // Same terminal value (Σ log r unchanged), wider path in between
+        assert_relative_eq!(scaled.paths[4], base.paths[4], max_relative = 1e-6);
+        assert!(scaled.paths[1] > base.paths[1]);
+    }
+}
@@ -0,0 +1,582 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::simulate::{OutputLayout, SimConfig};
+use crate::strict;
+
+// ════════════════════════════════════════════════════════════════
+// Risk measures on simulated (or externally supplied) P&L
+//
+// Sign convention: P&L is a gain (negative = loss); VaR and CVaR are
+// reported as positive losses at confidence α.
+// ════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VarCvar {
+    pub level: f64,
+    pub var: f64,
+    pub cvar: f64,
+}
+
+/// Per-path, per-asset terminal P&L S_T − S₀ ([path][asset]) from a
+/// path buffer in `config.layout` (S₀ = 1, so this is the return).
+pub fn terminal_pnl(paths: &[f32], n: usize, config: &SimConfig) -> Result<Vec<f64>, &'static str> {
+    let (n_paths, rows) = (config.n_paths, config.steps + 1);
+    if n == 0 || paths.len() != n_paths * rows * n {
+        return Err("Risk input mismatch: paths must be n_paths × (steps + 1) × N");
+    }
+    let mut out = vec![0.0; n_paths * n];
+    for (i, x) in out.iter_mut().enumerate() {
+        let (p, a) = (i / n, i % n);
+        let idx = match config.layout {
+            OutputLayout::Interleaved => (p * rows + rows - 1) * n + a,
+            OutputLayout::Planar => (a * n_paths + p) * rows + rows - 1,
+        };
+        *x = paths[idx] as f64 - 1.0;
+    }
+    Ok(out)
+}
+
+/// Σ_a w_a·pnl[p][a] per path. Empty weights mean `terminal_pnl` is
+/// already one portfolio value per path.
+pub fn portfolio_pnl(terminal_pnl: &[f64], weights: &[f64]) -> Result<Vec<f64>, &'static str> {
+    if weights.is_empty() {
+        return Ok(terminal_pnl.to_vec());
+    }
+    if !terminal_pnl.len().is_multiple_of(weights.len()) {
+        return Err("Risk input mismatch: P&L length must be a multiple of the weight count");
+    }
+    Ok(terminal_pnl
+        .chunks_exact(weights.len())
+        .map(|row| row.iter().zip(weights).map(|(x, w)| x * w).sum())
+        .collect())
+}
+
+// ────────────────────────────────────────────────────────────────
+// compute_var_cvar — empirical VaR and CVaR (Acerbi–Tasche)
+// Losses sorted worst-first, tail mass m = n·(1 − α):
+//   VaR  = L_(⌈m⌉)
+//   CVaR = (Σ_{i≤⌊m⌋} L_(i) + (m − ⌊m⌋)·L_(⌊m⌋+1)) / m
+// so CVaR ≥ VaR and both stay coherent for non-integer tail counts.
+// ────────────────────────────────────────────────────────────────
+pub fn compute_var_cvar(terminal_pnl: &[f64], weights: &[f64], levels: &[f64]) -> Result<Vec<VarCvar>, &'static str> {
+    let pnl = portfolio_pnl(terminal_pnl, weights)?;
+    if pnl.is_empty() {
+        return Err("Risk input mismatch: need at least one path");
+    }
+    if levels.iter().any(|&a| !(a > 0.0 && a < 1.0)) {
+        return Err("Risk input invalid: confidence levels must lie in (0, 1)");
+    }
+    if pnl.iter().any(|x| !x.is_finite()) {
+        return Err("Risk input invalid: P&L contains non-finite values");
+    }
+    let mut losses: Vec<f64> = pnl.iter().map(|x| -x).collect();
+    losses.sort_unstable_by(|a, b| b.total_cmp(a));
+    let n = losses.len();
+
+    Ok(levels
+        .iter()
+        .map(|&level| {
+            let m = tail_mass(n, level);
+            let whole = (m.floor() as usize).min(n);
+            let var = losses[(m.ceil() as usize).clamp(1, n) - 1];
+            let frac = if whole < n { (m - whole as f64) * losses[whole] } else { 0.0 };
+            let cvar = (losses[..whole].iter().sum::<f64>() + frac) / m;
+            VarCvar { level, var, cvar }
+        })
+        .collect())
+}
+
+/// n·(1 − α), snapped to an integer when it is one up to rounding
+fn tail_mass(n: usize, level: f64) -> f64 {
+    let raw = n as f64 * (1.0 - level);
+    if (raw - raw.round()).abs() < 1e-9 { raw.round() } else { raw }.max(f64::MIN_POSITIVE)
+}
+
+// ────────────────────────────────────────────────────────────────
+// Risk contributions
+// Volatility (Euler): MRC_a = (Σw)_a / σ_P,  component w_a·MRC_a,
+// which sum to σ_P.
+// CVaR (conditional expectation): component_a = E[−w_a·pnl_a | tail],
+// over the same Acerbi–Tasche tail weights as compute_var_cvar, so the
+// components sum to the portfolio CVaR.
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug, PartialEq)]
+pub struct VolContributions {
+    pub vol: f64,
+    /// ∂σ_P / ∂w_a
+    pub marginal: Vec<f64>,
+    pub component: Vec<f64>,
+    /// component / σ_P (sums to 1)
+    pub percent: Vec<f64>,
+}
+
+pub fn vol_contributions(weights: &[f64], cholesky_l: &DMatrix<f64>) -> Result<VolContributions, &'static str> {
+    let n = weights.len();
+    if n == 0 || cholesky_l.shape() != (n, n) {
+        return Err("Risk input mismatch: one weight per row of L is required");
+    }
+    let w = DVector::from_column_slice(weights);
+    let sigma_w = cholesky_l * (cholesky_l.transpose() * &w);
+    let vol = w.dot(&sigma_w).max(0.0).sqrt();
+    if vol == 0.0 {
+        return Err("Risk input invalid: portfolio has zero volatility");
+    }
+    let marginal: Vec<f64> = sigma_w.iter().map(|x| x / vol).collect();
+    let component: Vec<f64> = marginal.iter().zip(weights).map(|(m, w)| m * w).collect();
+    let percent = component.iter().map(|c| c / vol).collect();
+    Ok(VolContributions { vol, marginal, component, percent })
+}
+
+/// Per-asset CVaR components ([path][asset] terminal P&L, N weights).
+pub fn cvar_contributions(terminal_pnl: &[f64], weights: &[f64], level: f64) -> Result<Vec<f64>, &'static str> {
+    let n = weights.len();
+    let pnl = portfolio_pnl(terminal_pnl, weights)?;
+    if n == 0 || pnl.is_empty() {
+        return Err("Risk input mismatch: need weights and at least one path");
+    }
+    if !(level > 0.0 && level < 1.0) {
+        return Err("Risk input invalid: confidence levels must lie in (0, 1)");
+    }
+    let mut order: Vec<usize> = (0..pnl.len()).collect();
+    order.sort_unstable_by(|&a, &b| pnl[a].total_cmp(&pnl[b]));
+    let m = tail_mass(pnl.len(), level);
+    let whole = (m.floor() as usize).min(pnl.len());
+
+    let mut out = vec![0.0; n];
+    let tail = order.iter().take(whole + 1).enumerate();
+    for (rank, &p) in tail {
+        let mass = if rank < whole { 1.0 } else { m - whole as f64 };
+        for (a, c) in out.iter_mut().enumerate() {
+            *c -= mass * weights[a] * terminal_pnl[p * n + a];
+        }
+    }
+    out.iter_mut().for_each(|c| *c /= m);
+    Ok(out)
+}
+
+// ────────────────────────────────────────────────────────────────
+// Expected-shortfall decomposition
+// Packages the CVaR components for display: each asset's average loss
+// over the tail paths, its share of ES and the assets ranked by share,
+// e.g. "in the worst 1%, 70% of the damage comes from these three".
+// Shares can be negative for holdings that gain in the tail (hedges).
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug, PartialEq)]
+pub struct EsDecomposition {
+    pub level: f64,
+    /// Portfolio ES (loss, positive = bad)
+    pub es: f64,
+    /// Tail mass n·(1 − level) the averages are taken over
+    pub tail_paths: f64,
+    /// E[−w_a·pnl_a | tail], summing to `es`
+    pub contribution: Vec<f64>,
+    /// contribution / es (sums to 1; all 0 when es = 0)
+    pub share: Vec<f64>,
+    /// Asset indices by share, largest first
+    pub ranking: Vec<usize>,
+}
+
+pub fn es_decomposition(terminal_pnl: &[f64], weights: &[f64], level: f64) -> Result<EsDecomposition, &'static str> {
+    let contribution = cvar_contributions(terminal_pnl, weights, level)?;
+    let es: f64 = contribution.iter().sum();
+    let share: Vec<f64> = contribution.iter().map(|&c| if es != 0.0 { c / es } else { 0.0 }).collect();
+    let mut ranking: Vec<usize> = (0..share.len()).collect();
+    ranking.sort_by(|&a, &b| share[b].total_cmp(&share[a]));
+    let tail_paths = tail_mass(terminal_pnl.len() / weights.len(), level);
+    Ok(EsDecomposition { level, es, tail_paths, contribution, share, ranking })
+}
+
+// ────────────────────────────────────────────────────────────────
+// percentile_fan — per-step percentiles for fan charts
+// Output is [step][percentile] for the portfolio Σ w_a·S_a(t) (equal
+// weights when none are given), followed, if per_asset, by
+// [asset][step][percentile]. Percentiles are in percent (0–100) and
+// interpolate linearly between order statistics.
+// ────────────────────────────────────────────────────────────────
+pub fn percentile_fan(
+    paths: &[f32],
+    n: usize,
+    config: &SimConfig,
+    weights: &[f64],
+    percentiles: &[f64],
+    per_asset: bool,
+) -> Result<Vec<f32>, &'static str> {
+    let (n_paths, rows) = (config.n_paths, config.steps + 1);
+    if n == 0 || n_paths == 0 || paths.len() != n_paths * rows * n {
+        return Err("Risk input mismatch: paths must be n_paths × (steps + 1) × N");
+    }
+    if !(weights.is_empty() || weights.len() == n) {
+        return Err("Risk input mismatch: one weight per asset (or none)");
+    }
+    if percentiles.iter().any(|&q| !(0.0..=100.0).contains(&q)) {
+        return Err("Risk input invalid: percentiles must lie in [0, 100]");
+    }
+    let equal = vec![1.0 / n as f64; n];
+    let weights = if weights.is_empty() { &equal[..] } else { weights };
+    let value = |p: usize, t: usize, a: usize| {
+        paths[match config.layout {
+            OutputLayout::Interleaved => (p * rows + t) * n + a,
+            OutputLayout::Planar => (a * n_paths + p) * rows + t,
+        }] as f64
+    };
+
+    let series = if per_asset { n + 1 } else { 1 };
+    let mut out = Vec::with_capacity(series * rows * percentiles.len());
+    let mut column = vec![0.0; n_paths];
+    for s in 0..series {
+        for t in 0..rows {
+            for (p, x) in column.iter_mut().enumerate() {
+                *x = match s {
+                    0 => (0..n).map(|a| weights[a] * value(p, t, a)).sum(),
+                    s => value(p, t, s - 1),
+                };
+            }
+            column.sort_unstable_by(f64::total_cmp);
+            out.extend(percentiles.iter().map(|&q| quantile_sorted(&column, q / 100.0) as f32));
+        }
+    }
+    Ok(out)
+}
+
+/// Linear interpolation between order statistics (Hyndman–Fan type 7)
+fn quantile_sorted(sorted: &[f64], q: f64) -> f64 {
+    let h = q * (sorted.len() - 1) as f64;
+    let lo = h.floor() as usize;
+    let hi = (lo + 1).min(sorted.len() - 1);
+    sorted[lo] + (h - lo as f64) * (sorted[hi] - sorted[lo])
+}
+
+// ────────────────────────────────────────────────────────────────
+// FanAccumulator — percentile_fan over a run that arrives in batches
+// Keeps one histogram of ln(portfolio value) per step instead of the
+// paths, so a fan can be redrawn after every batch of a 10⁶-path run
+// in O(rows × bins) memory. Bins span ln V ∈ [−8, 8]; percentiles
+// interpolate within a bin, so they sit within one bin width (16/bins
+// in ln V) of percentile_fan's, and values outside the span (or ≤ 0)
+// are counted in the edge bins.
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug)]
+pub struct FanAccumulator {
+    n: usize,
+    rows: usize,
+    weights: Vec<f64>,
+    bins: usize,
+    /// rows × bins counts
+    counts: Vec<u32>,
+    n_paths: usize,
+}
+
+impl FanAccumulator {
+    const LN_MIN: f64 = -8.0;
+    const LN_MAX: f64 = 8.0;
+
+    /// `weights` as in percentile_fan (empty = equal)
+    pub fn new(n: usize, steps: usize, weights: &[f64], bins: usize) -> Result<Self, &'static str> {
+        if n == 0 || bins < 2 {
+            return Err("Risk input invalid: need at least one asset and two bins");
+        }
+        if !(weights.is_empty() || weights.len() == n) {
+            return Err("Risk input mismatch: one weight per asset (or none)");
+        }
+        let weights = if weights.is_empty() { vec![1.0 / n as f64; n] } else { weights.to_vec() };
+        let rows = steps + 1;
+        Ok(FanAccumulator { n, rows, weights, bins, counts: vec![0; rows * bins], n_paths: 0 })
+    }
+
+    /// Paths added so far
+    pub fn n_paths(&self) -> usize {
+        self.n_paths
+    }
+
+    /// Add a batch of interleaved [path][step][asset] paths.
+    pub fn add(&mut self, paths: &[f32]) -> Result<(), &'static str> {
+        let stride = self.rows * self.n;
+        if !paths.len().is_multiple_of(stride) {
+            return Err("Risk input mismatch: paths must be a whole number of (steps + 1) × N paths");
+        }
+        let scale = self.bins as f64 / (Self::LN_MAX - Self::LN_MIN);
+        for path in paths.chunks_exact(stride) {
+            for (t, row) in path.chunks_exact(self.n).enumerate() {
+                let v: f64 = row.iter().zip(&self.weights).map(|(&x, w)| w * x as f64).sum();
+                let b = if v > 0.0 { ((strict::ln(v) - Self::LN_MIN) * scale).floor().clamp(0.0, (self.bins - 1) as f64) } else { 0.0 };
+                self.counts[t * self.bins + b as usize] += 1;
+            }
+        }
+        self.n_paths += paths.len() / stride;
+        Ok(())
+    }
+
+    /// [step][percentile], percentiles in percent as in percentile_fan
+    pub fn percentiles(&self, percentiles: &[f64]) -> Result<Vec<f32>, &'static str> {
+        if self.n_paths == 0 {
+            return Err("Risk input mismatch: need at least one path");
+        }
+        if percentiles.iter().any(|&q| !(0.0..=100.0).contains(&q)) {
+            return Err("Risk input invalid: percentiles must lie in [0, 100]");
+        }
+        let width = (Self::LN_MAX - Self::LN_MIN) / self.bins as f64;
+        let mut out = Vec::with_capacity(self.rows * percentiles.len());
+        for counts in self.counts.chunks_exact(self.bins) {
+            for &q in percentiles {
+                let target = q / 100.0 * self.n_paths as f64;
+                let mut below = 0.0;
+                let mut value = Self::LN_MAX;
+                for (b, &c) in counts.iter().enumerate() {
+                    let c = c as f64;
+                    if c > 0.0 && below + c >= target {
+                        value = Self::LN_MIN + (b as f64 + (target - below) / c) * width;
+                        break;
+                    }
+                    below += c;
+                }
+                out.push(strict::exp(value) as f32);
+            }
+        }
+        Ok(out)
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Tail dependence — empirical λ_L, λ_U at threshold q
+// With k = ⌊q·M⌋ of M samples, λ_L(i, j) is the share of asset i's k
+// worst outcomes that are also among asset j's k worst (λ_U likewise
+// for the best). Independence gives ≈ q, comonotonic assets 1, so a
+// correlation shock that produces joint crashes shows up as λ_L ≫ q.
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TailSample {
+    /// One terminal log return per path
+    Terminal,
+    /// Every step's log increment, pooled over paths
+    Steps,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TailDependence {
+    pub lower: DMatrix<f64>,
+    pub upper: DMatrix<f64>,
+}
+
+pub fn tail_dependence(
+    paths: &[f32],
+    n: usize,
+    config: &SimConfig,
+    q: f64,
+    sample: TailSample,
+) -> Result<TailDependence, &'static str> {
+    let (n_paths, rows) = (config.n_paths, config.steps + 1);
+    if n == 0 || paths.len() != n_paths * rows * n {
+        return Err("Risk input mismatch: paths must be n_paths × (steps + 1) × N");
+    }
+    if !(q > 0.0 && q < 0.5) {
+        return Err("Risk input invalid: tail threshold must lie in (0, 0.5)");
+    }
+    let price = |p: usize, t: usize, a: usize| {
+        paths[match config.layout {
+            OutputLayout::Interleaved => (p * rows + t) * n + a,
+            OutputLayout::Planar => (a * n_paths + p) * rows + t,
+        }] as f64
+    };
+    // samples[a] = log returns of asset a
+    let samples: Vec<Vec<f64>> = (0..n)
+        .map(|a| match sample {
+            TailSample::Terminal => (0..n_paths).map(|p| strict::ln(price(p, rows - 1, a) / price(p, 0, a))).collect(),
+            TailSample::Steps => (0..n_paths)
+                .flat_map(|p| (1..rows).map(move |t| (p, t)))
+                .map(|(p, t)| strict::ln(price(p, t, a) / price(p, t - 1, a)))
+                .collect(),
+        })
+        .collect();
+    let m = samples[0].len();
+    let k = (q * m as f64).floor() as usize;
+    if k == 0 {
+        return Err("Risk input invalid: too few samples for the tail threshold");
+    }
+
+    // Membership masks of each asset's k lowest / highest samples
+    let masks = |upper: bool| -> Vec<Vec<bool>> {
+        samples
+            .iter()
+            .map(|xs| {
+                let mut order: Vec<usize> = (0..m).collect();
+                order.sort_unstable_by(|&i, &j| xs[i].total_cmp(&xs[j]));
+                let mut mask = vec![false; m];
+                let tail = if upper { &order[m - k..] } else { &order[..k] };
+                tail.iter().for_each(|&i| mask[i] = true);
+                mask
+            })
+            .collect()
+    };
+    let coefficients = |mask: &[Vec<bool>]| {
+        DMatrix::from_fn(n, n, |i, j| {
+            mask[i].iter().zip(&mask[j]).filter(|(a, b)| **a && **b).count() as f64 / k as f64
+        })
+    };
+    Ok(TailDependence { lower: coefficients(&masks(false)), upper: coefficients(&masks(true)) })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::inv_norm_cdf;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_var_cvar_on_uniform_grid() {
+        // P&L −99 … 0: worst 5 losses are 99, 98, 97, 96, 95
+        let pnl: Vec<f64> = (0..100).map(|i| -(i as f64)).collect();
+        let r = compute_var_cvar(&pnl, &[], &[0.95, 0.975]).unwrap();
+        assert_relative_eq!(r[0].var, 95.0);
+        assert_relative_eq!(r[0].cvar, 97.0);
+        // m = 2.5: (99 + 98 + 0.5·97) / 2.5
+        assert_relative_eq!(r[1].var, 97.0);
+        assert_relative_eq!(r[1].cvar, (99.0 + 98.0 + 48.5) / 2.5);
+    }
+
+    #[test]
+    fn test_gaussian_var_cvar() {
+        // N(0, 1) P&L: VaR_α = Φ⁻¹(α), CVaR_α = φ(Φ⁻¹(α)) / (1 − α)
+        let n = 200_000;
+        let pnl: Vec<f64> = (0..n).map(|i| inv_norm_cdf((i as f64 + 0.5) / n as f64)).collect();
+        let r = compute_var_cvar(&pnl, &[], &[0.99]).unwrap();
+        let q = inv_norm_cdf(0.99);
+        let pdf = (-0.5 * q * q).exp() / (2.0 * std::f64::consts::PI).sqrt();
+        assert_relative_eq!(r[0].var, q, epsilon = 1e-3);
+        assert_relative_eq!(r[0].cvar, pdf / 0.01, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_vol_contributions_sum_to_vol() {
+        let vol = DVector::from_vec(vec![0.2, 0.05]);
+        let r = DMatrix::from_row_slice(2, 2, &[1.0, 0.5, 0.5, 1.0]);
+        let l = crate::math::cholesky_decompose(&crate::math::rebuild_covariance(&vol, &r)).unwrap();
+        let c = vol_contributions(&[0.6, 0.4], &l).unwrap();
+        assert_relative_eq!(c.component.iter().sum::<f64>(), c.vol, epsilon = 1e-12);
+        assert_relative_eq!(c.percent.iter().sum::<f64>(), 1.0, epsilon = 1e-12);
+        // (Σw)_0 = 0.6·0.04 + 0.4·0.005
+        assert_relative_eq!(c.marginal[0], (0.6 * 0.04 + 0.4 * 0.005) / c.vol, epsilon = 1e-12);
+        assert!(c.percent[0] > 0.9);
+    }
+
+    #[test]
+    fn test_cvar_contributions_sum_to_cvar() {
+        let vol = DVector::from_vec(vec![0.3, 0.1]);
+        let l = DMatrix::from_diagonal(&vol);
+        let config = SimConfig::new(1.0, 4, 1001, 9);
+        let paths = crate::simulate::simulate_paths(&DVector::zeros(2), &vol, &l, &config).unwrap();
+        let pnl = terminal_pnl(&paths, 2, &config).unwrap();
+        let w = [0.5, 0.5];
+        let parts = cvar_contributions(&pnl, &w, 0.975).unwrap();
+        let cvar = compute_var_cvar(&pnl, &w, &[0.975]).unwrap()[0].cvar;
+        assert_relative_eq!(parts.iter().sum::<f64>(), cvar, epsilon = 1e-9);
+        assert!(parts[0] > parts[1]);
+    }
+
+    #[test]
+    fn test_tail_dependence_separates_independent_from_correlated() {
+        // Assets 0, 1 correlated at 0.9; asset 2 independent
+        let vol = DVector::from_vec(vec![0.2, 0.2, 0.2]);
+        let r = DMatrix::from_row_slice(3, 3, &[1.0, 0.9, 0.0, 0.9, 1.0, 0.0, 0.0, 0.0, 1.0]);
+        let l = crate::math::cholesky_decompose(&crate::math::rebuild_covariance(&vol, &r)).unwrap();
+        let config = SimConfig::new(1.0, 4, 5000, 8);
+        let paths = crate::simulate::simulate_paths(&DVector::zeros(3), &vol, &l, &config).unwrap();
+        let td = tail_dependence(&paths, 3, &config, 0.05, TailSample::Steps).unwrap();
+        assert_eq!(td.lower[(1, 1)], 1.0);
+        assert_relative_eq!(td.lower[(0, 2)], 0.05, epsilon = 0.02);
+        assert!(td.lower[(0, 1)] > 0.4);
+        assert_relative_eq!(td.lower, td.lower.transpose());
+
+        let terminal = tail_dependence(&paths, 3, &config, 0.05, TailSample::Terminal).unwrap();
+        assert!(terminal.upper[(0, 1)] > 0.4);
+        assert!(tail_dependence(&paths, 3, &config, 0.6, TailSample::Steps).is_err());
+    }
+
+    #[test]
+    fn test_es_decomposition_ranks_tail_damage() {
+        // Asset 0 crashes in the two worst paths, asset 2 hedges
+        let pnl = [
+            -0.5, -0.1, 0.1, //
+            -0.4, -0.1, 0.1, //
+            0.1, 0.0, 0.0, //
+            0.2, 0.1, -0.1,
+        ];
+        let w = [1.0, 1.0, 1.0];
+        let d = es_decomposition(&pnl, &w, 0.5).unwrap();
+        assert_relative_eq!(d.tail_paths, 2.0);
+        assert_relative_eq!(d.es, 0.45, epsilon = 1e-12);
+        assert_relative_eq!(d.contribution[1], 0.1, epsilon = 1e-12);
+        assert_relative_eq!(d.share.iter().sum::<f64>(), 1.0, epsilon = 1e-12);
+        assert!(d.share[2] < 0.0);
+        assert_eq!(d.ranking, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_fan_accumulator_tracks_percentile_fan() {
+        let vol = DVector::from_vec(vec![0.3, 0.1]);
+        let l = DMatrix::from_diagonal(&vol);
+        let config = SimConfig::new(1.0, 6, 20_000, 5);
+        let paths = crate::simulate::simulate_paths(&DVector::zeros(2), &vol, &l, &config).unwrap();
+        let q = [5.0, 25.0, 50.0, 75.0, 95.0];
+        let exact = percentile_fan(&paths, 2, &config, &[0.7, 0.3], &q, false).unwrap();
+
+        let mut fan = FanAccumulator::new(2, 6, &[0.7, 0.3], 4096).unwrap();
+        for batch in paths.chunks(7 * 2 * 3000) {
+            fan.add(batch).unwrap();
+        }
+        assert_eq!(fan.n_paths(), 20_000);
+        let approx = fan.percentiles(&q).unwrap();
+        assert_eq!(approx.len(), exact.len());
+        for (a, e) in approx.iter().zip(&exact) {
+            assert_relative_eq!(*a, *e, max_relative = 0.005);
+        }
+        assert!(fan.add(&paths[..5]).is_err());
+    }
+
+    #[test]
+    fn test_percentile_fan_shape_and_order() {
+        let vol = DVector::from_vec(vec![0.2, 0.1]);
+        let l = DMatrix::from_diagonal(&vol);
+        let config = SimConfig::new(1.0, 4, 2000, 3);
+        let paths = crate::simulate::simulate_paths(&DVector::zeros(2), &vol, &l, &config).unwrap();
+        let q = [5.0, 50.0, 95.0];
+        let fan = percentile_fan(&paths, 2, &config, &[], &q, true).unwrap();
+        assert_eq!(fan.len(), 3 * 5 * 3);
+        // t = 0: every path starts at 1
+        assert!(fan[..3].iter().all(|&x| x == 1.0));
+        for row in fan.chunks_exact(3) {
+            assert!(row[0] <= row[1] && row[1] <= row[2]);
+        }
+        // Asset 0 terminal median ≈ e^{−σ²T/2}; its band is wider than asset 1's
+        let (a0, a1) = (&fan[(5 + 4) * 3..(5 + 5) * 3], &fan[(10 + 4) * 3..(10 + 5) * 3]);
+        assert_relative_eq!(a0[1] as f64, (-0.02_f64).exp(), max_relative = 0.02);
+        assert!(a0[2] - a0[0] > a1[2] - a1[0]);
+    }
+
+    #[test]
+    fn test_quantile_interpolates() {
+        let xs = [1.0, 2.0, 3.0, 4.0];
+        assert_relative_eq!(quantile_sorted(&xs, 0.0), 1.0);
+        assert_relative_eq!(quantile_sorted(&xs, 0.5), 2.5);
+        assert_relative_eq!(quantile_sorted(&xs, 1.0), 4.0);
+    }
+
+    #[test]
+    fn test_weights_and_path_buffers() {
+        // Two paths, one step, two assets; planar and interleaved agree
+        let interleaved = [1.0, 1.0, 0.8, 1.1, 1.0, 1.0, 1.2, 0.7];
+        let config = SimConfig::new(1.0, 1, 2, 0);
+        let pnl = terminal_pnl(&interleaved, 2, &config).unwrap();
+        let planar = crate::simulate::to_planar(&interleaved, 2, 2, 2);
+        let planar_cfg = config.with_layout(OutputLayout::Planar);
+        assert_eq!(terminal_pnl(&planar, 2, &planar_cfg).unwrap(), pnl);
+
+        let port = portfolio_pnl(&pnl, &[0.5, 0.5]).unwrap();
+        assert_relative_eq!(port[0], -0.05, epsilon = 1e-6);
+        assert_relative_eq!(port[1], -0.05, epsilon = 1e-6);
+        assert!(compute_var_cvar(&pnl, &[1.0, 0.0, 0.0], &[0.95]).is_err());
+        assert!(compute_var_cvar(&pnl, &[0.5, 0.5], &[1.0]).is_err());
+    }
+}
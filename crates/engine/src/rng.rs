@@ -0,0 +1,320 @@
+use crate::simulate::Pcg32;
+
+// ════════════════════════════════════════════════════════════════
+// Selectable uniform generators for the simulator
+//
+// Every generator exposes a 32-bit output (wider generators return
+// the high half of each 64-bit word) so the draw → uniform → normal
+// chain is identical whichever algorithm feeds it. Per-path streams
+// are keyed on (seed, path) as for Pcg32::for_path.
+// ════════════════════════════════════════════════════════════════
+
+/// Which algorithm drives the pseudo-random streams.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RngKind {
+    #[default]
+    Pcg32,
+    Xoshiro256PlusPlus,
+    Pcg64,
+    /// Philox4x32-10; see Philox4x32 for the counter/key layout a GPU
+    /// kernel must use to reproduce the same draws.
+    Philox4x32,
+}
+
+impl RngKind {
+    pub const ALL: [RngKind; 4] = [RngKind::Pcg32, RngKind::Xoshiro256PlusPlus, RngKind::Pcg64, RngKind::Philox4x32];
+
+    /// Canonical name, as accepted by from_name
+    pub fn id(&self) -> &'static str {
+        match self {
+            RngKind::Pcg32 => "pcg32",
+            RngKind::Xoshiro256PlusPlus => "xoshiro256++",
+            RngKind::Pcg64 => "pcg64",
+            RngKind::Philox4x32 => "philox4x32",
+        }
+    }
+
+    /// Parse "pcg32", "xoshiro256++", "pcg64" or "philox4x32"
+    pub fn from_name(name: &str) -> Result<Self, &'static str> {
+        match name.to_ascii_lowercase().as_str() {
+            "pcg32" => Ok(RngKind::Pcg32),
+            "xoshiro256++" | "xoshiro256plusplus" => Ok(RngKind::Xoshiro256PlusPlus),
+            "pcg64" => Ok(RngKind::Pcg64),
+            "philox4x32" | "philox" => Ok(RngKind::Philox4x32),
+            _ => Err("RNG input invalid: expected pcg32, xoshiro256++, pcg64 or philox4x32"),
+        }
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// SplitMix64 — seeds xoshiro state from (seed, path)
+// ────────────────────────────────────────────────────────────────
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// ────────────────────────────────────────────────────────────────
+// Xoshiro256++ (Blackman–Vigna)
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug)]
+pub struct Xoshiro256PlusPlus {
+    s: [u64; 4],
+}
+
+impl Xoshiro256PlusPlus {
+    pub fn from_state(s: [u64; 4]) -> Self {
+        Xoshiro256PlusPlus { s }
+    }
+
+    /// State = four SplitMix64 outputs seeded with seed ⊕ SplitMix(path).
+    pub fn for_path(seed: u64, path: usize) -> Self {
+        let mut p = path as u64;
+        let mut sm = seed ^ splitmix64(&mut p);
+        let s = [
+            splitmix64(&mut sm),
+            splitmix64(&mut sm),
+            splitmix64(&mut sm),
+            splitmix64(&mut sm),
+        ];
+        Xoshiro256PlusPlus { s }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let out = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        out
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Pcg64 — PCG-XSL-RR 128/64, each path on its own stream
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug)]
+pub struct Pcg64 {
+    state: u128,
+    inc: u128,
+}
+
+impl Pcg64 {
+    const MULTIPLIER: u128 = 0x2360_ED05_1FC6_5DA4_4385_DF64_9FCC_F645;
+
+    pub fn new(init_state: u128, init_seq: u128) -> Self {
+        let mut rng = Pcg64 { state: 0, inc: (init_seq << 1) | 1 };
+        rng.step();
+        rng.state = rng.state.wrapping_add(init_state);
+        rng.step();
+        rng
+    }
+
+    pub fn for_path(seed: u64, path: usize) -> Self {
+        Pcg64::new(seed as u128, path as u128)
+    }
+
+    fn step(&mut self) {
+        self.state = self.state.wrapping_mul(Self::MULTIPLIER).wrapping_add(self.inc);
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.step();
+        let s = self.state;
+        (((s >> 64) as u64) ^ (s as u64)).rotate_right((s >> 122) as u32)
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Philox4x32-10 (Salmon et al., Random123)
+//
+// Path p, block b:  key = [seed_lo, seed_hi],
+//                   counter = [b_lo, b_hi, p_lo, p_hi]
+// Each block yields four words consumed in order x0, x1, x2, x3,
+// so draw i of path p is word i mod 4 of block i / 4.
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug)]
+pub struct Philox4x32 {
+    key: [u32; 2],
+    counter: [u32; 4],
+    buffer: [u32; 4],
+    next: usize,
+}
+
+impl Philox4x32 {
+    const M0: u32 = 0xD251_1F53;
+    const M1: u32 = 0xCD9E_8D57;
+    const W0: u32 = 0x9E37_79B9;
+    const W1: u32 = 0xBB67_AE85;
+
+    pub fn for_path(seed: u64, path: usize) -> Self {
+        let p = path as u64;
+        Philox4x32 {
+            key: [seed as u32, (seed >> 32) as u32],
+            counter: [0, 0, p as u32, (p >> 32) as u32],
+            buffer: [0; 4],
+            next: 4,
+        }
+    }
+
+    /// The ten-round bijection of one counter block.
+    pub fn block(counter: [u32; 4], key: [u32; 2]) -> [u32; 4] {
+        let mut x = counter;
+        let mut k = key;
+        for round in 0..10 {
+            if round > 0 {
+                k[0] = k[0].wrapping_add(Self::W0);
+                k[1] = k[1].wrapping_add(Self::W1);
+            }
+            let p0 = (Self::M0 as u64) * (x[0] as u64);
+            let p1 = (Self::M1 as u64) * (x[2] as u64);
+            x = [
+                ((p1 >> 32) as u32) ^ x[1] ^ k[0],
+                p1 as u32,
+                ((p0 >> 32) as u32) ^ x[3] ^ k[1],
+                p0 as u32,
+            ];
+        }
+        x
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        if self.next == 4 {
+            self.buffer = Self::block(self.counter, self.key);
+            let (lo, carry) = self.counter[0].overflowing_add(1);
+            self.counter[0] = lo;
+            self.counter[1] = self.counter[1].wrapping_add(carry as u32);
+            self.next = 0;
+        }
+        let out = self.buffer[self.next];
+        self.next += 1;
+        out
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// PathRng — one per-path stream of the selected kind
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug)]
+pub enum PathRng {
+    Pcg32(Pcg32),
+    Xoshiro256PlusPlus(Xoshiro256PlusPlus),
+    Pcg64(Pcg64),
+    Philox4x32(Philox4x32),
+}
+
+impl PathRng {
+    pub fn for_path(kind: RngKind, seed: u64, path: usize) -> Self {
+        match kind {
+            RngKind::Pcg32 => PathRng::Pcg32(Pcg32::for_path(seed, path)),
+            RngKind::Xoshiro256PlusPlus => PathRng::Xoshiro256PlusPlus(Xoshiro256PlusPlus::for_path(seed, path)),
+            RngKind::Pcg64 => PathRng::Pcg64(Pcg64::for_path(seed, path)),
+            RngKind::Philox4x32 => PathRng::Philox4x32(Philox4x32::for_path(seed, path)),
+        }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        match self {
+            PathRng::Pcg32(r) => r.next_u32(),
+            PathRng::Xoshiro256PlusPlus(r) => (r.next_u64() >> 32) as u32,
+            PathRng::Pcg64(r) => (r.next_u64() >> 32) as u32,
+            PathRng::Philox4x32(r) => r.next_u32(),
+        }
+    }
+
+    /// Uniform in (0, 1) — same mapping as Pcg32::next_f64
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u32() as f64 + 0.5) / 4_294_967_296.0
+    }
+}
+
+impl From<Pcg32> for PathRng {
+    fn from(rng: Pcg32) -> Self {
+        PathRng::Pcg32(rng)
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_philox_known_answers() {
+        // Random123 kat_vectors for philox4x32_10
+        assert_eq!(Philox4x32::block([0; 4], [0; 2]), [0x6627_e8d5, 0xe169_c58d, 0xbc57_ac4c, 0x9b00_dbd8]);
+        assert_eq!(
+            Philox4x32::block([u32::MAX; 4], [u32::MAX; 2]),
+            [0x408f_276d, 0x41c8_3b0e, 0xa20b_c7c6, 0x6d54_51fd]
+        );
+        assert_eq!(
+            Philox4x32::block([0x243f_6a88, 0x85a3_08d3, 0x1319_8a2e, 0x0370_7344], [0xa409_3822, 0x299f_31d0]),
+            [0xd16c_fe09, 0x94fd_cceb, 0x5001_e420, 0x2412_6ea1]
+        );
+    }
+
+    #[test]
+    fn test_philox_stream_walks_counter_blocks() {
+        let (seed, path) = (0x1234_5678_9ABC_DEF0_u64, 3);
+        let mut rng = Philox4x32::for_path(seed, path);
+        let key = [0x9ABC_DEF0, 0x1234_5678];
+        let draws: Vec<u32> = (0..8).map(|_| rng.next_u32()).collect();
+        assert_eq!(draws[..4], Philox4x32::block([0, 0, 3, 0], key));
+        assert_eq!(draws[4..], Philox4x32::block([1, 0, 3, 0], key));
+    }
+
+    #[test]
+    fn test_xoshiro_reference_output() {
+        // s = [1, 2, 3, 4]: rotl(1 + 4, 23) + 1
+        let mut rng = Xoshiro256PlusPlus::from_state([1, 2, 3, 4]);
+        assert_eq!(rng.next_u64(), 41_943_041);
+        assert_eq!(rng.next_u64(), 58_720_359);
+    }
+
+    #[test]
+    fn test_pcg64_reference_output() {
+        // pcg64_srandom_r(42, 54) from the PCG reference test suite
+        let mut rng = Pcg64::new(42, 54);
+        assert_eq!(rng.next_u64(), 0x86b1_da1d_7206_2b68);
+        assert_eq!(rng.next_u64(), 0x1304_aa46_c985_3d39);
+    }
+
+    #[test]
+    fn test_kind_from_name() {
+        assert_eq!(RngKind::from_name("Philox4x32"), Ok(RngKind::Philox4x32));
+        assert_eq!(RngKind::from_name("xoshiro256++"), Ok(RngKind::Xoshiro256PlusPlus));
+        for kind in RngKind::ALL {
+            assert_eq!(RngKind::from_name(kind.id()), Ok(kind));
+        }
+        assert!(RngKind::from_name("mt19937").is_err());
+    }
+
+    #[test]
+    fn test_every_kind_is_roughly_uniform() {
+        let kinds = [RngKind::Pcg32, RngKind::Xoshiro256PlusPlus, RngKind::Pcg64, RngKind::Philox4x32];
+        for kind in kinds {
+            let mut rng = PathRng::for_path(kind, 9, 1);
+            let n = 100_000;
+            let (mut sum, mut sum2) = (0.0, 0.0);
+            for _ in 0..n {
+                let u = rng.next_f64();
+                assert!(u > 0.0 && u < 1.0);
+                sum += u;
+                sum2 += u * u;
+            }
+            let mean = sum / n as f64;
+            assert_relative_eq!(mean, 0.5, epsilon = 0.005);
+            assert_relative_eq!(sum2 / n as f64 - mean * mean, 1.0 / 12.0, epsilon = 0.002);
+        }
+    }
+}
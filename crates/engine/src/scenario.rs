@@ -0,0 +1,406 @@
+use crate::json::{self, Json};
+use crate::math;
+use crate::simulate::{JumpParams, SimConfig};
+use nalgebra::DMatrix;
+
+// ════════════════════════════════════════════════════════════════
+// Scenario documents — one file per run, shared by every front end
+//
+//     { "kind": "scenario", "version": 1,
+//       "metadata":   { "name", "description", "author", "created", "tags" },
+//       "market":     { "assets", "base_drift", "base_vol",
+//                       "base_correlation" (N×N row-major), "weights" },
+//       "shock":      { "delta_drift", "vol_multiplier", "correlation_skew",
+//                       "jump_lambda", "jump_mean", "jump_vol" (1 or N) },
+//       "simulation": { "horizon", "steps", "n_paths", "seed", "levels" } }
+//
+// A seed above 2⁵³ has no exact JSON number, so it is written as a
+// decimal string; smaller ones stay numbers for older readers.
+// Only the market's drift, vol and correlation are required; every
+// other field has a neutral default (no shock, one-year daily run).
+// Unknown fields are ignored, so a newer writer's additions load in
+// an older engine of the same version. Documents with a higher
+// version are rejected, older ones go through `upgrade`, and a saved
+// ShockConfig (kind "shock_config") imports as a scenario too.
+// ════════════════════════════════════════════════════════════════
+
+pub const SCENARIO_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub name: String,
+    pub description: String,
+    pub author: String,
+    /// Free-form, conventionally an ISO 8601 date
+    pub created: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Market {
+    /// Labels, "asset 0", "asset 1", … when absent
+    pub assets: Vec<String>,
+    pub base_drift: Vec<f64>,
+    pub base_vol: Vec<f64>,
+    /// N×N row-major
+    pub base_correlation: Vec<f64>,
+    /// Portfolio weights, 1/N when absent
+    pub weights: Vec<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shock {
+    pub delta_drift: Vec<f64>,
+    pub vol_multiplier: Vec<f64>,
+    pub correlation_skew: f64,
+    /// Length 1 (broadcast) or N, as SimConfig::with_asset_jumps takes
+    pub jumps: Vec<JumpParams>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Simulation {
+    pub horizon: f64,
+    pub steps: usize,
+    pub n_paths: usize,
+    pub seed: u64,
+    /// VaR / CVaR confidence levels
+    pub levels: Vec<f64>,
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Simulation { horizon: 1.0, steps: 252, n_paths: 100_000, seed: 42, levels: vec![0.95, 0.99] }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scenario {
+    pub metadata: Metadata,
+    pub market: Market,
+    pub shock: Shock,
+    pub simulation: Simulation,
+}
+
+impl Scenario {
+    /// Unshocked scenario over a base market with default settings
+    pub fn new(base_drift: Vec<f64>, base_vol: Vec<f64>, base_correlation: Vec<f64>) -> Scenario {
+        let n = base_drift.len();
+        Scenario {
+            metadata: Metadata::default(),
+            market: Market {
+                assets: (0..n).map(|i| format!("asset {i}")).collect(),
+                base_drift,
+                base_vol,
+                base_correlation,
+                weights: vec![1.0 / n as f64; n],
+            },
+            shock: Shock {
+                delta_drift: vec![0.0; n],
+                vol_multiplier: vec![1.0; n],
+                correlation_skew: 0.0,
+                jumps: vec![JumpParams { lambda: 0.0, mean: 0.0, vol: 0.0 }],
+            },
+            simulation: Simulation::default(),
+        }
+    }
+
+    pub fn num_assets(&self) -> usize {
+        self.market.base_drift.len()
+    }
+
+    /// Shapes, ranges and a valid base correlation.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let n = self.num_assets();
+        let m = &self.market;
+        if n == 0 {
+            return Err("Scenario input invalid: the market has no assets");
+        }
+        if m.assets.len() != n
+            || m.base_vol.len() != n
+            || m.weights.len() != n
+            || m.base_correlation.len() != n * n
+            || self.shock.delta_drift.len() != n
+            || self.shock.vol_multiplier.len() != n
+        {
+            return Err("Scenario input mismatch: market and shock arrays must have length N (correlation N×N)");
+        }
+        if self.shock.jumps.len() != 1 && self.shock.jumps.len() != n {
+            return Err("Scenario input mismatch: jump arrays must have length 1 or N");
+        }
+        let s = &self.simulation;
+        let positive = s.steps > 0 && s.n_paths > 0 && s.horizon.is_finite() && s.horizon > 0.0;
+        if !positive || s.levels.iter().any(|&a| !(a > 0.0 && a < 1.0)) {
+            return Err("Scenario input invalid: steps, n_paths and horizon must be positive, levels in (0, 1)");
+        }
+        math::validate_correlation(&DMatrix::from_row_slice(n, n, &m.base_correlation), 1e-6)
+            .map_err(|_| "Scenario input invalid: base_correlation is not a valid correlation matrix")
+    }
+
+    /// Simulator settings with the scenario's jumps
+    pub fn sim_config(&self) -> SimConfig {
+        let s = &self.simulation;
+        SimConfig::new(s.horizon, s.steps, s.n_paths, s.seed).with_asset_jumps(self.shock.jumps.clone())
+    }
+
+    // ────────────────────────────────────────────────────────────
+    // JSON
+    // ────────────────────────────────────────────────────────────
+
+    pub fn to_json(&self) -> String {
+        let strings = |xs: &[String]| Json::Array(xs.iter().map(|s| Json::String(s.clone())).collect());
+        let jump = |f: fn(&JumpParams) -> f64| numbers(&self.shock.jumps.iter().map(f).collect::<Vec<_>>());
+        let (meta, m, s, sim) = (&self.metadata, &self.market, &self.shock, &self.simulation);
+        Json::Object(vec![
+            ("kind".into(), Json::String("scenario".into())),
+            ("version".into(), Json::Number(SCENARIO_VERSION as f64)),
+            (
+                "metadata".into(),
+                Json::Object(vec![
+                    ("name".into(), Json::String(meta.name.clone())),
+                    ("description".into(), Json::String(meta.description.clone())),
+                    ("author".into(), Json::String(meta.author.clone())),
+                    ("created".into(), Json::String(meta.created.clone())),
+                    ("tags".into(), strings(&meta.tags)),
+                ]),
+            ),
+            (
+                "market".into(),
+                Json::Object(vec![
+                    ("assets".into(), strings(&m.assets)),
+                    ("base_drift".into(), numbers(&m.base_drift)),
+                    ("base_vol".into(), numbers(&m.base_vol)),
+                    ("base_correlation".into(), numbers(&m.base_correlation)),
+                    ("weights".into(), numbers(&m.weights)),
+                ]),
+            ),
+            (
+                "shock".into(),
+                Json::Object(vec![
+                    ("delta_drift".into(), numbers(&s.delta_drift)),
+                    ("vol_multiplier".into(), numbers(&s.vol_multiplier)),
+                    ("correlation_skew".into(), Json::Number(s.correlation_skew)),
+                    ("jump_lambda".into(), jump(|j| j.lambda)),
+                    ("jump_mean".into(), jump(|j| j.mean)),
+                    ("jump_vol".into(), jump(|j| j.vol)),
+                ]),
+            ),
+            (
+                "simulation".into(),
+                Json::Object(vec![
+                    ("horizon".into(), Json::Number(sim.horizon)),
+                    ("steps".into(), Json::Number(sim.steps as f64)),
+                    ("n_paths".into(), Json::Number(sim.n_paths as f64)),
+                    ("seed".into(), write_seed(sim.seed)),
+                    ("levels".into(), numbers(&sim.levels)),
+                ]),
+            ),
+        ])
+        .to_string()
+    }
+
+    /// Parse, upgrade and validate a scenario document (or a saved
+    /// ShockConfig).
+    pub fn from_json(text: &str) -> Result<Scenario, &'static str> {
+        let json = json::parse(text)?;
+        let version = json.get("version").and_then(Json::as_f64).ok_or("Scenario input invalid: missing version")?;
+        let scenario = match json.get("kind").and_then(Json::as_str) {
+            Some("scenario") if version > SCENARIO_VERSION as f64 => {
+                return Err("Scenario input invalid: written by a newer engine (unsupported version)")
+            }
+            Some("scenario") => read_v1(&upgrade(json, version as u32)?)?,
+            // ShockConfig's flat layout is the shock plus the market
+            Some("shock_config") => read_v1(&Json::Object(vec![
+                ("market".into(), json.clone()),
+                ("shock".into(), json),
+            ]))?,
+            _ => return Err("Scenario input invalid: kind must be \"scenario\""),
+        };
+        scenario.validate()?;
+        Ok(scenario)
+    }
+}
+
+/// Rewrite an older document into the current layout, one version at
+/// a time. Version 1 is the first; later versions add arms here.
+fn upgrade(json: Json, version: u32) -> Result<Json, &'static str> {
+    match version {
+        1 => Ok(json),
+        _ => Err("Scenario input invalid: unsupported version"),
+    }
+}
+
+fn read_v1(json: &Json) -> Result<Scenario, &'static str> {
+    let empty = Json::Object(Vec::new());
+    let section = |key: &str| json.get(key).unwrap_or(&empty);
+    let (meta, market, shock, sim) = (section("metadata"), section("market"), section("shock"), section("simulation"));
+
+    let base_drift = read_numbers(market, "base_drift")?.ok_or("Scenario input invalid: market.base_drift is required")?;
+    let base_vol = read_numbers(market, "base_vol")?.ok_or("Scenario input invalid: market.base_vol is required")?;
+    let base_correlation =
+        read_numbers(market, "base_correlation")?.ok_or("Scenario input invalid: market.base_correlation is required")?;
+    let mut s = Scenario::new(base_drift, base_vol, base_correlation);
+
+    let text = |j: &Json, key: &str| j.get(key).and_then(Json::as_str).unwrap_or_default().to_string();
+    s.metadata = Metadata {
+        name: text(meta, "name"),
+        description: text(meta, "description"),
+        author: text(meta, "author"),
+        created: text(meta, "created"),
+        tags: read_strings(meta, "tags")?.unwrap_or_default(),
+    };
+    if let Some(assets) = read_strings(market, "assets")? {
+        s.market.assets = assets;
+    }
+    if let Some(weights) = read_numbers(market, "weights")? {
+        s.market.weights = weights;
+    }
+
+    if let Some(x) = read_numbers(shock, "delta_drift")? {
+        s.shock.delta_drift = x;
+    }
+    if let Some(x) = read_numbers(shock, "vol_multiplier")? {
+        s.shock.vol_multiplier = x;
+    }
+    s.shock.correlation_skew = shock.get("correlation_skew").and_then(Json::as_f64).unwrap_or(0.0);
+    let jump = |key: &str| -> Result<Vec<f64>, &'static str> {
+        Ok(match shock.get(key) {
+            Some(Json::Number(x)) => vec![*x],
+            _ => read_numbers(shock, key)?.unwrap_or_else(|| vec![0.0]),
+        })
+    };
+    let (lambda, mean, vol) = (jump("jump_lambda")?, jump("jump_mean")?, jump("jump_vol")?);
+    let width = lambda.len().max(mean.len()).max(vol.len());
+    if [&lambda, &mean, &vol].iter().any(|xs| xs.len() != 1 && xs.len() != width) {
+        return Err("Scenario input mismatch: jump arrays must share one length");
+    }
+    let at = |xs: &[f64], i: usize| if xs.len() == 1 { xs[0] } else { xs[i] };
+    s.shock.jumps = (0..width).map(|i| JumpParams { lambda: at(&lambda, i), mean: at(&mean, i), vol: at(&vol, i) }).collect();
+
+    let d = Simulation::default();
+    let count = |key: &str, default: usize| -> Result<usize, &'static str> {
+        match sim.get(key).and_then(Json::as_f64) {
+            None => Ok(default),
+            Some(x) if x >= 0.0 && x.fract() == 0.0 => Ok(x as usize),
+            Some(_) => Err("Scenario input invalid: steps and n_paths must be whole numbers"),
+        }
+    };
+    s.simulation = Simulation {
+        horizon: sim.get("horizon").and_then(Json::as_f64).unwrap_or(d.horizon),
+        steps: count("steps", d.steps)?,
+        n_paths: count("n_paths", d.n_paths)?,
+        seed: read_seed(sim)?.unwrap_or(d.seed),
+        levels: read_numbers(sim, "levels")?.unwrap_or(d.levels),
+    };
+    Ok(s)
+}
+
+/// Whole numbers are exact in f64 up to here
+const MAX_EXACT: u64 = 1 << 53;
+
+fn write_seed(seed: u64) -> Json {
+    if seed <= MAX_EXACT {
+        Json::Number(seed as f64)
+    } else {
+        Json::String(seed.to_string())
+    }
+}
+
+/// A decimal string, or a number that is exactly a u64 (None if absent)
+fn read_seed(sim: &Json) -> Result<Option<u64>, &'static str> {
+    match sim.get("seed") {
+        None => Ok(None),
+        Some(Json::String(text)) => {
+            text.parse().map(Some).map_err(|_| "Scenario input invalid: seed string is not a u64")
+        }
+        Some(Json::Number(x)) if *x >= 0.0 && x.fract() == 0.0 && *x <= MAX_EXACT as f64 => Ok(Some(*x as u64)),
+        Some(_) => Err("Scenario input invalid: seed must be a whole number up to 2^53 or a decimal string"),
+    }
+}
+
+fn numbers(xs: &[f64]) -> Json {
+    Json::Array(xs.iter().map(|&x| Json::Number(x)).collect())
+}
+
+/// Numbers at `key` in full precision (None if absent)
+fn read_numbers(json: &Json, key: &str) -> Result<Option<Vec<f64>>, &'static str> {
+    match json.get(key) {
+        None => Ok(None),
+        Some(Json::Array(items)) => items
+            .iter()
+            .map(|x| x.as_f64().ok_or("Scenario input invalid: expected an array of numbers"))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(_) => Err("Scenario input invalid: expected an array of numbers"),
+    }
+}
+
+fn read_strings(json: &Json, key: &str) -> Result<Option<Vec<String>>, &'static str> {
+    match json.get(key) {
+        None => Ok(None),
+        Some(Json::Array(items)) => items
+            .iter()
+            .map(|x| x.as_str().map(str::to_string).ok_or("Scenario input invalid: expected an array of strings"))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(_) => Err("Scenario input invalid: expected an array of strings"),
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn sample() -> Scenario {
+        let mut s = Scenario::new(vec![0.08, 0.03], vec![0.18, 0.05], vec![1.0, 0.2, 0.2, 1.0]);
+        s.metadata.name = "Fed \"shock\"".into();
+        s.metadata.tags = vec!["rates".into()];
+        s.market.assets = vec!["SPX".into(), "AGG".into()];
+        s.shock.vol_multiplier = vec![2.0, 1.1];
+        s.shock.correlation_skew = 0.4;
+        s.shock.jumps = vec![JumpParams { lambda: 1.0, mean: -0.1, vol: 0.05 }, JumpParams { lambda: 0.0, mean: 0.0, vol: 0.0 }];
+        s.simulation.n_paths = 5000;
+        s
+    }
+
+    #[test]
+    fn test_round_trip_and_defaults() {
+        let s = sample();
+        assert_eq!(Scenario::from_json(&s.to_json()).unwrap(), s);
+
+        let mut big = sample();
+        big.simulation.seed = u64::MAX - 2;
+        assert!(big.to_json().contains("\"seed\":\"18446744073709551613\""));
+        assert_eq!(Scenario::from_json(&big.to_json()).unwrap(), big);
+        let unsafe_number = s.to_json().replace("\"seed\":42", "\"seed\":1e19");
+        assert!(Scenario::from_json(&unsafe_number).is_err());
+
+        let minimal = r#"{"kind":"scenario","version":1,"future_field":true,
+            "market":{"base_drift":[0.05],"base_vol":[0.2],"base_correlation":[1]}}"#;
+        let m = Scenario::from_json(minimal).unwrap();
+        assert_eq!(m.market.assets, vec!["asset 0"]);
+        assert_eq!(m.shock.vol_multiplier, vec![1.0]);
+        assert_eq!(m.simulation, Simulation::default());
+        assert_relative_eq!(m.sim_config().horizon, 1.0);
+    }
+
+    #[test]
+    fn test_versions_and_imports() {
+        let newer = sample().to_json().replace("\"version\":1", "\"version\":2");
+        assert!(Scenario::from_json(&newer).unwrap_err().contains("newer"));
+        assert!(Scenario::from_json(r#"{"kind":"engine_result","version":1}"#).is_err());
+
+        let shock_config = r#"{"kind":"shock_config","version":1,"base_drift":[0.08,0.03],"base_vol":[0.18,0.05],
+            "base_correlation":[1,0.2,0.2,1],"delta_drift":[-0.1,0],"vol_multiplier":[2,1],"correlation_skew":0.5,
+            "jump_lambda":[1],"jump_mean":[-0.2],"jump_vol":[0.1]}"#;
+        let s = Scenario::from_json(shock_config).unwrap();
+        assert_eq!(s.shock.delta_drift, vec![-0.1, 0.0]);
+        assert_eq!(s.shock.jumps, vec![JumpParams { lambda: 1.0, mean: -0.2, vol: 0.1 }]);
+
+        let bad = sample().to_json().replace("[1,0.2,0.2,1]", "[1,0.2,0.3,1]");
+        assert!(Scenario::from_json(&bad).is_err());
+    }
+}
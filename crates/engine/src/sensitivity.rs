@@ -0,0 +1,340 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::engine::shock_market;
+use crate::math;
+use crate::portfolio::Portfolio;
+use crate::risk;
+use crate::simulate::{self, JumpParams, SimConfig};
+
+// ════════════════════════════════════════════════════════════════
+// Bump-and-revalue sensitivities
+//
+// Each shock input is bumped by h, the Phase A pipeline and simulator
+// rerun with the same seed (common random numbers), and the change in
+// the portfolio's terminal P&L summary divided by h. Jump-intensity
+// bumps change the Poisson draws, which shifts the rest of each path's
+// stream, so those rows are noisier than the diffusion ones.
+// ════════════════════════════════════════════════════════════════
+
+/// Inputs to one Phase A run plus scalar jumps (broadcast per asset).
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShockInputs {
+    pub base_drift: DVector<f64>,
+    pub base_vol: DVector<f64>,
+    pub base_corr: DMatrix<f64>,
+    pub delta_drift: DVector<f64>,
+    pub vol_multiplier: DVector<f64>,
+    pub skew: f64,
+    pub jumps: JumpParams,
+}
+
+/// Which dial a sensitivity row bumps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShockInput {
+    DeltaDrift(usize),
+    VolMultiplier(usize),
+    Skew,
+    JumpLambda,
+    JumpMean,
+    JumpVol,
+}
+
+/// Terminal portfolio P&L statistics.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PnlSummary {
+    pub mean: f64,
+    pub std: f64,
+    pub var: f64,
+    pub cvar: f64,
+}
+
+impl PnlSummary {
+    fn diff(&self, base: &PnlSummary, h: f64) -> PnlSummary {
+        PnlSummary {
+            mean: (self.mean - base.mean) / h,
+            std: (self.std - base.std) / h,
+            var: (self.var - base.var) / h,
+            cvar: (self.cvar - base.cvar) / h,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sensitivity {
+    pub input: ShockInput,
+    /// Signed bump actually applied (negative when +h would leave the
+    /// input's domain, e.g. skew near 1)
+    pub bump: f64,
+    /// ∂summary / ∂input by finite difference
+    pub gradient: PnlSummary,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SensitivityTable {
+    pub base: PnlSummary,
+    /// Rows in order: N drift deltas, N vol multipliers, skew, λ, μ_J, σ_J
+    pub rows: Vec<Sensitivity>,
+}
+
+/// A shocked market ready to simulate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarketState {
+    pub drift: DVector<f64>,
+    pub vol: DVector<f64>,
+    pub cholesky_l: DMatrix<f64>,
+    /// Per-asset (or one broadcast) jump set; None for pure diffusion
+    pub jumps: Option<Vec<JumpParams>>,
+}
+
+impl ShockInputs {
+    /// Phase A with PD repair (as compute_shock)
+    pub fn market(&self) -> Result<MarketState, &'static str> {
+        let m = shock_market(
+            &self.base_drift,
+            &self.base_vol,
+            &self.base_corr,
+            &self.delta_drift,
+            &self.vol_multiplier,
+            self.skew,
+            true,
+        )
+        .map_err(|e| e.reason())?;
+        Ok(MarketState {
+            drift: m.drift,
+            vol: m.vol,
+            cholesky_l: m.cholesky_l,
+            jumps: (self.jumps.lambda > 0.0).then(|| vec![self.jumps]),
+        })
+    }
+}
+
+/// Simulates `market` and summarizes terminal portfolio P&L at
+/// confidence `level`. Jumps come from the market or, when it has none,
+/// the config; setting both is rejected rather than silently dropping one.
+pub fn summarize(
+    market: &MarketState,
+    portfolio: &Portfolio,
+    level: f64,
+    config: &SimConfig,
+) -> Result<PnlSummary, &'static str> {
+    let mut config = config.clone();
+    if market.jumps.is_some() {
+        if config.jumps.is_some() {
+            return Err("Scenario input mismatch: jumps set on both the market and the config");
+        }
+        config.jumps = market.jumps.clone();
+    }
+    let paths = simulate::simulate_paths(&market.drift, &market.vol, &market.cholesky_l, &config)?;
+    let pnl = portfolio.pnl_paths(&paths, &config)?;
+    let terminal: Vec<f64> = pnl.chunks_exact(config.steps + 1).map(|row| row[config.steps] as f64).collect();
+
+    let n = terminal.len() as f64;
+    let mean = terminal.iter().sum::<f64>() / n;
+    let std = (terminal.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0)).sqrt();
+    let tail = risk::compute_var_cvar(&terminal, &[], &[level])?[0];
+    Ok(PnlSummary { mean, std, var: tail.var, cvar: tail.cvar })
+}
+
+/// Shocks, simulates and summarizes terminal P&L at confidence `level`.
+pub fn revalue(
+    inputs: &ShockInputs,
+    portfolio: &Portfolio,
+    level: f64,
+    config: &SimConfig,
+) -> Result<PnlSummary, &'static str> {
+    summarize(&inputs.market()?, portfolio, level, config)
+}
+
+pub fn bump_and_revalue(
+    inputs: &ShockInputs,
+    portfolio: &Portfolio,
+    level: f64,
+    bump: f64,
+    config: &SimConfig,
+) -> Result<SensitivityTable, &'static str> {
+    let n = inputs.base_drift.len();
+    if !(bump.is_finite() && bump > 0.0) {
+        return Err("Sensitivity input invalid: bump must be positive");
+    }
+    let base = revalue(inputs, portfolio, level, config)?;
+
+    let dials = (0..n)
+        .map(ShockInput::DeltaDrift)
+        .chain((0..n).map(ShockInput::VolMultiplier))
+        .chain([ShockInput::Skew, ShockInput::JumpLambda, ShockInput::JumpMean, ShockInput::JumpVol]);
+    let mut rows = Vec::with_capacity(2 * n + 4);
+    for input in dials {
+        let mut bumped = inputs.clone();
+        let h = match input {
+            ShockInput::DeltaDrift(i) => {
+                bumped.delta_drift[i] += bump;
+                bump
+            }
+            ShockInput::VolMultiplier(i) => {
+                bumped.vol_multiplier[i] += bump;
+                bump
+            }
+            ShockInput::Skew => {
+                let h = if inputs.skew + bump <= 1.0 { bump } else { -bump };
+                bumped.skew += h;
+                h
+            }
+            ShockInput::JumpLambda => {
+                bumped.jumps.lambda += bump;
+                bump
+            }
+            ShockInput::JumpMean => {
+                bumped.jumps.mean += bump;
+                bump
+            }
+            ShockInput::JumpVol => {
+                bumped.jumps.vol += bump;
+                bump
+            }
+        };
+        let value = revalue(&bumped, portfolio, level, config)?;
+        rows.push(Sensitivity { input, bump: h, gradient: value.diff(&base, h) });
+    }
+    Ok(SensitivityTable { base, rows })
+}
+
+// ────────────────────────────────────────────────────────────────
+// Scenario diff — what changed between two shocked markets
+// Both run with the config's seed, so the summary deltas reflect the
+// scenarios rather than sampling noise.
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioDiff {
+    /// b − a, per asset
+    pub drift: DVector<f64>,
+    pub vol: DVector<f64>,
+    /// b − a of the effective correlations (see correlation_from_cholesky)
+    pub correlation: DMatrix<f64>,
+    pub before: PnlSummary,
+    pub after: PnlSummary,
+}
+
+impl ScenarioDiff {
+    /// after − before
+    pub fn summary_delta(&self) -> PnlSummary {
+        self.after.diff(&self.before, 1.0)
+    }
+}
+
+pub fn diff_scenarios(
+    a: &MarketState,
+    b: &MarketState,
+    portfolio: &Portfolio,
+    level: f64,
+    config: &SimConfig,
+) -> Result<ScenarioDiff, &'static str> {
+    if a.drift.len() != b.drift.len() || a.cholesky_l.shape() != b.cholesky_l.shape() {
+        return Err("Scenario input mismatch: both scenarios need the same assets");
+    }
+    Ok(ScenarioDiff {
+        drift: &b.drift - &a.drift,
+        vol: &b.vol - &a.vol,
+        correlation: math::correlation_from_cholesky(&b.cholesky_l) - math::correlation_from_cholesky(&a.cholesky_l),
+        before: summarize(a, portfolio, level, config)?,
+        after: summarize(b, portfolio, level, config)?,
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn inputs() -> ShockInputs {
+        ShockInputs {
+            base_drift: DVector::from_vec(vec![0.08, 0.03]),
+            base_vol: DVector::from_vec(vec![0.2, 0.05]),
+            base_corr: DMatrix::from_row_slice(2, 2, &[1.0, 0.5, 0.5, 1.0]),
+            delta_drift: DVector::zeros(2),
+            vol_multiplier: DVector::from_element(2, 1.0),
+            skew: 0.0,
+            jumps: JumpParams { lambda: 0.0, mean: 0.0, vol: 0.0 },
+        }
+    }
+
+    #[test]
+    fn test_drift_sensitivity_matches_analytic_mean() {
+        // ∂E[P&L_T]/∂Δμ_0 = w_0·T·e^{μ_0 T}
+        let p = Portfolio::from_weights(vec![0.6, 0.4], 1.0).unwrap();
+        let config = SimConfig::new(1.0, 4, 20_000, 12);
+        let table = bump_and_revalue(&inputs(), &p, 0.95, 0.01, &config).unwrap();
+        assert_eq!(table.rows.len(), 2 * 2 + 4);
+        assert_eq!(table.rows[0].input, ShockInput::DeltaDrift(0));
+        assert_relative_eq!(table.rows[0].gradient.mean, 0.6 * 0.085_f64.exp(), max_relative = 0.05);
+        // Raising a vol multiplier widens the distribution
+        assert!(table.rows[2].gradient.std > 0.0);
+        assert!(table.rows[2].gradient.cvar > 0.0);
+    }
+
+    #[test]
+    fn test_common_random_numbers_make_zero_bumps_exact() {
+        // Dials nothing depends on (jump size with λ = 0) have zero gradient
+        let p = Portfolio::from_weights(vec![1.0, 0.0], 1.0).unwrap();
+        let config = SimConfig::new(1.0, 4, 500, 3);
+        let table = bump_and_revalue(&inputs(), &p, 0.99, 0.01, &config).unwrap();
+        let jump_mean = table.rows.iter().find(|r| r.input == ShockInput::JumpMean).unwrap();
+        assert_eq!(jump_mean.gradient, PnlSummary { mean: 0.0, std: 0.0, var: 0.0, cvar: 0.0 });
+        // Asset 1 carries no weight
+        assert_eq!(table.rows[1].gradient.mean, 0.0);
+    }
+
+    #[test]
+    fn test_diff_reports_element_and_risk_changes() {
+        let p = Portfolio::from_weights(vec![0.6, 0.4], 1.0).unwrap();
+        let config = SimConfig::new(1.0, 4, 2000, 4);
+        let calm = inputs();
+        let stressed = ShockInputs {
+            delta_drift: DVector::from_vec(vec![-0.1, 0.0]),
+            vol_multiplier: DVector::from_vec(vec![2.0, 1.0]),
+            skew: 0.5,
+            ..inputs()
+        };
+        let d = diff_scenarios(&calm.market().unwrap(), &stressed.market().unwrap(), &p, 0.95, &config).unwrap();
+        assert_relative_eq!(d.drift[0], -0.1, epsilon = 1e-12);
+        assert_relative_eq!(d.vol[0], 0.2, epsilon = 1e-12);
+        assert_eq!(d.vol[1], 0.0);
+        // 0.5 → 0.75 after blending halfway to 1
+        assert_relative_eq!(d.correlation[(0, 1)], 0.25, epsilon = 1e-9);
+        assert_eq!(d.correlation[(0, 0)], 0.0);
+        let delta = d.summary_delta();
+        assert!(delta.mean < 0.0 && delta.std > 0.0 && delta.cvar > 0.0);
+
+        // Identical scenarios diff to exactly zero
+        let same = diff_scenarios(&calm.market().unwrap(), &calm.market().unwrap(), &p, 0.95, &config).unwrap();
+        assert_eq!(same.summary_delta(), PnlSummary { mean: 0.0, std: 0.0, var: 0.0, cvar: 0.0 });
+    }
+
+    #[test]
+    fn test_summarize_keeps_config_jumps_and_rejects_both() {
+        let p = Portfolio::from_weights(vec![1.0, 0.0], 1.0).unwrap();
+        let jumps = JumpParams { lambda: 3.0, mean: -0.1, vol: 0.05 };
+        let plain = SimConfig::new(1.0, 4, 500, 8);
+        let jumpy = plain.clone().with_jumps(jumps);
+        // A diffusion market runs with the caller's jumps
+        let diffusion = inputs().market().unwrap();
+        let with_config = summarize(&diffusion, &p, 0.95, &jumpy).unwrap();
+        let with_market = summarize(&ShockInputs { jumps, ..inputs() }.market().unwrap(), &p, 0.95, &plain).unwrap();
+        assert_eq!(with_config, with_market);
+        assert_ne!(with_config, summarize(&diffusion, &p, 0.95, &plain).unwrap());
+        // Jumps on both sides are ambiguous
+        let jumpy_market = ShockInputs { jumps, ..inputs() }.market().unwrap();
+        assert!(summarize(&jumpy_market, &p, 0.95, &jumpy).is_err());
+    }
+
+    #[test]
+    fn test_skew_bumps_down_at_the_boundary() {
+        let p = Portfolio::from_weights(vec![0.5, 0.5], 1.0).unwrap();
+        let config = SimConfig::new(1.0, 2, 200, 1);
+        let table = bump_and_revalue(&ShockInputs { skew: 1.0, ..inputs() }, &p, 0.95, 0.05, &config).unwrap();
+        let skew = table.rows.iter().find(|r| r.input == ShockInput::Skew).unwrap();
+        assert_eq!(skew.bump, -0.05);
+    }
+}
@@ -0,0 +1,135 @@
+use nalgebra::{DMatrix, DVector};
+
+// ════════════════════════════════════════════════════════════════
+// SIMD kernels for the Phase A / simulator hot loops
+//
+// Built with `--features simd` and `-C target-feature=+simd128`, the
+// kernels run two f64 lanes at a time; every other build gets plain
+// loops that add in the same order as the code they replaced, so
+// non-SIMD output is unchanged bit for bit. The lane-paired dot sums
+// round differently and may move results in the last ulp; in strict
+// mode (see strict.rs) both builds use the pairwise strict::dot.
+// ════════════════════════════════════════════════════════════════
+
+#[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+use core::arch::wasm32::{f64x2, f64x2_add, f64x2_extract_lane, f64x2_mul, f64x2_splat};
+
+/// Whether this binary was built with the SIMD kernels
+pub const ENABLED: bool = cfg!(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"));
+
+/// Σ aᵢ·bᵢ over the shorter of the two slices
+#[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+pub fn dot(a: &[f64], b: &[f64]) -> f64 {
+    if crate::strict::enabled() {
+        return crate::strict::dot(a, b);
+    }
+    let len = a.len().min(b.len());
+    let mut acc = f64x2_splat(0.0);
+    for i in (0..len - len % 2).step_by(2) {
+        acc = f64x2_add(acc, f64x2_mul(f64x2(a[i], a[i + 1]), f64x2(b[i], b[i + 1])));
+    }
+    let mut sum = f64x2_extract_lane::<0>(acc) + f64x2_extract_lane::<1>(acc);
+    if len % 2 == 1 {
+        sum += a[len - 1] * b[len - 1];
+    }
+    sum
+}
+
+/// Σ aᵢ·bᵢ over the shorter of the two slices
+#[cfg(not(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128")))]
+pub fn dot(a: &[f64], b: &[f64]) -> f64 {
+    if crate::strict::enabled() {
+        return crate::strict::dot(a, b);
+    }
+    a.iter().zip(b).fold(0.0, |sum, (x, y)| sum + x * y)
+}
+
+/// out[i] = (x[i]·y[i])·s
+#[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+fn scaled_product(out: &mut [f64], x: &[f64], y: &[f64], s: f64) {
+    let len = out.len();
+    let s2 = f64x2_splat(s);
+    for i in (0..len - len % 2).step_by(2) {
+        let v = f64x2_mul(f64x2_mul(f64x2(x[i], x[i + 1]), f64x2(y[i], y[i + 1])), s2);
+        out[i] = f64x2_extract_lane::<0>(v);
+        out[i + 1] = f64x2_extract_lane::<1>(v);
+    }
+    if len % 2 == 1 {
+        out[len - 1] = x[len - 1] * y[len - 1] * s;
+    }
+}
+
+/// out[i] = (x[i]·y[i])·s
+#[cfg(not(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128")))]
+fn scaled_product(out: &mut [f64], x: &[f64], y: &[f64], s: f64) {
+    for ((o, &a), &b) in out.iter_mut().zip(x).zip(y) {
+        *o = a * b * s;
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Σ = D·R·D without the dense diagonal products: column j is σ ⊙ R₍:,j₎
+// scaled by σⱼ (the same two roundings as the matrix form)
+// ────────────────────────────────────────────────────────────────
+pub fn scale_symmetric(sigma: &DVector<f64>, r: &DMatrix<f64>) -> DMatrix<f64> {
+    let mut out = DMatrix::zeros(r.nrows(), r.ncols());
+    scale_symmetric_into(sigma, r, &mut out);
+    out
+}
+
+/// scale_symmetric into `out`, which must have R's shape
+pub fn scale_symmetric_into(sigma: &DVector<f64>, r: &DMatrix<f64>, out: &mut DMatrix<f64>) {
+    for j in 0..r.ncols() {
+        scaled_product(out.column_mut(j).as_mut_slice(), sigma.as_slice(), r.column(j).as_slice(), sigma[j]);
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// V·diag(λ)·Vᵀ from contiguous rows of V (columns of Vᵀ), lower
+// triangle by dot products and mirrored — the eigen-reconstruction in
+// every Higham iteration (tiled by blocked::matmul_tn for large N)
+// ────────────────────────────────────────────────────────────────
+pub fn reconstruct(vectors: &DMatrix<f64>, values: &DVector<f64>) -> DMatrix<f64> {
+    let n = vectors.nrows();
+    let vt = vectors.transpose();
+    let mut weighted = vt.clone();
+    for mut col in weighted.column_iter_mut() {
+        col.component_mul_assign(values);
+    }
+    if n >= crate::blocked::MIN_N && !crate::strict::enabled() {
+        return crate::blocked::matmul_tn(&weighted, &vt, true);
+    }
+    let mut out = DMatrix::zeros(n, n);
+    for i in 0..n {
+        for j in 0..=i {
+            let x = dot(weighted.column(i).as_slice(), vt.column(j).as_slice());
+            out[(i, j)] = x;
+            out[(j, i)] = x;
+        }
+    }
+    out
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_kernels_match_dense_forms() {
+        assert_relative_eq!(dot(&[1.0, 2.0, 3.0], &[4.0, -5.0, 6.0, 9.0]), 12.0);
+
+        let sigma = DVector::from_vec(vec![0.2, 0.1, 0.3]);
+        let r = DMatrix::from_row_slice(3, 3, &[1.0, 0.4, -0.2, 0.4, 1.0, 0.1, -0.2, 0.1, 1.0]);
+        let d = DMatrix::from_diagonal(&sigma);
+        assert_eq!(scale_symmetric(&sigma, &r), &d * &r * &d);
+
+        let eigen = r.clone().symmetric_eigen();
+        let dense = &eigen.eigenvectors * DMatrix::from_diagonal(&eigen.eigenvalues) * eigen.eigenvectors.transpose();
+        assert_relative_eq!(reconstruct(&eigen.eigenvectors, &eigen.eigenvalues), dense, epsilon = 1e-14);
+        assert_relative_eq!(reconstruct(&eigen.eigenvectors, &eigen.eigenvalues), r, epsilon = 1e-12);
+    }
+}
@@ -0,0 +1,2398 @@
+use std::ops::Range;
+
+use nalgebra::{DMatrix, DVector};
+
+use crate::rng::{PathRng, RngKind};
+use crate::sobol::{SobolSequence, SOBOL_MAX_DIM};
+use crate::strict;
+
+// ════════════════════════════════════════════════════════════════
+// Phase B — CPU Monte Carlo (fallback for browsers without WebGPU)
+// Correlated GBM:  ln S_{t+dt} = ln S_t + (μ − σ²/2)·dt + √dt·(L·Z)
+// L is the Cholesky factor of Σ = D·R·D, so it already carries σ.
+// ════════════════════════════════════════════════════════════════
+
+// ────────────────────────────────────────────────────────────────
+// Pcg32 — canonical PCG-XSH-RR (64-bit state, 32-bit output);
+// each path runs on its own stream (initseq = path index)
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug)]
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+
+    pub fn new(init_state: u64, init_seq: u64) -> Self {
+        let mut rng = Pcg32 { state: 0, inc: (init_seq << 1) | 1 };
+        rng.next_u32(); // Advance once to mix
+        rng.state = rng.state.wrapping_add(init_state);
+        rng.next_u32(); // Advance again
+        rng
+    }
+
+    pub fn for_path(seed: u64, path: usize) -> Self {
+        Pcg32::new(seed, path as u64)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(Self::MULTIPLIER).wrapping_add(self.inc);
+        let xsh = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xsh.rotate_right(rot)
+    }
+
+    /// Uniform in (0, 1) — excludes exact 0 to avoid ln(0)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u32() as f64 + 0.5) / 4_294_967_296.0
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// NormalSampler — Box-Muller, caching the second variate
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug)]
+pub struct NormalSampler {
+    rng: PathRng,
+    spare: Option<f64>,
+}
+
+impl NormalSampler {
+    pub fn new(rng: impl Into<PathRng>) -> Self {
+        NormalSampler { rng: rng.into(), spare: None }
+    }
+
+    pub fn sample(&mut self) -> f64 {
+        if let Some(z) = self.spare.take() {
+            return z;
+        }
+        let u1 = self.rng.next_f64();
+        let u2 = self.rng.next_f64();
+        let r = (-2.0 * strict::ln(u1)).sqrt();
+        let theta = std::f64::consts::TAU * u2;
+        let (sin, cos) = strict::sin_cos(theta);
+        self.spare = Some(r * sin);
+        r * cos
+    }
+
+    /// Uniform draw from the underlying stream (leaves the spare intact)
+    pub fn uniform(&mut self) -> f64 {
+        self.rng.next_f64()
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Poisson(mean) — Knuth's product method for the small λ·dt regime,
+// normal approximation once the mean is large enough not to matter
+// ────────────────────────────────────────────────────────────────
+pub fn sample_poisson(normals: &mut NormalSampler, mean: f64) -> u32 {
+    if mean <= 0.0 {
+        return 0;
+    }
+    if mean > 30.0 {
+        let x = mean + mean.sqrt() * normals.sample();
+        return x.round().max(0.0) as u32;
+    }
+    let limit = strict::exp(-mean);
+    let mut k = 0;
+    let mut prod = normals.uniform();
+    while prod > limit {
+        k += 1;
+        prod *= normals.uniform();
+    }
+    k
+}
+
+// ────────────────────────────────────────────────────────────────
+// Gamma(shape, 1) — Marsaglia–Tsang squeeze; shape < 1 via the
+// boost Gamma(k) = Gamma(k + 1)·U^{1/k}
+// ────────────────────────────────────────────────────────────────
+pub fn sample_gamma(normals: &mut NormalSampler, shape: f64) -> f64 {
+    if shape < 1.0 {
+        let u = normals.uniform();
+        return sample_gamma(normals, shape + 1.0) * strict::powf(u, 1.0 / shape);
+    }
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let z = normals.sample();
+        let v = (1.0 + c * z).powi(3);
+        if v <= 0.0 {
+            continue;
+        }
+        let u = normals.uniform();
+        if strict::ln(u) < 0.5 * z * z + d - d * v + d * strict::ln(v) {
+            return d * v;
+        }
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// BrownianBridge — coarse-to-fine construction on a uniform grid
+// (Jäckel's indexing). Draw 0 fixes W at the horizon, draw 1 the
+// midpoint, then successive bisections, so the leading (best
+// distributed) QMC dimensions carry most of the path's variance.
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrownianBridge {
+    bridge_index: Vec<usize>,
+    left_index: Vec<usize>,
+    right_index: Vec<usize>,
+    left_weight: Vec<f64>,
+    right_weight: Vec<f64>,
+    std_dev: Vec<f64>,
+}
+
+impl BrownianBridge {
+    pub fn new(steps: usize) -> Self {
+        let m = steps;
+        let t = |k: usize| (k + 1) as f64; // unit-spaced grid
+        let mut map = vec![0_usize; m];
+        let mut b = BrownianBridge {
+            bridge_index: vec![0; m],
+            left_index: vec![0; m],
+            right_index: vec![0; m],
+            left_weight: vec![0.0; m],
+            right_weight: vec![0.0; m],
+            std_dev: vec![0.0; m],
+        };
+        if m == 0 {
+            return b;
+        }
+
+        map[m - 1] = 1;
+        b.bridge_index[0] = m - 1;
+        b.std_dev[0] = t(m - 1).sqrt();
+
+        let mut j = 0;
+        for i in 1..m {
+            while map[j] != 0 {
+                j += 1;
+            }
+            let mut k = j;
+            while map[k] == 0 {
+                k += 1;
+            }
+            // map[j..k] unpopulated, map[k] populated
+            let l = j + ((k - 1 - j) >> 1);
+            map[l] = i;
+            b.bridge_index[i] = l;
+            b.left_index[i] = j;
+            b.right_index[i] = k;
+            let t_left = if j == 0 { 0.0 } else { t(j - 1) };
+            let span = t(k) - t_left;
+            b.left_weight[i] = (t(k) - t(l)) / span;
+            b.right_weight[i] = (t(l) - t_left) / span;
+            b.std_dev[i] = ((t(l) - t_left) * (t(k) - t(l)) / span).sqrt();
+            j = k + 1;
+            if j >= m {
+                j = 0;
+            }
+        }
+        b
+    }
+
+    /// Map `steps` iid N(0,1) draws (bridge order) to `steps` iid N(0,1)
+    /// per-step increments (time order). Same law, different ordering
+    /// of which draw controls which feature of the path.
+    pub fn increments(&self, z: &[f64], out: &mut [f64]) {
+        let m = self.std_dev.len();
+        if m == 0 {
+            return;
+        }
+        // Build W on the grid in `out`, then difference in place
+        out[m - 1] = self.std_dev[0] * z[0];
+        for (i, &zi) in z.iter().enumerate().take(m).skip(1) {
+            let (j, k, l) = (self.left_index[i], self.right_index[i], self.bridge_index[i]);
+            let left = if j == 0 { 0.0 } else { self.left_weight[i] * out[j - 1] };
+            out[l] = left + self.right_weight[i] * out[k] + self.std_dev[i] * zi;
+        }
+        for k in (1..m).rev() {
+            out[k] -= out[k - 1];
+        }
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// SimConfig — everything about a run except the market itself
+// ────────────────────────────────────────────────────────────────
+
+/// Merton jump component: per asset per step, N ~ Poisson(λ·dt) jumps,
+/// each adding J ~ N(μ_J, σ_J²) to the log-return. Like the GPU kernel,
+/// the drift is not compensated, so jumps shift the mean. SimConfig holds
+/// either one set broadcast to every asset or one per asset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JumpParams {
+    pub lambda: f64,
+    pub mean: f64,
+    pub vol: f64,
+}
+
+/// Systemic (market-wide) jumps: one Poisson(λ·dt) count per step shared
+/// by every asset, with a common log-jump J ~ N(k·μ_J, k·σ_J²) hitting
+/// asset i as β_i·J. Uncompensated, like JumpParams, and independent of
+/// any idiosyncratic jumps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SystemicJumps {
+    pub lambda: f64,
+    pub mean: f64,
+    pub vol: f64,
+    /// Per-asset jump beta (N)
+    pub betas: Vec<f64>,
+}
+
+/// Hawkes self-excitation for every jump process (each asset's own and
+/// the systemic one): intensity λ_t = λ + Σ_{t_k < t} α·e^{−β(t − t_k)},
+/// with the process's JumpParams / SystemicJumps λ as baseline. On the
+/// step grid the excess decays by e^{−β·dt} and gains α per jump, so a
+/// jump raises the odds of more from the next step on. Needs α < β
+/// (stationary mean intensity λ / (1 − α/β)).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HawkesParams {
+    pub excitation: f64,
+    pub decay: f64,
+}
+
+/// Vasicek short rate for a "rates" asset: dr = κ(θ − r)·dt + σ_r·dW,
+/// stepped exactly. The asset's price is a bond of modified duration D
+/// and convexity C held at constant maturity:
+///     ΔP/P = r·dt − D·Δr + ½·C·Δr²
+/// dW is the asset's correlated shock, so its vol only matters through
+/// the correlation (any positive value); σ_r is in rate units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShortRateParams {
+    pub kappa: f64,
+    pub theta: f64,
+    pub sigma: f64,
+    pub r0: f64,
+    pub duration: f64,
+    pub convexity: f64,
+}
+
+/// Heston variance for one asset: dv = κ(θ − v)·dt + ξ·√v·dW^v,
+/// corr(dW^v, dW^S) = ρ. v₀ is the asset's (shocked) σ².
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HestonParams {
+    pub kappa: f64,
+    pub theta: f64,
+    pub xi: f64,
+    pub rho: f64,
+}
+
+/// GARCH(1,1) for one asset, in per-step variance units:
+/// h_{t+1} = ω + α·ε_t² + β·h_t, with h₀ = σ²·dt and ε_t the step's
+/// shock. Long-run per-step variance is ω / (1 − α − β).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GarchParams {
+    pub omega: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+/// Variance Gamma for one asset: X = θ·G + σ·√G·W over a Gamma
+/// subordinator with E[G] = dt, Var[G] = ν·dt. σ is the asset's vol.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VarianceGammaParams {
+    pub theta: f64,
+    pub nu: f64,
+}
+
+/// Normal Inverse Gaussian for one asset: as Variance Gamma but with an
+/// inverse-Gaussian subordinator, E[G] = dt, Var[G] = κ·dt.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NigParams {
+    pub theta: f64,
+    pub kappa: f64,
+}
+
+/// Per-asset local-vol grid σ_i(t, S): knots in time (years) and,
+/// optionally, in spot level S/S₀, interpolated bilinearly with flat
+/// extrapolation beyond the edges. With no moneyness knots it is a
+/// pure term structure of vol.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalVolSurface {
+    times: Vec<f64>,
+    moneyness: Vec<f64>,
+    /// N × times × max(moneyness, 1), row-major
+    vols: Vec<f64>,
+}
+
+impl LocalVolSurface {
+    pub fn new(n: usize, times: Vec<f64>, moneyness: Vec<f64>, vols: Vec<f64>) -> Result<Self, &'static str> {
+        let ascending = |xs: &[f64]| xs.windows(2).all(|w| w[0] < w[1]) && xs.iter().all(|x| x.is_finite());
+        if times.is_empty() || !ascending(&times) || !ascending(&moneyness) {
+            return Err("Local vol input invalid: time and moneyness knots must be finite and strictly increasing");
+        }
+        if vols.len() != n * times.len() * moneyness.len().max(1) {
+            return Err("Local vol input mismatch: expected N × times × moneyness vols");
+        }
+        if vols.iter().any(|v| !(v.is_finite() && *v >= 0.0)) {
+            return Err("Local vol input invalid: vols must be non-negative");
+        }
+        Ok(LocalVolSurface { times, moneyness, vols })
+    }
+
+    pub fn num_assets(&self) -> usize {
+        self.vols.len() / (self.times.len() * self.moneyness.len().max(1))
+    }
+
+    /// Apply a shock's per-asset vol multiplier to the whole surface.
+    pub fn scale(&mut self, multiplier: &DVector<f64>) {
+        let per_asset = self.times.len() * self.moneyness.len().max(1);
+        for (chunk, m) in self.vols.chunks_exact_mut(per_asset).zip(multiplier.iter()) {
+            chunk.iter_mut().for_each(|v| *v *= m);
+        }
+    }
+
+    /// σ for `asset` at time t and spot level s (S₀ = 1).
+    pub fn vol(&self, asset: usize, t: f64, s: f64) -> f64 {
+        let cols = self.moneyness.len().max(1);
+        let grid = &self.vols[asset * self.times.len() * cols..(asset + 1) * self.times.len() * cols];
+        let (t0, t1, wt) = bracket(&self.times, t);
+        let (m0, m1, wm) = if self.moneyness.is_empty() { (0, 0, 0.0) } else { bracket(&self.moneyness, s) };
+        let at = |r: usize| grid[r * cols + m0] * (1.0 - wm) + grid[r * cols + m1] * wm;
+        at(t0) * (1.0 - wt) + at(t1) * wt
+    }
+}
+
+/// Knot indices around x and the weight on the upper one (flat outside).
+fn bracket(knots: &[f64], x: f64) -> (usize, usize, f64) {
+    let last = knots.len() - 1;
+    if x <= knots[0] {
+        return (0, 0, 0.0);
+    }
+    if x >= knots[last] {
+        return (last, last, 0.0);
+    }
+    let hi = knots.partition_point(|&k| k <= x);
+    let lo = hi - 1;
+    (lo, hi, (x - knots[lo]) / (knots[hi] - knots[lo]))
+}
+
+/// Piecewise-constant drift and vol: bucket b covers steps starting in
+/// [ends[b−1], ends[b]) years and uses row b of `drift` / `vol` (B × N);
+/// steps past the last end stay in the last bucket. The Cholesky
+/// factor's correlation is kept and rescaled to each bucket's vols.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TermStructure {
+    pub ends: Vec<f64>,
+    pub drift: DMatrix<f64>,
+    pub vol: DMatrix<f64>,
+}
+
+impl TermStructure {
+    pub(crate) fn bucket(&self, t: f64) -> usize {
+        self.ends.partition_point(|&e| e <= t).min(self.ends.len() - 1)
+    }
+}
+
+/// How each asset's instantaneous volatility evolves over the horizon.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum VolModel {
+    /// σ fixed at the shocked level (plain GBM)
+    #[default]
+    Constant,
+    /// Per-asset CIR variance, full-truncation Euler
+    Heston(Vec<HestonParams>),
+    /// Per-asset conditional variance driven by simulated shocks
+    Garch(Vec<GarchParams>),
+    /// Pure-jump Lévy returns: per-asset independent subordinators over
+    /// the Cholesky-correlated Brownian part, martingale-corrected so
+    /// E[S_T] = e^{μT}
+    VarianceGamma(Vec<VarianceGammaParams>),
+    Nig(Vec<NigParams>),
+    /// σ read from a local-vol grid at the start of each step (the
+    /// asset's own vol keeps setting the correlation structure)
+    Local(LocalVolSurface),
+}
+
+impl VolModel {
+    /// Every variant's id, in declaration order
+    pub const IDS: [&'static str; 6] = ["constant", "heston", "garch", "variance_gamma", "nig", "local"];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            VolModel::Constant => "constant",
+            VolModel::Heston(_) => "heston",
+            VolModel::Garch(_) => "garch",
+            VolModel::VarianceGamma(_) => "variance_gamma",
+            VolModel::Nig(_) => "nig",
+            VolModel::Local(_) => "local",
+        }
+    }
+}
+
+/// Distribution of the per-step shocks, applied to X = L·Z after the
+/// Cholesky step and scaled to unit variance so Σ is unchanged.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Innovations {
+    #[default]
+    Gaussian,
+    /// Multivariate t: X·√((ν−2)/W), W ~ χ²_ν shared across assets, so
+    /// the correlation is preserved exactly and tails are joint. ν > 2.
+    StudentT { nu: f64 },
+    /// Student-t followed by a two-piece scaling per asset (×γ above
+    /// zero, ÷γ below), re-standardized. γ < 1 fattens the downside.
+    /// Dependence is kept monotone; linear correlation shifts slightly
+    /// for strong skew.
+    SkewT { nu: f64, gamma: f64 },
+}
+
+impl Innovations {
+    /// Every variant's id, in declaration order
+    pub const IDS: [&'static str; 3] = ["gaussian", "student_t", "skew_t"];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Innovations::Gaussian => "gaussian",
+            Innovations::StudentT { .. } => "student_t",
+            Innovations::SkewT { .. } => "skew_t",
+        }
+    }
+
+    fn nu(&self) -> Option<f64> {
+        match *self {
+            Innovations::Gaussian => None,
+            Innovations::StudentT { nu } | Innovations::SkewT { nu, .. } => Some(nu),
+        }
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Inverse Gaussian(mean, shape) — Michael, Schucany & Haas (1976)
+// ────────────────────────────────────────────────────────────────
+pub fn sample_inverse_gaussian(normals: &mut NormalSampler, mean: f64, shape: f64) -> f64 {
+    let nu = normals.sample();
+    let y = nu * nu;
+    let m = mean;
+    let x = m + m * m * y / (2.0 * shape) - m / (2.0 * shape) * (4.0 * m * shape * y + m * m * y * y).sqrt();
+    if normals.uniform() <= m / (m + x) {
+        x
+    } else {
+        m * m / x
+    }
+}
+
+/// E|T| for a unit-variance Student-t with ν > 2 degrees of freedom
+fn unit_t_abs_mean(nu: f64) -> f64 {
+    use crate::math::ln_gamma;
+    let ln_ratio = ln_gamma((nu + 1.0) / 2.0) - ln_gamma(nu / 2.0);
+    2.0 * (nu - 2.0).sqrt() * strict::exp(ln_ratio) / (std::f64::consts::PI.sqrt() * (nu - 1.0))
+}
+
+/// Source of the Gaussian diffusion draws Z. Auxiliary draws (jump
+/// counts and sizes, t mixing, Heston vol noise, regime moves) always
+/// come from the pseudo-random stream.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Driver {
+    #[default]
+    PseudoRandom,
+    /// Digitally shifted Sobol, one point of dimension steps × N per
+    /// path (path p uses point p); requires steps × N ≤ SOBOL_MAX_DIM (53).
+    Sobol,
+    /// Pseudo-random, except the terminal Brownian value along Σ's first
+    /// principal component is split into n_paths equiprobable strata,
+    /// one per path; the orthogonal complement keeps independent draws.
+    /// Always builds paths with the Brownian bridge so that value is
+    /// drawn first. With regimes the component is the first regime's.
+    Stratified,
+}
+
+impl Driver {
+    pub const ALL: [Driver; 3] = [Driver::PseudoRandom, Driver::Sobol, Driver::Stratified];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Driver::PseudoRandom => "pseudo_random",
+            Driver::Sobol => "sobol",
+            Driver::Stratified => "stratified",
+        }
+    }
+}
+
+/// Order in which the Gaussian draws build each path.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathConstruction {
+    /// Draw k drives step k
+    #[default]
+    Incremental,
+    /// Brownian bridge: per asset, draw 0 sets the terminal value, then
+    /// midpoints. With the Sobol driver, dimension d = level·N + asset.
+    BrownianBridge,
+}
+
+/// Batch-level correction of the diffusion draws, applied across the
+/// paths of one call (or one PathStream chunk) before L is applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MomentMatching {
+    #[default]
+    None,
+    /// Each (step, asset) draw: sample mean 0, sample variance 1
+    MeanVariance,
+    /// Additionally each step's N-vector has sample covariance I, so
+    /// the realized shock correlation equals the target exactly
+    Full,
+}
+
+/// Memory order of the returned path buffer (S₀ row included).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputLayout {
+    /// [path][step][asset]
+    #[default]
+    Interleaved,
+    /// [asset][path][step] — one contiguous series per (asset, path)
+    Planar,
+}
+
+/// Reproducibility contract: for a fixed config (seed included) and
+/// market, every simulate_* function returns bit-identical output on
+/// every run of the same build — all draws come from per-path streams
+/// of the selected RngKind keyed on (seed, path), consumed in a fixed
+/// order, and nothing reads the clock or OS entropy. Across platforms
+/// and builds the libm transcendentals and SIMD sums may differ in the
+/// last ulp unless strict mode is on (see strict.rs). Path p also does
+/// not depend on n_paths, so a larger run extends a smaller one, except
+/// under Driver::Stratified (strata are sized by n_paths) or moment
+/// matching (which rescales across the whole batch).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimConfig {
+    pub horizon: f64,
+    pub steps: usize,
+    pub n_paths: usize,
+    pub seed: u64,
+    /// Idiosyncratic jumps: length 1 (broadcast) or N
+    pub jumps: Option<Vec<JumpParams>>,
+    pub systemic_jumps: Option<SystemicJumps>,
+    pub hawkes: Option<HawkesParams>,
+    pub term_structure: Option<TermStructure>,
+    /// Per-asset continuous dividend / carry yield q: price paths drift
+    /// at μ − q; total_return_paths adds it back
+    pub dividend_yield: Option<Vec<f64>>,
+    /// Per asset: Some for rates assets simulated in rate space (these
+    /// ignore drift, dividend yield and vol_model), None for the rest
+    pub short_rates: Option<Vec<Option<ShortRateParams>>>,
+    pub vol_model: VolModel,
+    pub innovations: Innovations,
+    pub driver: Driver,
+    pub construction: PathConstruction,
+    pub rng: RngKind,
+    pub layout: OutputLayout,
+    pub moment_matching: MomentMatching,
+    /// Importance sampling: per-asset annualized log-drift shift δ the
+    /// paths are simulated under (see simulate_weighted_paths)
+    pub tilt: Option<Vec<f64>>,
+}
+
+impl SimConfig {
+    pub fn new(horizon: f64, steps: usize, n_paths: usize, seed: u64) -> Self {
+        SimConfig {
+            horizon,
+            steps,
+            n_paths,
+            seed,
+            jumps: None,
+            systemic_jumps: None,
+            hawkes: None,
+            term_structure: None,
+            dividend_yield: None,
+            short_rates: None,
+            vol_model: VolModel::Constant,
+            innovations: Innovations::Gaussian,
+            driver: Driver::PseudoRandom,
+            construction: PathConstruction::Incremental,
+            rng: RngKind::Pcg32,
+            layout: OutputLayout::Interleaved,
+            moment_matching: MomentMatching::None,
+            tilt: None,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The same jump parameters for every asset
+    pub fn with_jumps(mut self, jumps: JumpParams) -> Self {
+        self.jumps = Some(vec![jumps]);
+        self
+    }
+
+    /// One set of jump parameters per asset
+    pub fn with_asset_jumps(mut self, jumps: Vec<JumpParams>) -> Self {
+        self.jumps = Some(jumps);
+        self
+    }
+
+    pub fn with_systemic_jumps(mut self, jumps: SystemicJumps) -> Self {
+        self.systemic_jumps = Some(jumps);
+        self
+    }
+
+    pub fn with_hawkes(mut self, hawkes: HawkesParams) -> Self {
+        self.hawkes = Some(hawkes);
+        self
+    }
+
+    pub fn with_term_structure(mut self, term: TermStructure) -> Self {
+        self.term_structure = Some(term);
+        self
+    }
+
+    pub fn with_dividend_yield(mut self, yields: Vec<f64>) -> Self {
+        self.dividend_yield = Some(yields);
+        self
+    }
+
+    pub fn with_short_rates(mut self, rates: Vec<Option<ShortRateParams>>) -> Self {
+        self.short_rates = Some(rates);
+        self
+    }
+
+    pub fn with_vol_model(mut self, vol_model: VolModel) -> Self {
+        self.vol_model = vol_model;
+        self
+    }
+
+    pub fn with_innovations(mut self, innovations: Innovations) -> Self {
+        self.innovations = innovations;
+        self
+    }
+
+    pub fn with_driver(mut self, driver: Driver) -> Self {
+        self.driver = driver;
+        self
+    }
+
+    pub fn with_construction(mut self, construction: PathConstruction) -> Self {
+        self.construction = construction;
+        self
+    }
+
+    pub fn with_rng(mut self, rng: RngKind) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    pub fn with_layout(mut self, layout: OutputLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_moment_matching(mut self, moment_matching: MomentMatching) -> Self {
+        self.moment_matching = moment_matching;
+        self
+    }
+
+    pub fn with_tilt(mut self, drift_shift: Vec<f64>) -> Self {
+        self.tilt = Some(drift_shift);
+        self
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// simulate_paths
+// Output: n_paths × (steps + 1) × N prices, interleaved
+// [path][step][asset] unless config.layout is Planar, every asset
+// starting at S₀ = 1.
+// ────────────────────────────────────────────────────────────────
+pub fn simulate_paths(
+    drift: &DVector<f64>,
+    vol: &DVector<f64>,
+    cholesky_l: &DMatrix<f64>,
+    config: &SimConfig,
+) -> Result<Vec<f32>, &'static str> {
+    if config.tilt.is_some() {
+        return Err("Simulation input invalid: importance tilt requires simulate_weighted_paths");
+    }
+    let market = Prepared::new(drift, vol, cholesky_l, config)?;
+    validate_config(config, drift.len(), std::slice::from_ref(&market))?;
+    Ok(run_paths(std::slice::from_ref(&market), config, None, 0..config.n_paths).paths)
+}
+
+// ────────────────────────────────────────────────────────────────
+// Total return — price paths with the carry reinvested
+// TR_t = S_t·e^{q·t}, in whichever layout the prices were produced.
+// ────────────────────────────────────────────────────────────────
+pub fn total_return_paths(prices: &[f32], n: usize, config: &SimConfig) -> Result<Vec<f32>, &'static str> {
+    let rows = config.steps + 1;
+    if prices.len() != config.n_paths * rows * n {
+        return Err("Simulation input mismatch: price buffer does not match the config");
+    }
+    let q = match &config.dividend_yield {
+        None => return Ok(prices.to_vec()),
+        Some(q) if q.len() != n => return Err("Simulation input mismatch: dividend yield needs one value per asset"),
+        Some(q) => q,
+    };
+    let dt = config.horizon / config.steps as f64;
+    let mut out = prices.to_vec();
+    for (idx, x) in out.iter_mut().enumerate() {
+        let (step, asset) = match config.layout {
+            OutputLayout::Interleaved => ((idx / n) % rows, idx % n),
+            OutputLayout::Planar => (idx % rows, idx / (config.n_paths * rows)),
+        };
+        *x = (*x as f64 * strict::exp(q[asset] * step as f64 * dt)) as f32;
+    }
+    Ok(out)
+}
+
+// ────────────────────────────────────────────────────────────────
+// Importance sampling — exponentially tilted drift
+// Under the sampling measure each step's Z is shifted by a = θ·√dt with
+// L·θ = δ, so asset log drifts move by δ. Path p carries the likelihood
+// ratio w_p = Π_k exp(−a·Z'_k + |a|²/2), and E_P[f] = E_Q[w·f]: weight
+// every path statistic by w (E_Q[w] = 1).
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightedPaths {
+    pub paths: Vec<f32>,
+    /// One likelihood ratio per path (all 1 without a tilt)
+    pub weights: Vec<f64>,
+}
+
+pub fn simulate_weighted_paths(
+    drift: &DVector<f64>,
+    vol: &DVector<f64>,
+    cholesky_l: &DMatrix<f64>,
+    config: &SimConfig,
+) -> Result<WeightedPaths, &'static str> {
+    let market = Prepared::new(drift, vol, cholesky_l, config)?;
+    validate_config(config, drift.len(), std::slice::from_ref(&market))?;
+    let run = run_paths(std::slice::from_ref(&market), config, None, 0..config.n_paths);
+    let weights = match run.log_weights.is_empty() {
+        true => vec![1.0; config.n_paths],
+        false => run.log_weights.iter().map(|&lw| strict::exp(lw)).collect(),
+    };
+    Ok(WeightedPaths { paths: run.paths, weights })
+}
+
+// ────────────────────────────────────────────────────────────────
+// Regime switching
+// Each regime is a full Phase A output (drift, vol, L). Per path the
+// regime follows a Markov chain with per-step transition matrix P
+// (row r = probabilities of moving from r); the step from t to t+dt
+// uses the regime in force at t.
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug, PartialEq)]
+pub struct Regime {
+    pub drift: DVector<f64>,
+    pub vol: DVector<f64>,
+    pub cholesky_l: DMatrix<f64>,
+}
+
+/// Paths plus the regime index in force for each path and step
+/// (n_paths × steps, row-major).
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegimePaths {
+    pub paths: Vec<f32>,
+    pub regimes: Vec<u8>,
+}
+
+pub fn simulate_regime_paths(
+    regimes: &[Regime],
+    transition: &DMatrix<f64>,
+    initial_regime: usize,
+    config: &SimConfig,
+) -> Result<RegimePaths, &'static str> {
+    let k = regimes.len();
+    if k == 0 || k > u8::MAX as usize {
+        return Err("Regime input invalid: between 1 and 255 regimes are supported");
+    }
+    if transition.nrows() != k || transition.ncols() != k {
+        return Err("Regime input mismatch: transition matrix must be K×K");
+    }
+    for r in 0..k {
+        let row = transition.row(r);
+        if row.iter().any(|&p| !(0.0..=1.0).contains(&p)) || (row.sum() - 1.0).abs() > 1e-6 {
+            return Err("Regime input invalid: each transition row must be probabilities summing to 1");
+        }
+    }
+    if initial_regime >= k {
+        return Err("Regime input invalid: initial regime out of range");
+    }
+    if config.tilt.is_some() {
+        return Err("Regime input invalid: importance tilt is not supported with regime switching");
+    }
+    if k > 1 && !matches!(config.vol_model, VolModel::Constant) {
+        return Err("Regime input invalid: stochastic-vol and GARCH models are not supported with regime switching");
+    }
+
+    let n = regimes[0].drift.len();
+    let markets = regimes
+        .iter()
+        .map(|r| {
+            if r.drift.len() != n {
+                return Err("Regime input mismatch: all regimes must share the same N");
+            }
+            Prepared::new(&r.drift, &r.vol, &r.cholesky_l, config)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    validate_config(config, n, &markets)?;
+
+    let switching = Switching { transition, initial: initial_regime };
+    let run = run_paths(&markets, config, Some(&switching), 0..config.n_paths);
+    Ok(RegimePaths { paths: run.paths, regimes: run.regimes })
+}
+
+// ────────────────────────────────────────────────────────────────
+// PathStream — the simulate_paths run, handed out in batches
+// Chunks concatenate to exactly the one-shot output (same seed, same
+// paths), so callers can keep running statistics over runs that would
+// not fit in memory. Planar layout and moment matching apply within
+// each chunk (the latter breaks the concatenation identity).
+// ────────────────────────────────────────────────────────────────
+pub struct PathStream {
+    market: Prepared,
+    config: SimConfig,
+    next_path: usize,
+}
+
+impl PathStream {
+    pub fn new(
+        drift: &DVector<f64>,
+        vol: &DVector<f64>,
+        cholesky_l: &DMatrix<f64>,
+        config: &SimConfig,
+    ) -> Result<Self, &'static str> {
+        if config.tilt.is_some() {
+            return Err("Simulation input invalid: importance tilt requires simulate_weighted_paths");
+        }
+        let market = Prepared::new(drift, vol, cholesky_l, config)?;
+        validate_config(config, drift.len(), std::slice::from_ref(&market))?;
+        Ok(PathStream { market, config: config.clone(), next_path: 0 })
+    }
+
+    /// Paths not yet handed out (of config.n_paths)
+    pub fn remaining(&self) -> usize {
+        self.config.n_paths - self.next_path
+    }
+
+    /// Up to `n_paths` further paths; empty once the run is exhausted.
+    pub fn next_chunk(&mut self, n_paths: usize) -> Vec<f32> {
+        let end = self.config.n_paths.min(self.next_path.saturating_add(n_paths));
+        let range = self.next_path..end;
+        self.next_path = end;
+        run_paths(std::slice::from_ref(&self.market), &self.config, None, range).paths
+    }
+
+    /// Rewind to path 0 (replays the identical run)
+    pub fn reset(&mut self) {
+        self.next_path = 0;
+    }
+
+    /// Paths `range` of the run, independent of the cursor. Each path
+    /// is seeded from its index, so ranges can be generated on separate
+    /// threads and concatenated into the one-shot output.
+    pub fn paths(&self, range: Range<usize>) -> Vec<f32> {
+        let range = range.start.min(self.config.n_paths)..range.end.min(self.config.n_paths);
+        run_paths(std::slice::from_ref(&self.market), &self.config, None, range).paths
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Shared path kernel
+// ────────────────────────────────────────────────────────────────
+
+/// A market with its per-step constants precomputed for one config.
+struct Prepared {
+    /// Price drift μ − q
+    drift: DVector<f64>,
+    vol: DVector<f64>,
+    /// Rows of L's lower triangle packed end to end (row i at i(i+1)/2)
+    l_rows: Vec<f64>,
+    /// (μ − σ²/2)·dt
+    drift_dt: Vec<f64>,
+    /// √Σ_ii, to move between X_i and its unit-variance shock
+    sd: Vec<f64>,
+    /// Dividend / carry yield q (zeros when none)
+    carry: Vec<f64>,
+    /// Per-step Z shift a = θ·√dt solving L·θ = δ (importance tilt)
+    tilt_z: Option<DVector<f64>>,
+}
+
+impl Prepared {
+    fn new(
+        drift: &DVector<f64>,
+        vol: &DVector<f64>,
+        cholesky_l: &DMatrix<f64>,
+        config: &SimConfig,
+    ) -> Result<Self, &'static str> {
+        let n = drift.len();
+        if vol.len() != n || cholesky_l.nrows() != n || cholesky_l.ncols() != n {
+            return Err("Simulation input mismatch: drift, vol and Cholesky factor must agree on N");
+        }
+        let dt = config.horizon / config.steps.max(1) as f64;
+        let carry = match &config.dividend_yield {
+            None => vec![0.0; n],
+            Some(q) if q.len() != n => {
+                return Err("Simulation input mismatch: dividend yield needs one value per asset")
+            }
+            Some(q) if q.iter().any(|x| !x.is_finite()) => return Err("Simulation input invalid: dividend yield must be finite"),
+            Some(q) => q.clone(),
+        };
+        // Price paths drift at μ − q
+        let drift = DVector::from_iterator(n, drift.iter().zip(&carry).map(|(mu, q)| mu - q));
+        let tilt_z = match &config.tilt {
+            None => None,
+            Some(delta) if delta.len() != n => {
+                return Err("Simulation input mismatch: importance tilt needs one drift shift per asset")
+            }
+            Some(delta) if delta.iter().any(|d| !d.is_finite()) => {
+                return Err("Simulation input invalid: importance tilt must be finite")
+            }
+            Some(delta) => {
+                let theta = cholesky_l
+                    .solve_lower_triangular(&DVector::from_column_slice(delta))
+                    .filter(|t| t.iter().all(|x| x.is_finite()))
+                    .ok_or("Simulation input invalid: importance tilt needs a nonsingular Cholesky factor")?;
+                Some(theta * dt.sqrt())
+            }
+        };
+        Ok(Prepared {
+            drift_dt: (0..n).map(|i| (drift[i] - 0.5 * vol[i] * vol[i]) * dt).collect(),
+            drift,
+            vol: vol.clone(),
+            l_rows: (0..n).flat_map(|i| (0..=i).map(move |j| cholesky_l[(i, j)])).collect(),
+            sd: (0..n).map(|i| cholesky_l.row(i).norm()).collect(),
+            carry,
+            tilt_z,
+        })
+    }
+}
+
+struct Switching<'a> {
+    transition: &'a DMatrix<f64>,
+    initial: usize,
+}
+
+fn validate_config(config: &SimConfig, n: usize, markets: &[Prepared]) -> Result<(), &'static str> {
+    let SimConfig { horizon, steps, innovations, .. } = *config;
+    if steps == 0 {
+        return Err("Simulation input invalid: steps must be at least 1");
+    }
+    if !(horizon.is_finite() && horizon > 0.0) {
+        return Err("Simulation input invalid: horizon must be a positive number of years");
+    }
+    if let Some(jumps) = &config.jumps {
+        if jumps.len() != 1 && jumps.len() != n {
+            return Err("Simulation input mismatch: jump parameters must be a single set or one per asset");
+        }
+        if jumps.iter().any(|j| !(j.lambda >= 0.0 && j.vol >= 0.0 && j.mean.is_finite())) {
+            return Err("Simulation input invalid: jump intensity and jump vol must be non-negative");
+        }
+    }
+    if let Some(j) = &config.systemic_jumps {
+        if j.betas.len() != n {
+            return Err("Simulation input mismatch: systemic jumps need one beta per asset");
+        }
+        if !(j.lambda >= 0.0 && j.vol >= 0.0 && j.mean.is_finite() && j.betas.iter().all(|b| b.is_finite())) {
+            return Err("Simulation input invalid: systemic jump intensity and vol must be non-negative");
+        }
+    }
+    if let Some(h) = config.hawkes {
+        if !(h.excitation >= 0.0 && h.decay > 0.0 && h.excitation < h.decay && h.decay.is_finite()) {
+            return Err("Simulation input invalid: Hawkes needs 0 ≤ α < β for a stationary intensity");
+        }
+    }
+    if let Some(rates) = &config.short_rates {
+        if rates.len() != n {
+            return Err("Simulation input mismatch: short rates need one entry per asset");
+        }
+        let valid = |r: &ShortRateParams| {
+            r.kappa > 0.0
+                && r.sigma >= 0.0
+                && r.duration >= 0.0
+                && [r.kappa, r.theta, r.sigma, r.r0, r.duration, r.convexity].iter().all(|x| x.is_finite())
+        };
+        if !rates.iter().flatten().all(valid) {
+            return Err("Simulation input invalid: short rates need κ > 0 and σ_r, duration ≥ 0");
+        }
+    }
+    if let VolModel::Heston(params) = &config.vol_model {
+        if params.len() != n {
+            return Err("Simulation input mismatch: one Heston parameter set per asset is required");
+        }
+        if markets.iter().any(|m| m.vol.iter().any(|&v| v <= 0.0)) {
+            return Err("Simulation input invalid: Heston needs strictly positive initial vol");
+        }
+        if params.iter().any(|h| !(h.kappa >= 0.0 && h.theta >= 0.0 && h.xi >= 0.0 && h.rho.abs() <= 1.0)) {
+            return Err("Simulation input invalid: Heston needs κ, θ, ξ ≥ 0 and |ρ| ≤ 1");
+        }
+    }
+    if let VolModel::Garch(params) = &config.vol_model {
+        if params.len() != n {
+            return Err("Simulation input mismatch: one GARCH parameter set per asset is required");
+        }
+        if markets.iter().any(|m| m.vol.iter().any(|&v| v <= 0.0)) {
+            return Err("Simulation input invalid: GARCH needs strictly positive initial vol");
+        }
+        if params.iter().any(|g| !(g.omega > 0.0 && g.alpha >= 0.0 && g.beta >= 0.0 && g.alpha + g.beta < 1.0)) {
+            return Err("Simulation input invalid: GARCH needs ω > 0, α, β ≥ 0 and α + β < 1");
+        }
+    }
+    if let VolModel::VarianceGamma(params) = &config.vol_model {
+        if params.len() != n {
+            return Err("Simulation input mismatch: one Variance Gamma parameter set per asset is required");
+        }
+        let ok = |m: &Prepared| {
+            params.iter().zip(m.vol.iter()).all(|(g, s)| {
+                g.nu > 0.0 && g.theta.is_finite() && 1.0 - g.theta * g.nu - 0.5 * s * s * g.nu > 0.0
+            })
+        };
+        if !markets.iter().all(ok) {
+            return Err("Simulation input invalid: Variance Gamma needs ν > 0 and 1 − θν − σ²ν/2 > 0");
+        }
+    }
+    if let VolModel::Nig(params) = &config.vol_model {
+        if params.len() != n {
+            return Err("Simulation input mismatch: one NIG parameter set per asset is required");
+        }
+        let ok = |m: &Prepared| {
+            params.iter().zip(m.vol.iter()).all(|(g, s)| {
+                g.kappa > 0.0 && g.theta.is_finite() && 1.0 - 2.0 * g.theta * g.kappa - s * s * g.kappa > 0.0
+            })
+        };
+        if !markets.iter().all(ok) {
+            return Err("Simulation input invalid: NIG needs κ > 0 and 1 − 2θκ − σ²κ > 0");
+        }
+    }
+    if let Some(term) = &config.term_structure {
+        let b = term.ends.len();
+        if b == 0 || term.drift.shape() != (b, n) || term.vol.shape() != (b, n) {
+            return Err("Simulation input mismatch: term structure needs B ends and B×N drift and vol");
+        }
+        if !term.ends.windows(2).all(|w| w[0] < w[1]) || term.vol.iter().any(|v| !(v.is_finite() && *v >= 0.0)) {
+            return Err("Simulation input invalid: term structure ends must increase and vols be non-negative");
+        }
+        if config.vol_model != VolModel::Constant {
+            return Err("Simulation input invalid: term structure requires the constant vol model");
+        }
+    }
+    if let VolModel::Local(surface) = &config.vol_model {
+        if surface.num_assets() != n {
+            return Err("Simulation input mismatch: local vol surface must cover every asset");
+        }
+    }
+    if config.driver == Driver::Sobol {
+        if steps * n > SOBOL_MAX_DIM {
+            return Err("Simulation input invalid: Sobol driver supports at most 53 dimensions (steps × N)");
+        }
+        // Any other construction failure surfaces here, not as a silent
+        // fall back to pseudo-random draws in run_paths
+        SobolSequence::new(steps * n, config.seed)?;
+    }
+    if let Some(nu) = innovations.nu() {
+        if nu.is_nan() || nu <= 2.0 {
+            return Err("Simulation input invalid: Student-t innovations need ν > 2 for finite variance");
+        }
+    }
+    if let Innovations::SkewT { gamma, .. } = innovations {
+        if !(gamma > 0.0 && gamma.is_finite()) {
+            return Err("Simulation input invalid: skew-t γ must be positive");
+        }
+    }
+    Ok(())
+}
+
+/// Draw the next regime from row `current` of the transition matrix.
+fn next_regime(normals: &mut NormalSampler, transition: &DMatrix<f64>, current: usize) -> usize {
+    let u = normals.uniform();
+    let mut acc = 0.0;
+    for (r, &p) in transition.row(current).iter().enumerate() {
+        acc += p;
+        if u < acc {
+            return r;
+        }
+    }
+    current
+}
+
+/// Whole-path diffusion draws, time-major [step][asset], for drivers
+/// that must know them up front (QMC point or bridge reordering).
+struct UpfrontDraws {
+    sobol: Option<SobolSequence>,
+    bridge: Option<BrownianBridge>,
+    /// Unit u with L·u along Σ's top eigenvector, for Driver::Stratified
+    stratified: Option<DVector<f64>>,
+    n_paths: usize,
+    n: usize,
+    level: Vec<f64>,
+    incr: Vec<f64>,
+}
+
+impl UpfrontDraws {
+    fn new(config: &SimConfig, market: &Prepared) -> Option<Self> {
+        let (steps, n) = (config.steps, market.drift.len());
+        let sobol = match config.driver {
+            Driver::Sobol => {
+                Some(SobolSequence::new(steps * n, config.seed).expect("Sobol dimensions checked by validate_config"))
+            }
+            Driver::PseudoRandom | Driver::Stratified => None,
+        };
+        let stratified = (config.driver == Driver::Stratified).then(|| principal_direction(market));
+        let bridge = match config.construction {
+            _ if stratified.is_some() => Some(BrownianBridge::new(steps)),
+            PathConstruction::BrownianBridge => Some(BrownianBridge::new(steps)),
+            PathConstruction::Incremental => None,
+        };
+        (sobol.is_some() || bridge.is_some()).then(|| UpfrontDraws {
+            sobol,
+            bridge,
+            stratified,
+            n_paths: config.n_paths,
+            n,
+            level: vec![0.0; steps],
+            incr: vec![0.0; steps],
+        })
+    }
+
+    fn fill(&mut self, p: usize, normals: &mut NormalSampler, qmc: &mut [f64]) {
+        let n = self.n;
+        if let Some(seq) = &self.sobol {
+            seq.normal_point(p as u64, qmc);
+        } else {
+            qmc.iter_mut().for_each(|x| *x = normals.sample());
+        }
+        if let Some(dir) = &self.stratified {
+            // Level 0 (the terminal value) becomes ξ·u plus the draw's
+            // part orthogonal to u, ξ uniform within stratum p
+            let u = (p as f64 + normals.uniform()) / self.n_paths as f64;
+            let xi = crate::math::inv_norm_cdf(u);
+            let along = strict::dot(&qmc[..n], dir.as_slice());
+            for (z, d) in qmc[..n].iter_mut().zip(dir.iter()) {
+                *z += (xi - along) * d;
+            }
+        }
+        if let Some(bb) = &self.bridge {
+            // qmc is [level][asset] here; rewrite it as [step][asset]
+            for a in 0..n {
+                for (k, lv) in self.level.iter_mut().enumerate() {
+                    *lv = qmc[k * n + a];
+                }
+                bb.increments(&self.level, &mut self.incr);
+                for (k, dw) in self.incr.iter().enumerate() {
+                    qmc[k * n + a] = *dw;
+                }
+            }
+        }
+    }
+}
+
+/// u = Lᵀ·v/‖Lᵀ·v‖ for Σ = L·Lᵀ's top eigenvector v, so L·u = √λ·v:
+/// moving Z along u moves the returns along the first principal
+/// component. Signed so the component loads positively on the assets'
+/// sum; e₀ when Σ is zero.
+fn principal_direction(market: &Prepared) -> DVector<f64> {
+    let n = market.drift.len();
+    let l = DMatrix::from_fn(n, n, |i, j| if j <= i { market.l_rows[i * (i + 1) / 2 + j] } else { 0.0 });
+    let eigen = (&l * l.transpose()).symmetric_eigen();
+    let top = eigen.eigenvalues.imax();
+    let mut u = l.transpose() * eigen.eigenvectors.column(top);
+    if (&l * &u).sum() < 0.0 {
+        u.neg_mut();
+    }
+    match u.norm() {
+        norm if norm > 0.0 => u / norm,
+        _ => DVector::from_fn(n, |i, _| if i == 0 { 1.0 } else { 0.0 }),
+    }
+}
+
+/// Standardize a [path][dim] batch of draws per dimension to sample
+/// mean 0 and sample variance 1 (n − 1 denominator). With `full`, each
+/// consecutive group of `n` dims (one step's N-vector) is instead
+/// whitened by its sample covariance, so its sample covariance is
+/// exactly I; groups with too few paths or a singular sample fall back
+/// to per-dimension scaling.
+fn match_moments(draws: &mut [f64], count: usize, dims: usize, n: usize, full: bool) {
+    if count < 2 {
+        return;
+    }
+    let denom = (count - 1) as f64;
+    for g in (0..dims).step_by(n) {
+        let mut mean = DVector::zeros(n);
+        for row in draws.chunks_exact(dims) {
+            mean += DVector::from_column_slice(&row[g..g + n]);
+        }
+        mean /= count as f64;
+        let mut cov = DMatrix::zeros(n, n);
+        for row in draws.chunks_exact_mut(dims) {
+            for (x, m) in row[g..g + n].iter_mut().zip(mean.iter()) {
+                *x -= m;
+            }
+            let c = DVector::from_column_slice(&row[g..g + n]);
+            cov += &c * c.transpose();
+        }
+        cov /= denom;
+
+        let whitener = if full && count > n {
+            cov.clone().cholesky().and_then(|c| c.l().try_inverse())
+        } else {
+            None
+        };
+        match whitener {
+            Some(w) => {
+                for row in draws.chunks_exact_mut(dims) {
+                    let c = &w * DVector::from_column_slice(&row[g..g + n]);
+                    row[g..g + n].copy_from_slice(c.as_slice());
+                }
+            }
+            None => {
+                let scale: Vec<f64> = (0..n)
+                    .map(|i| if cov[(i, i)] > 0.0 { 1.0 / cov[(i, i)].sqrt() } else { 1.0 })
+                    .collect();
+                for row in draws.chunks_exact_mut(dims) {
+                    for (x, s) in row[g..g + n].iter_mut().zip(&scale) {
+                        *x *= s;
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct RunOutput {
+    paths: Vec<f32>,
+    /// [path][step]; empty without regime switching
+    regimes: Vec<u8>,
+    /// ln w per path; empty without an importance tilt
+    log_weights: Vec<f64>,
+}
+
+/// Simulate global path indices `range` of the run described by
+/// `config`; path p's draws depend only on (seed, p), so disjoint
+/// ranges concatenate to the full run (moment matching aside).
+fn run_paths(
+    markets: &[Prepared],
+    config: &SimConfig,
+    switching: Option<&Switching>,
+    range: Range<usize>,
+) -> RunOutput {
+    let SimConfig { horizon, steps, seed, innovations, .. } = *config;
+    let count = range.len();
+    let n = markets[0].drift.len();
+
+    // Two-piece standardization: E[Y] = ½(γ − 1/γ)·E|T|, E[Y²] = ½(γ² + 1/γ²)
+    let skew = match innovations {
+        Innovations::SkewT { nu, gamma } => {
+            let mean = 0.5 * (gamma - 1.0 / gamma) * unit_t_abs_mean(nu);
+            let var = 0.5 * (gamma * gamma + 1.0 / (gamma * gamma)) - mean * mean;
+            Some((gamma, mean, var.sqrt()))
+        }
+        _ => None,
+    };
+
+    let dt = horizon / steps as f64;
+    let sqrt_dt = dt.sqrt();
+
+    let stride = (steps + 1) * n;
+    let mut out = vec![0.0_f32; count * stride];
+    let mut regimes_out = vec![0_u8; if switching.is_some() { count * steps } else { 0 }];
+    let mut log_weights = vec![0.0; if config.tilt.is_some() { count } else { 0 }];
+    let mut z = vec![0.0; n];
+    let mut log_s = vec![0.0; n];
+    let mut var = vec![0.0; n];
+    let mut rate = vec![0.0; n];
+    // Hawkes excess intensity: one per asset, last slot systemic
+    let mut excess = vec![0.0; n + 1];
+    let (excite, retain) = config.hawkes.map_or((0.0, 0.0), |h| (h.excitation, strict::exp(-h.decay * dt)));
+    let mut upfront = UpfrontDraws::new(config, &markets[0]);
+    let whole_path = upfront.is_some() || config.moment_matching != MomentMatching::None;
+    let mut qmc = vec![0.0; if whole_path { steps * n } else { 0 }];
+
+    // Moment matching needs every path's draws before any path runs, so
+    // it keeps the whole batch and each path's sampler (for aux draws)
+    let mut batch = match config.moment_matching {
+        MomentMatching::None => None,
+        mode => {
+            let mut draws = vec![0.0; count * steps * n];
+            let mut samplers = Vec::with_capacity(count);
+            for (local, qmc) in draws.chunks_exact_mut(steps * n).enumerate() {
+                let p = range.start + local;
+                let mut normals = NormalSampler::new(PathRng::for_path(config.rng, seed, p));
+                match &mut upfront {
+                    Some(u) => u.fill(p, &mut normals, qmc),
+                    None => qmc.iter_mut().for_each(|x| *x = normals.sample()),
+                }
+                samplers.push(normals);
+            }
+            match_moments(&mut draws, count, steps * n, n, mode == MomentMatching::Full);
+            Some((draws, samplers.into_iter()))
+        }
+    };
+
+    for (local, path) in out.chunks_exact_mut(stride).enumerate() {
+        let p = range.start + local;
+        let mut normals = match &mut batch {
+            Some((draws, samplers)) => {
+                qmc.copy_from_slice(&draws[local * steps * n..(local + 1) * steps * n]);
+                samplers.next().expect("one sampler per path")
+            }
+            None => {
+                let mut normals = NormalSampler::new(PathRng::for_path(config.rng, seed, p));
+                if let Some(u) = &mut upfront {
+                    u.fill(p, &mut normals, &mut qmc);
+                }
+                normals
+            }
+        };
+        let mut regime = switching.map_or(0, |s| s.initial);
+        log_s.iter_mut().for_each(|x| *x = 0.0);
+        excess.iter_mut().for_each(|x| *x = 0.0);
+        // Heston tracks annualized v; GARCH tracks per-step h
+        let var_scale = if matches!(config.vol_model, VolModel::Garch(_)) { dt } else { 1.0 };
+        for (v, s) in var.iter_mut().zip(markets[regime].vol.iter()) {
+            *v = s * s * var_scale;
+        }
+        if let Some(rates) = &config.short_rates {
+            for (r, params) in rate.iter_mut().zip(rates) {
+                *r = params.map_or(0.0, |sr| sr.r0);
+            }
+        }
+        path[..n].iter_mut().for_each(|x| *x = 1.0);
+
+        for step in 1..=steps {
+            let m = &markets[regime];
+            if !qmc.is_empty() {
+                z.copy_from_slice(&qmc[(step - 1) * n..step * n]);
+            } else {
+                for zi in z.iter_mut() {
+                    *zi = normals.sample();
+                }
+            }
+            if let Some(a) = &m.tilt_z {
+                // Z' = Z + a;  ln dP/dQ = −a·Z' + |a|²/2
+                let mut dot = 0.0;
+                for (zi, ai) in z.iter_mut().zip(a.iter()) {
+                    *zi += ai;
+                    dot += ai * *zi;
+                }
+                log_weights[local] += 0.5 * a.norm_squared() - dot;
+            }
+            // Shared χ² mixing for the t family: √((ν−2)/W), W ~ 2·Gamma(ν/2)
+            let mix = match innovations.nu() {
+                Some(nu) => ((nu - 2.0) / (2.0 * sample_gamma(&mut normals, nu / 2.0))).sqrt(),
+                None => 1.0,
+            };
+            // One market-wide jump for the step, loaded by β_i below
+            let systemic = match &config.systemic_jumps {
+                Some(j) => {
+                    let k = sample_poisson(&mut normals, (j.lambda + excess[n]) * dt);
+                    excess[n] = excess[n] * retain + excite * k as f64;
+                    match k {
+                        0 => 0.0,
+                        k => k as f64 * j.mean + (k as f64).sqrt() * j.vol * normals.sample(),
+                    }
+                }
+                None => 0.0,
+            };
+            let row = &mut path[step * n..(step + 1) * n];
+            for i in 0..n {
+                // X_i = Σ_{j≤i} L[i,j]·Z_j  (L lower-triangular)
+                let start = i * (i + 1) / 2;
+                let mut x = crate::simd::dot(&m.l_rows[start..start + i + 1], &z);
+                x *= mix;
+                if let Some((gamma, mean, std)) = skew {
+                    if m.sd[i] > 0.0 {
+                        let u = x / m.sd[i];
+                        let y = if u >= 0.0 { u * gamma } else { u / gamma };
+                        x = m.sd[i] * (y - mean) / std;
+                    }
+                }
+
+                let short_rate = config.short_rates.as_ref().and_then(|r| r[i]);
+                if let Some(sr) = short_rate {
+                    // Exact OU step; ln(1 + ΔP/P) keeps the price positive
+                    let w = if m.sd[i] > 0.0 { x / m.sd[i] } else { 0.0 };
+                    let decay = strict::exp(-sr.kappa * dt);
+                    let spread = sr.sigma * ((1.0 - decay * decay) / (2.0 * sr.kappa)).sqrt();
+                    let next = sr.theta + (rate[i] - sr.theta) * decay + spread * w;
+                    let dr = next - rate[i];
+                    let ret = rate[i] * dt - sr.duration * dr + 0.5 * sr.convexity * dr * dr;
+                    log_s[i] += strict::ln_1p(ret.max(f64::EPSILON - 1.0));
+                    rate[i] = next;
+                } else {
+                    match &config.vol_model {
+                        VolModel::Constant => match &config.term_structure {
+                            Some(term) => {
+                                let b = term.bucket((step - 1) as f64 * dt);
+                                let (mu, sigma) = (term.drift[(b, i)] - m.carry[i], term.vol[(b, i)]);
+                                let w = if m.sd[i] > 0.0 { x / m.sd[i] } else { 0.0 };
+                                log_s[i] += (mu - 0.5 * sigma * sigma) * dt + sigma * sqrt_dt * w;
+                            }
+                            None => log_s[i] += m.drift_dt[i] + sqrt_dt * x,
+                        },
+                        VolModel::Heston(params) => {
+                            // Full truncation: v⁺ = max(v, 0) in drift and diffusion
+                            let h = params[i];
+                            let w = x / m.vol[i]; // unit-variance, still correlated
+                            let v_pos = var[i].max(0.0);
+                            let z_v = h.rho * w + (1.0 - h.rho * h.rho).sqrt() * normals.sample();
+                            log_s[i] += (m.drift[i] - 0.5 * v_pos) * dt + (v_pos * dt).sqrt() * w;
+                            var[i] += h.kappa * (h.theta - v_pos) * dt + h.xi * (v_pos * dt).sqrt() * z_v;
+                        }
+                        VolModel::Garch(params) => {
+                            let g = params[i];
+                            let eps = var[i].sqrt() * (x / m.vol[i]);
+                            log_s[i] += m.drift[i] * dt - 0.5 * var[i] + eps;
+                            var[i] = g.omega + g.alpha * eps * eps + g.beta * var[i];
+                        }
+                        VolModel::Local(surface) => {
+                            let t = (step - 1) as f64 * dt;
+                            let sigma = surface.vol(i, t, strict::exp(log_s[i]));
+                            let w = if m.sd[i] > 0.0 { x / m.sd[i] } else { 0.0 };
+                            log_s[i] += (m.drift[i] - 0.5 * sigma * sigma) * dt + sigma * sqrt_dt * w;
+                        }
+                        VolModel::VarianceGamma(params) => {
+                            // E[e^X] = (1 − θν − σ²ν/2)^{−dt/ν}
+                            let g = params[i];
+                            let s = m.vol[i];
+                            let w = if s > 0.0 { x / s } else { 0.0 };
+                            let sub = g.nu * sample_gamma(&mut normals, dt / g.nu);
+                            let omega = strict::ln(1.0 - g.theta * g.nu - 0.5 * s * s * g.nu) / g.nu;
+                            log_s[i] += (m.drift[i] + omega) * dt + g.theta * sub + s * sub.sqrt() * w;
+                        }
+                        VolModel::Nig(params) => {
+                            // E[e^X] = exp((dt/κ)·(1 − √(1 − 2θκ − σ²κ)))
+                            let g = params[i];
+                            let s = m.vol[i];
+                            let w = if s > 0.0 { x / s } else { 0.0 };
+                            let sub = sample_inverse_gaussian(&mut normals, dt, dt * dt / g.kappa);
+                            let omega = ((1.0 - 2.0 * g.theta * g.kappa - s * s * g.kappa).sqrt() - 1.0) / g.kappa;
+                            log_s[i] += (m.drift[i] + omega) * dt + g.theta * sub + s * sub.sqrt() * w;
+                        }
+                    }
+                }
+
+                // Σ of k jumps ~ N(k·μ_J, k·σ_J²)
+                if let Some(jumps) = &config.jumps {
+                    let j = jumps[if jumps.len() == 1 { 0 } else { i }];
+                    let k = sample_poisson(&mut normals, (j.lambda + excess[i]) * dt);
+                    excess[i] = excess[i] * retain + excite * k as f64;
+                    if k > 0 {
+                        let kf = k as f64;
+                        log_s[i] += kf * j.mean + kf.sqrt() * j.vol * normals.sample();
+                    }
+                }
+                if let Some(j) = &config.systemic_jumps {
+                    log_s[i] += j.betas[i] * systemic;
+                }
+
+                row[i] = strict::exp(log_s[i]) as f32;
+            }
+
+            if let Some(sw) = switching {
+                regimes_out[local * steps + step - 1] = regime as u8;
+                regime = next_regime(&mut normals, sw.transition, regime);
+            }
+        }
+    }
+
+    if config.layout == OutputLayout::Planar {
+        out = to_planar(&out, count, steps + 1, n);
+    }
+    RunOutput { paths: out, regimes: regimes_out, log_weights }
+}
+
+/// Transpose an interleaved [path][step][asset] buffer to planar
+/// [asset][path][step].
+pub fn to_planar(interleaved: &[f32], n_paths: usize, rows: usize, n: usize) -> Vec<f32> {
+    let mut planar = vec![0.0_f32; interleaved.len()];
+    for (p, path) in interleaved.chunks_exact(rows * n).enumerate() {
+        for (t, row) in path.chunks_exact(n).enumerate() {
+            for (a, &x) in row.iter().enumerate() {
+                planar[(a * n_paths + p) * rows + t] = x;
+            }
+        }
+    }
+    planar
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::inv_norm_cdf;
+    use approx::assert_relative_eq;
+
+    fn two_asset_market() -> (DVector<f64>, DVector<f64>, DMatrix<f64>) {
+        let drift = DVector::from_vec(vec![0.08, 0.03]);
+        let vol = DVector::from_vec(vec![0.2, 0.05]);
+        let r = DMatrix::from_row_slice(2, 2, &[1.0, 0.5, 0.5, 1.0]);
+        let cov = crate::math::rebuild_covariance(&vol, &r);
+        let l = crate::math::cholesky_decompose(&cov).unwrap();
+        (drift, vol, l)
+    }
+
+    #[test]
+    fn test_pcg32_matches_reference() {
+        // pcg32-demo: pcg32_srandom_r(42, 54)
+        let mut rng = Pcg32::new(42, 54);
+        let first: Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+        assert_eq!(first, vec![0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e]);
+    }
+
+    #[test]
+    fn test_simulate_paths_shape_and_start() {
+        let (drift, vol, l) = two_asset_market();
+        let out = simulate_paths(&drift, &vol, &l, &SimConfig::new(1.0, 12, 5, 42)).unwrap();
+        assert_eq!(out.len(), 5 * 13 * 2);
+        for p in 0..5 {
+            assert_eq!(out[p * 26], 1.0);
+            assert_eq!(out[p * 26 + 1], 1.0);
+        }
+        assert!(out.iter().all(|x| x.is_finite() && *x > 0.0));
+    }
+
+    #[test]
+    fn test_simulate_paths_terminal_moments() {
+        let (drift, vol, l) = two_asset_market();
+        let n_paths = 20_000;
+        let out = simulate_paths(&drift, &vol, &l, &SimConfig::new(1.0, 4, n_paths, 7)).unwrap();
+        let stride = 5 * 2;
+
+        // E[S_T] = e^{μT}
+        let mean0: f64 = (0..n_paths).map(|p| out[p * stride + 8] as f64).sum::<f64>() / n_paths as f64;
+        let mean1: f64 = (0..n_paths).map(|p| out[p * stride + 9] as f64).sum::<f64>() / n_paths as f64;
+        assert_relative_eq!(mean0, 0.08_f64.exp(), epsilon = 0.01);
+        assert_relative_eq!(mean1, 0.03_f64.exp(), epsilon = 0.002);
+
+        // corr(ln S₀, ln S₁) ≈ 0.5
+        let logs: Vec<(f64, f64)> = (0..n_paths)
+            .map(|p| ((out[p * stride + 8] as f64).ln(), (out[p * stride + 9] as f64).ln()))
+            .collect();
+        let (ma, mb) = logs.iter().fold((0.0, 0.0), |acc, &(a, b)| (acc.0 + a, acc.1 + b));
+        let (ma, mb) = (ma / n_paths as f64, mb / n_paths as f64);
+        let (mut sab, mut saa, mut sbb) = (0.0, 0.0, 0.0);
+        for &(a, b) in &logs {
+            sab += (a - ma) * (b - mb);
+            saa += (a - ma) * (a - ma);
+            sbb += (b - mb) * (b - mb);
+        }
+        assert_relative_eq!(sab / (saa * sbb).sqrt(), 0.5, epsilon = 0.03);
+    }
+
+    #[test]
+    fn test_simulate_paths_rejects_bad_input() {
+        let (drift, vol, l) = two_asset_market();
+        assert!(simulate_paths(&drift, &vol, &l, &SimConfig::new(1.0, 0, 1, 0)).is_err());
+        assert!(simulate_paths(&drift, &vol, &l, &SimConfig::new(-1.0, 4, 1, 0)).is_err());
+        let short = DVector::from_vec(vec![0.1]);
+        assert!(simulate_paths(&short, &vol, &l, &SimConfig::new(1.0, 4, 1, 0)).is_err());
+    }
+
+    #[test]
+    fn test_poisson_mean() {
+        let mut normals = NormalSampler::new(Pcg32::new(1, 1));
+        let draws = 50_000;
+        let total: u32 = (0..draws).map(|_| sample_poisson(&mut normals, 0.4)).sum();
+        assert_relative_eq!(total as f64 / draws as f64, 0.4, epsilon = 0.01);
+        assert_eq!(sample_poisson(&mut normals, 0.0), 0);
+    }
+
+    #[test]
+    fn test_jumps_shift_terminal_log_mean() {
+        let (drift, vol, l) = two_asset_market();
+        let n_paths = 20_000;
+        let jumps = JumpParams { lambda: 2.0, mean: -0.05, vol: 0.02 };
+        let config = SimConfig::new(1.0, 12, n_paths, 3).with_jumps(jumps);
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let stride = 13 * 2;
+
+        // E[ln S_T] = (μ − σ²/2)·T + λ·T·μ_J
+        let log_mean: f64 = (0..n_paths)
+            .map(|p| (out[p * stride + 24] as f64).ln())
+            .sum::<f64>() / n_paths as f64;
+        let expected = 0.08 - 0.5 * 0.04 + 2.0 * -0.05;
+        assert_relative_eq!(log_mean, expected, epsilon = 0.006);
+    }
+
+    #[test]
+    fn test_systemic_jumps_hit_all_assets_together() {
+        let (drift, vol, l) = two_asset_market();
+        let n_paths = 20_000;
+        let jumps = SystemicJumps { lambda: 1.5, mean: -0.1, vol: 0.0, betas: vec![1.0, 0.4] };
+        let config = SimConfig::new(1.0, 12, n_paths, 4).with_systemic_jumps(jumps);
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let stride = 13 * 2;
+        let logs: Vec<(f64, f64)> = (0..n_paths)
+            .map(|p| ((out[p * stride + 24] as f64).ln(), (out[p * stride + 25] as f64).ln()))
+            .collect();
+
+        // E[ln S_T,i] = (μ_i − σ_i²/2)·T + β_i·λ·T·μ_J
+        let m0 = logs.iter().map(|x| x.0).sum::<f64>() / n_paths as f64;
+        let m1 = logs.iter().map(|x| x.1).sum::<f64>() / n_paths as f64;
+        assert_relative_eq!(m0, 0.08 - 0.02 - 0.15, epsilon = 0.006);
+        assert_relative_eq!(m1, 0.03 - 0.00125 - 0.06, epsilon = 0.003);
+
+        // Cov picks up β₀·β₁·λ·T·μ_J² on top of the diffusion's ρσ₀σ₁
+        let cov = logs.iter().map(|x| (x.0 - m0) * (x.1 - m1)).sum::<f64>() / n_paths as f64;
+        assert_relative_eq!(cov, 0.5 * 0.2 * 0.05 + 0.4 * 1.5 * 0.01, epsilon = 0.001);
+    }
+
+    #[test]
+    fn test_systemic_jumps_need_one_beta_per_asset() {
+        let (drift, vol, l) = two_asset_market();
+        let jumps = SystemicJumps { lambda: 1.0, mean: -0.1, vol: 0.1, betas: vec![1.0] };
+        let config = SimConfig::new(1.0, 4, 4, 0).with_systemic_jumps(jumps);
+        assert!(simulate_paths(&drift, &vol, &l, &config).is_err());
+    }
+
+    #[test]
+    fn test_per_asset_jumps_apply_to_their_own_asset() {
+        let (drift, vol, l) = two_asset_market();
+        let n_paths = 20_000;
+        let calm = JumpParams { lambda: 0.0, mean: 0.0, vol: 0.0 };
+        let jumpy = JumpParams { lambda: 3.0, mean: -0.05, vol: 0.02 };
+        let config = SimConfig::new(1.0, 12, n_paths, 3).with_asset_jumps(vec![calm, jumpy]);
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let log_mean = |a: usize| (0..n_paths).map(|p| (out[p * 26 + 24 + a] as f64).ln()).sum::<f64>() / n_paths as f64;
+        assert_relative_eq!(log_mean(0), 0.08 - 0.02, epsilon = 0.006);
+        assert_relative_eq!(log_mean(1), 0.03 - 0.00125 - 0.15, epsilon = 0.003);
+
+        let bad = SimConfig::new(1.0, 12, 4, 3).with_asset_jumps(vec![calm, jumpy, calm]);
+        assert!(simulate_paths(&drift, &vol, &l, &bad).is_err());
+    }
+
+    #[test]
+    fn test_hawkes_jumps_cluster() {
+        // Zero diffusion and fixed-size jumps: ln S_T = μ_J·N_T exactly
+        let drift = DVector::from_vec(vec![0.0]);
+        let vol = DVector::from_vec(vec![0.0]);
+        let l = DMatrix::zeros(1, 1);
+        let n_paths = 10_000;
+        let jumps = JumpParams { lambda: 2.0, mean: -0.05, vol: 0.0 };
+        let counts = |config: SimConfig| -> (f64, f64) {
+            let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+            let k: Vec<f64> = (0..n_paths).map(|p| (out[p * 253 + 252] as f64).ln() / -0.05).collect();
+            let m = k.iter().sum::<f64>() / n_paths as f64;
+            (m, k.iter().map(|x| (x - m).powi(2)).sum::<f64>() / n_paths as f64)
+        };
+        let base = SimConfig::new(1.0, 252, n_paths, 8).with_jumps(jumps);
+        let (pm, pv) = counts(base.clone());
+        let (hm, hv) = counts(base.with_hawkes(HawkesParams { excitation: 3.0, decay: 6.0 }));
+
+        // E[N_T] = λT + αλ/(β−α)·(T − (1 − e^{−(β−α)T})/(β−α))
+        let expected = 2.0 + 2.0 * (1.0 - (1.0 - (-3.0_f64).exp()) / 3.0);
+        assert_relative_eq!(pm, 2.0, epsilon = 0.05);
+        assert_relative_eq!(hm, expected, max_relative = 0.03);
+        // Poisson counts have variance ≈ mean; clustering overdisperses them
+        assert!(pv / pm < 1.1);
+        assert!(hv / hm > 1.5);
+    }
+
+    #[test]
+    fn test_hawkes_rejects_explosive_excitation() {
+        let (drift, vol, l) = two_asset_market();
+        let config = SimConfig::new(1.0, 4, 4, 0)
+            .with_jumps(JumpParams { lambda: 1.0, mean: -0.1, vol: 0.1 })
+            .with_hawkes(HawkesParams { excitation: 5.0, decay: 5.0 });
+        assert!(simulate_paths(&drift, &vol, &l, &config).is_err());
+    }
+
+    #[test]
+    fn test_inverse_gaussian_moments() {
+        let mut normals = NormalSampler::new(Pcg32::new(3, 3));
+        let (mean, shape) = (0.5, 2.0);
+        let n = 100_000;
+        let x: Vec<f64> = (0..n).map(|_| sample_inverse_gaussian(&mut normals, mean, shape)).collect();
+        let m = x.iter().sum::<f64>() / n as f64;
+        let v = x.iter().map(|x| (x - m).powi(2)).sum::<f64>() / n as f64;
+        assert_relative_eq!(m, mean, max_relative = 0.01);
+        assert_relative_eq!(v, mean.powi(3) / shape, max_relative = 0.05);
+    }
+
+    #[test]
+    fn test_levy_models_keep_mean_and_add_skewed_variance() {
+        let (drift, vol, l) = two_asset_market();
+        let n_paths = 20_000;
+        let vg = VolModel::VarianceGamma(vec![VarianceGammaParams { theta: -0.2, nu: 0.3 }; 2]);
+        let nig = VolModel::Nig(vec![NigParams { theta: -0.2, kappa: 0.3 }; 2]);
+        for model in [vg, nig] {
+            let config = SimConfig::new(1.0, 12, n_paths, 5).with_vol_model(model);
+            let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+            let s: Vec<f64> = (0..n_paths).map(|p| out[p * 26 + 24] as f64).collect();
+            let mean = s.iter().sum::<f64>() / n_paths as f64;
+            assert_relative_eq!(mean, 0.08_f64.exp(), max_relative = 0.01);
+
+            // Var[X_T] = σ²T + θ²·ν·T, and θ < 0 skews returns left
+            let r: Vec<f64> = s.iter().map(|x| x.ln()).collect();
+            let m = r.iter().sum::<f64>() / n_paths as f64;
+            let var = r.iter().map(|x| (x - m).powi(2)).sum::<f64>() / n_paths as f64;
+            let skew = r.iter().map(|x| (x - m).powi(3)).sum::<f64>() / n_paths as f64 / var.powf(1.5);
+            assert_relative_eq!(var, 0.04 + 0.04 * 0.3, max_relative = 0.05);
+            assert!(skew < -0.2, "skew {skew}");
+        }
+    }
+
+    #[test]
+    fn test_local_vol_surface_interpolates_and_scales() {
+        // One asset, knots t ∈ {0, 1}, S ∈ {0.8, 1.2}
+        let mut surface = LocalVolSurface::new(1, vec![0.0, 1.0], vec![0.8, 1.2], vec![0.3, 0.1, 0.5, 0.3]).unwrap();
+        assert_relative_eq!(surface.vol(0, 0.0, 1.0), 0.2, epsilon = 1e-12);
+        assert_relative_eq!(surface.vol(0, 0.5, 1.0), 0.3, epsilon = 1e-12);
+        assert_relative_eq!(surface.vol(0, 2.0, 0.5), 0.5, epsilon = 1e-12);
+        surface.scale(&DVector::from_vec(vec![2.0]));
+        assert_relative_eq!(surface.vol(0, 0.0, 1.0), 0.4, epsilon = 1e-12);
+        assert!(LocalVolSurface::new(2, vec![0.0, 1.0], vec![], vec![0.2; 3]).is_err());
+    }
+
+    #[test]
+    fn test_flat_local_vol_matches_gbm_and_term_structure_adds_up() {
+        let (drift, vol, l) = two_asset_market();
+        let config = SimConfig::new(1.0, 12, 20, 6);
+        let flat = LocalVolSurface::new(2, vec![0.0], vec![], vec![0.2, 0.05]).unwrap();
+        let gbm = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let local = simulate_paths(&drift, &vol, &l, &config.clone().with_vol_model(VolModel::Local(flat))).unwrap();
+        for (a, b) in gbm.iter().zip(local.iter()) {
+            assert_relative_eq!(*a, *b, epsilon = 1e-5);
+        }
+
+        // σ = 0.1 for the first half, 0.3 after: Var[ln S_T] = ½(0.01 + 0.09)
+        let n_paths = 20_000;
+        let steps = vec![0.1, 0.1, 0.3, 0.3];
+        let term = LocalVolSurface::new(2, vec![0.0, 0.45, 0.46, 1.0], vec![], [steps.clone(), steps].concat()).unwrap();
+        let config = SimConfig::new(1.0, 12, n_paths, 2).with_vol_model(VolModel::Local(term));
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let r: Vec<f64> = (0..n_paths).map(|p| (out[p * 26 + 24] as f64).ln()).collect();
+        let m = r.iter().sum::<f64>() / n_paths as f64;
+        let var = r.iter().map(|x| (x - m).powi(2)).sum::<f64>() / n_paths as f64;
+        assert_relative_eq!(var, 0.05, max_relative = 0.05);
+    }
+
+    #[test]
+    fn test_term_structure_crash_then_recovery() {
+        // −40% drift and doubled vol for the first quarter, calm afterwards
+        let (drift, vol, l) = two_asset_market();
+        let n_paths = 20_000;
+        let term = TermStructure {
+            ends: vec![0.25, 1.0],
+            drift: DMatrix::from_row_slice(2, 2, &[-0.4, 0.03, 0.08, 0.03]),
+            vol: DMatrix::from_row_slice(2, 2, &[0.4, 0.05, 0.2, 0.05]),
+        };
+        let config = SimConfig::new(1.0, 12, n_paths, 9).with_term_structure(term);
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let r: Vec<f64> = (0..n_paths).map(|p| (out[p * 26 + 24] as f64).ln()).collect();
+        let m = r.iter().sum::<f64>() / n_paths as f64;
+        let var = r.iter().map(|x| (x - m).powi(2)).sum::<f64>() / n_paths as f64;
+        assert_relative_eq!(m, 0.25 * (-0.4 - 0.08) + 0.75 * (0.08 - 0.02), epsilon = 0.006);
+        assert_relative_eq!(var, 0.25 * 0.16 + 0.75 * 0.04, max_relative = 0.05);
+
+        // Flat term structure equal to the market reproduces plain GBM
+        let flat = TermStructure {
+            ends: vec![1.0],
+            drift: DMatrix::from_row_slice(1, 2, drift.as_slice()),
+            vol: DMatrix::from_row_slice(1, 2, vol.as_slice()),
+        };
+        let plain = simulate_paths(&drift, &vol, &l, &SimConfig::new(1.0, 6, 10, 3)).unwrap();
+        let term = simulate_paths(&drift, &vol, &l, &SimConfig::new(1.0, 6, 10, 3).with_term_structure(flat)).unwrap();
+        for (a, b) in plain.iter().zip(term.iter()) {
+            assert_relative_eq!(*a, *b, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_short_rate_bond_follows_deterministic_rate_path() {
+        // σ_r = 0: r_t = θ + (r₀ − θ)e^{−κt}, price compounds the duration return
+        let (drift, vol, l) = two_asset_market();
+        let sr = ShortRateParams { kappa: 0.8, theta: 0.04, sigma: 0.0, r0: 0.02, duration: 7.0, convexity: 60.0 };
+        let config = SimConfig::new(1.0, 12, 3, 5).with_short_rates(vec![None, Some(sr)]);
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let dt = 1.0 / 12.0;
+        let mut price = 1.0;
+        for step in 1..=12 {
+            let r = |k: usize| 0.04 + (0.02 - 0.04) * (-0.8 * k as f64 * dt).exp();
+            let dr = r(step) - r(step - 1);
+            price *= 1.0 + r(step - 1) * dt - 7.0 * dr + 0.5 * 60.0 * dr * dr;
+            for p in 0..3 {
+                assert_relative_eq!(out[p * 26 + step * 2 + 1] as f64, price, max_relative = 1e-6);
+            }
+        }
+        // Rising rates: the bond loses despite the carry
+        assert!(price < 1.0);
+    }
+
+    #[test]
+    fn test_short_rate_log_price_variance_matches_ou() {
+        // ln P_T ≈ ∫Y dt − D·Y_T for the OU deviation Y = r − θ (Y₀ = 0)
+        let (drift, vol, l) = two_asset_market();
+        let (kappa, sigma, d, t) = (0.5_f64, 0.01_f64, 7.0, 5.0);
+        let sr = ShortRateParams { kappa, theta: 0.03, sigma, r0: 0.03, duration: d, convexity: 0.0 };
+        let n_paths = 20_000;
+        let config = SimConfig::new(t, 60, n_paths, 31).with_short_rates(vec![Some(sr), None]);
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let logs: Vec<f64> = (0..n_paths).map(|p| (out[p * 122 + 120] as f64).ln()).collect();
+        let mean = logs.iter().sum::<f64>() / n_paths as f64;
+        let var = logs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n_paths - 1) as f64;
+
+        let (e1, e2) = ((-kappa * t).exp(), (-2.0 * kappa * t).exp());
+        let var_end = sigma * sigma * (1.0 - e2) / (2.0 * kappa);
+        let var_int = sigma * sigma / (kappa * kappa) * (t - 2.0 * (1.0 - e1) / kappa + (1.0 - e2) / (2.0 * kappa));
+        let cov = sigma * sigma / (2.0 * kappa * kappa) * (1.0 - e1).powi(2);
+        assert_relative_eq!(var, var_int + d * d * var_end - 2.0 * d * cov, max_relative = 0.1);
+        // Mean-reverting rates: far less spread than a GBM with the same 1y vol
+        assert!(var < (d * sigma).powi(2) * t);
+    }
+
+    #[test]
+    fn test_dividend_yield_lowers_price_but_not_total_return() {
+        let (drift, vol, l) = two_asset_market();
+        let n_paths = 20_000;
+        let config = SimConfig::new(1.0, 12, n_paths, 14).with_dividend_yield(vec![0.04, 0.0]);
+        let prices = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let total = total_return_paths(&prices, 2, &config).unwrap();
+        let mean = |xs: &[f32], a: usize| (0..n_paths).map(|p| xs[p * 26 + 24 + a] as f64).sum::<f64>() / n_paths as f64;
+        assert_relative_eq!(mean(&prices, 0), 0.04_f64.exp(), max_relative = 0.01);
+        assert_relative_eq!(mean(&total, 0), 0.08_f64.exp(), max_relative = 0.01);
+        assert_eq!(mean(&prices, 1), mean(&total, 1));
+
+        // Planar layout gets the same adjustment
+        let planar_cfg = config.clone().with_layout(OutputLayout::Planar);
+        let planar = simulate_paths(&drift, &vol, &l, &planar_cfg).unwrap();
+        let planar_total = total_return_paths(&planar, 2, &planar_cfg).unwrap();
+        assert_eq!(to_planar(&total, n_paths, 13, 2), planar_total);
+    }
+
+    #[test]
+    fn test_zero_intensity_jumps_match_plain_gbm() {
+        let (drift, vol, l) = two_asset_market();
+        let plain = simulate_paths(&drift, &vol, &l, &SimConfig::new(1.0, 6, 50, 11)).unwrap();
+        let jumps = JumpParams { lambda: 0.0, mean: -0.1, vol: 0.05 };
+        let with = simulate_paths(&drift, &vol, &l, &SimConfig::new(1.0, 6, 50, 11).with_jumps(jumps)).unwrap();
+        // λ = 0 draws nothing, so the stream and the paths are unchanged
+        assert_eq!(plain, with);
+    }
+
+    #[test]
+    fn test_heston_zero_vol_of_vol_matches_gbm_distribution() {
+        // ξ = 0 and θ = σ₀² → variance stays at σ² and Heston is GBM
+        let (drift, vol, l) = two_asset_market();
+        let params: Vec<HestonParams> = vol
+            .iter()
+            .map(|&s| HestonParams { kappa: 2.0, theta: s * s, xi: 0.0, rho: -0.7 })
+            .collect();
+        let gbm = simulate_paths(&drift, &vol, &l, &SimConfig::new(1.0, 8, 200, 5)).unwrap();
+        let heston = simulate_paths(
+            &drift,
+            &vol,
+            &l,
+            &SimConfig::new(1.0, 8, 200, 5).with_vol_model(VolModel::Heston(params)),
+        )
+        .unwrap();
+        // Same diffusion draws; the extra vol-noise draw shifts the stream,
+        // so compare only the first step, which precedes any divergence.
+        for p in 0..200 {
+            let base = p * 18;
+            assert_relative_eq!(heston[base + 2], gbm[base + 2], epsilon = 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_heston_leverage_produces_negative_skew() {
+        let drift = DVector::from_vec(vec![0.05]);
+        let vol = DVector::from_vec(vec![0.2]);
+        let l = DMatrix::from_element(1, 1, 0.2);
+        let heston = VolModel::Heston(vec![HestonParams { kappa: 1.5, theta: 0.04, xi: 0.8, rho: -0.9 }]);
+        let n_paths = 20_000;
+        let config = SimConfig::new(1.0, 50, n_paths, 9).with_vol_model(heston);
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+
+        let r: Vec<f64> = (0..n_paths).map(|p| (out[p * 51 + 50] as f64).ln()).collect();
+        let m = r.iter().sum::<f64>() / n_paths as f64;
+        let m2 = r.iter().map(|x| (x - m).powi(2)).sum::<f64>() / n_paths as f64;
+        let m3 = r.iter().map(|x| (x - m).powi(3)).sum::<f64>() / n_paths as f64;
+        assert!(m3 / m2.powf(1.5) < -0.2, "ρ < 0 should skew log-returns left");
+        assert!(out.iter().all(|x| x.is_finite() && *x > 0.0));
+    }
+
+    #[test]
+    fn test_heston_rejects_mismatched_params() {
+        let (drift, vol, l) = two_asset_market();
+        let one = VolModel::Heston(vec![HestonParams { kappa: 1.0, theta: 0.04, xi: 0.3, rho: 0.0 }]);
+        let config = SimConfig::new(1.0, 4, 1, 0).with_vol_model(one);
+        assert!(simulate_paths(&drift, &vol, &l, &config).is_err());
+    }
+
+    #[test]
+    fn test_gamma_sampler_moments() {
+        let mut normals = NormalSampler::new(Pcg32::new(2, 2));
+        for &k in &[0.5, 3.0] {
+            let draws: Vec<f64> = (0..40_000).map(|_| sample_gamma(&mut normals, k)).collect();
+            let m = draws.iter().sum::<f64>() / draws.len() as f64;
+            let v = draws.iter().map(|x| (x - m).powi(2)).sum::<f64>() / draws.len() as f64;
+            assert_relative_eq!(m, k, epsilon = 0.03 * k.max(1.0));
+            assert_relative_eq!(v, k, epsilon = 0.08 * k.max(1.0));
+        }
+    }
+
+    fn terminal_log_returns(config: &SimConfig) -> Vec<(f64, f64)> {
+        let (drift, vol, l) = two_asset_market();
+        let out = simulate_paths(&drift, &vol, &l, config).unwrap();
+        let stride = (config.steps + 1) * 2;
+        (0..config.n_paths)
+            .map(|p| {
+                let a = (out[p * stride + 2] as f64).ln();
+                let b = (out[p * stride + 3] as f64).ln();
+                (a, b)
+            })
+            .collect()
+    }
+
+    fn moments(x: &[f64]) -> (f64, f64, f64, f64) {
+        let n = x.len() as f64;
+        let m = x.iter().sum::<f64>() / n;
+        let m2 = x.iter().map(|v| (v - m).powi(2)).sum::<f64>() / n;
+        let m3 = x.iter().map(|v| (v - m).powi(3)).sum::<f64>() / n;
+        let m4 = x.iter().map(|v| (v - m).powi(4)).sum::<f64>() / n;
+        (m, m2, m3 / m2.powf(1.5), m4 / (m2 * m2))
+    }
+
+    #[test]
+    fn test_student_t_preserves_vol_and_correlation() {
+        // One step → the terminal log-return is a single innovation
+        let config = SimConfig::new(1.0, 1, 40_000, 21).with_innovations(Innovations::StudentT { nu: 5.0 });
+        let r = terminal_log_returns(&config);
+        let a: Vec<f64> = r.iter().map(|x| x.0).collect();
+        let b: Vec<f64> = r.iter().map(|x| x.1).collect();
+        let (ma, va, _, kurt) = moments(&a);
+        let (mb, vb, _, _) = moments(&b);
+        assert_relative_eq!(va.sqrt(), 0.2, epsilon = 0.01);
+        assert!(kurt > 4.0, "t₅ should be leptokurtic, got {}", kurt);
+        let cov = r.iter().map(|x| (x.0 - ma) * (x.1 - mb)).sum::<f64>() / r.len() as f64;
+        assert_relative_eq!(cov / (va * vb).sqrt(), 0.5, epsilon = 0.03);
+    }
+
+    #[test]
+    fn test_skew_t_is_left_skewed_with_unit_variance() {
+        let innovations = Innovations::SkewT { nu: 8.0, gamma: 0.7 };
+        let config = SimConfig::new(1.0, 1, 40_000, 22).with_innovations(innovations);
+        let a: Vec<f64> = terminal_log_returns(&config).iter().map(|x| x.0).collect();
+        let (m, v, skew, _) = moments(&a);
+        assert_relative_eq!(v.sqrt(), 0.2, epsilon = 0.01);
+        // Re-centred, so the mean stays at (μ − σ²/2)·T
+        assert_relative_eq!(m, 0.08 - 0.02, epsilon = 0.005);
+        assert!(skew < -0.3, "γ < 1 should skew left, got {}", skew);
+    }
+
+    #[test]
+    fn test_student_t_rejects_low_nu() {
+        let (drift, vol, l) = two_asset_market();
+        let config = SimConfig::new(1.0, 4, 1, 0).with_innovations(Innovations::StudentT { nu: 2.0 });
+        assert!(simulate_paths(&drift, &vol, &l, &config).is_err());
+    }
+
+    fn regime(drift: f64, vol: f64) -> Regime {
+        Regime {
+            drift: DVector::from_vec(vec![drift]),
+            vol: DVector::from_vec(vec![vol]),
+            cholesky_l: DMatrix::from_element(1, 1, vol),
+        }
+    }
+
+    #[test]
+    fn test_regime_absorbing_matches_single_market() {
+        // P = I: the chain never leaves regime 1
+        let regimes = vec![regime(0.1, 0.1), regime(-0.2, 0.4)];
+        let config = SimConfig::new(1.0, 10, 50, 4);
+        let switched = simulate_regime_paths(&regimes, &DMatrix::identity(2, 2), 1, &config).unwrap();
+        assert!(switched.regimes.iter().all(|&r| r == 1));
+
+        let r = &regimes[1];
+        let plain = simulate_paths(&r.drift, &r.vol, &r.cholesky_l, &config).unwrap();
+        // Plain runs skip the transition draw, so only step 1 shares draws
+        for p in 0..50 {
+            assert_eq!(switched.paths[p * 11 + 1], plain[p * 11 + 1]);
+        }
+    }
+
+    #[test]
+    fn test_regime_occupancy_follows_stationary_distribution() {
+        // Stationary π = (2/3, 1/3) for P = [[0.9, 0.1], [0.2, 0.8]]
+        let regimes = vec![regime(0.05, 0.1), regime(-0.3, 0.5)];
+        let p = DMatrix::from_row_slice(2, 2, &[0.9, 0.1, 0.2, 0.8]);
+        let config = SimConfig::new(10.0, 500, 200, 8);
+        let out = simulate_regime_paths(&regimes, &p, 0, &config).unwrap();
+        let crisis = out.regimes.iter().filter(|&&r| r == 1).count() as f64 / out.regimes.len() as f64;
+        assert_relative_eq!(crisis, 1.0 / 3.0, epsilon = 0.03);
+    }
+
+    #[test]
+    fn test_regime_rejects_bad_transition() {
+        let regimes = vec![regime(0.05, 0.1), regime(-0.3, 0.5)];
+        let bad = DMatrix::from_row_slice(2, 2, &[0.9, 0.2, 0.2, 0.8]);
+        let config = SimConfig::new(1.0, 4, 1, 0);
+        assert!(simulate_regime_paths(&regimes, &bad, 0, &config).is_err());
+        assert!(simulate_regime_paths(&regimes, &DMatrix::identity(2, 2), 2, &config).is_err());
+    }
+
+    #[test]
+    fn test_garch_zero_alpha_is_deterministic_variance() {
+        // α = 0, ω = (1 − β)·σ²·dt → h stays at σ²·dt and GARCH is GBM
+        let (drift, vol, l) = two_asset_market();
+        let dt = 1.0 / 12.0;
+        let params: Vec<GarchParams> = vol
+            .iter()
+            .map(|&s| GarchParams { omega: 0.1 * s * s * dt, alpha: 0.0, beta: 0.9 })
+            .collect();
+        let config = SimConfig::new(1.0, 12, 20, 6);
+        let gbm = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let garch = simulate_paths(&drift, &vol, &l, &config.clone().with_vol_model(VolModel::Garch(params))).unwrap();
+        for (a, b) in gbm.iter().zip(garch.iter()) {
+            assert_relative_eq!(*a, *b, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_garch_produces_volatility_clustering() {
+        let drift = DVector::from_vec(vec![0.0]);
+        let vol = DVector::from_vec(vec![0.2]);
+        let l = DMatrix::from_element(1, 1, 0.2);
+        let dt: f64 = 1.0 / 252.0;
+        let garch = VolModel::Garch(vec![GarchParams { omega: 0.04 * dt * 0.05, alpha: 0.1, beta: 0.85 }]);
+        let steps = 2_000;
+        let config = SimConfig::new(steps as f64 * dt, steps, 20, 13).with_vol_model(garch);
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+
+        // Autocorrelation of squared returns at lag 1 should be clearly > 0
+        let mut num = 0.0;
+        let mut den = 0.0;
+        for p in 0..20 {
+            let path = &out[p * (steps + 1)..(p + 1) * (steps + 1)];
+            let sq: Vec<f64> = path.windows(2).map(|w| ((w[1] / w[0]) as f64).ln().powi(2)).collect();
+            let m = sq.iter().sum::<f64>() / sq.len() as f64;
+            for t in 1..sq.len() {
+                num += (sq[t] - m) * (sq[t - 1] - m);
+            }
+            den += sq.iter().map(|x| (x - m).powi(2)).sum::<f64>();
+        }
+        assert!(num / den > 0.1, "squared-return autocorrelation {} too small", num / den);
+    }
+
+    #[test]
+    fn test_garch_rejects_nonstationary_params() {
+        let (drift, vol, l) = two_asset_market();
+        let bad = vec![GarchParams { omega: 1e-5, alpha: 0.3, beta: 0.8 }; 2];
+        let config = SimConfig::new(1.0, 4, 1, 0).with_vol_model(VolModel::Garch(bad));
+        assert!(simulate_paths(&drift, &vol, &l, &config).is_err());
+    }
+
+    #[test]
+    fn test_sobol_driver_reduces_terminal_mean_error() {
+        // GBM terminal mean e^{μT}: compare |error| over a few seeds
+        let (drift, vol, l) = two_asset_market();
+        let exact = 0.08_f64.exp();
+        let err = |driver: Driver| -> f64 {
+            (0..8)
+                .map(|seed| {
+                    let config = SimConfig::new(1.0, 4, 1024, seed).with_driver(driver);
+                    let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+                    let mean = (0..1024).map(|p| out[p * 10 + 8] as f64).sum::<f64>() / 1024.0;
+                    (mean - exact).abs()
+                })
+                .sum::<f64>()
+        };
+        assert!(err(Driver::Sobol) < 0.5 * err(Driver::PseudoRandom));
+    }
+
+    #[test]
+    fn test_stratified_driver_reduces_terminal_mean_error() {
+        let (drift, vol, l) = two_asset_market();
+        let exact = 0.08_f64.exp();
+        let err = |driver: Driver| -> f64 {
+            (0..8)
+                .map(|seed| {
+                    let config = SimConfig::new(1.0, 4, 512, seed).with_driver(driver);
+                    let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+                    let mean = (0..512).map(|p| out[p * 10 + 8] as f64).sum::<f64>() / 512.0;
+                    (mean - exact).abs()
+                })
+                .sum::<f64>()
+        };
+        assert!(err(Driver::Stratified) < 0.5 * err(Driver::PseudoRandom));
+    }
+
+    #[test]
+    fn test_ids_cover_every_variant() {
+        let surface = LocalVolSurface::new(1, vec![0.0], vec![], vec![0.2]).unwrap();
+        let vol_models = [
+            VolModel::Constant,
+            VolModel::Heston(vec![]),
+            VolModel::Garch(vec![]),
+            VolModel::VarianceGamma(vec![]),
+            VolModel::Nig(vec![]),
+            VolModel::Local(surface),
+        ];
+        assert_eq!(vol_models.iter().map(|v| v.id()).collect::<Vec<_>>(), VolModel::IDS);
+        let innovations = [Innovations::Gaussian, Innovations::StudentT { nu: 5.0 }, Innovations::SkewT { nu: 5.0, gamma: 0.9 }];
+        assert_eq!(innovations.iter().map(|i| i.id()).collect::<Vec<_>>(), Innovations::IDS);
+        assert_eq!(Driver::ALL.map(|d| d.id()), ["pseudo_random", "sobol", "stratified"]);
+    }
+
+    #[test]
+    fn test_stratified_driver_fills_every_stratum() {
+        // One-asset, one-step: each path's terminal shock sits in its own stratum
+        let drift = DVector::from_vec(vec![0.0]);
+        let vol = DVector::from_vec(vec![0.2]);
+        let l = DMatrix::from_element(1, 1, 0.2);
+        let n_paths = 64;
+        let config = SimConfig::new(1.0, 1, n_paths, 5).with_driver(Driver::Stratified);
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        for p in 0..n_paths {
+            // log S₁ = −σ²/2 + σZ, and path p owns stratum p
+            let z = ((out[p * 2 + 1] as f64).ln() + 0.02) / 0.2;
+            let lo = inv_norm_cdf(p as f64 / n_paths as f64);
+            let hi = inv_norm_cdf((p + 1) as f64 / n_paths as f64);
+            assert!(z > lo - 1e-5 && z < hi + 1e-5, "path {p}: {z} not in [{lo}, {hi}]");
+        }
+    }
+
+    #[test]
+    fn test_stratified_driver_strata_follow_principal_component() {
+        // Two correlated assets, one step: recover Z = L⁻¹·log S and check
+        // its projection on the principal direction sits in stratum p
+        let drift = DVector::from_vec(vec![0.02, 0.005]);
+        let vol = DVector::from_vec(vec![0.2, 0.1]);
+        let cov = DMatrix::from_row_slice(2, 2, &[0.04, 0.012, 0.012, 0.01]);
+        let l = cov.clone().cholesky().unwrap().l();
+        let eigen = cov.clone().symmetric_eigen();
+        let mut u = l.transpose() * eigen.eigenvectors.column(eigen.eigenvalues.imax());
+        if (&l * &u).sum() < 0.0 {
+            u.neg_mut();
+        }
+        u /= u.norm();
+        let n_paths = 64;
+        let config = SimConfig::new(1.0, 1, n_paths, 9).with_driver(Driver::Stratified);
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        for p in 0..n_paths {
+            let x = DVector::from_fn(2, |i, _| (out[p * 4 + 2 + i] as f64).ln() - drift[i] + 0.5 * cov[(i, i)]);
+            let xi = u.dot(&l.solve_lower_triangular(&x).unwrap());
+            let lo = inv_norm_cdf(p as f64 / n_paths as f64);
+            let hi = inv_norm_cdf((p + 1) as f64 / n_paths as f64);
+            assert!(xi > lo - 1e-4 && xi < hi + 1e-4, "path {p}: {xi} not in [{lo}, {hi}]");
+        }
+    }
+
+    #[test]
+    fn test_same_seed_is_bit_identical_across_modes() {
+        let (drift, vol, l) = two_asset_market();
+        let base = SimConfig::new(1.0, 8, 64, 0xDEAD_BEEF).with_jumps(JumpParams {
+            lambda: 2.0,
+            mean: -0.05,
+            vol: 0.1,
+        });
+        let heston = HestonParams { kappa: 2.0, theta: 0.04, xi: 0.3, rho: -0.7 };
+        let configs = [
+            base.clone(),
+            base.clone().with_vol_model(VolModel::Heston(vec![heston; 2])),
+            base.clone().with_innovations(Innovations::SkewT { nu: 5.0, gamma: 1.3 }),
+            base.clone().with_driver(Driver::Sobol),
+            base.clone().with_driver(Driver::Stratified),
+            base.clone().with_construction(PathConstruction::BrownianBridge),
+            base.clone().with_rng(RngKind::Philox4x32),
+            base.clone().with_rng(RngKind::Xoshiro256PlusPlus),
+            base.clone().with_rng(RngKind::Pcg64),
+        ];
+        for config in &configs {
+            let a = simulate_paths(&drift, &vol, &l, config).unwrap();
+            let b = simulate_paths(&drift, &vol, &l, config).unwrap();
+            assert!(a.iter().zip(&b).all(|(x, y)| x.to_bits() == y.to_bits()));
+            let c = simulate_paths(&drift, &vol, &l, &config.clone().with_seed(7)).unwrap();
+            assert_ne!(a, c);
+        }
+    }
+
+    #[test]
+    fn test_paths_do_not_depend_on_path_count() {
+        let (drift, vol, l) = two_asset_market();
+        for driver in [Driver::PseudoRandom, Driver::Sobol] {
+            let small = SimConfig::new(1.0, 8, 16, 11).with_driver(driver);
+            let large = SimConfig { n_paths: 64, ..small.clone() };
+            let a = simulate_paths(&drift, &vol, &l, &small).unwrap();
+            let b = simulate_paths(&drift, &vol, &l, &large).unwrap();
+            assert_eq!(a[..], b[..a.len()]);
+        }
+    }
+
+    #[test]
+    fn test_planar_layout_transposes_interleaved() {
+        let (drift, vol, l) = two_asset_market();
+        let (steps, n_paths) = (5, 3);
+        let config = SimConfig::new(1.0, steps, n_paths, 4);
+        let inter = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let planar = simulate_paths(&drift, &vol, &l, &config.with_layout(OutputLayout::Planar)).unwrap();
+        for p in 0..n_paths {
+            for t in 0..=steps {
+                for a in 0..2 {
+                    let x = inter[(p * (steps + 1) + t) * 2 + a];
+                    assert_eq!(planar[(a * n_paths + p) * (steps + 1) + t], x);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_chunks_concatenate_to_full_run() {
+        let (drift, vol, l) = two_asset_market();
+        for driver in [Driver::PseudoRandom, Driver::Sobol, Driver::Stratified] {
+            let config = SimConfig::new(1.0, 6, 50, 21).with_driver(driver);
+            let full = simulate_paths(&drift, &vol, &l, &config).unwrap();
+            let mut stream = PathStream::new(&drift, &vol, &l, &config).unwrap();
+            let mut joined = Vec::new();
+            for chunk in [7, 20, 1, 100] {
+                joined.extend(stream.next_chunk(chunk));
+            }
+            assert_eq!(stream.remaining(), 0);
+            assert!(stream.next_chunk(10).is_empty());
+            assert_eq!(joined, full);
+
+            stream.reset();
+            assert_eq!(stream.next_chunk(50), full);
+
+            // An unbounded request past a partial read drains the rest
+            stream.reset();
+            let mut head = stream.next_chunk(7);
+            head.extend(stream.next_chunk(usize::MAX));
+            assert_eq!(head, full);
+        }
+    }
+
+    /// Sample mean and covariance of one-step log returns
+    fn one_step_log_moments(out: &[f32], n_paths: usize, n: usize) -> (DVector<f64>, DMatrix<f64>) {
+        let rows: Vec<DVector<f64>> = (0..n_paths)
+            .map(|p| DVector::from_iterator(n, (0..n).map(|a| (out[p * 2 * n + n + a] as f64).ln())))
+            .collect();
+        let mean = rows.iter().sum::<DVector<f64>>() / n_paths as f64;
+        let cov = rows.iter().map(|r| (r - &mean) * (r - &mean).transpose()).sum::<DMatrix<f64>>()
+            / (n_paths - 1) as f64;
+        (mean, cov)
+    }
+
+    #[test]
+    fn test_moment_matching_recovers_exact_moments() {
+        let (drift, vol, l) = two_asset_market();
+        let cov = &l * l.transpose();
+        let n_paths = 40;
+        let config = SimConfig::new(1.0, 1, n_paths, 8);
+
+        let out = simulate_paths(&drift, &vol, &l, &config.clone().with_moment_matching(MomentMatching::MeanVariance))
+            .unwrap();
+        let (mean, sample) = one_step_log_moments(&out, n_paths, 2);
+        for i in 0..2 {
+            assert_relative_eq!(mean[i], drift[i] - 0.5 * vol[i] * vol[i], epsilon = 1e-6);
+        }
+        // Asset 0 is driven by Z₀ alone, so its variance is matched too
+        assert_relative_eq!(sample[(0, 0)], cov[(0, 0)], max_relative = 1e-5);
+
+        let out = simulate_paths(&drift, &vol, &l, &config.clone().with_moment_matching(MomentMatching::Full)).unwrap();
+        let (_, sample) = one_step_log_moments(&out, n_paths, 2);
+        assert_relative_eq!(sample, cov, max_relative = 1e-4);
+
+        // Without matching the same seed is visibly off
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let (_, sample) = one_step_log_moments(&out, n_paths, 2);
+        assert!((sample[(0, 1)] - cov[(0, 1)]).abs() > 1e-4);
+    }
+
+    #[test]
+    fn test_moment_matching_is_reproducible_with_jumps() {
+        let (drift, vol, l) = two_asset_market();
+        let config = SimConfig::new(1.0, 6, 30, 2)
+            .with_jumps(JumpParams { lambda: 3.0, mean: -0.1, vol: 0.1 })
+            .with_moment_matching(MomentMatching::Full);
+        let a = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let b = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        assert_eq!(a, b);
+        assert!(a.iter().all(|x| x.is_finite() && *x > 0.0));
+    }
+
+    #[test]
+    fn test_importance_tilt_estimates_deep_tail() {
+        // P(S_T < K) = 0.1% for asset 0; tilt along Z₀ so its drift hits K
+        let (drift, vol, l) = two_asset_market();
+        let m = drift[0] - 0.5 * vol[0] * vol[0];
+        let target = 1e-3;
+        let ln_k = m + vol[0] * inv_norm_cdf(target);
+        let n_paths = 4000;
+        let shift = (ln_k - m) / l[(0, 0)];
+        let config = SimConfig::new(1.0, 4, n_paths, 12).with_tilt(vec![l[(0, 0)] * shift, l[(1, 0)] * shift]);
+        let run = simulate_weighted_paths(&drift, &vol, &l, &config).unwrap();
+
+        let est = (0..n_paths)
+            .filter(|&p| (run.paths[p * 10 + 8] as f64).ln() < ln_k)
+            .map(|p| run.weights[p])
+            .sum::<f64>()
+            / n_paths as f64;
+        // Plain MC with 4000 paths would see ~4 hits (±50%)
+        assert_relative_eq!(est, target, max_relative = 0.1);
+    }
+
+    #[test]
+    fn test_zero_tilt_has_unit_weights_and_plain_paths() {
+        let (drift, vol, l) = two_asset_market();
+        let config = SimConfig::new(1.0, 5, 20, 6);
+        let plain = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let run = simulate_weighted_paths(&drift, &vol, &l, &config.clone().with_tilt(vec![0.0, 0.0])).unwrap();
+        assert_eq!(run.paths, plain);
+        assert!(run.weights.iter().all(|&w| w == 1.0));
+        assert!(simulate_paths(&drift, &vol, &l, &config.with_tilt(vec![0.0, 0.0])).is_err());
+    }
+
+    #[test]
+    fn test_sobol_driver_rejects_too_many_dimensions() {
+        let (drift, vol, l) = two_asset_market();
+        // 26 × 2 = 52 fits the Joe–Kuo table; 27 × 2 = 54 does not
+        let config = SimConfig::new(1.0, 26, 1, 0).with_driver(Driver::Sobol);
+        assert!(simulate_paths(&drift, &vol, &l, &config).is_ok());
+        let config = SimConfig::new(1.0, 27, 1, 0).with_driver(Driver::Sobol);
+        assert!(simulate_paths(&drift, &vol, &l, &config).is_err());
+    }
+
+    #[test]
+    fn test_brownian_bridge_terminal_uses_first_draw() {
+        let steps = 7;
+        let bb = BrownianBridge::new(steps);
+        let mut z = vec![0.0; steps];
+        z[0] = 1.5;
+        let mut incr = vec![0.0; steps];
+        bb.increments(&z, &mut incr);
+        // Only the terminal draw is set → straight line to W_T = 1.5·√7
+        let total: f64 = incr.iter().sum();
+        assert_relative_eq!(total, 1.5 * (steps as f64).sqrt(), epsilon = 1e-12);
+        for w in incr.windows(2) {
+            assert_relative_eq!(w[0], w[1], epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_brownian_bridge_increments_are_iid_standard() {
+        // Linear map z → ΔW must be orthogonal: Cov(ΔW) = I
+        let steps = 12;
+        let bb = BrownianBridge::new(steps);
+        let mut cov = DMatrix::zeros(steps, steps);
+        let mut incr = vec![0.0; steps];
+        for d in 0..steps {
+            let mut e = vec![0.0; steps];
+            e[d] = 1.0;
+            bb.increments(&e, &mut incr);
+            let col = DVector::from_column_slice(&incr);
+            cov += &col * col.transpose();
+        }
+        assert_relative_eq!(cov, DMatrix::identity(steps, steps), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_bridge_with_sobol_matches_distribution() {
+        let (drift, vol, l) = two_asset_market();
+        let config = SimConfig::new(1.0, 16, 2048, 3)
+            .with_driver(Driver::Sobol)
+            .with_construction(PathConstruction::BrownianBridge);
+        let out = simulate_paths(&drift, &vol, &l, &config).unwrap();
+        let stride = 17 * 2;
+        let mean = (0..2048).map(|p| out[p * stride + 32] as f64).sum::<f64>() / 2048.0;
+        assert_relative_eq!(mean, 0.08_f64.exp(), epsilon = 0.004);
+    }
+}
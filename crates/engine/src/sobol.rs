@@ -0,0 +1,359 @@
+use std::sync::OnceLock;
+
+use crate::simulate::Pcg32;
+
+// ════════════════════════════════════════════════════════════════
+// Sobol low-discrepancy sequence (32-bit, Antonov–Saleev Gray code)
+//
+// Dimension 1 is van der Corput; dimension j ≥ 2 uses the (j−1)-th
+// primitive polynomial over GF(2) in degree-then-coefficient order,
+// with the Joe–Kuo new-joe-kuo-6.21201 initial direction numbers m_k.
+// Only the rows for every polynomial up to degree 8 are embedded, so
+// SOBOL_MAX_DIM is 53; more dimensions need more rows of that table,
+// not generated m_k, which would spoil the 2-D projections.
+// ════════════════════════════════════════════════════════════════
+
+pub const SOBOL_BITS: usize = 32;
+pub const SOBOL_MAX_DIM: usize = JOE_KUO.len() + 1;
+
+// ────────────────────────────────────────────────────────────────
+// Direction numbers v_{j,k} = m_{j,k} · 2^{32−k}
+// ────────────────────────────────────────────────────────────────
+/// Joe–Kuo (degree s, packed coefficients a, m₁…m_s) for dimensions
+/// 2–53, from new-joe-kuo-6.21201.
+const JOE_KUO: [(u32, u32, &[u64]); 52] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+    (6, 19, &[1, 1, 1, 15, 7, 5]),
+    (6, 22, &[1, 3, 1, 15, 13, 25]),
+    (6, 25, &[1, 1, 5, 5, 19, 61]),
+    (7, 1, &[1, 3, 7, 11, 23, 15, 103]),
+    (7, 4, &[1, 3, 7, 13, 13, 15, 69]),
+    (7, 7, &[1, 1, 3, 13, 7, 35, 63]),
+    (7, 8, &[1, 3, 5, 9, 1, 25, 53]),
+    (7, 14, &[1, 3, 1, 13, 9, 35, 107]),
+    (7, 19, &[1, 3, 1, 5, 27, 61, 31]),
+    (7, 21, &[1, 1, 5, 11, 19, 41, 61]),
+    (7, 28, &[1, 3, 5, 3, 3, 13, 69]),
+    (7, 31, &[1, 1, 7, 13, 1, 19, 1]),
+    (7, 32, &[1, 3, 7, 5, 13, 19, 59]),
+    (7, 37, &[1, 1, 3, 9, 25, 29, 41]),
+    (7, 41, &[1, 3, 5, 13, 23, 1, 55]),
+    (7, 42, &[1, 3, 7, 3, 13, 59, 17]),
+    (7, 50, &[1, 3, 1, 3, 5, 53, 69]),
+    (7, 55, &[1, 1, 5, 5, 23, 33, 13]),
+    (7, 56, &[1, 1, 7, 7, 1, 61, 123]),
+    (7, 59, &[1, 1, 7, 9, 13, 61, 49]),
+    (7, 62, &[1, 3, 3, 5, 3, 55, 33]),
+    (8, 14, &[1, 3, 1, 15, 31, 13, 49, 245]),
+    (8, 21, &[1, 3, 5, 15, 31, 59, 63, 97]),
+    (8, 22, &[1, 3, 1, 11, 11, 11, 77, 249]),
+    (8, 38, &[1, 3, 1, 11, 27, 43, 71, 9]),
+    (8, 47, &[1, 1, 7, 15, 21, 11, 81, 45]),
+    (8, 49, &[1, 3, 7, 3, 25, 31, 65, 79]),
+    (8, 50, &[1, 3, 1, 1, 19, 11, 3, 205]),
+    (8, 52, &[1, 1, 5, 9, 19, 21, 29, 157]),
+    (8, 56, &[1, 3, 7, 11, 1, 33, 89, 185]),
+    (8, 67, &[1, 3, 3, 3, 15, 9, 79, 71]),
+    (8, 70, &[1, 3, 7, 11, 15, 39, 119, 27]),
+    (8, 84, &[1, 1, 3, 1, 11, 31, 97, 225]),
+    (8, 97, &[1, 1, 1, 3, 23, 43, 57, 177]),
+    (8, 103, &[1, 3, 7, 7, 17, 17, 37, 71]),
+    (8, 115, &[1, 3, 1, 5, 27, 63, 123, 213]),
+    (8, 122, &[1, 1, 3, 5, 11, 43, 53, 133]),
+];
+
+fn direction_numbers() -> &'static [[u32; SOBOL_BITS]] {
+    static TABLE: OnceLock<Vec<[u32; SOBOL_BITS]>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = Vec::with_capacity(SOBOL_MAX_DIM);
+
+        // Dimension 1: van der Corput (m_k = 1)
+        let mut first = [0_u32; SOBOL_BITS];
+        for (k, v) in first.iter_mut().enumerate() {
+            *v = 1 << (SOBOL_BITS - 1 - k);
+        }
+        table.push(first);
+
+        for &(degree, a, init) in &JOE_KUO {
+            let s = degree as usize;
+            let mut m = [0_u64; SOBOL_BITS];
+            m[..s].copy_from_slice(init);
+            for k in s..SOBOL_BITS {
+                let mut mk = m[k - s] ^ (m[k - s] << s);
+                for i in 1..s {
+                    if (a >> (s - 1 - i)) & 1 == 1 {
+                        mk ^= m[k - i] << i;
+                    }
+                }
+                m[k] = mk;
+            }
+            let mut v = [0_u32; SOBOL_BITS];
+            for k in 0..SOBOL_BITS {
+                v[k] = (m[k] << (SOBOL_BITS - 1 - k)) as u32;
+            }
+            table.push(v);
+        }
+        table
+    })
+}
+
+// ────────────────────────────────────────────────────────────────
+// SobolSequence — random-access points with a seeded digital shift
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone, Debug)]
+pub struct SobolSequence {
+    dim: usize,
+    shift: Vec<u32>,
+}
+
+impl SobolSequence {
+    /// `seed` drives a random digital shift (XOR per dimension), which
+    /// keeps low discrepancy while making runs with different seeds
+    /// independent randomized-QMC replicates.
+    pub fn new(dim: usize, seed: u64) -> Result<Self, &'static str> {
+        if dim == 0 || dim > SOBOL_MAX_DIM {
+            return Err("Sobol input invalid: dimension must be between 1 and 53");
+        }
+        let mut rng = Pcg32::new(seed, 0x5_0B01);
+        let shift = (0..dim).map(|_| rng.next_u32()).collect();
+        Ok(SobolSequence { dim, shift })
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Point `index` (0-based; index 0 is the origin before shifting)
+    /// written into `out` as uniforms in (0, 1).
+    pub fn point(&self, index: u64, out: &mut [f64]) {
+        let table = direction_numbers();
+        let gray = index ^ (index >> 1);
+        for (j, u) in out.iter_mut().enumerate().take(self.dim) {
+            let mut x = self.shift[j];
+            for (k, v) in table[j].iter().enumerate() {
+                if (gray >> k) & 1 == 1 {
+                    x ^= v;
+                }
+            }
+            *u = (x as f64 + 0.5) / 4_294_967_296.0;
+        }
+    }
+
+    /// Point `index` mapped through Φ⁻¹ to standard normals.
+    pub fn normal_point(&self, index: u64, out: &mut [f64]) {
+        self.point(index, out);
+        for u in out.iter_mut().take(self.dim) {
+            *u = crate::math::inv_norm_cdf(*u);
+        }
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    // Primitivity search, to check the embedded table's polynomials
+    /// Primitive polynomial x^s + a₁x^{s−1} + … + a_{s−1}x + 1, with the
+    /// middle coefficients a₁…a_{s−1} packed MSB-first into `a`.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Primitive {
+        degree: u32,
+        a: u32,
+    }
+
+    // ────────────────────────────────────────────────────────────────
+    // GF(2) polynomial arithmetic (bit i = coefficient of x^i)
+    // ────────────────────────────────────────────────────────────────
+    fn poly_mulmod(mut a: u64, mut b: u64, modulus: u64, degree: u32) -> u64 {
+        let mut out = 0;
+        while b != 0 {
+            if b & 1 == 1 {
+                out ^= a;
+            }
+            b >>= 1;
+            a <<= 1;
+            if a >> degree & 1 == 1 {
+                a ^= modulus;
+            }
+        }
+        out
+    }
+
+    /// x^e mod p
+    fn poly_xpow(mut e: u64, modulus: u64, degree: u32) -> u64 {
+        // x itself, reduced: x ≡ 1 mod (x + 1)
+        let mut base = if degree == 1 { 1 } else { 0b10 };
+        let mut out = 1;
+        while e != 0 {
+            if e & 1 == 1 {
+                out = poly_mulmod(out, base, modulus, degree);
+            }
+            base = poly_mulmod(base, base, modulus, degree);
+            e >>= 1;
+        }
+        out
+    }
+
+    fn prime_factors(mut n: u64) -> Vec<u64> {
+        let mut out = Vec::new();
+        let mut f = 2;
+        while f * f <= n {
+            if n.is_multiple_of(f) {
+                out.push(f);
+                while n.is_multiple_of(f) {
+                    n /= f;
+                }
+            }
+            f += 1;
+        }
+        if n > 1 {
+            out.push(n);
+        }
+        out
+    }
+
+    /// p is primitive iff x has multiplicative order exactly 2^s − 1.
+    fn is_primitive(modulus: u64, degree: u32) -> bool {
+        if modulus & 1 == 0 {
+            return false;
+        }
+        let order = (1_u64 << degree) - 1;
+        if poly_xpow(order, modulus, degree) != 1 {
+            return false;
+        }
+        prime_factors(order)
+            .into_iter()
+            .all(|q| poly_xpow(order / q, modulus, degree) != 1)
+    }
+
+    fn primitive_polynomials(count: usize) -> Vec<Primitive> {
+        let mut out = Vec::with_capacity(count);
+        let mut degree = 1;
+        while out.len() < count {
+            for a in 0..(1_u32 << (degree - 1)) {
+                let modulus = (1_u64 << degree) | ((a as u64) << 1) | 1;
+                if is_primitive(modulus, degree) {
+                    out.push(Primitive { degree, a });
+                    if out.len() == count {
+                        break;
+                    }
+                }
+            }
+            degree += 1;
+        }
+        out
+    }
+
+    #[test]
+    fn test_primitive_polynomial_counts() {
+        // φ(2^s − 1) / s primitive polynomials of degree s
+        let polys = primitive_polynomials(1 + 1 + 2 + 2 + 6 + 6);
+        let degrees: Vec<u32> = polys.iter().map(|p| p.degree).collect();
+        assert_eq!(degrees, vec![1, 2, 3, 3, 4, 4, 5, 5, 5, 5, 5, 5, 6, 6, 6, 6, 6, 6]);
+        // x³ + x + 1 (a = 0b01) and x³ + x² + 1 (a = 0b10)
+        assert_eq!(polys[2], Primitive { degree: 3, a: 1 });
+        assert_eq!(polys[3], Primitive { degree: 3, a: 2 });
+    }
+
+    #[test]
+    fn test_joe_kuo_rows_follow_polynomial_order() {
+        let polys = primitive_polynomials(JOE_KUO.len());
+        for (p, &(degree, a, m)) in polys.iter().zip(&JOE_KUO) {
+            assert_eq!(*p, Primitive { degree, a });
+            assert_eq!(m.len(), degree as usize);
+            assert!(m.iter().enumerate().all(|(k, &mk)| mk & 1 == 1 && mk < 1 << (k + 1)));
+        }
+        assert_eq!(polys.last().unwrap().degree, 8);
+        assert_eq!(primitive_polynomials(JOE_KUO.len() + 1)[JOE_KUO.len()].degree, 9);
+    }
+
+    #[test]
+    fn test_unshifted_points_match_reference() {
+        // First eight points of the unscrambled 3-D Joe–Kuo Sobol sequence
+        let seq = SobolSequence { dim: 3, shift: vec![0; 3] };
+        let expected = [
+            [0.0, 0.0, 0.0],
+            [0.5, 0.5, 0.5],
+            [0.75, 0.25, 0.25],
+            [0.25, 0.75, 0.75],
+            [0.375, 0.375, 0.625],
+            [0.875, 0.875, 0.125],
+            [0.625, 0.125, 0.875],
+            [0.125, 0.625, 0.375],
+        ];
+        let mut u = [0.0; 3];
+        for (i, e) in expected.iter().enumerate() {
+            seq.point(i as u64, &mut u);
+            for j in 0..3 {
+                assert_relative_eq!(u[j], e[j] + 0.5 / 4_294_967_296.0, epsilon = 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_first_dimension_is_van_der_corput() {
+        let seq = SobolSequence { dim: 1, shift: vec![0] };
+        let mut u = [0.0];
+        let expected = [0.0, 0.5, 0.75, 0.25, 0.375, 0.875];
+        for (i, e) in expected.iter().enumerate() {
+            seq.point(i as u64, &mut u);
+            assert_relative_eq!(u[0], e + 0.5 / 4_294_967_296.0, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_each_dimension_is_stratified() {
+        // Any 2^k consecutive points from 0 hit each dyadic interval once
+        let dim = SOBOL_MAX_DIM;
+        let seq = SobolSequence::new(dim, 99).unwrap();
+        let mut counts = vec![[0_u32; 16]; dim];
+        let mut u = vec![0.0; dim];
+        for i in 0..16 {
+            seq.point(i, &mut u);
+            for j in 0..dim {
+                counts[j][(u[j] * 16.0) as usize] += 1;
+            }
+        }
+        assert!(counts.iter().all(|c| c.iter().all(|&k| k == 1)));
+    }
+
+    #[test]
+    fn test_sobol_integrates_product_function() {
+        // ∫ Π (2u_j) over [0,1]^5 = 1
+        let dim = 5;
+        let seq = SobolSequence::new(dim, 1).unwrap();
+        let mut u = vec![0.0; dim];
+        let n = 4096;
+        let mut est = 0.0;
+        for i in 0..n {
+            seq.point(i, &mut u);
+            est += u.iter().map(|x| 2.0 * x).product::<f64>();
+        }
+        assert_relative_eq!(est / n as f64, 1.0, epsilon = 0.01);
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_dimension() {
+        assert!(SobolSequence::new(0, 0).is_err());
+        assert!(SobolSequence::new(SOBOL_MAX_DIM + 1, 0).is_err());
+        assert!(SobolSequence::new(SOBOL_MAX_DIM, 0).is_ok());
+    }
+}
@@ -0,0 +1,405 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use nalgebra::{DMatrix, DVector};
+
+// ════════════════════════════════════════════════════════════════
+// Strict reproducibility — identical bits on wasm, x86-64 and ARM
+//
+// IEEE-754 + − × ÷ and √ are correctly rounded on every target, and
+// Rust never fuses them into FMAs, so the engine's own loops already
+// give the same bits everywhere. What does not:
+//   - transcendentals (exp, ln, sin, cos, …) come from the platform
+//     libm — glibc, Apple's, MSVC's, or the musl port in wasm's
+//     compiler-builtins — which differ in the last ulp;
+//   - nalgebra's gemm goes through matrixmultiply, which picks an
+//     AVX/FMA or NEON kernel at run time;
+//   - the simd128 kernels pair up lanes in their dot products.
+// With strict mode on, the functions below use fdlibm's algorithms
+// (ported from musl, basic operations only), dot products are pairwise
+// over a fixed split, the Higham loop's V·Λ·Vᵀ is built from those
+// dots rather than nalgebra's gemm, and the blocked paths are
+// bypassed, so the f64 pipeline and the CPU simulator are
+// reproducible across builds and machines. It is process-wide and off by default. It costs about 2×
+// on transcendentals, and large N loses the blocked kernels.
+// ════════════════════════════════════════════════════════════════
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+pub fn set_enabled(enabled: bool) {
+    STRICT.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+pub fn exp(x: f64) -> f64 {
+    if enabled() { fdlibm_exp(x) } else { x.exp() }
+}
+
+pub fn ln(x: f64) -> f64 {
+    if enabled() { fdlibm_log(x) } else { x.ln() }
+}
+
+/// ln(1 + x), accurate near 0 (Goldberg's correction of the rounded 1 + x)
+pub fn ln_1p(x: f64) -> f64 {
+    if !enabled() {
+        return x.ln_1p();
+    }
+    let u = 1.0 + x;
+    if u == 1.0 {
+        x
+    } else if u.is_infinite() {
+        u
+    } else {
+        fdlibm_log(u) * x / (u - 1.0)
+    }
+}
+
+/// exp(y·ln x) for finite x > 0, the only case the engine needs; zero,
+/// negative and non-finite bases are left to libm
+pub fn powf(x: f64, y: f64) -> f64 {
+    if enabled() && x > 0.0 && x.is_finite() {
+        fdlibm_exp(y * fdlibm_log(x))
+    } else {
+        x.powf(y)
+    }
+}
+
+pub fn sin_cos(x: f64) -> (f64, f64) {
+    if enabled() { fdlibm_sin_cos(x) } else { x.sin_cos() }
+}
+
+pub fn sin(x: f64) -> f64 {
+    sin_cos(x).0
+}
+
+// ────────────────────────────────────────────────────────────────
+// Fixed-order reductions
+// Pairwise: halve at len/2 down to LEAF terms, added left to right.
+// The order depends only on the length, and the rounding error grows
+// as log N rather than N.
+// ────────────────────────────────────────────────────────────────
+const LEAF: usize = 8;
+
+fn pairwise(range: Range<usize>, term: &impl Fn(usize) -> f64) -> f64 {
+    if range.len() <= LEAF {
+        return range.map(term).fold(0.0, |sum, x| sum + x);
+    }
+    let mid = range.start + range.len() / 2;
+    pairwise(range.start..mid, term) + pairwise(mid..range.end, term)
+}
+
+/// Σ aᵢ·bᵢ over the shorter of the two slices
+pub fn dot(a: &[f64], b: &[f64]) -> f64 {
+    pairwise(0..a.len().min(b.len()), &|i| a[i] * b[i])
+}
+
+/// L·Lᵀ by pairwise row dots, exactly symmetric
+pub fn gram(l: &DMatrix<f64>) -> DMatrix<f64> {
+    let n = l.nrows();
+    let lt = l.transpose();
+    let mut out = DMatrix::zeros(n, n);
+    for i in 0..n {
+        for j in 0..=i {
+            let x = dot(lt.column(i).as_slice(), lt.column(j).as_slice());
+            out[(i, j)] = x;
+            out[(j, i)] = x;
+        }
+    }
+    out
+}
+
+/// V·diag(λ)·Vᵀ by pairwise dots of (V·diag(λ)) rows with V rows,
+/// exactly symmetric
+pub fn reconstruct(vectors: &DMatrix<f64>, values: &DVector<f64>) -> DMatrix<f64> {
+    let n = vectors.nrows();
+    let vt = vectors.transpose();
+    let mut weighted = vt.clone();
+    for mut col in weighted.column_iter_mut() {
+        col.component_mul_assign(values);
+    }
+    let mut out = DMatrix::zeros(n, n);
+    for i in 0..n {
+        for j in 0..=i {
+            let x = dot(weighted.column(i).as_slice(), vt.column(j).as_slice());
+            out[(i, j)] = x;
+            out[(j, i)] = x;
+        }
+    }
+    out
+}
+
+// ────────────────────────────────────────────────────────────────
+// fdlibm exp
+// x = k·ln2 + r with |r| ≤ ½ln2 (ln2 split hi/lo so k·ln2_hi is
+// exact), then a degree-5 minimax rational for e^r and a 2^k scale.
+// Error below 1 ulp.
+// ────────────────────────────────────────────────────────────────
+fn fdlibm_exp(x: f64) -> f64 {
+    const LN2_HI: f64 = 0.6931471803691238;
+    const LN2_LO: f64 = 1.9082149292705877e-10;
+    const INV_LN2: f64 = std::f64::consts::LOG2_E;
+    const P1: f64 = 0.16666666666666602;
+    const P2: f64 = -0.0027777777777015593;
+    const P3: f64 = 6.613756321437934e-05;
+    const P4: f64 = -1.6533902205465252e-06;
+    const P5: f64 = 4.1381367970572385e-08;
+
+    let hx = (x.to_bits() >> 32) as u32 & 0x7fff_ffff;
+    let negative = x.is_sign_negative();
+    if hx >= 0x4086_232b {
+        // |x| ≥ 708.39
+        if x.is_nan() {
+            return x;
+        }
+        if x > 709.782712893384 {
+            return f64::INFINITY;
+        }
+        if x < -745.1332191019411 {
+            return 0.0;
+        }
+    }
+    let (hi, lo, k) = if hx > 0x3fd6_2e42 {
+        // |x| > ½ln2
+        let k = if hx >= 0x3ff0_a2b2 {
+            (INV_LN2 * x + if negative { -0.5 } else { 0.5 }) as i32
+        } else if negative {
+            -1
+        } else {
+            1
+        };
+        (x - k as f64 * LN2_HI, k as f64 * LN2_LO, k)
+    } else if hx > 0x3e30_0000 {
+        // |x| > 2⁻²⁸
+        (x, 0.0, 0)
+    } else {
+        return 1.0 + x;
+    };
+    let r = hi - lo;
+    let rr = r * r;
+    let c = r - rr * (P1 + rr * (P2 + rr * (P3 + rr * (P4 + rr * P5))));
+    let y = 1.0 + (r * c / (2.0 - c) - lo + hi);
+    if k == 0 { y } else { scalbn(y, k) }
+}
+
+/// y·2ⁿ without double rounding in the subnormal range
+fn scalbn(mut y: f64, mut n: i32) -> f64 {
+    const TWO_1023: f64 = 8.98846567431158e307;
+    // 2⁻¹⁰²² · 2⁵³
+    const TWO_M969: f64 = 2.004168360008973e-292;
+    if n > 1023 {
+        y *= TWO_1023;
+        n -= 1023;
+        if n > 1023 {
+            y *= TWO_1023;
+            n = (n - 1023).min(1023);
+        }
+    } else if n < -1022 {
+        y *= TWO_M969;
+        n += 1022 - 53;
+        if n < -1022 {
+            y *= TWO_M969;
+            n = (n + 1022 - 53).max(-1022);
+        }
+    }
+    y * f64::from_bits(((0x3ff + n) as u64) << 52)
+}
+
+// ────────────────────────────────────────────────────────────────
+// fdlibm log
+// x = 2ᵏ·(1 + f) with 1 + f ∈ [√2/2, √2), then ln(1 + f) from the
+// series in s = f/(2 + f), a degree-14 minimax in s². Error below 1 ulp.
+// ────────────────────────────────────────────────────────────────
+fn fdlibm_log(x: f64) -> f64 {
+    const LN2_HI: f64 = 0.6931471803691238;
+    const LN2_LO: f64 = 1.9082149292705877e-10;
+    const LG1: f64 = 0.6666666666666735;
+    const LG2: f64 = 0.3999999999940942;
+    const LG3: f64 = 0.2857142874366239;
+    const LG4: f64 = 0.22222198432149784;
+    const LG5: f64 = 0.1818357216161805;
+    const LG6: f64 = 0.15313837699209373;
+    const LG7: f64 = 0.14798198605116586;
+    // 2⁵⁴
+    const TWO_54: f64 = 1.8014398509481984e16;
+
+    let mut bits = x.to_bits();
+    let mut hx = (bits >> 32) as u32;
+    let mut k: i32 = 0;
+    if hx < 0x0010_0000 || hx >> 31 != 0 {
+        if bits << 1 == 0 {
+            return f64::NEG_INFINITY;
+        }
+        if hx >> 31 != 0 {
+            return f64::NAN;
+        }
+        // Subnormal: scale into the normal range
+        k -= 54;
+        bits = (x * TWO_54).to_bits();
+        hx = (bits >> 32) as u32;
+    } else if hx >= 0x7ff0_0000 {
+        return x;
+    } else if hx == 0x3ff0_0000 && bits << 32 == 0 {
+        return 0.0;
+    }
+
+    hx += 0x3ff0_0000 - 0x3fe6_a09e;
+    k += (hx >> 20) as i32 - 0x3ff;
+    hx = (hx & 0x000f_ffff) + 0x3fe6_a09e;
+    let x = f64::from_bits((hx as u64) << 32 | (bits & 0xffff_ffff));
+
+    let f = x - 1.0;
+    let hfsq = 0.5 * f * f;
+    let s = f / (2.0 + f);
+    let z = s * s;
+    let w = z * z;
+    let t1 = w * (LG2 + w * (LG4 + w * LG6));
+    let t2 = z * (LG1 + w * (LG3 + w * (LG5 + w * LG7)));
+    let r = t2 + t1;
+    let dk = k as f64;
+    s * (hfsq + r) + dk * LN2_LO - hfsq + f + dk * LN2_HI
+}
+
+// ────────────────────────────────────────────────────────────────
+// fdlibm sin / cos
+// x = n·π/2 + (y₀ + y₁) by Cody–Waite with π/2 split at 33 bits, then
+// the degree-13 sin and degree-14 cos kernels on |y| ≤ π/4. The split
+// is exact for |x| < 2²⁰·π/2 (the simulator only needs [0, 2π)); past
+// that accuracy degrades, but the result is still the same on every
+// target.
+// ────────────────────────────────────────────────────────────────
+fn fdlibm_sin_cos(x: f64) -> (f64, f64) {
+    const INV_PIO2: f64 = std::f64::consts::FRAC_2_PI;
+    const PIO2_1: f64 = 1.5707963267341256;
+    const PIO2_1T: f64 = 6.077100506506192e-11;
+
+    if !x.is_finite() {
+        return (f64::NAN, f64::NAN);
+    }
+    let (n, y0, y1) = if x.abs() <= std::f64::consts::FRAC_PI_4 {
+        (0, x, 0.0)
+    } else {
+        let q = (x * INV_PIO2).round();
+        let r = x - q * PIO2_1;
+        let w = q * PIO2_1T;
+        let y0 = r - w;
+        (q as i64, y0, (r - y0) - w)
+    };
+    let (s, c) = (kernel_sin(y0, y1), kernel_cos(y0, y1));
+    match n & 3 {
+        0 => (s, c),
+        1 => (c, -s),
+        2 => (-s, -c),
+        _ => (-c, s),
+    }
+}
+
+/// sin(x + y) for |x + y| ≤ π/4, y the tail of the reduced argument
+fn kernel_sin(x: f64, y: f64) -> f64 {
+    const S1: f64 = -0.16666666666666632;
+    const S2: f64 = 0.00833333333332249;
+    const S3: f64 = -0.0001984126982985795;
+    const S4: f64 = 2.7557313707070068e-06;
+    const S5: f64 = -2.5050760253406863e-08;
+    const S6: f64 = 1.58969099521155e-10;
+    let z = x * x;
+    let w = z * z;
+    let r = S2 + z * (S3 + z * S4) + z * w * (S5 + z * S6);
+    let v = z * x;
+    if y == 0.0 {
+        x + v * (S1 + z * r)
+    } else {
+        x - ((z * (0.5 * y - v * r) - y) - v * S1)
+    }
+}
+
+/// cos(x + y) for |x + y| ≤ π/4
+fn kernel_cos(x: f64, y: f64) -> f64 {
+    const C1: f64 = 0.0416666666666666;
+    const C2: f64 = -0.001388888888887411;
+    const C3: f64 = 2.480158728947673e-05;
+    const C4: f64 = -2.7557314351390663e-07;
+    const C5: f64 = 2.087572321298175e-09;
+    const C6: f64 = -1.1359647557788195e-11;
+    let z = x * x;
+    let w = z * z;
+    let r = z * (C1 + z * (C2 + z * C3)) + w * w * (C4 + z * (C5 + z * C6));
+    let hz = 0.5 * z;
+    let w = 1.0 - hz;
+    w + (((1.0 - w) - hz) + (z * r - x * y))
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Distance in ulps, for same-sign finite values
+    fn ulps(a: f64, b: f64) -> u64 {
+        (a.to_bits() as i64 - b.to_bits() as i64).unsigned_abs()
+    }
+
+    #[test]
+    fn test_fdlibm_ports_match_libm() {
+        // The ports and the host libm are both within 1 ulp of the true
+        // value, so they agree to 1–2 ulps; calls bypass the global switch
+        for i in 0..20_000 {
+            let t = i as f64 / 20_000.0;
+            let x = -740.0 + 1449.0 * t;
+            assert!(ulps(fdlibm_exp(x), x.exp()) <= 2, "exp({x})");
+            let y = (1e-300f64).powf(1.0 - 2.0 * t);
+            assert!(ulps(fdlibm_log(y), y.ln()) <= 2 || (fdlibm_log(y) - y.ln()).abs() < 1e-300, "ln({y})");
+            let theta = -40.0 + 80.0 * t;
+            let (s, c) = fdlibm_sin_cos(theta);
+            assert!((s - theta.sin()).abs() <= 2.0 * f64::EPSILON * s.abs().max(1e-3), "sin({theta})");
+            assert!((c - theta.cos()).abs() <= 2.0 * f64::EPSILON * c.abs().max(1e-3), "cos({theta})");
+        }
+        assert_eq!(fdlibm_exp(0.0), 1.0);
+        assert_eq!(fdlibm_exp(800.0), f64::INFINITY);
+        assert_eq!(fdlibm_exp(-800.0), 0.0);
+        assert_eq!(fdlibm_exp(-745.0), 5e-324);
+        assert_eq!(fdlibm_log(1.0), 0.0);
+        assert_eq!(fdlibm_log(0.0), f64::NEG_INFINITY);
+        assert!(fdlibm_log(-1.0).is_nan() && fdlibm_exp(f64::NAN).is_nan());
+        assert_eq!(fdlibm_log(5e-324), (5e-324f64).ln());
+        assert_eq!(fdlibm_sin_cos(0.0), (0.0, 1.0));
+
+        // Pairwise order is fixed by length alone
+        let a: Vec<f64> = (0..1000).map(|i| 1.0 / (i as f64 + 1.0)).collect();
+        let b: Vec<f64> = (0..1000).map(|i| (i % 7) as f64 - 3.0).collect();
+        let sequential = a.iter().zip(&b).fold(0.0, |s, (x, y)| s + x * y);
+        assert!((dot(&a, &b) - sequential).abs() < 1e-13);
+        let l = DMatrix::from_fn(5, 5, |i, j| if j <= i { (i + 2 * j + 1) as f64 / 7.0 } else { 0.0 });
+        let g = gram(&l);
+        assert_eq!(g, g.transpose());
+        approx::assert_relative_eq!(g, &l * l.transpose(), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_reconstruct_is_fixed_order() {
+        // Against a sum written out by hand: halves down to 8 terms,
+        // each product (vᵢₖ·λₖ)·vⱼₖ, added left to right
+        fn reference(terms: &[f64]) -> f64 {
+            if terms.len() <= 8 {
+                return terms.iter().fold(0.0, |sum, &x| sum + x);
+            }
+            let (lo, hi) = terms.split_at(terms.len() / 2);
+            reference(lo) + reference(hi)
+        }
+        let n = 40;
+        let v = DMatrix::from_fn(n, n, |i, j| ((i * 31 + j * 17) % 23) as f64 / 23.0 - 0.45);
+        let lambda = DVector::from_fn(n, |k, _| 1.0 / (k as f64 + 1.0));
+        let out = reconstruct(&v, &lambda);
+        for i in 0..n {
+            for j in 0..=i {
+                let terms: Vec<f64> = (0..n).map(|k| (v[(i, k)] * lambda[k]) * v[(j, k)]).collect();
+                assert_eq!(out[(i, j)].to_bits(), reference(&terms).to_bits(), "({i}, {j})");
+            }
+        }
+        assert_eq!(out, out.transpose());
+        approx::assert_relative_eq!(out, &v * DMatrix::from_diagonal(&lambda) * v.transpose(), epsilon = 1e-12);
+    }
+}
@@ -0,0 +1,261 @@
+use nalgebra::{DMatrix, DVector};
+
+// ════════════════════════════════════════════════════════════════
+// Correlation structure — orderings and groupings for display
+// ════════════════════════════════════════════════════════════════
+
+// ────────────────────────────────────────────────────────────────
+// Hierarchical clustering
+// Agglomerative over the correlation distance d_ij = √(½·(1 − ρ_ij)),
+// which is 0 for ρ = 1 and 1 for ρ = −1. Each step merges the closest
+// pair of clusters (lowest ids on ties), with the cluster distance
+// set by the linkage. Merges are numbered like scipy's linkage matrix:
+// leaves are 0..N, merge k creates cluster N + k. The leaf order is the
+// dendrogram read left to right, so a heatmap permuted by it shows
+// the clusters as diagonal blocks.
+// ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Linkage {
+    /// Closest members
+    Single,
+    /// Farthest members
+    Complete,
+    /// Mean over member pairs (UPGMA)
+    Average,
+}
+
+impl Linkage {
+    /// "single", "complete" or "average"
+    pub fn from_name(name: &str) -> Result<Self, &'static str> {
+        match name.to_ascii_lowercase().as_str() {
+            "single" => Ok(Linkage::Single),
+            "complete" => Ok(Linkage::Complete),
+            "average" => Ok(Linkage::Average),
+            _ => Err("Clustering input invalid: linkage must be single, complete or average"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Merge {
+    /// Cluster ids, left < right
+    pub left: usize,
+    pub right: usize,
+    pub distance: f64,
+    /// Leaves under the new cluster
+    pub size: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dendrogram {
+    /// N − 1 merges, in order
+    pub merges: Vec<Merge>,
+    /// Leaves left to right
+    pub order: Vec<usize>,
+}
+
+impl Dendrogram {
+    /// Cluster label per asset after cutting into `k` clusters (the
+    /// last k − 1 merges undone), numbered 0..k in leaf order.
+    pub fn clusters(&self, k: usize) -> Result<Vec<usize>, &'static str> {
+        let n = self.order.len();
+        if k == 0 || k > n {
+            return Err("Clustering input invalid: cluster count must be between 1 and N");
+        }
+        // Each node's parent among the merges kept; roots point to themselves
+        let mut parent: Vec<usize> = (0..n + self.merges.len()).collect();
+        for (i, m) in self.merges[..n - k].iter().enumerate() {
+            parent[m.left] = n + i;
+            parent[m.right] = n + i;
+        }
+        let root = |mut i: usize| {
+            while parent[i] != i {
+                i = parent[i];
+            }
+            i
+        };
+        let mut label = vec![usize::MAX; parent.len()];
+        let mut labels = vec![0; n];
+        let mut next = 0;
+        for &leaf in &self.order {
+            let r = root(leaf);
+            if label[r] == usize::MAX {
+                label[r] = next;
+                next += 1;
+            }
+            labels[leaf] = label[r];
+        }
+        Ok(labels)
+    }
+}
+
+pub fn cluster_correlation(correlation: &DMatrix<f64>, linkage: Linkage) -> Result<Dendrogram, &'static str> {
+    let n = correlation.nrows();
+    if n == 0 || correlation.ncols() != n {
+        return Err("Clustering input mismatch: correlation must be N×N");
+    }
+    if correlation.iter().any(|v| !v.is_finite()) {
+        return Err("Clustering input invalid: correlation must be finite");
+    }
+    let mut dist = correlation.map(|r| (0.5 * (1.0 - r.clamp(-1.0, 1.0))).sqrt());
+    // Slot i holds cluster ids[i] while active; a merge reuses the lower slot
+    let mut ids: Vec<usize> = (0..n).collect();
+    let mut sizes = vec![1usize; n];
+    let mut active = vec![true; n];
+    let mut merges = Vec::with_capacity(n.saturating_sub(1));
+
+    for step in 0..n.saturating_sub(1) {
+        let mut best = (usize::MAX, usize::MAX, f64::INFINITY);
+        for i in (0..n).filter(|&i| active[i]) {
+            for j in (i + 1..n).filter(|&j| active[j]) {
+                if dist[(i, j)] < best.2 {
+                    best = (i, j, dist[(i, j)]);
+                }
+            }
+        }
+        let (i, j, d) = best;
+        let (si, sj) = (sizes[i] as f64, sizes[j] as f64);
+        for m in (0..n).filter(|&m| active[m] && m != i && m != j) {
+            let (di, dj) = (dist[(i, m)], dist[(j, m)]);
+            let merged = match linkage {
+                Linkage::Single => di.min(dj),
+                Linkage::Complete => di.max(dj),
+                Linkage::Average => (si * di + sj * dj) / (si + sj),
+            };
+            dist[(i, m)] = merged;
+            dist[(m, i)] = merged;
+        }
+        let (left, right) = (ids[i].min(ids[j]), ids[i].max(ids[j]));
+        merges.push(Merge { left, right, distance: d, size: sizes[i] + sizes[j] });
+        ids[i] = n + step;
+        sizes[i] += sizes[j];
+        active[j] = false;
+    }
+
+    let mut order = Vec::with_capacity(n);
+    let mut stack = vec![if n == 1 { 0 } else { 2 * n - 2 }];
+    while let Some(id) = stack.pop() {
+        if id < n {
+            order.push(id);
+        } else {
+            let m = &merges[id - n];
+            stack.push(m.right);
+            stack.push(m.left);
+        }
+    }
+    Ok(Dendrogram { merges, order })
+}
+
+// ────────────────────────────────────────────────────────────────
+// Principal components of Σ (or R)
+// The top k eigenpairs, largest first, with each eigenvalue's share of
+// the trace (total variance). Loadings are unit eigenvectors signed so
+// their entries sum to ≥ 0, so a market factor comes out long-only.
+// ────────────────────────────────────────────────────────────────
+
+#[derive(Clone, Debug)]
+pub struct Pca {
+    /// k values, descending
+    pub eigenvalues: DVector<f64>,
+    /// λ_i / tr Σ
+    pub explained_variance_ratio: DVector<f64>,
+    /// N×k, column i for eigenvalue i
+    pub loadings: DMatrix<f64>,
+}
+
+pub fn pca(covariance: &DMatrix<f64>, k: usize) -> Result<Pca, &'static str> {
+    let n = covariance.nrows();
+    if n == 0 || covariance.ncols() != n {
+        return Err("PCA input mismatch: covariance must be N×N");
+    }
+    if k == 0 || k > n {
+        return Err("PCA input invalid: k must be between 1 and N");
+    }
+    if covariance.iter().any(|v| !v.is_finite()) {
+        return Err("PCA input invalid: covariance must be finite");
+    }
+    let trace = covariance.trace();
+    if trace <= 0.0 {
+        return Err("PCA input invalid: covariance has no variance");
+    }
+    let eigen = covariance.clone().symmetric_eigen();
+    let mut idx: Vec<usize> = (0..n).collect();
+    idx.sort_by(|&a, &b| eigen.eigenvalues[b].total_cmp(&eigen.eigenvalues[a]));
+    idx.truncate(k);
+
+    let eigenvalues = DVector::from_iterator(k, idx.iter().map(|&i| eigen.eigenvalues[i]));
+    let mut loadings = eigen.eigenvectors.select_columns(&idx);
+    for mut column in loadings.column_iter_mut() {
+        if column.sum() < 0.0 {
+            column.neg_mut();
+        }
+    }
+    Ok(Pca { explained_variance_ratio: &eigenvalues / trace, eigenvalues, loadings })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_clustering_groups_interleaved_blocks() {
+        // Assets 0, 2, 4 and 1, 3, 5 form two blocks (ρ = 0.8 inside,
+        // 0.1 across); asset 4 sits a little apart from 0 and 2
+        let r = DMatrix::from_fn(6, 6, |a, b| match (a, b) {
+            _ if a == b => 1.0,
+            (0, 4) | (4, 0) => 0.6,
+            (2, 4) | (4, 2) => 0.4,
+            _ if a % 2 == b % 2 => 0.8,
+            _ => 0.1,
+        });
+        for linkage in [Linkage::Single, Linkage::Complete, Linkage::Average] {
+            let d = cluster_correlation(&r, linkage).unwrap();
+            assert_eq!(d.merges.len(), 5);
+            assert!(d.merges.windows(2).all(|w| w[0].distance <= w[1].distance));
+            assert_eq!(d.merges[4].size, 6);
+            assert_relative_eq!(d.merges[0].distance, 0.1f64.sqrt(), epsilon = 1e-12);
+            assert_relative_eq!(d.merges[4].distance, 0.45f64.sqrt(), epsilon = 1e-12);
+
+            // Each block contiguous in the leaf order
+            let first: Vec<usize> = d.order[..3].iter().map(|a| a % 2).collect();
+            assert!(first.iter().all(|&p| p == first[0]));
+            let labels = d.clusters(2).unwrap();
+            assert!((0..6).all(|a| labels[a] == labels[a % 2]));
+            assert_ne!(labels[0], labels[1]);
+            assert_eq!(d.clusters(6).unwrap().iter().filter(|&&l| l == 0).count(), 1);
+        }
+        // Linkages differ in how far 4 sits from {0, 2}
+        let single = cluster_correlation(&r, Linkage::Single).unwrap();
+        let complete = cluster_correlation(&r, Linkage::Complete).unwrap();
+        let join_4 = |d: &Dendrogram| d.merges.iter().find(|m| m.right == 4 || m.left == 4).unwrap().distance;
+        assert_relative_eq!(join_4(&single), 0.2f64.sqrt(), epsilon = 1e-12);
+        assert_relative_eq!(join_4(&complete), 0.3f64.sqrt(), epsilon = 1e-12);
+        assert!(Linkage::from_name("ward").is_err());
+    }
+
+    #[test]
+    fn test_pca_one_factor_dominates() {
+        // Equicorrelated ρ = 0.9 over 4 assets: λ₁ = 1 + 3ρ, the rest 1 − ρ
+        let r = DMatrix::from_fn(4, 4, |a, b| if a == b { 1.0 } else { 0.9 });
+        let vol = DVector::from_vec(vec![0.1, 0.2, 0.3, 0.4]);
+        let p = pca(&r, 2).unwrap();
+        assert_relative_eq!(p.eigenvalues[0], 3.7, epsilon = 1e-12);
+        assert_relative_eq!(p.eigenvalues[1], 0.1, epsilon = 1e-12);
+        assert_relative_eq!(p.explained_variance_ratio[0], 0.925, epsilon = 1e-12);
+        assert!(p.loadings.column(0).iter().all(|&x| (x - 0.5).abs() < 1e-12));
+        assert_eq!(p.loadings.shape(), (4, 2));
+
+        // Σ = D·R·D with all N components: ratios sum to 1, loadings orthonormal
+        let cov = DMatrix::from_diagonal(&vol) * &r * DMatrix::from_diagonal(&vol);
+        let p = pca(&cov, 4).unwrap();
+        assert_relative_eq!(p.explained_variance_ratio.sum(), 1.0, epsilon = 1e-12);
+        assert_relative_eq!(p.loadings.transpose() * &p.loadings, DMatrix::identity(4, 4), epsilon = 1e-12);
+        assert!(p.eigenvalues.as_slice().windows(2).all(|w| w[0] >= w[1]));
+        assert!(pca(&cov, 5).is_err());
+    }
+}
@@ -0,0 +1,50 @@
+/* cc examples/example.c -Iinclude -Ltarget/debug -lmssim -o example && LD_LIBRARY_PATH=target/debug ./example */
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "mssim.h"
+
+#define N 2
+#define STEPS 252
+#define PATHS 10000
+
+int main(void) {
+  const double drift[N] = {0.08, 0.03};
+  const double vol[N] = {0.18, 0.05};
+  const double correlation[N * N] = {1.0, 0.2, 0.2, 1.0};
+  const double delta_drift[N] = {-0.15, 0.01};
+  const double vol_multiplier[N] = {2.0, 1.2};
+
+  MssimEngine *engine = mssim_engine_new(N, drift, vol, correlation);
+  if (!engine) {
+    fprintf(stderr, "mssim_engine_new: %s\n", mssim_last_error());
+    return 1;
+  }
+  if (mssim_compute_shock(engine, delta_drift, vol_multiplier, 0.6) != MSSIM_STATUS_OK) {
+    fprintf(stderr, "mssim_compute_shock: %s\n", mssim_last_error());
+    mssim_engine_free(engine);
+    return 1;
+  }
+
+  MssimSimOptions options = {1.0, STEPS, PATHS, 42, 0.0, 0.0, 0.0};
+  size_t len = (size_t)PATHS * (STEPS + 1) * N;
+  float *paths = malloc(len * sizeof(float));
+  double *pnl = malloc(PATHS * N * sizeof(double));
+  if (mssim_simulate(engine, &options, paths, len) != MSSIM_STATUS_OK) {
+    fprintf(stderr, "mssim_simulate: %s\n", mssim_last_error());
+    return 1;
+  }
+  for (size_t p = 0; p < PATHS; p++)
+    for (size_t a = 0; a < N; a++)
+      pnl[p * N + a] = paths[(p * (STEPS + 1) + STEPS) * N + a] - 1.0;
+
+  const double weights[N] = {0.6, 0.4};
+  double var, cvar;
+  mssim_var_cvar(pnl, PATHS, N, weights, 0.99, &var, &cvar);
+  printf("99%% VaR %.4f  CVaR %.4f\n", var, cvar);
+
+  free(pnl);
+  free(paths);
+  mssim_engine_free(engine);
+  return 0;
+}
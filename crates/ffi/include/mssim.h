@@ -0,0 +1,173 @@
+#ifndef MSSIM_H
+#define MSSIM_H
+
+/* Generated by cbindgen from crates/ffi/src/lib.rs — do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum MssimStatus {
+  MSSIM_STATUS_OK = 0,
+  /**
+   * Null pointer, wrong buffer length or out-of-range parameter
+   */
+  MSSIM_STATUS_INVALID_ARGUMENT = 1,
+  /**
+   * The engine rejected the inputs (see mssim_last_error)
+   */
+  MSSIM_STATUS_ENGINE_ERROR = 2,
+  /**
+   * Bug in the engine; the handle is still safe to free
+   */
+  MSSIM_STATUS_PANIC = 3,
+} MssimStatus;
+
+typedef struct MssimEngine MssimEngine;
+
+/**
+ * Simulation settings for mssim_simulate
+ */
+typedef struct MssimSimOptions {
+  double horizon;
+  size_t steps;
+  size_t n_paths;
+  uint64_t seed;
+  double jump_lambda;
+  double jump_mean;
+  double jump_vol;
+} MssimSimOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Last failure message on this thread ("" after a successful call).
+ * The pointer stays valid until the next mssim_* call on the thread.
+ */
+const char *mssim_last_error(void);
+
+/**
+ * New engine over an N-asset base market, or null on invalid input
+ * (see mssim_last_error). Free with mssim_engine_free.
+ *
+ * # Safety
+ * `base_drift` and `base_vol` must point to N doubles and
+ * `base_correlation` to N×N doubles (row-major).
+ */
+MssimEngine *mssim_engine_new(size_t n,
+                              const double *base_drift,
+                              const double *base_vol,
+                              const double *base_correlation);
+
+/**
+ * # Safety
+ * `engine` must come from mssim_engine_new (or be null) and not be
+ * used afterwards.
+ */
+void mssim_engine_free(MssimEngine *engine);
+
+/**
+ * # Safety
+ * `engine` must be a live handle or null.
+ */
+size_t mssim_num_assets(const MssimEngine *engine);
+
+/**
+ * Run the shock pipeline; results are read with mssim_get_*.
+ *
+ * # Safety
+ * `engine` must be a live handle; `delta_drift` and `vol_multiplier`
+ * must point to N doubles.
+ */
+MssimStatus mssim_compute_shock(MssimEngine *engine,
+                                const double *delta_drift,
+                                const double *vol_multiplier,
+                                double correlation_skew);
+
+/**
+ * Shocked drift into `out` (N doubles).
+ *
+ * # Safety
+ * `engine` must be a live handle and `out` point to N doubles.
+ */
+MssimStatus mssim_get_drift(const MssimEngine *engine, double *out);
+
+/**
+ * Shocked vol into `out` (N doubles).
+ *
+ * # Safety
+ * `engine` must be a live handle and `out` point to N doubles.
+ */
+MssimStatus mssim_get_vol(const MssimEngine *engine, double *out);
+
+/**
+ * Cholesky factor L (N×N row-major) into `out`.
+ *
+ * # Safety
+ * `engine` must be a live handle and `out` point to N×N doubles.
+ */
+MssimStatus mssim_get_cholesky(const MssimEngine *engine, double *out);
+
+/**
+ * Black–Litterman posterior drift into `out` (N doubles), over the
+ * shocked Σ = L·Lᵀ: the equilibrium r_f + δ·Σ·w blended with
+ * `n_views` views. View k is row k of `view_portfolios` (n_views × N
+ * row-major) with annual drift `view_returns[k]` and confidence
+ * `view_confidences[k]` in (0, 1]; the view arrays may be null when
+ * n_views is 0.
+ *
+ * # Safety
+ * `engine` must be a live handle, `market_weights` and `out` point to
+ * N doubles, and the view arrays to n_views × N, n_views and n_views
+ * doubles.
+ */
+MssimStatus mssim_black_litterman(const MssimEngine *engine,
+                                  const double *market_weights,
+                                  double risk_aversion,
+                                  double tau,
+                                  double risk_free,
+                                  size_t n_views,
+                                  const double *view_portfolios,
+                                  const double *view_returns,
+                                  const double *view_confidences,
+                                  double *out);
+
+/**
+ * Paths for the shocked market into `out`, which must hold exactly
+ * n_paths × (steps + 1) × N floats.
+ *
+ * # Safety
+ * `engine` must be a live handle, `options` point to an
+ * MssimSimOptions and `out` to `out_len` floats.
+ */
+MssimStatus mssim_simulate(const MssimEngine *engine,
+                           const MssimSimOptions *options,
+                           float *out,
+                           size_t out_len);
+
+/**
+ * VaR and CVaR (positive losses) at `level` of the weighted P&L.
+ * `pnl` is n_paths × n_assets row-major; with `weights` null it must
+ * be one portfolio value per path (n_assets = 1).
+ *
+ * # Safety
+ * `pnl` must point to n_paths × n_assets doubles, `weights` to
+ * n_assets doubles or be null, and the outputs to one double each.
+ */
+MssimStatus mssim_var_cvar(const double *pnl,
+                           size_t n_paths,
+                           size_t n_assets,
+                           const double *weights,
+                           double level,
+                           double *out_var,
+                           double *out_cvar);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MSSIM_H */
@@ -0,0 +1,449 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use nalgebra::{DMatrix, DVector};
+
+use mssim_engine::simulate::{self, JumpParams, SimConfig};
+use mssim_engine::calibrate::{self, View};
+use mssim_engine::{math, risk, shock_market, ShockedMarket};
+
+// ════════════════════════════════════════════════════════════════
+// C ABI for embedding the engine (C, C++, C# P/Invoke, …)
+//
+// An opaque MssimEngine holds the base market and the last shocked
+// market. Functions return MssimStatus; on failure the message is
+// available from mssim_last_error() on the same thread until the next
+// call. Matrices are row-major doubles, paths row-major floats laid
+// out [path][step][asset]. Panics never cross the boundary: they come
+// back as MSSIM_STATUS_PANIC.
+//
+// include/mssim.h is generated from this file by cbindgen (see
+// cbindgen.toml).
+// ════════════════════════════════════════════════════════════════
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MssimStatus {
+    Ok = 0,
+    /// Null pointer, wrong buffer length or out-of-range parameter
+    InvalidArgument = 1,
+    /// The engine rejected the inputs (see mssim_last_error)
+    EngineError = 2,
+    /// Bug in the engine; the handle is still safe to free
+    Panic = 3,
+}
+
+/// Simulation settings for mssim_simulate
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MssimSimOptions {
+    pub horizon: f64,
+    pub steps: usize,
+    pub n_paths: usize,
+    pub seed: u64,
+    pub jump_lambda: f64,
+    pub jump_mean: f64,
+    pub jump_vol: f64,
+}
+
+pub struct MssimEngine {
+    base_drift: DVector<f64>,
+    base_vol: DVector<f64>,
+    base_correlation: DMatrix<f64>,
+    market: Option<ShockedMarket>,
+}
+
+// ────────────────────────────────────────────────────────────────
+// Error plumbing
+// ────────────────────────────────────────────────────────────────
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+type Failure = (MssimStatus, String);
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = message);
+}
+
+fn invalid(message: impl Into<String>) -> Failure {
+    (MssimStatus::InvalidArgument, message.into())
+}
+
+fn engine_error(e: impl ToString) -> Failure {
+    (MssimStatus::EngineError, e.to_string())
+}
+
+/// Run `body`, recording any failure (or panic) for mssim_last_error.
+fn boundary(body: impl FnOnce() -> Result<(), Failure>) -> MssimStatus {
+    let (status, message) = match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => (MssimStatus::Ok, String::new()),
+        Ok(Err(failure)) => failure,
+        Err(_) => (MssimStatus::Panic, "internal error: the engine panicked".into()),
+    };
+    set_last_error(&message);
+    status
+}
+
+/// `len` values at `data`, or an error naming `field` if it is null.
+unsafe fn slice<'a, T>(data: *const T, len: usize, field: &str) -> Result<&'a [T], Failure> {
+    if data.is_null() {
+        return Err(invalid(format!("{field} is null")));
+    }
+    Ok(std::slice::from_raw_parts(data, len))
+}
+
+unsafe fn slice_mut<'a, T>(data: *mut T, len: usize, field: &str) -> Result<&'a mut [T], Failure> {
+    if data.is_null() {
+        return Err(invalid(format!("{field} is null")));
+    }
+    Ok(std::slice::from_raw_parts_mut(data, len))
+}
+
+unsafe fn handle_ref<'a>(handle: *const MssimEngine) -> Result<&'a MssimEngine, Failure> {
+    handle.as_ref().ok_or_else(|| invalid("engine handle is null"))
+}
+
+fn shocked(engine: &MssimEngine) -> Result<&ShockedMarket, Failure> {
+    engine.market.as_ref().ok_or_else(|| invalid("call mssim_compute_shock first"))
+}
+
+/// Last failure message on this thread ("" after a successful call).
+/// The pointer stays valid until the next mssim_* call on the thread.
+#[no_mangle]
+pub extern "C" fn mssim_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ptr())
+}
+
+// ────────────────────────────────────────────────────────────────
+// Engine handle
+// ────────────────────────────────────────────────────────────────
+
+/// New engine over an N-asset base market, or null on invalid input
+/// (see mssim_last_error). Free with mssim_engine_free.
+///
+/// # Safety
+/// `base_drift` and `base_vol` must point to N doubles and
+/// `base_correlation` to N×N doubles (row-major).
+#[no_mangle]
+pub unsafe extern "C" fn mssim_engine_new(
+    n: usize,
+    base_drift: *const f64,
+    base_vol: *const f64,
+    base_correlation: *const f64,
+) -> *mut MssimEngine {
+    let mut handle = ptr::null_mut();
+    boundary(|| {
+        if n == 0 {
+            return Err(invalid("n must be positive"));
+        }
+        let correlation = DMatrix::from_row_slice(n, n, slice(base_correlation, n * n, "base_correlation")?);
+        math::validate_correlation(&correlation, 1e-6)
+            .map_err(|d| engine_error(format!("{} at ({}, {})", d.reason, d.row, d.col)))?;
+        let engine = MssimEngine {
+            base_drift: DVector::from_column_slice(slice(base_drift, n, "base_drift")?),
+            base_vol: DVector::from_column_slice(slice(base_vol, n, "base_vol")?),
+            base_correlation: correlation,
+            market: None,
+        };
+        handle = Box::into_raw(Box::new(engine));
+        Ok(())
+    });
+    handle
+}
+
+/// # Safety
+/// `engine` must come from mssim_engine_new (or be null) and not be
+/// used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn mssim_engine_free(engine: *mut MssimEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+/// # Safety
+/// `engine` must be a live handle or null.
+#[no_mangle]
+pub unsafe extern "C" fn mssim_num_assets(engine: *const MssimEngine) -> usize {
+    engine.as_ref().map_or(0, |e| e.base_drift.len())
+}
+
+// ────────────────────────────────────────────────────────────────
+// Phase A
+// ────────────────────────────────────────────────────────────────
+
+/// Run the shock pipeline; results are read with mssim_get_*.
+///
+/// # Safety
+/// `engine` must be a live handle; `delta_drift` and `vol_multiplier`
+/// must point to N doubles.
+#[no_mangle]
+pub unsafe extern "C" fn mssim_compute_shock(
+    engine: *mut MssimEngine,
+    delta_drift: *const f64,
+    vol_multiplier: *const f64,
+    correlation_skew: f64,
+) -> MssimStatus {
+    boundary(|| {
+        let engine = engine.as_mut().ok_or_else(|| invalid("engine handle is null"))?;
+        let n = engine.base_drift.len();
+        let market = shock_market(
+            &engine.base_drift,
+            &engine.base_vol,
+            &engine.base_correlation,
+            &DVector::from_column_slice(slice(delta_drift, n, "delta_drift")?),
+            &DVector::from_column_slice(slice(vol_multiplier, n, "vol_multiplier")?),
+            correlation_skew,
+            true,
+        )
+        .map_err(engine_error)?;
+        engine.market = Some(market);
+        Ok(())
+    })
+}
+
+/// Shocked drift into `out` (N doubles).
+///
+/// # Safety
+/// `engine` must be a live handle and `out` point to N doubles.
+#[no_mangle]
+pub unsafe extern "C" fn mssim_get_drift(engine: *const MssimEngine, out: *mut f64) -> MssimStatus {
+    boundary(|| {
+        let market = shocked(handle_ref(engine)?)?;
+        slice_mut(out, market.drift.len(), "out")?.copy_from_slice(market.drift.as_slice());
+        Ok(())
+    })
+}
+
+/// Shocked vol into `out` (N doubles).
+///
+/// # Safety
+/// `engine` must be a live handle and `out` point to N doubles.
+#[no_mangle]
+pub unsafe extern "C" fn mssim_get_vol(engine: *const MssimEngine, out: *mut f64) -> MssimStatus {
+    boundary(|| {
+        let market = shocked(handle_ref(engine)?)?;
+        slice_mut(out, market.vol.len(), "out")?.copy_from_slice(market.vol.as_slice());
+        Ok(())
+    })
+}
+
+/// Cholesky factor L (N×N row-major) into `out`.
+///
+/// # Safety
+/// `engine` must be a live handle and `out` point to N×N doubles.
+#[no_mangle]
+pub unsafe extern "C" fn mssim_get_cholesky(engine: *const MssimEngine, out: *mut f64) -> MssimStatus {
+    boundary(|| {
+        let market = shocked(handle_ref(engine)?)?;
+        let l = &market.cholesky_l;
+        let n = l.nrows();
+        for (k, x) in slice_mut(out, n * n, "out")?.iter_mut().enumerate() {
+            *x = l[(k / n, k % n)];
+        }
+        Ok(())
+    })
+}
+
+/// Black–Litterman posterior drift into `out` (N doubles), over the
+/// shocked Σ = L·Lᵀ: the equilibrium r_f + δ·Σ·w blended with
+/// `n_views` views. View k is row k of `view_portfolios` (n_views × N
+/// row-major) with annual drift `view_returns[k]` and confidence
+/// `view_confidences[k]` in (0, 1]; the view arrays may be null when
+/// n_views is 0.
+///
+/// # Safety
+/// `engine` must be a live handle, `market_weights` and `out` point to
+/// N doubles, and the view arrays to n_views × N, n_views and n_views
+/// doubles.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn mssim_black_litterman(
+    engine: *const MssimEngine,
+    market_weights: *const f64,
+    risk_aversion: f64,
+    tau: f64,
+    risk_free: f64,
+    n_views: usize,
+    view_portfolios: *const f64,
+    view_returns: *const f64,
+    view_confidences: *const f64,
+    out: *mut f64,
+) -> MssimStatus {
+    boundary(|| {
+        let market = shocked(handle_ref(engine)?)?;
+        let n = market.drift.len();
+        let weights = DVector::from_column_slice(slice(market_weights, n, "market_weights")?);
+        let views = if n_views == 0 {
+            Vec::new()
+        } else {
+            let portfolios = slice(view_portfolios, n_views * n, "view_portfolios")?;
+            let returns = slice(view_returns, n_views, "view_returns")?;
+            let confidences = slice(view_confidences, n_views, "view_confidences")?;
+            (0..n_views)
+                .map(|k| View {
+                    portfolio: portfolios[k * n..(k + 1) * n].to_vec(),
+                    expected: returns[k],
+                    confidence: confidences[k],
+                })
+                .collect()
+        };
+        let l = &market.cholesky_l;
+        let bl = calibrate::black_litterman(&(l * l.transpose()), &weights, risk_aversion, tau, risk_free, &views)
+            .map_err(engine_error)?;
+        slice_mut(out, n, "out")?.copy_from_slice(bl.posterior.as_slice());
+        Ok(())
+    })
+}
+
+// ────────────────────────────────────────────────────────────────
+// Simulation and risk
+// ────────────────────────────────────────────────────────────────
+
+/// Paths for the shocked market into `out`, which must hold exactly
+/// n_paths × (steps + 1) × N floats.
+///
+/// # Safety
+/// `engine` must be a live handle, `options` point to an
+/// MssimSimOptions and `out` to `out_len` floats.
+#[no_mangle]
+pub unsafe extern "C" fn mssim_simulate(
+    engine: *const MssimEngine,
+    options: *const MssimSimOptions,
+    out: *mut f32,
+    out_len: usize,
+) -> MssimStatus {
+    boundary(|| {
+        let market = shocked(handle_ref(engine)?)?;
+        let o = options.as_ref().ok_or_else(|| invalid("options is null"))?;
+        let want = o.n_paths * (o.steps + 1) * market.drift.len();
+        if out_len != want {
+            return Err(invalid(format!("out must hold {want} floats, got {out_len}")));
+        }
+        let config = SimConfig::new(o.horizon, o.steps, o.n_paths, o.seed).with_jumps(JumpParams {
+            lambda: o.jump_lambda,
+            mean: o.jump_mean,
+            vol: o.jump_vol,
+        });
+        let paths = simulate::simulate_paths(&market.drift, &market.vol, &market.cholesky_l, &config)
+            .map_err(engine_error)?;
+        slice_mut(out, out_len, "out")?.copy_from_slice(&paths);
+        Ok(())
+    })
+}
+
+/// VaR and CVaR (positive losses) at `level` of the weighted P&L.
+/// `pnl` is n_paths × n_assets row-major; with `weights` null it must
+/// be one portfolio value per path (n_assets = 1).
+///
+/// # Safety
+/// `pnl` must point to n_paths × n_assets doubles, `weights` to
+/// n_assets doubles or be null, and the outputs to one double each.
+#[no_mangle]
+pub unsafe extern "C" fn mssim_var_cvar(
+    pnl: *const f64,
+    n_paths: usize,
+    n_assets: usize,
+    weights: *const f64,
+    level: f64,
+    out_var: *mut f64,
+    out_cvar: *mut f64,
+) -> MssimStatus {
+    boundary(|| {
+        let pnl = slice(pnl, n_paths * n_assets, "pnl")?;
+        let weights = match weights.is_null() {
+            true if n_assets != 1 => return Err(invalid("weights are required when n_assets > 1")),
+            true => &[][..],
+            false => slice(weights, n_assets, "weights")?,
+        };
+        let v = risk::compute_var_cvar(pnl, weights, &[level]).map_err(engine_error)?[0];
+        *slice_mut(out_var, 1, "out_var")?.first_mut().ok_or_else(|| invalid("out_var"))? = v.var;
+        *slice_mut(out_cvar, 1, "out_cvar")?.first_mut().ok_or_else(|| invalid("out_cvar"))? = v.cvar;
+        Ok(())
+    })
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use std::ffi::CStr;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(mssim_last_error()) }.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_shock_and_simulate_round_trip() {
+        let (mu, sigma) = ([0.08, 0.03], [0.2, 0.05]);
+        let r = [1.0, 0.3, 0.3, 1.0];
+        unsafe {
+            let e = mssim_engine_new(2, mu.as_ptr(), sigma.as_ptr(), r.as_ptr());
+            assert!(!e.is_null());
+            assert_eq!(mssim_num_assets(e), 2);
+
+            let mut l = [0.0; 4];
+            assert_eq!(mssim_get_cholesky(e, l.as_mut_ptr()), MssimStatus::InvalidArgument);
+            assert!(last_error().contains("mssim_compute_shock"));
+
+            let (dd, vm) = ([-0.1, 0.0], [2.0, 1.0]);
+            assert_eq!(mssim_compute_shock(e, dd.as_ptr(), vm.as_ptr(), 0.5), MssimStatus::Ok);
+            assert_eq!(last_error(), "");
+            assert_eq!(mssim_get_cholesky(e, l.as_mut_ptr()), MssimStatus::Ok);
+            assert_relative_eq!(l[0], 0.4, epsilon = 1e-12);
+            assert_eq!(l[1], 0.0);
+
+            let options = MssimSimOptions {
+                horizon: 1.0,
+                steps: 4,
+                n_paths: 8,
+                seed: 7,
+                jump_lambda: 0.0,
+                jump_mean: 0.0,
+                jump_vol: 0.0,
+            };
+            let mut paths = vec![0.0_f32; 8 * 5 * 2];
+            assert_eq!(mssim_simulate(e, &options, paths.as_mut_ptr(), paths.len() - 1), MssimStatus::InvalidArgument);
+            assert_eq!(mssim_simulate(e, &options, paths.as_mut_ptr(), paths.len()), MssimStatus::Ok);
+            assert_eq!(&paths[..2], &[1.0, 1.0]);
+
+            // A certain view on asset 0 pins its posterior drift
+            let (w, mut drift) = ([0.6, 0.4], [0.0; 2]);
+            let (p, q, c) = ([1.0, 0.0], [-0.2], [1.0]);
+            let status = mssim_black_litterman(e, w.as_ptr(), 2.5, 0.05, 0.0, 1, p.as_ptr(), q.as_ptr(), c.as_ptr(), drift.as_mut_ptr());
+            assert_eq!(status, MssimStatus::Ok);
+            assert_relative_eq!(drift[0], -0.2, epsilon = 1e-12);
+            let null = ptr::null();
+            let status = mssim_black_litterman(e, w.as_ptr(), 2.5, 0.05, 0.0, 0, null, null, null, drift.as_mut_ptr());
+            assert_eq!(status, MssimStatus::Ok);
+            assert!(drift[0] > 0.0);
+            mssim_engine_free(e);
+        }
+    }
+
+    #[test]
+    fn test_invalid_inputs_report_errors() {
+        let bad_r = [1.0, 0.3, 0.2, 1.0];
+        let x = [0.1, 0.1];
+        unsafe {
+            assert!(mssim_engine_new(2, x.as_ptr(), x.as_ptr(), bad_r.as_ptr()).is_null());
+            assert!(last_error().contains("symmetric"));
+            assert!(mssim_engine_new(2, ptr::null(), x.as_ptr(), [1.0, 0.0, 0.0, 1.0].as_ptr()).is_null());
+            assert_eq!(last_error(), "base_drift is null");
+
+            let pnl = [-0.3, -0.1, 0.0, 0.2];
+            let (mut var, mut cvar) = (0.0, 0.0);
+            assert_eq!(mssim_var_cvar(pnl.as_ptr(), 4, 1, ptr::null(), 0.75, &mut var, &mut cvar), MssimStatus::Ok);
+            assert_relative_eq!(var, 0.3);
+            assert_relative_eq!(cvar, 0.3);
+            assert_eq!(mssim_var_cvar(pnl.as_ptr(), 2, 2, ptr::null(), 0.75, &mut var, &mut cvar), MssimStatus::InvalidArgument);
+        }
+    }
+}
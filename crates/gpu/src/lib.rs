@@ -0,0 +1,474 @@
+use nalgebra::{DMatrix, DVector};
+use wgpu::util::DeviceExt;
+
+use mssim_engine::gpu::{BindingKind, BufferSize, Kernel, KernelOutput, KernelSpec, PARAMS_BYTES};
+use mssim_engine::rng::PathRng;
+use mssim_engine::simulate::{self, Driver, Innovations, MomentMatching, OutputLayout, PathConstruction, SimConfig, VolModel};
+
+// ════════════════════════════════════════════════════════════════
+// mssim-gpu — native wgpu backend for the Monte Carlo simulator
+//
+//     let paths = mssim_gpu::simulate_paths(Backend::Gpu, &drift, &vol, &l, &config)?;
+//
+// Runs the kernel gpu::KernelSpec generates (GpuKernel in the wasm
+// build) on a local adapter (Vulkan, Metal, DX12). Output has
+// simulate_paths' shape and layout. The GPU works in f32 with 24-bit
+// uniforms, so it is not bit-compatible with Backend::Cpu, which
+// draws f64 uniforms from all 32 bits; the two agree to about 1e-3.
+// Backend::GpuParity runs the kernel's own arithmetic on the CPU (f32,
+// ((u >> 8) + ½)/2²⁴ uniforms, the kernel's draw order), and the GPU
+// matches it to a few f32 ulps; what remains is vendor exp/log/cos.
+// Covered: constant-vol GBM, per-asset jumps, pcg32 / philox4x32,
+// either layout. Anything else in SimConfig needs Backend::Cpu.
+// ════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    #[default]
+    Cpu,
+    Gpu,
+    /// The GPU kernel's f32 arithmetic and draw order, run on the CPU
+    GpuParity,
+}
+
+impl Backend {
+    /// Parse "cpu", "gpu" or "gpu-parity"
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "cpu" => Ok(Backend::Cpu),
+            "gpu" => Ok(Backend::Gpu),
+            "gpu-parity" => Ok(Backend::GpuParity),
+            _ => Err(format!("unknown backend '{name}' (expected cpu, gpu or gpu-parity)")),
+        }
+    }
+}
+
+/// Price paths on `backend`, as simulate::simulate_paths. Gpu opens a
+/// device per call; keep a GpuContext to reuse one.
+pub fn simulate_paths(
+    backend: Backend,
+    drift: &DVector<f64>,
+    vol: &DVector<f64>,
+    cholesky_l: &DMatrix<f64>,
+    config: &SimConfig,
+) -> Result<Vec<f32>, String> {
+    match backend {
+        Backend::Cpu => Ok(simulate::simulate_paths(drift, vol, cholesky_l, config)?),
+        Backend::Gpu => GpuContext::new()?.simulate_paths(drift, vol, cholesky_l, config),
+        Backend::GpuParity => parity_paths(drift, vol, cholesky_l, config),
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// GpuContext — one adapter / device / queue
+// ────────────────────────────────────────────────────────────────
+pub struct GpuContext {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    adapter_name: String,
+}
+
+impl GpuContext {
+    pub fn new() -> Result<Self, String> {
+        pollster::block_on(async {
+            let instance = wgpu::Instance::default();
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::HighPerformance,
+                    ..Default::default()
+                })
+                .await
+                .ok_or("gpu: no compatible adapter")?;
+            let (device, queue) = adapter
+                .request_device(&wgpu::DeviceDescriptor::default(), None)
+                .await
+                .map_err(|e| format!("gpu: {e}"))?;
+            Ok::<_, String>(GpuContext { device, queue, adapter_name: adapter.get_info().name })
+        })
+    }
+
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+
+    pub fn simulate_paths(
+        &self,
+        drift: &DVector<f64>,
+        vol: &DVector<f64>,
+        cholesky_l: &DMatrix<f64>,
+        config: &SimConfig,
+    ) -> Result<Vec<f32>, String> {
+        let n = drift.len();
+        if vol.len() != n || cholesky_l.shape() != (n, n) {
+            return Err("gpu: vol must have length N and cholesky_l shape N×N".into());
+        }
+        check_supported(config, n)?;
+        let kernel = KernelSpec::new(n, config.steps)
+            .with_rng(config.rng)
+            .with_output(KernelOutput::Paths)
+            .with_jumps(config.jumps.is_some())
+            .generate()?;
+
+        let f32s = |xs: &[f64]| xs.iter().map(|&x| x as f32).collect::<Vec<f32>>();
+        let jump = |f: fn(&simulate::JumpParams) -> f64| match &config.jumps {
+            Some(j) => (0..n).map(|i| f(&j[if j.len() == 1 { 0 } else { i }]) as f32).collect(),
+            None => Vec::new(),
+        };
+        let inputs: Vec<(&str, Vec<f32>)> = vec![
+            ("drift", f32s(drift.as_slice())),
+            ("vol", f32s(vol.as_slice())),
+            ("cholesky", f32s(cholesky_l.transpose().as_slice())), // row-major
+            ("jump_lambda", jump(|j| j.lambda)),
+            ("jump_mean", jump(|j| j.mean)),
+            ("jump_vol", jump(|j| j.vol)),
+        ];
+
+        let out = self.dispatch(&kernel, &inputs, config)?;
+        Ok(match config.layout {
+            OutputLayout::Interleaved => out,
+            OutputLayout::Planar => simulate::to_planar(&out, config.n_paths, config.steps + 1, n),
+        })
+    }
+
+    /// Run `kernel` over all paths in chunks that fit one storage
+    /// binding and one dispatch, reading each back in turn.
+    fn dispatch(&self, kernel: &Kernel, inputs: &[(&str, Vec<f32>)], config: &SimConfig) -> Result<Vec<f32>, String> {
+        let bytes_per_path = match kernel.bindings.last().map(|b| b.size) {
+            Some(BufferSize::BytesPerPath(x)) => x,
+            _ => return Err("gpu: kernel has no output binding".into()),
+        };
+        let limits = self.device.limits();
+        let max_bytes = limits.max_storage_buffer_binding_size.min(limits.max_buffer_size as u32) as usize;
+        let max_groups = limits.max_compute_workgroups_per_dimension as usize;
+        let chunk = (max_bytes / bytes_per_path)
+            .min(max_groups * kernel.spec.workgroup_size as usize)
+            .min(config.n_paths)
+            .max(1);
+
+        let module = self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("mssim simulate"),
+            source: wgpu::ShaderSource::Wgsl(kernel.source.as_str().into()),
+        });
+        let pipeline = self.device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("mssim simulate"),
+            layout: None,
+            module: &module,
+            entry_point: "main",
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let storage = |name: &str, data: &[f32]| {
+            self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(name),
+                contents: &data.iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<u8>>(),
+                usage: wgpu::BufferUsages::STORAGE,
+            })
+        };
+        let params = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("params"),
+            size: PARAMS_BYTES as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("output"),
+            size: (chunk * bytes_per_path) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: (chunk * bytes_per_path) as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Bind by name, in the kernel's own binding order
+        let mut buffers = Vec::with_capacity(kernel.bindings.len());
+        for b in &kernel.bindings {
+            buffers.push(match (b.kind, b.name) {
+                (BindingKind::Uniform, _) | (BindingKind::Storage, _) => None,
+                (BindingKind::ReadOnlyStorage, name) => {
+                    let data = inputs.iter().find(|(k, _)| *k == name).map(|(_, v)| v.as_slice());
+                    Some(storage(name, data.ok_or_else(|| format!("gpu: no input for binding '{name}'"))?))
+                }
+            });
+        }
+        let entries: Vec<wgpu::BindGroupEntry> = kernel
+            .bindings
+            .iter()
+            .zip(&buffers)
+            .map(|(b, buffer)| wgpu::BindGroupEntry {
+                binding: b.binding,
+                resource: match b.kind {
+                    BindingKind::Uniform => params.as_entire_binding(),
+                    BindingKind::Storage => output.as_entire_binding(),
+                    BindingKind::ReadOnlyStorage => buffer.as_ref().expect("input buffer").as_entire_binding(),
+                },
+            })
+            .collect();
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("mssim simulate"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+
+        let mut out = Vec::with_capacity(config.n_paths * bytes_per_path / 4);
+        let mut offset = 0;
+        while offset < config.n_paths {
+            let count = chunk.min(config.n_paths - offset);
+            let bytes = (count * bytes_per_path) as u64;
+            self.queue.write_buffer(&params, 0, &kernel.params(count as u32, offset as u32, config.seed, config.horizon));
+
+            let mut encoder = self.device.create_command_encoder(&Default::default());
+            {
+                let mut pass = encoder.begin_compute_pass(&Default::default());
+                pass.set_pipeline(&pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.dispatch_workgroups(kernel.workgroups(count as u32), 1, 1);
+            }
+            encoder.copy_buffer_to_buffer(&output, 0, &readback, 0, bytes);
+            self.queue.submit(Some(encoder.finish()));
+
+            let slice = readback.slice(..bytes);
+            let (tx, rx) = std::sync::mpsc::channel();
+            slice.map_async(wgpu::MapMode::Read, move |r| {
+                let _ = tx.send(r);
+            });
+            self.device.poll(wgpu::Maintain::Wait);
+            rx.recv().map_err(|e| format!("gpu: {e}"))?.map_err(|e| format!("gpu: {e}"))?;
+            out.extend(slice.get_mapped_range().chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])));
+            readback.unmap();
+            offset += count;
+        }
+        Ok(out)
+    }
+}
+
+/// The slice of SimConfig the generated kernel implements
+fn check_supported(config: &SimConfig, n: usize) -> Result<(), String> {
+    let plain = config.systemic_jumps.is_none()
+        && config.hawkes.is_none()
+        && config.term_structure.is_none()
+        && config.dividend_yield.is_none()
+        && config.short_rates.is_none()
+        && config.tilt.is_none()
+        && config.vol_model == VolModel::Constant
+        && config.innovations == Innovations::Gaussian
+        && config.driver == Driver::PseudoRandom
+        && config.construction == PathConstruction::Incremental
+        && config.moment_matching == MomentMatching::None;
+    if !plain {
+        return Err("gpu: only constant-vol GBM with per-asset jumps runs on the GPU; use the cpu backend".into());
+    }
+    if config.n_paths > u32::MAX as usize {
+        return Err("gpu: n_paths must fit in u32".into());
+    }
+    match &config.jumps {
+        Some(j) if j.len() != 1 && j.len() != n => Err("gpu: jumps must have length 1 or N".into()),
+        _ => Ok(()),
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// GPU parity — the generated kernel, step for step, on the CPU
+// ────────────────────────────────────────────────────────────────
+
+/// Paths as the GPU kernel computes them: f32 state, 24-bit uniforms,
+/// and the kernel's draw order (N normals per step, then each asset's
+/// Poisson count and jump normal). A GPU run lands within a few f32
+/// ulps of this; Backend::Cpu is only close to either.
+pub fn parity_paths(
+    drift: &DVector<f64>,
+    vol: &DVector<f64>,
+    cholesky_l: &DMatrix<f64>,
+    config: &SimConfig,
+) -> Result<Vec<f32>, String> {
+    let n = drift.len();
+    if vol.len() != n || cholesky_l.shape() != (n, n) {
+        return Err("gpu: vol must have length N and cholesky_l shape N×N".into());
+    }
+    check_supported(config, n)?;
+    let steps = config.steps;
+    let dt64 = config.horizon / steps as f64;
+    let (dt, sqrt_dt) = (dt64 as f32, dt64.sqrt() as f32);
+    let l: Vec<f32> = cholesky_l.transpose().as_slice().iter().map(|&x| x as f32).collect(); // row-major
+    let drift_dt: Vec<f32> = (0..n)
+        .map(|i| {
+            let (mu, sigma) = (drift[i] as f32, vol[i] as f32);
+            (mu - 0.5 * sigma * sigma) * dt
+        })
+        .collect();
+    let jumps: Option<Vec<[f32; 3]>> = config.jumps.as_ref().map(|j| {
+        (0..n)
+            .map(|i| {
+                let j = &j[if j.len() == 1 { 0 } else { i }];
+                [j.lambda as f32, j.mean as f32, j.vol as f32]
+            })
+            .collect()
+    });
+
+    let stride = (steps + 1) * n;
+    let mut out = vec![0.0_f32; config.n_paths * stride];
+    let mut log_s = vec![0.0_f32; n];
+    let mut z = vec![0.0_f32; n];
+    for (p, path) in out.chunks_exact_mut(stride).enumerate() {
+        let mut draws = KernelDraws { rng: PathRng::for_path(config.rng, config.seed, p), spare: None };
+        log_s.iter_mut().for_each(|x| *x = 0.0);
+        path[..n].iter_mut().for_each(|x| *x = 1.0);
+        for step in 1..=steps {
+            z.iter_mut().for_each(|x| *x = draws.normal());
+            for i in 0..n {
+                let mut x = 0.0_f32;
+                for j in 0..=i {
+                    x += l[i * n + j] * z[j];
+                }
+                log_s[i] += drift_dt[i] + sqrt_dt * x;
+                if let Some(jumps) = &jumps {
+                    let [lambda, mean, vol] = jumps[i];
+                    let k = draws.poisson(lambda * dt);
+                    if k > 0 {
+                        let kf = k as f32;
+                        log_s[i] += kf * mean + kf.sqrt() * vol * draws.normal();
+                    }
+                }
+                path[step * n + i] = log_s[i].exp();
+            }
+        }
+    }
+    Ok(match config.layout {
+        OutputLayout::Interleaved => out,
+        OutputLayout::Planar => simulate::to_planar(&out, config.n_paths, steps + 1, n),
+    })
+}
+
+/// next_uniform / normal / poisson from the kernel's WGSL
+struct KernelDraws {
+    rng: PathRng,
+    spare: Option<f32>,
+}
+
+impl KernelDraws {
+    fn uniform(&mut self) -> f32 {
+        ((self.rng.next_u32() >> 8) as f32 + 0.5) / 16_777_216.0
+    }
+
+    fn normal(&mut self) -> f32 {
+        if let Some(z) = self.spare.take() {
+            return z;
+        }
+        let u1 = self.uniform();
+        let u2 = self.uniform();
+        let r = (-2.0 * u1.ln()).sqrt();
+        let theta = std::f32::consts::TAU * u2;
+        self.spare = Some(r * theta.sin());
+        r * theta.cos()
+    }
+
+    fn poisson(&mut self, mean: f32) -> u32 {
+        if mean <= 0.0 {
+            return 0;
+        }
+        if mean > 30.0 {
+            // WGSL round() ties to even
+            return (mean + mean.sqrt() * self.normal()).round_ties_even().max(0.0) as u32;
+        }
+        let limit = (-mean).exp();
+        let mut k = 0;
+        let mut prod = self.uniform();
+        while prod > limit {
+            k += 1;
+            prod *= self.uniform();
+        }
+        k
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests — skipped (pass trivially) on machines without an adapter
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::{assert_relative_eq, assert_ulps_eq};
+    use mssim_engine::rng::RngKind;
+    use mssim_engine::simulate::JumpParams;
+
+    fn market() -> (DVector<f64>, DVector<f64>, DMatrix<f64>) {
+        let drift = DVector::from_vec(vec![0.08, 0.03, 0.05]);
+        let vol = DVector::from_vec(vec![0.2, 0.06, 0.15]);
+        let r = DMatrix::from_row_slice(3, 3, &[1.0, 0.3, 0.1, 0.3, 1.0, -0.2, 0.1, -0.2, 1.0]);
+        let d = DMatrix::from_diagonal(&vol);
+        let l = (&d * r * &d).cholesky().unwrap().l();
+        (drift, vol, l)
+    }
+
+    #[test]
+    fn test_parity_mode_tracks_cpu() {
+        // Runs everywhere: only 24-bit uniforms and f32 separate them
+        let (drift, vol, l) = market();
+        for rng in [RngKind::Pcg32, RngKind::Philox4x32] {
+            let config = SimConfig::new(1.0, 16, 2_000, 7).with_rng(rng);
+            let cpu = simulate_paths(Backend::Cpu, &drift, &vol, &l, &config).unwrap();
+            let parity = simulate_paths(Backend::GpuParity, &drift, &vol, &l, &config).unwrap();
+            assert_eq!(cpu.len(), parity.len());
+            for (a, b) in cpu.iter().zip(&parity) {
+                assert_relative_eq!(*a, *b, max_relative = 1e-3);
+            }
+        }
+        // With jumps a rare Poisson comparison may land differently, so
+        // compare the terminal means instead of each path
+        let config = SimConfig::new(1.0, 12, 20_000, 3).with_jumps(JumpParams { lambda: 2.0, mean: -0.05, vol: 0.1 });
+        let cpu = simulate_paths(Backend::Cpu, &drift, &vol, &l, &config).unwrap();
+        let parity = parity_paths(&drift, &vol, &l, &config).unwrap();
+        let mean = |p: &[f32]| p.chunks_exact(3 * 13).map(|x| x[12 * 3] as f64).sum::<f64>() / 20_000.0;
+        assert_relative_eq!(mean(&cpu), mean(&parity), max_relative = 1e-3);
+
+        let planar = SimConfig::new(1.0, 4, 10, 1).with_layout(OutputLayout::Planar);
+        let interleaved = parity_paths(&drift, &vol, &l, &SimConfig::new(1.0, 4, 10, 1)).unwrap();
+        let planar = parity_paths(&drift, &vol, &l, &planar).unwrap();
+        assert_eq!(planar, simulate::to_planar(&interleaved, 10, 5, 3));
+    }
+
+    #[test]
+    fn test_gpu_matches_parity_mode_to_f32_ulps() {
+        let Ok(gpu) = GpuContext::new() else {
+            eprintln!("no GPU adapter; skipping");
+            return;
+        };
+        let (drift, vol, l) = market();
+        let terminal = |p: &[f32], steps: usize| -> Vec<f32> {
+            p.chunks_exact(3 * (steps + 1)).flat_map(|x| x[3 * steps..].to_vec()).collect()
+        };
+        for rng in [RngKind::Pcg32, RngKind::Philox4x32] {
+            let config = SimConfig::new(1.0, 16, 2_000, 7).with_rng(rng);
+            let parity = parity_paths(&drift, &vol, &l, &config).unwrap();
+            let on_gpu = gpu.simulate_paths(&drift, &vol, &l, &config).unwrap();
+            assert_eq!(parity.len(), on_gpu.len());
+            // Same uniforms and draw order; only vendor exp/log/cos differ
+            for (a, b) in terminal(&parity, 16).iter().zip(&terminal(&on_gpu, 16)) {
+                assert_ulps_eq!(*a, *b, max_ulps = 8);
+            }
+        }
+
+        // With jumps also per path: the Poisson comparison sees the same
+        // uniforms, so a count can only flip on a draw within ulps of e^-λdt
+        let config = SimConfig::new(1.0, 12, 2_000, 3).with_jumps(JumpParams { lambda: 2.0, mean: -0.05, vol: 0.1 });
+        let parity = parity_paths(&drift, &vol, &l, &config).unwrap();
+        let on_gpu = gpu.simulate_paths(&drift, &vol, &l, &config).unwrap();
+        for (a, b) in terminal(&parity, 12).iter().zip(&terminal(&on_gpu, 12)) {
+            assert_ulps_eq!(*a, *b, max_ulps = 8);
+        }
+    }
+
+    #[test]
+    fn test_backend_names_and_unsupported_configs() {
+        assert_eq!(Backend::from_name("GPU").unwrap(), Backend::Gpu);
+        assert_eq!(Backend::from_name("gpu-parity").unwrap(), Backend::GpuParity);
+        assert!(Backend::from_name("tpu").is_err());
+        let stratified = SimConfig::new(1.0, 4, 10, 1).with_driver(Driver::Stratified);
+        assert!(check_supported(&stratified, 3).is_err());
+        assert!(check_supported(&SimConfig::new(1.0, 4, 10, 1), 3).is_ok());
+    }
+}
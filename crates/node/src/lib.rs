@@ -0,0 +1,273 @@
+use napi::bindgen_prelude::*;
+use napi::{Env, Task};
+use napi_derive::napi;
+use nalgebra::{DMatrix, DVector};
+
+use mssim_engine::simulate::{self, JumpParams, SimConfig};
+use mssim_engine::{math, risk, shock_market, ShockedMarket};
+
+// ════════════════════════════════════════════════════════════════
+// @mssim/node — native Node.js addon (napi-rs)
+//
+//     const mssim = require('@mssim/node')
+//     const r = mssim.computeShock(mu, sigma, R, { volMultiplier, correlationSkew: 0.85 })
+//     const paths = await mssim.simulateAsync(r.drift, r.vol, r.choleskyL, { nPaths: 100_000 })
+//     mssim.varCvar(mssim.terminalPnl(paths, 3, 252), 3, [0.6, 0.3, 0.1])
+//
+// A subset of the wasm build's surface, on the same core: the Phase A
+// shock (computeShock), the CPU simulator (simulate, simulateAsync),
+// terminalPnl and varCvar.
+// Inputs are Float64Arrays read in place; outputs are Float64Array /
+// Float32Array backed by the Rust allocation (no copy). Matrices are
+// N×N row-major. Engine errors throw with the engine's message.
+// ════════════════════════════════════════════════════════════════
+
+fn js_error(e: impl ToString) -> Error {
+    Error::new(Status::InvalidArg, e.to_string())
+}
+
+fn to_vector(a: &[f64]) -> DVector<f64> {
+    DVector::from_column_slice(a)
+}
+
+/// N×N row-major → DMatrix, where N = `n`.
+fn to_matrix(a: &[f64], n: usize, field: &str) -> Result<DMatrix<f64>> {
+    if a.len() != n * n {
+        return Err(js_error(format!("{field} must be N×N row-major ({} values), got {}", n * n, a.len())));
+    }
+    Ok(DMatrix::from_row_slice(n, n, a))
+}
+
+fn row_major(m: &DMatrix<f64>) -> Float64Array {
+    Float64Array::new(m.transpose().as_slice().to_vec())
+}
+
+// ────────────────────────────────────────────────────────────────
+// Phase A
+// ────────────────────────────────────────────────────────────────
+
+#[napi(object)]
+pub struct ShockOptions {
+    pub delta_drift: Option<Float64Array>,
+    pub vol_multiplier: Option<Float64Array>,
+    pub correlation_skew: Option<f64>,
+}
+
+/// The shocked market from computeShock
+#[napi]
+pub struct ShockResult {
+    market: ShockedMarket,
+}
+
+#[napi]
+impl ShockResult {
+    #[napi(getter)]
+    pub fn drift(&self) -> Float64Array {
+        Float64Array::new(self.market.drift.as_slice().to_vec())
+    }
+
+    #[napi(getter)]
+    pub fn vol(&self) -> Float64Array {
+        Float64Array::new(self.market.vol.as_slice().to_vec())
+    }
+
+    /// Lower-triangular L with L·Lᵀ = Σ, N×N row-major
+    #[napi(getter)]
+    pub fn cholesky_l(&self) -> Float64Array {
+        row_major(&self.market.cholesky_l)
+    }
+
+    /// Correlation after blend and repair, N×N row-major
+    #[napi(getter)]
+    pub fn correlation(&self) -> Float64Array {
+        row_major(&self.market.correlation)
+    }
+
+    #[napi(getter)]
+    pub fn ridge_jitter(&self) -> f64 {
+        self.market.ridge_jitter
+    }
+
+    #[napi(getter)]
+    pub fn higham_iterations(&self) -> u32 {
+        self.market.repair.iterations as u32
+    }
+
+    #[napi(getter)]
+    pub fn higham_converged(&self) -> bool {
+        self.market.repair.converged
+    }
+}
+
+/// Steps 1–6 of the pipeline; shocks default to "no shock".
+#[napi]
+pub fn compute_shock(
+    base_drift: Float64Array,
+    base_vol: Float64Array,
+    base_correlation: Float64Array,
+    options: Option<ShockOptions>,
+) -> Result<ShockResult> {
+    let n = base_drift.len();
+    let base_correlation = to_matrix(&base_correlation, n, "baseCorrelation")?;
+    if base_vol.len() != n {
+        return Err(js_error("baseVol must have length N"));
+    }
+    math::validate_correlation(&base_correlation, 1e-6)
+        .map_err(|d| js_error(format!("{} at ({}, {})", d.reason, d.row, d.col)))?;
+    let options = options.unwrap_or(ShockOptions { delta_drift: None, vol_multiplier: None, correlation_skew: None });
+    let delta_drift = options.delta_drift.map_or_else(|| DVector::zeros(n), |a| to_vector(&a));
+    let vol_multiplier = options.vol_multiplier.map_or_else(|| DVector::from_element(n, 1.0), |a| to_vector(&a));
+    if delta_drift.len() != n || vol_multiplier.len() != n {
+        return Err(js_error("deltaDrift and volMultiplier must have length N"));
+    }
+    let market = shock_market(
+        &to_vector(&base_drift),
+        &to_vector(&base_vol),
+        &base_correlation,
+        &delta_drift,
+        &vol_multiplier,
+        options.correlation_skew.unwrap_or(0.0),
+        true,
+    )
+    .map_err(js_error)?;
+    Ok(ShockResult { market })
+}
+
+// ────────────────────────────────────────────────────────────────
+// Simulation
+// ────────────────────────────────────────────────────────────────
+
+#[napi(object)]
+pub struct SimulateOptions {
+    pub horizon: Option<f64>,
+    pub steps: Option<u32>,
+    pub n_paths: Option<u32>,
+    pub seed: Option<u32>,
+    pub jump_lambda: Option<f64>,
+    pub jump_mean: Option<f64>,
+    pub jump_vol: Option<f64>,
+}
+
+/// Owned inputs, so the run can move to the libuv thread pool.
+pub struct SimulateTask {
+    drift: DVector<f64>,
+    vol: DVector<f64>,
+    cholesky_l: DMatrix<f64>,
+    config: SimConfig,
+}
+
+impl SimulateTask {
+    fn new(drift: &[f64], vol: &[f64], cholesky_l: &[f64], options: Option<SimulateOptions>) -> Result<Self> {
+        let n = drift.len();
+        let o = options.unwrap_or(SimulateOptions {
+            horizon: None,
+            steps: None,
+            n_paths: None,
+            seed: None,
+            jump_lambda: None,
+            jump_mean: None,
+            jump_vol: None,
+        });
+        let config = SimConfig::new(
+            o.horizon.unwrap_or(1.0),
+            o.steps.unwrap_or(252) as usize,
+            o.n_paths.unwrap_or(10_000) as usize,
+            o.seed.unwrap_or(42) as u64,
+        )
+        .with_jumps(JumpParams {
+            lambda: o.jump_lambda.unwrap_or(0.0),
+            mean: o.jump_mean.unwrap_or(0.0),
+            vol: o.jump_vol.unwrap_or(0.0),
+        });
+        Ok(SimulateTask {
+            drift: to_vector(drift),
+            vol: to_vector(vol),
+            cholesky_l: to_matrix(cholesky_l, n, "choleskyL")?,
+            config,
+        })
+    }
+}
+
+impl Task for SimulateTask {
+    type Output = Vec<f32>;
+    type JsValue = Float32Array;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        simulate::simulate_paths(&self.drift, &self.vol, &self.cholesky_l, &self.config).map_err(js_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(Float32Array::new(output))
+    }
+}
+
+/// Price paths laid out [path][step][asset], S₀ = 1, length
+/// nPaths × (steps + 1) × N. Blocks the event loop; see simulateAsync.
+#[napi]
+pub fn simulate(
+    drift: Float64Array,
+    vol: Float64Array,
+    cholesky_l: Float64Array,
+    options: Option<SimulateOptions>,
+) -> Result<Float32Array> {
+    let mut task = SimulateTask::new(&drift, &vol, &cholesky_l, options)?;
+    task.compute().map(Float32Array::new)
+}
+
+/// simulate on the libuv thread pool, resolving to the same array.
+#[napi]
+pub fn simulate_async(
+    drift: Float64Array,
+    vol: Float64Array,
+    cholesky_l: Float64Array,
+    options: Option<SimulateOptions>,
+) -> Result<AsyncTask<SimulateTask>> {
+    Ok(AsyncTask::new(SimulateTask::new(&drift, &vol, &cholesky_l, options)?))
+}
+
+// ────────────────────────────────────────────────────────────────
+// Risk
+// ────────────────────────────────────────────────────────────────
+
+/// Terminal return S_T − 1 per path and asset, nPaths × N row-major.
+#[napi]
+pub fn terminal_pnl(paths: Float32Array, num_assets: u32, steps: u32) -> Result<Float64Array> {
+    let (n, rows) = (num_assets as usize, steps as usize + 1);
+    if n == 0 || paths.len() % (n * rows) != 0 {
+        return Err(js_error("paths must be nPaths × (steps + 1) × numAssets"));
+    }
+    let config = SimConfig::new(1.0, steps as usize, paths.len() / (n * rows), 0);
+    risk::terminal_pnl(&paths, n, &config).map(Float64Array::new).map_err(js_error)
+}
+
+#[napi(object)]
+pub struct VarCvar {
+    pub level: f64,
+    pub var: f64,
+    pub cvar: f64,
+}
+
+/// VaR/CVaR as positive losses per level (default 95% and 99%). `pnl`
+/// is nPaths × numAssets with `weights` (numAssets of them), or one
+/// portfolio P&L per path (numAssets = 1) without.
+#[napi]
+pub fn var_cvar(
+    pnl: Float64Array,
+    num_assets: u32,
+    weights: Option<Float64Array>,
+    levels: Option<Vec<f64>>,
+) -> Result<Vec<VarCvar>> {
+    let n = num_assets as usize;
+    if n == 0 || pnl.len() % n != 0 {
+        return Err(js_error("pnl must be nPaths × numAssets"));
+    }
+    let weights = match weights.as_deref() {
+        None if n != 1 => return Err(js_error("weights are required when numAssets > 1")),
+        None => &[][..],
+        Some(w) if w.len() != n => return Err(js_error("weights must have numAssets entries")),
+        Some(w) => w,
+    };
+    let levels = levels.unwrap_or_else(|| vec![0.95, 0.99]);
+    let results = risk::compute_var_cvar(&pnl, weights, &levels).map_err(js_error)?;
+    Ok(results.iter().map(|v| VarCvar { level: v.level, var: v.var, cvar: v.cvar }).collect())
+}
@@ -0,0 +1,233 @@
+use nalgebra::{DMatrix, DVector};
+use numpy::ndarray::{Array2, Array3};
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyArray3, PyReadonlyArray1, PyReadonlyArray2, PyReadonlyArray3, PyUntypedArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use mssim_engine::scenario::Scenario;
+use mssim_engine::simulate::{self, JumpParams, SimConfig};
+use mssim_engine::{math, risk, shock_market, ShockedMarket};
+
+// ════════════════════════════════════════════════════════════════
+// mssim — Python bindings (PyO3 + NumPy), built with maturin
+//
+//     import mssim
+//     r = mssim.compute_shock(mu, sigma, R, vol_multiplier=[2, 1.5, 1], correlation_skew=0.85)
+//     paths = mssim.simulate(r.drift, r.vol, r.cholesky_l, n_paths=50_000)
+//     pnl = mssim.terminal_pnl(paths)
+//     mssim.var_cvar(pnl, weights=[0.6, 0.3, 0.1])
+//
+// Arrays go in as float64 NumPy arrays and come back as NumPy arrays
+// (paths as float32, the simulator's native precision). Engine errors
+// surface as ValueError with the engine's message.
+// ════════════════════════════════════════════════════════════════
+
+fn value_error(e: impl ToString) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn to_vector(a: &PyReadonlyArray1<'_, f64>) -> DVector<f64> {
+    DVector::from_iterator(a.len(), a.as_array().iter().copied())
+}
+
+fn to_matrix(a: &PyReadonlyArray2<'_, f64>) -> DMatrix<f64> {
+    let view = a.as_array();
+    DMatrix::from_fn(view.nrows(), view.ncols(), |i, j| view[[i, j]])
+}
+
+fn to_numpy2<'py>(py: Python<'py>, m: &DMatrix<f64>) -> Bound<'py, PyArray2<f64>> {
+    Array2::from_shape_fn(m.shape(), |(i, j)| m[(i, j)]).into_pyarray_bound(py)
+}
+
+// ────────────────────────────────────────────────────────────────
+// Phase A
+// ────────────────────────────────────────────────────────────────
+
+/// The shocked market from compute_shock
+#[pyclass(frozen, module = "mssim")]
+struct ShockResult {
+    market: ShockedMarket,
+}
+
+#[pymethods]
+impl ShockResult {
+    #[getter]
+    fn drift<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        self.market.drift.as_slice().to_vec().into_pyarray_bound(py)
+    }
+
+    #[getter]
+    fn vol<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        self.market.vol.as_slice().to_vec().into_pyarray_bound(py)
+    }
+
+    /// Lower-triangular L with L·Lᵀ = Σ, shape (N, N)
+    #[getter]
+    fn cholesky_l<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        to_numpy2(py, &self.market.cholesky_l)
+    }
+
+    /// Correlation after blend and repair, shape (N, N)
+    #[getter]
+    fn correlation<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f64>> {
+        to_numpy2(py, &self.market.correlation)
+    }
+
+    #[getter]
+    fn ridge_jitter(&self) -> f64 {
+        self.market.ridge_jitter
+    }
+
+    #[getter]
+    fn higham_iterations(&self) -> usize {
+        self.market.repair.iterations
+    }
+
+    #[getter]
+    fn higham_converged(&self) -> bool {
+        self.market.repair.converged
+    }
+}
+
+/// Steps 1–6 of the pipeline; shocks default to "no shock".
+#[pyfunction]
+#[pyo3(signature = (base_drift, base_vol, base_correlation, delta_drift=None, vol_multiplier=None, correlation_skew=0.0))]
+fn compute_shock(
+    base_drift: PyReadonlyArray1<'_, f64>,
+    base_vol: PyReadonlyArray1<'_, f64>,
+    base_correlation: PyReadonlyArray2<'_, f64>,
+    delta_drift: Option<PyReadonlyArray1<'_, f64>>,
+    vol_multiplier: Option<PyReadonlyArray1<'_, f64>>,
+    correlation_skew: f64,
+) -> PyResult<ShockResult> {
+    let n = base_drift.len();
+    let base_correlation = to_matrix(&base_correlation);
+    if base_vol.len() != n || base_correlation.shape() != (n, n) {
+        return Err(value_error("base_vol must have length N and base_correlation shape (N, N)"));
+    }
+    math::validate_correlation(&base_correlation, 1e-6)
+        .map_err(|d| value_error(format!("{} at ({}, {})", d.reason, d.row, d.col)))?;
+    let delta_drift = delta_drift.map_or_else(|| DVector::zeros(n), |a| to_vector(&a));
+    let vol_multiplier = vol_multiplier.map_or_else(|| DVector::from_element(n, 1.0), |a| to_vector(&a));
+    if delta_drift.len() != n || vol_multiplier.len() != n {
+        return Err(value_error("delta_drift and vol_multiplier must have length N"));
+    }
+    let market = shock_market(
+        &to_vector(&base_drift),
+        &to_vector(&base_vol),
+        &base_correlation,
+        &delta_drift,
+        &vol_multiplier,
+        correlation_skew,
+        true,
+    )
+    .map_err(value_error)?;
+    Ok(ShockResult { market })
+}
+
+// ────────────────────────────────────────────────────────────────
+// Simulation and risk
+// ────────────────────────────────────────────────────────────────
+
+/// Price paths, shape (n_paths, steps + 1, N), S₀ = 1.
+#[pyfunction]
+#[pyo3(signature = (drift, vol, cholesky_l, horizon=1.0, steps=252, n_paths=10_000, seed=42, jump_lambda=0.0, jump_mean=0.0, jump_vol=0.0))]
+#[allow(clippy::too_many_arguments)]
+fn simulate<'py>(
+    py: Python<'py>,
+    drift: PyReadonlyArray1<'_, f64>,
+    vol: PyReadonlyArray1<'_, f64>,
+    cholesky_l: PyReadonlyArray2<'_, f64>,
+    horizon: f64,
+    steps: usize,
+    n_paths: usize,
+    seed: u64,
+    jump_lambda: f64,
+    jump_mean: f64,
+    jump_vol: f64,
+) -> PyResult<Bound<'py, PyArray3<f32>>> {
+    let n = drift.len();
+    let config = SimConfig::new(horizon, steps, n_paths, seed).with_jumps(JumpParams {
+        lambda: jump_lambda,
+        mean: jump_mean,
+        vol: jump_vol,
+    });
+    let (drift, vol, l) = (to_vector(&drift), to_vector(&vol), to_matrix(&cholesky_l));
+    let paths = py
+        .allow_threads(|| simulate::simulate_paths(&drift, &vol, &l, &config))
+        .map_err(value_error)?;
+    let paths = Array3::from_shape_vec((n_paths, steps + 1, n), paths).map_err(value_error)?;
+    Ok(paths.into_pyarray_bound(py))
+}
+
+/// Terminal return S_T − 1 per path and asset, shape (n_paths, N).
+#[pyfunction]
+fn terminal_pnl<'py>(py: Python<'py>, paths: PyReadonlyArray3<'_, f32>) -> PyResult<Bound<'py, PyArray2<f64>>> {
+    let view = paths.as_array();
+    let (n_paths, rows, n) = view.dim();
+    if rows < 1 {
+        return Err(value_error("paths need at least one time step"));
+    }
+    Ok(Array2::from_shape_fn((n_paths, n), |(p, a)| view[[p, rows - 1, a]] as f64 - 1.0).into_pyarray_bound(py))
+}
+
+/// [(level, VaR, CVaR)] as positive losses. `pnl` is (n_paths, N) with
+/// `weights`, or one portfolio P&L per path without.
+#[pyfunction]
+#[pyo3(signature = (pnl, weights=None, levels=vec![0.95, 0.99]))]
+fn var_cvar(
+    pnl: PyReadonlyArray2<'_, f64>,
+    weights: Option<PyReadonlyArray1<'_, f64>>,
+    levels: Vec<f64>,
+) -> PyResult<Vec<(f64, f64, f64)>> {
+    let view = pnl.as_array();
+    let flat: Vec<f64> = view.iter().copied().collect();
+    let weights: Vec<f64> = match weights {
+        Some(w) => w.as_array().to_vec(),
+        None if view.ncols() == 1 => Vec::new(),
+        None => return Err(value_error("weights are required when pnl has more than one column")),
+    };
+    let results = risk::compute_var_cvar(&flat, &weights, &levels).map_err(value_error)?;
+    Ok(results.iter().map(|v| (v.level, v.var, v.cvar)).collect())
+}
+
+/// A scenario document (or a saved ShockConfig) as a dict: the
+/// compute_shock arguments as arrays (base_correlation N×N), plus
+/// name, assets, weights and the simulation settings.
+#[pyfunction]
+fn load_scenario<'py>(py: Python<'py>, text: &str) -> PyResult<Bound<'py, PyDict>> {
+    let s = Scenario::from_json(text).map_err(value_error)?;
+    let n = s.num_assets();
+    let jump = |f: fn(&JumpParams) -> f64| s.shock.jumps.iter().map(f).collect::<Vec<_>>().into_pyarray_bound(py);
+    let d = PyDict::new_bound(py);
+    d.set_item("name", &s.metadata.name)?;
+    d.set_item("assets", &s.market.assets)?;
+    d.set_item("base_drift", s.market.base_drift.clone().into_pyarray_bound(py))?;
+    d.set_item("base_vol", s.market.base_vol.clone().into_pyarray_bound(py))?;
+    d.set_item("base_correlation", to_numpy2(py, &DMatrix::from_row_slice(n, n, &s.market.base_correlation)))?;
+    d.set_item("weights", s.market.weights.clone().into_pyarray_bound(py))?;
+    d.set_item("delta_drift", s.shock.delta_drift.clone().into_pyarray_bound(py))?;
+    d.set_item("vol_multiplier", s.shock.vol_multiplier.clone().into_pyarray_bound(py))?;
+    d.set_item("correlation_skew", s.shock.correlation_skew)?;
+    d.set_item("jump_lambda", jump(|j| j.lambda))?;
+    d.set_item("jump_mean", jump(|j| j.mean))?;
+    d.set_item("jump_vol", jump(|j| j.vol))?;
+    d.set_item("horizon", s.simulation.horizon)?;
+    d.set_item("steps", s.simulation.steps)?;
+    d.set_item("n_paths", s.simulation.n_paths)?;
+    d.set_item("seed", s.simulation.seed)?;
+    d.set_item("levels", &s.simulation.levels)?;
+    Ok(d)
+}
+
+#[pymodule]
+fn mssim(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<ShockResult>()?;
+    m.add_function(wrap_pyfunction!(compute_shock, m)?)?;
+    m.add_function(wrap_pyfunction!(simulate, m)?)?;
+    m.add_function(wrap_pyfunction!(terminal_pnl, m)?)?;
+    m.add_function(wrap_pyfunction!(var_cvar, m)?)?;
+    m.add_function(wrap_pyfunction!(load_scenario, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,214 @@
+use std::io::{BufRead, Read, Take, Write};
+
+use mssim_engine::json::Json;
+
+// ════════════════════════════════════════════════════════════════
+// Minimal HTTP/1.1 — one request per connection
+//
+// Enough of the protocol for a JSON API behind a reverse proxy:
+// request line, headers, a Content-Length body (no chunked uploads),
+// and responses sent with Connection: close. The head is read through
+// a byte budget so a client cannot grow a line or the header list
+// without bound.
+// ════════════════════════════════════════════════════════════════
+
+/// Largest request body accepted (a scenario document is a few kB)
+pub const MAX_BODY: usize = 4 << 20;
+/// Largest request line plus headers accepted, in bytes
+pub const MAX_HEAD: usize = 16 << 10;
+/// Most header fields accepted
+pub const MAX_HEADERS: usize = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    pub method: String,
+    /// Path without the query string
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    /// Names lower-cased
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn query(&self, key: &str) -> Option<&str> {
+        self.query.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// Path split on '/', empty segments dropped
+    pub fn segments(&self) -> Vec<&str> {
+        self.path.split('/').filter(|s| !s.is_empty()).collect()
+    }
+}
+
+/// Parses one request. Failures come back as the response to send:
+/// 413 for an oversized body, 431 for an oversized head, 400 otherwise.
+pub fn read_request(reader: &mut impl BufRead) -> Result<Request, Response> {
+    let bad = |e: &str| Response::error(400, e);
+    let mut head = reader.by_ref().take(MAX_HEAD as u64);
+    let mut line = String::new();
+    read_head_line(&mut head, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(m), Some(t), Some(v)) if v.starts_with("HTTP/1.") => (m.to_string(), t.to_string()),
+        _ => return Err(bad("malformed request line")),
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        read_head_line(&mut head, &mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(Response::error(431, "too many header fields"));
+        }
+        let (k, v) = header.split_once(':').ok_or_else(|| bad("malformed header"))?;
+        headers.push((k.trim().to_ascii_lowercase(), v.trim().to_string()));
+    }
+
+    let length = match headers.iter().find(|(k, _)| k == "content-length") {
+        Some((_, v)) => v.parse::<usize>().map_err(|_| bad("bad Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return Err(Response::error(413, "request body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(|e| bad(&e.to_string()))?;
+
+    let (path, query) = match target.split_once('?') {
+        Some((p, q)) => (p.to_string(), q),
+        None => (target, ""),
+    };
+    let query = query
+        .split('&')
+        .filter(|kv| !kv.is_empty())
+        .map(|kv| match kv.split_once('=') {
+            Some((k, v)) => (k.to_string(), v.to_string()),
+            None => (kv.to_string(), String::new()),
+        })
+        .collect();
+    Ok(Request { method, path, query, headers, body })
+}
+
+/// One line of the head into `line`, failing once the budget is spent
+fn read_head_line<R: BufRead>(head: &mut Take<R>, line: &mut String) -> Result<(), Response> {
+    line.clear();
+    head.read_line(line).map_err(|e| Response::error(400, e.to_string()))?;
+    match line.ends_with('\n') {
+        true => Ok(()),
+        false if head.limit() == 0 => Err(Response::error(431, "request header fields too large")),
+        false => Err(Response::error(400, "connection closed in headers")),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(status: u16, body: Json) -> Response {
+        Response { status, content_type: "application/json", body: body.to_string().into_bytes() }
+    }
+
+    pub fn text(status: u16, content_type: &'static str, body: String) -> Response {
+        Response { status, content_type, body: body.into_bytes() }
+    }
+
+    /// {"error": message}
+    pub fn error(status: u16, message: impl Into<String>) -> Response {
+        Response::json(status, Json::Object(vec![("error".into(), Json::String(message.into()))]))
+    }
+
+    pub fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        write!(
+            w,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason(self.status),
+            self.content_type,
+            self.body.len()
+        )?;
+        w.write_all(&self.body)?;
+        w.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        101 => "Switching Protocols",
+        200 => "OK",
+        202 => "Accepted",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_request_and_write_response() {
+        let raw = b"POST /jobs/7/results?format=json&x HTTP/1.1\r\nHost: a\r\nContent-Length: 5\r\n\r\nhello";
+        let r = read_request(&mut &raw[..]).unwrap();
+        assert_eq!((r.method.as_str(), r.path.as_str()), ("POST", "/jobs/7/results"));
+        assert_eq!(r.segments(), vec!["jobs", "7", "results"]);
+        assert_eq!((r.query("format"), r.query("x"), r.query("y")), (Some("json"), Some(""), None));
+        assert_eq!((r.header("host"), r.header("accept")), (Some("a"), None));
+        assert_eq!(r.body, b"hello");
+        assert_eq!(read_request(&mut &b"GET /\r\n\r\n"[..]).unwrap_err().status, 400);
+
+        let mut out = Vec::new();
+        Response::error(404, "no such job").write_to(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(out.ends_with("\r\n\r\n{\"error\":\"no such job\"}"));
+    }
+
+    #[test]
+    fn test_read_request_bounds_the_head() {
+        let status = |raw: &[u8]| read_request(&mut &raw[..]).unwrap_err().status;
+        // One endless header line stops at the byte budget
+        let mut long = b"GET / HTTP/1.1\r\nX: ".to_vec();
+        long.resize(2 * MAX_HEAD, b'a');
+        assert_eq!(status(&long), 431);
+        // So does an endless request line
+        assert_eq!(status(&vec![b'G'; 2 * MAX_HEAD]), 431);
+        // Short headers, but too many of them
+        let mut many = b"GET / HTTP/1.1\r\n".to_vec();
+        for i in 0..=MAX_HEADERS {
+            many.extend(format!("X-{i}: 1\r\n").bytes());
+        }
+        many.extend(b"\r\n");
+        assert_eq!(status(&many), 431);
+        // Truncated heads and oversized bodies
+        assert_eq!(status(b"GET / HTTP/1.1\r\nHost: a"), 400);
+        assert_eq!(status(format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY + 1).as_bytes()), 413);
+        // Exactly MAX_HEADERS is fine
+        let mut ok = b"GET / HTTP/1.1\r\n".to_vec();
+        for i in 0..MAX_HEADERS {
+            ok.extend(format!("X-{i}: 1\r\n").bytes());
+        }
+        ok.extend(b"\r\n");
+        assert_eq!(read_request(&mut &ok[..]).unwrap().headers.len(), MAX_HEADERS);
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use mssim_engine::json::Json;
+use mssim_engine::risk;
+use mssim_engine::scenario::Scenario;
+use mssim_engine::shock_market;
+use mssim_engine::simulate::{PathStream, SimConfig};
+use nalgebra::{DMatrix, DVector};
+
+// ════════════════════════════════════════════════════════════════
+// Job queue
+//
+// Submitted scenarios wait in a FIFO for a fixed pool of worker
+// threads. A worker runs the shock pipeline, then simulates in chunks
+// of CHUNK_PATHS, reducing each to portfolio returns
+// Σ_a w_a·(S_T,a − 1) and publishing progress after every chunk. The
+// returns and their summary stay in memory until the job is deleted;
+// deleting a running job stops it at the next chunk. Each job also
+// keeps a risk::FanAccumulator of its portfolio paths, which stream
+// subscribers read after every chunk (wait_frame).
+// ════════════════════════════════════════════════════════════════
+
+/// Paths simulated per chunk (the progress granularity)
+pub const CHUNK_PATHS: usize = 10_000;
+
+/// Largest n_paths one job may request
+pub const MAX_PATHS: usize = 10_000_000;
+
+/// Histogram bins per step for the live fan
+pub const FAN_BINS: usize = 2048;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+impl Status {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Status::Queued => "queued",
+            Status::Running => "running",
+            Status::Done => "done",
+            Status::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Job {
+    pub id: u64,
+    pub name: String,
+    pub status: Status,
+    pub paths_done: usize,
+    pub n_paths: usize,
+    /// Chunks of CHUNK_PATHS simulated so far
+    pub chunks_done: usize,
+    pub error: Option<String>,
+    /// Portfolio return per path, filled when Done
+    pub returns: Vec<f64>,
+    pub summary: Option<Summary>,
+    /// Portfolio value fan over the paths so far
+    pub fan: Option<risk::FanAccumulator>,
+}
+
+impl Job {
+    /// {"id", "name", "status", "paths_done", "n_paths"} plus "error"
+    /// once failed
+    pub fn status_json(&self) -> Json {
+        let mut fields = vec![
+            ("id".into(), Json::Number(self.id as f64)),
+            ("name".into(), Json::String(self.name.clone())),
+            ("status".into(), Json::String(self.status.as_str().into())),
+            ("paths_done".into(), Json::Number(self.paths_done as f64)),
+            ("n_paths".into(), Json::Number(self.n_paths as f64)),
+        ];
+        if let Some(e) = &self.error {
+            fields.push(("error".into(), Json::String(e.clone())));
+        }
+        Json::Object(fields)
+    }
+
+    pub fn finished(&self) -> bool {
+        matches!(self.status, Status::Done | Status::Failed)
+    }
+}
+
+/// A job's state as seen by a stream subscriber
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub status: Status,
+    pub paths_done: usize,
+    pub n_paths: usize,
+    pub chunks_done: usize,
+    pub error: Option<String>,
+    pub fan: Option<risk::FanAccumulator>,
+}
+
+/// Distribution of a job's portfolio returns
+#[derive(Clone, Debug)]
+pub struct Summary {
+    pub mean: f64,
+    pub sd: f64,
+    pub worst: f64,
+    pub best: f64,
+    pub p_loss: f64,
+    pub risk: Vec<risk::VarCvar>,
+}
+
+impl Summary {
+    pub fn new(returns: &[f64], levels: &[f64]) -> Result<Summary, String> {
+        let m = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / m;
+        Ok(Summary {
+            mean,
+            sd: (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (m - 1.0).max(1.0)).sqrt(),
+            worst: returns.iter().copied().fold(f64::INFINITY, f64::min),
+            best: returns.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            p_loss: returns.iter().filter(|&&r| r < 0.0).count() as f64 / m,
+            risk: risk::compute_var_cvar(returns, &[], levels)?,
+        })
+    }
+
+    /// {"mean", "sd", "worst", "best", "p_loss", "risk": [{level, var, cvar}]}
+    pub fn to_json(&self) -> Json {
+        let risk = self
+            .risk
+            .iter()
+            .map(|v| {
+                Json::Object(vec![
+                    ("level".into(), Json::Number(v.level)),
+                    ("var".into(), Json::Number(v.var)),
+                    ("cvar".into(), Json::Number(v.cvar)),
+                ])
+            })
+            .collect();
+        Json::Object(vec![
+            ("mean".into(), Json::Number(self.mean)),
+            ("sd".into(), Json::Number(self.sd)),
+            ("worst".into(), Json::Number(self.worst)),
+            ("best".into(), Json::Number(self.best)),
+            ("p_loss".into(), Json::Number(self.p_loss)),
+            ("risk".into(), Json::Array(risk)),
+        ])
+    }
+}
+
+// ────────────────────────────────────────────────────────────────
+// Jobs — shared handle to the store and the worker pool
+// ────────────────────────────────────────────────────────────────
+#[derive(Clone)]
+pub struct Jobs {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    queue: Mutex<VecDeque<(u64, Scenario)>>,
+    ready: Condvar,
+    /// Signalled on every job update, paired with `jobs`
+    progressed: Condvar,
+    next_id: AtomicU64,
+}
+
+impl Jobs {
+    /// Store plus `workers` threads (at least one)
+    pub fn new(workers: usize) -> Jobs {
+        let inner = Arc::new(Inner {
+            jobs: Mutex::new(BTreeMap::new()),
+            queue: Mutex::new(VecDeque::new()),
+            ready: Condvar::new(),
+            progressed: Condvar::new(),
+            next_id: AtomicU64::new(1),
+        });
+        for _ in 0..workers.max(1) {
+            let inner = Arc::clone(&inner);
+            std::thread::spawn(move || worker(&inner));
+        }
+        Jobs { inner }
+    }
+
+    pub fn submit(&self, scenario: Scenario) -> Result<u64, String> {
+        scenario.validate()?;
+        if scenario.simulation.n_paths > MAX_PATHS {
+            return Err(format!("n_paths is limited to {MAX_PATHS} per job"));
+        }
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let job = Job {
+            id,
+            name: scenario.metadata.name.clone(),
+            status: Status::Queued,
+            paths_done: 0,
+            n_paths: scenario.simulation.n_paths,
+            chunks_done: 0,
+            error: None,
+            returns: Vec::new(),
+            summary: None,
+            fan: None,
+        };
+        self.inner.jobs.lock().unwrap().insert(id, job);
+        self.inner.queue.lock().unwrap().push_back((id, scenario));
+        self.inner.ready.notify_one();
+        Ok(id)
+    }
+
+    /// Inspect a job under the store lock
+    pub fn with_job<R>(&self, id: u64, f: impl FnOnce(&Job) -> R) -> Option<R> {
+        self.inner.jobs.lock().unwrap().get(&id).map(f)
+    }
+
+    /// Status of every job, oldest first
+    pub fn list(&self) -> Vec<Json> {
+        self.inner.jobs.lock().unwrap().values().map(Job::status_json).collect()
+    }
+
+    /// Block until the job has `every` more chunks than `seen_chunks`,
+    /// finishes, or `timeout` passes; None once the job is gone.
+    pub fn wait_frame(&self, id: u64, seen_chunks: usize, every: usize, timeout: Duration) -> Option<Frame> {
+        let deadline = Instant::now() + timeout;
+        let mut jobs = self.inner.jobs.lock().unwrap();
+        loop {
+            let job = jobs.get(&id)?;
+            let now = Instant::now();
+            if job.finished() || job.chunks_done >= seen_chunks + every.max(1) || now >= deadline {
+                return Some(Frame {
+                    status: job.status,
+                    paths_done: job.paths_done,
+                    n_paths: job.n_paths,
+                    chunks_done: job.chunks_done,
+                    error: job.error.clone(),
+                    fan: job.fan.clone(),
+                });
+            }
+            jobs = self.inner.progressed.wait_timeout(jobs, deadline - now).unwrap().0;
+        }
+    }
+
+    /// Forget a job (and stop it if running); false if unknown
+    pub fn remove(&self, id: u64) -> bool {
+        self.inner.queue.lock().unwrap().retain(|(queued, _)| *queued != id);
+        let removed = self.inner.jobs.lock().unwrap().remove(&id).is_some();
+        self.inner.progressed.notify_all();
+        removed
+    }
+}
+
+fn worker(inner: &Inner) {
+    loop {
+        let (id, scenario) = {
+            let mut queue = inner.queue.lock().unwrap();
+            loop {
+                match queue.pop_front() {
+                    Some(next) => break next,
+                    None => queue = inner.ready.wait(queue).unwrap(),
+                }
+            }
+        };
+        let result = run(inner, id, &scenario);
+        if let Some(job) = inner.jobs.lock().unwrap().get_mut(&id) {
+            match result {
+                Ok(Some((returns, summary))) => {
+                    job.status = Status::Done;
+                    job.returns = returns;
+                    job.summary = Some(summary);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    job.status = Status::Failed;
+                    job.error = Some(e);
+                }
+            }
+        }
+        inner.progressed.notify_all();
+    }
+}
+
+/// Simulate one job; None if it was deleted part-way.
+fn run(inner: &Inner, id: u64, s: &Scenario) -> Result<Option<(Vec<f64>, Summary)>, String> {
+    let progress = |done: usize, chunks: usize, fan: Option<&risk::FanAccumulator>| -> bool {
+        let alive = match inner.jobs.lock().unwrap().get_mut(&id) {
+            Some(job) => {
+                job.status = Status::Running;
+                job.paths_done = done;
+                job.chunks_done = chunks;
+                job.fan = fan.cloned();
+                true
+            }
+            None => false,
+        };
+        inner.progressed.notify_all();
+        alive
+    };
+    if !progress(0, 0, None) {
+        return Ok(None);
+    }
+    let n = s.num_assets();
+    let market = shock_market(
+        &DVector::from_column_slice(&s.market.base_drift),
+        &DVector::from_column_slice(&s.market.base_vol),
+        &DMatrix::from_row_slice(n, n, &s.market.base_correlation),
+        &DVector::from_column_slice(&s.shock.delta_drift),
+        &DVector::from_column_slice(&s.shock.vol_multiplier),
+        s.shock.correlation_skew,
+        true,
+    )
+    .map_err(|e| e.to_string())?;
+    let config = s.sim_config();
+    let rows = config.steps + 1;
+    let mut stream = PathStream::new(&market.drift, &market.vol, &market.cholesky_l, &config)?;
+    let mut fan = risk::FanAccumulator::new(n, config.steps, &s.market.weights, FAN_BINS)?;
+    let mut returns = Vec::with_capacity(config.n_paths);
+    let mut chunks = 0;
+    while stream.remaining() > 0 {
+        let chunk = stream.next_chunk(CHUNK_PATHS);
+        let chunk_config = SimConfig { n_paths: chunk.len() / (rows * n), ..config.clone() };
+        let terminal = risk::terminal_pnl(&chunk, n, &chunk_config)?;
+        returns.extend(risk::portfolio_pnl(&terminal, &s.market.weights)?);
+        fan.add(&chunk)?;
+        chunks += 1;
+        if !progress(returns.len(), chunks, Some(&fan)) {
+            return Ok(None);
+        }
+    }
+    let summary = Summary::new(&returns, &s.simulation.levels)?;
+    Ok(Some((returns, summary)))
+}
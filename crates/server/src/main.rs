@@ -0,0 +1,78 @@
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+mod http;
+mod jobs;
+mod routes;
+mod ws;
+
+use jobs::Jobs;
+
+// ════════════════════════════════════════════════════════════════
+// mssim-server — the engine behind an HTTP API
+//     mssim-server [--addr 127.0.0.1:8080] [--workers 2]
+//
+// Heavy runs go to a shared machine instead of each browser: POST a
+// scenario document, poll its status, fetch the summary or the
+// per-path returns (see routes.rs), or follow a run's percentile fan
+// over a WebSocket as it refines. Jobs live in memory only and the
+// server speaks plain HTTP; put TLS and auth in a reverse proxy.
+// ════════════════════════════════════════════════════════════════
+
+const USAGE: &str = "\
+usage: mssim-server [--addr HOST:PORT] [--workers N]
+
+options:
+  --addr HOST:PORT  listen address (default 127.0.0.1:8080)
+  --workers N       simulation threads (default 2)";
+
+fn main() {
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&argv) {
+        eprintln!("mssim-server: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run(argv: &[String]) -> Result<(), String> {
+    let (mut addr, mut workers) = ("127.0.0.1:8080".to_string(), 2);
+    let mut args = argv.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or(format!("{flag} needs a value"));
+        match flag.as_str() {
+            "--addr" => addr = value()?.clone(),
+            "--workers" => workers = value()?.parse().map_err(|_| "--workers must be a positive integer")?,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return Ok(());
+            }
+            other => return Err(format!("unknown option '{other}'\n{USAGE}")),
+        }
+    }
+
+    let listener = TcpListener::bind(&addr).map_err(|e| format!("{addr}: {e}"))?;
+    let jobs = Jobs::new(workers);
+    println!("mssim-server listening on http://{addr} with {workers} workers");
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let jobs = jobs.clone();
+        std::thread::spawn(move || serve(&jobs, stream));
+    }
+    Ok(())
+}
+
+fn serve(jobs: &Jobs, stream: TcpStream) {
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(30)));
+    let Ok(mut writer) = stream.try_clone() else { return };
+    let response = match http::read_request(&mut BufReader::new(stream)) {
+        Ok(req) if ws::is_upgrade(&req) => {
+            let _ = writer.set_read_timeout(None);
+            let _ = routes::stream(jobs, &req, &mut writer);
+            return;
+        }
+        Ok(req) => routes::handle(jobs, &req),
+        Err(response) => response,
+    };
+    let _ = response.write_to(&mut writer);
+}
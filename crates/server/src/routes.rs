@@ -0,0 +1,273 @@
+use std::fmt::Write as _;
+use std::io::Write;
+use std::time::Duration;
+
+use mssim_engine::json::Json;
+use mssim_engine::scenario::Scenario;
+
+use crate::http::{Request, Response};
+use crate::jobs::{Frame, Jobs, Status};
+use crate::ws;
+
+// ════════════════════════════════════════════════════════════════
+// Routes
+//     GET    /health
+//     POST   /jobs                 scenario document → 202 {"id", "status"}
+//     GET    /jobs                 every job's status
+//     GET    /jobs/{id}            status and progress
+//     GET    /jobs/{id}/summary    mean, sd, P(loss), VaR / CVaR
+//     GET    /jobs/{id}/results    path,return CSV (?format=json for JSON)
+//     DELETE /jobs/{id}            drop the job, stopping it if running
+//     GET    /jobs/{id}/stream     WebSocket of live fan frames (see stream)
+// The body of POST /jobs is a scenario document (mssim_engine::scenario)
+// or a saved ShockConfig; results are 409 until the job is done.
+// ════════════════════════════════════════════════════════════════
+
+pub fn handle(jobs: &Jobs, req: &Request) -> Response {
+    let segments = req.segments();
+    match (req.method.as_str(), segments.as_slice()) {
+        ("GET", ["health"]) => Response::json(200, Json::Object(vec![("status".into(), Json::String("ok".into()))])),
+        ("POST", ["jobs"]) => submit(jobs, req),
+        ("GET", ["jobs"]) => Response::json(200, Json::Array(jobs.list())),
+        (method, ["jobs", id, rest @ ..]) => {
+            let Ok(id) = id.parse::<u64>() else {
+                return Response::error(404, "no such job");
+            };
+            match (method, rest) {
+                ("GET", []) => found(jobs.with_job(id, |job| Response::json(200, job.status_json()))),
+                ("GET", ["summary"]) => found(jobs.with_job(id, |job| match &job.summary {
+                    Some(summary) => Response::json(200, summary.to_json()),
+                    None => not_done(job.status),
+                })),
+                ("GET", ["results"]) => found(jobs.with_job(id, |job| match job.status {
+                    Status::Done => results(&job.returns, req.query("format") == Some("json")),
+                    status => not_done(status),
+                })),
+                ("DELETE", []) if jobs.remove(id) => Response::text(204, "text/plain", String::new()),
+                ("DELETE", []) => Response::error(404, "no such job"),
+                ("GET", _) => Response::error(404, "no such route"),
+                _ => Response::error(405, "method not allowed"),
+            }
+        }
+        _ => Response::error(404, "no such route"),
+    }
+}
+
+/// GET /jobs/{id}/stream?every=k&percentiles=5,50,95 as a WebSocket:
+/// a text frame {"status", "paths_done", "n_paths", "steps",
+/// "percentiles", "fan"} every k chunks of the run (CHUNK_PATHS paths
+/// each), with "fan" the portfolio value's [step][percentile] so far,
+/// then a last frame once the job is done (or failed, with "error")
+/// and a close.
+pub fn stream(jobs: &Jobs, req: &Request, out: &mut impl Write) -> std::io::Result<()> {
+    let id = match req.segments().as_slice() {
+        ["jobs", id, "stream"] => id.parse::<u64>().ok(),
+        _ => return Response::error(404, "no such route").write_to(out),
+    };
+    let every = req.query("every").map_or(Ok(1), str::parse::<usize>);
+    let percentiles = match req.query("percentiles") {
+        Some(list) => list.split(',').map(str::parse::<f64>).collect::<Result<Vec<_>, _>>(),
+        None => Ok(vec![5.0, 25.0, 50.0, 75.0, 95.0]),
+    };
+    let (Ok(every), Ok(percentiles)) = (every, percentiles) else {
+        return Response::error(400, "every must be an integer, percentiles a comma-separated list").write_to(out);
+    };
+    if percentiles.iter().any(|&q| !(0.0..=100.0).contains(&q)) {
+        return Response::error(400, "percentiles must lie in [0, 100]").write_to(out);
+    }
+    let Some(id) = id.filter(|&id| jobs.with_job(id, |_| ()).is_some()) else {
+        return Response::error(404, "no such job").write_to(out);
+    };
+    let accept = match ws::handshake(req) {
+        Ok(accept) => accept,
+        Err(e) => return Response::error(400, e).write_to(out),
+    };
+    out.write_all(accept.as_bytes())?;
+
+    let mut seen = None;
+    while let Some(frame) = jobs.wait_frame(id, seen.unwrap_or(0), every, Duration::from_secs(15)) {
+        let finished = matches!(frame.status, Status::Done | Status::Failed);
+        if finished || seen.is_none_or(|s| frame.chunks_done > s) {
+            out.write_all(&ws::text_frame(&frame_json(&frame, &percentiles).to_string()))?;
+            out.flush()?;
+            seen = Some(frame.chunks_done);
+        }
+        if finished {
+            break;
+        }
+    }
+    out.write_all(&ws::close_frame())?;
+    out.flush()
+}
+
+fn frame_json(frame: &Frame, percentiles: &[f64]) -> Json {
+    let fan = frame.fan.as_ref().and_then(|f| f.percentiles(percentiles).ok()).unwrap_or_default();
+    let mut fields = vec![
+        ("status".into(), Json::String(frame.status.as_str().into())),
+        ("paths_done".into(), Json::Number(frame.paths_done as f64)),
+        ("n_paths".into(), Json::Number(frame.n_paths as f64)),
+        ("steps".into(), Json::Number((fan.len() / percentiles.len().max(1)).saturating_sub(1) as f64)),
+        ("percentiles".into(), Json::Array(percentiles.iter().map(|&q| Json::Number(q)).collect())),
+        ("fan".into(), Json::from_f32s(&fan)),
+    ];
+    if let Some(e) = &frame.error {
+        fields.push(("error".into(), Json::String(e.clone())));
+    }
+    Json::Object(fields)
+}
+
+fn submit(jobs: &Jobs, req: &Request) -> Response {
+    let Ok(text) = std::str::from_utf8(&req.body) else {
+        return Response::error(400, "body must be UTF-8 JSON");
+    };
+    let submitted = Scenario::from_json(text).map_err(String::from).and_then(|s| jobs.submit(s));
+    match submitted {
+        Ok(id) => Response::json(
+            202,
+            Json::Object(vec![
+                ("id".into(), Json::Number(id as f64)),
+                ("status".into(), Json::String(Status::Queued.as_str().into())),
+            ]),
+        ),
+        Err(e) => Response::error(400, e),
+    }
+}
+
+fn found(response: Option<Response>) -> Response {
+    response.unwrap_or_else(|| Response::error(404, "no such job"))
+}
+
+fn not_done(status: Status) -> Response {
+    Response::error(409, format!("job is {}", status.as_str()))
+}
+
+fn results(returns: &[f64], json: bool) -> Response {
+    if json {
+        let values = returns.iter().map(|&r| Json::Number(r)).collect();
+        return Response::json(200, Json::Object(vec![("returns".into(), Json::Array(values))]));
+    }
+    let mut csv = String::from("path,return\n");
+    for (i, r) in returns.iter().enumerate() {
+        let _ = writeln!(csv, "{i},{r}");
+    }
+    Response::text(200, "text/csv", csv)
+}
+
+// ════════════════════════════════════════════════════════════════
+// Tests
+// ════════════════════════════════════════════════════════════════
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn request(method: &str, path: &str, body: &str) -> Request {
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        Request {
+            method: method.into(),
+            path: path.into(),
+            query: query.split_once('=').map(|(k, v)| vec![(k.into(), v.into())]).unwrap_or_default(),
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn body(r: &Response) -> Json {
+        mssim_engine::json::parse(std::str::from_utf8(&r.body).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_submit_poll_and_fetch() {
+        let jobs = Jobs::new(1);
+        let mut s = Scenario::new(vec![0.08, 0.03], vec![0.2, 0.05], vec![1.0, 0.3, 0.3, 1.0]);
+        s.shock.vol_multiplier = vec![2.0, 1.0];
+        s.simulation.n_paths = 25_000;
+        s.simulation.steps = 4;
+
+        let r = handle(&jobs, &request("POST", "/jobs", &s.to_json()));
+        assert_eq!(r.status, 202);
+        let id = body(&r).get("id").and_then(Json::as_f64).unwrap() as u64;
+        let status = format!("/jobs/{id}");
+        let deadline = Instant::now() + Duration::from_secs(30);
+        loop {
+            let r = body(&handle(&jobs, &request("GET", &status, "")));
+            if r.get("status").and_then(Json::as_str) == Some("done") {
+                assert_eq!(r.get("paths_done").and_then(Json::as_f64), Some(25_000.0));
+                break;
+            }
+            assert!(Instant::now() < deadline, "job did not finish");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let summary = body(&handle(&jobs, &request("GET", &format!("{status}/summary"), "")));
+        let Some(Json::Array(levels)) = summary.get("risk") else { panic!("no risk levels") };
+        assert_eq!(levels.len(), 2);
+        assert!(levels[1].get("cvar").and_then(Json::as_f64).unwrap() > 0.0);
+        let csv = handle(&jobs, &request("GET", &format!("{status}/results"), ""));
+        assert_eq!(String::from_utf8(csv.body).unwrap().lines().count(), 25_001);
+        let json = body(&handle(&jobs, &request("GET", &format!("{status}/results?format=json"), "")));
+        assert!(matches!(json.get("returns"), Some(Json::Array(xs)) if xs.len() == 25_000));
+
+        assert_eq!(handle(&jobs, &request("DELETE", &status, "")).status, 204);
+        assert_eq!(handle(&jobs, &request("GET", &status, "")).status, 404);
+    }
+
+    #[test]
+    fn test_stream_fan_frames() {
+        let jobs = Jobs::new(1);
+        let mut s = Scenario::new(vec![0.08, 0.03], vec![0.2, 0.05], vec![1.0, 0.3, 0.3, 1.0]);
+        s.simulation.n_paths = 30_000;
+        s.simulation.steps = 4;
+        let id = jobs.submit(s).unwrap();
+
+        let mut req = request("GET", &format!("/jobs/{id}/stream?percentiles=5,50,95"), "");
+        req.headers = vec![
+            ("upgrade".into(), "websocket".into()),
+            ("sec-websocket-key".into(), "dGhlIHNhbXBsZSBub25jZQ==".into()),
+            ("sec-websocket-version".into(), "13".into()),
+        ];
+        assert!(ws::is_upgrade(&req));
+        let mut out = Vec::new();
+        stream(&jobs, &req, &mut out).unwrap();
+
+        let head = out.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        assert!(out.starts_with(b"HTTP/1.1 101 Switching Protocols\r\n"));
+        let (mut frames, mut at) = (Vec::new(), head);
+        while at < out.len() {
+            let (opcode, mut len, mut start) = (out[at] & 0x0F, (out[at + 1] & 0x7F) as usize, at + 2);
+            if len == 126 {
+                len = u16::from_be_bytes([out[at + 2], out[at + 3]]) as usize;
+                start = at + 4;
+            }
+            frames.push((opcode, &out[start..start + len]));
+            at = start + len;
+        }
+        let (close, texts) = frames.split_last().unwrap();
+        assert_eq!(close.0, 0x8);
+        let last = mssim_engine::json::parse(std::str::from_utf8(texts.last().unwrap().1).unwrap()).unwrap();
+        assert_eq!(last.get("status").and_then(Json::as_str), Some("done"));
+        assert_eq!(last.get("paths_done").and_then(Json::as_f64), Some(30_000.0));
+        let fan = last.get("fan").and_then(Json::as_f32_vec).unwrap();
+        assert_eq!(fan.len(), 5 * 3);
+        // every path starts at 1; later steps fan out in percentile order
+        assert!((fan[0] - 1.0).abs() < 0.02 && (fan[2] - 1.0).abs() < 0.02);
+        assert!(fan[12] < fan[13] && fan[13] < fan[14]);
+
+        req.path = "/jobs/999/stream".into();
+        let mut out = Vec::new();
+        stream(&jobs, &req, &mut out).unwrap();
+        assert!(out.starts_with(b"HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_bad_requests() {
+        let jobs = Jobs::new(1);
+        assert_eq!(handle(&jobs, &request("GET", "/health", "")).status, 200);
+        assert_eq!(handle(&jobs, &request("POST", "/jobs", "{not json")).status, 400);
+        let newer = r#"{"kind":"scenario","version":99}"#;
+        assert_eq!(handle(&jobs, &request("POST", "/jobs", newer)).status, 400);
+        assert_eq!(handle(&jobs, &request("GET", "/jobs/42/summary", "")).status, 404);
+        assert_eq!(handle(&jobs, &request("PUT", "/jobs/1", "")).status, 405);
+        assert_eq!(handle(&jobs, &request("GET", "/nope", "")).status, 404);
+    }
+}
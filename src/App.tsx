@@ -10,7 +10,7 @@ import { ExportBar } from './components/ExportBar';
 import { CanvasOverlay } from './components/CanvasOverlay';
 import { PerfHUD } from './components/PerfHUD';
 import { initWebGPU } from './gpu';
-import { createComputePipeline, dispatchSimulation, readbackPositions } from './compute';
+import { createComputePipeline, dispatchSimulation, readbackReturns } from './compute';
 import { createRenderPipeline, updateRenderUniforms, renderFrame } from './renderer';
 import { computeStats } from './stats';
 import { DEFAULT_PORTFOLIO } from './data/portfolio';
@@ -79,13 +79,13 @@ export default function App() {
                     setGpuName(info.description || info.vendor || 'WebGPU');
                 }
 
-                const compute = await createComputePipeline(ctx.device, NUM_PARTICLES);
+                const compute = await createComputePipeline(ctx.device, NUM_PARTICLES, DEFAULT_PORTFOLIO.assets.length);
                 if (cancelled) return;
                 computeRef.current = compute;
 
                 const render = createRenderPipeline(
                     ctx.device, ctx.context, canvas,
-                    compute.positionBuffer, NUM_PARTICLES,
+                    compute.returnsBuffer, NUM_PARTICLES,
                 );
                 renderRef.current = render;
                 updateRenderUniforms(render);
@@ -145,8 +145,8 @@ export default function App() {
         if (shockId) setActiveShockId(shockId);
 
         try {
-            const positions = await readbackPositions(compute);
-            const s = computeStats(positions, NUM_PARTICLES);
+            const returns = await readbackReturns(compute);
+            const s = computeStats(returns, NUM_PARTICLES);
             setStats(s);
 
             // Add to comparison
@@ -1,31 +1,41 @@
 // ── WebGPU Compute Pipeline Orchestration ───────────────────────
 //
-// Manages GPU buffers, pipeline creation, and dispatch.
-// Data flow: EngineOutput (WASM Float32Arrays) → GPU buffers → compute → positions
+// Builds the pipeline from the engine's generated kernel (GpuKernel,
+// crates/engine/src/gpu.rs) and binds buffers by the names in its
+// layout_json. The kernel writes one portfolio return Σ wᵢ·(S_T,i − 1)
+// per particle, and is regenerated when the asset count changes.
+// Data flow: EngineOutput (WASM Float32Arrays) → GPU buffers → compute → returns
 
 import type { EngineOutput } from './types';
-import shaderSource from './shaders/simulate.wgsl?raw';
+import { createKernel, wasmReady, type GpuKernel } from './engine';
 
 // ── Constants ───────────────────────────────────────────────────
-const WORKGROUP_SIZE = 256;
-const MAX_ASSETS = 16;
-
-// Must match the WGSL SimParams struct layout (8 × u32/f32 = 32 bytes)
-const SIM_PARAMS_SIZE = 32;
+const STEPS = 1;      // one step over the whole horizon
+const HORIZON = 1.0;  // years
 
 // ── Public Types ────────────────────────────────────────────────
+
+/** One entry of GpuKernel.layout_json's "bindings" */
+interface KernelBinding {
+    binding: number;
+    name: string;
+    type: GPUBufferBindingType;
+    bytes?: number;
+    bytes_per_path?: number;
+    contents: string;
+}
+
 export interface ComputeResources {
     device: GPUDevice;
+    kernel: GpuKernel;
+    bindings: KernelBinding[];
     pipeline: GPUComputePipeline;
     paramBuffer: GPUBuffer;
-    driftBuffer: GPUBuffer;
-    volBuffer: GPUBuffer;
-    choleskyBuffer: GPUBuffer;
-    weightsBuffer: GPUBuffer;
-    positionBuffer: GPUBuffer;
+    inputBuffers: Map<string, GPUBuffer>;
+    returnsBuffer: GPUBuffer;
     readbackBuffer: GPUBuffer;
-    bindGroupLayout: GPUBindGroupLayout;
     bindGroup: GPUBindGroup;
+    numAssets: number;
     numParticles: number;
 }
 
@@ -36,51 +46,60 @@ function align(size: number, alignment: number): number {
     return Math.ceil(size / alignment) * alignment;
 }
 
-/** Create a GPU buffer and optionally write initial data. */
+/** Create a GPU buffer of at least `size` bytes. */
 function makeBuffer(
     device: GPUDevice,
     label: string,
     size: number,
     usage: GPUBufferUsageFlags,
-    data?: ArrayBufferView,
 ): GPUBuffer {
-    const alignedSize = align(Math.max(size, 4), 4);
-    const buffer = device.createBuffer({ label, size: alignedSize, usage, mappedAtCreation: !!data });
-    if (data) {
-        const dst = new Float32Array(buffer.getMappedRange());
-        dst.set(data instanceof Float32Array ? data : new Float32Array(data.buffer));
-        buffer.unmap();
-    }
-    return buffer;
+    return device.createBuffer({ label, size: align(Math.max(size, 4), 4), usage });
 }
 
-// ── Pipeline Creation ───────────────────────────────────────────
+/** The EngineOutput array behind each input binding of the kernel. */
+function bindingData(name: string, output: EngineOutput, weights: number[]): Float32Array {
+    const n = output.numAssets;
+    switch (name) {
+        case 'drift': return output.adjustedDrift;
+        case 'vol': return output.adjustedVol;
+        case 'cholesky': return output.choleskyL;
+        case 'jump_lambda': return new Float32Array(n).fill(output.jumpLambda);
+        case 'jump_mean': return new Float32Array(n).fill(output.jumpMean);
+        case 'jump_vol': return new Float32Array(n).fill(output.jumpVol);
+        case 'weights': return new Float32Array(weights);
+    }
+    throw new Error(`[MSSIM] No data for kernel binding '${name}'`);
+}
 
-export async function createComputePipeline(
+/** Generate the kernel for `numAssets` and build its pipeline and bind group. */
+function buildKernel(
     device: GPUDevice,
+    numAssets: number,
+    returnsBuffer: GPUBuffer,
     numParticles: number,
-): Promise<ComputeResources> {
-    // Compile shader module
+): Pick<ComputeResources, 'kernel' | 'bindings' | 'pipeline' | 'paramBuffer' | 'inputBuffers' | 'bindGroup' | 'numAssets'> {
+    const kernel = createKernel(numAssets, STEPS);
+    if (!kernel) {
+        throw new Error('[MSSIM] WASM engine not available — the GPU kernel is generated by it');
+    }
+    const bindings: KernelBinding[] = JSON.parse(kernel.layout_json).bindings;
+
     const shaderModule = device.createShaderModule({
         label: 'simulate',
-        code: shaderSource,
+        code: kernel.source,
     });
 
-    // Define bind group layout
+    // Bind group layout, straight from the kernel's description
     const bindGroupLayout = device.createBindGroupLayout({
         label: 'simulate-bgl',
-        entries: [
-            { binding: 0, visibility: GPUShaderStage.COMPUTE, buffer: { type: 'uniform' } },
-            { binding: 1, visibility: GPUShaderStage.COMPUTE, buffer: { type: 'read-only-storage' } },
-            { binding: 2, visibility: GPUShaderStage.COMPUTE, buffer: { type: 'read-only-storage' } },
-            { binding: 3, visibility: GPUShaderStage.COMPUTE, buffer: { type: 'read-only-storage' } },
-            { binding: 4, visibility: GPUShaderStage.COMPUTE, buffer: { type: 'read-only-storage' } },
-            { binding: 5, visibility: GPUShaderStage.COMPUTE, buffer: { type: 'storage' } },
-        ],
+        entries: bindings.map((b) => ({
+            binding: b.binding,
+            visibility: GPUShaderStage.COMPUTE,
+            buffer: { type: b.type },
+        })),
     });
 
-    // Create pipeline
-    const pipeline = await device.createComputePipelineAsync({
+    const pipeline = device.createComputePipeline({
         label: 'simulate-pipeline',
         layout: device.createPipelineLayout({
             label: 'simulate-layout',
@@ -89,72 +108,68 @@ export async function createComputePipeline(
         compute: { module: shaderModule, entryPoint: 'main' },
     });
 
-    // ── Allocate buffers ────────────────────────────────────────
+    // ── Allocate buffers by binding ─────────────────────────────
+    let paramBuffer: GPUBuffer | null = null;
+    const inputBuffers = new Map<string, GPUBuffer>();
+    const entries: GPUBindGroupEntry[] = [];
+    for (const b of bindings) {
+        let buffer: GPUBuffer;
+        if (b.type === 'uniform') {
+            buffer = makeBuffer(device, b.name, b.bytes ?? 0, GPUBufferUsage.UNIFORM | GPUBufferUsage.COPY_DST);
+            paramBuffer = buffer;
+        } else if (b.type === 'storage') {
+            if ((b.bytes_per_path ?? 0) * numParticles > returnsBuffer.size) {
+                throw new Error(`[MSSIM] Kernel output needs ${b.bytes_per_path} bytes per particle`);
+            }
+            buffer = returnsBuffer;
+        } else {
+            buffer = makeBuffer(device, b.name, b.bytes ?? 0, GPUBufferUsage.STORAGE | GPUBufferUsage.COPY_DST);
+            inputBuffers.set(b.name, buffer);
+        }
+        entries.push({ binding: b.binding, resource: { buffer } });
+    }
+    if (!paramBuffer) {
+        throw new Error('[MSSIM] Kernel has no params binding');
+    }
 
-    // Storage buffer sizes — sized for MAX_ASSETS to avoid recreation
-    const vecSize = MAX_ASSETS * 4;           // N f32s
-    const matSize = MAX_ASSETS * MAX_ASSETS * 4; // N×N f32s
-    const posSize = numParticles * 2 * 4;     // vec2<f32> per particle
+    const bindGroup = device.createBindGroup({
+        label: 'simulate-bg',
+        layout: bindGroupLayout,
+        entries,
+    });
 
-    const paramBuffer = makeBuffer(
-        device, 'params', SIM_PARAMS_SIZE,
-        GPUBufferUsage.UNIFORM | GPUBufferUsage.COPY_DST,
-    );
+    console.log(`[MSSIM] Compute kernel generated: ${numAssets} assets, ${kernel.workgroups(numParticles)} workgroups`);
 
-    const driftBuffer = makeBuffer(
-        device, 'drift', vecSize,
-        GPUBufferUsage.STORAGE | GPUBufferUsage.COPY_DST,
-    );
-
-    const volBuffer = makeBuffer(
-        device, 'vol', vecSize,
-        GPUBufferUsage.STORAGE | GPUBufferUsage.COPY_DST,
-    );
+    return { kernel, bindings, pipeline, paramBuffer, inputBuffers, bindGroup, numAssets };
+}
 
-    const choleskyBuffer = makeBuffer(
-        device, 'cholesky', matSize,
-        GPUBufferUsage.STORAGE | GPUBufferUsage.COPY_DST,
-    );
+// ── Pipeline Creation ───────────────────────────────────────────
 
-    const weightsBuffer = makeBuffer(
-        device, 'weights', vecSize,
-        GPUBufferUsage.STORAGE | GPUBufferUsage.COPY_DST,
-    );
+export async function createComputePipeline(
+    device: GPUDevice,
+    numParticles: number,
+    numAssets: number,
+): Promise<ComputeResources> {
+    await wasmReady;
 
-    // Position buffer: written by compute, read by render shader as storage
-    const positionBuffer = makeBuffer(
-        device, 'positions', posSize,
+    // One f32 return per particle, read by the render shader as storage
+    const returnsSize = numParticles * 4;
+    const returnsBuffer = makeBuffer(
+        device, 'returns', returnsSize,
         GPUBufferUsage.STORAGE | GPUBufferUsage.COPY_SRC,
     );
 
     // Readback buffer for CPU-side statistics computation
     const readbackBuffer = device.createBuffer({
         label: 'readback',
-        size: posSize,
+        size: returnsSize,
         usage: GPUBufferUsage.MAP_READ | GPUBufferUsage.COPY_DST,
     });
 
-    // ── Bind group ──────────────────────────────────────────────
-    const bindGroup = device.createBindGroup({
-        label: 'simulate-bg',
-        layout: bindGroupLayout,
-        entries: [
-            { binding: 0, resource: { buffer: paramBuffer } },
-            { binding: 1, resource: { buffer: driftBuffer } },
-            { binding: 2, resource: { buffer: volBuffer } },
-            { binding: 3, resource: { buffer: choleskyBuffer } },
-            { binding: 4, resource: { buffer: weightsBuffer } },
-            { binding: 5, resource: { buffer: positionBuffer } },
-        ],
-    });
-
-    console.log(`[MSSIM] Compute pipeline created: ${numParticles} particles, ${Math.ceil(numParticles / WORKGROUP_SIZE)} workgroups`);
-
     return {
-        device, pipeline,
-        paramBuffer, driftBuffer, volBuffer, choleskyBuffer, weightsBuffer,
-        positionBuffer, readbackBuffer,
-        bindGroupLayout, bindGroup,
+        device,
+        ...buildKernel(device, numAssets, returnsBuffer, numParticles),
+        returnsBuffer, readbackBuffer,
         numParticles,
     };
 }
@@ -166,34 +181,29 @@ export function dispatchSimulation(
     engineOutput: EngineOutput,
     weights: number[],
 ): void {
-    const { device, pipeline, bindGroup, numParticles } = resources;
-    const n = engineOutput.numAssets;
-
-    // Write SimParams uniform
-    const paramsData = new ArrayBuffer(SIM_PARAMS_SIZE);
-    const paramsU32 = new Uint32Array(paramsData);
-    const paramsF32 = new Float32Array(paramsData);
-    paramsU32[0] = n;                               // num_assets
-    paramsU32[1] = numParticles;                     // num_particles
-    paramsF32[2] = 1.0;                              // dt (1 year)
-    paramsF32[3] = engineOutput.jumpLambda;           // jump_lambda
-    paramsF32[4] = engineOutput.jumpMean;             // jump_mean
-    paramsF32[5] = engineOutput.jumpVol;              // jump_vol
-    paramsU32[6] = (Math.random() * 0xFFFFFFFF) >>> 0; // seed
-    paramsU32[7] = 0;                                // _pad
-
-    device.queue.writeBuffer(resources.paramBuffer, 0, paramsData);
-    device.queue.writeBuffer(resources.driftBuffer, 0, engineOutput.adjustedDrift);
-    device.queue.writeBuffer(resources.volBuffer, 0, engineOutput.adjustedVol);
-    device.queue.writeBuffer(resources.choleskyBuffer, 0, engineOutput.choleskyL);
-    device.queue.writeBuffer(resources.weightsBuffer, 0, new Float32Array(weights));
+    const { device, numParticles } = resources;
+
+    // N is compiled into the kernel: regenerate when it changes
+    if (engineOutput.numAssets !== resources.numAssets) {
+        resources.kernel.free();
+        resources.paramBuffer.destroy();
+        resources.inputBuffers.forEach((b) => b.destroy());
+        Object.assign(resources, buildKernel(device, engineOutput.numAssets, resources.returnsBuffer, numParticles));
+    }
+    const { kernel, pipeline, bindGroup } = resources;
+
+    const seed = BigInt((Math.random() * 0xFFFFFFFF) >>> 0);
+    device.queue.writeBuffer(resources.paramBuffer, 0, kernel.params(numParticles, 0, seed, HORIZON));
+    resources.inputBuffers.forEach((buffer, name) => {
+        device.queue.writeBuffer(buffer, 0, bindingData(name, engineOutput, weights));
+    });
 
     // Encode and submit compute pass
     const encoder = device.createCommandEncoder({ label: 'simulate-cmd' });
     const pass = encoder.beginComputePass({ label: 'simulate-pass' });
     pass.setPipeline(pipeline);
     pass.setBindGroup(0, bindGroup);
-    pass.dispatchWorkgroups(Math.ceil(numParticles / WORKGROUP_SIZE));
+    pass.dispatchWorkgroups(kernel.workgroups(numParticles));
     pass.end();
 
     device.queue.submit([encoder.finish()]);
@@ -201,14 +211,14 @@ export function dispatchSimulation(
 
 // ── Readback (for statistics computation) ────────────────────────
 
-export async function readbackPositions(
+export async function readbackReturns(
     resources: ComputeResources,
 ): Promise<Float32Array> {
-    const { device, positionBuffer, readbackBuffer, numParticles } = resources;
-    const byteSize = numParticles * 2 * 4;
+    const { device, returnsBuffer, readbackBuffer, numParticles } = resources;
+    const byteSize = numParticles * 4;
 
     const encoder = device.createCommandEncoder({ label: 'readback-cmd' });
-    encoder.copyBufferToBuffer(positionBuffer, 0, readbackBuffer, 0, byteSize);
+    encoder.copyBufferToBuffer(returnsBuffer, 0, readbackBuffer, 0, byteSize);
     device.queue.submit([encoder.finish()]);
 
     await readbackBuffer.mapAsync(GPUMapMode.READ);
@@ -7,12 +7,40 @@ import type { Portfolio, MacroShock, EngineOutput } from './types';
 
 let wasmModule: typeof import('./wasm/engine/mssim_engine') | null = null;
 
+export type GpuKernel = import('./wasm/engine/mssim_engine').GpuKernel;
+
+// Smallest module using a v128 op (i8x16.splat, i8x16.popcnt): it
+// validates only where the browser supports simd128
+const SIMD_PROBE = new Uint8Array([
+    0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 123, 3, 2, 1, 0, 10, 10, 1, 8, 0, 65, 0, 253, 15, 253, 98, 11,
+]);
+
+function simdSupported(): boolean {
+    try {
+        return typeof WebAssembly === 'object' && WebAssembly.validate(SIMD_PROBE);
+    } catch {
+        return false;
+    }
+}
+
+// The simd128 build (build-wasm.ps1) where supported, else the baseline
+async function importWasm(): Promise<typeof import('./wasm/engine/mssim_engine')> {
+    if (simdSupported()) {
+        try {
+            return await import('./wasm/engine-simd/mssim_engine');
+        } catch {
+            console.warn('[MSSIM] SIMD build not available — using the baseline build');
+        }
+    }
+    return import('./wasm/engine/mssim_engine');
+}
+
 async function loadWasm() {
     try {
-        const mod = await import('./wasm/engine/mssim_engine');
+        const mod = await importWasm();
         await mod.default();           // init WASM
         wasmModule = mod;
-        console.log('[MSSIM] WASM engine loaded');
+        console.log(`[MSSIM] WASM engine loaded${mod.simd_enabled() ? ' (SIMD)' : ''}`);
     } catch {
         console.warn('[MSSIM] WASM engine not available — using JS fallback');
     }
@@ -84,5 +112,14 @@ export function runEngine(p: Portfolio, s: MacroShock): EngineOutput {
     return fallbackEngine(p, s);
 }
 
+/**
+ * The engine's generated WGSL kernel for `numAssets` (pcg32, with
+ * jumps, one portfolio return per path), or null without WASM.
+ */
+export function createKernel(numAssets: number, steps: number): GpuKernel | null {
+    if (!wasmModule) return null;
+    return new wasmModule.GpuKernel(numAssets, steps, 'pcg32', 'portfolio', true);
+}
+
 /** Await this if you need to guarantee WASM is loaded before first use. */
 export { wasmReady };
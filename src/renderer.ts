@@ -1,7 +1,7 @@
 // ── WebGPU Render Pipeline ──────────────────────────────────────
 //
 // Instanced quad rendering with additive blending.
-// Reads the position buffer written by the compute shader.
+// Reads the per-particle returns written by the compute kernel.
 
 import renderShaderSource from './shaders/render.wgsl?raw';
 
@@ -27,7 +27,7 @@ export function createRenderPipeline(
     device: GPUDevice,
     context: GPUCanvasContext,
     canvas: HTMLCanvasElement,
-    positionBuffer: GPUBuffer,
+    returnsBuffer: GPUBuffer,
     numParticles: number,
 ): RenderResources {
     const format = navigator.gpu.getPreferredCanvasFormat();
@@ -67,7 +67,7 @@ export function createRenderPipeline(
         layout: bindGroupLayout,
         entries: [
             { binding: 0, resource: { buffer: uniformBuffer } },
-            { binding: 1, resource: { buffer: positionBuffer } },
+            { binding: 1, resource: { buffer: returnsBuffer } },
         ],
     });
 
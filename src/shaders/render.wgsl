@@ -15,7 +15,7 @@ struct RenderParams {
 }
 
 @group(0) @binding(0) var<uniform> params: RenderParams;
-@group(0) @binding(1) var<storage, read> positions: array<vec2<f32>>;
+@group(0) @binding(1) var<storage, read> returns: array<f32>;   // [numParticles], from the compute kernel
 
 // ── Vertex output / Fragment input ──────────────────────────────
 struct VertexOut {
@@ -46,14 +46,17 @@ fn vs_main(
     @builtin(vertex_index) vid: u32,
     @builtin(instance_index) iid: u32,
 ) -> VertexOut {
-    let particle = positions[iid];
+    let r = returns[iid];
 
-    // Map compute space → NDC
-    // x: [0, 1] → [-1, 1]
-    let ndc_x = particle.x * 2.0 - 1.0;
+    // x: particle index spread over [0, 1] with a tiny hashed jitter,
+    // then → [-1, 1]
+    let h = (iid ^ 61u ^ (iid >> 16u)) * 2654435761u;
+    let jitter = (f32(h >> 8u) / 16777216.0 - 0.5) * 0.002;
+    let x = f32(iid) / f32(arrayLength(&returns)) + jitter;
+    let ndc_x = x * 2.0 - 1.0;
 
     // y: raw portfolio return, scaled + centered
-    let ndc_y = particle.y * params.y_scale + params.y_offset;
+    let ndc_y = r * params.y_scale + params.y_offset;
 
     // Quad offset, corrected for aspect ratio
     let offset = quad_offset(vid % 6u);
@@ -66,7 +69,7 @@ fn vs_main(
         0.0, 1.0
     );
     out.uv = offset;
-    out.portfolio_return = particle.y;
+    out.portfolio_return = r;
     return out;
 }
 
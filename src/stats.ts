@@ -14,16 +14,10 @@ export interface SimStats {
 }
 
 /**
- * Compute statistics from an array of portfolio returns.
- * The positions buffer is vec2<f32> — we extract every 2nd element (y = return).
+ * Compute statistics from an array of portfolio returns, one f32 per
+ * particle as the compute kernel writes them.
  */
-export function computeStats(positions: Float32Array, numParticles: number): SimStats {
-    // Extract Y values (portfolio returns)
-    const returns = new Float32Array(numParticles);
-    for (let i = 0; i < numParticles; i++) {
-        returns[i] = positions[i * 2 + 1];
-    }
-
+export function computeStats(returns: Float32Array, numParticles: number): SimStats {
     // Sort for percentile computation
     const sorted = Float32Array.from(returns).sort();
 